@@ -0,0 +1,10 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // The gRPC service is generated from its .proto with a bundled protoc, so building
+    // with the grpc feature needs no system protobuf install
+    #[cfg(feature = "grpc")]
+    {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().expect("No bundled protoc for this platform"));
+        tonic_build::compile_protos("proto/evm_json_rpc.proto").expect("Failed to compile proto/evm_json_rpc.proto");
+    }
+}
@@ -0,0 +1,30 @@
+#ifndef EVM_JSON_RPC_H
+#define EVM_JSON_RPC_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Strings returned by these functions must be released with evm_string_free.
+ * On failure they return NULL (or -1) and evm_last_error describes why. */
+
+const char *evm_last_error(void);
+void evm_string_free(char *value);
+
+char *evm_encode_function_call(const char *signature, const char *const *params, size_t params_len);
+int evm_decode_uint(const char *hex, uint64_t *out);
+char *evm_decode_string(const char *hex);
+char *evm_decode_address(const char *hex);
+
+/* Blocking calls; params_json is a JSON array and the result is JSON-encoded */
+char *evm_rpc_send(const char *rpc_url, const char *method, const char *params_json);
+char *evm_call(const char *rpc_url, const char *to, const char *data);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,227 @@
+use ethabi::ethereum_types::U256;
+use ethabi::param_type::Reader;
+use ethabi::token::{LenientTokenizer, Tokenizer};
+use ethabi::{decode, ParamType, Token};
+
+use crate::error::{Error, Result};
+use crate::format;
+
+pub fn encode_function_call(method_signature: &str, params: Vec<String>) -> Result<String> {
+    let selector = keccak_hash::keccak(method_signature.as_bytes());
+    let selector = hex::encode(&selector[0..4]);
+    
+    let mut encoded_params = String::new();
+    
+    if method_signature.contains("string") {
+        if method_signature.contains(",uint256,uint256") {
+            expect_params(method_signature, &params, 3)?;
+            let subject = &params[0];
+            let offset = &params[1];
+            let limit = &params[2];
+            
+            encoded_params.push_str(&format!("{:0>64}", "60"));
+            encoded_params.push_str(&format!("{:0>64}", offset));
+            encoded_params.push_str(&format!("{:0>64}", limit));
+            encoded_params.push_str(&format!("{:0>64}", format!("{:x}", subject.len())));
+            let mut hex_string = hex::encode(subject.as_bytes());
+            while !hex_string.len().is_multiple_of(64) {
+                hex_string.push('0');
+            }
+            encoded_params.push_str(&hex_string);
+        } else {
+            expect_params(method_signature, &params, 1)?;
+            let param = &params[0];
+            encoded_params.push_str(&format!("{:0>64}", "20"));
+            encoded_params.push_str(&format!("{:0>64}", format!("{:x}", param.len())));
+            let mut hex_string = hex::encode(param.as_bytes());
+            while !hex_string.len().is_multiple_of(64) {
+                hex_string.push('0');
+            }
+            encoded_params.push_str(&hex_string);
+        }
+    } else {
+        for param in params {
+            if let Some(param) = param.strip_prefix("0x") {
+                encoded_params.push_str(param);
+            } else {
+                encoded_params.push_str(&format!("{:0>64}", param));
+            }
+        }
+    }
+    
+    Ok(format!("0x{}{}", selector, encoded_params))
+}
+
+fn expect_params(method_signature: &str, params: &[String], count: usize) -> Result<()> {
+    if params.len() < count {
+        return Err(Error::Abi(format!("{} expects {} parameters, got {}", method_signature, count, params.len())));
+    }
+    Ok(())
+}
+
+// An empty result usually means the target has no code, which must not read as zero
+fn word(hex_str: &str, index: usize) -> Result<&str> {
+    let hex_str = hex_str.trim_start_matches("0x");
+    if hex_str.is_empty() {
+        return Err(Error::Decode("Empty result, the target may not be a contract".to_string()));
+    }
+    let word = index.checked_mul(64).and_then(|start| hex_str.get(start..start.checked_add(64)?));
+    let word = word.ok_or_else(|| Error::Decode(format!("Result is too short: {} hex characters", hex_str.len())))?;
+    if !word.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(Error::Decode("Result is not valid hex".to_string()));
+    }
+    Ok(word)
+}
+
+fn word_to_usize(word: &str) -> Result<usize> {
+    usize::from_str_radix(word, 16).map_err(|_| Error::Decode(format!("Offset or length 0x{} is out of range", word.trim_start_matches('0'))))
+}
+
+pub fn decode_uint(hex_str: &str) -> Result<u64> {
+    let word = word(hex_str, 0)?;
+    u64::from_str_radix(word, 16).map_err(|_| Error::Decode(format!("0x{} does not fit in a u64", word.trim_start_matches('0'))))
+}
+
+// For amounts such as balances and supplies, which routinely exceed a u64
+pub fn decode_u256(hex_str: &str) -> Result<U256> {
+    let word = word(hex_str, 0)?;
+    U256::from_str_radix(word, 16).map_err(|_| Error::Decode("Result is not valid hex".to_string()))
+}
+
+pub fn decode_address(hex_str: &str) -> Result<String> {
+    let word = word(hex_str, 0)?;
+    if word[..24].bytes().any(|byte| byte != b'0') {
+        return Err(Error::Decode("Address word has non-zero padding".to_string()));
+    }
+    Ok(format!("0x{}", &word[24..64]))
+}
+
+pub fn decode_string(hex_str: &str) -> Result<String> {
+    let hex_str = hex_str.trim_start_matches("0x");
+    // Offsets and lengths come from the provider, so bound them by the data itself
+    let offset = word_to_usize(word(hex_str, 0)?)?;
+    if offset % 32 != 0 {
+        return Err(Error::Decode(format!("String offset {} is not word aligned", offset)));
+    }
+    let length = word_to_usize(word(hex_str, offset / 32)?)?;
+    let data_start = (offset + 32) * 2;
+    let string_data = length
+        .checked_mul(2)
+        .and_then(|size| hex_str.get(data_start..data_start.checked_add(size)?))
+        .ok_or_else(|| Error::Decode(format!("String of {} bytes runs past the end of the result", length)))?;
+    let bytes = hex::decode(string_data).map_err(|error| Error::Decode(error.to_string()))?;
+    String::from_utf8(bytes).map_err(|_| Error::Decode("String is not valid UTF-8".to_string()))
+}
+
+pub fn decode_string_array(response: &str) -> Result<Vec<String>> {
+    let response_data = response.strip_prefix("0x").unwrap_or(response);
+
+    let bytes = hex::decode(response_data).map_err(|error| Error::Decode(error.to_string()))?;
+    let decoded = decode(
+        &[ParamType::Array(Box::new(ParamType::String))],
+        &bytes,
+    ).map_err(|error| Error::Abi(error.to_string()))?;
+
+    if let Some(Token::Array(tokens)) = decoded.first() {
+        let values = tokens
+            .iter()
+            .filter_map(|token| {
+                if let Token::String(value) = token {
+                    Some(value.clone())
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok(values)
+    } else {
+        Err(Error::Decode("Unexpected response format".to_string()))
+    }
+}
+
+// Selector plus ethabi-encoded arguments, as 0x hex
+pub fn encode_call(signature: &str, tokens: &[Token]) -> String {
+    let selector = &keccak_hash::keccak(signature.as_bytes())[..4];
+    format!("0x{}{}", hex::encode(selector), hex::encode(ethabi::encode(tokens)))
+}
+
+// Parameter types of a signature such as "transfer(address,uint256)", tuples included
+pub fn signature_params(signature: &str) -> Result<Vec<ParamType>> {
+    let invalid = || Error::Abi(format!("Invalid function signature {}", signature));
+    let params = &signature[signature.find('(').ok_or_else(invalid)?..];
+    if params == "()" {
+        return Ok(Vec::new());
+    }
+    match Reader::read(params) {
+        Ok(ParamType::Tuple(types)) => Ok(types),
+        _ => Err(invalid()),
+    }
+}
+
+// encode_call with the arguments as text, e.g. from a CSV row, parsed for the
+// signature's types: decimal or 0x numbers, 0x addresses and bytes, [a,b] arrays
+pub fn encode_call_args(signature: &str, args: &[&str]) -> Result<String> {
+    let types = signature_params(signature)?;
+    if types.len() != args.len() {
+        return Err(Error::Abi(format!("{} expects {} arguments, got {}", signature, types.len(), args.len())));
+    }
+    let tokens = types
+        .iter()
+        .zip(args)
+        .map(|(kind, value)| LenientTokenizer::tokenize(kind, value.trim()).map_err(|error| Error::Abi(format!("Invalid {} argument {}: {}", kind, value, error))))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(encode_call(signature, &tokens))
+}
+
+// Decodes an eth_call result into the given output types
+pub fn decode_result(hex_str: &str, types: &[ParamType]) -> Result<Vec<Token>> {
+    let bytes = hex::decode(hex_str.trim_start_matches("0x")).map_err(|error| Error::Decode(error.to_string()))?;
+    if bytes.is_empty() && !types.is_empty() {
+        return Err(Error::Decode("Empty result, the target may not be a contract".to_string()));
+    }
+    decode(types, &bytes).map_err(|error| Error::Abi(error.to_string()))
+}
+
+// Addresses and bytes as 0x hex, integers as decimal strings so they survive JSON parsers
+pub fn token_to_json(token: &Token) -> serde_json::Value {
+    match token {
+        Token::Address(address) => serde_json::json!(format::address(&format!("{:?}", address))),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => serde_json::json!(format!("0x{}", hex::encode(bytes))),
+        // Int holds the two's complement word
+        Token::Int(value) if value.bit(255) => serde_json::json!(format!("-{}", (!*value).overflowing_add(U256::one()).0)),
+        Token::Uint(value) | Token::Int(value) => serde_json::json!(value.to_string()),
+        Token::Bool(value) => serde_json::json!(value),
+        Token::String(value) => serde_json::json!(value),
+        Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => tokens.iter().map(token_to_json).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_uints_past_a_u64() {
+        let supply = format!("0x{:064x}", u128::MAX);
+        assert_eq!(decode_u256(&supply).unwrap(), U256::from(u128::MAX));
+        assert!(matches!(decode_uint(&supply), Err(Error::Decode(_))));
+        assert_eq!(decode_uint(&format!("0x{:064x}", 18)).unwrap(), 18);
+        assert!(matches!(decode_u256("0x"), Err(Error::Decode(_))));
+    }
+
+    #[test]
+    fn renders_signed_ints_as_negative_decimals() {
+        let minus_one = U256::MAX;
+        assert_eq!(token_to_json(&Token::Int(minus_one)), serde_json::json!("-1"));
+        assert_eq!(token_to_json(&Token::Int(U256::from(42))), serde_json::json!("42"));
+        // int256's minimum has no positive counterpart
+        let min = U256::one() << 255;
+        assert_eq!(token_to_json(&Token::Int(min)), serde_json::json!("-57896044618658097711785492504343953926634992332820282019728792003956564819968"));
+        // The same word as a uint stays unsigned
+        assert_eq!(token_to_json(&Token::Uint(minus_one)), serde_json::json!(U256::MAX.to_string()));
+
+        let decoded = decode_result(&format!("0x{}", "f".repeat(62) + "85"), &[ParamType::Int(8)]).unwrap();
+        assert_eq!(token_to_json(&decoded[0]), serde_json::json!("-123"));
+    }
+}
@@ -0,0 +1,424 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use std::time::Duration;
+
+use ethabi::ethereum_types::{H256, U256};
+use ethabi::{ParamType, Token};
+use futures_util::future::try_join_all;
+use futures_util::stream::{self, Stream};
+
+use crate::abi::{decode_result, encode_call};
+use crate::blocks::BlockTag;
+use crate::classify::{classify, Category};
+use crate::client::EthClient;
+use crate::crawler::DEFAULT_CHUNK_SIZE;
+use crate::error::{Error, Result};
+use crate::format::{self, TokenAmount};
+use crate::labels::LabelResolver;
+use crate::logs::Filter;
+use crate::multicall::{self, Call};
+use crate::portfolio::decode_symbol;
+
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityKind {
+    Received,
+    Sent,
+    Approved,
+    Revoked,
+    Deployed,
+    Called,
+}
+
+// One thing that happened to the watched address, with its counterparty already
+// labelled. A transaction can produce several, e.g. a swap sends one token and
+// receives another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Activity {
+    pub block_number: u64,
+    pub transaction_hash: String,
+    pub kind: ActivityKind,
+    // Token contract, None for ETH and plain calls
+    pub token: Option<String>,
+    // None for unlimited approvals, ERC-721 ids and plain calls
+    pub amount: Option<TokenAmount>,
+    pub token_id: Option<U256>,
+    pub counterparty: Option<String>,
+}
+
+// "Received 1.5 USDC from vitalik.eth (0xd8da...)"
+impl fmt::Display for Activity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let asset = match (&self.amount, self.token_id) {
+            (_, Some(id)) => format!("#{} of {}", id, self.token.as_deref().map(format::address).unwrap_or_default()),
+            (Some(amount), None) => amount.to_string(),
+            (None, None) => "all tokens".to_string(),
+        };
+        let counterparty = self.counterparty.as_deref().unwrap_or("unknown");
+        match self.kind {
+            ActivityKind::Received => write!(f, "Received {} from {}", asset, counterparty),
+            ActivityKind::Sent => write!(f, "Sent {} to {}", asset, counterparty),
+            ActivityKind::Approved => write!(f, "Approved {} to spend {}", counterparty, asset),
+            ActivityKind::Revoked => write!(f, "Revoked {}'s approval", counterparty),
+            ActivityKind::Deployed => write!(f, "Deployed {}", counterparty),
+            ActivityKind::Called => write!(f, "Called {}", counterparty),
+        }
+    }
+}
+
+// Streams the activity of one address from a start block onward, following the
+// chain head. Token transfers and approvals are found through eth_getLogs. Plain ETH
+// transfers and contract calls leave no log, so they only show up with a block scan,
+// which reads every block in full.
+pub struct ActivityFeed {
+    client: EthClient,
+    address: String,
+    from_block: Option<u64>,
+    poll_interval: Duration,
+    chunk_size: u64,
+    block_scan: bool,
+    labels: Option<LabelResolver>,
+}
+
+impl ActivityFeed {
+    pub fn new(client: &EthClient, address: &str) -> Self {
+        ActivityFeed {
+            client: client.clone(),
+            address: address.to_lowercase(),
+            from_block: None,
+            poll_interval: Duration::from_secs(12),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            block_scan: false,
+            labels: None,
+        }
+    }
+
+    // Defaults to the head at the first poll
+    pub fn with_from_block(mut self, from_block: u64) -> Self {
+        self.from_block = Some(from_block);
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: u64) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    pub fn with_block_scan(mut self, block_scan: bool) -> Self {
+        self.block_scan = block_scan;
+        self
+    }
+
+    // Names counterparties through ENS, the address book and known contracts;
+    // without it they're shown as bare addresses
+    pub fn with_labels(mut self, labels: LabelResolver) -> Self {
+        self.labels = Some(labels);
+        self
+    }
+
+    // Yields activities in chain order. A failed poll yields its error and the same
+    // blocks are retried after the poll interval, so the stream only ends when dropped.
+    pub fn stream(self) -> impl Stream<Item = Result<Activity>> + Send {
+        let state = FeedState {
+            next: self.from_block,
+            pending: VecDeque::new(),
+            tokens: HashMap::new(),
+            failed: false,
+            feed: self,
+        };
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(activity) = state.pending.pop_front() {
+                    return Some((Ok(activity), state));
+                }
+                if std::mem::take(&mut state.failed) {
+                    tokio::time::sleep(crate::poll::jittered(state.feed.poll_interval)).await;
+                }
+                match state.poll().await {
+                    Ok(true) => {}
+                    Ok(false) => tokio::time::sleep(crate::poll::jittered(state.feed.poll_interval)).await,
+                    Err(error) => {
+                        state.failed = true;
+                        return Some((Err(error), state));
+                    }
+                }
+            }
+        })
+    }
+}
+
+// Token symbol and decimals, None for contracts that don't answer like an ERC-20
+type TokenInfo = Option<(String, u32)>;
+
+struct FeedState {
+    feed: ActivityFeed,
+    next: Option<u64>,
+    pending: VecDeque<Activity>,
+    tokens: HashMap<String, TokenInfo>,
+    failed: bool,
+}
+
+impl FeedState {
+    // Processes the next range up to the head; false when there was nothing new
+    async fn poll(&mut self) -> Result<bool> {
+        let latest = self.feed.client.get_block_number().await?;
+        let next = *self.next.get_or_insert(latest);
+        if next > latest {
+            return Ok(false);
+        }
+        let to_block = next.saturating_add(self.feed.chunk_size - 1).min(latest);
+        let activities = self.activities(next, to_block).await?;
+        self.pending.extend(activities);
+        self.next = Some(to_block + 1);
+        Ok(true)
+    }
+
+    async fn activities(&mut self, from_block: u64, to_block: u64) -> Result<Vec<Activity>> {
+        let client = &self.feed.client;
+        let address = self.feed.address.as_str();
+        let watched = H256::from(address.trim_start_matches("0x").parse::<ethabi::Address>().map_err(|_| Error::InvalidInput(format!("Invalid address {}", address)))?);
+
+        // Transfers out, transfers in, and approvals given
+        let range = Filter::new().with_from_block(from_block).with_to_block(to_block);
+        let filters = [
+            range.clone().with_event("Transfer(address,address,uint256)").with_topic(1, watched),
+            range.clone().with_event("Transfer(address,address,uint256)").with_topic(2, watched),
+            range.with_event("Approval(address,address,uint256)").with_event("ApprovalForAll(address,address,bool)").with_topic(1, watched),
+        ];
+        let mut transactions: BTreeSet<(u64, u64, H256)> = BTreeSet::new();
+        for logs in try_join_all(filters.iter().map(|filter| client.get_logs(filter))).await? {
+            for log in logs {
+                if let (Some(block), Some(index), Some(hash)) = (log.block_number, log.transaction_index, log.transaction_hash) {
+                    transactions.insert((block.as_u64(), index.as_u64(), hash));
+                }
+            }
+        }
+        if self.feed.block_scan {
+            let blocks = try_join_all((from_block..=to_block).map(|number| client.send("eth_getBlockByNumber", vec![BlockTag::Number(number).to_json(), true.into()]))).await?;
+            for (number, block) in (from_block..=to_block).zip(blocks) {
+                for (index, transaction) in block["transactions"].as_array().into_iter().flatten().enumerate() {
+                    let involved = |field: &str| transaction[field].as_str().is_some_and(|party| party.eq_ignore_ascii_case(address));
+                    if involved("from") || involved("to") {
+                        let hash = serde_json::from_value(transaction["hash"].clone()).map_err(|error| Error::Decode(format!("Invalid transaction hash: {}", error)))?;
+                        transactions.insert((number, index as u64, hash));
+                    }
+                }
+            }
+        }
+
+        let fetched = try_join_all(transactions.iter().map(|(block, _, hash)| async move {
+            let hash = format!("{:?}", hash);
+            let transaction = client.send("eth_getTransactionByHash", vec![serde_json::json!(hash)]).await?;
+            let receipt = client.send("eth_getTransactionReceipt", vec![serde_json::json!(hash)]).await?;
+            Ok::<_, Error>((*block, hash, transaction, receipt))
+        }))
+        .await?;
+
+        let mut activities = Vec::new();
+        for (block_number, transaction_hash, transaction, receipt) in fetched {
+            let classification = classify(&transaction, &receipt)?;
+            let mut push = |kind, token: Option<&str>, amount, token_id, counterparty: &str| {
+                activities.push(Activity {
+                    block_number,
+                    transaction_hash: transaction_hash.clone(),
+                    kind,
+                    token: token.map(str::to_string),
+                    amount,
+                    token_id,
+                    counterparty: Some(counterparty.to_string()),
+                })
+            };
+
+            let to = transaction["to"].as_str().unwrap_or_default().to_lowercase();
+            if !classification.value.is_zero() {
+                if classification.sender == address {
+                    push(ActivityKind::Sent, None, Some(TokenAmount::ether(classification.value)), None, &to);
+                } else if to == address {
+                    push(ActivityKind::Received, None, Some(TokenAmount::ether(classification.value)), None, &classification.sender);
+                }
+            }
+            for movement in &classification.movements {
+                if movement.from == address {
+                    push(ActivityKind::Sent, Some(&movement.token), Some(TokenAmount::new(movement.amount, 0)), movement.token_id, &movement.to);
+                } else if movement.to == address {
+                    let from = if movement.from == ZERO_ADDRESS { &movement.token } else { &movement.from };
+                    push(ActivityKind::Received, Some(&movement.token), Some(TokenAmount::new(movement.amount, 0)), movement.token_id, from);
+                }
+            }
+            for approval in classification.approvals.iter().filter(|approval| approval.owner == address) {
+                match approval.amount {
+                    Some(amount) if amount.is_zero() => push(ActivityKind::Revoked, Some(&approval.token), None, None, &approval.spender),
+                    Some(amount) if amount == U256::MAX => push(ActivityKind::Approved, Some(&approval.token), None, None, &approval.spender),
+                    Some(amount) => push(ActivityKind::Approved, Some(&approval.token), Some(TokenAmount::new(amount, 0)), None, &approval.spender),
+                    None => push(ActivityKind::Approved, Some(&approval.token), None, None, &approval.spender),
+                }
+            }
+            if classification.sender == address && classification.value.is_zero() && classification.movements.is_empty() && classification.approvals.is_empty() {
+                match (classification.category, &classification.contract_address) {
+                    (Category::ContractDeploy, Some(contract)) => push(ActivityKind::Deployed, None, None, None, contract),
+                    _ => push(ActivityKind::Called, None, None, None, &to),
+                }
+            }
+        }
+
+        self.describe_tokens(&mut activities).await?;
+        label_counterparties(self.feed.labels.as_ref(), &mut activities).await?;
+        Ok(activities)
+    }
+
+    // Gives token amounts their symbol and decimals, looking each token up once
+    async fn describe_tokens(&mut self, activities: &mut [Activity]) -> Result<()> {
+        let unknown: Vec<String> = activities
+            .iter()
+            .filter_map(|activity| activity.token.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter(|token| !self.tokens.contains_key(token))
+            .collect();
+        if !unknown.is_empty() {
+            let calls: Vec<Call> = unknown.iter().flat_map(|token| [Call::new(token, &encode_call("symbol()", &[])), Call::new(token, &encode_call("decimals()", &[]))]).collect();
+            let results = multicall::aggregate(&self.feed.client, &calls).await?;
+            for (token, results) in unknown.into_iter().zip(results.chunks(2)) {
+                let decimals = match results[1].as_deref().and_then(|result| decode_result(result, &[ParamType::Uint(8)]).ok()).and_then(|mut tokens| tokens.pop()) {
+                    Some(Token::Uint(decimals)) => Some(decimals.min(U256::from(77)).as_u32()),
+                    _ => None,
+                };
+                let symbol = results[0].as_deref().map(decode_symbol).unwrap_or_default();
+                self.tokens.insert(token, decimals.map(|decimals| (symbol, decimals)));
+            }
+        }
+
+        for activity in activities.iter_mut() {
+            let (Some(token), Some(amount)) = (&activity.token, &mut activity.amount) else {
+                continue;
+            };
+            match self.tokens.get(token).cloned().flatten() {
+                Some((symbol, decimals)) => {
+                    amount.decimals = decimals;
+                    amount.symbol = (!symbol.is_empty()).then_some(symbol);
+                }
+                None => amount.symbol = Some(format!("of {}", format::address(token))),
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn label_counterparties(labels: Option<&LabelResolver>, activities: &mut [Activity]) -> Result<()> {
+    let Some(labels) = labels else {
+        for activity in activities.iter_mut() {
+            activity.counterparty = activity.counterparty.as_deref().map(format::address);
+        }
+        return Ok(());
+    };
+    let addresses: Vec<String> = activities.iter().filter_map(|activity| activity.counterparty.clone()).collect::<BTreeSet<_>>().into_iter().collect();
+    let names = labels.labels(&addresses.iter().map(String::as_str).collect::<Vec<_>>()).await?;
+    let names: HashMap<&String, Option<String>> = addresses.iter().zip(names).map(|(address, label)| (address, label.map(|label| label.name))).collect();
+    for activity in activities.iter_mut() {
+        if let Some(address) = &activity.counterparty {
+            activity.counterparty = Some(match names.get(address).cloned().flatten() {
+                Some(name) => format!("{} ({})", name, format::address(address)),
+                None => format::address(address),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::multicall::tests::Multicall;
+    use crate::testing::MockNode;
+
+    const ALICE: &str = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const BOB: &str = "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+    const USDC: &str = "0xcccccccccccccccccccccccccccccccccccccccc";
+    const SPENDER: &str = "0xdddddddddddddddddddddddddddddddddddddddd";
+
+    fn topic(address: &str) -> String {
+        format!("0x{:0>64}", &address[2..])
+    }
+
+    fn transaction(hash: u64) -> (serde_json::Value, serde_json::Value) {
+        let log = |block: u64, event: &str, topics: [&str; 2], data: String| {
+            serde_json::json!({
+                "address": USDC,
+                "topics": [format!("{:?}", keccak_hash::keccak(event.as_bytes())), topic(topics[0]), topic(topics[1])],
+                "data": data,
+                "blockNumber": format!("0x{:x}", block),
+                "transactionHash": H256::from_low_u64_be(hash),
+                "transactionIndex": "0x0",
+                "logIndex": "0x0",
+            })
+        };
+        let (block, from, to, value, logs) = match hash {
+            1 => (10, BOB, USDC, 0, vec![log(10, "Transfer(address,address,uint256)", [BOB, ALICE], format!("0x{:064x}", 1_500_000))]),
+            2 => (11, ALICE, USDC, 0, vec![log(11, "Approval(address,address,uint256)", [ALICE, SPENDER], format!("0x{:x}", U256::MAX))]),
+            _ => (12, ALICE, BOB, 1_000_000_000_000_000_000u64, vec![]),
+        };
+        let transaction = serde_json::json!({"hash": H256::from_low_u64_be(hash), "from": from, "to": to, "value": format!("0x{:x}", value), "input": if value == 0 { "0xa9059cbb" } else { "0x" }});
+        let receipt = serde_json::json!({"transactionHash": H256::from_low_u64_be(hash), "blockNumber": format!("0x{:x}", block), "from": from, "to": to, "status": "0x1", "logs": logs});
+        (transaction, receipt)
+    }
+
+    // Alice receives 1.5 USDC from Bob in block 10, approves a spender for unlimited
+    // USDC in block 11 and sends Bob 1 ETH in block 12; token calls go to `tokens`
+    fn wallet(tokens: Multicall) -> EthClient {
+        MockNode::new(move |method, params| {
+            let hash = |value: &serde_json::Value| u64::from_str_radix(&value.as_str().unwrap()[50..], 16).unwrap();
+            match method {
+                "eth_blockNumber" => Ok(serde_json::json!("0x10")),
+                "eth_getLogs" => {
+                    let topics = &params[0]["topics"];
+                    let logs: Vec<serde_json::Value> = (1..=2).flat_map(|hash| transaction(hash).1["logs"].as_array().unwrap().clone()).collect();
+                    let matching = |log: &&serde_json::Value| {
+                        let event = |alternatives: &serde_json::Value| alternatives == &log["topics"][0] || alternatives.as_array().is_some_and(|topics| topics.contains(&log["topics"][0]));
+                        event(&topics[0]) && (1..=2).all(|position| topics[position].is_null() || topics[position] == log["topics"][position])
+                    };
+                    Ok(logs.iter().filter(matching).cloned().collect())
+                }
+                "eth_getBlockByNumber" if params[0] == "0xc" => Ok(serde_json::json!({"transactions": [transaction(3).0]})),
+                "eth_getBlockByNumber" => Ok(serde_json::json!({"transactions": []})),
+                "eth_getTransactionByHash" => Ok(transaction(hash(&params[0])).0),
+                "eth_getTransactionReceipt" => Ok(transaction(hash(&params[0])).1),
+                _ => tokens.reply(method, params),
+            }
+        })
+        .client()
+    }
+
+    #[tokio::test]
+    async fn describes_transfers_approvals_and_native_sends() {
+        let mut answers = HashMap::new();
+        answers.insert((USDC.to_string(), encode_call("symbol()", &[])), format!("0x{}", hex::encode(ethabi::encode(&[Token::String("USDC".to_string())]))));
+        answers.insert((USDC.to_string(), encode_call("decimals()", &[])), format!("0x{:064x}", 6));
+        let client = wallet(Multicall(answers));
+        let address_book = [("bob".to_string(), BOB.to_string())].into_iter().collect();
+        let labels = LabelResolver::new(&client, 1).without_ens().with_address_book(&address_book);
+
+        let feed = client.activity_feed(ALICE).with_from_block(10).with_block_scan(true).with_labels(labels).stream();
+        let activities: Vec<Activity> = feed.take(3).map(Result::unwrap).collect().await;
+        let described: Vec<String> = activities.iter().map(Activity::to_string).collect();
+        assert_eq!(
+            described,
+            [
+                format!("Received 1.5 USDC from bob ({})", format::address(BOB)),
+                format!("Approved {} to spend all tokens", format::address(SPENDER)),
+                format!("Sent 1 ETH to bob ({})", format::address(BOB)),
+            ]
+        );
+        assert_eq!(activities.iter().map(|activity| activity.block_number).collect::<Vec<_>>(), [10, 11, 12]);
+        assert_eq!(activities[1].token.as_deref(), Some(USDC));
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+
+pub struct Artifact {
+    pub abi: ethabi::Contract,
+    // Hex strings with 0x prefix; unlinked library placeholders are kept as-is
+    pub bytecode: String,
+    pub deployed_bytecode: String,
+    pub storage_layout: Option<StorageLayout>,
+    // Byte ranges of the deployed bytecode filled in with immutable values at deploy time
+    pub immutable_references: Vec<ImmutableReference>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ImmutableReference {
+    pub start: usize,
+    pub length: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StorageLayout {
+    pub storage: Vec<StorageSlot>,
+    #[serde(default)]
+    pub types: HashMap<String, StorageType>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StorageSlot {
+    pub label: String,
+    pub slot: String,
+    pub offset: u64,
+    #[serde(rename = "type")]
+    pub type_name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageType {
+    pub encoding: String,
+    pub label: String,
+    pub number_of_bytes: String,
+    pub key: Option<String>,
+    pub value: Option<String>,
+    pub base: Option<String>,
+    pub members: Option<Vec<StorageSlot>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FoundryArtifact {
+    abi: ethabi::Contract,
+    bytecode: FoundryBytecode,
+    deployed_bytecode: FoundryDeployedBytecode,
+    storage_layout: Option<StorageLayout>,
+}
+
+#[derive(Deserialize)]
+struct FoundryBytecode {
+    object: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FoundryDeployedBytecode {
+    object: String,
+    #[serde(default)]
+    immutable_references: HashMap<String, Vec<ImmutableReference>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HardhatArtifact {
+    abi: ethabi::Contract,
+    bytecode: String,
+    deployed_bytecode: String,
+}
+
+// Loads a Foundry `out/<File>.sol/<Contract>.json` artifact
+pub fn load_foundry_artifact(path: impl AsRef<Path>) -> Result<Artifact> {
+    foundry_artifact(read_json(path.as_ref())?)
+}
+
+fn foundry_artifact(value: serde_json::Value) -> Result<Artifact> {
+    let artifact: FoundryArtifact = serde_json::from_value(value).map_err(|error| Error::Decode(format!("Invalid Foundry artifact: {}", error)))?;
+    Ok(Artifact {
+        abi: artifact.abi,
+        bytecode: with_hex_prefix(artifact.bytecode.object),
+        deployed_bytecode: with_hex_prefix(artifact.deployed_bytecode.object),
+        storage_layout: artifact.storage_layout,
+        immutable_references: artifact
+            .deployed_bytecode
+            .immutable_references
+            .into_values()
+            .flatten()
+            .collect(),
+    })
+}
+
+// Loads a Hardhat `artifacts/<File>.sol/<Contract>.json` artifact; Hardhat keeps
+// storage layouts and immutable references in build-info files, so none are attached here
+pub fn load_hardhat_artifact(path: impl AsRef<Path>) -> Result<Artifact> {
+    hardhat_artifact(read_json(path.as_ref())?)
+}
+
+fn hardhat_artifact(value: serde_json::Value) -> Result<Artifact> {
+    let artifact: HardhatArtifact = serde_json::from_value(value).map_err(|error| Error::Decode(format!("Invalid Hardhat artifact: {}", error)))?;
+    Ok(Artifact {
+        abi: artifact.abi,
+        bytecode: with_hex_prefix(artifact.bytecode),
+        deployed_bytecode: with_hex_prefix(artifact.deployed_bytecode),
+        storage_layout: None,
+        immutable_references: Vec::new(),
+    })
+}
+
+// Detects the format from the shape of the `bytecode` field
+pub fn load_artifact(path: impl AsRef<Path>) -> Result<Artifact> {
+    let value = read_json(path.as_ref())?;
+    if value["bytecode"].is_object() {
+        foundry_artifact(value)
+    } else {
+        hardhat_artifact(value)
+    }
+}
+
+fn read_json(path: &Path) -> Result<serde_json::Value> {
+    let contents = std::fs::read_to_string(path)?;
+    serde_json::from_str(&contents).map_err(|error| Error::Decode(format!("Invalid JSON in {}: {}", path.display(), error)))
+}
+
+fn with_hex_prefix(bytecode: String) -> String {
+    if bytecode.starts_with("0x") {
+        bytecode
+    } else {
+        format!("0x{}", bytecode)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    // Deployed code with an immutable at bytes 1..3, followed by solc's CBOR metadata
+    pub(crate) const DEPLOYED: &str = "60aabbcc00a16501020304050007";
+
+    fn foundry_json() -> serde_json::Value {
+        json!({
+            "abi": [{"type": "function", "name": "owner", "inputs": [], "outputs": [{"name": "", "type": "address"}], "stateMutability": "view"}],
+            "bytecode": {"object": "0x6080"},
+            "deployedBytecode": {"object": DEPLOYED, "immutableReferences": {"7": [{"start": 1, "length": 2}]}},
+            "storageLayout": {"storage": [{"label": "owner", "slot": "0", "offset": 0, "type": "t_address"}], "types": {}},
+        })
+    }
+
+    #[test]
+    fn loads_foundry_and_hardhat_artifacts_by_shape() {
+        let path = std::env::temp_dir().join(format!("evm-json-rpc-artifact-{}.json", std::process::id()));
+        std::fs::write(&path, foundry_json().to_string()).unwrap();
+        let foundry = load_artifact(&path).unwrap();
+        assert_eq!((foundry.bytecode.as_str(), foundry.deployed_bytecode.as_str()), ("0x6080", "0x60aabbcc00a16501020304050007"));
+        assert_eq!((foundry.immutable_references[0].start, foundry.immutable_references[0].length), (1, 2));
+        assert_eq!(foundry.storage_layout.unwrap().storage[0].label, "owner");
+        assert!(foundry.abi.function("owner").is_ok());
+
+        let hardhat = json!({"abi": [], "bytecode": "0x6080", "deployedBytecode": "6080"});
+        std::fs::write(&path, hardhat.to_string()).unwrap();
+        let hardhat = load_artifact(&path).unwrap();
+        assert_eq!(hardhat.deployed_bytecode, "0x6080");
+        assert!(hardhat.storage_layout.is_none() && hardhat.immutable_references.is_empty());
+
+        std::fs::write(&path, "{").unwrap();
+        assert!(matches!(load_artifact(&path), Err(Error::Decode(_))));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(load_artifact(&path), Err(Error::Io(_))));
+    }
+}
@@ -0,0 +1,292 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ethabi::ethereum_types::H256;
+use ethabi::{Event, RawLog, Token};
+
+use crate::abi::token_to_json;
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::format;
+use crate::utils::parse_quantity;
+
+// Blocks fetched per eth_getLogs request, below most providers' range limits
+pub const MAX_BLOCK_RANGE: u64 = 1_000;
+
+// A decoded event that matched a rule
+#[derive(Debug, Clone)]
+pub struct Trigger {
+    pub rule: String,
+    pub address: String,
+    pub block_number: u64,
+    pub transaction_hash: String,
+    pub params: Vec<(String, Token)>,
+    // The raw eth_getLogs entry
+    pub log: serde_json::Value,
+}
+
+impl Trigger {
+    pub fn param(&self, name: &str) -> Option<&Token> {
+        self.params.iter().find(|(param, _)| param == name).map(|(_, token)| token)
+    }
+
+    // Decoded parameters as a JSON object, as sent to webhooks. Addresses and the
+    // transaction hash follow the global format options.
+    pub fn to_json(&self) -> serde_json::Value {
+        let params: serde_json::Map<String, serde_json::Value> = self.params.iter().map(|(name, token)| (name.clone(), token_to_json(token))).collect();
+        serde_json::json!({
+            "rule": self.rule,
+            "address": format::address(&self.address),
+            "blockNumber": self.block_number,
+            "transactionHash": format::hash(&self.transaction_hash),
+            "params": params,
+        })
+    }
+}
+
+type CallBuilder = Arc<dyn Fn(&Trigger) -> (String, String) + Send + Sync>;
+type Callback = Arc<dyn Fn(&Trigger) + Send + Sync>;
+
+pub enum Action {
+    // eth_call built from the trigger, e.g. to read a balance the event touched.
+    // The builder returns (to, data).
+    Call(CallBuilder),
+    // POSTs Trigger::to_json to the URL
+    Webhook(String),
+    Callback(Callback),
+}
+
+#[derive(Debug)]
+pub struct Outcome {
+    pub trigger: Trigger,
+    // eth_call result for Call actions, null otherwise
+    pub results: Vec<Result<serde_json::Value, String>>,
+}
+
+pub struct Rule {
+    name: String,
+    address: Option<String>,
+    event: Event,
+    actions: Vec<Action>,
+}
+
+impl Rule {
+    // Matches `event` from any contract unless `from_address` narrows it
+    pub fn new(name: &str, event: Event) -> Self {
+        Rule {
+            name: name.to_string(),
+            address: None,
+            event,
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn from_address(mut self, address: &str) -> Self {
+        self.address = Some(address.to_lowercase());
+        self
+    }
+
+    pub fn call(mut self, build: impl Fn(&Trigger) -> (String, String) + Send + Sync + 'static) -> Self {
+        self.actions.push(Action::Call(Arc::new(build)));
+        self
+    }
+
+    pub fn webhook(mut self, url: &str) -> Self {
+        self.actions.push(Action::Webhook(url.to_string()));
+        self
+    }
+
+    pub fn on_trigger(mut self, callback: impl Fn(&Trigger) + Send + Sync + 'static) -> Self {
+        self.actions.push(Action::Callback(Arc::new(callback)));
+        self
+    }
+
+    fn matches(&self, log: &serde_json::Value) -> bool {
+        let address_matches = self.address.as_ref().is_none_or(|address| log["address"].as_str().is_some_and(|log_address| log_address.eq_ignore_ascii_case(address)));
+        let topic = format!("{:?}", self.event.signature());
+        address_matches && log["topics"][0].as_str().is_some_and(|topic0| topic0.eq_ignore_ascii_case(&topic))
+    }
+}
+
+// Polls eth_getLogs and runs each matching rule's actions in order. Action failures are
+// reported in the outcome and never stop the engine; RPC failures end a poll and the
+// same range is retried on the next one.
+pub struct Automation {
+    client: EthClient,
+    http: reqwest::Client,
+    rules: Vec<Rule>,
+    next_block: Option<u64>,
+}
+
+impl Automation {
+    pub fn new(client: &EthClient) -> Self {
+        Automation {
+            client: client.clone(),
+            http: client.http_client().clone(),
+            rules: Vec::new(),
+            next_block: None,
+        }
+    }
+
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    // Replays logs from `block` on; by default the engine starts at the current head
+    pub fn from_block(mut self, block: u64) -> Self {
+        self.next_block = Some(block);
+        self
+    }
+
+    // Processes the blocks since the last poll
+    pub async fn poll(&mut self) -> Result<Vec<Outcome>> {
+        if self.rules.is_empty() {
+            return Ok(Vec::new());
+        }
+        let latest = self.client.get_block_number().await?;
+        let from = *self.next_block.get_or_insert(latest);
+        if from > latest {
+            return Ok(Vec::new());
+        }
+        let to = latest.min(from + MAX_BLOCK_RANGE - 1);
+
+        let mut filter = serde_json::json!({
+            "fromBlock": format!("0x{:x}", from),
+            "toBlock": format!("0x{:x}", to),
+        });
+        let topics: Vec<H256> = self.rules.iter().map(|rule| rule.event.signature()).collect();
+        filter["topics"] = serde_json::json!([topics]);
+        let addresses: Option<Vec<&String>> = self.rules.iter().map(|rule| rule.address.as_ref()).collect();
+        if let Some(addresses) = addresses {
+            filter["address"] = serde_json::json!(addresses);
+        }
+        let logs = self.client.send("eth_getLogs", vec![filter]).await?;
+
+        let mut outcomes = Vec::new();
+        for log in logs.as_array().ok_or_else(|| Error::Decode(format!("Invalid eth_getLogs response: {}", logs)))? {
+            if log["removed"].as_bool() == Some(true) {
+                continue;
+            }
+            for rule in self.rules.iter().filter(|rule| rule.matches(log)) {
+                // Logs that share the signature but not the indexed layout don't decode
+                let Ok(trigger) = decode_trigger(rule, log) else { continue };
+                let mut results = Vec::new();
+                for action in &rule.actions {
+                    results.push(self.run_action(action, &trigger).await);
+                }
+                outcomes.push(Outcome { trigger, results });
+            }
+        }
+        self.next_block = Some(to + 1);
+        Ok(outcomes)
+    }
+
+    // Polls forever, handing each outcome to `on_outcome`
+    pub async fn run(mut self, interval: Duration, on_outcome: impl Fn(Outcome)) -> Result<()> {
+        loop {
+            for outcome in self.poll().await? {
+                on_outcome(outcome);
+            }
+            tokio::time::sleep(crate::poll::jittered(interval)).await;
+        }
+    }
+
+    async fn run_action(&self, action: &Action, trigger: &Trigger) -> Result<serde_json::Value, String> {
+        match action {
+            Action::Call(build) => {
+                let (to, data) = build(trigger);
+                self.client.call(&to, &data).await.map(serde_json::Value::String).map_err(|error| error.to_string())
+            }
+            Action::Webhook(url) => {
+                let response = self.http.post(url).json(&trigger.to_json()).send().await.map_err(|error| error.without_url().to_string())?;
+                response.error_for_status().map_err(|error| error.without_url().to_string())?;
+                Ok(serde_json::Value::Null)
+            }
+            Action::Callback(callback) => {
+                callback(trigger);
+                Ok(serde_json::Value::Null)
+            }
+        }
+    }
+}
+
+fn decode_trigger(rule: &Rule, log: &serde_json::Value) -> Result<Trigger> {
+    let topics: Vec<H256> = serde_json::from_value(log["topics"].clone()).map_err(|error| Error::Decode(format!("Invalid log topics: {}", error)))?;
+    let data = hex::decode(log["data"].as_str().unwrap_or_default().trim_start_matches("0x")).map_err(|error| Error::Decode(format!("Invalid log data: {}", error)))?;
+    let parsed = rule.event.parse_log(RawLog { topics, data }).map_err(|error| Error::Abi(error.to_string()))?;
+    Ok(Trigger {
+        rule: rule.name.clone(),
+        address: log["address"].as_str().unwrap_or_default().to_string(),
+        block_number: parse_quantity(&log["blockNumber"])?,
+        transaction_hash: log["transactionHash"].as_str().unwrap_or_default().to_string(),
+        params: parsed.params.into_iter().map(|param| (param.name, param.value)).collect(),
+        log: log.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use ethabi::{EventParam, ParamType};
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{Failure, MockNode};
+
+    const TOKEN: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+    fn transfer_event() -> Event {
+        let param = |name: &str, kind: ParamType, indexed: bool| EventParam { name: name.to_string(), kind, indexed };
+        Event {
+            name: "Transfer".to_string(),
+            inputs: vec![param("from", ParamType::Address, true), param("to", ParamType::Address, true), param("value", ParamType::Uint(256), false)],
+            anonymous: false,
+        }
+    }
+
+    fn log(removed: bool) -> serde_json::Value {
+        let address = |byte: &str| format!("0x{:0>64}", byte.repeat(20));
+        json!({
+            "address": TOKEN,
+            "topics": [format!("{:?}", transfer_event().signature()), address("11"), address("22")],
+            "data": format!("0x{:064x}", 500),
+            "blockNumber": "0x4",
+            "transactionHash": format!("0x{}", "ab".repeat(32)),
+            "removed": removed,
+        })
+    }
+
+    #[tokio::test]
+    async fn runs_the_actions_of_matching_rules() {
+        let node = MockNode::new(|method, _| match method {
+            "eth_blockNumber" => Ok(json!("0x5")),
+            "eth_getLogs" => Ok(json!([log(false), log(true)])),
+            "eth_call" => Ok(json!("0x2a")),
+            _ => Err(Failure::unexpected(method)),
+        });
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = seen.clone();
+        let rule = Rule::new("large transfers", transfer_event())
+            .from_address(&TOKEN.to_uppercase().replacen("0X", "0x", 1))
+            .on_trigger(move |trigger| recorded.lock().unwrap().push(trigger.param("value").cloned()))
+            .call(|trigger| (trigger.address.clone(), "0x70a08231".to_string()));
+        let mut automation = Automation::new(&node.client()).rule(rule).from_block(3);
+
+        let outcomes = automation.poll().await.unwrap();
+        // The removed log is skipped
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].results, [Ok(serde_json::Value::Null), Ok(json!("0x2a"))]);
+        assert_eq!(*seen.lock().unwrap(), [Some(Token::Uint(500.into()))]);
+        let trigger = &outcomes[0].trigger;
+        assert_eq!((trigger.rule.as_str(), trigger.block_number), ("large transfers", 4));
+        assert_eq!(trigger.to_json()["params"]["to"], json!(format!("0x{}", "22".repeat(20))));
+        let filter = &node.params_of("eth_getLogs")[0][0];
+        assert_eq!((filter["fromBlock"].as_str(), filter["toBlock"].as_str(), filter["address"].clone()), (Some("0x3"), Some("0x5"), json!([TOKEN])));
+
+        // Caught up with the head: nothing to fetch
+        assert!(automation.poll().await.unwrap().is_empty());
+        assert_eq!(node.params_of("eth_getLogs").len(), 1);
+    }
+}
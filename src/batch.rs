@@ -0,0 +1,362 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ethabi::ethereum_types::{H256, U256};
+use ethabi::{ParamType, Token};
+
+use crate::abi::{decode_result, encode_call, encode_call_args, token_to_json};
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::multicall::{self, Call};
+use crate::pending::PendingTransaction;
+use crate::progress::{Progress, ProgressTracker};
+use crate::transaction::TransactionRequest;
+use crate::transport::WsTransport;
+use crate::utils::{is_checksum_address, parse_units, to_checksum_address};
+use crate::wallet::Wallet;
+
+// Calls per aggregate3 request in batch-call
+pub const CALL_CHUNK_SIZE: usize = 200;
+
+// Tab-separated for .tsv and .tab files, comma-separated otherwise
+pub fn delimiter_for(path: &Path) -> u8 {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("tsv" | "tab") => b'\t',
+        _ => b',',
+    }
+}
+
+// A row of the input file, kept as read so the results file can echo it. Lines count
+// from 1 with the header on line 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub line: usize,
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Row>,
+}
+
+impl Table {
+    pub fn read(reader: impl Read, delimiter: u8) -> Result<Self> {
+        let mut reader = csv::ReaderBuilder::new().delimiter(delimiter).trim(csv::Trim::All).comment(Some(b'#')).from_reader(reader);
+        let headers = reader.headers().map_err(csv_error)?.iter().map(str::to_string).collect();
+        let rows = reader
+            .records()
+            .map(|record| {
+                let record = record.map_err(csv_error)?;
+                Ok(Row {
+                    line: record.position().map_or(0, |position| position.line() as usize),
+                    fields: record.iter().map(str::to_string).collect(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Table { headers, rows })
+    }
+
+    fn column(&self, name: &str) -> Option<usize> {
+        self.headers.iter().position(|header| header.eq_ignore_ascii_case(name))
+    }
+}
+
+// One payment: ETH when token is None
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    pub row: Row,
+    pub to: String,
+    pub token: Option<String>,
+    pub amount: U256,
+}
+
+impl Transfer {
+    pub fn request(&self) -> TransactionRequest {
+        match &self.token {
+            Some(token) => TransactionRequest {
+                to: Some(token.clone()),
+                data: Some(encode_call("transfer(address,uint256)", &[Token::Address(self.to.trim_start_matches("0x").parse().unwrap_or_default()), Token::Uint(self.amount)])),
+                ..Default::default()
+            },
+            None => TransactionRequest {
+                to: Some(self.to.clone()),
+                ..Default::default()
+            }
+            .with_value(self.amount),
+        }
+    }
+}
+
+// Reads `to` and `amount` columns, plus an optional `token` column (empty for ETH).
+// Every row is checked before anything is sent: addresses (address book names are
+// resolved, mixed case must be a valid EIP-55 checksum) and amounts against each
+// token's decimals. All problems are reported together.
+pub async fn read_transfers(client: &EthClient, table: &Table, address_book: &BTreeMap<String, String>) -> Result<Vec<Transfer>> {
+    let (Some(to), Some(amount)) = (table.column("to"), table.column("amount")) else {
+        return Err(Error::InvalidInput("Batch file needs `to` and `amount` columns".to_string()));
+    };
+    let token = table.column("token");
+    let field = |row: &Row, column: Option<usize>| column.and_then(|column| row.fields.get(column)).map(String::as_str).unwrap_or_default().to_string();
+    let resolve = |value: String| address_book.get(&value).cloned().unwrap_or(value);
+
+    let mut problems = Vec::new();
+    let mut tokens: HashMap<String, Option<u32>> = HashMap::new();
+    for row in &table.rows {
+        match field(row, token) {
+            token if token.is_empty() => {}
+            token => match checked_address(&resolve(token)) {
+                Ok(token) => {
+                    tokens.insert(token, None);
+                }
+                Err(error) => problems.push(format!("line {}: token {}", row.line, error)),
+            },
+        }
+    }
+    let unique: Vec<String> = tokens.keys().cloned().collect::<BTreeSet<_>>().into_iter().collect();
+    let decimals = encode_call("decimals()", &[]);
+    let calls: Vec<Call> = unique.iter().map(|token| Call::new(token, &decimals)).collect();
+    for (token, result) in unique.iter().zip(multicall::aggregate(client, &calls).await?) {
+        let decimals = match result.as_deref().map(|result| decode_result(result, &[ParamType::Uint(8)])) {
+            Some(Ok(tokens)) => match tokens.first() {
+                Some(Token::Uint(decimals)) if *decimals <= U256::from(77) => Some(decimals.as_u32()),
+                _ => None,
+            },
+            _ => None,
+        };
+        tokens.insert(token.clone(), decimals);
+    }
+
+    let mut transfers = Vec::with_capacity(table.rows.len());
+    for row in &table.rows {
+        let mut problem = |message: String| problems.push(format!("line {}: {}", row.line, message));
+        let to = match checked_address(&resolve(field(row, Some(to)))) {
+            Ok(to) => to,
+            Err(error) => {
+                problem(format!("recipient {}", error));
+                continue;
+            }
+        };
+        let (token, decimals) = match field(row, token) {
+            token if token.is_empty() => (None, 18),
+            token => match checked_address(&resolve(token)).ok().map(|token| (tokens.get(&token).copied().flatten(), token)) {
+                Some((Some(decimals), token)) => (Some(token), decimals),
+                Some((None, token)) => {
+                    problem(format!("{} does not answer decimals() like an ERC-20 token", token));
+                    continue;
+                }
+                None => continue,
+            },
+        };
+        match parse_units(&field(row, Some(amount)), decimals) {
+            Ok(amount) if amount.is_zero() => problem("amount is zero".to_string()),
+            Ok(amount) => transfers.push(Transfer { row: row.clone(), to, token, amount }),
+            Err(error) => problem(error.to_string()),
+        }
+    }
+    if !problems.is_empty() {
+        return Err(Error::InvalidInput(format!("{} invalid rows:\n{}", problems.len(), problems.join("\n"))));
+    }
+    Ok(transfers)
+}
+
+// One call per row, each column an argument of `signature` in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchCall {
+    pub row: Row,
+    pub data: String,
+}
+
+// Encodes every row up front, reporting all rows that don't fit the signature
+pub fn read_calls(table: &Table, signature: &str) -> Result<Vec<BatchCall>> {
+    let mut problems = Vec::new();
+    let mut calls = Vec::with_capacity(table.rows.len());
+    for row in &table.rows {
+        let args: Vec<&str> = row.fields.iter().map(String::as_str).collect();
+        match encode_call_args(signature, &args) {
+            Ok(data) => calls.push(BatchCall { row: row.clone(), data }),
+            Err(error) => problems.push(format!("line {}: {}", row.line, error)),
+        }
+    }
+    if !problems.is_empty() {
+        return Err(Error::InvalidInput(format!("{} invalid rows:\n{}", problems.len(), problems.join("\n"))));
+    }
+    Ok(calls)
+}
+
+// What happened to one row: a transaction hash or call result, or the error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub row: Row,
+    pub result: std::result::Result<String, String>,
+}
+
+// Sends the transfers one after another, numbering nonces locally. A failed send is
+// recorded and the batch carries on, since later rows may still go through.
+pub async fn send_transfers(wallet: &Wallet, transfers: &[Transfer], mut on_outcome: impl FnMut(&Outcome, &Progress)) -> Vec<Outcome> {
+    let tracker = ProgressTracker::new(Some(transfers.len() as u64), None);
+    let mut outcomes = Vec::with_capacity(transfers.len());
+    for transfer in transfers {
+        let result = wallet.send(&transfer.request()).await.map(|hash| format!("{:?}", hash)).map_err(|error| error.to_string());
+        let outcome = Outcome { row: transfer.row.clone(), result };
+        on_outcome(&outcome, &tracker.report(outcomes.len() as u64 + 1, None));
+        outcomes.push(outcome);
+    }
+    outcomes
+}
+
+// Waits for the sent transactions' receipts, turning reverted or unconfirmed ones into
+// errors. Gives up on the rest once `timeout` has passed. With `heads` receipts are
+// checked on each new block instead of on a timer.
+pub async fn confirm(client: &EthClient, outcomes: &mut [Outcome], heads: Option<Arc<WsTransport>>, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    for outcome in outcomes.iter_mut() {
+        let Ok(hash) = &outcome.result else { continue };
+        let Ok(parsed) = hash.parse::<H256>() else { continue };
+        let mut pending = PendingTransaction::new(client, parsed).with_timeout(deadline.saturating_duration_since(Instant::now()));
+        if let Some(heads) = &heads {
+            pending = pending.with_heads(heads.clone());
+        }
+        outcome.result = match pending.wait().await {
+            Ok(receipt) if receipt.is_success() => Ok(hash.clone()),
+            Ok(_) => Err(format!("{} reverted", hash)),
+            Err(error) => Err(format!("{}: {}", hash, error)),
+        };
+    }
+}
+
+// Runs the calls through Multicall3 in chunks. With `returns` the results are decoded
+// (JSON for multiple values), otherwise they stay raw hex; reverts become errors.
+pub async fn execute_calls(client: &EthClient, to: &str, calls: &[BatchCall], returns: &[ParamType], mut on_outcome: impl FnMut(&Outcome, &Progress)) -> Result<Vec<Outcome>> {
+    let tracker = ProgressTracker::new(Some(calls.len() as u64), None);
+    let mut outcomes = Vec::with_capacity(calls.len());
+    for chunk in calls.chunks(CALL_CHUNK_SIZE) {
+        let batch: Vec<Call> = chunk.iter().map(|call| Call::new(to, &call.data)).collect();
+        for (call, result) in chunk.iter().zip(multicall::aggregate(client, &batch).await?) {
+            let result = match result {
+                None => Err("reverted".to_string()),
+                Some(data) if returns.is_empty() => Ok(data),
+                Some(data) => decode_result(&data, returns).map_err(|error| error.to_string()).map(|tokens| match tokens.as_slice() {
+                    [token] => token_text(token),
+                    tokens => serde_json::Value::Array(tokens.iter().map(token_to_json).collect()).to_string(),
+                }),
+            };
+            let outcome = Outcome { row: call.row.clone(), result };
+            on_outcome(&outcome, &tracker.report(outcomes.len() as u64 + 1, None));
+            outcomes.push(outcome);
+        }
+    }
+    Ok(outcomes)
+}
+
+// The input columns followed by `result` and `error`
+pub fn write_results(writer: impl Write, delimiter: u8, headers: &[String], outcomes: &[Outcome]) -> Result<()> {
+    let mut writer = csv::WriterBuilder::new().delimiter(delimiter).from_writer(writer);
+    writer.write_record(headers.iter().map(String::as_str).chain(["result", "error"])).map_err(csv_error)?;
+    for outcome in outcomes {
+        let (result, error) = match &outcome.result {
+            Ok(result) => (result.as_str(), ""),
+            Err(error) => ("", error.as_str()),
+        };
+        writer.write_record(outcome.row.fields.iter().map(String::as_str).chain([result, error])).map_err(csv_error)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// Returns the checksummed form; lowercase and uppercase are taken as unchecked
+fn checked_address(address: &str) -> std::result::Result<String, String> {
+    let digits = address.strip_prefix("0x").ok_or_else(|| format!("{} is not a 0x address", address))?;
+    if digits.len() != 40 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("{} is not a 20-byte hex address", address));
+    }
+    let mixed = digits.chars().any(|c| c.is_ascii_lowercase()) && digits.chars().any(|c| c.is_ascii_uppercase());
+    if mixed && !is_checksum_address(address) {
+        return Err(format!("{} has an invalid EIP-55 checksum", address));
+    }
+    Ok(to_checksum_address(address))
+}
+
+fn token_text(token: &Token) -> String {
+    match token_to_json(token) {
+        serde_json::Value::String(text) => text,
+        value => value.to_string(),
+    }
+}
+
+fn csv_error(error: csv::Error) -> Error {
+    match error.into_kind() {
+        csv::ErrorKind::Io(error) => Error::Io(error),
+        kind => Error::Decode(format!("Invalid batch file: {:?}", kind)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::multicall::tests::Multicall;
+
+    const USDC: &str = "0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238";
+
+    fn client() -> EthClient {
+        let mut answers = HashMap::new();
+        answers.insert((USDC.to_lowercase(), encode_call("decimals()", &[])), format!("0x{:064x}", 6));
+        Multicall(answers).client()
+    }
+
+    #[tokio::test]
+    async fn validates_every_transfer_row() {
+        let csv = format!(
+            "to,amount,token\n\
+             0x52908400098527886e0f7030069857d2e4169ee7,1.5,\n\
+             alice,2.25,{}\n",
+            USDC.to_lowercase()
+        );
+        let book = [("alice".to_string(), "0x8617E340B3D01FA5F11F306F4090FD50E238070D".to_string())].into_iter().collect();
+        let transfers = read_transfers(&client(), &Table::read(csv.as_bytes(), b',').unwrap(), &book).await.unwrap();
+        assert_eq!(transfers[0].to, "0x52908400098527886E0F7030069857D2E4169EE7");
+        assert_eq!((transfers[0].token.as_deref(), transfers[0].amount), (None, U256::from(1_500_000_000_000_000_000u64)));
+        assert_eq!((transfers[1].token.as_deref(), transfers[1].amount, transfers[1].row.line), (Some(USDC), U256::from(2_250_000), 3));
+        let request = transfers[1].request();
+        assert_eq!(request.to.as_deref(), Some(USDC));
+        assert!(request.data.unwrap().starts_with("0xa9059cbb0000000000000000000000008617e340"));
+
+        let bad = format!(
+            "to\tamount\ttoken\n\
+             0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD\t1\t\n\
+             0xde709f2102306220921060314715629080e2fb77\t1.0000001\t{}\n\
+             0xde709f2102306220921060314715629080e2fb77\t1\t0x000000000000000000000000000000000000dead\n\
+             bob\tten\t\n",
+            USDC
+        );
+        let Err(Error::InvalidInput(problems)) = read_transfers(&client(), &Table::read(bad.as_bytes(), b'\t').unwrap(), &BTreeMap::new()).await else { panic!("expected invalid rows") };
+        assert!(problems.starts_with("4 invalid rows"), "{}", problems);
+        assert!(problems.contains("line 2: recipient 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD has an invalid EIP-55 checksum"));
+        assert!(problems.contains("line 3: Invalid input: Invalid amount 1.0000001: more than 6 decimal places"));
+        assert!(problems.contains("line 4: 0x000000000000000000000000000000000000dEaD does not answer decimals()"));
+        assert!(problems.contains("line 5: recipient bob is not a 0x address"));
+    }
+
+    #[tokio::test]
+    async fn runs_calls_and_writes_results() {
+        let table = Table::read("account\n0x52908400098527886e0f7030069857d2e4169ee7\n0xde709f2102306220921060314715629080e2fb77\n".as_bytes(), b',').unwrap();
+        let calls = read_calls(&table, "balanceOf(address)").unwrap();
+        assert!(read_calls(&table, "balanceOf(address,uint256)").is_err());
+
+        let mut answers = HashMap::new();
+        answers.insert((USDC.to_lowercase(), calls[0].data.clone()), format!("0x{:064x}", 42));
+        let client = Multicall(answers).client();
+        let mut seen = 0;
+        let outcomes = execute_calls(&client, USDC, &calls, &[ParamType::Uint(256)], |_, progress| seen = progress.done).await.unwrap();
+        assert_eq!(seen, 2);
+
+        let mut results = Vec::new();
+        write_results(&mut results, b',', &table.headers, &outcomes).unwrap();
+        assert_eq!(String::from_utf8(results).unwrap(), "account,result,error\n0x52908400098527886e0f7030069857d2e4169ee7,42,\n0xde709f2102306220921060314715629080e2fb77,,reverted\n");
+    }
+}
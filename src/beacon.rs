@@ -0,0 +1,310 @@
+use ethabi::ethereum_types::H256;
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+
+pub const BEACON_BLOCK_HEADER_SIZE: usize = 112;
+pub const VALIDATOR_SIZE: usize = 121;
+// A phase0 SignedBeaconBlockHeader: the header followed by a BLS signature
+pub const SIGNED_BEACON_BLOCK_HEADER_SIZE: usize = BEACON_BLOCK_HEADER_SIZE + 96;
+// Epoch value of exit and withdrawable epochs that haven't been set
+pub const FAR_FUTURE_EPOCH: u64 = u64::MAX;
+
+// SSZ Merkleization: chunks padded with zero chunks to a power of two, then hashed
+// pairwise up to the root
+fn merkleize(chunks: &[[u8; 32]]) -> H256 {
+    let mut layer = chunks.to_vec();
+    layer.resize(chunks.len().next_power_of_two().max(1), [0u8; 32]);
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| {
+                let mut hasher = Sha256::new();
+                hasher.update(pair[0]);
+                hasher.update(pair[1]);
+                hasher.finalize().into()
+            })
+            .collect();
+    }
+    H256(layer[0])
+}
+
+fn uint_chunk(value: u64) -> [u8; 32] {
+    let mut chunk = [0u8; 32];
+    chunk[..8].copy_from_slice(&value.to_le_bytes());
+    chunk
+}
+
+// Fixed-size byte vectors longer than a chunk are packed into chunks and merkleized
+fn bytes_root(bytes: &[u8]) -> [u8; 32] {
+    let chunks: Vec<[u8; 32]> = bytes
+        .chunks(32)
+        .map(|part| {
+            let mut chunk = [0u8; 32];
+            chunk[..part.len()].copy_from_slice(part);
+            chunk
+        })
+        .collect();
+    merkleize(&chunks).0
+}
+
+// Reads fixed-size SSZ fields in order
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take(&mut self, length: usize) -> &[u8] {
+        let (field, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        field
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take(8).try_into().unwrap())
+    }
+
+    fn root(&mut self) -> H256 {
+        H256::from_slice(self.take(32))
+    }
+}
+
+fn expect_size(name: &str, bytes: &[u8], size: usize) -> Result<()> {
+    if bytes.len() != size {
+        return Err(Error::Decode(format!("{} SSZ is {} bytes, expected {}", name, bytes.len(), size)));
+    }
+    Ok(())
+}
+
+// Beacon API objects carry numbers as decimal strings
+fn json_u64(value: &serde_json::Value, field: &str) -> Result<u64> {
+    value[field].as_str().and_then(|number| number.parse().ok()).ok_or_else(|| Error::Decode(format!("Invalid {} in beacon response", field)))
+}
+
+fn json_bytes(value: &serde_json::Value, field: &str, size: usize) -> Result<Vec<u8>> {
+    let bytes = value[field].as_str().and_then(|bytes| hex::decode(bytes.trim_start_matches("0x")).ok());
+    bytes.filter(|bytes| bytes.len() == size).ok_or_else(|| Error::Decode(format!("Invalid {} in beacon response", field)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BeaconBlockHeader {
+    pub slot: u64,
+    pub proposer_index: u64,
+    pub parent_root: H256,
+    pub state_root: H256,
+    pub body_root: H256,
+}
+
+impl BeaconBlockHeader {
+    pub fn from_ssz(bytes: &[u8]) -> Result<Self> {
+        expect_size("BeaconBlockHeader", bytes, BEACON_BLOCK_HEADER_SIZE)?;
+        let mut reader = Reader { bytes };
+        Ok(BeaconBlockHeader {
+            slot: reader.u64(),
+            proposer_index: reader.u64(),
+            parent_root: reader.root(),
+            state_root: reader.root(),
+            body_root: reader.root(),
+        })
+    }
+
+    // The header of a SignedBeaconBlockHeader, dropping the signature
+    pub fn from_signed_ssz(bytes: &[u8]) -> Result<Self> {
+        expect_size("SignedBeaconBlockHeader", bytes, SIGNED_BEACON_BLOCK_HEADER_SIZE)?;
+        Self::from_ssz(&bytes[..BEACON_BLOCK_HEADER_SIZE])
+    }
+
+    // The `message` object of a /eth/v1/beacon/headers response
+    pub fn from_json(message: &serde_json::Value) -> Result<Self> {
+        Ok(BeaconBlockHeader {
+            slot: json_u64(message, "slot")?,
+            proposer_index: json_u64(message, "proposer_index")?,
+            parent_root: H256::from_slice(&json_bytes(message, "parent_root", 32)?),
+            state_root: H256::from_slice(&json_bytes(message, "state_root", 32)?),
+            body_root: H256::from_slice(&json_bytes(message, "body_root", 32)?),
+        })
+    }
+
+    pub fn to_ssz(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(BEACON_BLOCK_HEADER_SIZE);
+        bytes.extend_from_slice(&self.slot.to_le_bytes());
+        bytes.extend_from_slice(&self.proposer_index.to_le_bytes());
+        bytes.extend_from_slice(self.parent_root.as_bytes());
+        bytes.extend_from_slice(self.state_root.as_bytes());
+        bytes.extend_from_slice(self.body_root.as_bytes());
+        bytes
+    }
+
+    // The block root: the same value as the block's own hash_tree_root
+    pub fn hash_tree_root(&self) -> H256 {
+        merkleize(&[uint_chunk(self.slot), uint_chunk(self.proposer_index), self.parent_root.0, self.state_root.0, self.body_root.0])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validator {
+    pub pubkey: [u8; 48],
+    pub withdrawal_credentials: H256,
+    // In gwei
+    pub effective_balance: u64,
+    pub slashed: bool,
+    pub activation_eligibility_epoch: u64,
+    pub activation_epoch: u64,
+    pub exit_epoch: u64,
+    pub withdrawable_epoch: u64,
+}
+
+impl Validator {
+    pub fn from_ssz(bytes: &[u8]) -> Result<Self> {
+        expect_size("Validator", bytes, VALIDATOR_SIZE)?;
+        let mut reader = Reader { bytes };
+        Ok(Validator {
+            pubkey: reader.take(48).try_into().unwrap(),
+            withdrawal_credentials: reader.root(),
+            effective_balance: reader.u64(),
+            slashed: match reader.take(1)[0] {
+                0 => false,
+                1 => true,
+                _ => return Err(Error::Decode("Invalid SSZ boolean in Validator".to_string())),
+            },
+            activation_eligibility_epoch: reader.u64(),
+            activation_epoch: reader.u64(),
+            exit_epoch: reader.u64(),
+            withdrawable_epoch: reader.u64(),
+        })
+    }
+
+    // An SSZ List[Validator], e.g. the validators field of a state
+    pub fn list_from_ssz(bytes: &[u8]) -> Result<Vec<Self>> {
+        if !bytes.len().is_multiple_of(VALIDATOR_SIZE) {
+            return Err(Error::Decode(format!("Validator list SSZ of {} bytes is not a multiple of {}", bytes.len(), VALIDATOR_SIZE)));
+        }
+        bytes.chunks(VALIDATOR_SIZE).map(Self::from_ssz).collect()
+    }
+
+    // The `validator` object of a /eth/v1/beacon/states/{state}/validators response
+    pub fn from_json(validator: &serde_json::Value) -> Result<Self> {
+        Ok(Validator {
+            pubkey: json_bytes(validator, "pubkey", 48)?.try_into().unwrap(),
+            withdrawal_credentials: H256::from_slice(&json_bytes(validator, "withdrawal_credentials", 32)?),
+            effective_balance: json_u64(validator, "effective_balance")?,
+            slashed: validator["slashed"].as_bool().ok_or_else(|| Error::Decode("Invalid slashed in beacon response".to_string()))?,
+            activation_eligibility_epoch: json_u64(validator, "activation_eligibility_epoch")?,
+            activation_epoch: json_u64(validator, "activation_epoch")?,
+            exit_epoch: json_u64(validator, "exit_epoch")?,
+            withdrawable_epoch: json_u64(validator, "withdrawable_epoch")?,
+        })
+    }
+
+    pub fn to_ssz(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(VALIDATOR_SIZE);
+        bytes.extend_from_slice(&self.pubkey);
+        bytes.extend_from_slice(self.withdrawal_credentials.as_bytes());
+        bytes.extend_from_slice(&self.effective_balance.to_le_bytes());
+        bytes.push(self.slashed as u8);
+        for epoch in [self.activation_eligibility_epoch, self.activation_epoch, self.exit_epoch, self.withdrawable_epoch] {
+            bytes.extend_from_slice(&epoch.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn hash_tree_root(&self) -> H256 {
+        merkleize(&[
+            bytes_root(&self.pubkey),
+            self.withdrawal_credentials.0,
+            uint_chunk(self.effective_balance),
+            uint_chunk(self.slashed as u64),
+            uint_chunk(self.activation_eligibility_epoch),
+            uint_chunk(self.activation_epoch),
+            uint_chunk(self.exit_epoch),
+            uint_chunk(self.withdrawable_epoch),
+        ])
+    }
+}
+
+// Client for the beacon node REST API (consensus layer), separate from the execution
+// JSON-RPC client
+#[derive(Clone)]
+pub struct BeaconClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl BeaconClient {
+    pub fn new(base_url: &str) -> Self {
+        Self::with_client(reqwest::Client::new(), base_url)
+    }
+
+    pub fn with_client(client: reqwest::Client, base_url: &str) -> Self {
+        BeaconClient {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<serde_json::Value> {
+        let response = self.client.get(format!("{}{}", self.base_url, path)).header("accept", "application/json").send().await.map_err(|error| Error::Transport(Box::new(error.without_url())))?;
+        let response = response.error_for_status().map_err(|error| Error::Transport(Box::new(error.without_url())))?;
+        response.json().await.map_err(|error| Error::Decode(format!("Invalid beacon response: {}", error)))
+    }
+
+    // `block_id` is "head", "finalized", a slot or a block root. The header is checked
+    // against the root the node reports for it.
+    pub async fn header(&self, block_id: &str) -> Result<BeaconBlockHeader> {
+        let response = self.get(&format!("/eth/v1/beacon/headers/{}", block_id)).await?;
+        let header = BeaconBlockHeader::from_json(&response["data"]["header"]["message"])?;
+        let root = H256::from_slice(&json_bytes(&response["data"], "root", 32)?);
+        if header.hash_tree_root() != root {
+            return Err(Error::Decode(format!("Beacon header for {} does not hash to its reported root {:?}", block_id, root)));
+        }
+        Ok(header)
+    }
+
+    // `validator_id` is an index or a 0x pubkey
+    pub async fn validator(&self, state_id: &str, validator_id: &str) -> Result<Validator> {
+        let response = self.get(&format!("/eth/v1/beacon/states/{}/validators/{}", state_id, validator_id)).await?;
+        Validator::from_json(&response["data"]["validator"])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SSZ bytes and roots generated offline with the ethereum_ssz and tree_hash crates
+    const HEADER_SSZ: &str = "415489000000000040e2010000000000111111111111111111111111111111111111111111111111111111111111111122222222222222222222222222222222222222222222222222222222222222223333333333333333333333333333333333333333333333333333333333333333";
+    const HEADER_ROOT: &str = "0xbf0ab27e1bf2860ef4099eea792d18a5fb0f2635e85b7a6ba5910fb48fb0929d";
+    const VALIDATOR_SSZ: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f202122232425262728292a2b2c2d2e2f01010101010101010101010101010101010101010101010101010101010101010040597307000000000a000000000000001400000000000000ffffffffffffffffffffffffffffffff";
+    const VALIDATOR_ROOT: &str = "0xde9b8f657bc771ccae0a39b36a8677b6d7470f7bd307b3f4685a4f39a4537b5c";
+
+    #[test]
+    fn decodes_and_roots_headers() {
+        let bytes = hex::decode(HEADER_SSZ).unwrap();
+        let header = BeaconBlockHeader::from_ssz(&bytes).unwrap();
+        assert_eq!((header.slot, header.proposer_index), (9_000_001, 123_456));
+        assert_eq!(header.state_root, H256::repeat_byte(0x22));
+        assert_eq!(header.to_ssz(), bytes);
+        assert_eq!(header.hash_tree_root(), HEADER_ROOT.parse().unwrap());
+
+        let message = serde_json::json!({
+            "slot": "9000001", "proposer_index": "123456",
+            "parent_root": H256::repeat_byte(0x11), "state_root": H256::repeat_byte(0x22), "body_root": H256::repeat_byte(0x33),
+        });
+        assert_eq!(BeaconBlockHeader::from_json(&message).unwrap(), header);
+        assert!(BeaconBlockHeader::from_ssz(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn decodes_and_roots_validators() {
+        let bytes = hex::decode(VALIDATOR_SSZ).unwrap();
+        let validator = Validator::from_ssz(&bytes).unwrap();
+        assert_eq!(validator.effective_balance, 32_000_000_000);
+        assert_eq!((validator.activation_epoch, validator.exit_epoch), (20, FAR_FUTURE_EPOCH));
+        assert_eq!(validator.to_ssz(), bytes);
+        assert_eq!(validator.hash_tree_root(), VALIDATOR_ROOT.parse().unwrap());
+
+        let list = [bytes.clone(), bytes].concat();
+        assert_eq!(Validator::list_from_ssz(&list).unwrap().len(), 2);
+        assert!(Validator::list_from_ssz(&list[1..]).is_err());
+    }
+}
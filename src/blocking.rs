@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use ethabi::ethereum_types::U256;
+use tokio::runtime::Runtime;
+
+use crate::blocks::BlockTag;
+use crate::client;
+use crate::dialect::Dialect;
+use crate::error::Result;
+use crate::gas::FeeHistory;
+use crate::transaction::TransactionRequest;
+
+// Synchronous wrapper around EthClient for scripts and CLI tools. Each call blocks on
+// an internal runtime, so it must not be used from inside another async runtime.
+#[derive(Clone)]
+pub struct EthClient {
+    inner: client::EthClient,
+    runtime: Arc<Runtime>,
+}
+
+impl EthClient {
+    pub fn new(rpc_url: &str) -> Result<Self> {
+        Self::from_async(client::EthClient::new(rpc_url))
+    }
+
+    // Wraps a client built with ClientBuilder (custom transport or layers)
+    pub fn from_async(inner: client::EthClient) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        Ok(EthClient {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    pub fn inner(&self) -> &client::EthClient {
+        &self.inner
+    }
+
+    pub fn send(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value> {
+        self.runtime.block_on(self.inner.send(method, params))
+    }
+
+    pub fn send_raw(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value> {
+        self.runtime.block_on(self.inner.send_raw(method, params))
+    }
+
+    pub fn dialect(&self) -> Result<Dialect> {
+        self.runtime.block_on(self.inner.dialect())
+    }
+
+    pub fn get_block_number(&self) -> Result<u64> {
+        self.runtime.block_on(self.inner.get_block_number())
+    }
+
+    pub fn chain_id(&self) -> Result<u64> {
+        self.runtime.block_on(self.inner.chain_id())
+    }
+
+    pub fn verify_chain_id(&self, expected: u64) -> Result<()> {
+        self.runtime.block_on(self.inner.verify_chain_id(expected))
+    }
+
+    pub fn get_balance(&self, address: &str, block: impl Into<BlockTag>) -> Result<U256> {
+        self.runtime.block_on(self.inner.get_balance(address, block))
+    }
+
+    pub fn gas_price(&self) -> Result<U256> {
+        self.runtime.block_on(self.inner.gas_price())
+    }
+
+    pub fn max_priority_fee(&self) -> Result<U256> {
+        self.runtime.block_on(self.inner.max_priority_fee())
+    }
+
+    pub fn fee_history(&self, block_count: u64, newest_block: impl Into<BlockTag>, reward_percentiles: &[f64]) -> Result<FeeHistory> {
+        self.runtime.block_on(self.inner.fee_history(block_count, newest_block, reward_percentiles))
+    }
+
+    pub fn estimate_gas(&self, transaction: &TransactionRequest, block: impl Into<BlockTag>) -> Result<u64> {
+        self.runtime.block_on(self.inner.estimate_gas(transaction, block))
+    }
+
+    pub fn get_transaction_count(&self, address: &str, block: impl Into<BlockTag>) -> Result<u64> {
+        self.runtime.block_on(self.inner.get_transaction_count(address, block))
+    }
+
+    pub fn call(&self, to: &str, data: &str) -> Result<String> {
+        self.runtime.block_on(self.inner.call(to, data))
+    }
+
+    pub fn call_function(&self, to: &str, method_signature: &str, params: Vec<String>) -> Result<String> {
+        self.runtime.block_on(self.inner.call_function(to, method_signature, params))
+    }
+
+    pub fn call_uint(&self, to: &str, method_signature: &str, params: Vec<String>) -> Result<u64> {
+        self.runtime.block_on(self.inner.call_uint(to, method_signature, params))
+    }
+
+    pub fn call_u256(&self, to: &str, method_signature: &str, params: Vec<String>) -> Result<U256> {
+        self.runtime.block_on(self.inner.call_u256(to, method_signature, params))
+    }
+
+    pub fn call_string(&self, to: &str, method_signature: &str, params: Vec<String>) -> Result<String> {
+        self.runtime.block_on(self.inner.call_string(to, method_signature, params))
+    }
+
+    pub fn call_address(&self, to: &str, method_signature: &str, params: Vec<String>) -> Result<String> {
+        self.runtime.block_on(self.inner.call_address(to, method_signature, params))
+    }
+
+    pub fn call_string_array(&self, to: &str, method_signature: &str, params: Vec<String>) -> Result<Vec<String>> {
+        self.runtime.block_on(self.inner.call_string_array(to, method_signature, params))
+    }
+}
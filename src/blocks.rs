@@ -0,0 +1,209 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use ethabi::ethereum_types::{H256, U256};
+use futures_util::future::try_join_all;
+use serde::{Serialize, Serializer};
+
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::poll::poll_until;
+use crate::utils::{parse_quantity, parse_quantity_u256};
+
+// Block parameter for state reads. Hash uses the EIP-1898 object form, which not every
+// node accepts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BlockTag {
+    #[default]
+    Latest,
+    Earliest,
+    Pending,
+    Safe,
+    Finalized,
+    Number(u64),
+    Hash(H256),
+}
+
+impl BlockTag {
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            BlockTag::Hash(hash) => serde_json::json!({ "blockHash": hash }),
+            _ => serde_json::json!(self.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for BlockTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockTag::Latest => write!(f, "latest"),
+            BlockTag::Earliest => write!(f, "earliest"),
+            BlockTag::Pending => write!(f, "pending"),
+            BlockTag::Safe => write!(f, "safe"),
+            BlockTag::Finalized => write!(f, "finalized"),
+            BlockTag::Number(number) => write!(f, "0x{:x}", number),
+            BlockTag::Hash(hash) => write!(f, "{:?}", hash),
+        }
+    }
+}
+
+// Accepts the tag names, a hex or decimal block number, or a 32-byte block hash
+impl FromStr for BlockTag {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || Error::InvalidInput(format!("Invalid block {}", value));
+        Ok(match value {
+            "latest" => BlockTag::Latest,
+            "earliest" => BlockTag::Earliest,
+            "pending" => BlockTag::Pending,
+            "safe" => BlockTag::Safe,
+            "finalized" => BlockTag::Finalized,
+            _ => match value.strip_prefix("0x") {
+                Some(hash) if hash.len() == 64 => BlockTag::Hash(H256::from_slice(&hex::decode(hash).map_err(|_| invalid())?)),
+                Some(number) => BlockTag::Number(u64::from_str_radix(number, 16).map_err(|_| invalid())?),
+                None => BlockTag::Number(value.parse().map_err(|_| invalid())?),
+            },
+        })
+    }
+}
+
+impl From<u64> for BlockTag {
+    fn from(number: u64) -> Self {
+        BlockTag::Number(number)
+    }
+}
+
+impl From<H256> for BlockTag {
+    fn from(hash: H256) -> Self {
+        BlockTag::Hash(hash)
+    }
+}
+
+impl Serialize for BlockTag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+// Lightweight per-block figures taken from the header (no full transactions)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockStats {
+    pub number: u64,
+    pub timestamp: u64,
+    pub transaction_count: u64,
+    pub gas_used: u64,
+    pub gas_limit: u64,
+    // None before London
+    pub base_fee: Option<U256>,
+}
+
+impl BlockStats {
+    // Percentage of the gas limit used, 0.0 to 100.0
+    pub fn gas_utilization(&self) -> f64 {
+        if self.gas_limit == 0 {
+            return 0.0;
+        }
+        self.gas_used as f64 * 100.0 / self.gas_limit as f64
+    }
+}
+
+// Polls eth_blockNumber until the chain reaches `number` and returns the height seen,
+// which may already be past it
+pub async fn wait_for_block(client: &EthClient, number: u64, interval: Duration, timeout: Duration) -> Result<u64> {
+    let poll = || async {
+        let latest = client.get_block_number().await?;
+        Ok((latest >= number).then_some(latest))
+    };
+    poll_until(poll, interval, Instant::now() + timeout).await.map_err(|error| match error {
+        Error::Timeout(_) => Error::Timeout(format!("Block {} not reached", number)),
+        error => error,
+    })
+}
+
+// First block with a timestamp at or after `timestamp` (unix seconds), by binary search
+// over block timestamps. One past the latest block when the chain hasn't got there yet.
+pub async fn block_at_timestamp(client: &EthClient, timestamp: u64) -> Result<u64> {
+    let latest = client.get_block_number().await?;
+    let (mut low, mut high) = (0, latest + 1);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if block_timestamp(client, middle).await? < timestamp {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(low)
+}
+
+async fn block_timestamp(client: &EthClient, number: u64) -> Result<u64> {
+    let header = client.send("eth_getBlockByNumber", vec![BlockTag::Number(number).to_json(), serde_json::json!(false)]).await?;
+    if header.is_null() {
+        return Err(Error::Decode(format!("Block {} not found", number)));
+    }
+    parse_quantity(&header["timestamp"])
+}
+
+// `block` is a tag such as "latest" or a hex block number
+pub async fn get_block_transaction_count(client: &EthClient, block: &str) -> Result<u64> {
+    let count = client.send("eth_getBlockTransactionCountByNumber", vec![serde_json::json!(block)]).await?;
+    parse_quantity(&count)
+}
+
+pub async fn get_block_stats(client: &EthClient, block: &str) -> Result<BlockStats> {
+    let header = client.send("eth_getBlockByNumber", vec![serde_json::json!(block), serde_json::json!(false)]).await?;
+    if header.is_null() {
+        return Err(Error::Decode(format!("Block {} not found", block)));
+    }
+
+    Ok(BlockStats {
+        number: parse_quantity(&header["number"])?,
+        timestamp: parse_quantity(&header["timestamp"])?,
+        // Only hashes are returned, so counting them is cheap
+        transaction_count: header["transactions"].as_array().map_or(0, |transactions| transactions.len() as u64),
+        gas_used: parse_quantity(&header["gasUsed"])?,
+        gas_limit: parse_quantity(&header["gasLimit"])?,
+        base_fee: match header.get("baseFeePerGas") {
+            Some(base_fee) if !base_fee.is_null() => Some(parse_quantity_u256(base_fee)?),
+            _ => None,
+        },
+    })
+}
+
+// Stats for the last `count` blocks up to the latest one, oldest first
+pub async fn recent_block_stats(client: &EthClient, count: u64) -> Result<Vec<BlockStats>> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let latest = get_block_stats(client, "latest").await?;
+    let first = latest.number.saturating_sub(count - 1);
+
+    let blocks: Vec<String> = (first..latest.number).map(|number| format!("0x{:x}", number)).collect();
+    let mut stats = try_join_all(blocks.iter().map(|block| get_block_stats(client, block))).await?;
+    stats.push(latest);
+    Ok(stats)
+}
+
+// Percentage change in base fee from the oldest to the newest block; None without
+// at least two post-London blocks
+pub fn base_fee_trend(stats: &[BlockStats]) -> Option<f64> {
+    let oldest = stats.iter().find_map(|block| block.base_fee)?;
+    let newest = stats.iter().rev().find_map(|block| block.base_fee)?;
+    if stats.iter().filter(|block| block.base_fee.is_some()).count() < 2 || oldest.is_zero() {
+        return None;
+    }
+    Some((u256_to_f64(newest) - u256_to_f64(oldest)) * 100.0 / u256_to_f64(oldest))
+}
+
+pub fn average_gas_utilization(stats: &[BlockStats]) -> Option<f64> {
+    if stats.is_empty() {
+        return None;
+    }
+    Some(stats.iter().map(BlockStats::gas_utilization).sum::<f64>() / stats.len() as f64)
+}
+
+fn u256_to_f64(value: U256) -> f64 {
+    value.to_string().parse().unwrap_or(f64::MAX)
+}
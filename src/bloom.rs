@@ -0,0 +1,153 @@
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::{Error, Result};
+
+// 2048-bit logs bloom as found in block headers and receipts
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Bloom(pub [u8; 256]);
+
+impl Bloom {
+    pub fn empty() -> Self {
+        Bloom([0u8; 256])
+    }
+
+    pub fn from_hex(hex_str: &str) -> Result<Self> {
+        let bytes = hex::decode(hex_str.trim_start_matches("0x")).map_err(|error| Error::Decode(format!("Invalid bloom: {}", error)))?;
+        let bytes: [u8; 256] = bytes.try_into().map_err(|_| Error::Decode("Bloom must be 256 bytes".to_string()))?;
+        Ok(Bloom(bytes))
+    }
+
+    // Adds raw input (a 20-byte address or 32-byte topic)
+    pub fn accrue(&mut self, input: &[u8]) {
+        for (index, bit) in bloom_bits(input) {
+            self.0[index] |= bit;
+        }
+    }
+
+    pub fn accrue_log(&mut self, address: &[u8], topics: &[&[u8]]) {
+        self.accrue(address);
+        for topic in topics {
+            self.accrue(topic);
+        }
+    }
+
+    // False positives are possible, false negatives are not
+    pub fn might_contain(&self, input: &[u8]) -> bool {
+        bloom_bits(input).all(|(index, bit)| self.0[index] & bit == bit)
+    }
+
+    pub fn might_contain_hex(&self, hex_str: &str) -> bool {
+        match hex::decode(hex_str.trim_start_matches("0x")) {
+            Ok(input) => self.might_contain(&input),
+            Err(_) => false,
+        }
+    }
+
+    // True if any of the inputs might be present, e.g. one of several topic alternatives
+    pub fn might_contain_any(&self, inputs: &[&[u8]]) -> bool {
+        inputs.iter().any(|input| self.might_contain(input))
+    }
+
+    pub fn union(&mut self, other: &Bloom) {
+        for (byte, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *byte |= other;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|byte| *byte == 0)
+    }
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+// Three 11-bit indices from the first six bytes of keccak256(input)
+fn bloom_bits(input: &[u8]) -> impl Iterator<Item = (usize, u8)> {
+    let hash = keccak_hash::keccak(input);
+    (0..3).map(move |i| {
+        let bit = (((hash[2 * i] as usize) << 8) | hash[2 * i + 1] as usize) & 2047;
+        (255 - bit / 8, 1u8 << (bit % 8))
+    })
+}
+
+impl fmt::Debug for Bloom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0))
+    }
+}
+
+impl Serialize for Bloom {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(self.0)))
+    }
+}
+
+impl<'de> Deserialize<'de> for Bloom {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex_str = String::deserialize(deserializer)?;
+        Bloom::from_hex(&hex_str).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USDC: &str = "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const TRANSFER: &str = "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+    const FROM: &str = "00000000000000000000000028c6c06298d514db089934071355e5743bf21d60";
+    const TO: &str = "000000000000000000000000dfd5293d8e347dfe59e90efd55b2956a1343963d";
+    // The logsBloom of a receipt holding only a USDC Transfer with the topics above,
+    // computed offline with the ethbloom crate
+    const TRANSFER_BLOOM: &str = "00000000000000000000000000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000008000008000000000000000000000000000000000000000000000000000000000000000000000000000000000000000002000010000000000000000000000000000000000002000000000000010000000000000000000000000000000080200000000000000000000000000000000000000000020000000000000002000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000000000000000000000000000";
+
+    fn bytes(hex: &str) -> Vec<u8> {
+        hex::decode(hex).unwrap()
+    }
+
+    #[test]
+    fn accrues_a_transfer_log() {
+        let mut bloom = Bloom::empty();
+        bloom.accrue_log(&bytes(USDC), &[&bytes(TRANSFER), &bytes(FROM), &bytes(TO)]);
+        assert_eq!(bloom, Bloom::from_hex(TRANSFER_BLOOM).unwrap());
+    }
+
+    #[test]
+    fn checks_membership() {
+        let bloom = Bloom::from_hex(&format!("0x{}", TRANSFER_BLOOM)).unwrap();
+        for input in [USDC, TRANSFER, FROM, TO] {
+            assert!(bloom.might_contain(&bytes(input)));
+            assert!(bloom.might_contain_hex(&format!("0x{}", input)));
+        }
+        // Approval(address,address,uint256)
+        let approval = bytes("8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925");
+        assert!(!bloom.might_contain(&approval));
+        assert!(bloom.might_contain_any(&[&approval, &bytes(TRANSFER)]));
+        assert!(!bloom.might_contain_hex("not hex"));
+        assert!(!Bloom::empty().might_contain(&bytes(USDC)));
+    }
+
+    #[test]
+    fn unions_and_serializes() {
+        let mut bloom = Bloom::empty();
+        assert!(bloom.is_empty());
+        let mut usdc = Bloom::empty();
+        usdc.accrue(&bytes(USDC));
+        let mut topics = Bloom::empty();
+        topics.accrue_log(&bytes(TRANSFER), &[&bytes(FROM), &bytes(TO)]);
+        bloom.union(&usdc);
+        bloom.union(&topics);
+        assert_eq!(bloom, Bloom::from_hex(TRANSFER_BLOOM).unwrap());
+
+        let json = serde_json::to_string(&bloom).unwrap();
+        assert_eq!(json, format!("\"0x{}\"", TRANSFER_BLOOM));
+        assert_eq!(serde_json::from_str::<Bloom>(&json).unwrap(), bloom);
+        assert!(Bloom::from_hex("0x00").is_err());
+    }
+}
@@ -0,0 +1,605 @@
+use ethabi::ethereum_types::{H160, H256, U256};
+use ethabi::{ParamType, Token};
+
+use crate::abi::{decode_result, encode_call};
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::logs::Log;
+use crate::rlp::{self, Encodable};
+use crate::transaction::{DepositTransaction, TransactionReceipt, TypedTransaction};
+
+// OP-stack L2 predeploy that records withdrawals
+pub const OP_L2_TO_L1_MESSAGE_PASSER: &str = "0x4200000000000000000000000000000000000016";
+// Arbitrum precompile managing retryable tickets
+pub const ARB_RETRYABLE_TX: &str = "0x000000000000000000000000000000000000006e";
+// Arbitrum L1MessageType_submitRetryableTx
+const ARB_RETRYABLE_MESSAGE_KIND: u8 = 9;
+const ARB_RETRYABLE_TX_TYPE: u8 = 0x69;
+
+const TRANSACTION_DEPOSITED: &str = "TransactionDeposited(address,address,uint256,bytes)";
+const MESSAGE_PASSED: &str = "MessagePassed(uint256,address,address,uint256,uint256,bytes,bytes32)";
+const MESSAGE_DELIVERED: &str = "MessageDelivered(uint256,bytes32,address,uint8,address,bytes32,uint256,uint64)";
+const INBOX_MESSAGE_DELIVERED: &str = "InboxMessageDelivered(uint256,bytes)";
+const REDEEM_SCHEDULED: &str = "RedeemScheduled(bytes32,bytes32,uint64,uint64,address,uint256,uint256)";
+
+// L1 contracts of an OP-stack chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpStackContracts {
+    pub portal: &'static str,
+    // Only on chains still using output proposals rather than fault proofs
+    pub output_oracle: Option<&'static str>,
+}
+
+pub const OP_MAINNET: OpStackContracts = OpStackContracts {
+    portal: "0xbEb5Fc579115071764c7423A4f12eDde41f106Ed",
+    output_oracle: None,
+};
+
+pub const BASE: OpStackContracts = OpStackContracts {
+    portal: "0x49048044D57e1C92A77f79988d21Fa8fAF74E97e",
+    output_oracle: None,
+};
+
+fn topic(signature: &str) -> H256 {
+    keccak_hash::keccak(signature.as_bytes())
+}
+
+fn logs_with<'a>(receipt: &'a TransactionReceipt, address: Option<&str>, signature: &str) -> impl Iterator<Item = &'a Log> {
+    let topic0 = topic(signature);
+    let address = address.map(str::to_lowercase);
+    receipt.logs.iter().filter(move |log| log.topics.first() == Some(&topic0) && address.as_ref().is_none_or(|address| log.address.eq_ignore_ascii_case(address)))
+}
+
+fn indexed_address(log: &Log, index: usize) -> Result<H160> {
+    let topic = log.topics.get(index).ok_or_else(|| Error::Decode("Log is missing an indexed topic".to_string()))?;
+    Ok(H160::from_slice(&topic[12..]))
+}
+
+fn word(bytes: &[u8], offset: usize) -> Result<U256> {
+    let word = bytes.get(offset..offset + 32).ok_or_else(|| Error::Decode("Bridge message is too short".to_string()))?;
+    Ok(U256::from_big_endian(word))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepositStatus {
+    // Not derived on L2 yet
+    Pending,
+    Succeeded,
+    // Included on L2 but reverted; minted ETH still arrives
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpDeposit {
+    pub l2_tx_hash: H256,
+    pub from: H160,
+    pub to: Option<H160>,
+    pub mint: U256,
+    pub value: U256,
+    pub status: DepositStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WithdrawalStatus {
+    // Not proven, and no output covering it is known yet
+    Unproven,
+    // An L2 output covers it, so it can be proven on L1
+    ReadyToProve,
+    // Proven at this L1 timestamp; finalizable after the challenge period
+    Proven { timestamp: u64 },
+    Finalized,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpWithdrawal {
+    pub withdrawal_hash: H256,
+    pub nonce: U256,
+    pub sender: H160,
+    pub target: H160,
+    pub value: U256,
+    pub l2_block: u64,
+    pub status: WithdrawalStatus,
+}
+
+// Tracks canonical bridge transfers of an OP-stack chain between its L1 and L2
+pub struct OpBridge {
+    l1: EthClient,
+    l2: EthClient,
+    contracts: OpStackContracts,
+}
+
+impl OpBridge {
+    pub fn new(l1: &EthClient, l2: &EthClient, contracts: OpStackContracts) -> Self {
+        OpBridge {
+            l1: l1.clone(),
+            l2: l2.clone(),
+            contracts,
+        }
+    }
+
+    // Deposits made by an L1 transaction, each with its derived L2 transaction. None
+    // while the L1 transaction is pending.
+    pub async fn deposits(&self, l1_tx_hash: &str) -> Result<Option<Vec<OpDeposit>>> {
+        let Some(receipt) = self.l1.get_transaction_receipt(l1_tx_hash).await? else {
+            return Ok(None);
+        };
+        let mut deposits = Vec::new();
+        for log in logs_with(&receipt, Some(self.contracts.portal), TRANSACTION_DEPOSITED) {
+            let transaction = deposit_transaction(log)?;
+            let l2_tx_hash = TypedTransaction::Deposit(transaction.clone()).hash();
+            let status = match self.l2.get_transaction_receipt(&format!("{:?}", l2_tx_hash)).await? {
+                None => DepositStatus::Pending,
+                Some(receipt) if receipt.is_success() => DepositStatus::Succeeded,
+                Some(_) => DepositStatus::Failed,
+            };
+            deposits.push(OpDeposit {
+                l2_tx_hash,
+                from: transaction.from,
+                to: transaction.to,
+                mint: transaction.mint,
+                value: transaction.value,
+                status,
+            });
+        }
+        Ok(Some(deposits))
+    }
+
+    // Withdrawals started by an L2 transaction. None while it is pending.
+    pub async fn withdrawals(&self, l2_tx_hash: &str) -> Result<Option<Vec<OpWithdrawal>>> {
+        let Some(receipt) = self.l2.get_transaction_receipt(l2_tx_hash).await? else {
+            return Ok(None);
+        };
+        let mut withdrawals = Vec::new();
+        for log in logs_with(&receipt, Some(OP_L2_TO_L1_MESSAGE_PASSER), MESSAGE_PASSED) {
+            let nonce = U256::from_big_endian(log.topics.get(1).ok_or_else(|| Error::Decode("MessagePassed is missing its nonce".to_string()))?.as_bytes());
+            let fields = ethabi::decode(&[ParamType::Uint(256), ParamType::Uint(256), ParamType::Bytes, ParamType::FixedBytes(32)], &log.data_bytes()?).map_err(|error| Error::Abi(error.to_string()))?;
+            let (Token::Uint(value), Token::FixedBytes(withdrawal_hash)) = (&fields[0], &fields[3]) else {
+                return Err(Error::Decode("Invalid MessagePassed data".to_string()));
+            };
+            let withdrawal_hash = H256::from_slice(withdrawal_hash);
+            let l2_block = receipt.block_number.as_u64();
+            withdrawals.push(OpWithdrawal {
+                withdrawal_hash,
+                nonce,
+                sender: indexed_address(log, 2)?,
+                target: indexed_address(log, 3)?,
+                value: *value,
+                l2_block,
+                status: self.withdrawal_status(withdrawal_hash, l2_block).await?,
+            });
+        }
+        Ok(Some(withdrawals))
+    }
+
+    pub async fn withdrawal_status(&self, withdrawal_hash: H256, l2_block: u64) -> Result<WithdrawalStatus> {
+        let hash = Token::FixedBytes(withdrawal_hash.as_bytes().to_vec());
+        let portal = self.contracts.portal;
+        let finalized = self.l1.call(portal, &encode_call("finalizedWithdrawals(bytes32)", std::slice::from_ref(&hash))).await?;
+        if decode_result(&finalized, &[ParamType::Bool])?.pop() == Some(Token::Bool(true)) {
+            return Ok(WithdrawalStatus::Finalized);
+        }
+
+        if let Some(timestamp) = self.proven_at(&hash).await? {
+            return Ok(WithdrawalStatus::Proven { timestamp });
+        }
+        if let Some(oracle) = self.contracts.output_oracle {
+            let latest = self.l1.call(oracle, &encode_call("latestBlockNumber()", &[])).await?;
+            if matches!(decode_result(&latest, &[ParamType::Uint(256)])?.pop(), Some(Token::Uint(latest)) if latest >= U256::from(l2_block)) {
+                return Ok(WithdrawalStatus::ReadyToProve);
+            }
+        }
+        Ok(WithdrawalStatus::Unproven)
+    }
+
+    // Output-oracle portals key proofs by hash alone; fault-proof portals also by the
+    // proof submitter, so the first submitter's proof is used
+    async fn proven_at(&self, hash: &Token) -> Result<Option<u64>> {
+        let portal = self.contracts.portal;
+        let legacy = encode_call("provenWithdrawals(bytes32)", std::slice::from_ref(hash));
+        match self.l1.call(portal, &legacy).await {
+            Ok(result) => {
+                let fields = decode_result(&result, &[ParamType::FixedBytes(32), ParamType::Uint(128), ParamType::Uint(128)])?;
+                return Ok(match fields.get(1) {
+                    Some(Token::Uint(timestamp)) if !timestamp.is_zero() => Some(timestamp.low_u64()),
+                    _ => None,
+                });
+            }
+            Err(Error::Rpc(_)) => {}
+            Err(error) => return Err(error),
+        }
+
+        let submitters = self.l1.call(portal, &encode_call("numProofSubmitters(bytes32)", std::slice::from_ref(hash))).await?;
+        if matches!(decode_result(&submitters, &[ParamType::Uint(256)])?.pop(), Some(Token::Uint(count)) if count.is_zero()) {
+            return Ok(None);
+        }
+        let submitter = self.l1.call(portal, &encode_call("proofSubmitters(bytes32,uint256)", &[hash.clone(), Token::Uint(U256::zero())])).await?;
+        let Some(submitter) = decode_result(&submitter, &[ParamType::Address])?.pop() else {
+            return Ok(None);
+        };
+        let proven = self.l1.call(portal, &encode_call("provenWithdrawals(bytes32,address)", &[hash.clone(), submitter])).await?;
+        match decode_result(&proven, &[ParamType::Address, ParamType::Uint(64)])?.get(1) {
+            Some(Token::Uint(timestamp)) if !timestamp.is_zero() => Ok(Some(timestamp.low_u64())),
+            _ => Ok(None),
+        }
+    }
+}
+
+// Rebuilds the L2 deposit transaction from a TransactionDeposited log. Its source hash
+// ties it to the L1 block and log index, which gives the L2 transaction hash.
+pub fn deposit_transaction(log: &Log) -> Result<DepositTransaction> {
+    let (Some(block_hash), Some(log_index)) = (log.block_hash, log.log_index) else {
+        return Err(Error::InvalidInput("Deposit log is not mined yet".to_string()));
+    };
+    let Some(Token::Bytes(opaque)) = ethabi::decode(&[ParamType::Bytes], &log.data_bytes()?).map_err(|error| Error::Abi(error.to_string()))?.pop() else {
+        return Err(Error::Decode("Invalid TransactionDeposited data".to_string()));
+    };
+    // abi.encodePacked(mint, value, uint64 gasLimit, bool isCreation, data)
+    if opaque.len() < 73 {
+        return Err(Error::Decode("TransactionDeposited data is too short".to_string()));
+    }
+    let is_creation = opaque[72] != 0;
+
+    let mut deposit_id = block_hash.as_bytes().to_vec();
+    deposit_id.extend_from_slice(&<[u8; 32]>::from(U256::from(log_index.as_u64())));
+    let mut source = [0u8; 32].to_vec();
+    source.extend_from_slice(keccak_hash::keccak(deposit_id).as_bytes());
+
+    Ok(DepositTransaction {
+        source_hash: keccak_hash::keccak(source),
+        from: indexed_address(log, 1)?,
+        to: (!is_creation).then(|| indexed_address(log, 2)).transpose()?,
+        mint: word(&opaque, 0)?,
+        value: word(&opaque, 32)?,
+        gas_limit: u64::from_be_bytes(opaque[64..72].try_into().unwrap()),
+        is_system_tx: false,
+        data: opaque[73..].to_vec(),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryableStatus {
+    // The ticket isn't on L2 yet
+    NotCreated,
+    CreationFailed,
+    // Created but not redeemed; anyone can redeem it until the timeout (unix seconds)
+    FundsDeposited { timeout: u64 },
+    Redeemed { retry_tx_hash: H256 },
+    // Dropped unredeemed after its lifetime
+    Expired,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Retryable {
+    pub ticket_id: H256,
+    pub message_number: U256,
+    pub to: Option<H160>,
+    pub l2_call_value: U256,
+    pub status: RetryableStatus,
+}
+
+// Tracks Arbitrum retryable tickets created by L1 transactions to the delayed inbox
+pub struct ArbitrumBridge {
+    l1: EthClient,
+    l2: EthClient,
+}
+
+impl ArbitrumBridge {
+    pub fn new(l1: &EthClient, l2: &EthClient) -> Self {
+        ArbitrumBridge {
+            l1: l1.clone(),
+            l2: l2.clone(),
+        }
+    }
+
+    // Retryables created by the L1 transaction. None while it is pending.
+    pub async fn retryables(&self, l1_tx_hash: &str) -> Result<Option<Vec<Retryable>>> {
+        let Some(receipt) = self.l1.get_transaction_receipt(l1_tx_hash).await? else {
+            return Ok(None);
+        };
+        let chain_id = self.l2.chain_id().await?;
+        let mut retryables = Vec::new();
+        for delivered in logs_with(&receipt, None, MESSAGE_DELIVERED) {
+            let fields = ethabi::decode(&[ParamType::Address, ParamType::Uint(8), ParamType::Address, ParamType::FixedBytes(32), ParamType::Uint(256), ParamType::Uint(64)], &delivered.data_bytes()?).map_err(|error| Error::Abi(error.to_string()))?;
+            let (Token::Uint(kind), Token::Address(sender), Token::Uint(l1_base_fee)) = (&fields[1], &fields[2], &fields[4]) else {
+                return Err(Error::Decode("Invalid MessageDelivered data".to_string()));
+            };
+            if *kind != U256::from(ARB_RETRYABLE_MESSAGE_KIND) {
+                continue;
+            }
+            let message_number = delivered.topics.get(1).ok_or_else(|| Error::Decode("MessageDelivered is missing its index".to_string()))?;
+            let inbox_topic = topic(INBOX_MESSAGE_DELIVERED);
+            let message = receipt.logs.iter().find(|log| log.topics.first() == Some(&inbox_topic) && log.topics.get(1) == Some(message_number));
+            let Some(message) = message else {
+                return Err(Error::Decode("Retryable message data not found in the receipt".to_string()));
+            };
+            let Some(Token::Bytes(data)) = ethabi::decode(&[ParamType::Bytes], &message.data_bytes()?).map_err(|error| Error::Abi(error.to_string()))?.pop() else {
+                return Err(Error::Decode("Invalid InboxMessageDelivered data".to_string()));
+            };
+
+            let ticket = RetryableTicket::decode(chain_id, *message_number, *sender, *l1_base_fee, &data)?;
+            let ticket_id = ticket.id();
+            retryables.push(Retryable {
+                ticket_id,
+                message_number: U256::from_big_endian(message_number.as_bytes()),
+                to: ticket.to,
+                l2_call_value: ticket.l2_call_value,
+                status: self.retryable_status(ticket_id).await?,
+            });
+        }
+        Ok(Some(retryables))
+    }
+
+    pub async fn retryable_status(&self, ticket_id: H256) -> Result<RetryableStatus> {
+        let Some(creation) = self.l2.get_transaction_receipt(&format!("{:?}", ticket_id)).await? else {
+            return Ok(RetryableStatus::NotCreated);
+        };
+        if !creation.is_success() {
+            return Ok(RetryableStatus::CreationFailed);
+        }
+        // The auto-redeem scheduled at creation, if it succeeded
+        if let Some(retry_tx_hash) = self.successful_redeem(logs_with(&creation, Some(ARB_RETRYABLE_TX), REDEEM_SCHEDULED)).await? {
+            return Ok(RetryableStatus::Redeemed { retry_tx_hash });
+        }
+
+        let ticket = Token::FixedBytes(ticket_id.as_bytes().to_vec());
+        match self.l2.call(ARB_RETRYABLE_TX, &encode_call("getTimeout(bytes32)", &[ticket])).await {
+            Ok(result) => match decode_result(&result, &[ParamType::Uint(256)])?.pop() {
+                Some(Token::Uint(timeout)) => return Ok(RetryableStatus::FundsDeposited { timeout: timeout.low_u64() }),
+                _ => return Err(Error::Decode("Invalid getTimeout response".to_string())),
+            },
+            // Gone: redeemed manually later, or expired
+            Err(Error::Rpc(_)) => {}
+            Err(error) => return Err(error),
+        }
+
+        let filter = serde_json::json!({
+            "address": ARB_RETRYABLE_TX,
+            "fromBlock": format!("0x{:x}", creation.block_number.as_u64()),
+            "toBlock": "latest",
+            "topics": [format!("{:?}", topic(REDEEM_SCHEDULED)), format!("{:?}", ticket_id)],
+        });
+        let logs: Vec<Log> = serde_json::from_value(self.l2.send("eth_getLogs", vec![filter]).await?).map_err(|error| Error::Decode(format!("Invalid eth_getLogs response: {}", error)))?;
+        Ok(match self.successful_redeem(logs.iter()).await? {
+            Some(retry_tx_hash) => RetryableStatus::Redeemed { retry_tx_hash },
+            None => RetryableStatus::Expired,
+        })
+    }
+
+    async fn successful_redeem(&self, redeems: impl Iterator<Item = &Log>) -> Result<Option<H256>> {
+        for redeem in redeems {
+            let Some(retry_tx_hash) = redeem.topics.get(2) else { continue };
+            if let Some(receipt) = self.l2.get_transaction_receipt(&format!("{:?}", retry_tx_hash)).await? {
+                if receipt.is_success() {
+                    return Ok(Some(*retry_tx_hash));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+// The fields of a submitRetryable L1 message, which determine the ticket id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryableTicket {
+    pub chain_id: u64,
+    pub message_number: H256,
+    // Already aliased when the L1 sender is a contract
+    pub sender: H160,
+    pub l1_base_fee: U256,
+    pub deposit_value: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: U256,
+    pub to: Option<H160>,
+    pub l2_call_value: U256,
+    pub call_value_refund_address: H160,
+    pub max_submission_fee: U256,
+    pub excess_fee_refund_address: H160,
+    pub data: Vec<u8>,
+}
+
+impl RetryableTicket {
+    // `data` is the packed message from InboxMessageDelivered
+    pub fn decode(chain_id: u64, message_number: H256, sender: H160, l1_base_fee: U256, data: &[u8]) -> Result<Self> {
+        let address = |offset: usize| -> Result<H160> { Ok(H160::from_slice(&<[u8; 32]>::from(word(data, offset)?)[12..])) };
+        let length = word(data, 256)?;
+        let payload = data.get(288..).filter(|payload| U256::from(payload.len()) == length).ok_or_else(|| Error::Decode("Retryable data length mismatch".to_string()))?;
+        let to = address(0)?;
+        Ok(RetryableTicket {
+            chain_id,
+            message_number,
+            sender,
+            l1_base_fee,
+            to: (!to.is_zero()).then_some(to),
+            l2_call_value: word(data, 32)?,
+            deposit_value: word(data, 64)?,
+            max_submission_fee: word(data, 96)?,
+            excess_fee_refund_address: address(128)?,
+            call_value_refund_address: address(160)?,
+            gas_limit: word(data, 192)?,
+            max_fee_per_gas: word(data, 224)?,
+            data: payload.to_vec(),
+        })
+    }
+
+    // keccak256(0x69 || rlp(fields)), the hash of the ticket creation transaction on L2
+    pub fn id(&self) -> H256 {
+        let mut payload = Vec::new();
+        self.chain_id.rlp_append(&mut payload);
+        self.message_number.rlp_append(&mut payload);
+        self.sender.rlp_append(&mut payload);
+        self.l1_base_fee.rlp_append(&mut payload);
+        self.deposit_value.rlp_append(&mut payload);
+        self.max_fee_per_gas.rlp_append(&mut payload);
+        self.gas_limit.rlp_append(&mut payload);
+        self.to.rlp_append(&mut payload);
+        self.l2_call_value.rlp_append(&mut payload);
+        self.call_value_refund_address.rlp_append(&mut payload);
+        self.max_submission_fee.rlp_append(&mut payload);
+        self.excess_fee_refund_address.rlp_append(&mut payload);
+        self.data.rlp_append(&mut payload);
+
+        let mut encoded = vec![ARB_RETRYABLE_TX_TYPE];
+        rlp::append_list_payload(&payload, &mut encoded);
+        keccak_hash::keccak(encoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::testing::{Failure, MockNode};
+
+    // Serves receipts by hash and eth_call answers by (target, calldata); unknown
+    // calls revert and unknown receipts are null
+    #[derive(Default)]
+    struct Node {
+        chain_id: u64,
+        receipts: HashMap<String, Value>,
+        calls: HashMap<(String, String), String>,
+        logs: Vec<Value>,
+    }
+
+    impl Node {
+        fn client(self) -> EthClient {
+            MockNode::new(move |method, params| match method {
+                "eth_chainId" => Ok(json!(format!("0x{:x}", self.chain_id))),
+                "eth_getTransactionReceipt" => Ok(self.receipts.get(&params[0].as_str().unwrap().to_lowercase()).cloned().unwrap_or(Value::Null)),
+                "eth_getLogs" => Ok(json!(self.logs)),
+                "eth_call" => {
+                    let key = (params[0]["to"].as_str().unwrap().to_lowercase(), params[0]["data"].as_str().unwrap().to_string());
+                    self.calls.get(&key).map(|result| json!(result)).ok_or_else(Failure::reverted)
+                }
+                _ => Err(Failure::unexpected(method)),
+            })
+            .client()
+        }
+    }
+
+    fn receipt(hash: H256, block_number: u64, block_hash: H256, status: u64, logs: Vec<Value>) -> Value {
+        json!({
+            "transactionHash": hash, "transactionIndex": "0x0", "blockHash": block_hash, "blockNumber": format!("0x{:x}", block_number),
+            "from": "0x1111111111111111111111111111111111111111", "to": null, "cumulativeGasUsed": "0x5208", "gasUsed": "0x5208",
+            "contractAddress": null, "logs": logs, "status": format!("0x{:x}", status),
+        })
+    }
+
+    fn log(address: &str, topics: Vec<H256>, data: &[u8], block_hash: H256, log_index: u64) -> Value {
+        json!({
+            "address": address, "topics": topics, "data": format!("0x{}", hex::encode(data)),
+            "blockNumber": "0x1", "blockHash": block_hash, "logIndex": format!("0x{:x}", log_index),
+        })
+    }
+
+    fn address_topic(address: &str) -> H256 {
+        H256::from(address.parse::<H160>().unwrap())
+    }
+
+    fn word(value: impl Into<U256>) -> String {
+        format!("0x{}", hex::encode(ethabi::encode(&[Token::Uint(value.into())])))
+    }
+
+    #[tokio::test]
+    async fn tracks_op_deposit_to_its_l2_transaction() {
+        // Expected hashes from op-alloy-consensus for the same deposit
+        let block_hash: H256 = "0xc2ee2c4d4e0e8d9e2a6f4c0e3f1c1f7d9b3a2e1d0c9b8a7f6e5d4c3b2a190807".parse().unwrap();
+        let l2_hash: H256 = "0xd38631e484b4891e592e820de9cef482e1ae9b36268dd9ff94996885483b8855".parse().unwrap();
+        let eth = U256::exp10(18);
+        let mut opaque = <[u8; 32]>::from(eth).to_vec();
+        opaque.extend_from_slice(&<[u8; 32]>::from(eth));
+        opaque.extend_from_slice(&100_000u64.to_be_bytes());
+        opaque.push(0);
+        opaque.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        let topics = vec![topic(TRANSACTION_DEPOSITED), address_topic("1111111111111111111111111111111111111111"), address_topic("2222222222222222222222222222222222222222"), H256::zero()];
+        let deposited = log(OP_MAINNET.portal, topics, &ethabi::encode(&[Token::Bytes(opaque)]), block_hash, 42);
+
+        let l1_hash = H256::repeat_byte(1);
+        let mut l1 = Node::default();
+        l1.receipts.insert(format!("{:?}", l1_hash), receipt(l1_hash, 1, block_hash, 1, vec![deposited]));
+        let l1 = l1.client();
+        let bridge = OpBridge::new(&l1, &Node::default().client(), OP_MAINNET);
+
+        let deposits = bridge.deposits(&format!("{:?}", l1_hash)).await.unwrap().unwrap();
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits[0].l2_tx_hash, l2_hash);
+        assert_eq!(deposits[0].mint, eth);
+        assert_eq!(deposits[0].status, DepositStatus::Pending);
+        assert!(bridge.deposits(&format!("{:?}", H256::repeat_byte(2))).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn reports_op_withdrawal_proven_on_a_fault_proof_portal() {
+        let withdrawal_hash = H256::repeat_byte(0xaa);
+        let topics = vec![topic(MESSAGE_PASSED), H256::from_low_u64_be(7), address_topic("1111111111111111111111111111111111111111"), address_topic("2222222222222222222222222222222222222222")];
+        let data = ethabi::encode(&[Token::Uint(5.into()), Token::Uint(100_000.into()), Token::Bytes(Vec::new()), Token::FixedBytes(withdrawal_hash.as_bytes().to_vec())]);
+        let l2_hash = H256::repeat_byte(3);
+        let mut l2 = Node::default();
+        l2.receipts.insert(format!("{:?}", l2_hash), receipt(l2_hash, 500, H256::zero(), 1, vec![log(OP_L2_TO_L1_MESSAGE_PASSER, topics, &data, H256::zero(), 0)]));
+
+        let hash = Token::FixedBytes(withdrawal_hash.as_bytes().to_vec());
+        let submitter = Token::Address("3333333333333333333333333333333333333333".parse().unwrap());
+        let portal = OP_MAINNET.portal.to_lowercase();
+        let mut l1 = Node::default();
+        l1.calls.insert((portal.clone(), encode_call("finalizedWithdrawals(bytes32)", std::slice::from_ref(&hash))), word(0));
+        l1.calls.insert((portal.clone(), encode_call("numProofSubmitters(bytes32)", std::slice::from_ref(&hash))), word(1));
+        l1.calls.insert((portal.clone(), encode_call("proofSubmitters(bytes32,uint256)", &[hash.clone(), Token::Uint(0.into())])), format!("0x{}", hex::encode(ethabi::encode(std::slice::from_ref(&submitter)))));
+        let proven = ethabi::encode(&[Token::Address(H160::repeat_byte(4)), Token::Uint(1_700_000_000.into())]);
+        l1.calls.insert((portal, encode_call("provenWithdrawals(bytes32,address)", &[hash, submitter])), format!("0x{}", hex::encode(proven)));
+
+        let bridge = OpBridge::new(&l1.client(), &l2.client(), OP_MAINNET);
+        let withdrawals = bridge.withdrawals(&format!("{:?}", l2_hash)).await.unwrap().unwrap();
+        assert_eq!(withdrawals.len(), 1);
+        assert_eq!(withdrawals[0].withdrawal_hash, withdrawal_hash);
+        assert_eq!(withdrawals[0].nonce, U256::from(7));
+        assert_eq!(withdrawals[0].value, U256::from(5));
+        assert_eq!(withdrawals[0].l2_block, 500);
+        assert_eq!(withdrawals[0].status, WithdrawalStatus::Proven { timestamp: 1_700_000_000 });
+    }
+
+    #[tokio::test]
+    async fn follows_arbitrum_retryable_to_its_manual_redeem() {
+        let sender: H160 = "1111111111111111111111111111111111111111".parse().unwrap();
+        let to: H160 = "2222222222222222222222222222222222222222".parse().unwrap();
+        let mut message = Vec::new();
+        for value in [U256::from_big_endian(to.as_bytes()), 1.into(), 1_000.into(), 100.into(), U256::from_big_endian(sender.as_bytes()), U256::from_big_endian(sender.as_bytes()), 50_000.into(), 10.into(), 2.into()] {
+            message.extend_from_slice(&<[u8; 32]>::from(value));
+        }
+        message.extend_from_slice(&[0xca, 0xfe]);
+        let message_number = H256::from_low_u64_be(12);
+        let delivered_data = ethabi::encode(&[Token::Address(H160::repeat_byte(9)), Token::Uint(9.into()), Token::Address(sender), Token::FixedBytes(vec![0; 32]), Token::Uint(30.into()), Token::Uint(0.into())]);
+        let delivered = log("0x8315177ab297ba92a06054ce80a67ed4dbd7ed3a", vec![topic(MESSAGE_DELIVERED), message_number, H256::zero()], &delivered_data, H256::zero(), 0);
+        let inbox = log("0x4dbd4fc535ac27206064b68ffcf827b0a60bab3f", vec![topic(INBOX_MESSAGE_DELIVERED), message_number], &ethabi::encode(&[Token::Bytes(message.clone())]), H256::zero(), 1);
+
+        let ticket = RetryableTicket::decode(42161, message_number, sender, 30.into(), &message).unwrap();
+        assert_eq!(ticket.to, Some(to));
+        assert_eq!(ticket.deposit_value, U256::from(1_000));
+        assert_eq!(ticket.data, vec![0xca, 0xfe]);
+        let ticket_id = ticket.id();
+
+        let l1_hash = H256::repeat_byte(5);
+        let mut l1 = Node::default();
+        l1.receipts.insert(format!("{:?}", l1_hash), receipt(l1_hash, 1, H256::zero(), 1, vec![delivered, inbox]));
+        // Auto-redeem failed, the ticket left the retryable table, and a later manual redeem succeeded
+        let (auto_redeem, manual_redeem) = (H256::repeat_byte(6), H256::repeat_byte(7));
+        let scheduled = |retry: H256| log(ARB_RETRYABLE_TX, vec![topic(REDEEM_SCHEDULED), ticket_id, retry, H256::zero()], &[], H256::zero(), 0);
+        let mut l2 = Node {
+            chain_id: 42161,
+            logs: vec![scheduled(auto_redeem), scheduled(manual_redeem)],
+            ..Node::default()
+        };
+        l2.receipts.insert(format!("{:?}", ticket_id), receipt(ticket_id, 80, H256::zero(), 1, vec![scheduled(auto_redeem)]));
+        l2.receipts.insert(format!("{:?}", auto_redeem), receipt(auto_redeem, 80, H256::zero(), 0, Vec::new()));
+        l2.receipts.insert(format!("{:?}", manual_redeem), receipt(manual_redeem, 90, H256::zero(), 1, Vec::new()));
+
+        let bridge = ArbitrumBridge::new(&l1.client(), &l2.client());
+        let retryables = bridge.retryables(&format!("{:?}", l1_hash)).await.unwrap().unwrap();
+        assert_eq!(retryables.len(), 1);
+        assert_eq!(retryables[0].ticket_id, ticket_id);
+        assert_eq!(retryables[0].message_number, U256::from(12));
+        assert_eq!(retryables[0].status, RetryableStatus::Redeemed { retry_tx_hash: manual_redeem });
+        assert_eq!(bridge.retryable_status(H256::repeat_byte(8)).await.unwrap(), RetryableStatus::NotCreated);
+    }
+}
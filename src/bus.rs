@@ -0,0 +1,263 @@
+use async_trait::async_trait;
+use ethabi::Event;
+
+use crate::enrich::EventRecord;
+use crate::error::{Error, Result};
+use crate::graphql::BlockData;
+use crate::logs::Log;
+use crate::sink::Sink;
+use crate::store::{Store, StoredBlock};
+
+// Avro schema of log records as serialized by Log, e.g. from Crawler
+#[cfg(feature = "avro")]
+pub const LOG_SCHEMA: &str = r#"{
+    "type": "record",
+    "name": "Log",
+    "fields": [
+        {"name": "address", "type": "string"},
+        {"name": "topics", "type": {"type": "array", "items": "string"}},
+        {"name": "data", "type": "string"},
+        {"name": "blockNumber", "type": ["null", "string"]},
+        {"name": "blockHash", "type": ["null", "string"]},
+        {"name": "transactionHash", "type": ["null", "string"]},
+        {"name": "transactionIndex", "type": ["null", "string"]},
+        {"name": "logIndex", "type": ["null", "string"]},
+        {"name": "removed", "type": "boolean"}
+    ]
+}"#;
+
+// Avro schema of the block notifications published by PublishingStore
+#[cfg(feature = "avro")]
+pub const BLOCK_SCHEMA: &str = r#"{
+    "type": "record",
+    "name": "Block",
+    "fields": [
+        {"name": "number", "type": "long"},
+        {"name": "hash", "type": "string"},
+        {"name": "timestamp", "type": ["null", "long"]},
+        {"name": "transactions", "type": ["null", "long"]},
+        {"name": "logs", "type": ["null", "long"]},
+        {"name": "removed", "type": "boolean"}
+    ]
+}"#;
+
+// How a message bus sink serializes each record
+#[derive(Debug, Clone, Default)]
+pub enum Encoding {
+    #[default]
+    Json,
+    // A bare Avro datum per message, without a schema registry header; consumers
+    // read it with the same schema
+    #[cfg(feature = "avro")]
+    Avro(apache_avro::Schema),
+}
+
+impl Encoding {
+    #[cfg(feature = "avro")]
+    pub fn avro(schema: &str) -> Result<Self> {
+        apache_avro::Schema::parse_str(schema).map(Encoding::Avro).map_err(|error| Error::Config(format!("Invalid Avro schema: {}", error)))
+    }
+
+    pub fn encode(&self, record: &serde_json::Value) -> Result<Vec<u8>> {
+        match self {
+            Encoding::Json => serde_json::to_vec(record).map_err(|error| Error::Decode(format!("Failed to serialize record: {}", error))),
+            #[cfg(feature = "avro")]
+            Encoding::Avro(schema) => {
+                let value = apache_avro::types::Value::from(record.clone());
+                let value = value.resolve(schema).map_err(|error| Error::InvalidInput(format!("Record doesn't match the Avro schema: {}", error)))?;
+                apache_avro::to_avro_datum(schema, value).map_err(|error| Error::Decode(format!("Failed to serialize record: {}", error)))
+            }
+        }
+    }
+}
+
+// The notification published for a block; a rewound block is published again with
+// `removed` set, and only its number and hash
+pub fn block_record(block: &BlockData) -> serde_json::Value {
+    serde_json::json!({
+        "number": block.number,
+        "hash": block.hash,
+        "timestamp": block.timestamp,
+        "transactions": block.transactions.len(),
+        "logs": block.logs.len(),
+        "removed": false,
+    })
+}
+
+fn removed_block_record(block: &StoredBlock) -> serde_json::Value {
+    serde_json::json!({
+        "number": block.number,
+        "hash": block.hash,
+        "timestamp": null,
+        "transactions": null,
+        "logs": null,
+        "removed": true,
+    })
+}
+
+// Wraps a Store so an Indexer publishes what it stores: a notification per block to
+// one sink, and optionally the blocks' logs to another. Rewound blocks and their logs
+// are published again with `removed` set. Publishing follows each store call, so a
+// crash in between publishes nothing for that call; the next sync stores and
+// publishes it again.
+pub struct PublishingStore<S> {
+    store: S,
+    blocks: Box<dyn Sink>,
+    logs: Option<Box<dyn Sink>>,
+}
+
+impl<S: Store> PublishingStore<S> {
+    pub fn new(store: S, blocks: Box<dyn Sink>) -> Self {
+        PublishingStore { store, blocks, logs: None }
+    }
+
+    pub fn with_logs(mut self, logs: Box<dyn Sink>) -> Self {
+        self.logs = Some(logs);
+        self
+    }
+
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+
+    async fn publish_logs(&mut self, logs: &[Log]) -> Result<()> {
+        let Some(sink) = self.logs.as_mut() else { return Ok(()) };
+        for log in logs {
+            sink.write(&serde_json::to_value(log).map_err(|error| Error::Decode(error.to_string()))?).await?;
+        }
+        sink.flush().await
+    }
+}
+
+#[async_trait]
+impl<S: Store> Store for PublishingStore<S> {
+    async fn head(&mut self) -> Result<Option<StoredBlock>> {
+        self.store.head().await
+    }
+
+    async fn block(&mut self, number: u64) -> Result<Option<StoredBlock>> {
+        self.store.block(number).await
+    }
+
+    async fn insert(&mut self, blocks: &[BlockData]) -> Result<()> {
+        self.store.insert(blocks).await?;
+        for block in blocks {
+            self.blocks.write(&block_record(block)).await?;
+        }
+        self.blocks.flush().await?;
+        let logs: Vec<Log> = blocks.iter().flat_map(|block| block.logs.iter().cloned()).collect();
+        self.publish_logs(&logs).await
+    }
+
+    async fn rewind(&mut self, number: u64) -> Result<()> {
+        let Some(head) = self.store.head().await? else { return Ok(()) };
+        let mut removed = Vec::new();
+        for number in (number + 1..=head.number).rev() {
+            if let Some(block) = self.store.block(number).await? {
+                removed.push(block);
+            }
+        }
+        let mut logs = if self.logs.is_some() { self.store.logs(number + 1, head.number).await? } else { Vec::new() };
+        self.store.rewind(number).await?;
+
+        for block in &removed {
+            self.blocks.write(&removed_block_record(block)).await?;
+        }
+        self.blocks.flush().await?;
+        logs.reverse();
+        for log in &mut logs {
+            log.removed = true;
+        }
+        self.publish_logs(&logs).await
+    }
+
+    async fn logs(&mut self, from: u64, to: u64) -> Result<Vec<Log>> {
+        self.store.logs(from, to).await
+    }
+}
+
+// Decodes log records, e.g. from Crawler, with `event` and writes them to `sink` as
+// EventRecords; logs of other events are skipped
+pub struct DecodingSink {
+    event: Event,
+    sink: Box<dyn Sink>,
+}
+
+impl DecodingSink {
+    pub fn new(event: Event, sink: Box<dyn Sink>) -> Self {
+        DecodingSink { event, sink }
+    }
+}
+
+#[async_trait]
+impl Sink for DecodingSink {
+    async fn write(&mut self, record: &serde_json::Value) -> Result<()> {
+        let log: Log = serde_json::from_value(record.clone()).map_err(|error| Error::InvalidInput(format!("DecodingSink only takes logs: {}", error)))?;
+        if log.topics.first() != Some(&self.event.signature()) {
+            return Ok(());
+        }
+        let decoded = EventRecord::decode(&self.event, &log)?;
+        self.sink.write(&serde_json::to_value(decoded).map_err(|error| Error::Decode(error.to_string()))?).await
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.sink.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::store::{tests::block, MemoryStore};
+
+    // A Vec sink the test can still read after handing it over
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<serde_json::Value>>>);
+
+    #[async_trait]
+    impl Sink for Shared {
+        async fn write(&mut self, record: &serde_json::Value) -> Result<()> {
+            self.0.lock().unwrap().push(record.clone());
+            Ok(())
+        }
+
+        async fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn publishes_stored_and_rewound_blocks() {
+        let (blocks, logs) = (Shared::default(), Shared::default());
+        let mut store = PublishingStore::new(MemoryStore::default(), Box::new(blocks.clone())).with_logs(Box::new(logs.clone()));
+        store.insert(&[block(1, 0), block(2, 0), block(3, 0)]).await.unwrap();
+        store.rewind(1).await.unwrap();
+        assert_eq!(store.head().await.unwrap().map(|head| head.number), Some(1));
+
+        let blocks = blocks.0.lock().unwrap();
+        let published: Vec<_> = blocks.iter().map(|record| (record["number"].as_u64().unwrap(), record["removed"].as_bool().unwrap())).collect();
+        assert_eq!(published, [(1, false), (2, false), (3, false), (3, true), (2, true)]);
+        assert_eq!(blocks[3]["hash"], serde_json::json!(block(3, 0).hash));
+
+        let logs = logs.0.lock().unwrap();
+        assert_eq!(logs.len(), 5);
+        assert_eq!(logs[3]["blockNumber"], "0x3");
+        assert_eq!(logs[3]["removed"], true);
+    }
+
+    #[cfg(feature = "avro")]
+    #[test]
+    fn encodes_logs_and_blocks_as_avro() {
+        let log = serde_json::to_value(&block(1, 0).logs[0]).unwrap();
+        let encoded = Encoding::avro(LOG_SCHEMA).unwrap().encode(&log).unwrap();
+        let schema = apache_avro::Schema::parse_str(LOG_SCHEMA).unwrap();
+        let decoded = apache_avro::from_avro_datum(&schema, &mut encoded.as_slice(), None).unwrap();
+        assert_eq!(serde_json::Value::try_from(decoded).unwrap()["blockNumber"], "0x1");
+
+        Encoding::avro(BLOCK_SCHEMA).unwrap().encode(&block_record(&block(1, 0))).unwrap();
+        Encoding::avro(BLOCK_SCHEMA).unwrap().encode(&removed_block_record(&StoredBlock { number: 1, hash: block(1, 0).hash })).unwrap();
+        assert!(Encoding::avro(BLOCK_SCHEMA).unwrap().encode(&log).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use crate::artifacts::{Artifact, ImmutableReference};
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+
+// keccak256("eip1967.proxy.implementation") - 1
+pub const EIP1967_IMPLEMENTATION_SLOT: &str = "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a9398c5f0c8b3bd8ca";
+
+#[derive(Debug, PartialEq)]
+pub struct BytecodeDiff {
+    pub matches: bool,
+    pub onchain_length: usize,
+    pub local_length: usize,
+    // Offset of the first differing byte after masking, if any
+    pub first_mismatch: Option<usize>,
+}
+
+// Compares deployed code against the artifact with immutables and the metadata hash masked out
+pub fn compare_bytecode(onchain_code: &str, artifact: &Artifact) -> Result<BytecodeDiff> {
+    let onchain = hex::decode(onchain_code.trim_start_matches("0x")).map_err(|error| Error::Decode(format!("Invalid on-chain code: {}", error)))?;
+    let local = hex::decode(artifact.deployed_bytecode.trim_start_matches("0x")).map_err(|error| Error::Decode(format!("Invalid artifact bytecode: {}", error)))?;
+
+    let onchain = normalize(onchain, &artifact.immutable_references);
+    let local = normalize(local, &artifact.immutable_references);
+
+    let first_mismatch = onchain
+        .iter()
+        .zip(local.iter())
+        .position(|(a, b)| a != b)
+        .or(if onchain.len() != local.len() {
+            Some(onchain.len().min(local.len()))
+        } else {
+            None
+        });
+
+    Ok(BytecodeDiff {
+        matches: first_mismatch.is_none(),
+        onchain_length: onchain.len(),
+        local_length: local.len(),
+        first_mismatch,
+    })
+}
+
+fn normalize(mut code: Vec<u8>, immutables: &[ImmutableReference]) -> Vec<u8> {
+    for reference in immutables {
+        let end = (reference.start + reference.length).min(code.len());
+        if reference.start < end {
+            code[reference.start..end].fill(0);
+        }
+    }
+    let length = code.len() - metadata_length(&code);
+    code.truncate(length);
+    code
+}
+
+// solc appends CBOR metadata followed by its length as a big-endian u16
+fn metadata_length(code: &[u8]) -> usize {
+    if code.len() < 2 {
+        return 0;
+    }
+    let cbor_length = u16::from_be_bytes([code[code.len() - 2], code[code.len() - 1]]) as usize;
+    // CBOR metadata always starts with a map header (0xa1..0xa7)
+    match code.len().checked_sub(cbor_length + 2) {
+        Some(start) if (0xa1..=0xa7).contains(&code[start]) => cbor_length + 2,
+        _ => 0,
+    }
+}
+
+pub async fn get_code(client: &EthClient, address: &str) -> Result<String> {
+    let result = client.send("eth_getCode", vec![serde_json::json!(address), serde_json::json!("latest")]).await?;
+    Ok(result.as_str().unwrap_or("0x").to_string())
+}
+
+// Reads the implementation address out of an EIP-1967 proxy's storage
+pub async fn get_implementation(client: &EthClient, proxy: &str) -> Result<String> {
+    let params = vec![
+        serde_json::json!(proxy),
+        serde_json::json!(EIP1967_IMPLEMENTATION_SLOT),
+        serde_json::json!("latest"),
+    ];
+    let result = client.send("eth_getStorageAt", params).await?;
+    let invalid = || Error::Decode(format!("Invalid eth_getStorageAt response: {}", result));
+    let slot = result.as_str().ok_or_else(invalid)?.trim_start_matches("0x");
+    if slot.len() != 64 {
+        return Err(invalid());
+    }
+    Ok(format!("0x{}", &slot[24..64]))
+}
+
+// Confirms the implementation behind an EIP-1967 proxy matches the artifact
+pub async fn check_implementation(client: &EthClient, proxy: &str, artifact: &Artifact) -> Result<BytecodeDiff> {
+    let implementation = get_implementation(client, proxy).await?;
+    let code = get_code(client, &implementation).await?;
+    compare_bytecode(&code, artifact)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::artifacts::tests::DEPLOYED;
+    use crate::testing::{Failure, MockNode};
+
+    fn artifact() -> Artifact {
+        Artifact {
+            abi: ethabi::Contract::default(),
+            bytecode: "0x".to_string(),
+            deployed_bytecode: format!("0x{}", DEPLOYED),
+            storage_layout: None,
+            immutable_references: vec![ImmutableReference { start: 1, length: 2 }],
+        }
+    }
+
+    #[test]
+    fn ignores_immutables_and_metadata() {
+        // Different immutable value and metadata hash, same code
+        let diff = compare_bytecode("0x601234cc00a16509080706050007", &artifact()).unwrap();
+        assert_eq!(diff, BytecodeDiff { matches: true, onchain_length: 5, local_length: 5, first_mismatch: None });
+
+        let diff = compare_bytecode("0x601234cd00a16501020304050007", &artifact()).unwrap();
+        assert_eq!((diff.matches, diff.first_mismatch), (false, Some(3)));
+        // Code that stops short differs where it ends
+        assert_eq!(compare_bytecode("0x6012", &artifact()).unwrap().first_mismatch, Some(2));
+        assert!(matches!(compare_bytecode("0xzz", &artifact()), Err(Error::Decode(_))));
+    }
+
+    #[tokio::test]
+    async fn checks_the_implementation_behind_a_proxy() {
+        let implementation = "0x2222222222222222222222222222222222222222";
+        let node = MockNode::new(move |method, params| match method {
+            "eth_getStorageAt" if params[1] == EIP1967_IMPLEMENTATION_SLOT => Ok(json!(format!("0x{:0>64}", &implementation[2..]))),
+            "eth_getCode" if params[0] == implementation => Ok(json!(format!("0x{}", DEPLOYED))),
+            _ => Err(Failure::unexpected(method)),
+        });
+        let client = node.client();
+        assert_eq!(get_implementation(&client, "0x1111111111111111111111111111111111111111").await.unwrap(), implementation);
+        assert!(check_implementation(&client, "0x1111111111111111111111111111111111111111", &artifact()).await.unwrap().matches);
+
+        let short = MockNode::with_results([("eth_getStorageAt", json!("0x01"))]).client();
+        assert!(matches!(get_implementation(&short, "0x1111111111111111111111111111111111111111").await, Err(Error::Decode(_))));
+    }
+}
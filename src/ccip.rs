@@ -0,0 +1,274 @@
+use ethabi::{decode, encode, ParamType, Token};
+
+use crate::error::{Error, Result};
+
+// keccak256("OffchainLookup(address,string[],bytes,bytes4,bytes)")[0..4]
+pub const OFFCHAIN_LOOKUP_SELECTOR: &str = "556f1830";
+// EIP-3668 recommends clients cap the number of lookups per call
+pub const MAX_REDIRECTS: usize = 4;
+
+pub struct OffchainLookup {
+    pub sender: String,
+    pub urls: Vec<String>,
+    pub call_data: Vec<u8>,
+    pub callback_function: Vec<u8>,
+    pub extra_data: Vec<u8>,
+}
+
+// Returns the decoded OffchainLookup revert if the eth_call response carries one
+pub fn parse_offchain_lookup(response: &serde_json::Value) -> Option<OffchainLookup> {
+    let revert_data = response["error"]["data"].as_str()?;
+    let revert_data = revert_data.trim_start_matches("0x");
+    let args = revert_data.strip_prefix(OFFCHAIN_LOOKUP_SELECTOR)?;
+    let bytes = hex::decode(args).ok()?;
+
+    let decoded = decode(
+        &[
+            ParamType::Address,
+            ParamType::Array(Box::new(ParamType::String)),
+            ParamType::Bytes,
+            ParamType::FixedBytes(4),
+            ParamType::Bytes,
+        ],
+        &bytes,
+    )
+    .ok()?;
+
+    match decoded.as_slice() {
+        [Token::Address(sender), Token::Array(urls), Token::Bytes(call_data), Token::FixedBytes(callback_function), Token::Bytes(extra_data)] => {
+            Some(OffchainLookup {
+                sender: format!("0x{}", hex::encode(sender.as_bytes())),
+                urls: urls
+                    .iter()
+                    .filter_map(|url| url.clone().into_string())
+                    .collect(),
+                call_data: call_data.clone(),
+                callback_function: callback_function.clone(),
+                extra_data: extra_data.clone(),
+            })
+        }
+        _ => None,
+    }
+}
+
+// Queries the gateway URLs in order and returns the first successful response body.
+// As EIP-3668 asks, a 4xx answer ends the lookup, while network errors and 5xx
+// answers move on to the next URL; the last of those is returned if none succeeds.
+pub async fn fetch_gateway(client: &reqwest::Client, lookup: &OffchainLookup) -> Result<Vec<u8>> {
+    let data = format!("0x{}", hex::encode(&lookup.call_data));
+
+    let mut last_error = None;
+    for template in &lookup.urls {
+        let url = gateway_url(template, &lookup.sender, &data);
+        let request = match &url {
+            GatewayUrl::Get(url) => client.get(url),
+            GatewayUrl::Post(url) => client.post(url).json(&serde_json::json!({
+                "data": data,
+                "sender": lookup.sender,
+            })),
+        };
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(error) => {
+                last_error = Some(Error::Transport(Box::new(error)));
+                continue;
+            }
+        };
+
+        let status = response.status();
+        if status.is_client_error() {
+            return Err(Error::Transport(format!("CCIP-Read gateway {} rejected the request: {}", url.as_str(), status).into()));
+        }
+        if !status.is_success() {
+            last_error = Some(Error::Transport(format!("CCIP-Read gateway {} failed: {}", url.as_str(), status).into()));
+            continue;
+        }
+
+        let body: serde_json::Value = response.json().await.map_err(|error| Error::Decode(format!("Invalid CCIP-Read gateway response: {}", error)))?;
+        let result = body["data"].as_str().ok_or_else(|| Error::Decode("CCIP-Read gateway response is missing data".to_string()))?;
+        return hex::decode(result.trim_start_matches("0x")).map_err(|error| Error::Decode(format!("Invalid CCIP-Read gateway data: {}", error)));
+    }
+
+    Err(last_error.unwrap_or_else(|| Error::Decode("OffchainLookup lists no gateway URLs".to_string())))
+}
+
+// A gateway URL template with its placeholders filled in: templates carrying {data} are
+// fetched with GET, the rest with POST and the data in the body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GatewayUrl {
+    Get(String),
+    Post(String),
+}
+
+impl GatewayUrl {
+    pub fn as_str(&self) -> &str {
+        match self {
+            GatewayUrl::Get(url) | GatewayUrl::Post(url) => url,
+        }
+    }
+}
+
+pub fn gateway_url(template: &str, sender: &str, data: &str) -> GatewayUrl {
+    let url = template.replace("{sender}", sender);
+    if url.contains("{data}") {
+        GatewayUrl::Get(url.replace("{data}", data))
+    } else {
+        GatewayUrl::Post(url)
+    }
+}
+
+// Builds the calldata for callbackFunction(bytes response, bytes extraData)
+pub fn encode_callback(lookup: &OffchainLookup, response: Vec<u8>) -> String {
+    let params = encode(&[
+        Token::Bytes(response),
+        Token::Bytes(lookup.extra_data.clone()),
+    ]);
+    format!("0x{}{}", hex::encode(&lookup.callback_function), hex::encode(params))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Method, Request, Response, Server, StatusCode};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::client::EthClient;
+
+    const SENDER: &str = "0x8464135c8f25da09e49bc8782676a84730c318bc";
+
+    fn revert(urls: &[String]) -> Value {
+        let args = encode(&[
+            Token::Address(SENDER.parse().unwrap()),
+            Token::Array(urls.iter().map(|url| Token::String(url.clone())).collect()),
+            Token::Bytes(vec![0xca, 0xfe]),
+            Token::FixedBytes(vec![0x12, 0x34, 0x56, 0x78]),
+            Token::Bytes(vec![0xee]),
+        ]);
+        json!({"jsonrpc": "2.0", "id": 1, "error": {"code": 3, "message": "execution reverted", "data": format!("0x{}{}", OFFCHAIN_LOOKUP_SELECTOR, hex::encode(args))}})
+    }
+
+    // Gateway answering /ok/... with 0xbeef, /down with a 500 and anything else with a
+    // 400, recording each request's method, path and body
+    async fn gateway() -> (String, Arc<Mutex<Vec<(Method, String, String)>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        listener.set_nonblocking(true).unwrap();
+        let seen = requests.clone();
+        let service = make_service_fn(move |_| {
+            let seen = seen.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let seen = seen.clone();
+                    async move {
+                        let (method, path) = (request.method().clone(), request.uri().path().to_string());
+                        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                        seen.lock().unwrap().push((method, path.clone(), String::from_utf8(body.to_vec()).unwrap()));
+                        let (status, body) = match path.as_str() {
+                            path if path.starts_with("/ok") => (StatusCode::OK, json!({"data": "0xbeef"}).to_string()),
+                            "/down" => (StatusCode::INTERNAL_SERVER_ERROR, String::new()),
+                            _ => (StatusCode::BAD_REQUEST, String::new()),
+                        };
+                        Ok::<_, Infallible>(Response::builder().status(status).body(Body::from(body)).unwrap())
+                    }
+                }))
+            }
+        });
+        tokio::spawn(Server::from_tcp(listener).unwrap().serve(service));
+        (url, requests)
+    }
+
+    fn lookup(urls: Vec<String>) -> OffchainLookup {
+        parse_offchain_lookup(&revert(&urls)).unwrap()
+    }
+
+    #[test]
+    fn parses_offchain_lookup_reverts() {
+        let lookup = lookup(vec!["https://gateway.example/{sender}/{data}.json".to_string()]);
+        assert_eq!(lookup.sender, SENDER);
+        assert_eq!(lookup.urls, ["https://gateway.example/{sender}/{data}.json"]);
+        assert_eq!((lookup.call_data, lookup.callback_function, lookup.extra_data), (vec![0xca, 0xfe], vec![0x12, 0x34, 0x56, 0x78], vec![0xee]));
+
+        // Other reverts, successes and garbage after the selector are not lookups
+        assert!(parse_offchain_lookup(&json!({"error": {"code": 3, "data": "0x08c379a0"}})).is_none());
+        assert!(parse_offchain_lookup(&json!({"result": "0x"})).is_none());
+        assert!(parse_offchain_lookup(&json!({"error": {"data": format!("0x{}00", OFFCHAIN_LOOKUP_SELECTOR)}})).is_none());
+    }
+
+    #[test]
+    fn fills_gateway_url_templates() {
+        assert_eq!(gateway_url("https://g.example/{sender}/{data}.json", SENDER, "0xcafe"), GatewayUrl::Get(format!("https://g.example/{}/0xcafe.json", SENDER)));
+        assert_eq!(gateway_url("https://g.example/{sender}", SENDER, "0xcafe"), GatewayUrl::Post(format!("https://g.example/{}", SENDER)));
+        assert_eq!(gateway_url("https://g.example/lookup", SENDER, "0xcafe").as_str(), "https://g.example/lookup");
+    }
+
+    #[test]
+    fn encodes_the_callback() {
+        let lookup = lookup(Vec::new());
+        let expected = format!("0x12345678{}", hex::encode(encode(&[Token::Bytes(vec![0xbe, 0xef]), Token::Bytes(vec![0xee])])));
+        assert_eq!(encode_callback(&lookup, vec![0xbe, 0xef]), expected);
+    }
+
+    #[tokio::test]
+    async fn moves_past_failing_gateways_until_one_answers() {
+        let (url, requests) = gateway().await;
+        let closed = format!("http://{}", TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap());
+        let client = reqwest::Client::new();
+
+        let urls = vec![format!("{}/x", closed), format!("{}/down", url), format!("{}/ok/{{sender}}/{{data}}", url), format!("{}/ok/post", url)];
+        assert_eq!(fetch_gateway(&client, &lookup(urls)).await.unwrap(), [0xbe, 0xef]);
+        let paths: Vec<String> = requests.lock().unwrap().drain(..).map(|(_, path, _)| path).collect();
+        assert_eq!(paths, ["/down".to_string(), format!("/ok/{}/0xcafe", SENDER)]);
+
+        // POST carries the sender and data in the body
+        fetch_gateway(&client, &lookup(vec![format!("{}/ok/{{sender}}", url)])).await.unwrap();
+        let (method, _, body) = requests.lock().unwrap().pop().unwrap();
+        assert_eq!((method, serde_json::from_str::<Value>(&body).unwrap()), (Method::POST, json!({"data": "0xcafe", "sender": SENDER})));
+
+        // A 4xx ends the lookup; when every gateway fails the last error is returned
+        let rejected = fetch_gateway(&client, &lookup(vec![format!("{}/bad", url), format!("{}/ok/post", url)])).await.unwrap_err();
+        assert!(rejected.to_string().contains("rejected"), "{}", rejected);
+        let failed = fetch_gateway(&client, &lookup(vec![format!("{}/x", closed), format!("{}/down", url)])).await.unwrap_err();
+        assert!(failed.to_string().contains("500"), "{}", failed);
+        assert!(matches!(fetch_gateway(&client, &lookup(Vec::new())).await, Err(Error::Decode(_))));
+    }
+
+    // A contract answering its callback, or reverting with the lookup again when `loops`
+    struct Resolver {
+        revert: Value,
+        loops: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl crate::transport::Transport for Resolver {
+        async fn request(&self, _: &str, params: Vec<Value>) -> std::result::Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+            let data = params[0]["data"].as_str().unwrap_or_default();
+            if data.starts_with("0x12345678") && !self.loops {
+                return Ok(json!({"jsonrpc": "2.0", "id": 1, "result": "0x2a"}));
+            }
+            Ok(self.revert.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_lookups_through_the_callback_and_caps_redirects() {
+        let (url, requests) = gateway().await;
+        let revert = revert(&[format!("{}/ok/{{sender}}/{{data}}", url)]);
+        let client = EthClient::with_transport(Resolver { revert: revert.clone(), loops: false });
+        assert_eq!(client.call(SENDER, "0x01").await.unwrap(), "0x2a");
+
+        let looping = EthClient::with_transport(Resolver { revert, loops: true });
+        let error = looping.call(SENDER, "0x01").await.unwrap_err();
+        assert!(matches!(error, Error::Decode(message) if message.contains("redirects")));
+        assert_eq!(requests.lock().unwrap().len(), 1 + MAX_REDIRECTS + 1);
+
+        // A lookup from another contract than the one called is refused
+        let error = client.call("0x0000000000000000000000000000000000000001", "0x01").await.unwrap_err();
+        assert!(matches!(error, Error::Decode(message) if message.contains("sender")));
+    }
+}
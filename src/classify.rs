@@ -0,0 +1,258 @@
+use ethabi::ethereum_types::U256;
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+use crate::utils::parse_quantity_u256;
+
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+// Calldata of the common routers and token standards, used when the logs alone don't
+// say what the sender meant, e.g. for reverted transactions
+const SWAP_FUNCTIONS: [&str; 12] = [
+    "swapExactTokensForTokens(uint256,uint256,address[],address,uint256)",
+    "swapTokensForExactTokens(uint256,uint256,address[],address,uint256)",
+    "swapExactETHForTokens(uint256,address[],address,uint256)",
+    "swapTokensForExactETH(uint256,uint256,address[],address,uint256)",
+    "swapExactTokensForETH(uint256,uint256,address[],address,uint256)",
+    "swapETHForExactTokens(uint256,address[],address,uint256)",
+    "exactInputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160))",
+    "exactInput((bytes,address,uint256,uint256,uint256))",
+    "exactOutputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160))",
+    "exactOutput((bytes,address,uint256,uint256,uint256))",
+    "execute(bytes,bytes[],uint256)",
+    "execute(bytes,bytes[])",
+];
+const APPROVAL_FUNCTIONS: [&str; 2] = ["approve(address,uint256)", "setApprovalForAll(address,bool)"];
+const TRANSFER_FUNCTIONS: [&str; 4] = [
+    "transfer(address,uint256)",
+    "transferFrom(address,address,uint256)",
+    "safeTransferFrom(address,address,uint256)",
+    "safeTransferFrom(address,address,uint256,bytes)",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    ContractDeploy,
+    Swap,
+    Mint,
+    Approval,
+    TokenTransfer,
+    NativeTransfer,
+    ContractCall,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenMovement {
+    pub token: String,
+    pub from: String,
+    pub to: String,
+    // Always 1 for ERC-721 transfers, with the id in token_id
+    pub amount: U256,
+    pub token_id: Option<U256>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenApproval {
+    pub token: String,
+    pub owner: String,
+    pub spender: String,
+    // None for ApprovalForAll
+    pub amount: Option<U256>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Classification {
+    pub category: Category,
+    pub sender: String,
+    pub value: U256,
+    // First four bytes of the calldata, e.g. "0xa9059cbb"; None for plain transfers
+    pub selector: Option<String>,
+    pub contract_address: Option<String>,
+    pub movements: Vec<TokenMovement>,
+    pub approvals: Vec<TokenApproval>,
+}
+
+// Classifies an eth_getTransactionByHash result together with its receipt. The logs
+// decide where they can; otherwise the calldata's function selector does.
+pub fn classify(transaction: &Value, receipt: &Value) -> Result<Classification> {
+    let sender = transaction["from"].as_str().unwrap_or_default().to_lowercase();
+    let value = parse_quantity_u256(&transaction["value"]).map_err(|_| Error::Decode(format!("Invalid transaction value: {}", transaction["value"])))?;
+    let input = transaction["input"].as_str().unwrap_or("0x").trim_start_matches("0x");
+    let selector = input.get(..8).map(|selector| format!("0x{}", selector.to_lowercase()));
+    let calls = |functions: &[&str]| selector.as_deref().is_some_and(|selector| functions.iter().any(|function| function_selector(function) == selector));
+    let logs = receipt["logs"].as_array().map(Vec::as_slice).unwrap_or_default();
+
+    let transfer_topic = event_topic("Transfer(address,address,uint256)");
+    let approval_topic = event_topic("Approval(address,address,uint256)");
+    let approval_for_all_topic = event_topic("ApprovalForAll(address,address,bool)");
+    let swap_topics = [
+        event_topic("Swap(address,uint256,uint256,uint256,uint256,address)"),
+        event_topic("Swap(address,address,int256,int256,uint160,uint128,int24)"),
+    ];
+
+    let mut movements = Vec::new();
+    let mut approvals = Vec::new();
+    let mut has_swap_event = false;
+
+    for log in logs {
+        let token = log["address"].as_str().unwrap_or_default().to_lowercase();
+        let topics: Vec<&str> = log["topics"]
+            .as_array()
+            .map(|topics| topics.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let data = log["data"].as_str().unwrap_or("0x");
+
+        match topics.first() {
+            Some(topic) if *topic == transfer_topic && topics.len() >= 3 => {
+                let (amount, token_id) = match topics.get(3) {
+                    Some(id) => (U256::one(), Some(parse_word(id)?)),
+                    None => (parse_word(data)?, None),
+                };
+                movements.push(TokenMovement {
+                    token,
+                    from: topic_address(topics[1]),
+                    to: topic_address(topics[2]),
+                    amount,
+                    token_id,
+                });
+            }
+            Some(topic) if *topic == approval_topic && topics.len() >= 3 => {
+                approvals.push(TokenApproval {
+                    token,
+                    owner: topic_address(topics[1]),
+                    spender: topic_address(topics[2]),
+                    amount: Some(match topics.get(3) {
+                        Some(id) => parse_word(id)?,
+                        None => parse_word(data)?,
+                    }),
+                });
+            }
+            Some(topic) if *topic == approval_for_all_topic && topics.len() >= 3 => {
+                approvals.push(TokenApproval {
+                    token,
+                    owner: topic_address(topics[1]),
+                    spender: topic_address(topics[2]),
+                    amount: None,
+                });
+            }
+            Some(topic) if swap_topics.iter().any(|swap| swap == topic) => has_swap_event = true,
+            _ => {}
+        }
+    }
+
+    // A sender who both pays and receives different tokens is swapping, whatever the venue
+    let sent_tokens: Vec<&str> = movements.iter().filter(|m| m.from == sender).map(|m| m.token.as_str()).collect();
+    let swapped = movements
+        .iter()
+        .any(|m| m.to == sender && sent_tokens.iter().any(|token| *token != m.token))
+        || (!value.is_zero() && movements.iter().any(|m| m.to == sender && m.from != ZERO_ADDRESS));
+
+    let category = if transaction["to"].is_null() {
+        Category::ContractDeploy
+    } else if has_swap_event || swapped || (movements.is_empty() && calls(&SWAP_FUNCTIONS)) {
+        Category::Swap
+    } else if movements.iter().any(|m| m.from == ZERO_ADDRESS) {
+        Category::Mint
+    } else if !approvals.is_empty() || (movements.is_empty() && calls(&APPROVAL_FUNCTIONS)) {
+        Category::Approval
+    } else if !movements.is_empty() || calls(&TRANSFER_FUNCTIONS) {
+        Category::TokenTransfer
+    } else if input.is_empty() && !value.is_zero() {
+        Category::NativeTransfer
+    } else {
+        Category::ContractCall
+    };
+
+    Ok(Classification {
+        category,
+        sender,
+        value,
+        selector,
+        contract_address: receipt["contractAddress"].as_str().map(str::to_lowercase),
+        movements,
+        approvals,
+    })
+}
+
+fn event_topic(signature: &str) -> String {
+    format!("0x{}", hex::encode(keccak_hash::keccak(signature.as_bytes())))
+}
+
+fn function_selector(signature: &str) -> String {
+    format!("0x{}", hex::encode(&keccak_hash::keccak(signature.as_bytes())[..4]))
+}
+
+fn topic_address(topic: &str) -> String {
+    let topic = topic.trim_start_matches("0x");
+    format!("0x{}", &topic[topic.len().saturating_sub(40)..]).to_lowercase()
+}
+
+// A 32-byte log word: a topic or the data of a single-value event
+fn parse_word(hex_str: &str) -> Result<U256> {
+    let digits = hex_str.trim_start_matches("0x");
+    U256::from_str_radix(digits.get(..64).unwrap_or(digits), 16).map_err(|_| Error::Decode(format!("Invalid log word {}", hex_str)))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const SENDER: &str = "0x1111111111111111111111111111111111111111";
+    const TOKEN_A: &str = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const TOKEN_B: &str = "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+    const POOL: &str = "0xcccccccccccccccccccccccccccccccccccccccc";
+
+    fn topic(address: &str) -> String {
+        format!("0x{:0>64}", address.trim_start_matches("0x"))
+    }
+
+    fn transfer(token: &str, from: &str, to: &str, amount: u64) -> Value {
+        json!({"address": token, "topics": [event_topic("Transfer(address,address,uint256)"), topic(from), topic(to)], "data": format!("0x{:064x}", amount)})
+    }
+
+    fn transaction(to: Value, value: &str, input: &str) -> Value {
+        json!({"from": SENDER, "to": to, "value": value, "input": input})
+    }
+
+    #[test]
+    fn classifies_by_logs_first() {
+        let call = transaction(json!(POOL), "0x0", "0x12345678");
+        let receipt = json!({"logs": [transfer(TOKEN_A, SENDER, POOL, 5)]});
+        let transfer_only = classify(&call, &receipt).unwrap();
+        assert_eq!(transfer_only.category, Category::TokenTransfer);
+        assert_eq!(transfer_only.movements, [TokenMovement { token: TOKEN_A.to_string(), from: SENDER.to_string(), to: POOL.to_string(), amount: 5.into(), token_id: None }]);
+        assert_eq!(transfer_only.selector.as_deref(), Some("0x12345678"));
+
+        // Paying one token and receiving another is a swap, without any Swap event
+        let receipt = json!({"logs": [transfer(TOKEN_A, SENDER, POOL, 5), transfer(TOKEN_B, POOL, SENDER, 7)]});
+        assert_eq!(classify(&call, &receipt).unwrap().category, Category::Swap);
+
+        let receipt = json!({"logs": [transfer(TOKEN_A, ZERO_ADDRESS, SENDER, 1)]});
+        assert_eq!(classify(&call, &receipt).unwrap().category, Category::Mint);
+
+        // ERC-721 carries the token id as a fourth topic
+        let mut nft = transfer(TOKEN_A, SENDER, POOL, 0);
+        nft["topics"].as_array_mut().unwrap().push(json!(format!("0x{:064x}", 42)));
+        let movement = &classify(&call, &json!({"logs": [nft]})).unwrap().movements[0];
+        assert_eq!((movement.amount, movement.token_id), (U256::one(), Some(42.into())));
+    }
+
+    #[test]
+    fn falls_back_to_the_calldata_and_value() {
+        // A reverted approve leaves no logs
+        let approve = format!("{}{}{:064x}", function_selector("approve(address,uint256)"), topic(POOL).trim_start_matches("0x"), 1);
+        let classified = classify(&transaction(json!(TOKEN_A), "0x0", &approve), &json!({"logs": []})).unwrap();
+        assert_eq!((classified.category, classified.approvals.len()), (Category::Approval, 0));
+
+        let native = classify(&transaction(json!(POOL), "0xde0b6b3a7640000", "0x"), &json!({"logs": []})).unwrap();
+        assert_eq!((native.category, native.value, native.selector), (Category::NativeTransfer, U256::exp10(18), None));
+
+        let deploy = classify(&transaction(Value::Null, "0x0", "0x6080"), &json!({"logs": [], "contractAddress": "0xCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCC"})).unwrap();
+        assert_eq!((deploy.category, deploy.contract_address.as_deref()), (Category::ContractDeploy, Some(POOL)));
+
+        assert_eq!(classify(&transaction(json!(POOL), "0x0", "0x12345678"), &json!({})).unwrap().category, Category::ContractCall);
+        assert!(matches!(classify(&transaction(json!(POOL), "0xzz", "0x"), &json!({})), Err(Error::Decode(_))));
+    }
+}
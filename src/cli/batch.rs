@@ -0,0 +1,107 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use evm_json_rpc::abi::signature_params;
+use evm_json_rpc::batch::{self, Outcome, Table};
+use evm_json_rpc::format::{self, TokenAmount};
+use evm_json_rpc::progress::Progress;
+use evm_json_rpc::{Error, Result};
+
+use super::progress::ProgressBar;
+use super::Context;
+
+#[derive(clap::Args)]
+pub struct SendArgs {
+    #[arg(help = "CSV (or .tsv) with to, amount and optional token columns")]
+    file: PathBuf,
+    #[arg(long, help = "File holding the hex private key, instead of ETH_PRIVATE_KEY")]
+    key_file: Option<PathBuf>,
+    #[arg(long, help = "Where to write per-row results (input columns plus result and error)")]
+    results: Option<PathBuf>,
+    #[arg(long, default_value_t = 300, help = "Seconds to wait for receipts, 0 to skip waiting")]
+    wait: u64,
+}
+
+#[derive(clap::Args)]
+pub struct CallArgs {
+    #[arg(help = "Contract address or address book name")]
+    to: String,
+    #[arg(help = "Function signature, e.g. balanceOf(address)")]
+    signature: String,
+    #[arg(help = "CSV (or .tsv) with one row of arguments per call, after a header row")]
+    file: PathBuf,
+    #[arg(long, help = "Return types to decode, e.g. uint256 or (uint256,bool); raw hex otherwise")]
+    returns: Option<String>,
+    #[arg(long, help = "Where to write per-row results (input columns plus result and error)")]
+    results: Option<PathBuf>,
+}
+
+pub async fn send(context: &Context, args: SendArgs) -> Result<()> {
+    let client = context.connect().await?;
+    let delimiter = batch::delimiter_for(&args.file);
+    let table = Table::read(File::open(&args.file)?, delimiter)?;
+    let transfers = batch::read_transfers(&client, &table, &context.config()?.address_book).await?;
+    let wallet = context.wallet(&client, args.key_file.as_deref())?;
+    println!("Sending {} transfers from {}", transfers.len(), format::address(&wallet.address()));
+
+    let bar = ProgressBar::new("Sending");
+    let mut outcomes = batch::send_transfers(&wallet, &transfers, |outcome, progress| {
+        let transfer = &transfers[progress.done as usize - 1];
+        let amount = match &transfer.token {
+            Some(token) => format!("{} of {}", transfer.amount, format::address(token)),
+            None => TokenAmount::ether(transfer.amount).to_string(),
+        };
+        report(&bar, progress, &format!("{} to {}", amount, format::address(&transfer.to)), outcome);
+    })
+    .await;
+    bar.finish();
+    if args.wait > 0 {
+        println!("Waiting for receipts");
+        let heads = context.profile()?.connect_ws().await?.map(Arc::new);
+        batch::confirm(&client, &mut outcomes, heads, Duration::from_secs(args.wait)).await;
+    }
+    finish(&table, &outcomes, args.results, delimiter)
+}
+
+pub async fn call(context: &Context, args: CallArgs) -> Result<()> {
+    let client = context.connect().await?;
+    let delimiter = batch::delimiter_for(&args.file);
+    let table = Table::read(File::open(&args.file)?, delimiter)?;
+    let calls = batch::read_calls(&table, &args.signature)?;
+    let returns = match &args.returns {
+        Some(returns) if returns.starts_with('(') => signature_params(returns)?,
+        Some(returns) => signature_params(&format!("({})", returns))?,
+        None => Vec::new(),
+    };
+
+    let bar = ProgressBar::new("Calling");
+    let to = context.config()?.resolve_address(&args.to);
+    let outcomes = batch::execute_calls(&client, to, &calls, &returns, |outcome, progress| report(&bar, progress, &outcome.row.fields.join(" "), outcome)).await?;
+    bar.finish();
+    finish(&table, &outcomes, args.results, delimiter)
+}
+
+fn report(bar: &ProgressBar, progress: &Progress, what: &str, outcome: &Outcome) {
+    let step = format!("[{}/{}]", progress.done, progress.total.unwrap_or_default());
+    match &outcome.result {
+        Ok(result) => bar.println(&format!("{} {}: {}", step, what, result)),
+        Err(error) => bar.eprintln(&format!("{} {} failed: {}", step, what, error)),
+    }
+    bar.update(progress);
+}
+
+// Writes the results file and fails the command if any row failed
+fn finish(table: &Table, outcomes: &[Outcome], results: Option<PathBuf>, delimiter: u8) -> Result<()> {
+    if let Some(path) = results {
+        batch::write_results(File::create(&path)?, delimiter, &table.headers, outcomes)?;
+        println!("Results written to {}", path.display());
+    }
+    let failed = outcomes.iter().filter(|outcome| outcome.result.is_err()).count();
+    println!("{} succeeded, {} failed", outcomes.len() - failed, failed);
+    if failed > 0 {
+        return Err(Error::InvalidInput(format!("{} of {} rows failed", failed, outcomes.len())));
+    }
+    Ok(())
+}
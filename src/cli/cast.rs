@@ -0,0 +1,216 @@
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use ethabi::ethereum_types::U256;
+use evm_json_rpc::abi::{decode_result, encode_call_args, signature_params, token_to_json};
+use evm_json_rpc::blocks::BlockTag;
+use evm_json_rpc::confirm::KNOWN_FUNCTIONS;
+use evm_json_rpc::interaction::InteractionProfile;
+use evm_json_rpc::transaction::TransactionRequest;
+use evm_json_rpc::utils::parse_units;
+use evm_json_rpc::{format, Error, Result};
+
+use super::{Context, KEY_ENV};
+
+// The subset of Foundry's cast that scripts lean on most, taking the same positional
+// arguments and flags so `cast call ...` keeps working as `evm-json-rpc cast call ...`,
+// or unchanged through a `cast` symlink to this binary
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    command: CastCommand,
+}
+
+#[derive(Subcommand)]
+enum CastCommand {
+    #[command(about = "Call a function without sending a transaction, e.g. call <TO> \"balanceOf(address)(uint256)\" <OWNER>")]
+    Call(CallArgs),
+    #[command(about = "Sign and send a transaction calling a function, or sending ETH when the signature is omitted")]
+    Send(SendArgs),
+    #[command(name = "4byte", about = "Function signatures matching a 4-byte selector")]
+    FourByte(FourByteArgs),
+    #[command(about = "Read a raw storage slot")]
+    Storage(StorageArgs),
+}
+
+#[derive(clap::Args)]
+struct CallArgs {
+    #[arg(help = "Contract address or address book name")]
+    to: String,
+    #[arg(help = "Function signature, with the return types in a second pair of parentheses to decode the result")]
+    sig: String,
+    #[arg(help = "Function arguments, in order")]
+    args: Vec<String>,
+    #[arg(long, short = 'b', default_value = "latest", help = "Block number, hash or tag to call at")]
+    block: BlockTag,
+    #[arg(long, help = "Address or address book name to call from")]
+    from: Option<String>,
+}
+
+#[derive(clap::Args)]
+struct SendArgs {
+    #[arg(help = "Recipient address or address book name")]
+    to: String,
+    #[arg(help = "Function signature; a plain transfer when omitted")]
+    sig: Option<String>,
+    #[arg(help = "Function arguments, in order")]
+    args: Vec<String>,
+    #[arg(long, help = "Amount to send: wei, or with an ether or gwei suffix such as 0.1ether")]
+    value: Option<String>,
+    #[arg(long, help = "Gas limit, instead of the node's estimate")]
+    gas_limit: Option<u64>,
+    #[arg(long, help = "File holding the hex private key, instead of ETH_PRIVATE_KEY")]
+    key_file: Option<PathBuf>,
+    // Accepted only to explain why it is refused
+    #[arg(long, hide = true)]
+    private_key: Option<String>,
+}
+
+#[derive(clap::Args)]
+struct FourByteArgs {
+    #[arg(help = "0x-prefixed selector, or calldata starting with one")]
+    selector: String,
+}
+
+#[derive(clap::Args)]
+struct StorageArgs {
+    #[arg(help = "Contract address or address book name")]
+    address: String,
+    #[arg(help = "Slot number, decimal or 0x hex")]
+    slot: String,
+    #[arg(long, short = 'b', default_value = "latest", help = "Block number, hash or tag to read at")]
+    block: BlockTag,
+}
+
+pub async fn run(context: &Context, args: Args) -> Result<()> {
+    match args.command {
+        CastCommand::Call(args) => call(context, args).await,
+        CastCommand::Send(args) => send(context, args).await,
+        CastCommand::FourByte(args) => four_byte(context, args),
+        CastCommand::Storage(args) => storage(context, args).await,
+    }
+}
+
+async fn call(context: &Context, args: CallArgs) -> Result<()> {
+    let (signature, returns) = split_returns(&args.sig)?;
+    let data = encode(signature, &args.args)?;
+    let config = context.config()?;
+    let mut request = serde_json::json!({ "to": config.resolve_address(&args.to), "data": data });
+    if let Some(from) = &args.from {
+        request["from"] = serde_json::json!(config.resolve_address(from));
+    }
+    let client = context.connect().await?;
+    let result = client.send("eth_call", vec![request, args.block.to_json()]).await?;
+    let result = result.as_str().ok_or_else(|| Error::Decode(format!("Unexpected eth_call result {}", result)))?;
+    // Like cast, the raw return data unless the signature names the return types
+    let Some(returns) = returns else {
+        println!("{}", result);
+        return Ok(());
+    };
+    for token in decode_result(result, &signature_params(returns)?)? {
+        match token_to_json(&token) {
+            serde_json::Value::String(text) => println!("{}", text),
+            value => println!("{}", value),
+        }
+    }
+    Ok(())
+}
+
+async fn send(context: &Context, args: SendArgs) -> Result<()> {
+    if args.private_key.is_some() {
+        return Err(Error::InvalidInput(format!("--private-key is not accepted, as it leaves the key in shell history; set {} or pass --key-file", KEY_ENV)));
+    }
+    let mut request = TransactionRequest {
+        to: Some(context.config()?.resolve_address(&args.to).to_string()),
+        gas: args.gas_limit.map(Into::into),
+        ..Default::default()
+    };
+    if let Some(sig) = &args.sig {
+        let (signature, _) = split_returns(sig)?;
+        request.data = Some(encode(signature, &args.args)?);
+    } else if !args.args.is_empty() {
+        return Err(Error::InvalidInput("Arguments given without a function signature".to_string()));
+    }
+    if let Some(value) = &args.value {
+        request.value = Some(parse_value(value)?);
+    }
+    let client = context.connect().await?;
+    let wallet = context.wallet(&client, args.key_file.as_deref())?;
+    let hash = wallet.send(&request).await?;
+    println!("Sent {}", format::hash(&format!("{:?}", hash)));
+    Ok(())
+}
+
+// Looked up offline, against the ABIs of the config's interactions and the functions
+// the confirmation prompt knows, rather than a public signature database
+fn four_byte(context: &Context, args: FourByteArgs) -> Result<()> {
+    let selector = args.selector.trim_start_matches("0x").to_lowercase();
+    let selector = selector.get(..8).filter(|selector| selector.chars().all(|c| c.is_ascii_hexdigit())).ok_or_else(|| Error::InvalidInput(format!("Invalid selector {}", args.selector)))?;
+    let config = context.config()?;
+    let mut signatures: Vec<String> = KNOWN_FUNCTIONS.iter().map(|signature| signature.to_string()).collect();
+    for interaction in config.interactions.values() {
+        // One unreadable ABI should not hide matches in the others
+        if let Ok(profile) = InteractionProfile::load(interaction, &config.address_book) {
+            signatures.extend(profile.abi.functions().map(|function| function.signature()));
+        }
+    }
+    signatures.sort();
+    signatures.dedup();
+    let matches: Vec<&String> = signatures.iter().filter(|signature| hex::encode(&keccak_hash::keccak(signature.as_bytes())[..4]) == selector).collect();
+    if matches.is_empty() {
+        return Err(Error::InvalidInput(format!("No known function has selector 0x{}", selector)));
+    }
+    for signature in matches {
+        println!("{}", signature);
+    }
+    Ok(())
+}
+
+async fn storage(context: &Context, args: StorageArgs) -> Result<()> {
+    let slot = match args.slot.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(&args.slot).ok(),
+    };
+    let slot = slot.ok_or_else(|| Error::InvalidInput(format!("Invalid slot {}", args.slot)))?;
+    let client = context.connect().await?;
+    let params = vec![serde_json::json!(context.config()?.resolve_address(&args.address)), serde_json::json!(format!("0x{:x}", slot)), args.block.to_json()];
+    let value = client.send("eth_getStorageAt", params).await?;
+    println!("{}", value.as_str().unwrap_or_default());
+    Ok(())
+}
+
+// cast's "balanceOf(address)(uint256)" as the call signature and its return types
+fn split_returns(sig: &str) -> Result<(&str, Option<&str>)> {
+    let invalid = || Error::InvalidInput(format!("Invalid function signature {}", sig));
+    let open = sig.find('(').ok_or_else(invalid)?;
+    let mut depth = 0;
+    for (index, c) in sig.char_indices().skip(open) {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            let (call, returns) = sig.split_at(index + 1);
+            return Ok((call, Some(returns).filter(|returns| !returns.is_empty())));
+        }
+    }
+    Err(invalid())
+}
+
+fn encode(signature: &str, args: &[String]) -> Result<String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    encode_call_args(signature, &args)
+}
+
+// cast's amount syntax: wei, or a number with an ether or gwei suffix
+fn parse_value(value: &str) -> Result<U256> {
+    let value = value.trim();
+    if let Some(ether) = value.strip_suffix("ether") {
+        return parse_units(ether.trim(), 18);
+    }
+    if let Some(gwei) = value.strip_suffix("gwei") {
+        return parse_units(gwei.trim(), 9);
+    }
+    parse_units(value.strip_suffix("wei").unwrap_or(value).trim(), 0)
+}
@@ -0,0 +1,21 @@
+use clap::CommandFactory;
+use clap_complete::Shell;
+use evm_json_rpc::Result;
+
+use super::{write_stdout, Cli};
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(help = "Shell to complete for")]
+    shell: Shell,
+}
+
+// Prints the completion script on stdout, e.g. for bash
+//   evm-json-rpc completions bash > ~/.local/share/bash-completion/completions/evm-json-rpc
+pub fn run(args: Args) -> Result<()> {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(args.shell, &mut command, name, &mut script);
+    write_stdout(String::from_utf8_lossy(&script).trim_end())
+}
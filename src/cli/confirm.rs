@@ -0,0 +1,31 @@
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use evm_json_rpc::confirm::{ConfirmFn, TransactionSummary};
+
+// Shows the decoded transaction on stderr and reads y/N from stdin; `a` approves this
+// one and every later one. End of input declines, so piped runs need --yes.
+pub fn prompt() -> ConfirmFn {
+    let all = AtomicBool::new(false);
+    Arc::new(move |summary: &TransactionSummary| {
+        if all.load(Ordering::Relaxed) {
+            return true;
+        }
+        eprintln!("\nAbout to send:\n{}", summary);
+        eprint!("Send this transaction? [y/N/a(ll)] ");
+        let _ = std::io::stderr().flush();
+        let mut answer = String::new();
+        if std::io::stdin().lock().read_line(&mut answer).is_err() {
+            return false;
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => true,
+            "a" | "all" => {
+                all.store(true, Ordering::Relaxed);
+                true
+            }
+            _ => false,
+        }
+    })
+}
@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+
+#[cfg(any(feature = "kafka", feature = "nats"))]
+use evm_json_rpc::bus::Encoding;
+use evm_json_rpc::crawler::{Crawler, DEFAULT_CHUNK_SIZE};
+use evm_json_rpc::logs::Filter;
+use evm_json_rpc::sink::{NdjsonSink, Sink};
+use evm_json_rpc::transport::redact_endpoint;
+use evm_json_rpc::{BlockTag, Error, Result};
+
+use super::progress::ProgressBar;
+use super::Context;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(help = "NDJSON file to append the logs to, or a postgres://, kafka://BROKERS/TOPIC or nats://SERVER/SUBJECT URL with the feature of that name")]
+    output: PathBuf,
+    #[arg(long = "address", help = "Emitting contract or address book name; repeat for several")]
+    addresses: Vec<String>,
+    #[arg(long, help = "Event signature, e.g. Transfer(address,address,uint256)")]
+    event: Option<String>,
+    #[arg(long, default_value = "earliest", help = "First block (number or tag)")]
+    from_block: BlockTag,
+    #[arg(long, default_value = "latest", help = "Last block (number or tag)")]
+    to_block: BlockTag,
+    #[arg(long, default_value_t = DEFAULT_CHUNK_SIZE, help = "Blocks per eth_getLogs request")]
+    chunk_size: u64,
+    #[arg(long, help = "Check each block's logs bloom before fetching its logs")]
+    bloom: bool,
+    #[arg(long, value_name = "SCHEMA", num_args = 0..=1, help = "Publish to Kafka or NATS as Avro, with the built-in log schema or this schema file (avro feature)")]
+    avro: Option<Option<PathBuf>>,
+}
+
+pub async fn run(context: &Context, args: Args) -> Result<()> {
+    let client = context.connect().await?;
+    let mut filter = Filter::new().with_from_block(args.from_block).with_to_block(args.to_block);
+    for address in &args.addresses {
+        filter = filter.with_address(context.config()?.resolve_address(address));
+    }
+    if let Some(event) = &args.event {
+        filter = filter.with_event(event);
+    }
+
+    let bar = ProgressBar::new("Crawling");
+    let crawler = Crawler::new(&client, filter).with_chunk_size(args.chunk_size).with_bloom_screening(args.bloom).with_progress(bar.callback());
+    let mut sink = open_sink(&args.output, args.avro.as_ref()).await?;
+    let stats = crawler.run(sink.as_mut()).await;
+    bar.finish();
+    let stats = stats?;
+    println!(
+        "{} logs from blocks {}-{} written to {} ({} requests, {} blocks screened out)",
+        stats.logs,
+        stats.from_block,
+        stats.to_block,
+        destination(&args.output),
+        stats.requests,
+        stats.screened_out
+    );
+    Ok(())
+}
+
+async fn open_sink(output: &Path, avro: Option<&Option<PathBuf>>) -> Result<Box<dyn Sink>> {
+    let url = output.to_string_lossy();
+    if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        #[cfg(feature = "postgres")]
+        return Ok(Box::new(evm_json_rpc::postgres::PostgresStore::connect(&url).await?));
+        #[cfg(not(feature = "postgres"))]
+        return Err(Error::Unsupported("Crawling into Postgres needs the postgres feature".to_string()));
+    }
+    if let Some(brokers) = url.strip_prefix("kafka://") {
+        let (brokers, topic) = split_destination(brokers)?;
+        // Keyed by emitter, so each contract's logs stay in order
+        #[cfg(feature = "kafka")]
+        return Ok(Box::new(evm_json_rpc::kafka::KafkaSink::new(brokers, topic, encoding(avro)?)?.with_key_field("address")));
+        #[cfg(not(feature = "kafka"))]
+        return Err(Error::Unsupported(format!("Publishing to Kafka topic {} on {} needs the kafka feature", topic, brokers)));
+    }
+    if url.starts_with("nats://") {
+        let (server, subject) = split_destination(&url)?;
+        #[cfg(feature = "nats")]
+        return Ok(Box::new(evm_json_rpc::nats::NatsSink::connect(server, subject, encoding(avro)?).await?));
+        #[cfg(not(feature = "nats"))]
+        return Err(Error::Unsupported(format!("Publishing to NATS subject {} on {} needs the nats feature", subject, redact_endpoint(server))));
+    }
+    if avro.is_some() {
+        return Err(Error::InvalidInput("--avro only applies to Kafka and NATS outputs".to_string()));
+    }
+    Ok(Box::new(NdjsonSink::create(output)?))
+}
+
+// Splits kafka://BROKERS/TOPIC or nats://SERVER/SUBJECT at the last slash
+fn split_destination(url: &str) -> Result<(&str, &str)> {
+    match url.rsplit_once('/') {
+        Some((server, name)) if !name.is_empty() && !server.ends_with('/') => Ok((server, name)),
+        _ => Err(Error::InvalidInput(format!("{} names no topic or subject", redact_endpoint(url)))),
+    }
+}
+
+#[cfg(any(feature = "kafka", feature = "nats"))]
+fn encoding(avro: Option<&Option<PathBuf>>) -> Result<Encoding> {
+    match avro {
+        None => Ok(Encoding::Json),
+        #[cfg(feature = "avro")]
+        Some(schema) => {
+            let schema = match schema {
+                Some(path) => std::fs::read_to_string(path)?,
+                None => evm_json_rpc::bus::LOG_SCHEMA.to_string(),
+            };
+            Encoding::avro(&schema)
+        }
+        #[cfg(not(feature = "avro"))]
+        Some(_) => Err(Error::Unsupported("Avro encoding needs the avro feature".to_string())),
+    }
+}
+
+// The output as printed; database URLs may hold a password
+fn destination(output: &Path) -> String {
+    let url = output.to_string_lossy();
+    if url.contains("://") {
+        redact_endpoint(&url)
+    } else {
+        output.display().to_string()
+    }
+}
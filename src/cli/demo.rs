@@ -0,0 +1,111 @@
+use evm_json_rpc::format;
+use evm_json_rpc::utils::format_units;
+use evm_json_rpc::Result;
+
+use super::Context;
+
+// The original walkthrough: reads an ERC-20, an NFT and a custom contract on Sepolia
+pub async fn run(context: &Context) -> Result<()> {
+    // The demo contracts live on Sepolia, used unless a profile or ETH_RPC_URL says otherwise
+    const DEFAULT_RPC_URL: &str = "https://sepolia.drpc.org";
+    const SEPOLIA_CHAIN_ID: u64 = 11155111;
+    const CONTRACT_ADDRESS: &str = "0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238";
+    let mut profile = context.profile()?.clone();
+    // Only the default endpoint is known to be Sepolia; any other is checked against the
+    // profile's chain_id if it sets one, and otherwise reports its own via eth_chainId
+    if profile.url.is_none() {
+        profile.url = Some(DEFAULT_RPC_URL.to_string());
+        profile.chain_id.get_or_insert(SEPOLIA_CHAIN_ID);
+    }
+    let client = profile.connect().await?;
+    let chain_id = client.chain_id().await?;
+    println!("Chain ID: {}", chain_id);
+
+    println!("\n-------FT ERC20 CONTRACT-------\n");
+    // Get token name
+    let name = client.call_string(CONTRACT_ADDRESS, "name()", vec![]).await?;
+    println!("Name: {}", name);
+
+    // Get token symbol
+    let symbol = client.call_string(CONTRACT_ADDRESS, "symbol()", vec![]).await?;
+    println!("Symbol: {}", symbol);
+
+    // Get decimals
+    let decimals = client.call_uint(CONTRACT_ADDRESS, "decimals()", vec![]).await?;
+    println!("Decimals: {}", decimals);
+
+    // Get total supply
+    let total_supply = client.call_u256(CONTRACT_ADDRESS, "totalSupply()", vec![]).await?;
+    println!("Total Supply: {} {}", format_units(total_supply, decimals as u32), symbol);
+
+    // Get balance of the contract
+    let balance = client.call_u256(
+        CONTRACT_ADDRESS,
+        "balanceOf(address)",
+        vec![format!("{:0>64}", CONTRACT_ADDRESS.trim_start_matches("0x"))]
+    ).await?;
+    println!("Balance: {} {}", format_units(balance, decimals as u32), symbol);
+
+    println!("\n-------NFT CONTRACT-------\n");
+    const NFT_ADDRESS: &str = "0x1238536071E1c677A632429e3655c799b22cDA52";
+
+    // Get NFT name
+    let nft_name = client.call_string(NFT_ADDRESS, "name()", vec![]).await?;
+    println!("NFT Name: {}", nft_name);
+
+    // Get NFT symbol
+    let nft_symbol = client.call_string(NFT_ADDRESS, "symbol()", vec![]).await?;
+    println!("NFT Symbol: {}", nft_symbol);
+
+    // Get total supply of NFTs
+    let nft_supply = client.call_u256(NFT_ADDRESS, "totalSupply()", vec![]).await?;
+    println!("Total NFTs: {}", nft_supply);
+
+    // Get owner of token ID 1
+    let token_id = format!("{:0>64}", "1"); // Pad token ID 1 to 64 characters
+    let owner = client.call_address(NFT_ADDRESS, "ownerOf(uint256)", vec![token_id.clone()]).await?;
+    println!("Owner of Token #1: {}", format::address(&owner));
+
+    // Get balance of NFTs for the contract address
+    let nft_balance = client.call_u256(
+        NFT_ADDRESS,
+        "balanceOf(address)",
+        vec![format!("{:0>64}", NFT_ADDRESS.trim_start_matches("0x"))]
+    ).await?;
+    println!("NFT Balance: {}", nft_balance);
+
+    // Get token URI
+    let _token_uri = client.call_string(NFT_ADDRESS, "tokenURI(uint256)", vec![token_id]).await?;
+    //println!("Token #1 URI: {}", token_uri);
+
+    // Subjects with students Sepolia -> Map<String, String[]>
+    println!("\n-------SUBJECT CONTRACT-------\n");
+    const SUBJECT_CONTRACT: &str = "0x5a9491e24f9de0dc6a82e280da939bf36269c48e";
+
+    let subject = "Mathematics";
+    let function_signature = "getStudentCount(string)";
+
+    let student_count = client.call_uint(
+        SUBJECT_CONTRACT,
+        function_signature,
+        vec![subject.to_string()],
+    ).await?;
+    println!("Number of students in {}: {}", subject, student_count);
+
+    // Get students by subject
+    let offset = 0;
+    let limit = 10;
+    let students = client.call_string_array(
+        SUBJECT_CONTRACT,
+        "getStudentsBySubject(string,uint256,uint256)",
+        vec![
+            subject.to_string(),
+            offset.to_string(),
+            limit.to_string(),
+        ]
+    ).await;
+
+    println!("Students response: {:?}", students);
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use std::process::ExitCode;
+
+use evm_json_rpc::Error;
+
+// Process exit codes, so scripts can branch on why a command failed. clap exits with
+// BAD_ARGS itself for malformed command lines.
+pub const FAILURE: u8 = 1;
+pub const BAD_ARGS: u8 = 2;
+pub const REVERT: u8 = 3;
+pub const PROVIDER: u8 = 4;
+pub const TIMEOUT: u8 = 5;
+pub const DECLINED: u8 = 6;
+
+// Shown at the end of --help
+pub const HELP: &str = "Exit codes:
+  0  success
+  1  any other failure (I/O, decoding, signing)
+  2  bad arguments, config or ABI input
+  3  the call or transaction reverted
+  4  the RPC endpoint failed or returned an error
+  5  a request or wait timed out
+  6  the transaction was declined at the confirmation prompt";
+
+pub fn code(error: &Error) -> u8 {
+    // Checked before the variants, as reverts and timeouts arrive as Rpc and Transport
+    if error.is_revert() {
+        return REVERT;
+    }
+    if error.is_timeout() {
+        return TIMEOUT;
+    }
+    match error {
+        Error::InvalidInput(_) | Error::Abi(_) | Error::Config(_) => BAD_ARGS,
+        Error::Transport(_) | Error::Rpc(_) | Error::ChainMismatch { .. } => PROVIDER,
+        Error::Declined(_) => DECLINED,
+        _ => FAILURE,
+    }
+}
+
+// Prints the error and picks the exit code for it
+pub fn report(result: Result<(), Error>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::from(code(&error))
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use std::path::PathBuf;
+
+use evm_json_rpc::vectors::{generate, VectorInputs};
+use evm_json_rpc::{Error, Result};
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(help = "JSON file of selectors, calls, transactions and typedData; a built-in set when omitted")]
+    input: Option<PathBuf>,
+}
+
+// Prints the vectors as JSON on stdout; no RPC connection needed
+pub fn run(args: Args) -> Result<()> {
+    let inputs = match &args.input {
+        Some(path) => VectorInputs::from_json(&std::fs::read_to_string(path)?)?,
+        None => VectorInputs::default_set(),
+    };
+    let vectors = generate(&inputs)?;
+    println!("{}", serde_json::to_string_pretty(&vectors).map_err(|error| Error::Decode(error.to_string()))?);
+    Ok(())
+}
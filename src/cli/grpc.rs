@@ -0,0 +1,31 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use evm_json_rpc::grpc::EthService;
+use evm_json_rpc::{Result, Wallet};
+
+use super::{signing_key, Context};
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long, default_value = "127.0.0.1:50051", help = "Address to serve gRPC on")]
+    listen: SocketAddr,
+    #[arg(long, help = "Sign and send unsigned transactions with ETH_PRIVATE_KEY or --key-file, without confirmation")]
+    sign: bool,
+    #[arg(long, help = "File holding the hex private key, instead of ETH_PRIVATE_KEY")]
+    key_file: Option<PathBuf>,
+}
+
+// Anyone who can reach the port can send from the signing key, so --sign is only for
+// servers behind their own access control
+pub async fn run(context: &Context, args: Args) -> Result<()> {
+    let client = context.connect().await?;
+    let mut service = EthService::new(&client);
+    if args.sign {
+        let wallet = Wallet::new(&client, signing_key(args.key_file.as_deref())?);
+        eprintln!("Signing transactions from {}", wallet.address());
+        service = service.with_wallet(wallet);
+    }
+    eprintln!("Serving gRPC on {}", args.listen);
+    service.serve(args.listen).await
+}
@@ -0,0 +1,30 @@
+use evm_json_rpc::format;
+use evm_json_rpc::labels::LabelResolver;
+use evm_json_rpc::Result;
+
+use super::Context;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(required = true, help = "Addresses or address book names")]
+    addresses: Vec<String>,
+    #[arg(long, help = "Skip ENS reverse lookups")]
+    no_ens: bool,
+}
+
+pub async fn run(context: &Context, args: Args) -> Result<()> {
+    let config = context.config()?;
+    let client = context.connect().await?;
+    let mut resolver = LabelResolver::new(&client, client.chain_id().await?).with_address_book(&config.address_book);
+    if args.no_ens {
+        resolver = resolver.without_ens();
+    }
+    let addresses: Vec<&str> = args.addresses.iter().map(|address| config.resolve_address(address)).collect();
+    for (address, label) in addresses.iter().zip(resolver.labels(&addresses).await?) {
+        match label {
+            Some(label) => println!("{}  {} ({})", format::address(address), label.name, label.source),
+            None => println!("{}  -", format::address(address)),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use std::io::Write;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use clap::{Parser, Subcommand};
+use evm_json_rpc::config::{Config, Profile};
+use evm_json_rpc::{Error, EthClient, Result, SecretKey, Wallet};
+use zeroize::Zeroizing;
+
+mod batch;
+mod cast;
+mod completions;
+mod confirm;
+mod crawl;
+mod demo;
+pub mod exit;
+mod gen_vectors;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod label;
+mod offline;
+mod portfolio;
+mod progress;
+mod proxy;
+mod repair_nonces;
+mod run_profile;
+mod schema;
+mod stuck;
+#[cfg(feature = "tui")]
+mod tui;
+
+#[derive(Parser)]
+#[command(name = "evm-json-rpc", version, about = "Ethereum JSON-RPC client", after_help = exit::HELP)]
+pub struct Cli {
+    #[arg(long, global = true, help = "Config profile, instead of ETH_RPC_PROFILE or default_profile")]
+    pub profile: Option<String>,
+    #[arg(long, global = true, help = "RPC URL, overriding the profile's")]
+    pub rpc_url: Option<String>,
+    #[arg(long, short = 'y', global = true, help = "Send transactions without asking for confirmation")]
+    pub yes: bool,
+    #[arg(long, help = "Print every command and flag as JSON and exit")]
+    pub schema: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(name = "batch-call", about = "Call a contract once per CSV row through Multicall3")]
+    BatchCall(batch::CallArgs),
+    #[command(name = "batch-send", about = "Send ETH and ERC-20 transfers listed in a CSV, validating every row first")]
+    BatchSend(batch::SendArgs),
+    #[command(about = "Foundry cast compatible call, send, 4byte and storage, for migrating scripts")]
+    Cast(cast::Args),
+    #[command(about = "Print a bash, zsh, fish, elvish or PowerShell completion script")]
+    Completions(completions::Args),
+    #[command(about = "Export matching logs over a block range to NDJSON")]
+    Crawl(crawl::Args),
+    #[command(about = "Run the Sepolia walkthrough (the default)")]
+    Demo,
+    #[command(name = "gen-vectors", about = "Print canonical encodings (transactions, typed data, selectors) for cross-implementation tests")]
+    GenVectors(gen_vectors::Args),
+    #[cfg(feature = "grpc")]
+    #[command(about = "Serve token metadata, balances, event streams and transaction sending over gRPC")]
+    Grpc(grpc::Args),
+    #[command(about = "Label addresses from ENS, the address book and well-known contracts")]
+    Label(label::Args),
+    #[command(about = "Prepare a transaction online, sign it on an airgapped machine and broadcast it")]
+    Offline(offline::Args),
+    #[command(about = "Native and ERC-20 balances of an address across every configured chain")]
+    Portfolio(portfolio::Args),
+    #[command(about = "Forward JSON-RPC from stdin or a local HTTP port to the profile's endpoints, with caching, rate limiting and retries")]
+    Proxy(proxy::Args),
+    #[command(name = "repair-nonces", about = "Fill nonce gaps that hold back queued transactions with empty self-transfers")]
+    RepairNonces(repair_nonces::Args),
+    #[command(name = "run-profile", about = "Run a named read or write preset from the config's [interactions]")]
+    RunProfile(run_profile::Args),
+    #[command(about = "List an account's unmined transactions, optionally cancelling them")]
+    Stuck(stuck::Args),
+    #[cfg(feature = "tui")]
+    #[command(about = "Live dashboard of gas prices, recent blocks and watched balances")]
+    Tui(tui::Args),
+}
+
+// Command-line arguments, with `cast` inserted as the subcommand when the binary is run
+// through a symlink named cast, so existing cast scripts work unchanged
+pub fn args() -> Vec<String> {
+    let mut args: Vec<String> = std::env::args().collect();
+    let invoked_as_cast = args.first().and_then(|program| Path::new(program).file_stem()).is_some_and(|stem| stem == "cast");
+    if invoked_as_cast {
+        args.insert(1, "cast".to_string());
+    }
+    args
+}
+
+pub const KEY_ENV: &str = "ETH_PRIVATE_KEY";
+
+// The config and selected profile, shared by every command. Both are loaded on first
+// use, so commands that need neither (completions, gen-vectors, offline sign) still run
+// with a broken config file or environment.
+pub struct Context {
+    profile_name: Option<String>,
+    rpc_url: Option<String>,
+    pub yes: bool,
+    config: OnceLock<Config>,
+    profile: OnceLock<Profile>,
+}
+
+impl Context {
+    pub fn new(cli: &Cli) -> Self {
+        Context {
+            profile_name: cli.profile.clone(),
+            rpc_url: cli.rpc_url.clone(),
+            yes: cli.yes,
+            config: OnceLock::new(),
+            profile: OnceLock::new(),
+        }
+    }
+
+    pub fn config(&self) -> Result<&Config> {
+        if let Some(config) = self.config.get() {
+            return Ok(config);
+        }
+        let config = Config::load()?;
+        Ok(self.config.get_or_init(|| config))
+    }
+
+    pub fn profile(&self) -> Result<&Profile> {
+        if let Some(profile) = self.profile.get() {
+            return Ok(profile);
+        }
+        let mut profile = self.config()?.profile(self.profile_name.as_deref())?;
+        if let Some(url) = &self.rpc_url {
+            profile.url = Some(url.clone());
+        }
+        Ok(self.profile.get_or_init(|| profile))
+    }
+
+    pub async fn connect(&self) -> Result<EthClient> {
+        self.profile()?.connect().await
+    }
+
+    // A wallet that asks on the terminal before every transaction, unless --yes
+    pub fn wallet(&self, client: &EthClient, key_file: Option<&Path>) -> Result<Wallet> {
+        let wallet = Wallet::new(client, signing_key(key_file)?);
+        Ok(if self.yes { wallet } else { wallet.with_confirmation(confirm::prompt()) })
+    }
+}
+
+// The signing key from a key file, or ETH_PRIVATE_KEY; never taken on the command line
+// where it would end up in shell history
+pub fn signing_key(key_file: Option<&Path>) -> Result<SecretKey> {
+    // Zeroized on drop, like the key itself
+    let key = Zeroizing::new(match key_file {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => std::env::var(KEY_ENV).map_err(|_| Error::Config(format!("No signing key; set {} or pass --key-file", KEY_ENV)))?,
+    });
+    SecretKey::from_hex(key.trim())
+}
+
+// Writes a line to stdout, quietly stopping when the reader has gone away, as with
+// `--schema | head`, where println! would panic
+pub fn write_stdout(text: &str) -> Result<()> {
+    match writeln!(std::io::stdout().lock(), "{}", text) {
+        Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+pub async fn run(cli: Cli) -> Result<()> {
+    if cli.schema {
+        return schema::print();
+    }
+    let context = Context::new(&cli);
+    match cli.command.unwrap_or(Command::Demo) {
+        Command::BatchCall(args) => batch::call(&context, args).await,
+        Command::BatchSend(args) => batch::send(&context, args).await,
+        Command::Cast(args) => cast::run(&context, args).await,
+        Command::Completions(args) => completions::run(args),
+        Command::Crawl(args) => crawl::run(&context, args).await,
+        Command::Demo => demo::run(&context).await,
+        Command::GenVectors(args) => gen_vectors::run(args),
+        #[cfg(feature = "grpc")]
+        Command::Grpc(args) => grpc::run(&context, args).await,
+        Command::Label(args) => label::run(&context, args).await,
+        Command::Offline(args) => offline::run(&context, args).await,
+        Command::Portfolio(args) => portfolio::run(&context, args).await,
+        Command::Proxy(args) => proxy::run(&context, args).await,
+        Command::RepairNonces(args) => repair_nonces::run(&context, args).await,
+        Command::RunProfile(args) => run_profile::run(&context, args).await,
+        Command::Stuck(args) => stuck::run(&context, args).await,
+        #[cfg(feature = "tui")]
+        Command::Tui(args) => tui::run(&context, args).await,
+    }
+}
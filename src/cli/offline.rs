@@ -0,0 +1,150 @@
+use std::path::{Path, PathBuf};
+
+use clap::Subcommand;
+use evm_json_rpc::abi::encode_call_args;
+use evm_json_rpc::confirm::TransactionSummary;
+use evm_json_rpc::offline::OfflineTransaction;
+use evm_json_rpc::transaction::TransactionRequest;
+use evm_json_rpc::utils::parse_units;
+use evm_json_rpc::{format, Error, Result};
+
+use super::{confirm, signing_key, Context};
+
+// Signing on an airgapped machine: `prepare` fills in a transaction online and writes it
+// unsigned, `sign` signs it with no network connection and `broadcast` sends the result.
+// Payloads are compact JSON, shown as a QR code with --qr when built with the qr feature.
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    command: OfflineCommand,
+}
+
+#[derive(Subcommand)]
+enum OfflineCommand {
+    #[command(about = "Fill in nonce, gas and fees for a sender and write the unsigned transaction")]
+    Prepare(PrepareArgs),
+    #[command(about = "Sign a prepared transaction without connecting to any node")]
+    Sign(SignArgs),
+    #[command(about = "Broadcast a signed transaction, from `sign` or as raw hex")]
+    Broadcast(BroadcastArgs),
+}
+
+#[derive(clap::Args)]
+struct PrepareArgs {
+    #[arg(long, help = "Sender address or address book name, whose key signs offline")]
+    from: String,
+    #[arg(help = "Recipient address or address book name")]
+    to: String,
+    #[arg(help = "Function signature; a plain transfer when omitted")]
+    signature: Option<String>,
+    #[arg(help = "Function arguments, in order")]
+    args: Vec<String>,
+    #[arg(long, help = "Amount of ETH to send, e.g. 0.5")]
+    value: Option<String>,
+    #[arg(long, help = "Gas limit, instead of the node's estimate")]
+    gas_limit: Option<u64>,
+    #[arg(long, help = "Nonce, instead of the sender's pending count")]
+    nonce: Option<u64>,
+    #[command(flatten)]
+    output: Output,
+}
+
+#[derive(clap::Args)]
+struct SignArgs {
+    #[arg(help = "Unsigned transaction file from `prepare`")]
+    input: PathBuf,
+    #[arg(long, help = "File holding the hex private key, instead of ETH_PRIVATE_KEY")]
+    key_file: Option<PathBuf>,
+    #[command(flatten)]
+    output: Output,
+}
+
+#[derive(clap::Args)]
+struct BroadcastArgs {
+    #[arg(help = "Signed transaction file, or - for stdin")]
+    input: PathBuf,
+}
+
+#[derive(clap::Args)]
+struct Output {
+    #[arg(long, help = "Write the payload to this file instead of stdout")]
+    out: Option<PathBuf>,
+    #[cfg(feature = "qr")]
+    #[arg(long, help = "Show the payload as a QR code on the terminal instead of printing it")]
+    qr: bool,
+}
+
+pub async fn run(context: &Context, args: Args) -> Result<()> {
+    match args.command {
+        OfflineCommand::Prepare(args) => prepare(context, args).await,
+        OfflineCommand::Sign(args) => sign(context, args),
+        OfflineCommand::Broadcast(args) => broadcast(context, args).await,
+    }
+}
+
+async fn prepare(context: &Context, args: PrepareArgs) -> Result<()> {
+    let config = context.config()?;
+    let mut request = TransactionRequest {
+        to: Some(config.resolve_address(&args.to).to_string()),
+        gas: args.gas_limit.map(Into::into),
+        nonce: args.nonce.map(Into::into),
+        ..Default::default()
+    };
+    if let Some(signature) = &args.signature {
+        let call_args: Vec<&str> = args.args.iter().map(String::as_str).collect();
+        request.data = Some(encode_call_args(signature, &call_args)?);
+    } else if !args.args.is_empty() {
+        return Err(Error::InvalidInput("Arguments given without a function signature".to_string()));
+    }
+    if let Some(value) = &args.value {
+        request.value = Some(parse_units(value, 18)?);
+    }
+    let client = context.connect().await?;
+    let from = config.resolve_address(&args.from);
+    let unsigned = OfflineTransaction::prepare(&client, from, &request).await?;
+    eprintln!("{}", TransactionSummary::new(from, &unsigned.decode()?, None));
+    write(&args.output, &unsigned)
+}
+
+// Shows what the transaction does before signing; nothing here touches the network
+fn sign(context: &Context, args: SignArgs) -> Result<()> {
+    let unsigned = OfflineTransaction::parse(&std::fs::read_to_string(&args.input)?)?;
+    let key = signing_key(args.key_file.as_deref())?;
+    let summary = TransactionSummary::new(&unsigned.from, &unsigned.decode()?, None);
+    if !context.yes && !confirm::prompt()(&summary) {
+        return Err(Error::Declined("Transaction was not signed".to_string()));
+    }
+    write(&args.output, &unsigned.sign(&key)?)
+}
+
+async fn broadcast(context: &Context, args: BroadcastArgs) -> Result<()> {
+    let signed = OfflineTransaction::parse(&read_input(&args.input)?)?;
+    let client = context.connect().await?;
+    let hash = signed.broadcast(&client).await?;
+    println!("Sent {}", format::hash(&format!("{:?}", hash)));
+    Ok(())
+}
+
+fn read_input(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        return Ok(std::io::read_to_string(std::io::stdin())?);
+    }
+    Ok(std::fs::read_to_string(path)?)
+}
+
+fn write(output: &Output, payload: &OfflineTransaction) -> Result<()> {
+    let json = payload.to_json();
+    if let Some(path) = &output.out {
+        std::fs::write(path, format!("{}\n", json))?;
+        eprintln!("Wrote {}", path.display());
+    }
+    #[cfg(feature = "qr")]
+    if output.qr {
+        println!("{}", evm_json_rpc::qr::render_terminal(&json)?);
+        return Ok(());
+    }
+    if output.out.is_none() {
+        println!("{}", json);
+    }
+    Ok(())
+}
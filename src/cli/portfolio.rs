@@ -0,0 +1,47 @@
+use std::collections::BTreeMap;
+
+use evm_json_rpc::format::{self, TokenAmount};
+use evm_json_rpc::portfolio::{portfolio, NATIVE_DECIMALS};
+use evm_json_rpc::{ChainRouter, Result};
+
+use super::Context;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(help = "Address or address book name")]
+    address: String,
+}
+
+// Every profile with a URL is a chain; without any, the selected profile alone is used
+pub async fn run(context: &Context, args: Args) -> Result<()> {
+    let config = context.config()?;
+    let mut router = ChainRouter::from_config(config).await?;
+    if router.is_empty() {
+        let client = context.connect().await?;
+        router.insert(client.chain_id().await?, "default", client);
+    }
+    let mut tokens = BTreeMap::new();
+    for (chain_id, name, _) in router.chains() {
+        let profile = config.profiles.get(name).map_or_else(|| context.profile(), Ok)?;
+        tokens.insert(chain_id, profile.tokens.clone());
+    }
+
+    let address = config.resolve_address(&args.address);
+    let portfolio = portfolio(&router, address, &tokens).await?;
+    println!("Portfolio of {}", format::address(address));
+    for chain in &portfolio.chains {
+        println!("\n{} ({})", chain.name, chain.chain_id);
+        println!("  {}", TokenAmount::new(chain.native, NATIVE_DECIMALS).with_symbol("ETH"));
+        for token in &chain.tokens {
+            println!("  {}", token.amount());
+        }
+    }
+    for (chain_id, error) in &portfolio.errors {
+        eprintln!("\n{} ({}) failed: {}", router.name(*chain_id).unwrap_or_default(), chain_id, error);
+    }
+    println!("\nTotal");
+    for total in portfolio.totals() {
+        println!("  {}", total.amount());
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use std::io::{IsTerminal, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use evm_json_rpc::progress::{Progress, ProgressFn};
+
+const WIDTH: usize = 30;
+
+// A one-line bar redrawn in place on stderr. When stderr isn't a terminal only the
+// messages are written, so logs stay readable.
+#[derive(Clone)]
+pub struct ProgressBar {
+    label: String,
+    // Length of the bar line currently on screen, 0 when none is drawn
+    drawn: Arc<Mutex<usize>>,
+    terminal: bool,
+}
+
+impl ProgressBar {
+    pub fn new(label: &str) -> Self {
+        ProgressBar {
+            label: label.to_string(),
+            drawn: Arc::new(Mutex::new(0)),
+            terminal: std::io::stderr().is_terminal(),
+        }
+    }
+
+    pub fn update(&self, progress: &Progress) {
+        if !self.terminal {
+            return;
+        }
+        let mut line = self.label.clone();
+        if let Some(fraction) = progress.fraction() {
+            let filled = (fraction * WIDTH as f64) as usize;
+            line += &format!(" [{}{}] {:>3}%", "#".repeat(filled), "-".repeat(WIDTH - filled), (fraction * 100.0) as u32);
+        }
+        line += &match progress.total {
+            Some(total) => format!(" {}/{}", progress.done, total),
+            None => format!(" {}", progress.done),
+        };
+        if let Some(block) = progress.block {
+            line += &format!(" block {}", block);
+        }
+        if let Some(eta) = progress.eta() {
+            line += &format!(" ETA {}", duration(eta));
+        }
+        let mut drawn = self.drawn.lock().unwrap();
+        eprint!("\r{:width$}", line, width = *drawn);
+        let _ = std::io::stderr().flush();
+        *drawn = line.len();
+    }
+
+    // Prints a line above the bar, which is redrawn on the next update
+    pub fn println(&self, message: &str) {
+        self.clear();
+        println!("{}", message);
+    }
+
+    pub fn eprintln(&self, message: &str) {
+        self.clear();
+        eprintln!("{}", message);
+    }
+
+    pub fn finish(&self) {
+        self.clear();
+    }
+
+    pub fn callback(&self) -> ProgressFn {
+        let bar = self.clone();
+        Arc::new(move |progress: &Progress| bar.update(progress))
+    }
+
+    fn clear(&self) {
+        let mut drawn = self.drawn.lock().unwrap();
+        if *drawn > 0 {
+            eprint!("\r{}\r", " ".repeat(*drawn));
+            *drawn = 0;
+        }
+    }
+}
+
+fn duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
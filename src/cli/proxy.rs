@@ -0,0 +1,97 @@
+use std::net::{SocketAddr, TcpListener};
+use std::time::Duration;
+
+use evm_json_rpc::middleware::{CacheLayer, DedupLayer, RateLimitLayer, RetryLayer};
+use evm_json_rpc::proxy::{Proxy, MAX_BATCH_SIZE, READ_METHODS};
+#[cfg(feature = "metrics")]
+use evm_json_rpc::ClientBuilder;
+use evm_json_rpc::Result;
+
+use super::Context;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long, help = "Serve JSON-RPC over HTTP on this address, e.g. 127.0.0.1:8545; stdin/stdout otherwise")]
+    listen: Option<SocketAddr>,
+    #[arg(long, help = "Only allow methods that read chain state; always the case over HTTP")]
+    read_only: bool,
+    #[arg(long = "allow", value_name = "METHOD", help = "Allow this method; repeat for more. Over HTTP these add to the read methods, e.g. --allow eth_sendRawTransaction; otherwise any other method is refused")]
+    allow: Vec<String>,
+    #[arg(long, default_value_t = MAX_BATCH_SIZE, help = "Most requests accepted in one batch")]
+    max_batch: usize,
+    #[arg(long, help = "Don't cache responses pinned to a block")]
+    no_cache: bool,
+    #[arg(long, default_value_t = 3600, help = "Seconds to keep cached responses from final blocks")]
+    cache_ttl: u64,
+    #[arg(long, default_value_t = 64, help = "Blocks behind the head after which a block is cached as final; newer ones are kept for two seconds")]
+    finality_depth: u64,
+    #[arg(long, help = "Limit upstream requests per second")]
+    rate_limit: Option<f64>,
+    #[arg(long, default_value_t = 5, help = "Attempts per request for transient failures, including the first")]
+    max_attempts: u32,
+    #[cfg(feature = "metrics")]
+    #[arg(long, help = "Serve Prometheus metrics at GET /metrics (needs --listen)")]
+    metrics: bool,
+}
+
+// Forwards requests to the profile's endpoints through caching, request
+// deduplication, rate limiting and retries. Over HTTP only reads are forwarded unless
+// --allow adds more, and [proxy_consumers] in the config turns on API keys and quotas.
+pub async fn run(context: &Context, args: Args) -> Result<()> {
+    let profile = context.profile()?;
+    let mut builder = profile.builder()?;
+    if !args.no_cache {
+        builder = builder.layer(CacheLayer {
+            ttl: Duration::from_secs(args.cache_ttl),
+            finality_depth: args.finality_depth,
+            ..Default::default()
+        });
+    }
+    builder = builder.layer(DedupLayer::default());
+    if let Some(requests_per_second) = args.rate_limit {
+        builder = builder.layer(RateLimitLayer::new(requests_per_second, requests_per_second.ceil().max(1.0) as u32));
+    }
+    let retry = RetryLayer {
+        max_attempts: args.max_attempts.max(1),
+        ..Default::default()
+    };
+    #[cfg(feature = "metrics")]
+    let (builder, registry) = with_metrics(builder, retry, args.metrics)?;
+    #[cfg(not(feature = "metrics"))]
+    let builder = builder.layer(retry);
+
+    let client = profile.verify(builder.build()).await?;
+    let mut proxy = Proxy::new(&client).with_max_batch(args.max_batch).with_consumers(context.config()?.proxy_consumers.iter().map(|(name, consumer)| consumer.consumer(name)));
+    // HTTP consumers may not be trusted, so writes there are opt-in on top of the reads
+    let read_only = args.read_only || args.listen.is_some();
+    if read_only || !args.allow.is_empty() {
+        let read_methods = READ_METHODS.iter().copied().filter(|_| read_only);
+        proxy = proxy.with_allowlist(read_methods.chain(args.allow.iter().map(String::as_str)));
+    }
+    #[cfg(feature = "metrics")]
+    let proxy = match registry {
+        Some(registry) => proxy.with_metrics(registry),
+        None => proxy,
+    };
+
+    match args.listen {
+        Some(address) => {
+            let listener = TcpListener::bind(address)?;
+            eprintln!("Proxying JSON-RPC on http://{}", listener.local_addr()?);
+            proxy.serve_http(listener).await
+        }
+        None => proxy.serve_stdio().await,
+    }
+}
+
+// Metrics go inside the retry layer so each attempt is measured
+#[cfg(feature = "metrics")]
+fn with_metrics(builder: ClientBuilder, mut retry: RetryLayer, enabled: bool) -> Result<(ClientBuilder, Option<evm_json_rpc::metrics::Registry>)> {
+    if !enabled {
+        return Ok((builder.layer(retry), None));
+    }
+    let registry = evm_json_rpc::metrics::Registry::new();
+    let metrics = evm_json_rpc::metrics::Metrics::register(&registry)?;
+    retry.metrics = Some(metrics.clone());
+    Ok((builder.layer(retry).layer(metrics), Some(registry)))
+}
@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use evm_json_rpc::stuck::repair_nonces;
+use evm_json_rpc::{format, Result};
+
+use super::Context;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long, help = "Fill gaps up to this nonce; by default up to the highest one in the txpool")]
+    through: Option<u64>,
+    #[arg(long, help = "File holding the hex private key, instead of ETH_PRIVATE_KEY")]
+    key_file: Option<PathBuf>,
+}
+
+pub async fn run(context: &Context, args: Args) -> Result<()> {
+    let client = context.connect().await?;
+    let wallet = context.wallet(&client, args.key_file.as_deref())?;
+    let filled = repair_nonces(&wallet, args.through).await?;
+    if filled.is_empty() {
+        println!("No nonce gaps for {}", format::address(&wallet.address()));
+    }
+    for (nonce, hash) in filled {
+        println!("Filled nonce {} with {}", nonce, format::hash(&format!("{:?}", hash)));
+    }
+    Ok(())
+}
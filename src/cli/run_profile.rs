@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use evm_json_rpc::abi::token_to_json;
+use evm_json_rpc::interaction::InteractionProfile;
+use evm_json_rpc::{format, Error, Result};
+
+use super::Context;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(help = "Name under [interactions] in the config")]
+    interaction: String,
+    #[arg(help = "Preset to run; lists the presets when omitted")]
+    preset: Option<String>,
+    #[arg(help = "Arguments replacing the preset's defaults, in order")]
+    args: Vec<String>,
+    #[arg(long, help = "File holding the hex private key, instead of ETH_PRIVATE_KEY")]
+    key_file: Option<PathBuf>,
+}
+
+pub async fn run(context: &Context, args: Args) -> Result<()> {
+    let config = context.config()?;
+    let interaction = config.interactions.get(&args.interaction).ok_or_else(|| Error::Config(format!("No interaction named {}", args.interaction)))?;
+    let profile = InteractionProfile::load(interaction, &config.address_book)?;
+    let Some(preset) = args.preset else {
+        println!("Presets for {} ({})", args.interaction, format::address(&profile.address));
+        for (name, function) in profile.presets() {
+            let inputs: Vec<String> = function.inputs.iter().map(|input| format!("{} {}", input.kind, input.name).trim_end().to_string()).collect();
+            println!("  {}  {}({})", name, function.name, inputs.join(", "));
+        }
+        return Ok(());
+    };
+
+    let client = context.connect().await?;
+    let overrides: Vec<&str> = args.args.iter().map(String::as_str).collect();
+    if profile.is_read(&preset)? {
+        for token in profile.call(&client, &preset, &overrides).await? {
+            match token_to_json(&token) {
+                serde_json::Value::String(text) => println!("{}", text),
+                value => println!("{}", value),
+            }
+        }
+        return Ok(());
+    }
+    let request = profile.request(&preset, &overrides)?;
+    let wallet = context.wallet(&client, args.key_file.as_deref())?;
+    let hash = wallet.send(&request).await?;
+    println!("Sent {}", format::hash(&format!("{:?}", hash)));
+    Ok(())
+}
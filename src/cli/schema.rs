@@ -0,0 +1,41 @@
+use clap::{Arg, CommandFactory};
+use evm_json_rpc::{Error, Result};
+
+use super::{write_stdout, Cli};
+
+// Every command and flag as JSON, for wrapper tools and GUIs that build invocations
+// rather than parse --help. Hidden commands and flags are left out; global flags are
+// listed once, on the top-level command.
+pub fn print() -> Result<()> {
+    let schema = command(&Cli::command());
+    write_stdout(&serde_json::to_string_pretty(&schema).map_err(|error| Error::Decode(error.to_string()))?)
+}
+
+fn command(command: &clap::Command) -> serde_json::Value {
+    serde_json::json!({
+        "name": command.get_name(),
+        "version": command.get_version(),
+        "about": command.get_about().map(ToString::to_string),
+        "args": command.get_arguments().filter(|arg| !arg.is_hide_set()).map(arg).collect::<Vec<_>>(),
+        "subcommands": command.get_subcommands().filter(|subcommand| !subcommand.is_hide_set()).map(self::command).collect::<Vec<_>>(),
+    })
+}
+
+fn arg(arg: &Arg) -> serde_json::Value {
+    let takes_value = arg.get_action().takes_values();
+    serde_json::json!({
+        "id": arg.get_id().as_str(),
+        "positional": arg.is_positional(),
+        "long": arg.get_long(),
+        "short": arg.get_short().map(String::from),
+        "help": arg.get_help().map(ToString::to_string),
+        "required": arg.is_required_set(),
+        "global": arg.is_global_set(),
+        "takes_value": takes_value,
+        // Repeatable flags and variadic positionals
+        "multiple": takes_value && arg.get_num_args().is_some_and(|range| range.max_values() > 1) || matches!(arg.get_action(), clap::ArgAction::Append | clap::ArgAction::Count),
+        "value_names": arg.get_value_names().map(|names| names.iter().map(ToString::to_string).collect::<Vec<_>>()),
+        "default": arg.get_default_values().iter().map(|value| value.to_string_lossy().into_owned()).collect::<Vec<_>>(),
+        "possible_values": if !takes_value { Vec::new() } else { arg.get_possible_values().iter().filter(|value| !value.is_hide_set()).map(|value| value.get_name().to_string()).collect::<Vec<_>>() },
+    })
+}
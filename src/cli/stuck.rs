@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use evm_json_rpc::format;
+use evm_json_rpc::stuck::stuck_transactions;
+use evm_json_rpc::{Error, Result, Wallet};
+
+use super::Context;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(help = "Address or address book name; the signing key's address when omitted")]
+    address: Option<String>,
+    #[arg(long, help = "Replace each stuck nonce with an empty self-transfer at higher fees")]
+    cancel: bool,
+    #[arg(long, help = "File holding the hex private key, instead of ETH_PRIVATE_KEY")]
+    key_file: Option<PathBuf>,
+}
+
+pub async fn run(context: &Context, args: Args) -> Result<()> {
+    let client = context.connect().await?;
+    let wallet = if args.cancel || args.address.is_none() { Some(context.wallet(&client, args.key_file.as_deref())?) } else { None };
+    let address = match &args.address {
+        Some(address) => context.config()?.resolve_address(address).to_string(),
+        None => wallet.as_ref().map(Wallet::address).unwrap_or_default(),
+    };
+
+    let stuck = stuck_transactions(&client, &address).await?;
+    if stuck.is_empty() {
+        println!("No unmined transactions from {}", format::address(&address));
+        return Ok(());
+    }
+    println!("Unmined transactions from {}", format::address(&address));
+    for transaction in &stuck {
+        let hash = transaction.hash.map_or("-".to_string(), |hash| format::hash(&format!("{:?}", hash)));
+        let fees = transaction.fees().map_or("fees unknown".to_string(), |(max_fee, tip)| format!("max fee {} gwei, tip {} gwei", format::number(max_fee, 9), format::number(tip, 9)));
+        println!("  nonce {}{}  {}  {}", transaction.nonce, if transaction.queued { " (queued)" } else { "" }, hash, fees);
+    }
+
+    let Some(wallet) = wallet.filter(|_| args.cancel) else { return Ok(()) };
+    if !wallet.address().eq_ignore_ascii_case(&address) {
+        return Err(Error::InvalidInput(format!("The signing key is for {}, not {}", wallet.address(), address)));
+    }
+    for transaction in &stuck {
+        match wallet.cancel_transaction(transaction.nonce).await {
+            Ok(hash) => println!("Cancelling nonce {} with {:?}", transaction.nonce, hash),
+            Err(error) => eprintln!("Failed to cancel nonce {}: {}", transaction.nonce, error),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,243 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crossterm::event::{Event, EventStream, KeyCode, KeyEventKind, KeyModifiers};
+use ethabi::ethereum_types::U256;
+use evm_json_rpc::blocks::{get_block_stats, BlockStats};
+use evm_json_rpc::format::{self, TokenAmount};
+use evm_json_rpc::portfolio::{chain_holdings, TokenBalance};
+use evm_json_rpc::utils::format_gwei;
+use evm_json_rpc::{EthClient, Result};
+use futures_util::StreamExt;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table};
+use ratatui::Frame;
+use tokio::sync::mpsc;
+
+use super::Context;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long = "watch", value_name = "ADDRESS", help = "Address or address book name to show balances of; repeat for several. The whole address book when omitted")]
+    watch: Vec<String>,
+    #[arg(long, default_value_t = 12, help = "Seconds between checks for a new block when the profile has no ws_url")]
+    interval: u64,
+    #[arg(long, default_value_t = 10, help = "Recent blocks to list")]
+    blocks: usize,
+}
+
+struct Watched {
+    label: String,
+    address: String,
+}
+
+// Everything read for one new block
+struct Snapshot {
+    block: BlockStats,
+    gas_price: U256,
+    priority_fee: U256,
+    balances: Vec<(U256, Vec<TokenBalance>)>,
+}
+
+enum Update {
+    Snapshot(Box<Snapshot>),
+    Error(String),
+}
+
+#[derive(Default)]
+struct Dashboard {
+    blocks: VecDeque<BlockStats>,
+    gas_price: Option<U256>,
+    priority_fee: Option<U256>,
+    balances: Vec<(U256, Vec<TokenBalance>)>,
+    status: String,
+}
+
+// Live gas prices, recent blocks and the balances of watched addresses, refreshed on
+// every new head: from the profile's ws_url subscription when it has one, otherwise
+// by polling. Quit with q or Esc.
+pub async fn run(context: &Context, args: Args) -> Result<()> {
+    let config = context.config()?;
+    let client = context.connect().await?;
+    let names: Vec<String> = if args.watch.is_empty() { config.address_book.keys().cloned().collect() } else { args.watch };
+    let watched: Vec<Watched> = names
+        .iter()
+        .map(|name| {
+            let address = config.resolve_address(name);
+            let label = if address == name { format::address(address) } else { name.clone() };
+            Watched { label, address: address.to_string() }
+        })
+        .collect();
+
+    let (sender, mut updates) = mpsc::unbounded_channel();
+    let heads = follow_heads(context, &client, Duration::from_secs(args.interval.max(1))).await?;
+    let addresses: Vec<String> = watched.iter().map(|watched| watched.address.clone()).collect();
+    tokio::spawn(refresh(client, heads, addresses, context.profile()?.tokens.clone(), sender));
+
+    let mut terminal = ratatui::init();
+    let mut dashboard = Dashboard {
+        status: "Loading the latest block".to_string(),
+        ..Default::default()
+    };
+    let mut events = EventStream::new();
+    let mut following = true;
+    let result = loop {
+        if let Err(error) = terminal.draw(|frame| draw(frame, &dashboard, &watched)) {
+            break Err(error.into());
+        }
+        tokio::select! {
+            update = updates.recv(), if following => match update {
+                Some(Update::Snapshot(snapshot)) => dashboard.apply(*snapshot, args.blocks),
+                Some(Update::Error(error)) => dashboard.status = error,
+                None => {
+                    following = false;
+                    dashboard.status = "The WebSocket subscription ended; restart to reconnect".to_string();
+                }
+            },
+            event = events.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                    let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                    if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                        break Ok(());
+                    }
+                }
+                Some(Ok(_)) => {}
+                Some(Err(error)) => break Err(error.into()),
+                None => break Ok(()),
+            },
+        }
+    };
+    ratatui::restore();
+    result
+}
+
+// A channel of new block numbers, from a newHeads subscription or by polling
+async fn follow_heads(context: &Context, client: &EthClient, interval: Duration) -> Result<mpsc::UnboundedReceiver<u64>> {
+    if let Some(ws) = context.profile()?.connect_ws().await? {
+        let (_, mut heads) = ws.subscribe(vec![serde_json::json!("newHeads")]).await?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        // Show the current head without waiting for the next one
+        let _ = sender.send(client.get_block_number().await?);
+        tokio::spawn(async move {
+            // Holding the transport keeps the connection open
+            let _ws = ws;
+            while let Some(header) = heads.recv().await {
+                let number = header["number"].as_str().and_then(|number| u64::from_str_radix(number.trim_start_matches("0x"), 16).ok());
+                if number.is_some_and(|number| sender.send(number).is_err()) {
+                    break;
+                }
+            }
+        });
+        return Ok(receiver);
+    }
+
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let client = client.clone();
+    tokio::spawn(async move {
+        let mut last = None;
+        loop {
+            if let Ok(number) = client.get_block_number().await {
+                if last != Some(number) {
+                    last = Some(number);
+                    if sender.send(number).is_err() {
+                        break;
+                    }
+                }
+            }
+            tokio::time::sleep(evm_json_rpc::poll::jittered(interval)).await;
+        }
+    });
+    Ok(receiver)
+}
+
+// Reads a snapshot per new head. Heads that arrived while a snapshot was being read
+// are skipped in favour of the newest.
+async fn refresh(client: EthClient, mut heads: mpsc::UnboundedReceiver<u64>, addresses: Vec<String>, tokens: Vec<String>, updates: mpsc::UnboundedSender<Update>) {
+    while let Some(mut number) = heads.recv().await {
+        while let Ok(newer) = heads.try_recv() {
+            number = newer;
+        }
+        let update = match snapshot(&client, number, &addresses, &tokens).await {
+            Ok(snapshot) => Update::Snapshot(Box::new(snapshot)),
+            Err(error) => Update::Error(format!("Block {}: {}", number, error)),
+        };
+        if updates.send(update).is_err() {
+            break;
+        }
+    }
+}
+
+async fn snapshot(client: &EthClient, number: u64, addresses: &[String], tokens: &[String]) -> Result<Snapshot> {
+    let block = get_block_stats(client, &format!("0x{:x}", number)).await?;
+    let gas_price = client.gas_price().await?;
+    // Pre-London chains have no tip to suggest
+    let priority_fee = client.max_priority_fee().await.unwrap_or_default();
+    let mut balances = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        balances.push(chain_holdings(client, address, tokens).await?);
+    }
+    Ok(Snapshot {
+        block,
+        gas_price,
+        priority_fee,
+        balances,
+    })
+}
+
+impl Dashboard {
+    fn apply(&mut self, snapshot: Snapshot, max_blocks: usize) {
+        self.status = format!("Updated at block {}", snapshot.block.number);
+        if self.blocks.front().is_some_and(|newest| newest.number >= snapshot.block.number) {
+            // A reorg or a late snapshot: keep the list in order
+            self.blocks.retain(|block| block.number < snapshot.block.number);
+        }
+        self.blocks.push_front(snapshot.block);
+        self.blocks.truncate(max_blocks.max(1));
+        self.gas_price = Some(snapshot.gas_price);
+        self.priority_fee = Some(snapshot.priority_fee);
+        self.balances = snapshot.balances;
+    }
+}
+
+fn gwei(value: Option<U256>) -> String {
+    value.map_or("-".to_string(), |value| format!("{} gwei", format_gwei(value)))
+}
+
+fn draw(frame: &mut Frame, dashboard: &Dashboard, watched: &[Watched]) {
+    let [gas_area, blocks_area, watched_area, status_area] = Layout::vertical([Constraint::Length(3), Constraint::Length(dashboard.blocks.len().max(1) as u16 + 3), Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+
+    let base_fee = dashboard.blocks.front().and_then(|block| block.base_fee);
+    let gas = format!("Base fee {}   Gas price {}   Priority fee {}", gwei(base_fee), gwei(dashboard.gas_price), gwei(dashboard.priority_fee));
+    frame.render_widget(Paragraph::new(gas).block(Block::default().borders(Borders::ALL).title("Gas")), gas_area);
+
+    let header = Row::new(["Block", "Time", "Txs", "Gas used", "Base fee"]).style(Style::default().add_modifier(Modifier::BOLD));
+    let rows = dashboard.blocks.iter().map(|block| {
+        Row::new([
+            block.number.to_string(),
+            evm_json_rpc::utils::format_rfc3339(block.timestamp as i64),
+            block.transaction_count.to_string(),
+            format!("{:.1}%", block.gas_utilization()),
+            gwei(block.base_fee),
+        ])
+    });
+    let widths = [Constraint::Length(12), Constraint::Length(26), Constraint::Length(6), Constraint::Length(10), Constraint::Min(12)];
+    frame.render_widget(Table::new(rows, widths).header(header).block(Block::default().borders(Borders::ALL).title("Latest blocks")), blocks_area);
+
+    let lines: Vec<Line> = watched
+        .iter()
+        .enumerate()
+        .map(|(index, watched)| match dashboard.balances.get(index) {
+            Some((native, tokens)) => {
+                let mut amounts = vec![TokenAmount::ether(*native).to_string()];
+                amounts.extend(tokens.iter().map(|token| token.amount().to_string()));
+                Line::from(format!("{:<20} {}", watched.label, amounts.join(", ")))
+            }
+            None => Line::from(format!("{:<20} -", watched.label)),
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Watched")), watched_area);
+
+    frame.render_widget(Paragraph::new(format!("{}   (q to quit)", dashboard.status)), status_area);
+}
@@ -0,0 +1,379 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ethabi::ethereum_types::{H256, U256};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, OnceCell};
+
+use crate::abi::{decode_address, decode_string, decode_string_array, decode_u256, decode_uint, encode_function_call};
+use crate::activity::ActivityFeed;
+use crate::blocks::BlockTag;
+use crate::ccip;
+use crate::context::{ContextLayer, RequestContext};
+use crate::dialect::{self, Dialect};
+use crate::error::{Error, Result};
+use crate::extension::{to_params, RpcMethod};
+use crate::gas::{self, BaseFeeCrossing, FeeHistory};
+use crate::logs::{Filter, Log};
+use crate::middleware::Layer;
+use crate::transaction::{TransactionReceipt, TransactionRequest, TypedTransaction};
+use crate::transport::{HttpTransport, Transport};
+use crate::utils::{parse_quantity, parse_quantity_u256};
+
+#[derive(Serialize, Deserialize)]
+pub struct JsonRpcRequest {
+    pub id: u64,
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: Vec<serde_json::Value>,
+}
+
+// The JSON-RPC error object. Nodes put revert data and similar details in data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (code {})", self.message, self.code)?;
+        if let Some(data) = &self.data {
+            write!(f, ", data: {}", data)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+// A response whose result is deserialized into T, or the node's error. A missing
+// result is treated as null, so T = Option<_> covers methods that may return nothing.
+#[derive(Debug, Clone)]
+pub struct JsonRpcResponse<T> {
+    pub id: serde_json::Value,
+    pub result: Result<T, RpcError>,
+}
+
+impl<T: DeserializeOwned> JsonRpcResponse<T> {
+    pub fn from_value(response: serde_json::Value) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Raw {
+            #[serde(default)]
+            id: serde_json::Value,
+            #[serde(default)]
+            result: serde_json::Value,
+            error: Option<RpcError>,
+        }
+
+        let raw: Raw = serde_json::from_value(response).map_err(|error| Error::Decode(format!("Invalid JSON-RPC response: {}", error)))?;
+        let result = match raw.error {
+            Some(error) => Err(error),
+            None => Ok(serde_json::from_value(raw.result).map_err(|error| Error::Decode(format!("Invalid JSON-RPC result: {}", error)))?),
+        };
+        Ok(JsonRpcResponse { id: raw.id, result })
+    }
+}
+
+// Ids are unique across every transport in the process
+pub fn next_request_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// Checks a response belongs to the request it answers. The spec allows a null id on
+// errors raised before the node could read the request's id.
+pub fn check_response_id(response: &serde_json::Value, id: u64) -> Result<()> {
+    match response.get("id") {
+        Some(response_id) if response_id.as_u64() == Some(id) => Ok(()),
+        Some(serde_json::Value::Null) | None if response.get("error").is_some() => Ok(()),
+        response_id => Err(Error::Decode(format!("Response id {} does not match request id {}", response_id.unwrap_or(&serde_json::Value::Null), id))),
+    }
+}
+
+#[derive(Clone)]
+pub struct EthClient {
+    transport: Arc<dyn Transport>,
+    // Used for off-chain HTTP such as CCIP-Read gateways, whatever the transport
+    client: reqwest::Client,
+    // Detected once and shared by clones, which talk to the same endpoint
+    dialect: Arc<OnceCell<Dialect>>,
+}
+
+impl EthClient {
+    pub fn new(rpc_url: &str) -> Self {
+        ClientBuilder::new(rpc_url).build()
+    }
+
+    // Reuses an existing reqwest client (connection pool, proxy and TLS settings)
+    pub fn with_client(client: reqwest::Client, rpc_url: &str) -> Self {
+        ClientBuilder::with_client(client, rpc_url).build()
+    }
+
+    // Sends requests through any transport (WebSocket, IPC, mocks) instead of HTTP
+    pub fn with_transport(transport: impl Transport + 'static) -> Self {
+        ClientBuilder::with_transport(transport).build()
+    }
+
+    pub fn builder(rpc_url: &str) -> ClientBuilder {
+        ClientBuilder::new(rpc_url)
+    }
+
+    pub fn transport(&self) -> &dyn Transport {
+        self.transport.as_ref()
+    }
+
+    pub fn http_client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    // A clone whose requests carry `context`, for attributing a task's traffic
+    pub fn with_context(&self, context: RequestContext) -> EthClient {
+        EthClient {
+            transport: ContextLayer::new(context).layer(self.transport.clone()),
+            client: self.client.clone(),
+            dialect: self.dialect.clone(),
+        }
+    }
+
+    // Sends any JSON-RPC method and returns its result, turning node errors into Err
+    pub async fn send(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value> {
+        let response = JsonRpcResponse::from_value(self.send_raw(method, params).await?)?;
+        match response.result {
+            Ok(result) => Ok(result),
+            Err(error) => Err(self.rpc_error(method, error).await),
+        }
+    }
+
+    // Calls a method declared with RpcMethod, serializing params and deserializing the result
+    pub async fn request<M: RpcMethod>(&self, params: M::Params) -> Result<M::Response> {
+        let params = serde_json::to_value(params).map_err(|error| Error::Abi(format!("Invalid {} params: {}", M::METHOD, error)))?;
+        let result = self.send(M::METHOD, to_params(params)).await?;
+        serde_json::from_value(result).map_err(|error| Error::Decode(format!("Invalid {} response: {}", M::METHOD, error)))
+    }
+
+    // Returns the whole JSON-RPC response so callers can inspect node errors themselves
+    pub async fn send_raw(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value> {
+        self.transport.request(method, params).await.map_err(Error::Transport)
+    }
+
+    // The endpoint's JSON-RPC dialect, probed on first use. A failed probe is not cached.
+    pub async fn dialect(&self) -> Result<Dialect> {
+        self.dialect.get_or_try_init(|| dialect::detect_dialect(self)).await.copied()
+    }
+
+    // A missing eth_* method usually means a non-EVM endpoint, so probe and say that
+    // plainly; if the probe itself fails the node's own error is kept
+    async fn rpc_error(&self, method: &str, mut error: RpcError) -> Error {
+        if error.code == dialect::METHOD_NOT_FOUND && method.starts_with("eth_") {
+            if let Ok(dialect) = self.dialect().await {
+                if let Err(unsupported) = dialect::check_evm(dialect) {
+                    error.message = unsupported.to_string();
+                }
+            }
+        }
+        Error::Rpc(error)
+    }
+
+    pub async fn get_block_number(&self) -> Result<u64> {
+        let number = self.send("eth_blockNumber", vec![]).await?;
+        parse_quantity(&number).map_err(|_| Error::Decode(format!("Invalid eth_blockNumber response: {}", number)))
+    }
+
+    pub async fn chain_id(&self) -> Result<u64> {
+        let chain_id = self.send("eth_chainId", vec![]).await?;
+        parse_quantity(&chain_id).map_err(|_| Error::Decode(format!("Invalid eth_chainId response: {}", chain_id)))
+    }
+
+    // Errors with Error::ChainMismatch unless the endpoint serves `expected`
+    pub async fn verify_chain_id(&self, expected: u64) -> Result<()> {
+        let actual = self.chain_id().await?;
+        if actual != expected {
+            return Err(Error::ChainMismatch { expected, actual });
+        }
+        Ok(())
+    }
+
+    pub async fn get_balance(&self, address: &str, block: impl Into<BlockTag>) -> Result<U256> {
+        let balance = self.send("eth_getBalance", vec![serde_json::json!(address), block.into().to_json()]).await?;
+        parse_quantity_u256(&balance).map_err(|_| Error::Decode(format!("Invalid eth_getBalance response: {}", balance)))
+    }
+
+    // Legacy gas price suggestion in wei; utils::format_gwei renders it for display
+    pub async fn gas_price(&self) -> Result<U256> {
+        let price = self.send("eth_gasPrice", vec![]).await?;
+        parse_quantity_u256(&price).map_err(|_| Error::Decode(format!("Invalid eth_gasPrice response: {}", price)))
+    }
+
+    // Gas the transaction would use at `block`. Set `from` when the call depends on the
+    // sender (transfers, access control); the node reverts the estimate otherwise.
+    pub async fn estimate_gas(&self, transaction: &TransactionRequest, block: impl Into<BlockTag>) -> Result<u64> {
+        let gas = self.send("eth_estimateGas", vec![serde_json::json!(transaction), block.into().to_json()]).await?;
+        parse_quantity(&gas).map_err(|_| Error::Decode(format!("Invalid eth_estimateGas response: {}", gas)))
+    }
+
+    // The node's suggested EIP-1559 tip in wei, to add on top of the base fee
+    pub async fn max_priority_fee(&self) -> Result<U256> {
+        let fee = self.send("eth_maxPriorityFeePerGas", vec![]).await?;
+        parse_quantity_u256(&fee).map_err(|_| Error::Decode(format!("Invalid eth_maxPriorityFeePerGas response: {}", fee)))
+    }
+
+    // Fee data for the `block_count` blocks up to `newest_block`, with each block's priority
+    // fees at the given percentiles (0-100, ascending). Nodes cap block_count, often at 1024.
+    pub async fn fee_history(&self, block_count: u64, newest_block: impl Into<BlockTag>, reward_percentiles: &[f64]) -> Result<FeeHistory> {
+        let params = vec![serde_json::json!(format!("0x{:x}", block_count)), newest_block.into().to_json(), serde_json::json!(reward_percentiles)];
+        let history = self.send("eth_feeHistory", params).await?;
+        serde_json::from_value(history).map_err(|error| Error::Decode(format!("Invalid eth_feeHistory response: {}", error)))
+    }
+
+    // Notifies when the base fee crosses any of `thresholds`; see gas::watch_base_fee
+    pub fn watch_base_fee(&self, thresholds: &[U256], interval: Duration) -> mpsc::UnboundedReceiver<BaseFeeCrossing> {
+        gas::watch_base_fee(self, thresholds, interval)
+    }
+
+    // The account's nonce; BlockTag::Pending also counts its transactions in the mempool
+    pub async fn get_transaction_count(&self, address: &str, block: impl Into<BlockTag>) -> Result<u64> {
+        let count = self.send("eth_getTransactionCount", vec![serde_json::json!(address), block.into().to_json()]).await?;
+        parse_quantity(&count).map_err(|_| Error::Decode(format!("Invalid eth_getTransactionCount response: {}", count)))
+    }
+
+    // Broadcasts a signed transaction and returns its hash
+    pub async fn send_raw_transaction(&self, transaction: &TypedTransaction) -> Result<H256> {
+        let hash = self.send("eth_sendRawTransaction", vec![serde_json::json!(transaction.to_hex())]).await?;
+        serde_json::from_value(hash.clone()).map_err(|_| Error::Decode(format!("Invalid eth_sendRawTransaction response: {}", hash)))
+    }
+
+    // None while the transaction is pending or unknown to the node
+    pub async fn get_transaction_receipt(&self, tx_hash: &str) -> Result<Option<TransactionReceipt>> {
+        let receipt = self.send("eth_getTransactionReceipt", vec![serde_json::json!(tx_hash)]).await?;
+        serde_json::from_value(receipt).map_err(|error| Error::Decode(format!("Invalid eth_getTransactionReceipt response: {}", error)))
+    }
+
+    // Human-readable activity of `address` from a start block onward; see ActivityFeed
+    pub fn activity_feed(&self, address: &str) -> ActivityFeed {
+        ActivityFeed::new(self, address)
+    }
+
+    // Time bounds on the filter are resolved to block numbers first
+    pub async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>> {
+        let filter = filter.resolve(self).await?;
+        if filter.is_empty_range() {
+            return Ok(Vec::new());
+        }
+        let logs = self.send("eth_getLogs", vec![filter.to_json()]).await?;
+        serde_json::from_value(logs).map_err(|error| Error::Decode(format!("Invalid eth_getLogs response: {}", error)))
+    }
+
+    // eth_call against the latest block, returning the raw hex result
+    pub async fn call(&self, to: &str, data: &str) -> Result<String> {
+        let mut data = data.to_string();
+
+        // Follow EIP-3668 OffchainLookup reverts through the gateway and callback
+        for _ in 0..=ccip::MAX_REDIRECTS {
+            let params = vec![
+                serde_json::json!({
+                    "to": to,
+                    "data": data,
+                }),
+                serde_json::json!("latest"),
+            ];
+            let response = self.send_raw("eth_call", params).await?;
+            let lookup = match ccip::parse_offchain_lookup(&response) {
+                Some(lookup) => lookup,
+                None => {
+                    return match JsonRpcResponse::from_value(response)?.result {
+                        Ok(result) => Ok(result),
+                        Err(error) => Err(self.rpc_error("eth_call", error).await),
+                    };
+                }
+            };
+            if !lookup.sender.eq_ignore_ascii_case(to) {
+                return Err(Error::Decode("OffchainLookup sender does not match the called contract".to_string()));
+            }
+            let response = ccip::fetch_gateway(&self.client, &lookup).await?;
+            data = ccip::encode_callback(&lookup, response);
+        }
+
+        Err(Error::Decode(format!("Too many OffchainLookup redirects, more than {}", ccip::MAX_REDIRECTS)))
+    }
+
+    // Encodes the call with encode_function_call and returns the raw hex result
+    pub async fn call_function(&self, to: &str, method_signature: &str, params: Vec<String>) -> Result<String> {
+        self.call(to, &encode_function_call(method_signature, params)?).await
+    }
+
+    pub async fn call_uint(&self, to: &str, method_signature: &str, params: Vec<String>) -> Result<u64> {
+        decode_uint(&self.call_function(to, method_signature, params).await?)
+    }
+
+    pub async fn call_u256(&self, to: &str, method_signature: &str, params: Vec<String>) -> Result<U256> {
+        decode_u256(&self.call_function(to, method_signature, params).await?)
+    }
+
+    pub async fn call_string(&self, to: &str, method_signature: &str, params: Vec<String>) -> Result<String> {
+        decode_string(&self.call_function(to, method_signature, params).await?)
+    }
+
+    pub async fn call_address(&self, to: &str, method_signature: &str, params: Vec<String>) -> Result<String> {
+        decode_address(&self.call_function(to, method_signature, params).await?)
+    }
+
+    pub async fn call_string_array(&self, to: &str, method_signature: &str, params: Vec<String>) -> Result<Vec<String>> {
+        decode_string_array(&self.call_function(to, method_signature, params).await?)
+    }
+}
+
+pub struct ClientBuilder {
+    transport: Arc<dyn Transport>,
+    client: reqwest::Client,
+    layers: Vec<Box<dyn Layer>>,
+}
+
+impl ClientBuilder {
+    pub fn new(rpc_url: &str) -> Self {
+        Self::with_client(reqwest::Client::new(), rpc_url)
+    }
+
+    pub fn with_client(client: reqwest::Client, rpc_url: &str) -> Self {
+        ClientBuilder {
+            transport: Arc::new(HttpTransport::with_client(client.clone(), rpc_url)),
+            client,
+            layers: Vec::new(),
+        }
+    }
+
+    pub fn with_transport(transport: impl Transport + 'static) -> Self {
+        ClientBuilder {
+            transport: Arc::new(transport),
+            client: reqwest::Client::new(),
+            layers: Vec::new(),
+        }
+    }
+
+    // HTTP client for off-chain requests (CCIP-Read gateways, metadata) when the
+    // transport isn't HTTP-based or has its own
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    // The first layer added is the outermost, so it sees each request first
+    pub fn layer(mut self, layer: impl Layer + 'static) -> Self {
+        self.layers.push(Box::new(layer));
+        self
+    }
+
+    pub fn build(self) -> EthClient {
+        let transport = self.layers.iter().rev().fold(self.transport, |inner, layer| layer.layer(inner));
+        EthClient {
+            transport,
+            client: self.client,
+            dialect: Arc::new(OnceCell::new()),
+        }
+    }
+}
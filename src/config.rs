@@ -0,0 +1,361 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::client::{ClientBuilder, EthClient};
+use crate::error::{Error, Result};
+use crate::provider::{FallbackProvider, QuorumProvider};
+use crate::proxy::{Consumer, Quota};
+use crate::transport::{HttpTransport, Transport, WsTransport};
+
+// Environment overrides, applied on top of the selected profile
+pub const PROFILE_ENV: &str = "ETH_RPC_PROFILE";
+pub const URL_ENV: &str = "ETH_RPC_URL";
+pub const CHAIN_ID_ENV: &str = "ETH_CHAIN_ID";
+pub const API_KEY_ENV: &str = "ETH_API_KEY";
+pub const TIMEOUT_ENV: &str = "ETH_RPC_TIMEOUT";
+
+// Named chain profiles, e.g.
+//
+// default_profile = "sepolia"
+//
+// [profiles.sepolia]
+// url = "https://eth-sepolia.g.alchemy.com/v2/{api_key}"
+// chain_id = 11155111
+// api_key = "..."
+// timeout_secs = 10
+// fallback_urls = ["https://sepolia.drpc.org"]
+// ws_url = "wss://eth-sepolia.g.alchemy.com/v2/{api_key}"
+// graphql_url = "http://localhost:8545/graphql"
+//
+// [address_book]
+// treasury = "0x..."
+//
+// [interactions.usdc]
+// address = "0x..."
+// abi = "abis/erc20.json"
+// presets.balance = { function = "balanceOf", args = ["treasury"] }
+// presets.pay = { function = "transfer", args = ["treasury", "1000000"] }
+//
+// [proxy_consumers.dashboard]
+// api_key = "..."
+// quota = 10000
+// quota_period_secs = 86400
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    // Name -> address, used to label addresses and accepted wherever an address is
+    #[serde(default)]
+    pub address_book: BTreeMap<String, String>,
+    // Named contracts with preset calls, run with run-profile
+    #[serde(default)]
+    pub interactions: BTreeMap<String, Interaction>,
+    // API keys accepted by `proxy --listen`, by consumer name
+    #[serde(default)]
+    pub proxy_consumers: BTreeMap<String, ProxyConsumer>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProxyConsumer {
+    pub api_key: String,
+    // Requests allowed per quota_period_secs (default a day); unlimited when unset
+    pub quota: Option<u64>,
+    pub quota_period_secs: Option<u64>,
+}
+
+impl ProxyConsumer {
+    pub fn consumer(&self, name: &str) -> Consumer {
+        Consumer {
+            name: name.to_string(),
+            api_key: self.api_key.clone(),
+            quota: self.quota.map(|requests| Quota {
+                requests,
+                period: Duration::from_secs(self.quota_period_secs.unwrap_or(86_400)),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Interaction {
+    // Address or address book name
+    pub address: String,
+    // ABI JSON or a Foundry/Hardhat artifact; relative to the config file
+    pub abi: PathBuf,
+    #[serde(default)]
+    pub presets: BTreeMap<String, Preset>,
+}
+
+// A function with default arguments, given as text like on the command line. Address
+// book names are accepted for address arguments.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    // Name, or full signature when the name is overloaded
+    pub function: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    // Wei sent with payable functions
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    // May contain {api_key}, replaced with the profile's key
+    pub url: Option<String>,
+    pub chain_id: Option<u64>,
+    pub api_key: Option<String>,
+    pub timeout_secs: Option<u64>,
+    // Tried in order when url fails, through a FallbackProvider
+    #[serde(default)]
+    pub fallback_urls: Vec<String>,
+    // Cross-checks url and fallback_urls through a QuorumProvider instead, requiring
+    // this many to agree
+    pub quorum: Option<usize>,
+    // WebSocket endpoint for subscriptions, e.g. new heads while waiting for receipts;
+    // may contain {api_key}
+    pub ws_url: Option<String>,
+    // EIP-1767 GraphQL endpoint for BlockFetcher, e.g. geth's /graphql; may contain {api_key}
+    pub graphql_url: Option<String>,
+    // ERC-20 contracts included in portfolio reports for this chain
+    #[serde(default)]
+    pub tokens: Vec<String>,
+}
+
+impl Config {
+    // $XDG_CONFIG_HOME/ethrpc/config.toml, falling back to ~/.config/ethrpc/config.toml
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(config_home.join("ethrpc").join("config.toml"))
+    }
+
+    // Reads the default config file; a missing file is an empty config
+    pub fn load() -> Result<Self> {
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::from_file(path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|error| std::io::Error::new(error.kind(), format!("Failed to read {}: {}", path.display(), error)))?;
+        let mut config: Config = toml::from_str(&contents).map_err(|error| Error::Config(format!("Invalid config {}: {}", path.display(), error)))?;
+        if let Some(directory) = path.parent() {
+            for interaction in config.interactions.values_mut() {
+                interaction.abi = directory.join(&interaction.abi);
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self> {
+        toml::from_str(contents).map_err(|error| Error::Config(format!("Invalid config: {}", error)))
+    }
+
+    // Resolves `name`, else ETH_RPC_PROFILE, else default_profile, then applies the
+    // environment overrides. With no profile selected only the environment is used.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile> {
+        let env = |key: &str| std::env::var(key).ok().filter(|value| !value.is_empty());
+        let name = name.map(str::to_string).or_else(|| env(PROFILE_ENV)).or_else(|| self.default_profile.clone());
+        let mut profile = match name {
+            Some(name) => self.profiles.get(&name).cloned().ok_or_else(|| Error::Config(format!("Unknown profile {}", name)))?,
+            None => Profile::default(),
+        };
+        if let Some(url) = env(URL_ENV) {
+            profile.url = Some(url);
+        }
+        if let Some(chain_id) = env(CHAIN_ID_ENV) {
+            profile.chain_id = Some(chain_id.parse().map_err(|_| Error::Config(format!("Invalid {}: {}", CHAIN_ID_ENV, chain_id)))?);
+        }
+        if let Some(api_key) = env(API_KEY_ENV) {
+            profile.api_key = Some(api_key);
+        }
+        if let Some(timeout) = env(TIMEOUT_ENV) {
+            profile.timeout_secs = Some(timeout.parse().map_err(|_| Error::Config(format!("Invalid {}: {}", TIMEOUT_ENV, timeout)))?);
+        }
+        Ok(profile)
+    }
+
+    // An address book name, or the input itself when it isn't one
+    pub fn resolve_address<'a>(&'a self, name_or_address: &'a str) -> &'a str {
+        self.address_book.get(name_or_address).map_or(name_or_address, String::as_str)
+    }
+}
+
+impl Profile {
+    // The URL with {api_key} filled in
+    pub fn rpc_url(&self) -> Result<String> {
+        let url = self.url.as_deref().ok_or_else(|| Error::Config(format!("No RPC URL configured; set {} or a profile url", URL_ENV)))?;
+        self.fill_api_key(url)
+    }
+
+    // url followed by fallback_urls
+    pub fn rpc_urls(&self) -> Result<Vec<String>> {
+        let mut urls = vec![self.rpc_url()?];
+        for url in &self.fallback_urls {
+            urls.push(self.fill_api_key(url)?);
+        }
+        Ok(urls)
+    }
+
+    // Connects to ws_url, if set
+    pub async fn connect_ws(&self) -> Result<Option<WsTransport>> {
+        match &self.ws_url {
+            Some(url) => Ok(Some(WsTransport::connect(&self.fill_api_key(url)?).await?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn graphql_url(&self) -> Result<Option<String>> {
+        self.graphql_url.as_deref().map(|url| self.fill_api_key(url)).transpose()
+    }
+
+    fn fill_api_key(&self, url: &str) -> Result<String> {
+        if !url.contains("{api_key}") {
+            return Ok(url.to_string());
+        }
+        let api_key = self.api_key.as_deref().ok_or_else(|| Error::Config(format!("The RPC URL needs an API key; set {} or a profile api_key", API_KEY_ENV)))?;
+        Ok(url.replace("{api_key}", api_key))
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_secs.map(Duration::from_secs)
+    }
+
+    pub fn client(&self) -> Result<EthClient> {
+        Ok(self.builder()?.build())
+    }
+
+    // The profile's endpoints as a ClientBuilder, for adding middleware layers
+    pub fn builder(&self) -> Result<ClientBuilder> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = self.timeout() {
+            builder = builder.timeout(timeout);
+        }
+        let client = builder.build().map_err(|error| Error::Config(format!("Failed to build the HTTP client: {}", error)))?;
+        let urls = self.rpc_urls()?;
+        if urls.len() == 1 && self.quorum.is_none() {
+            return Ok(ClientBuilder::with_client(client, &urls[0]));
+        }
+
+        let transports: Vec<Arc<dyn Transport>> = urls.iter().map(|url| Arc::new(HttpTransport::with_client(client.clone(), url)) as Arc<dyn Transport>).collect();
+        let builder = match self.quorum {
+            Some(quorum) if quorum == 0 || quorum > transports.len() => {
+                return Err(Error::Config(format!("Quorum {} needs between 1 and {} endpoints", quorum, transports.len())));
+            }
+            Some(quorum) => ClientBuilder::with_transport(QuorumProvider::from_transports(transports).with_quorum(quorum)),
+            None => ClientBuilder::with_transport(FallbackProvider::from_transports(transports)),
+        };
+        Ok(builder.http_client(client))
+    }
+
+    // Like client(), but when the profile sets chain_id the endpoint is asked for its
+    // chain first, so a URL pointing at the wrong network fails before anything runs
+    pub async fn connect(&self) -> Result<EthClient> {
+        self.verify(self.client()?).await
+    }
+
+    // Checks a client built from builder() the way connect() does
+    pub async fn verify(&self, client: EthClient) -> Result<EthClient> {
+        if let Some(chain_id) = self.chain_id {
+            client.verify_chain_id(chain_id).await?;
+        }
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+default_profile = "mainnet"
+
+[profiles.mainnet]
+url = "https://eth.example/v2/{api_key}"
+chain_id = 1
+api_key = "main-key"
+fallback_urls = ["https://backup.example/{api_key}", "https://public.example"]
+
+[profiles.sepolia]
+url = "https://sepolia.example"
+chain_id = 11155111
+
+[address_book]
+treasury = "0x1111111111111111111111111111111111111111"
+"#;
+
+    // The only test touching these variables, since the environment is shared by threads
+    #[test]
+    fn resolves_profiles_with_environment_overrides() {
+        let config = Config::from_toml(CONFIG).unwrap();
+        for key in [PROFILE_ENV, URL_ENV, CHAIN_ID_ENV, API_KEY_ENV, TIMEOUT_ENV] {
+            std::env::remove_var(key);
+        }
+        assert_eq!(config.profile(None).unwrap().chain_id, Some(1));
+        assert_eq!(config.profile(Some("sepolia")).unwrap().chain_id, Some(11155111));
+        assert!(matches!(config.profile(Some("goerli")), Err(Error::Config(_))));
+
+        // The environment picks the profile over default_profile, but not over a name given
+        std::env::set_var(PROFILE_ENV, "sepolia");
+        assert_eq!(config.profile(None).unwrap().chain_id, Some(11155111));
+        assert_eq!(config.profile(Some("mainnet")).unwrap().chain_id, Some(1));
+
+        // and its values override the profile's
+        std::env::set_var(URL_ENV, "http://localhost:8545");
+        std::env::set_var(TIMEOUT_ENV, "5");
+        std::env::set_var(API_KEY_ENV, "env-key");
+        let profile = config.profile(Some("mainnet")).unwrap();
+        assert_eq!((profile.rpc_url().unwrap().as_str(), profile.timeout(), profile.api_key.as_deref()), ("http://localhost:8545", Some(Duration::from_secs(5)), Some("env-key")));
+        std::env::set_var(CHAIN_ID_ENV, "one");
+        assert!(matches!(config.profile(None), Err(Error::Config(message)) if message.contains(CHAIN_ID_ENV)));
+
+        for key in [PROFILE_ENV, URL_ENV, CHAIN_ID_ENV, API_KEY_ENV, TIMEOUT_ENV] {
+            std::env::remove_var(key);
+        }
+        // Without a profile, only the environment counts
+        assert_eq!(Config::default().profile(None).unwrap(), Profile::default());
+    }
+
+    #[test]
+    fn fills_the_api_key_into_every_url() {
+        let config = Config::from_toml(CONFIG).unwrap();
+        let profile = config.profiles["mainnet"].clone();
+        assert_eq!(profile.rpc_urls().unwrap(), ["https://eth.example/v2/main-key", "https://backup.example/main-key", "https://public.example"]);
+        let keyless = Profile { api_key: None, ..profile };
+        assert!(matches!(keyless.rpc_url(), Err(Error::Config(message)) if message.contains(API_KEY_ENV)));
+        assert!(matches!(Profile::default().rpc_url(), Err(Error::Config(_))));
+
+        assert_eq!(config.resolve_address("treasury"), "0x1111111111111111111111111111111111111111");
+        assert_eq!(config.resolve_address("0x22"), "0x22");
+        assert!(matches!(Config::from_toml("[profiles.x]\nurll = \"typo\""), Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn bounds_the_quorum_by_the_endpoints() {
+        let profile = Profile {
+            url: Some("http://a.example".to_string()),
+            fallback_urls: vec!["http://b.example".to_string()],
+            ..Profile::default()
+        };
+        assert!(profile.builder().is_ok());
+        for quorum in [0, 3] {
+            let profile = Profile { quorum: Some(quorum), ..profile.clone() };
+            assert!(matches!(profile.builder(), Err(Error::Config(message)) if message.contains("between 1 and 2")));
+        }
+        assert!(Profile { quorum: Some(2), ..profile }.builder().is_ok());
+    }
+}
@@ -0,0 +1,158 @@
+use std::fmt;
+use std::sync::Arc;
+
+use ethabi::ethereum_types::U256;
+use ethabi::Contract;
+
+use crate::abi::{signature_params, token_to_json};
+use crate::format::{self, TokenAmount};
+use crate::transaction::TypedTransaction;
+
+// Functions decoded without an ABI
+pub const KNOWN_FUNCTIONS: &[&str] = &[
+    "transfer(address,uint256)",
+    "approve(address,uint256)",
+    "transferFrom(address,address,uint256)",
+    "safeTransferFrom(address,address,uint256)",
+    "setApprovalForAll(address,bool)",
+    "deposit()",
+    "withdraw(uint256)",
+];
+
+// What a transaction will do, for a human to check before it is signed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionSummary {
+    pub chain_id: Option<u64>,
+    pub from: String,
+    // None for contract creation
+    pub to: Option<String>,
+    pub nonce: Option<u64>,
+    pub value: U256,
+    // Signature when recognised, otherwise the raw selector; None for plain transfers
+    pub function: Option<String>,
+    pub args: Vec<String>,
+    pub gas_limit: u64,
+    pub max_fee_per_gas: U256,
+}
+
+impl TransactionSummary {
+    // Calldata is decoded against `abi` when given, then against KNOWN_FUNCTIONS
+    pub fn new(from: &str, transaction: &TypedTransaction, abi: Option<&Contract>) -> Self {
+        let (function, args) = describe_call(transaction.data(), transaction.to().is_none(), abi);
+        TransactionSummary {
+            chain_id: transaction.chain_id(),
+            from: from.to_string(),
+            to: transaction.to().map(|to| format!("{:?}", to)),
+            nonce: transaction.nonce(),
+            value: transaction.value(),
+            function,
+            args,
+            gas_limit: transaction.gas_limit(),
+            max_fee_per_gas: transaction.max_fee_per_gas(),
+        }
+    }
+
+    // Most the transaction can cost in gas
+    pub fn max_fee(&self) -> U256 {
+        self.max_fee_per_gas.saturating_mul(U256::from(self.gas_limit))
+    }
+}
+
+impl fmt::Display for TransactionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(chain_id) = self.chain_id {
+            writeln!(f, "  chain     {}", chain_id)?;
+        }
+        writeln!(f, "  from      {}", format::address(&self.from))?;
+        match &self.to {
+            Some(to) => writeln!(f, "  to        {}", format::address(to))?,
+            None => writeln!(f, "  to        (contract creation)")?,
+        }
+        if let Some(nonce) = self.nonce {
+            writeln!(f, "  nonce     {}", nonce)?;
+        }
+        if let Some(function) = &self.function {
+            writeln!(f, "  function  {}", function)?;
+            for arg in &self.args {
+                writeln!(f, "            {}", arg)?;
+            }
+        }
+        writeln!(f, "  value     {}", TokenAmount::ether(self.value))?;
+        write!(f, "  max fee   {} ({} gas at {} gwei)", TokenAmount::ether(self.max_fee()), self.gas_limit, format::number(self.max_fee_per_gas, 9))
+    }
+}
+
+// Asked before every signature; false declines the transaction
+pub type ConfirmFn = Arc<dyn Fn(&TransactionSummary) -> bool + Send + Sync>;
+
+fn describe_call(data: &[u8], creation: bool, abi: Option<&Contract>) -> (Option<String>, Vec<String>) {
+    if creation {
+        return ((!data.is_empty()).then(|| format!("deploy ({} bytes of init code)", data.len())), Vec::new());
+    }
+    let Some(selector) = data.get(..4) else {
+        return (None, Vec::new());
+    };
+    let from_abi = abi.into_iter().flat_map(|abi| abi.functions()).find(|function| function.short_signature() == selector).and_then(|function| {
+        let tokens = function.decode_input(&data[4..]).ok()?;
+        let args = function.inputs.iter().zip(&tokens).map(|(input, token)| format!("{}: {}", input.name, token_text(token))).collect();
+        Some((function.signature(), args))
+    });
+    if let Some((signature, args)) = from_abi {
+        return (Some(signature), args);
+    }
+    for signature in KNOWN_FUNCTIONS {
+        if keccak_hash::keccak(signature.as_bytes())[..4] != *selector {
+            continue;
+        }
+        let Ok(types) = signature_params(signature) else { continue };
+        if let Ok(tokens) = ethabi::decode(&types, &data[4..]) {
+            return (Some(signature.to_string()), tokens.iter().map(token_text).collect());
+        }
+    }
+    (Some(format!("0x{} (unknown, {} bytes of calldata)", hex::encode(selector), data.len())), Vec::new())
+}
+
+fn token_text(token: &ethabi::Token) -> String {
+    match token_to_json(token) {
+        serde_json::Value::String(text) => text,
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::encode_call;
+    use crate::transaction::Eip1559Transaction;
+
+    #[test]
+    fn summarizes_known_calls() {
+        let data = encode_call("transfer(address,uint256)", &[ethabi::Token::Address("0x3535353535353535353535353535353535353535".parse().unwrap()), ethabi::Token::Uint(U256::from(1_500_000))]);
+        let transaction = TypedTransaction::Eip1559(Eip1559Transaction {
+            chain_id: 1,
+            nonce: 4,
+            max_priority_fee_per_gas: U256::from(1_000_000_000u64),
+            max_fee_per_gas: U256::from(21_000_000_000u64),
+            gas_limit: 50_000,
+            to: Some("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".parse().unwrap()),
+            value: U256::zero(),
+            data: hex::decode(&data[2..]).unwrap(),
+            access_list: Vec::new(),
+            y_parity: false,
+            r: U256::zero(),
+            s: U256::zero(),
+        });
+        let summary = TransactionSummary::new("0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f", &transaction, None);
+        assert_eq!(summary.function.as_deref(), Some("transfer(address,uint256)"));
+        assert_eq!(summary.args, ["0x3535353535353535353535353535353535353535", "1500000"]);
+        assert_eq!(summary.max_fee(), U256::from(1_050_000_000_000_000u64));
+        let text = summary.to_string();
+        assert!(text.contains("nonce     4"), "{}", text);
+        assert!(text.contains("(50000 gas at 21 gwei)"), "{}", text);
+
+        let TypedTransaction::Eip1559(mut unknown) = transaction else { unreachable!() };
+        unknown.data = vec![0xde, 0xad, 0xbe, 0xef, 0];
+        let summary = TransactionSummary::new("0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f", &TypedTransaction::Eip1559(unknown), None);
+        assert_eq!(summary.function.as_deref(), Some("0xdeadbeef (unknown, 5 bytes of calldata)"));
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::middleware::Layer;
+use crate::transport::Transport;
+
+tokio::task_local! {
+    static CONTEXT: RequestContext;
+}
+
+// Attribution for RPC traffic: the job and tags show up on the `rpc` tracing span and
+// as metrics labels, and the headers go out with every HTTP request
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestContext {
+    pub job: Option<String>,
+    pub tags: BTreeMap<String, String>,
+    pub headers: BTreeMap<String, String>,
+}
+
+impl RequestContext {
+    pub fn new(job: &str) -> Self {
+        RequestContext {
+            job: Some(job.to_string()),
+            ..RequestContext::default()
+        }
+    }
+
+    pub fn with_tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    // The context of the enclosing scope, if any
+    pub fn current() -> Option<RequestContext> {
+        CONTEXT.try_with(RequestContext::clone).ok()
+    }
+
+    // Runs `future` with this context, layered over any enclosing one: the job is
+    // replaced when set, tags and headers are merged with these taking precedence
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        let context = match RequestContext::current() {
+            Some(outer) => outer.merge(self),
+            None => self,
+        };
+        CONTEXT.scope(context, future).await
+    }
+
+    fn merge(mut self, inner: RequestContext) -> Self {
+        if inner.job.is_some() {
+            self.job = inner.job;
+        }
+        self.tags.extend(inner.tags);
+        self.headers.extend(inner.headers);
+        self
+    }
+}
+
+// Tags as key=value pairs, for logs
+impl fmt::Display for RequestContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (key, value)) in self.tags.iter().enumerate() {
+            if index > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+// Gives every request through the client this context. A scope entered with
+// RequestContext::scope is layered on top, so a per-task job or tag wins over the
+// client's. Place it outside the metrics layer so the labels see it.
+#[derive(Debug, Clone)]
+pub struct ContextLayer {
+    pub context: RequestContext,
+}
+
+impl ContextLayer {
+    pub fn new(context: RequestContext) -> Self {
+        ContextLayer { context }
+    }
+}
+
+impl Layer for ContextLayer {
+    fn layer(&self, inner: Arc<dyn Transport>) -> Arc<dyn Transport> {
+        Arc::new(Scoped {
+            inner,
+            context: self.context.clone(),
+        })
+    }
+}
+
+struct Scoped {
+    inner: Arc<dyn Transport>,
+    context: RequestContext,
+}
+
+#[async_trait]
+impl Transport for Scoped {
+    async fn request(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        let context = match RequestContext::current() {
+            Some(task) => self.context.clone().merge(task),
+            None => self.context.clone(),
+        };
+        CONTEXT.scope(context, self.inner.request(method, params)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::EthClient;
+    use crate::testing::MockNode;
+
+    // Echoes the context each request arrives with
+    fn echo() -> EthClient {
+        MockNode::new(|_, _| {
+            let context = RequestContext::current().unwrap_or_default();
+            Ok(serde_json::json!({"job": context.job, "tags": context.to_string(), "headers": context.headers}))
+        })
+        .client()
+    }
+
+    #[tokio::test]
+    async fn task_scope_layers_over_the_client_context() {
+        let client = echo().with_context(RequestContext::new("indexer").with_tag("tenant", "acme").with_header("x-tenant", "acme"));
+        let plain = client.send("eth_blockNumber", Vec::new()).await.unwrap();
+        assert_eq!(plain, serde_json::json!({"job": "indexer", "tags": "tenant=acme", "headers": {"x-tenant": "acme"}}));
+
+        let scoped = RequestContext::new("backfill").with_tag("shard", "3").scope(client.send("eth_blockNumber", Vec::new())).await.unwrap();
+        assert_eq!(scoped, serde_json::json!({"job": "backfill", "tags": "shard=3,tenant=acme", "headers": {"x-tenant": "acme"}}));
+        assert!(RequestContext::current().is_none());
+    }
+}
@@ -0,0 +1,215 @@
+use futures_util::future::try_join_all;
+
+use crate::blocks::BlockTag;
+use crate::bloom::Bloom;
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::logs::{Filter, Log};
+use crate::progress::{ProgressFn, ProgressTracker};
+use crate::sink::Sink;
+
+pub const DEFAULT_CHUNK_SIZE: u64 = 2_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CrawlStats {
+    pub from_block: u64,
+    pub to_block: u64,
+    pub logs: u64,
+    pub requests: u64,
+    // Blocks whose logs were never fetched because their bloom ruled them out
+    pub screened_out: u64,
+}
+
+// Scans a block range for logs matching a filter, chunk by chunk, streaming each chunk
+// into a sink. A chunk the node refuses (too many results, range too wide) is split
+// in half and retried, then the chunk size grows back after successes.
+pub struct Crawler {
+    client: EthClient,
+    filter: Filter,
+    chunk_size: u64,
+    bloom_screening: bool,
+    progress: Option<ProgressFn>,
+}
+
+impl Crawler {
+    // The filter's block or time range is the scan range: from genesis and up to the
+    // current head when unset
+    pub fn new(client: &EthClient, filter: Filter) -> Self {
+        Crawler {
+            client: client.clone(),
+            filter,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            bloom_screening: false,
+            progress: None,
+        }
+    }
+
+    // Checks each block header's logsBloom first and only asks for the logs of blocks
+    // that might match. Worth it for sparse events on providers that limit or charge
+    // heavily for eth_getLogs, since headers are cheap and cacheable.
+    pub fn with_bloom_screening(mut self, bloom_screening: bool) -> Self {
+        self.bloom_screening = bloom_screening;
+        self
+    }
+
+    // Called after each chunk with the blocks scanned out of the range
+    pub fn with_progress(mut self, progress: ProgressFn) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: u64) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    async fn block_number(&self, block: BlockTag) -> Result<u64> {
+        match block {
+            BlockTag::Number(number) => Ok(number),
+            BlockTag::Earliest => Ok(0),
+            BlockTag::Latest => self.client.get_block_number().await,
+            tag => Err(Error::InvalidInput(format!("Crawl ranges need block numbers, not {}", tag))),
+        }
+    }
+
+    pub async fn run(&self, sink: &mut dyn Sink) -> Result<CrawlStats> {
+        let filter = self.filter.resolve(&self.client).await?;
+        let from_block = self.block_number(filter.from_block.unwrap_or(BlockTag::Earliest)).await?;
+        let to_block = self.block_number(filter.to_block.unwrap_or(BlockTag::Latest)).await?;
+        let mut stats = CrawlStats {
+            from_block,
+            to_block,
+            ..CrawlStats::default()
+        };
+
+        let tracker = ProgressTracker::new(Some((to_block + 1).saturating_sub(from_block)), self.progress.clone());
+        let mut chunk_size = self.chunk_size;
+        let mut next = from_block;
+        while next <= to_block {
+            let end = next.saturating_add(chunk_size - 1).min(to_block);
+            let logs = if self.bloom_screening { self.screened_logs(&filter, next, end, &mut stats).await } else { self.range_logs(&filter, next, end, &mut stats).await };
+            let logs = match logs {
+                Ok(logs) => logs,
+                Err(Error::Rpc(_)) if end > next => {
+                    chunk_size = (end - next).div_ceil(2);
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+            for log in &logs {
+                sink.write(&serde_json::to_value(log).map_err(|error| Error::Decode(error.to_string()))?).await?;
+            }
+            sink.flush().await?;
+            stats.logs += logs.len() as u64;
+            tracker.report(end + 1 - from_block, Some(end));
+            next = end + 1;
+            chunk_size = (chunk_size * 2).min(self.chunk_size);
+        }
+        Ok(stats)
+    }
+
+    async fn range_logs(&self, filter: &Filter, from_block: u64, to_block: u64, stats: &mut CrawlStats) -> Result<Vec<Log>> {
+        stats.requests += 1;
+        self.client.get_logs(&filter.clone().with_from_block(from_block).with_to_block(to_block)).await
+    }
+
+    async fn screened_logs(&self, filter: &Filter, from_block: u64, to_block: u64, stats: &mut CrawlStats) -> Result<Vec<Log>> {
+        let blooms = try_join_all((from_block..=to_block).map(|number| async move {
+            let block = self.client.send("eth_getBlockByNumber", vec![BlockTag::Number(number).to_json(), false.into()]).await?;
+            let bloom = block["logsBloom"].as_str().ok_or_else(|| Error::Decode(format!("Block {} has no logsBloom", number)))?;
+            Ok::<_, Error>((number, Bloom::from_hex(bloom)?))
+        }))
+        .await?;
+        stats.requests += blooms.len() as u64;
+
+        let mut logs = Vec::new();
+        for (number, bloom) in blooms {
+            if !filter.might_match(&bloom) {
+                stats.screened_out += 1;
+                continue;
+            }
+            logs.extend(self.range_logs(filter, number, number, stats).await?);
+        }
+        Ok(logs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethabi::ethereum_types::H256;
+
+    use super::*;
+    use crate::sink::NdjsonSink;
+    use crate::testing::{Failure, MockNode};
+
+    // One matching log per block; refuses ranges over 100 blocks like many providers
+    fn node() -> MockNode {
+        MockNode::new(|method, params| {
+            let block = |field: &str| u64::from_str_radix(params[0][field].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+            match method {
+                "eth_blockNumber" => Ok(serde_json::json!("0xfa")),
+                "eth_getLogs" if block("toBlock") - block("fromBlock") >= 100 => Err(Failure::rpc(-32005, "query returned more than 10000 results")),
+                "eth_getLogs" => Ok((block("fromBlock")..=block("toBlock"))
+                    .map(|number| serde_json::json!({"address": "0x1111111111111111111111111111111111111111", "topics": [H256::zero()], "data": "0x", "blockNumber": format!("0x{:x}", number)}))
+                    .collect()),
+                _ => Err(Failure::unexpected(method)),
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn streams_every_block_to_disk_splitting_refused_ranges() {
+        let path = std::env::temp_dir().join(format!("evm-json-rpc-crawl-{}.ndjson", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let (progress, mut updates) = crate::progress::channel();
+        let crawler = Crawler::new(&node().client(), Filter::new().with_from_block(10)).with_chunk_size(500).with_progress(progress);
+
+        let mut sink = NdjsonSink::create(&path).unwrap();
+        let stats = crawler.run(&mut sink).await.unwrap();
+        assert_eq!((stats.from_block, stats.to_block, stats.logs), (10, 250, 241));
+        let mut last = None;
+        while let Ok(update) = updates.try_recv() {
+            last = Some(update);
+        }
+        let last = last.unwrap();
+        assert_eq!((last.done, last.total, last.block), (241, Some(241), Some(250)));
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let blocks: Vec<u64> = contents.lines().map(|line| serde_json::from_str::<crate::logs::Log>(line).unwrap().block_number.unwrap().as_u64()).collect();
+        assert_eq!(blocks, (10..=250).collect::<Vec<_>>());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    // Only every tenth block's bloom has the watched address
+    fn sparse() -> MockNode {
+        MockNode::new(|method, params| {
+            let address = "0x1111111111111111111111111111111111111111";
+            match method {
+                "eth_getBlockByNumber" => {
+                    let number = u64::from_str_radix(params[0].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+                    let mut bloom = Bloom::empty();
+                    if number.is_multiple_of(10) {
+                        bloom.accrue(&hex::decode(&address[2..]).unwrap());
+                    }
+                    Ok(serde_json::json!({"number": params[0], "logsBloom": bloom}))
+                }
+                "eth_getLogs" => Ok(serde_json::json!([{"address": address, "topics": [], "data": "0x", "blockNumber": params[0]["fromBlock"]}])),
+                _ => Err(Failure::unexpected(method)),
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn bloom_screening_skips_blocks_without_matches() {
+        let node = sparse();
+        let filter = Filter::new().with_address("0x1111111111111111111111111111111111111111").with_from_block(1).with_to_block(50);
+        let crawler = Crawler::new(&node.client(), filter).with_chunk_size(20).with_bloom_screening(true);
+
+        let mut sink = Vec::new();
+        let stats = crawler.run(&mut sink).await.unwrap();
+        assert_eq!((stats.logs, stats.screened_out), (5, 45));
+        assert_eq!(node.params_of("eth_getLogs").len(), 5);
+        let blocks: Vec<&str> = sink.iter().map(|log| log["blockNumber"].as_str().unwrap()).collect();
+        assert_eq!(blocks, ["0xa", "0x14", "0x1e", "0x28", "0x32"]);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+
+// JSON-RPC error code for an unknown method
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Evm,
+    Starknet,
+    Solana,
+    Unknown,
+}
+
+// Identifies the endpoint's JSON-RPC dialect from which probe methods it answers.
+// EthClient::dialect caches the answer; this probes every time.
+pub async fn detect_dialect(client: &EthClient) -> Result<Dialect> {
+    if answers(client, "eth_chainId").await? {
+        return Ok(Dialect::Evm);
+    }
+    if answers(client, "starknet_chainId").await? {
+        return Ok(Dialect::Starknet);
+    }
+    let version = client.send_raw("getVersion", vec![]).await?;
+    if version["result"].get("solana-core").is_some() {
+        return Ok(Dialect::Solana);
+    }
+    Ok(Dialect::Unknown)
+}
+
+// Fails with a clear error when the endpoint is a known non-EVM chain
+pub async fn ensure_evm(client: &EthClient) -> Result<()> {
+    check_evm(client.dialect().await?)
+}
+
+pub fn check_evm(dialect: Dialect) -> Result<()> {
+    match dialect {
+        Dialect::Starknet => Err(Error::Unsupported("the endpoint speaks Starknet JSON-RPC, not Ethereum JSON-RPC".to_string())),
+        Dialect::Solana => Err(Error::Unsupported("the endpoint speaks Solana JSON-RPC, not Ethereum JSON-RPC".to_string())),
+        Dialect::Evm | Dialect::Unknown => Ok(()),
+    }
+}
+
+async fn answers(client: &EthClient, method: &str) -> Result<bool> {
+    let response = client.send_raw(method, vec![]).await?;
+    Ok(response.get("result").is_some_and(|result| !result.is_null()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Failure, MockNode};
+
+    // An endpoint answering only the given methods, as a non-EVM node would
+    fn endpoint(answers: &'static [(&'static str, &'static str)]) -> EthClient {
+        MockNode::new(move |method, _| match answers.iter().find(|(name, _)| *name == method) {
+            Some((_, result)) => Ok(serde_json::from_str(result).unwrap()),
+            None => Err(Failure::rpc(METHOD_NOT_FOUND, "Method not found")),
+        })
+        .client()
+    }
+
+    #[tokio::test]
+    async fn tells_evm_from_other_chains() {
+        assert_eq!(detect_dialect(&endpoint(&[("eth_chainId", "\"0x1\"")])).await.unwrap(), Dialect::Evm);
+        assert_eq!(detect_dialect(&endpoint(&[("starknet_chainId", "\"0x534e5f4d41494e\"")])).await.unwrap(), Dialect::Starknet);
+        assert_eq!(detect_dialect(&endpoint(&[("getVersion", r#"{"solana-core": "1.18.0"}"#)])).await.unwrap(), Dialect::Solana);
+        assert_eq!(detect_dialect(&endpoint(&[])).await.unwrap(), Dialect::Unknown);
+
+        // A missing eth_ method on a Starknet node says so, and ensure_evm refuses it
+        let starknet = endpoint(&[("starknet_chainId", "\"0x534e5f4d41494e\"")]);
+        let error = starknet.send("eth_blockNumber", Vec::new()).await.unwrap_err();
+        assert!(error.to_string().contains("Starknet"), "{}", error);
+        assert!(matches!(ensure_evm(&starknet).await, Err(Error::Unsupported(_))));
+        assert!(ensure_evm(&endpoint(&[])).await.is_ok());
+    }
+}
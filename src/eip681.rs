@@ -0,0 +1,302 @@
+use std::fmt;
+use std::str::FromStr;
+
+use ethabi::ethereum_types::{U256, U64};
+use ethabi::token::{LenientTokenizer, Tokenizer};
+use ethabi::{ParamType, Token};
+
+use crate::error::{Error, Result};
+use crate::transaction::TransactionRequest;
+
+// EIP-681 payment request, e.g. ethereum:0xToken@1/transfer?address=0xRecipient&uint256=1e6
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaymentRequest {
+    // Address or ENS name
+    pub target: String,
+    pub chain_id: Option<u64>,
+    pub function: Option<String>,
+    // (solidity type, value) pairs for the function call
+    pub params: Vec<(String, String)>,
+    pub value: Option<U256>,
+    pub gas_limit: Option<U256>,
+    pub gas_price: Option<U256>,
+}
+
+impl PaymentRequest {
+    pub fn payment(to: &str, value: U256, chain_id: Option<u64>) -> Self {
+        PaymentRequest {
+            target: to.to_string(),
+            chain_id,
+            value: Some(value),
+            ..Default::default()
+        }
+    }
+
+    pub fn function_call(contract: &str, chain_id: Option<u64>, function: &str, params: Vec<(String, String)>) -> Self {
+        PaymentRequest {
+            target: contract.to_string(),
+            chain_id,
+            function: Some(function.to_string()),
+            params,
+            ..Default::default()
+        }
+    }
+
+    // Plain value transfers only: calldata can't be turned back into a function name
+    // and typed params, so build those with function_call
+    pub fn from_transaction_request(transaction: &TransactionRequest) -> Result<Self> {
+        if transaction.data.as_deref().is_some_and(|data| !data.trim_start_matches("0x").is_empty()) {
+            return Err(Error::InvalidInput("Transactions with calldata need PaymentRequest::function_call".to_string()));
+        }
+        Ok(PaymentRequest {
+            target: transaction.to.clone().ok_or_else(|| Error::InvalidInput("Payment requests need a recipient".to_string()))?,
+            chain_id: transaction.chain_id.map(|chain_id| chain_id.as_u64()),
+            value: transaction.value,
+            gas_limit: transaction.gas.map(|gas| U256::from(gas.as_u64())),
+            gas_price: transaction.gas_price,
+            ..Default::default()
+        })
+    }
+
+    pub fn to_transaction_request(&self) -> Result<TransactionRequest> {
+        if !(self.target.starts_with("0x") && self.target.len() == 42) {
+            return Err(Error::InvalidInput(format!("Payment target {} must be resolved to an address first", self.target)));
+        }
+
+        let data = match &self.function {
+            Some(function) => {
+                let mut types = Vec::new();
+                let mut tokens = Vec::new();
+                for (kind, value) in &self.params {
+                    let param_type = ethabi::param_type::Reader::read(kind).map_err(|error| Error::Abi(format!("Invalid parameter type {}: {}", kind, error)))?;
+                    tokens.push(parse_token(&param_type, value)?);
+                    types.push(kind.as_str());
+                }
+                let signature = format!("{}({})", function, types.join(","));
+                let selector = &keccak_hash::keccak(signature.as_bytes())[..4];
+                Some(format!("0x{}{}", hex::encode(selector), hex::encode(ethabi::encode(&tokens))))
+            }
+            None => None,
+        };
+
+        let gas = match self.gas_limit {
+            Some(gas_limit) if gas_limit > U256::from(u64::MAX) => return Err(Error::InvalidInput("Gas limit does not fit in 64 bits".to_string())),
+            gas_limit => gas_limit.map(|gas_limit| U64::from(gas_limit.low_u64())),
+        };
+        Ok(TransactionRequest {
+            to: Some(self.target.clone()),
+            value: self.value,
+            data,
+            gas,
+            gas_price: self.gas_price,
+            chain_id: self.chain_id.map(U64::from),
+            ..Default::default()
+        })
+    }
+}
+
+impl fmt::Display for PaymentRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ethereum:{}", self.target)?;
+        if let Some(chain_id) = self.chain_id {
+            write!(f, "@{}", chain_id)?;
+        }
+        if let Some(function) = &self.function {
+            write!(f, "/{}", function)?;
+        }
+
+        let mut query: Vec<(&str, String)> = self.params.iter().map(|(kind, value)| (kind.as_str(), percent_encode(value))).collect();
+        if let Some(value) = self.value {
+            query.push(("value", value.to_string()));
+        }
+        if let Some(gas_limit) = self.gas_limit {
+            query.push(("gasLimit", gas_limit.to_string()));
+        }
+        if let Some(gas_price) = self.gas_price {
+            query.push(("gasPrice", gas_price.to_string()));
+        }
+        for (index, (key, value)) in query.iter().enumerate() {
+            write!(f, "{}{}={}", if index == 0 { "?" } else { "&" }, key, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PaymentRequest {
+    type Err = Error;
+
+    fn from_str(uri: &str) -> Result<Self> {
+        let rest = uri.strip_prefix("ethereum:").ok_or_else(|| Error::Decode("Payment URIs must start with ethereum:".to_string()))?;
+        let rest = rest.strip_prefix("pay-").unwrap_or(rest);
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let (target, function) = match path.split_once('/') {
+            Some((target, function)) => (target, Some(function.to_string())),
+            None => (path, None),
+        };
+        let (target, chain_id) = match target.split_once('@') {
+            Some((target, chain_id)) => (target, Some(chain_id.parse().map_err(|_| Error::Decode(format!("Invalid chain id {}", chain_id)))?)),
+            None => (target, None),
+        };
+        if target.is_empty() {
+            return Err(Error::Decode("Payment URI has no target address".to_string()));
+        }
+
+        let mut request = PaymentRequest {
+            target: target.to_string(),
+            chain_id,
+            function,
+            ..Default::default()
+        };
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').ok_or_else(|| Error::Decode(format!("Invalid payment URI parameter {}", pair)))?;
+            match key {
+                "value" => request.value = Some(parse_number(value)?),
+                "gasLimit" | "gas" => request.gas_limit = Some(parse_number(value)?),
+                "gasPrice" => request.gas_price = Some(parse_number(value)?),
+                kind => request.params.push((kind.to_string(), percent_decode(value)?)),
+            }
+        }
+        Ok(request)
+    }
+}
+
+// EIP-681 numbers allow scientific notation such as 2.014e18
+pub fn parse_number(number: &str) -> Result<U256> {
+    let invalid = || Error::Decode(format!("Invalid number {}", number));
+    let unsigned = number.strip_prefix('+').unwrap_or(number);
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, if exponent.is_empty() { 0 } else { exponent.parse::<usize>().map_err(|_| invalid())? }),
+        None => (unsigned, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", whole, fraction);
+    if whole.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    // Fractional digits beyond the exponent must be zero
+    let digits = match exponent.checked_sub(fraction.len()) {
+        Some(zeros) => format!("{}{}", digits, "0".repeat(zeros)),
+        None => {
+            let (kept, dropped) = digits.split_at(digits.len() - (fraction.len() - exponent));
+            if dropped.bytes().any(|byte| byte != b'0') {
+                return Err(Error::Decode(format!("{} is not a whole number", number)));
+            }
+            kept.to_string()
+        }
+    };
+    U256::from_dec_str(&digits).map_err(|_| invalid())
+}
+
+fn parse_token(param_type: &ParamType, value: &str) -> Result<Token> {
+    Ok(match param_type {
+        ParamType::Uint(_) => Token::Uint(parse_number(value)?),
+        _ => LenientTokenizer::tokenize(param_type, value).map_err(|error| Error::Decode(format!("Invalid {} value {}: {}", param_type, value, error)))?,
+    })
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn percent_decode(value: &str) -> Result<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let escape = value.get(index + 1..index + 3).ok_or_else(|| Error::Decode(format!("Truncated percent escape in {}", value)))?;
+            decoded.push(u8::from_str_radix(escape, 16).map_err(|_| Error::Decode(format!("Invalid percent escape %{}", escape)))?);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| Error::Decode(format!("Parameter {} is not valid UTF-8", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_eip_examples() {
+        let payment: PaymentRequest = "ethereum:0xfb6916095ca1df60bb79Ce92cE3Ea74c37c5d359?value=2.014e18".parse().unwrap();
+        assert_eq!(payment.target, "0xfb6916095ca1df60bb79Ce92cE3Ea74c37c5d359");
+        assert_eq!(payment.value, Some(U256::from(2_014_000_000_000_000_000u64)));
+        assert_eq!(payment.function, None);
+
+        let transfer: PaymentRequest = "ethereum:0x89205a3a3b2a69de6dbf7f01ed13b2108b2c43e7/transfer?address=0x8e23ee67d1332ad560396262c48ffbb01f93d052&uint256=1".parse().unwrap();
+        assert_eq!(transfer.function.as_deref(), Some("transfer"));
+        assert_eq!(
+            transfer.params,
+            vec![("address".to_string(), "0x8e23ee67d1332ad560396262c48ffbb01f93d052".to_string()), ("uint256".to_string(), "1".to_string())]
+        );
+        let request = transfer.to_transaction_request().unwrap();
+        assert_eq!(request.to.as_deref(), Some("0x89205a3a3b2a69de6dbf7f01ed13b2108b2c43e7"));
+        assert_eq!(
+            request.data.as_deref(),
+            Some("0xa9059cbb0000000000000000000000008e23ee67d1332ad560396262c48ffbb01f93d0520000000000000000000000000000000000000000000000000000000000000001")
+        );
+    }
+
+    #[test]
+    fn parses_chain_ids_prefixes_and_gas() {
+        let request: PaymentRequest = "ethereum:pay-0xfb6916095ca1df60bb79Ce92cE3Ea74c37c5d359@137?value=1e15&gas=21000&gasPrice=3e10".parse().unwrap();
+        assert_eq!(request.chain_id, Some(137));
+        assert_eq!(request.gas_limit, Some(U256::from(21_000)));
+        assert_eq!(request.gas_price, Some(U256::from(30_000_000_000u64)));
+        let transaction = request.to_transaction_request().unwrap();
+        assert_eq!(transaction.chain_id, Some(U64::from(137)));
+        assert_eq!(transaction.gas, Some(U64::from(21_000)));
+        assert_eq!(transaction.data, None);
+    }
+
+    #[test]
+    fn round_trips_through_display() {
+        let request = PaymentRequest::function_call(
+            "0x89205a3a3b2a69de6dbf7f01ed13b2108b2c43e7",
+            Some(1),
+            "setName",
+            vec![("string".to_string(), "hello world & more".to_string())],
+        );
+        let uri = request.to_string();
+        assert_eq!(uri, "ethereum:0x89205a3a3b2a69de6dbf7f01ed13b2108b2c43e7@1/setName?string=hello%20world%20%26%20more");
+        assert_eq!(uri.parse::<PaymentRequest>().unwrap(), request);
+
+        let payment = PaymentRequest::payment("0xfb6916095ca1df60bb79Ce92cE3Ea74c37c5d359", U256::exp10(18), None);
+        assert_eq!(payment.to_string(), "ethereum:0xfb6916095ca1df60bb79Ce92cE3Ea74c37c5d359?value=1000000000000000000");
+        assert_eq!(PaymentRequest::from_transaction_request(&payment.to_transaction_request().unwrap()).unwrap(), payment);
+    }
+
+    #[test]
+    fn parses_scientific_numbers() {
+        assert_eq!(parse_number("1e18").unwrap(), U256::exp10(18));
+        assert_eq!(parse_number("+5").unwrap(), U256::from(5));
+        assert_eq!(parse_number("1.50e1").unwrap(), U256::from(15));
+        assert_eq!(parse_number("0.5e1").unwrap(), U256::from(5));
+        assert_eq!(parse_number("42").unwrap(), U256::from(42));
+        assert!(parse_number("1.5").is_err());
+        assert!(parse_number("0.05e1").is_err());
+        assert!(parse_number("-1").is_err());
+        assert!(parse_number("e5").is_err());
+        assert!(parse_number("0x10").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_uris_and_requests() {
+        assert!("bitcoin:0xfb6916095ca1df60bb79Ce92cE3Ea74c37c5d359".parse::<PaymentRequest>().is_err());
+        assert!("ethereum:?value=1".parse::<PaymentRequest>().is_err());
+        assert!("ethereum:0xfb6916095ca1df60bb79Ce92cE3Ea74c37c5d359@mainnet".parse::<PaymentRequest>().is_err());
+        assert!("ethereum:0xfb6916095ca1df60bb79Ce92cE3Ea74c37c5d359?string=%zz".parse::<PaymentRequest>().is_err());
+        // ENS names must be resolved before building a transaction
+        assert!("ethereum:vitalik.eth?value=1".parse::<PaymentRequest>().unwrap().to_transaction_request().is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::{BTreeSet, HashMap};
+
+use ethabi::ethereum_types::{H256, U256};
+use ethabi::Event;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use serde::Serialize;
+
+use crate::abi::token_to_json;
+use crate::blocks::BlockTag;
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::headers::get_header;
+use crate::logs::Log;
+use crate::transaction::TransactionReceipt;
+
+// Blocks and receipts fetched at once while enriching
+pub const DEFAULT_CONCURRENCY: usize = 16;
+
+// A decoded event joined with its block and transaction. The joined fields stay None
+// until `enrich` fills them in.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventRecord {
+    pub event: String,
+    pub address: String,
+    pub block_number: u64,
+    pub transaction_hash: H256,
+    pub log_index: u64,
+    pub params: serde_json::Map<String, serde_json::Value>,
+    pub timestamp: Option<u64>,
+    pub from: Option<String>,
+    pub gas_used: Option<U256>,
+    pub effective_gas_price: Option<U256>,
+}
+
+impl EventRecord {
+    // Only mined logs can be joined, so pending ones are rejected
+    pub fn decode(event: &Event, log: &Log) -> Result<Self> {
+        let pending = || Error::Decode("Log is pending, it has no block or transaction".to_string());
+        let parsed = event.parse_log(log.raw_log()?).map_err(|error| Error::Abi(error.to_string()))?;
+        Ok(EventRecord {
+            event: event.name.clone(),
+            address: log.address.clone(),
+            block_number: log.block_number.ok_or_else(pending)?.as_u64(),
+            transaction_hash: log.transaction_hash.ok_or_else(pending)?,
+            log_index: log.log_index.ok_or_else(pending)?.as_u64(),
+            params: parsed.params.iter().map(|param| (param.name.clone(), token_to_json(&param.value))).collect(),
+            timestamp: None,
+            from: None,
+            gas_used: None,
+            effective_gas_price: None,
+        })
+    }
+}
+
+// Fills in block timestamps and transaction sender and gas, fetching each distinct
+// block header and receipt once however many records share it
+pub async fn enrich(client: &EthClient, records: &mut [EventRecord]) -> Result<()> {
+    let blocks: BTreeSet<u64> = records.iter().map(|record| record.block_number).collect();
+    let timestamps: HashMap<u64, u64> = stream::iter(blocks)
+        .map(|number| async move { Ok::<_, Error>((number, get_header(client, &BlockTag::Number(number).to_string()).await?.timestamp)) })
+        .buffer_unordered(DEFAULT_CONCURRENCY)
+        .try_collect()
+        .await?;
+
+    let transactions: BTreeSet<H256> = records.iter().map(|record| record.transaction_hash).collect();
+    let receipts: HashMap<H256, TransactionReceipt> = stream::iter(transactions)
+        .map(|hash| async move {
+            let receipt = client.get_transaction_receipt(&format!("{:?}", hash)).await?;
+            receipt.map(|receipt| (hash, receipt)).ok_or_else(|| Error::Decode(format!("Receipt for {:?} not found", hash)))
+        })
+        .buffer_unordered(DEFAULT_CONCURRENCY)
+        .try_collect()
+        .await?;
+
+    for record in records {
+        record.timestamp = timestamps.get(&record.block_number).copied();
+        if let Some(receipt) = receipts.get(&record.transaction_hash) {
+            record.from = Some(receipt.from.clone());
+            record.gas_used = Some(receipt.gas_used);
+            record.effective_gas_price = receipt.effective_gas_price;
+        }
+    }
+    Ok(())
+}
+
+// Decodes the logs matching `event` and enriches them; logs of other events are skipped
+pub async fn decode_and_enrich(client: &EthClient, event: &Event, logs: &[Log]) -> Result<Vec<EventRecord>> {
+    let signature = event.signature();
+    let mut records = logs.iter().filter(|log| log.topics.first() == Some(&signature)).map(|log| EventRecord::decode(event, log)).collect::<Result<Vec<_>>>()?;
+    enrich(client, &mut records).await?;
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use ethabi::{EventParam, ParamType};
+
+    use super::*;
+    use crate::testing::{Failure, MockNode};
+
+    // Every block is at 1000 + number, every tx from 0xaa..
+    fn node() -> MockNode {
+        MockNode::new(|method, params| match method {
+            "eth_getBlockByNumber" => {
+                let number = u64::from_str_radix(params[0].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+                Ok(serde_json::json!({
+                    "number": params[0],
+                    "hash": H256::from_low_u64_be(number),
+                    "parentHash": H256::from_low_u64_be(number - 1),
+                    "timestamp": format!("0x{:x}", 1000 + number),
+                    "gasUsed": "0x0",
+                    "gasLimit": "0x1c9c380",
+                }))
+            }
+            "eth_getTransactionReceipt" => Ok(serde_json::json!({
+                "transactionHash": params[0],
+                "transactionIndex": "0x0",
+                "blockHash": H256::zero(),
+                "blockNumber": "0x1",
+                "from": "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+                "to": null,
+                "cumulativeGasUsed": "0xc350",
+                "gasUsed": "0xc350",
+                "effectiveGasPrice": "0x3b9aca00",
+                "contractAddress": null,
+                "logs": [],
+                "status": "0x1",
+            })),
+            _ => Err(Failure::unexpected(method)),
+        })
+    }
+
+    fn transfer() -> Event {
+        let param = |name: &str, kind: ParamType, indexed: bool| EventParam { name: name.to_string(), kind, indexed };
+        Event {
+            name: "Transfer".to_string(),
+            inputs: vec![param("from", ParamType::Address, true), param("to", ParamType::Address, true), param("value", ParamType::Uint(256), false)],
+            anonymous: false,
+        }
+    }
+
+    fn log(event: &Event, block: u64, transaction: u64, index: u64) -> Log {
+        Log {
+            address: "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string(),
+            topics: vec![event.signature(), H256::from_low_u64_be(1), H256::from_low_u64_be(2)],
+            data: format!("0x{:064x}", 500),
+            block_number: Some(block.into()),
+            block_hash: None,
+            transaction_hash: Some(H256::from_low_u64_be(transaction)),
+            transaction_index: None,
+            log_index: Some(index.into()),
+            removed: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn fetches_each_block_and_transaction_once() {
+        let node = node();
+        let client = node.client();
+        let event = transfer();
+        let mut other = log(&event, 9, 9, 9);
+        other.topics[0] = H256::from_low_u64_be(3);
+        let logs = [log(&event, 7, 1, 0), log(&event, 7, 1, 1), log(&event, 8, 2, 0), other];
+
+        let records = decode_and_enrich(&client, &event, &logs).await.unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records.iter().map(|record| record.timestamp).collect::<Vec<_>>(), [Some(1007), Some(1007), Some(1008)]);
+        assert_eq!(records[2].params["value"], "500");
+        assert_eq!(records[2].params["to"], "0x0000000000000000000000000000000000000002");
+        assert_eq!(records[0].from.as_deref(), Some("0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"));
+        assert_eq!((records[0].gas_used, records[0].effective_gas_price), (Some(U256::from(50_000)), Some(U256::from(1_000_000_000))));
+
+        let mut calls: Vec<String> = node.calls().into_iter().map(|(method, _)| method).collect();
+        calls.sort();
+        assert_eq!(calls, ["eth_getBlockByNumber", "eth_getBlockByNumber", "eth_getTransactionReceipt", "eth_getTransactionReceipt"]);
+    }
+}
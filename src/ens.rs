@@ -0,0 +1,608 @@
+use std::fmt;
+use std::str::FromStr;
+
+use base64::Engine;
+use ethabi::ethereum_types::{H160, H256, U256};
+use ethabi::{ParamType, Token};
+
+use crate::abi::{decode_result, encode_call};
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::multicall::{self, Call};
+
+pub const ENS_REGISTRY: &str = "0x00000000000C2E074eC69A0dFb2997BA6C7d2e1e";
+// ENSIP-10 resolve(bytes,bytes), which wildcard and offchain resolvers implement
+pub const EXTENDED_RESOLVER_INTERFACE: &str = "9061b923";
+// Used to turn ipfs:// and ipns:// URIs into fetchable URLs
+pub const IPFS_GATEWAY: &str = "https://ipfs.io";
+
+// EIP-137 namehash. Names must already be normalized (ENSIP-15); only ASCII case is
+// folded here.
+pub fn namehash(name: &str) -> H256 {
+    let mut node = [0u8; 32];
+    if !name.is_empty() {
+        for label in name.to_ascii_lowercase().rsplit('.') {
+            let mut preimage = node.to_vec();
+            preimage.extend_from_slice(keccak_hash::keccak(label.as_bytes()).as_bytes());
+            node = keccak_hash::keccak(preimage).0;
+        }
+    }
+    H256(node)
+}
+
+// DNS wire format of the name, as passed to resolve(bytes,bytes)
+pub fn dns_encode(name: &str) -> Result<Vec<u8>> {
+    let mut encoded = Vec::new();
+    for label in name.to_ascii_lowercase().split('.').filter(|label| !label.is_empty()) {
+        let length = u8::try_from(label.len()).map_err(|_| Error::InvalidInput(format!("ENS label longer than 255 bytes in {}", name)))?;
+        encoded.push(length);
+        encoded.extend_from_slice(label.as_bytes());
+    }
+    encoded.push(0);
+    Ok(encoded)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolver {
+    pub address: String,
+    // The name the resolver is set on; a parent of the queried name for wildcards
+    pub name: String,
+    pub extended: bool,
+}
+
+// Finds the resolver for `name` the ENSIP-10 way: the name's own resolver, else the
+// closest parent's, which only counts if it supports wildcard resolution
+pub async fn find_resolver(client: &EthClient, name: &str) -> Result<Option<Resolver>> {
+    let mut current = name;
+    loop {
+        let data = encode_call("resolver(bytes32)", &[Token::FixedBytes(namehash(current).as_bytes().to_vec())]);
+        let result = client.call(ENS_REGISTRY, &data).await?;
+        // No registry deployed, so this chain has no ENS
+        if result.trim_start_matches("0x").is_empty() {
+            return Ok(None);
+        }
+        let address = match decode_result(&result, &[ParamType::Address])?.as_slice() {
+            [Token::Address(address)] => *address,
+            _ => return Err(Error::Decode("Invalid ENS registry response".to_string())),
+        };
+        if !address.is_zero() {
+            let address = format!("{:?}", address);
+            let extended = supports_interface(client, &address, EXTENDED_RESOLVER_INTERFACE).await?;
+            if current != name && !extended {
+                return Ok(None);
+            }
+            return Ok(Some(Resolver {
+                address,
+                name: current.to_string(),
+                extended,
+            }));
+        }
+        match current.split_once('.') {
+            Some((_, parent)) => current = parent,
+            None if !current.is_empty() => current = "",
+            None => return Ok(None),
+        }
+    }
+}
+
+// ERC-165; a revert or an account without code counts as unsupported
+pub async fn supports_interface(client: &EthClient, address: &str, interface_id: &str) -> Result<bool> {
+    let interface_id = hex::decode(interface_id).map_err(|_| Error::InvalidInput(format!("Invalid interface id {}", interface_id)))?;
+    let data = encode_call("supportsInterface(bytes4)", &[Token::FixedBytes(interface_id)]);
+    match client.call(address, &data).await {
+        Ok(result) => Ok(matches!(decode_result(&result, &[ParamType::Bool]).as_deref(), Ok([Token::Bool(true)]))),
+        Err(Error::Rpc(_)) => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+// Calls `signature(node, args...)` on the name's resolver and decodes the single output.
+// None when the name has no resolver or the resolver has no such record.
+async fn resolve_record(client: &EthClient, name: &str, signature: &str, args: Vec<Token>, output: ParamType) -> Result<Option<Token>> {
+    let Some(resolver) = find_resolver(client, name).await? else {
+        return Ok(None);
+    };
+    let mut tokens = vec![Token::FixedBytes(namehash(name).as_bytes().to_vec())];
+    tokens.extend(args);
+    let mut data = encode_call(signature, &tokens);
+    if resolver.extended {
+        let inner = hex::decode(data.trim_start_matches("0x")).map_err(|error| Error::Abi(error.to_string()))?;
+        data = encode_call("resolve(bytes,bytes)", &[Token::Bytes(dns_encode(name)?), Token::Bytes(inner)]);
+    }
+
+    let mut result = match client.call(&resolver.address, &data).await {
+        Ok(result) => result,
+        // Resolvers revert for names and records they don't know
+        Err(Error::Rpc(_)) => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    if resolver.extended {
+        match decode_result(&result, &[ParamType::Bytes])?.pop() {
+            Some(Token::Bytes(inner)) => result = format!("0x{}", hex::encode(inner)),
+            _ => return Err(Error::Decode("Invalid resolve(bytes,bytes) response".to_string())),
+        }
+    }
+    if result.trim_start_matches("0x").is_empty() {
+        return Ok(None);
+    }
+    Ok(decode_result(&result, &[output])?.pop())
+}
+
+// The name's ETH address, None when unset
+pub async fn resolve_name(client: &EthClient, name: &str) -> Result<Option<String>> {
+    match resolve_record(client, name, "addr(bytes32)", vec![], ParamType::Address).await? {
+        Some(Token::Address(address)) if !address.is_zero() => Ok(Some(format!("{:?}", address))),
+        _ => Ok(None),
+    }
+}
+
+// The address's primary name. Reverse records are self-declared, so the name only
+// counts if it resolves back to the address.
+pub async fn lookup_address(client: &EthClient, address: &str) -> Result<Option<String>> {
+    let Some(name) = reverse_name(client, address).await? else {
+        return Ok(None);
+    };
+    let forward = resolve_name(client, &name).await?;
+    Ok(forward.filter(|forward| forward.eq_ignore_ascii_case(address)).map(|_| name))
+}
+
+// Primary names for many addresses with a handful of multicalls instead of several
+// calls per address. Like lookup_address, each name must resolve back to its address;
+// names that need wildcard or offchain resolution fall back to resolve_name.
+pub async fn lookup_addresses(client: &EthClient, addresses: &[&str]) -> Result<Vec<Option<String>>> {
+    let nodes = addresses.iter().map(|address| reverse_node_name(address).map(|name| namehash(&name))).collect::<Result<Vec<_>>>()?;
+    let resolvers = batch_resolvers(client, &nodes).await?;
+
+    let calls: Vec<Call> = nodes
+        .iter()
+        .zip(&resolvers)
+        .filter_map(|(node, resolver)| Some(Call::new(resolver.as_ref()?, &encode_call("name(bytes32)", &[Token::FixedBytes(node.as_bytes().to_vec())]))))
+        .collect();
+    let mut results = multicall::aggregate(client, &calls).await?.into_iter();
+    let names: Vec<Option<String>> = resolvers
+        .iter()
+        .map(|resolver| {
+            resolver.as_ref()?;
+            match decode_result(&results.next().flatten()?, &[ParamType::String]).ok()?.pop() {
+                Some(Token::String(name)) if !name.is_empty() => Some(name),
+                _ => None,
+            }
+        })
+        .collect();
+
+    let forward = batch_addresses(client, &names).await?;
+    Ok(names
+        .into_iter()
+        .zip(forward)
+        .zip(addresses)
+        .map(|((name, forward), address)| name.filter(|_| forward.is_some_and(|forward| forward.eq_ignore_ascii_case(address))))
+        .collect())
+}
+
+// The registry's resolver for each node, None where unset
+async fn batch_resolvers(client: &EthClient, nodes: &[H256]) -> Result<Vec<Option<String>>> {
+    let calls: Vec<Call> = nodes.iter().map(|node| Call::new(ENS_REGISTRY, &encode_call("resolver(bytes32)", &[Token::FixedBytes(node.as_bytes().to_vec())]))).collect();
+    Ok(multicall::aggregate(client, &calls)
+        .await?
+        .into_iter()
+        .map(|result| match decode_result(&result?, &[ParamType::Address]).ok()?.pop() {
+            Some(Token::Address(address)) if !address.is_zero() => Some(format!("{:?}", address)),
+            _ => None,
+        })
+        .collect())
+}
+
+// Forward-resolves the names with direct addr(node) calls, then one by one through
+// resolve_name for any the batch couldn't answer
+async fn batch_addresses(client: &EthClient, names: &[Option<String>]) -> Result<Vec<Option<String>>> {
+    let nodes: Vec<H256> = names.iter().flatten().map(|name| namehash(name)).collect();
+    let resolvers = batch_resolvers(client, &nodes).await?;
+    let calls: Vec<Call> = nodes
+        .iter()
+        .zip(&resolvers)
+        .filter_map(|(node, resolver)| Some(Call::new(resolver.as_ref()?, &encode_call("addr(bytes32)", &[Token::FixedBytes(node.as_bytes().to_vec())]))))
+        .collect();
+    let mut results = multicall::aggregate(client, &calls).await?.into_iter();
+    let mut resolvers = resolvers.into_iter();
+
+    let mut addresses = Vec::with_capacity(names.len());
+    for name in names {
+        let Some(name) = name else {
+            addresses.push(None);
+            continue;
+        };
+        let batched = match resolvers.next().flatten() {
+            Some(_) => results.next().flatten(),
+            None => None,
+        };
+        let address = match batched.and_then(|result| decode_result(&result, &[ParamType::Address]).ok()?.pop()) {
+            Some(Token::Address(address)) => (!address.is_zero()).then(|| format!("{:?}", address)),
+            _ => resolve_name(client, name).await?,
+        };
+        addresses.push(address);
+    }
+    Ok(addresses)
+}
+
+// The unverified name(node) record of <address>.addr.reverse
+pub async fn reverse_name(client: &EthClient, address: &str) -> Result<Option<String>> {
+    match resolve_record(client, &reverse_node_name(address)?, "name(bytes32)", vec![], ParamType::String).await? {
+        Some(Token::String(name)) if !name.is_empty() => Ok(Some(name)),
+        _ => Ok(None),
+    }
+}
+
+pub fn reverse_node_name(address: &str) -> Result<String> {
+    let hex = address.trim_start_matches("0x");
+    if hex.len() != 40 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(Error::InvalidInput(format!("Invalid address {}", address)));
+    }
+    Ok(format!("{}.addr.reverse", hex.to_lowercase()))
+}
+
+// A text record such as "url", "com.twitter" or "avatar"; None when unset
+pub async fn text(client: &EthClient, name: &str, key: &str) -> Result<Option<String>> {
+    match resolve_record(client, name, "text(bytes32,string)", vec![Token::String(key.to_string())], ParamType::String).await? {
+        Some(Token::String(value)) if !value.is_empty() => Ok(Some(value)),
+        _ => Ok(None),
+    }
+}
+
+pub async fn contenthash(client: &EthClient, name: &str) -> Result<Option<ContentHash>> {
+    match resolve_record(client, name, "contenthash(bytes32)", vec![], ParamType::Bytes).await? {
+        Some(Token::Bytes(bytes)) if !bytes.is_empty() => Ok(Some(ContentHash::decode(&bytes)?)),
+        _ => Ok(None),
+    }
+}
+
+// EIP-1577 content hash, displayed as its URI (ipfs://, ipns://, bzz://)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentHash {
+    // CID, base58 for CIDv0-compatible hashes and base32 otherwise
+    Ipfs(String),
+    Ipns(String),
+    // Swarm reference as hex
+    Swarm(String),
+    Other { codec: u64, data: Vec<u8> },
+}
+
+const IPFS_NS: u64 = 0xe3;
+const SWARM_NS: u64 = 0xe4;
+const IPNS_NS: u64 = 0xe5;
+
+impl ContentHash {
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let (codec, cid) = read_varint(bytes)?;
+        Ok(match codec {
+            IPFS_NS => ContentHash::Ipfs(encode_cid(cid)?),
+            IPNS_NS => ContentHash::Ipns(encode_cid(cid)?),
+            // CIDv1, swarm-manifest codec, keccak-256 multihash of 32 bytes
+            SWARM_NS => match cid {
+                [0x01, 0xfa, 0x01, 0x1b, 0x20, hash @ ..] if hash.len() == 32 => ContentHash::Swarm(hex::encode(hash)),
+                _ => return Err(Error::Decode("Unsupported Swarm content hash".to_string())),
+            },
+            _ => ContentHash::Other { codec, data: cid.to_vec() },
+        })
+    }
+
+    // An HTTP URL for the content, through IPFS_GATEWAY for IPFS and IPNS
+    pub fn gateway_url(&self) -> Option<String> {
+        match self {
+            ContentHash::Ipfs(cid) => Some(format!("{}/ipfs/{}", IPFS_GATEWAY, cid)),
+            ContentHash::Ipns(cid) => Some(format!("{}/ipns/{}", IPFS_GATEWAY, cid)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentHash::Ipfs(cid) => write!(f, "ipfs://{}", cid),
+            ContentHash::Ipns(cid) => write!(f, "ipns://{}", cid),
+            ContentHash::Swarm(hash) => write!(f, "bzz://{}", hash),
+            ContentHash::Other { codec, data } => write!(f, "0x{:x}:0x{}", codec, hex::encode(data)),
+        }
+    }
+}
+
+fn read_varint(bytes: &[u8]) -> Result<(u64, &[u8])> {
+    let mut value = 0u64;
+    for (index, byte) in bytes.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * index);
+        if byte & 0x80 == 0 {
+            return Ok((value, &bytes[index + 1..]));
+        }
+    }
+    Err(Error::Decode("Invalid content hash codec".to_string()))
+}
+
+// CIDv1 dag-pb with a sha2-256 multihash is shown as the familiar CIDv0 ("Qm...")
+fn encode_cid(cid: &[u8]) -> Result<String> {
+    match cid {
+        [0x01, 0x70, 0x12, 0x20, hash @ ..] if hash.len() == 32 => Ok(base58_encode(&cid[2..])),
+        [0x01, ..] => Ok(format!("b{}", base32_encode(cid))),
+        _ => Err(Error::Decode("Unsupported content hash CID version".to_string())),
+    }
+}
+
+fn base58_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    let mut digits: Vec<u8> = Vec::new();
+    for byte in bytes {
+        let mut carry = u32::from(*byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+    std::iter::repeat_n('1', zeros).chain(digits.iter().rev().map(|digit| ALPHABET[*digit as usize] as char)).collect()
+}
+
+// RFC 4648 lowercase, unpadded, as used by multibase "b"
+fn base32_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+    let mut encoded = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    encoded
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NftStandard {
+    Erc721,
+    Erc1155,
+}
+
+// ENSIP-12 avatar record: a URL (https, ipfs, data) or an NFT reference such as
+// eip155:1/erc721:0xb47e3cd837ddf8e4c57f05d70ab865de6e193bbb/0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AvatarUri {
+    Url(String),
+    Nft { chain_id: u64, standard: NftStandard, contract: String, token_id: U256 },
+}
+
+impl FromStr for AvatarUri {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let Some(reference) = value.strip_prefix("eip155:") else {
+            return Ok(AvatarUri::Url(value.to_string()));
+        };
+        let invalid = || Error::InvalidInput(format!("Invalid NFT avatar {}", value));
+        let (chain_id, asset) = reference.split_once('/').ok_or_else(invalid)?;
+        let (standard, asset) = asset.split_once(':').ok_or_else(invalid)?;
+        let (contract, token_id) = asset.split_once('/').ok_or_else(invalid)?;
+        let standard = match standard.to_ascii_lowercase().as_str() {
+            "erc721" => NftStandard::Erc721,
+            "erc1155" => NftStandard::Erc1155,
+            _ => return Err(invalid()),
+        };
+        contract.trim_start_matches("0x").parse::<H160>().map_err(|_| invalid())?;
+        Ok(AvatarUri::Nft {
+            chain_id: chain_id.parse().map_err(|_| invalid())?,
+            standard,
+            contract: contract.to_lowercase(),
+            token_id: U256::from_dec_str(token_id).map_err(|_| invalid())?,
+        })
+    }
+}
+
+// Rewrites ipfs:// and ipns:// URIs to IPFS_GATEWAY; other URIs are returned as they are
+pub fn gateway_url(uri: &str) -> String {
+    if let Some(path) = uri.strip_prefix("ipfs://") {
+        return format!("{}/ipfs/{}", IPFS_GATEWAY, path.trim_start_matches("ipfs/"));
+    }
+    if let Some(path) = uri.strip_prefix("ipns://") {
+        return format!("{}/ipns/{}", IPFS_GATEWAY, path.trim_start_matches("ipns/"));
+    }
+    uri.to_string()
+}
+
+// The name's avatar as a URL an HTTP client or browser can load. NFT avatars are
+// followed to the token metadata's image, and only count while the name's address
+// owns the token; NFTs on other chains than the client's are not resolved.
+pub async fn avatar(client: &EthClient, name: &str) -> Result<Option<String>> {
+    let Some(record) = text(client, name, "avatar").await? else {
+        return Ok(None);
+    };
+    let (chain_id, standard, contract, token_id) = match record.parse()? {
+        AvatarUri::Url(url) => return Ok(Some(gateway_url(&url))),
+        AvatarUri::Nft { chain_id, standard, contract, token_id } => (chain_id, standard, contract, token_id),
+    };
+    if chain_id != client.chain_id().await? {
+        return Err(Error::Unsupported(format!("Avatar NFT is on chain {}, not the client's", chain_id)));
+    }
+    let Some(owner) = resolve_name(client, name).await? else {
+        return Ok(None);
+    };
+    let owner = owner.trim_start_matches("0x").parse::<H160>().map_err(|_| Error::Decode(format!("Invalid address {}", owner)))?;
+
+    let metadata_uri = match standard {
+        NftStandard::Erc721 => {
+            let result = client.call(&contract, &encode_call("ownerOf(uint256)", &[Token::Uint(token_id)])).await?;
+            if decode_result(&result, &[ParamType::Address])?.pop() != Some(Token::Address(owner)) {
+                return Ok(None);
+            }
+            let result = client.call(&contract, &encode_call("tokenURI(uint256)", &[Token::Uint(token_id)])).await?;
+            decode_string_token(&result)?
+        }
+        NftStandard::Erc1155 => {
+            let result = client.call(&contract, &encode_call("balanceOf(address,uint256)", &[Token::Address(owner), Token::Uint(token_id)])).await?;
+            if matches!(decode_result(&result, &[ParamType::Uint(256)])?.pop(), Some(Token::Uint(balance)) if balance.is_zero()) {
+                return Ok(None);
+            }
+            let result = client.call(&contract, &encode_call("uri(uint256)", &[Token::Uint(token_id)])).await?;
+            // ERC-1155 substitutes the token id as 64 lowercase hex digits
+            decode_string_token(&result)?.replace("{id}", &format!("{:064x}", token_id))
+        }
+    };
+
+    let metadata = fetch_metadata(client, &metadata_uri).await?;
+    let image = ["image", "image_url"].iter().find_map(|key| metadata[key].as_str().filter(|image| !image.is_empty()));
+    Ok(image.map(gateway_url))
+}
+
+fn decode_string_token(result: &str) -> Result<String> {
+    match decode_result(result, &[ParamType::String])?.pop() {
+        Some(Token::String(value)) => Ok(value),
+        _ => Err(Error::Decode("Expected a string result".to_string())),
+    }
+}
+
+// Token metadata JSON from a data: URI (base64 or plain) or over HTTP
+async fn fetch_metadata(client: &EthClient, uri: &str) -> Result<serde_json::Value> {
+    let invalid = |error: &dyn fmt::Display| Error::Decode(format!("Invalid token metadata: {}", error));
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (media_type, payload) = data.split_once(',').ok_or_else(|| invalid(&"malformed data URI"))?;
+        let payload = if media_type.ends_with(";base64") {
+            base64::engine::general_purpose::STANDARD.decode(payload).map_err(|error| invalid(&error))?
+        } else {
+            payload.as_bytes().to_vec()
+        };
+        return serde_json::from_slice(&payload).map_err(|error| invalid(&error));
+    }
+    let response = client.http_client().get(gateway_url(uri)).send().await.map_err(|error| Error::Transport(Box::new(error.without_url())))?;
+    let response = response.error_for_status().map_err(|error| Error::Transport(Box::new(error.without_url())))?;
+    response.json().await.map_err(|error| invalid(&error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namehash_matches_eip_137() {
+        assert_eq!(namehash(""), H256::zero());
+        assert_eq!(format!("{:?}", namehash("eth")), "0x93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae");
+        assert_eq!(format!("{:?}", namehash("foo.eth")), "0xde9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f");
+        assert_eq!(namehash("Foo.ETH"), namehash("foo.eth"));
+    }
+
+    #[test]
+    fn dns_encodes_names() {
+        assert_eq!(dns_encode("foo.eth").unwrap(), b"\x03foo\x03eth\x00");
+        assert_eq!(dns_encode("").unwrap(), b"\x00");
+        assert!(dns_encode(&format!("{}.eth", "a".repeat(256))).is_err());
+    }
+
+    #[test]
+    fn decodes_eip_1577_content_hashes() {
+        let ipfs = hex::decode("e3010170122029f2d17be6139079dc48696d1f582a8530eb9805b561eda517e22a892c7e3f1f").unwrap();
+        let ipfs = ContentHash::decode(&ipfs).unwrap();
+        assert_eq!(ipfs.to_string(), "ipfs://QmRAQB6YaCyidP37UdDnjFY5vQuiBrcqdyoW1CuDgwxkD4");
+        assert_eq!(ipfs.gateway_url().unwrap(), "https://ipfs.io/ipfs/QmRAQB6YaCyidP37UdDnjFY5vQuiBrcqdyoW1CuDgwxkD4");
+
+        let swarm = hex::decode("e40101fa011b20d1de9994b4d039f6548d191eb26786769f580809256b4685ef316805265ea162").unwrap();
+        assert_eq!(ContentHash::decode(&swarm).unwrap().to_string(), "bzz://d1de9994b4d039f6548d191eb26786769f580809256b4685ef316805265ea162");
+
+        // CIDv1 raw codec stays in base32
+        let raw = hex::decode("e30101551220b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9").unwrap();
+        assert_eq!(ContentHash::decode(&raw).unwrap().to_string(), "ipfs://bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e");
+    }
+
+    #[test]
+    fn parses_ensip_12_avatar_uris() {
+        let nft: AvatarUri = "eip155:1/erc721:0xb47e3cd837dDF8e4c57F05d70Ab865de6e193BBB/0".parse().unwrap();
+        assert_eq!(nft, AvatarUri::Nft {
+            chain_id: 1,
+            standard: NftStandard::Erc721,
+            contract: "0xb47e3cd837ddf8e4c57f05d70ab865de6e193bbb".to_string(),
+            token_id: U256::zero(),
+        });
+        let multi: AvatarUri = "eip155:1/erc1155:0x495f947276749ce646f68ac8c248420045cb7b5e/8112316025873927737505937898915153732580103913704334048512380490797008551937".parse().unwrap();
+        assert!(matches!(multi, AvatarUri::Nft { standard: NftStandard::Erc1155, .. }));
+        assert_eq!("https://example.com/a.png".parse::<AvatarUri>().unwrap(), AvatarUri::Url("https://example.com/a.png".to_string()));
+        assert!("eip155:1/erc20:0xb47e3cd837ddf8e4c57f05d70ab865de6e193bbb/0".parse::<AvatarUri>().is_err());
+        assert_eq!(gateway_url("ipfs://ipfs/QmRAQB6YaCyidP37UdDnjFY5vQuiBrcqdyoW1CuDgwxkD4"), "https://ipfs.io/ipfs/QmRAQB6YaCyidP37UdDnjFY5vQuiBrcqdyoW1CuDgwxkD4");
+    }
+
+    #[tokio::test]
+    async fn batch_reverse_lookup_verifies_forward_resolution() {
+        use std::collections::HashMap;
+
+        use crate::multicall::tests::Multicall;
+
+        let resolver = "0x231b0ee14048e9dccd1d247744d114a4eb5e8e63";
+        let alice = "0x1111111111111111111111111111111111111111";
+        let mallory = "0x2222222222222222222222222222222222222222";
+        let nobody = "0x3333333333333333333333333333333333333333";
+        let word = |token: Token| format!("0x{}", hex::encode(ethabi::encode(&[token])));
+        let node = |name: &str| Token::FixedBytes(namehash(name).as_bytes().to_vec());
+        let address = |address: &str| Token::Address(address[2..].parse().unwrap());
+
+        let mut answers = HashMap::new();
+        let mut answer = |target: &str, data: String, result: String| answers.insert((target.to_lowercase(), data.to_lowercase()), result);
+        for claimant in [alice, mallory] {
+            let reverse = reverse_node_name(claimant).unwrap();
+            answer(ENS_REGISTRY, encode_call("resolver(bytes32)", &[node(&reverse)]), word(address(resolver)));
+            // Mallory's reverse record claims alice.eth as well
+            answer(resolver, encode_call("name(bytes32)", &[node(&reverse)]), word(Token::String("alice.eth".to_string())));
+        }
+        answer(ENS_REGISTRY, encode_call("resolver(bytes32)", &[node("alice.eth")]), word(address(resolver)));
+        answer(resolver, encode_call("addr(bytes32)", &[node("alice.eth")]), word(address(alice)));
+        let client = Multicall(answers).client();
+
+        let names = lookup_addresses(&client, &[alice, mallory, nobody]).await.unwrap();
+        assert_eq!(names, vec![Some("alice.eth".to_string()), None, None]);
+    }
+
+    // Registry answers for `resolvers` (name -> resolver, zero where unset), plus a
+    // resolver at 0x...ee that supports ENSIP-10 and one at 0x...dd that doesn't
+    fn wildcard_client(resolvers: &[(&str, &str)], answers: &[(&str, String, String)]) -> EthClient {
+        use std::collections::HashMap;
+
+        let mut table = HashMap::new();
+        for (name, resolver) in resolvers {
+            let data = encode_call("resolver(bytes32)", &[Token::FixedBytes(namehash(name).as_bytes().to_vec())]);
+            table.insert((ENS_REGISTRY.to_lowercase(), data), format!("0x{:0>64}", resolver.trim_start_matches("0x")));
+        }
+        let supports = |id: &str| encode_call("supportsInterface(bytes4)", &[Token::FixedBytes(hex::decode(id).unwrap())]);
+        table.insert((format!("0x{:0>40}", "ee"), supports(EXTENDED_RESOLVER_INTERFACE)), format!("0x{:064x}", 1));
+        table.insert((format!("0x{:0>40}", "dd"), supports(EXTENDED_RESOLVER_INTERFACE)), format!("0x{:064x}", 0));
+        for (target, data, result) in answers {
+            table.insert((target.to_string(), data.to_lowercase()), result.clone());
+        }
+        crate::multicall::tests::Multicall(table).client()
+    }
+
+    #[tokio::test]
+    async fn resolves_dns_names_through_a_wildcard_parent() {
+        let extended = format!("0x{:0>40}", "ee");
+        let name = "www.example.com";
+        let inner = encode_call("addr(bytes32)", &[Token::FixedBytes(namehash(name).as_bytes().to_vec())]);
+        let call = encode_call("resolve(bytes,bytes)", &[Token::Bytes(dns_encode(name).unwrap()), Token::Bytes(hex::decode(&inner[2..]).unwrap())]);
+        let address = format!("0x{:0>40}", "abcd");
+        let wrapped = format!("0x{}", hex::encode(ethabi::encode(&[Token::Bytes(ethabi::encode(&[Token::Address(address[2..].parse().unwrap())]))])));
+        let client = wildcard_client(&[(name, "0x0"), ("example.com", "0x0"), ("com", &extended)], &[(&extended, call, wrapped)]);
+
+        let resolver = find_resolver(&client, name).await.unwrap().unwrap();
+        assert_eq!(resolver, Resolver { address: extended.clone(), name: "com".to_string(), extended: true });
+        assert_eq!(resolve_name(&client, name).await.unwrap(), Some(address));
+    }
+
+    #[tokio::test]
+    async fn ignores_parent_resolvers_without_wildcard_support() {
+        let legacy = format!("0x{:0>40}", "dd");
+        let client = wildcard_client(&[("sub.foo.eth", "0x0"), ("foo.eth", &legacy)], &[]);
+        assert_eq!(find_resolver(&client, "sub.foo.eth").await.unwrap(), None);
+        assert_eq!(find_resolver(&client, "foo.eth").await.unwrap().map(|resolver| resolver.extended), Some(false));
+    }
+}
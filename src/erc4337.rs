@@ -0,0 +1,573 @@
+use async_trait::async_trait;
+use ethabi::ethereum_types::{H160, H256, U256};
+use ethabi::Token;
+use serde::{Deserialize, Serialize};
+
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::key::SecretKey;
+use crate::transaction::TransactionReceipt;
+
+// EntryPoint v0.7, deployed at the same address on every supported chain
+pub const ENTRY_POINT_V07: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+
+// A v0.7 UserOperation in the unpacked form bundler RPCs use. Byte fields are
+// 0x-prefixed hex.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperation {
+    pub sender: String,
+    pub nonce: U256,
+    // Only for the first operation, which deploys the account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub factory: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub factory_data: Option<String>,
+    pub call_data: String,
+    pub call_gas_limit: U256,
+    pub verification_gas_limit: U256,
+    pub pre_verification_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paymaster: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paymaster_verification_gas_limit: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paymaster_post_op_gas_limit: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paymaster_data: Option<String>,
+    pub signature: String,
+}
+
+fn bytes(field: &str, value: &str) -> Result<Vec<u8>> {
+    hex::decode(value.trim_start_matches("0x")).map_err(|_| Error::InvalidInput(format!("Invalid {} hex", field)))
+}
+
+fn address(field: &str, value: &str) -> Result<H160> {
+    value.trim_start_matches("0x").parse().map_err(|_| Error::InvalidInput(format!("Invalid {} address {}", field, value)))
+}
+
+// Two 128-bit values in one word, as the packed operation stores gas fields
+fn pack_u128(high: U256, low: U256) -> Result<Vec<u8>> {
+    let limit = U256::from(u128::MAX);
+    if high > limit || low > limit {
+        return Err(Error::InvalidInput("Gas value does not fit in 128 bits".to_string()));
+    }
+    Ok(<[u8; 32]>::from(high << 128 | low).to_vec())
+}
+
+impl UserOperation {
+    // factory ++ factoryData, empty for deployed accounts
+    pub fn init_code(&self) -> Result<Vec<u8>> {
+        let Some(factory) = &self.factory else {
+            return Ok(Vec::new());
+        };
+        let mut init_code = address("factory", factory)?.as_bytes().to_vec();
+        init_code.extend(bytes("factoryData", self.factory_data.as_deref().unwrap_or("0x"))?);
+        Ok(init_code)
+    }
+
+    // paymaster ++ verification gas (16 bytes) ++ postOp gas (16 bytes) ++ paymasterData
+    pub fn paymaster_and_data(&self) -> Result<Vec<u8>> {
+        let Some(paymaster) = &self.paymaster else {
+            return Ok(Vec::new());
+        };
+        let mut encoded = address("paymaster", paymaster)?.as_bytes().to_vec();
+        let gas = pack_u128(self.paymaster_verification_gas_limit.unwrap_or_default(), self.paymaster_post_op_gas_limit.unwrap_or_default())?;
+        encoded.extend(gas);
+        encoded.extend(bytes("paymasterData", self.paymaster_data.as_deref().unwrap_or("0x"))?);
+        Ok(encoded)
+    }
+
+    // EntryPoint.getUserOpHash: keccak(abi.encode(keccak(pack(op)), entryPoint, chainId))
+    pub fn hash(&self, entry_point: &str, chain_id: u64) -> Result<H256> {
+        let keccak = |data: &[u8]| Token::FixedBytes(keccak_hash::keccak(data).as_bytes().to_vec());
+        let packed = ethabi::encode(&[
+            Token::Address(address("sender", &self.sender)?),
+            Token::Uint(self.nonce),
+            keccak(&self.init_code()?),
+            keccak(&bytes("callData", &self.call_data)?),
+            Token::FixedBytes(pack_u128(self.verification_gas_limit, self.call_gas_limit)?),
+            Token::Uint(self.pre_verification_gas),
+            Token::FixedBytes(pack_u128(self.max_priority_fee_per_gas, self.max_fee_per_gas)?),
+            keccak(&self.paymaster_and_data()?),
+        ]);
+        let encoded = ethabi::encode(&[keccak(&packed), Token::Address(address("entry point", entry_point)?), Token::Uint(chain_id.into())]);
+        Ok(keccak_hash::keccak(encoded))
+    }
+
+    // Signs the hash as an EIP-191 message, which is what SimpleAccount-style
+    // accounts verify
+    pub fn sign(&mut self, key: &SecretKey, entry_point: &str, chain_id: u64) -> Result<()> {
+        let hash = self.hash(entry_point, chain_id)?;
+        self.signature = format!("0x{}", hex::encode(key.sign_message(hash.as_bytes())?));
+        Ok(())
+    }
+
+    pub fn apply_gas(&mut self, gas: &UserOperationGas) {
+        self.pre_verification_gas = gas.pre_verification_gas;
+        self.verification_gas_limit = gas.verification_gas_limit;
+        self.call_gas_limit = gas.call_gas_limit;
+        if gas.paymaster_verification_gas_limit.is_some() {
+            self.paymaster_verification_gas_limit = gas.paymaster_verification_gas_limit;
+        }
+        if gas.paymaster_post_op_gas_limit.is_some() {
+            self.paymaster_post_op_gas_limit = gas.paymaster_post_op_gas_limit;
+        }
+    }
+
+    // Copies the paymaster fields, and any gas limits the paymaster estimated
+    pub fn apply_sponsorship(&mut self, sponsorship: &Sponsorship) {
+        self.paymaster = Some(sponsorship.paymaster.clone());
+        self.paymaster_data = Some(sponsorship.paymaster_data.clone());
+        let fields = [
+            (&mut self.paymaster_verification_gas_limit, sponsorship.paymaster_verification_gas_limit),
+            (&mut self.paymaster_post_op_gas_limit, sponsorship.paymaster_post_op_gas_limit),
+        ];
+        for (field, value) in fields {
+            if value.is_some() {
+                *field = value;
+            }
+        }
+        let fields = [
+            (&mut self.pre_verification_gas, sponsorship.pre_verification_gas),
+            (&mut self.verification_gas_limit, sponsorship.verification_gas_limit),
+            (&mut self.call_gas_limit, sponsorship.call_gas_limit),
+        ];
+        for (field, value) in fields {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+    }
+}
+
+// eth_estimateUserOperationGas result
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationGas {
+    pub pre_verification_gas: U256,
+    pub verification_gas_limit: U256,
+    pub call_gas_limit: U256,
+    pub paymaster_verification_gas_limit: Option<U256>,
+    pub paymaster_post_op_gas_limit: Option<U256>,
+}
+
+// eth_getUserOperationReceipt result
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationReceipt {
+    pub user_op_hash: H256,
+    pub sender: String,
+    pub nonce: U256,
+    pub success: bool,
+    pub actual_gas_cost: U256,
+    pub actual_gas_used: U256,
+    // The bundle transaction that included the operation
+    pub receipt: TransactionReceipt,
+}
+
+fn decode<T: serde::de::DeserializeOwned>(method: &str, value: serde_json::Value) -> Result<T> {
+    serde_json::from_value(value).map_err(|error| Error::Decode(format!("Invalid {} response: {}", method, error)))
+}
+
+fn to_json(op: &UserOperation) -> serde_json::Value {
+    serde_json::to_value(op).expect("UserOperation serializes")
+}
+
+pub async fn send_user_operation(client: &EthClient, op: &UserOperation, entry_point: &str) -> Result<H256> {
+    decode("eth_sendUserOperation", client.send("eth_sendUserOperation", vec![to_json(op), entry_point.into()]).await?)
+}
+
+// Needs a signature of the right shape (a dummy one works) for validation to run
+pub async fn estimate_user_operation_gas(client: &EthClient, op: &UserOperation, entry_point: &str) -> Result<UserOperationGas> {
+    decode("eth_estimateUserOperationGas", client.send("eth_estimateUserOperationGas", vec![to_json(op), entry_point.into()]).await?)
+}
+
+// None until the operation is included
+pub async fn get_user_operation_receipt(client: &EthClient, user_op_hash: &str) -> Result<Option<UserOperationReceipt>> {
+    decode("eth_getUserOperationReceipt", client.send("eth_getUserOperationReceipt", vec![user_op_hash.into()]).await?)
+}
+
+pub async fn supported_entry_points(client: &EthClient) -> Result<Vec<String>> {
+    decode("eth_supportedEntryPoints", client.send("eth_supportedEntryPoints", Vec::new()).await?)
+}
+
+// Paymaster fields for an operation. Gas limits are set when the paymaster
+// estimated them itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sponsorship {
+    pub paymaster: String,
+    pub paymaster_data: String,
+    pub paymaster_verification_gas_limit: Option<U256>,
+    pub paymaster_post_op_gas_limit: Option<U256>,
+    pub pre_verification_gas: Option<U256>,
+    pub verification_gas_limit: Option<U256>,
+    pub call_gas_limit: Option<U256>,
+    // Stub data that is already final needs no second round after estimation
+    #[serde(default)]
+    pub is_final: bool,
+}
+
+// Source of paymaster data. Implement it to plug in a paymaster that isn't reachable
+// over the RPC flavours PaymasterClient speaks.
+#[async_trait]
+pub trait Paymaster: Send + Sync {
+    // Placeholder data used while estimating gas; the final data by default
+    async fn stub_data(&self, op: &UserOperation, entry_point: &str, chain_id: u64) -> Result<Sponsorship> {
+        self.data(op, entry_point, chain_id).await
+    }
+
+    // Signed data for the operation with its final gas limits
+    async fn data(&self, op: &UserOperation, entry_point: &str, chain_id: u64) -> Result<Sponsorship>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymasterApi {
+    // pm_sponsorUserOperation(op, entryPoint, context), one round that also estimates gas
+    SponsorUserOperation,
+    // ERC-7677 pm_getPaymasterStubData / pm_getPaymasterData
+    Erc7677,
+}
+
+// A paymaster service reached over JSON-RPC, often the bundler endpoint itself
+pub struct PaymasterClient {
+    client: EthClient,
+    api: PaymasterApi,
+    context: serde_json::Value,
+}
+
+impl PaymasterClient {
+    pub fn new(client: &EthClient) -> Self {
+        PaymasterClient {
+            client: client.clone(),
+            api: PaymasterApi::SponsorUserOperation,
+            context: serde_json::json!({}),
+        }
+    }
+
+    pub fn with_api(mut self, api: PaymasterApi) -> Self {
+        self.api = api;
+        self
+    }
+
+    // Provider-specific context, e.g. a sponsorship policy id
+    pub fn with_context(mut self, context: serde_json::Value) -> Self {
+        self.context = context;
+        self
+    }
+
+    // Pays gas in an ERC-20 token instead of having it sponsored. The account must
+    // approve the paymaster for the token, usually in the same operation's calldata.
+    pub fn with_token(mut self, token: &str) -> Self {
+        if !self.context.is_object() {
+            self.context = serde_json::json!({});
+        }
+        self.context["token"] = token.into();
+        self
+    }
+
+    async fn erc7677(&self, method: &str, op: &UserOperation, entry_point: &str, chain_id: u64) -> Result<Sponsorship> {
+        let params = vec![to_json(op), entry_point.into(), format!("0x{:x}", chain_id).into(), self.context.clone()];
+        decode(method, self.client.send(method, params).await?)
+    }
+}
+
+#[async_trait]
+impl Paymaster for PaymasterClient {
+    async fn stub_data(&self, op: &UserOperation, entry_point: &str, chain_id: u64) -> Result<Sponsorship> {
+        match self.api {
+            PaymasterApi::Erc7677 => self.erc7677("pm_getPaymasterStubData", op, entry_point, chain_id).await,
+            PaymasterApi::SponsorUserOperation => self.data(op, entry_point, chain_id).await,
+        }
+    }
+
+    async fn data(&self, op: &UserOperation, entry_point: &str, chain_id: u64) -> Result<Sponsorship> {
+        match self.api {
+            PaymasterApi::Erc7677 => {
+                let mut sponsorship = self.erc7677("pm_getPaymasterData", op, entry_point, chain_id).await?;
+                sponsorship.is_final = true;
+                Ok(sponsorship)
+            }
+            PaymasterApi::SponsorUserOperation => {
+                let params = vec![to_json(op), entry_point.into(), self.context.clone()];
+                let mut sponsorship: Sponsorship = decode("pm_sponsorUserOperation", self.client.send("pm_sponsorUserOperation", params).await?)?;
+                sponsorship.is_final = true;
+                Ok(sponsorship)
+            }
+        }
+    }
+}
+
+// Fills the paymaster fields and gas limits: stub data, then a bundler gas estimate
+// unless the paymaster already estimated, then the final data. The operation still
+// needs signing afterwards.
+pub async fn sponsor_user_operation(bundler: &dyn Bundler, paymaster: &dyn Paymaster, op: &mut UserOperation, entry_point: &str) -> Result<()> {
+    let chain_id = bundler.client().chain_id().await?;
+    let stub = paymaster.stub_data(op, entry_point, chain_id).await?;
+    op.apply_sponsorship(&stub);
+    if stub.call_gas_limit.is_none() {
+        op.apply_gas(&bundler.estimate_user_operation_gas(op, entry_point).await?);
+    }
+    if !stub.is_final {
+        op.apply_sponsorship(&paymaster.data(op, entry_point, chain_id).await?);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationGasPrice {
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+}
+
+// A bundler endpoint. The defaults are the standard eth_* bundler methods; providers
+// override what they do differently, so code written against the trait doesn't change
+// when the bundler does.
+#[async_trait]
+pub trait Bundler: Send + Sync {
+    fn client(&self) -> &EthClient;
+
+    async fn send_user_operation(&self, op: &UserOperation, entry_point: &str) -> Result<H256> {
+        send_user_operation(self.client(), op, entry_point).await
+    }
+
+    async fn estimate_user_operation_gas(&self, op: &UserOperation, entry_point: &str) -> Result<UserOperationGas> {
+        estimate_user_operation_gas(self.client(), op, entry_point).await
+    }
+
+    async fn get_user_operation_receipt(&self, user_op_hash: &str) -> Result<Option<UserOperationReceipt>> {
+        get_user_operation_receipt(self.client(), user_op_hash).await
+    }
+
+    async fn supported_entry_points(&self) -> Result<Vec<String>> {
+        supported_entry_points(self.client()).await
+    }
+
+    // Fees the bundler accepts, by default the node's suggested tip
+    async fn gas_price(&self) -> Result<UserOperationGasPrice> {
+        gas_price_with_tip(self.client(), self.client().max_priority_fee().await?).await
+    }
+}
+
+// Twice the next base fee plus the tip, which survives a few full blocks
+async fn gas_price_with_tip(client: &EthClient, priority_fee: U256) -> Result<UserOperationGasPrice> {
+    let history = client.fee_history(1, crate::BlockTag::Latest, &[]).await?;
+    let base_fee = history.base_fee_per_gas.last().copied().unwrap_or_default();
+    let max_fee = base_fee.checked_mul(2.into()).and_then(|fee| fee.checked_add(priority_fee));
+    Ok(UserOperationGasPrice {
+        max_fee_per_gas: max_fee.ok_or_else(|| Error::Decode(format!("Max fee overflows with base fee {} and tip {}", base_fee, priority_fee)))?,
+        max_priority_fee_per_gas: priority_fee,
+    })
+}
+
+// Any client pointed at a standard bundler
+impl Bundler for EthClient {
+    fn client(&self) -> &EthClient {
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundlerPreset {
+    Pimlico,
+    Alchemy,
+    Stackup,
+}
+
+impl BundlerPreset {
+    // Endpoint for the chain. Stackup keys are per chain, so its URL only needs the key.
+    pub fn url(&self, chain_id: u64, api_key: &str) -> Result<String> {
+        match self {
+            BundlerPreset::Pimlico => Ok(format!("https://api.pimlico.io/v2/{}/rpc?apikey={}", chain_id, api_key)),
+            BundlerPreset::Alchemy => {
+                let network = match chain_id {
+                    1 => "eth-mainnet",
+                    10 => "opt-mainnet",
+                    137 => "polygon-mainnet",
+                    8453 => "base-mainnet",
+                    42161 => "arb-mainnet",
+                    84532 => "base-sepolia",
+                    11155111 => "eth-sepolia",
+                    _ => return Err(Error::Unsupported(format!("No Alchemy bundler preset for chain {}", chain_id))),
+                };
+                Ok(format!("https://{}.g.alchemy.com/v2/{}", network, api_key))
+            }
+            BundlerPreset::Stackup => Ok(format!("https://api.stackup.sh/v1/node/{}", api_key)),
+        }
+    }
+}
+
+// A provider's bundler with its nonstandard methods and gas quirks handled
+pub struct BundlerClient {
+    client: EthClient,
+    preset: BundlerPreset,
+}
+
+impl BundlerClient {
+    pub fn new(client: &EthClient, preset: BundlerPreset) -> Self {
+        BundlerClient {
+            client: client.clone(),
+            preset,
+        }
+    }
+
+    pub fn connect(preset: BundlerPreset, chain_id: u64, api_key: &str) -> Result<Self> {
+        Ok(Self::new(&EthClient::new(&preset.url(chain_id, api_key)?), preset))
+    }
+
+    pub fn preset(&self) -> BundlerPreset {
+        self.preset
+    }
+
+    // The provider's paymaster on the same endpoint. Alchemy serves ERC-7677 and
+    // needs a gas manager {"policyId": ...} context; Stackup's needs {"type": "payg"}.
+    pub fn paymaster(&self) -> PaymasterClient {
+        let paymaster = PaymasterClient::new(&self.client);
+        match self.preset {
+            BundlerPreset::Pimlico => paymaster,
+            BundlerPreset::Alchemy => paymaster.with_api(PaymasterApi::Erc7677),
+            BundlerPreset::Stackup => paymaster.with_context(serde_json::json!({"type": "payg"})),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PimlicoGasPrices {
+    standard: UserOperationGasPrice,
+}
+
+#[async_trait]
+impl Bundler for BundlerClient {
+    fn client(&self) -> &EthClient {
+        &self.client
+    }
+
+    async fn estimate_user_operation_gas(&self, op: &UserOperation, entry_point: &str) -> Result<UserOperationGas> {
+        let mut gas = estimate_user_operation_gas(&self.client, op, entry_point).await?;
+        // Rundler prices preVerificationGas off the current L1/base fee and rejects
+        // operations that fall behind it, so leave headroom for fee movement
+        if self.preset == BundlerPreset::Alchemy {
+            gas.pre_verification_gas = gas.pre_verification_gas.saturating_add(gas.pre_verification_gas / 10);
+        }
+        Ok(gas)
+    }
+
+    async fn gas_price(&self) -> Result<UserOperationGasPrice> {
+        match self.preset {
+            // Pimlico rejects fees below its own quote
+            BundlerPreset::Pimlico => Ok(decode::<PimlicoGasPrices>("pimlico_getUserOperationGasPrice", self.client.send("pimlico_getUserOperationGasPrice", Vec::new()).await?)?.standard),
+            // Rundler requires at least its own tip, which eth_maxPriorityFeePerGas doesn't give
+            BundlerPreset::Alchemy => {
+                let tip = self.client.send("rundler_maxPriorityFeePerGas", Vec::new()).await?;
+                let priority_fee = crate::utils::parse_quantity_u256(&tip).map_err(|_| Error::Decode(format!("Invalid rundler_maxPriorityFeePerGas response: {}", tip)))?;
+                gas_price_with_tip(&self.client, priority_fee).await
+            }
+            BundlerPreset::Stackup => gas_price_with_tip(&self.client, self.client.max_priority_fee().await?).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{Failure, MockNode};
+
+    fn operation() -> UserOperation {
+        UserOperation {
+            sender: "0x1111111111111111111111111111111111111111".to_string(),
+            nonce: 5.into(),
+            factory: Some("0x2222222222222222222222222222222222222222".to_string()),
+            factory_data: Some("0xaabb".to_string()),
+            call_data: "0xb61d27f6".to_string(),
+            call_gas_limit: 80_000.into(),
+            verification_gas_limit: 150_000.into(),
+            pre_verification_gas: 45_000.into(),
+            max_fee_per_gas: 30_000_000_000u64.into(),
+            max_priority_fee_per_gas: 1_000_000_000.into(),
+            paymaster: Some("0x3333333333333333333333333333333333333333".to_string()),
+            paymaster_verification_gas_limit: Some(60_000.into()),
+            paymaster_post_op_gas_limit: Some(10_000.into()),
+            paymaster_data: Some("0x010203".to_string()),
+            signature: "0x".to_string(),
+        }
+    }
+
+    #[test]
+    fn hashes_like_the_entry_point() {
+        // Expected hash computed offline with alloy-sol-types from the v0.7 packing
+        let expected: H256 = "0xccba6de755ce0aed89ac925184fdf05438049086f4f48462a4d037d8f0e3a561".parse().unwrap();
+        assert_eq!(operation().hash(ENTRY_POINT_V07, 11155111).unwrap(), expected);
+    }
+
+    // Answers the ERC-7677, bundler and provider methods
+    fn service() -> MockNode {
+        MockNode::new(|method, _| match method {
+            "eth_chainId" => Ok(json!("0xaa36a7")),
+            "pm_getPaymasterStubData" => Ok(json!({"paymaster": "0x4444444444444444444444444444444444444444", "paymasterData": "0x00", "paymasterPostOpGasLimit": "0x3e8"})),
+            "eth_estimateUserOperationGas" => Ok(json!({"preVerificationGas": "0xc350", "verificationGasLimit": "0x186a0", "callGasLimit": "0x7530", "paymasterVerificationGasLimit": "0x4e20"})),
+            "pimlico_getUserOperationGasPrice" => Ok(json!({
+                "slow": {"maxFeePerGas": "0x3e8", "maxPriorityFeePerGas": "0x1"},
+                "standard": {"maxFeePerGas": "0x7d0", "maxPriorityFeePerGas": "0x2"},
+                "fast": {"maxFeePerGas": "0xbb8", "maxPriorityFeePerGas": "0x3"},
+            })),
+            "rundler_maxPriorityFeePerGas" => Ok(json!("0x7")),
+            "eth_feeHistory" => Ok(json!({"oldestBlock": "0x1", "baseFeePerGas": ["0x50", "0x64"], "gasUsedRatio": [0.5]})),
+            "pm_getPaymasterData" => Ok(json!({"paymaster": "0x4444444444444444444444444444444444444444", "paymasterData": "0xfeed"})),
+            _ => Err(Failure::unexpected(method)),
+        })
+    }
+
+    #[tokio::test]
+    async fn rejects_fees_that_overflow() {
+        let node = MockNode::with_results([("eth_feeHistory", json!({"oldestBlock": "0x1", "baseFeePerGas": [format!("{:#x}", U256::MAX / 2)], "gasUsedRatio": []}))]);
+        assert!(matches!(gas_price_with_tip(&node.client(), U256::from(2)).await, Err(Error::Decode(_))));
+    }
+
+    #[tokio::test]
+    async fn presets_handle_provider_quirks() {
+        assert_eq!(BundlerPreset::Pimlico.url(8453, "key").unwrap(), "https://api.pimlico.io/v2/8453/rpc?apikey=key");
+        assert_eq!(BundlerPreset::Alchemy.url(11155111, "key").unwrap(), "https://eth-sepolia.g.alchemy.com/v2/key");
+        assert!(BundlerPreset::Alchemy.url(999_999, "key").is_err());
+
+        let pimlico = BundlerClient::new(&service().client(), BundlerPreset::Pimlico);
+        let price = pimlico.gas_price().await.unwrap();
+        assert_eq!(price.max_fee_per_gas, U256::from(2_000));
+        assert_eq!(pimlico.estimate_user_operation_gas(&operation(), ENTRY_POINT_V07).await.unwrap().pre_verification_gas, U256::from(50_000));
+
+        let alchemy = BundlerClient::new(&service().client(), BundlerPreset::Alchemy);
+        let price = alchemy.gas_price().await.unwrap();
+        assert_eq!((price.max_fee_per_gas, price.max_priority_fee_per_gas), (U256::from(2 * 100 + 7), U256::from(7)));
+        assert_eq!(alchemy.estimate_user_operation_gas(&operation(), ENTRY_POINT_V07).await.unwrap().pre_verification_gas, U256::from(55_000));
+    }
+
+    #[tokio::test]
+    async fn sponsors_through_an_erc7677_paymaster() {
+        let service = service();
+        let client = service.client();
+        let paymaster = PaymasterClient::new(&client).with_api(PaymasterApi::Erc7677).with_token("0x5555555555555555555555555555555555555555");
+        let mut op = UserOperation {
+            paymaster: None,
+            paymaster_data: None,
+            ..operation()
+        };
+
+        sponsor_user_operation(&client, &paymaster, &mut op, ENTRY_POINT_V07).await.unwrap();
+        assert_eq!(op.paymaster.as_deref(), Some("0x4444444444444444444444444444444444444444"));
+        assert_eq!(op.paymaster_data.as_deref(), Some("0xfeed"));
+        assert_eq!(op.call_gas_limit, U256::from(30_000));
+        assert_eq!(op.paymaster_verification_gas_limit, Some(U256::from(20_000)));
+        assert_eq!(op.paymaster_post_op_gas_limit, Some(U256::from(1_000)));
+
+        let calls = service.calls();
+        let methods: Vec<&str> = calls.iter().map(|(method, _)| method.as_str()).collect();
+        assert_eq!(methods, vec!["eth_chainId", "pm_getPaymasterStubData", "eth_estimateUserOperationGas", "pm_getPaymasterData"]);
+        assert_eq!(calls[1].1[2], json!("0xaa36a7"));
+        assert_eq!(calls[1].1[3], json!({"token": "0x5555555555555555555555555555555555555555"}));
+    }
+}
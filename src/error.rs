@@ -0,0 +1,115 @@
+use crate::client::RpcError;
+use crate::middleware::{is_transient, RATE_LIMIT_ERROR_CODES};
+use crate::transport::TransportError;
+
+// Every fallible function in the crate returns this. Transport implementations return a
+// boxed error, which EthClient wraps in Error::Transport.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    // The request never produced a JSON-RPC response: connection, HTTP status, timeouts
+    #[error("Transport error: {0}")]
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+    // The node answered with a JSON-RPC error object
+    #[error(transparent)]
+    Rpc(#[from] RpcError),
+    #[error("ABI error: {0}")]
+    Abi(String),
+    // A response, file or encoding (RLP, hex, JSON, proofs) that is not what it should be
+    #[error("Decode error: {0}")]
+    Decode(String),
+    // An argument the caller passed is out of range or malformed
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    // Missing or inconsistent settings: config files, profiles, environment variables
+    #[error("Config error: {0}")]
+    Config(String),
+    // Signing failed, or a signature doesn't recover or verify
+    #[error("Signature error: {0}")]
+    Signature(String),
+    // The endpoint or input needs something the crate doesn't handle, e.g. a non-EVM chain
+    #[error("Unsupported: {0}")]
+    Unsupported(String),
+    // A wait or poll ran past its deadline
+    #[error("Timed out: {0}")]
+    Timeout(String),
+    // A confirmation prompt or hook refused to sign the transaction
+    #[error("Declined: {0}")]
+    Declined(String),
+    // The endpoint serves a different network than the caller expected
+    #[error("Chain id mismatch: expected {expected}, endpoint reports {actual}")]
+    ChainMismatch { expected: u64, actual: u64 },
+}
+
+impl Error {
+    // Whether the same call may succeed if retried, possibly against another endpoint:
+    // dropped connections, timeouts, HTTP 429/5xx and rate-limit error responses
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Transport(error) => is_transient(error.as_ref()),
+            Error::Rpc(error) => RATE_LIMIT_ERROR_CODES.contains(&error.code),
+            Error::Timeout(_) => true,
+            _ => false,
+        }
+    }
+
+    // Whether the node reported that execution reverted, for eth_call, eth_estimateGas
+    // or a transaction sent with eth_sendRawTransaction
+    pub fn is_revert(&self) -> bool {
+        match self {
+            Error::Rpc(error) => error.code == 3 || error.message.to_lowercase().contains("revert"),
+            _ => false,
+        }
+    }
+
+    // A deadline ran out: a wait in the crate, or a request to the endpoint
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            Error::Timeout(_) => true,
+            Error::Transport(error) => {
+                let mut current: Option<&(dyn std::error::Error + 'static)> = Some(error.as_ref());
+                while let Some(error) = current {
+                    let timed_out = error.downcast_ref::<reqwest::Error>().is_some_and(reqwest::Error::is_timeout) || matches!(error.downcast_ref::<TransportError>(), Some(TransportError::Timeout { .. }));
+                    if timed_out {
+                        return true;
+                    }
+                    current = error.source();
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn classifies_reverts_and_timeouts() {
+        let rpc = |code: i64, message: &str| {
+            Error::Rpc(RpcError {
+                code,
+                message: message.to_string(),
+                data: None,
+            })
+        };
+        assert!(rpc(3, "execution reverted: not owner").is_revert());
+        assert!(rpc(-32000, "Execution Reverted").is_revert());
+        assert!(!rpc(-32000, "nonce too low").is_revert());
+
+        let timeout = TransportError::Timeout {
+            method: "eth_call".to_string(),
+            timeout: Duration::from_secs(1),
+        };
+        assert!(Error::Transport(Box::new(TransportError::Exhausted(Box::new(timeout)))).is_timeout());
+        assert!(Error::Timeout("receipt".to_string()).is_timeout());
+        assert!(!Error::Transport(Box::new(TransportError::Closed)).is_timeout());
+    }
+}
@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+
+pub struct VerificationSettings {
+    // e.g. https://api.etherscan.io/v2/api
+    pub api_url: String,
+    pub api_key: String,
+    pub chain_id: u64,
+    // Fully qualified name from the standard-json input, e.g. contracts/Token.sol:Token
+    pub contract_name: String,
+    // Full solc version string, e.g. v0.8.24+commit.e11b9ed9
+    pub compiler_version: String,
+    // ABI-encoded constructor arguments without the 0x prefix
+    pub constructor_arguments: String,
+    pub poll_interval: Duration,
+    pub max_polls: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum VerificationStatus {
+    Verified,
+    AlreadyVerified,
+    Failed(String),
+}
+
+// Submits the standard-json input and polls until the explorer reports a final status
+pub async fn verify_contract(client: &reqwest::Client, address: &str, source: &serde_json::Value, settings: &VerificationSettings) -> Result<VerificationStatus> {
+    let guid = match submit_verification(client, address, source, settings).await? {
+        Some(guid) => guid,
+        None => return Ok(VerificationStatus::AlreadyVerified),
+    };
+
+    for _ in 0..settings.max_polls {
+        tokio::time::sleep(settings.poll_interval).await;
+        if let Some(status) = check_verification_status(client, &guid, settings).await? {
+            return Ok(status);
+        }
+    }
+
+    Err(Error::Timeout(format!("Verification {} still pending after {} polls", guid, settings.max_polls)))
+}
+
+// Returns the verification guid, or None if the contract is already verified
+pub async fn submit_verification(client: &reqwest::Client, address: &str, source: &serde_json::Value, settings: &VerificationSettings) -> Result<Option<String>> {
+    let source_code = source.to_string();
+    let chain_id = settings.chain_id.to_string();
+
+    let response: serde_json::Value = client
+        .post(&settings.api_url)
+        .query(&[("chainid", chain_id.as_str())])
+        .form(&[
+            ("apikey", settings.api_key.as_str()),
+            ("module", "contract"),
+            ("action", "verifysourcecode"),
+            ("contractaddress", address),
+            ("sourceCode", source_code.as_str()),
+            ("codeformat", "solidity-standard-json-input"),
+            ("contractname", settings.contract_name.as_str()),
+            ("compilerversion", settings.compiler_version.as_str()),
+            // Misspelling is part of the Etherscan API
+            ("constructorArguements", settings.constructor_arguments.as_str()),
+        ])
+        .send()
+        .await
+        .map_err(|error| Error::Transport(Box::new(error)))?
+        .json()
+        .await
+        .map_err(|error| Error::Decode(format!("Invalid explorer response: {}", error)))?;
+
+    let result = response["result"].as_str().unwrap_or_default();
+    if response["status"] != "1" {
+        if result.to_lowercase().contains("already verified") {
+            return Ok(None);
+        }
+        return Err(Error::InvalidInput(format!("Verification submission failed: {}", result)));
+    }
+
+    Ok(Some(result.to_string()))
+}
+
+// Returns None while the verification is still queued or in progress
+pub async fn check_verification_status(client: &reqwest::Client, guid: &str, settings: &VerificationSettings) -> Result<Option<VerificationStatus>> {
+    let chain_id = settings.chain_id.to_string();
+
+    let response: serde_json::Value = client
+        .get(&settings.api_url)
+        .query(&[
+            ("chainid", chain_id.as_str()),
+            ("apikey", settings.api_key.as_str()),
+            ("module", "contract"),
+            ("action", "checkverifystatus"),
+            ("guid", guid),
+        ])
+        .send()
+        .await
+        .map_err(|error| Error::Transport(Box::new(error)))?
+        .json()
+        .await
+        .map_err(|error| Error::Decode(format!("Invalid explorer response: {}", error)))?;
+
+    let result = response["result"].as_str().unwrap_or_default();
+    let status = if result.starts_with("Pending") || result.starts_with("In progress") {
+        None
+    } else if result.starts_with("Pass") {
+        Some(VerificationStatus::Verified)
+    } else if result.to_lowercase().contains("already verified") {
+        Some(VerificationStatus::AlreadyVerified)
+    } else {
+        Some(VerificationStatus::Failed(result.to_string()))
+    };
+
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Method, Request, Response, Server};
+    use serde_json::json;
+
+    use super::*;
+
+    // An explorer that queues a submission and reports it pending once before it passes,
+    // recording each request's query and form body
+    async fn explorer() -> (String, Arc<Mutex<Vec<String>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let polls = Arc::new(AtomicU32::new(0));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v2/api", listener.local_addr().unwrap());
+        listener.set_nonblocking(true).unwrap();
+        let seen = requests.clone();
+        let service = make_service_fn(move |_| {
+            let (seen, polls) = (seen.clone(), polls.clone());
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let (seen, polls) = (seen.clone(), polls.clone());
+                    async move {
+                        let (method, query) = (request.method().clone(), request.uri().query().unwrap_or_default().to_string());
+                        let body = String::from_utf8(hyper::body::to_bytes(request.into_body()).await.unwrap().to_vec()).unwrap();
+                        seen.lock().unwrap().push(format!("{}&{}", query, body));
+                        let response = match (method, body.contains("0xdead")) {
+                            (Method::POST, true) => json!({"status": "0", "result": "Contract source code already verified"}),
+                            (Method::POST, false) => json!({"status": "1", "result": "guid-1"}),
+                            _ if polls.fetch_add(1, Ordering::SeqCst) == 0 => json!({"status": "0", "result": "Pending in queue"}),
+                            _ => json!({"status": "1", "result": "Pass - Verified"}),
+                        };
+                        Ok::<_, Infallible>(Response::new(Body::from(response.to_string())))
+                    }
+                }))
+            }
+        });
+        tokio::spawn(Server::from_tcp(listener).unwrap().serve(service));
+        (url, requests)
+    }
+
+    #[tokio::test]
+    async fn submits_and_polls_until_verified() {
+        let (api_url, requests) = explorer().await;
+        let settings = VerificationSettings {
+            api_url,
+            api_key: "KEY".to_string(),
+            chain_id: 10,
+            contract_name: "src/Token.sol:Token".to_string(),
+            compiler_version: "v0.8.24+commit.e11b9ed9".to_string(),
+            constructor_arguments: String::new(),
+            poll_interval: Duration::from_millis(1),
+            max_polls: 5,
+        };
+        let client = reqwest::Client::new();
+        let source = json!({"language": "Solidity"});
+
+        let status = verify_contract(&client, "0x1111111111111111111111111111111111111111", &source, &settings).await.unwrap();
+        assert_eq!(status, VerificationStatus::Verified);
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].starts_with("chainid=10&apikey=KEY&module=contract&action=verifysourcecode"), "{}", requests[0]);
+        assert!(requests[0].contains("contractname=src%2FToken.sol%3AToken") && requests[0].contains("constructorArguements="));
+        assert!(requests[1].contains("action=checkverifystatus&guid=guid-1"));
+
+        let status = verify_contract(&client, "0xdead", &source, &settings).await.unwrap();
+        assert_eq!(status, VerificationStatus::AlreadyVerified);
+    }
+}
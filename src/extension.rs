@@ -0,0 +1,36 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// A JSON-RPC method outside the built-in eth_* helpers (zks_*, debug_*, vendor
+// namespaces), described by its name and typed params/response. Calls go through
+// EthClient::request, so they share the client's transport and layers.
+pub trait RpcMethod {
+    const METHOD: &'static str;
+    // A tuple (or Vec) for positional params, () for none, or a single value
+    type Params: Serialize;
+    type Response: DeserializeOwned;
+}
+
+// Turns serialized params into the positional array JSON-RPC expects
+pub fn to_params(params: serde_json::Value) -> Vec<serde_json::Value> {
+    match params {
+        serde_json::Value::Null => Vec::new(),
+        serde_json::Value::Array(params) => params,
+        param => vec![param],
+    }
+}
+
+// Declares a marker type implementing RpcMethod, e.g.
+// rpc_method!(ZksL1ChainId, "zks_L1ChainId", (), String);
+#[macro_export]
+macro_rules! rpc_method {
+    ($vis:vis $name:ident, $method:literal, $params:ty, $response:ty) => {
+        $vis struct $name;
+
+        impl $crate::extension::RpcMethod for $name {
+            const METHOD: &'static str = $method;
+            type Params = $params;
+            type Response = $response;
+        }
+    };
+}
@@ -0,0 +1,127 @@
+// C ABI for embedding. Every string argument must be a NUL-terminated UTF-8 string.
+// Returned strings are owned by the caller and released with evm_string_free; on
+// failure functions return NULL (or -1) and evm_last_error describes why.
+#![allow(clippy::missing_safety_doc)]
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, UnwindSafe};
+use std::ptr;
+use std::sync::OnceLock;
+
+use crate::abi::{decode_address, decode_string, decode_uint, encode_function_call};
+use crate::error::{Error, Result};
+use crate::EthClient;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+// Shared runtime so blocking C callers don't pay for one per request
+pub(crate) fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| tokio::runtime::Runtime::new().expect("Failed to start tokio runtime"))
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message).unwrap_or_else(|_| CString::from(c"Error message contained a NUL byte"));
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+// Runs the body, storing any error or panic as the last error instead of unwinding into C
+fn guard<T>(fallback: T, body: impl FnOnce() -> Result<T> + UnwindSafe) -> T {
+    match catch_unwind(body) {
+        Ok(Ok(value)) => value,
+        Ok(Err(error)) => {
+            set_last_error(error.to_string());
+            fallback
+        }
+        Err(_) => {
+            set_last_error("Panic inside evm_json_rpc".to_string());
+            fallback
+        }
+    }
+}
+
+unsafe fn read_str<'a>(value: *const c_char) -> Result<&'a str> {
+    if value.is_null() {
+        return Err(Error::InvalidInput("Unexpected NULL string argument".to_string()));
+    }
+    CStr::from_ptr(value).to_str().map_err(|_| Error::InvalidInput("String argument is not valid UTF-8".to_string()))
+}
+
+fn into_raw(value: String) -> Result<*mut c_char> {
+    Ok(CString::new(value).map_err(|_| Error::Decode("Result contains a NUL byte".to_string()))?.into_raw())
+}
+
+// Message of the last failed call on this thread, or NULL; valid until the next failure
+#[no_mangle]
+pub extern "C" fn evm_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+// Frees a string returned by any evm_* function
+#[no_mangle]
+pub unsafe extern "C" fn evm_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+// params points to params_len strings and may be NULL when params_len is 0
+#[no_mangle]
+pub unsafe extern "C" fn evm_encode_function_call(signature: *const c_char, params: *const *const c_char, params_len: usize) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let signature = read_str(signature)?;
+        let mut values = Vec::with_capacity(params_len);
+        for index in 0..params_len {
+            values.push(read_str(*params.add(index))?.to_string());
+        }
+        into_raw(encode_function_call(signature, values)?)
+    })
+}
+
+// Returns 0 on success and -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn evm_decode_uint(hex: *const c_char, out: *mut u64) -> c_int {
+    guard(-1, || {
+        if out.is_null() {
+            return Err(Error::InvalidInput("Unexpected NULL output pointer".to_string()));
+        }
+        *out = decode_uint(read_str(hex)?)?;
+        Ok(0)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn evm_decode_string(hex: *const c_char) -> *mut c_char {
+    guard(ptr::null_mut(), || into_raw(decode_string(read_str(hex)?)?))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn evm_decode_address(hex: *const c_char) -> *mut c_char {
+    guard(ptr::null_mut(), || into_raw(decode_address(read_str(hex)?)?))
+}
+
+// Blocking send; params_json is a JSON array and the result comes back JSON-encoded
+#[no_mangle]
+pub unsafe extern "C" fn evm_rpc_send(rpc_url: *const c_char, method: *const c_char, params_json: *const c_char) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let client = EthClient::new(read_str(rpc_url)?);
+        let method = read_str(method)?;
+        let params: Vec<serde_json::Value> = serde_json::from_str(read_str(params_json)?).map_err(|error| Error::InvalidInput(format!("Invalid params JSON: {}", error)))?;
+        let result = runtime().block_on(client.send(method, params))?;
+        into_raw(result.to_string())
+    })
+}
+
+// Blocking eth_call, following CCIP-Read lookups like EthClient::call
+#[no_mangle]
+pub unsafe extern "C" fn evm_call(rpc_url: *const c_char, to: *const c_char, data: *const c_char) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let client = EthClient::new(read_str(rpc_url)?);
+        let (to, data) = (read_str(to)?, read_str(data)?);
+        let result = runtime().block_on(client.call(to, data))?;
+        into_raw(result)
+    })
+}
@@ -0,0 +1,375 @@
+use std::fmt;
+use std::sync::RwLock;
+
+use ethabi::ethereum_types::U256;
+use serde::Serializer;
+
+use crate::utils::to_checksum_address;
+
+static GLOBAL: RwLock<FormatOptions> = RwLock::new(FormatOptions::new());
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AddressFormat {
+    // EIP-55 mixed case
+    #[default]
+    Checksum,
+    Lowercase,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HashFormat {
+    #[default]
+    Full,
+    // 0x1234ab…cdef, for tables and logs
+    Abbreviated,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Notation {
+    // 1234567.89
+    #[default]
+    Plain,
+    // 1.23456789e6
+    Scientific,
+    // 1.23456789e6, or 12.345e3: scientific with the exponent a multiple of three
+    Engineering,
+}
+
+// How big numbers such as token amounts are rendered. The defaults print the exact
+// value with a dot and no grouping, like utils::format_units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    pub notation: Notation,
+    // Between groups of three integer digits in plain notation, e.g. ',' or ' '
+    pub group_separator: Option<char>,
+    pub decimal_separator: char,
+    // Rounds half up to this many significant digits
+    pub significant_digits: Option<usize>,
+}
+
+impl NumberFormat {
+    pub const fn new() -> Self {
+        NumberFormat {
+            notation: Notation::Plain,
+            group_separator: None,
+            decimal_separator: '.',
+            significant_digits: None,
+        }
+    }
+
+    pub fn with_notation(mut self, notation: Notation) -> Self {
+        self.notation = notation;
+        self
+    }
+
+    // e.g. (Some(','), '.') for en-US or (Some('.'), ',') for de-DE
+    pub fn with_separators(mut self, group: Option<char>, decimal: char) -> Self {
+        self.group_separator = group;
+        self.decimal_separator = decimal;
+        self
+    }
+
+    pub fn with_significant_digits(mut self, digits: usize) -> Self {
+        self.significant_digits = Some(digits.max(1));
+        self
+    }
+
+    // Renders `value` scaled down by `decimals`, so (1500, 3) is 1.5
+    pub fn format(&self, value: U256, decimals: u32) -> String {
+        if value.is_zero() {
+            return "0".to_string();
+        }
+        let mut digits = value.to_string();
+        // Exponent of the leading digit: digits[0] * 10^exponent
+        let mut exponent = digits.len() as i64 - 1 - decimals as i64;
+        if let Some(significant) = self.significant_digits {
+            if round_digits(&mut digits, significant) {
+                exponent += 1;
+            }
+        }
+        let digits = digits.trim_end_matches('0');
+
+        match self.notation {
+            Notation::Plain => {
+                let (integer, fraction) = if exponent >= 0 {
+                    let split = exponent as usize + 1;
+                    let integer = format!("{:0<width$}", &digits[..split.min(digits.len())], width = split);
+                    (integer, digits.get(split..).unwrap_or_default().to_string())
+                } else {
+                    ("0".to_string(), format!("{}{}", "0".repeat((-exponent - 1) as usize), digits))
+                };
+                self.join(&self.group(&integer), &fraction)
+            }
+            Notation::Scientific => format!("{}e{}", self.join(&digits[..1], &digits[1..]), exponent),
+            Notation::Engineering => {
+                let engineering = exponent.div_euclid(3) * 3;
+                let split = (exponent - engineering) as usize + 1;
+                let integer = format!("{:0<width$}", &digits[..split.min(digits.len())], width = split);
+                format!("{}e{}", self.join(&integer, digits.get(split..).unwrap_or_default()), engineering)
+            }
+        }
+    }
+
+    fn join(&self, integer: &str, fraction: &str) -> String {
+        if fraction.is_empty() {
+            integer.to_string()
+        } else {
+            format!("{}{}{}", integer, self.decimal_separator, fraction)
+        }
+    }
+
+    fn group(&self, integer: &str) -> String {
+        let Some(separator) = self.group_separator else { return integer.to_string() };
+        let mut grouped = String::new();
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index).is_multiple_of(3) {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Rounds a decimal digit string half up to `significant` digits, zero filling the rest.
+// Returns true when rounding carried into a new leading digit (999 -> 1000).
+fn round_digits(digits: &mut String, significant: usize) -> bool {
+    if digits.len() <= significant {
+        return false;
+    }
+    let round_up = digits.as_bytes()[significant] >= b'5';
+    let mut kept: Vec<u8> = digits.as_bytes()[..significant].to_vec();
+    let mut carry = round_up;
+    for digit in kept.iter_mut().rev() {
+        if !carry {
+            break;
+        }
+        if *digit == b'9' {
+            *digit = b'0';
+        } else {
+            *digit += 1;
+            carry = false;
+        }
+    }
+    let length = digits.len();
+    *digits = String::from_utf8(kept).unwrap_or_default();
+    if carry {
+        digits.insert(0, '1');
+    }
+    *digits = format!("{:0<width$}", digits, width = length + carry as usize);
+    carry
+}
+
+// How addresses, hashes and numbers are rendered in human-facing output: the CLI,
+// trace trees, labels and automation payloads. Values sent to the node are never affected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub address: AddressFormat,
+    pub hash: HashFormat,
+    pub number: NumberFormat,
+}
+
+impl FormatOptions {
+    pub const fn new() -> Self {
+        FormatOptions {
+            address: AddressFormat::Checksum,
+            hash: HashFormat::Full,
+            number: NumberFormat::new(),
+        }
+    }
+
+    // The process-wide options used by address(), hash() and number()
+    pub fn global() -> Self {
+        *GLOBAL.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn set_global(options: FormatOptions) {
+        *GLOBAL.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = options;
+    }
+
+    pub fn with_address(mut self, address: AddressFormat) -> Self {
+        self.address = address;
+        self
+    }
+
+    pub fn with_hash(mut self, hash: HashFormat) -> Self {
+        self.hash = hash;
+        self
+    }
+
+    pub fn with_number(mut self, number: NumberFormat) -> Self {
+        self.number = number;
+        self
+    }
+
+    // Anything that isn't a 20-byte hex address is returned unchanged
+    pub fn format_address(&self, address: &str) -> String {
+        let digits = address.trim_start_matches("0x");
+        if digits.len() != 40 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return address.to_string();
+        }
+        match self.address {
+            AddressFormat::Checksum => to_checksum_address(digits),
+            AddressFormat::Lowercase => format!("0x{}", digits.to_lowercase()),
+        }
+    }
+
+    pub fn format_hash(&self, hash: &str) -> String {
+        let digits = hash.trim_start_matches("0x").to_lowercase();
+        match self.hash {
+            HashFormat::Abbreviated if digits.len() > 10 => format!("0x{}…{}", &digits[..6], &digits[digits.len() - 4..]),
+            _ => format!("0x{}", digits),
+        }
+    }
+}
+
+// Formats with the global options
+pub fn address(address: &str) -> String {
+    FormatOptions::global().format_address(address)
+}
+
+pub fn hash(hash: &str) -> String {
+    FormatOptions::global().format_hash(hash)
+}
+
+pub fn number(value: U256, decimals: u32) -> String {
+    FormatOptions::global().number.format(value, decimals)
+}
+
+// A raw integer amount with its token's decimals. Display uses the global number format
+// and appends the symbol when there is one; {:e} forces scientific notation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenAmount {
+    pub value: U256,
+    pub decimals: u32,
+    pub symbol: Option<String>,
+}
+
+impl TokenAmount {
+    pub fn new(value: U256, decimals: u32) -> Self {
+        TokenAmount { value, decimals, symbol: None }
+    }
+
+    // 18 decimals, symbol ETH
+    pub fn ether(wei: U256) -> Self {
+        Self::new(wei, 18).with_symbol("ETH")
+    }
+
+    pub fn with_symbol(mut self, symbol: &str) -> Self {
+        self.symbol = Some(symbol.to_string());
+        self
+    }
+
+    pub fn format_with(&self, format: &NumberFormat) -> String {
+        let amount = format.format(self.value, self.decimals);
+        match &self.symbol {
+            Some(symbol) => format!("{} {}", amount, symbol),
+            None => amount,
+        }
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format_with(&FormatOptions::global().number))
+    }
+}
+
+impl fmt::LowerExp for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format_with(&FormatOptions::global().number.with_notation(Notation::Scientific)))
+    }
+}
+
+// For #[serde(serialize_with = "...")] on output types holding 0x strings
+pub fn serialize_address<S: Serializer>(value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&address(value))
+}
+
+pub fn serialize_hash<S: Serializer>(value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hash(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(value: u64, decimals: u32) -> TokenAmount {
+        TokenAmount::new(U256::from(value), decimals)
+    }
+
+    #[test]
+    fn formats_plain_numbers() {
+        let plain = NumberFormat::new();
+        assert_eq!(plain.format(U256::zero(), 18), "0");
+        assert_eq!(plain.format(U256::from(1500), 3), "1.5");
+        assert_eq!(plain.format(U256::from(1), 18), "0.000000000000000001");
+        assert_eq!(plain.format(U256::from(1_000_000), 0), "1000000");
+        assert_eq!(plain.format(U256::exp10(18), 18), "1");
+        assert_eq!(plain.format(U256::MAX, 18), "115792089237316195423570985008687907853269984665640564039457.584007913129639935");
+    }
+
+    #[test]
+    fn groups_and_localizes() {
+        let en = NumberFormat::new().with_separators(Some(','), '.');
+        assert_eq!(en.format(U256::from(1_234_567_890u64), 2), "12,345,678.9");
+        assert_eq!(en.format(U256::from(123u64), 0), "123");
+        assert_eq!(en.format(U256::from(1234u64), 0), "1,234");
+        let de = NumberFormat::new().with_separators(Some('.'), ',');
+        assert_eq!(de.format(U256::from(123_456_789u64), 3), "123.456,789");
+    }
+
+    #[test]
+    fn rounds_half_up_to_significant_digits() {
+        let three = NumberFormat::new().with_significant_digits(3);
+        assert_eq!(three.format(U256::from(12_345u64), 0), "12300");
+        assert_eq!(three.format(U256::from(12_350u64), 0), "12400");
+        assert_eq!(three.format(U256::from(12_349u64), 0), "12300");
+        // Carries into a new leading digit
+        assert_eq!(three.format(U256::from(9_995u64), 0), "10000");
+        assert_eq!(three.format(U256::from(999_600u64), 9), "0.001");
+        assert_eq!(three.format(U256::from(123u64), 2), "1.23");
+        // Zero is never rounded and fewer digits than requested are left alone
+        assert_eq!(three.format(U256::zero(), 2), "0");
+        assert_eq!(three.format(U256::from(5u64), 0), "5");
+        // At least one significant digit is kept
+        assert_eq!(NumberFormat::new().with_significant_digits(0).format(U256::from(96u64), 0), "100");
+    }
+
+    #[test]
+    fn formats_scientific_and_engineering() {
+        let scientific = NumberFormat::new().with_notation(Notation::Scientific);
+        assert_eq!(scientific.format(U256::from(123_456_789u64), 2), "1.23456789e6");
+        assert_eq!(scientific.format(U256::from(5u64), 3), "5e-3");
+        assert_eq!(scientific.with_significant_digits(2).format(U256::from(995u64), 0), "1e3");
+        let engineering = NumberFormat::new().with_notation(Notation::Engineering);
+        assert_eq!(engineering.format(U256::from(12_345u64), 0), "12.345e3");
+        assert_eq!(engineering.format(U256::from(1_000u64), 0), "1e3");
+        assert_eq!(engineering.format(U256::from(5u64), 4), "500e-6");
+    }
+
+    #[test]
+    fn formats_token_amounts() {
+        let plain = NumberFormat::new();
+        assert_eq!(amount(1_500_000, 6).with_symbol("USDC").format_with(&plain), "1.5 USDC");
+        assert_eq!(amount(42, 0).format_with(&plain), "42");
+        assert_eq!(TokenAmount::ether(U256::exp10(17)).format_with(&plain), "0.1 ETH");
+    }
+
+    #[test]
+    fn formats_addresses_and_hashes() {
+        let options = FormatOptions::new();
+        assert_eq!(options.format_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"), "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+        assert_eq!(options.with_address(AddressFormat::Lowercase).format_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"), "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        assert_eq!(options.format_address("vitalik.eth"), "vitalik.eth");
+        let hash = "0x88DF016429689C079F3B2F6AD39FA052532C56795B733DA78A91EBE6A713944B";
+        assert_eq!(options.format_hash(hash), hash.to_lowercase());
+        assert_eq!(options.with_hash(HashFormat::Abbreviated).format_hash(hash), "0x88df01…944b");
+    }
+}
@@ -0,0 +1,308 @@
+use std::time::Duration;
+
+use ethabi::ethereum_types::{U256, U64};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::headers::get_header;
+use crate::poll::jittered;
+use crate::utils::parse_quantity;
+
+pub const TX_BASE_GAS: u64 = 21_000;
+pub const TX_CREATE_GAS: u64 = 32_000;
+// EIP-2028 calldata costs
+pub const TX_DATA_ZERO_GAS: u64 = 4;
+pub const TX_DATA_NON_ZERO_GAS: u64 = 16;
+// EIP-2930 access list costs
+pub const ACCESS_LIST_ADDRESS_GAS: u64 = 2_400;
+pub const ACCESS_LIST_STORAGE_KEY_GAS: u64 = 1_900;
+// EIP-3860 initcode limits
+pub const INITCODE_WORD_GAS: u64 = 2;
+pub const MAX_INITCODE_SIZE: usize = 49_152;
+// EIP-7623 calldata floor, per token: a zero byte is one token, any other byte four
+pub const TX_DATA_FLOOR_TOKEN_GAS: u64 = 10;
+// EIP-7702 charge per authorization, as if it delegated an empty account
+pub const AUTHORIZATION_GAS: u64 = 25_000;
+// OP-stack GasPriceOracle predeploy
+pub const OP_GAS_PRICE_ORACLE: &str = "0x420000000000000000000000000000000000000F";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListItem {
+    pub address: String,
+    pub storage_keys: Vec<String>,
+}
+
+// Gas charged before any execution: base cost, calldata, creation, access list and
+// EIP-7702 authorizations
+pub fn intrinsic_gas(data: &[u8], is_create: bool, access_list: &[AccessListItem], authorizations: usize) -> u64 {
+    let zero_bytes = data.iter().filter(|byte| **byte == 0).count() as u64;
+    let non_zero_bytes = data.len() as u64 - zero_bytes;
+
+    let mut gas = TX_BASE_GAS + zero_bytes * TX_DATA_ZERO_GAS + non_zero_bytes * TX_DATA_NON_ZERO_GAS;
+    if is_create {
+        gas += TX_CREATE_GAS + (data.len() as u64).div_ceil(32) * INITCODE_WORD_GAS;
+    }
+    for item in access_list {
+        gas += ACCESS_LIST_ADDRESS_GAS + item.storage_keys.len() as u64 * ACCESS_LIST_STORAGE_KEY_GAS;
+    }
+    gas + authorizations as u64 * AUTHORIZATION_GAS
+}
+
+// Least gas an EIP-7623 transaction may use, however little it executes
+pub fn calldata_floor_gas(data: &[u8]) -> u64 {
+    let tokens: u64 = data.iter().map(|byte| if *byte == 0 { 1 } else { 4 }).sum();
+    TX_BASE_GAS + tokens * TX_DATA_FLOOR_TOKEN_GAS
+}
+
+// Chains known to have activated Prague, and with it the EIP-7623 calldata floor:
+// Ethereum mainnet, Sepolia, Holesky and Hoodi. L2s are left out until they're known to.
+pub fn has_calldata_floor(chain_id: u64) -> bool {
+    matches!(chain_id, 1 | 11_155_111 | 17_000 | 560_048)
+}
+
+// Rejects transactions the node would refuse before execution. The EIP-7623 floor only
+// applies when `calldata_floor` says the chain enforces it.
+pub fn validate_gas_limit(gas_limit: u64, data: &[u8], is_create: bool, access_list: &[AccessListItem], authorizations: usize, calldata_floor: bool, block_gas_limit: u64) -> Result<()> {
+    if is_create && data.len() > MAX_INITCODE_SIZE {
+        return Err(Error::InvalidInput(format!("Initcode is {} bytes, above the {} byte limit", data.len(), MAX_INITCODE_SIZE)));
+    }
+
+    let intrinsic = intrinsic_gas(data, is_create, access_list, authorizations);
+    if gas_limit < intrinsic {
+        return Err(Error::InvalidInput(format!("Gas limit {} is below the intrinsic gas {}", gas_limit, intrinsic)));
+    }
+    let floor = calldata_floor_gas(data);
+    if calldata_floor && gas_limit < floor {
+        return Err(Error::InvalidInput(format!("Gas limit {} is below the EIP-7623 calldata floor {}", gas_limit, floor)));
+    }
+    if gas_limit > block_gas_limit {
+        return Err(Error::InvalidInput(format!("Gas limit {} exceeds the block gas limit {}", gas_limit, block_gas_limit)));
+    }
+
+    Ok(())
+}
+
+pub async fn get_block_gas_limit(client: &EthClient) -> Result<u64> {
+    let block = client.send("eth_getBlockByNumber", vec![serde_json::json!("latest"), serde_json::json!(false)]).await?;
+    parse_quantity(&block["gasLimit"])
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalldataStats {
+    pub size: usize,
+    pub zero_bytes: usize,
+    pub non_zero_bytes: usize,
+    // L1 calldata gas at EIP-2028 prices
+    pub calldata_gas: u64,
+}
+
+// OP-stack (Ecotone) L1 fee inputs, as exposed by the GasPriceOracle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L1FeeParams {
+    pub l1_base_fee: U256,
+    pub blob_base_fee: U256,
+    pub base_fee_scalar: u64,
+    pub blob_base_fee_scalar: u64,
+}
+
+// Accepts calldata as produced by encode_function_call, with or without 0x
+pub fn analyze_calldata(calldata: &str) -> Result<CalldataStats> {
+    let data = hex::decode(calldata.trim_start_matches("0x")).map_err(|error| Error::InvalidInput(format!("Invalid calldata: {}", error)))?;
+    let zero_bytes = data.iter().filter(|byte| **byte == 0).count();
+    let non_zero_bytes = data.len() - zero_bytes;
+
+    Ok(CalldataStats {
+        size: data.len(),
+        zero_bytes,
+        non_zero_bytes,
+        calldata_gas: zero_bytes as u64 * TX_DATA_ZERO_GAS + non_zero_bytes as u64 * TX_DATA_NON_ZERO_GAS,
+    })
+}
+
+impl CalldataStats {
+    // Estimated rollup L1 data fee in wei for these bytes alone; the signed transaction
+    // envelope adds roughly another hundred bytes on top
+    pub fn l1_data_fee(&self, params: &L1FeeParams) -> U256 {
+        let weighted_gas_price = U256::from(16) * U256::from(params.base_fee_scalar) * params.l1_base_fee
+            + U256::from(params.blob_base_fee_scalar) * params.blob_base_fee;
+        U256::from(self.calldata_gas) * weighted_gas_price / U256::from(16_000_000u64)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessListComparison {
+    pub access_list: Vec<AccessListItem>,
+    pub gas_without: u64,
+    pub gas_with: u64,
+}
+
+impl AccessListComparison {
+    // Only worth including when the warm-access discounts outweigh the list's own cost
+    pub fn recommended(&self) -> bool {
+        !self.access_list.is_empty() && self.gas_with < self.gas_without
+    }
+
+    pub fn savings(&self) -> i64 {
+        self.gas_without as i64 - self.gas_with as i64
+    }
+}
+
+// Estimates a call object ({from, to, data, value}) with and without the access list
+// the node generates for it
+pub async fn compare_access_list(client: &EthClient, transaction: &serde_json::Value) -> Result<AccessListComparison> {
+    let created = client.send("eth_createAccessList", vec![transaction.clone(), serde_json::json!("latest")]).await?;
+    let access_list: Vec<AccessListItem> = serde_json::from_value(created["accessList"].clone()).map_err(|error| Error::Decode(format!("Invalid eth_createAccessList response: {}", error)))?;
+
+    let mut with_list = transaction.clone();
+    with_list["accessList"] = serde_json::json!(access_list);
+
+    let gas_without = client.send("eth_estimateGas", vec![transaction.clone()]).await?;
+    let gas_with = client.send("eth_estimateGas", vec![with_list]).await?;
+
+    Ok(AccessListComparison {
+        access_list,
+        gas_without: parse_quantity(&gas_without)?,
+        gas_with: parse_quantity(&gas_with)?,
+    })
+}
+
+pub async fn get_op_l1_fee_params(client: &EthClient) -> Result<L1FeeParams> {
+    Ok(L1FeeParams {
+        l1_base_fee: oracle_call(client, "l1BaseFee()").await?,
+        blob_base_fee: oracle_call(client, "blobBaseFee()").await?,
+        base_fee_scalar: oracle_call(client, "baseFeeScalar()").await?.low_u64(),
+        blob_base_fee_scalar: oracle_call(client, "blobBaseFeeScalar()").await?.low_u64(),
+    })
+}
+
+async fn oracle_call(client: &EthClient, signature: &str) -> Result<U256> {
+    let result = client.call_function(OP_GAS_PRICE_ORACLE, signature, vec![]).await?;
+    U256::from_str_radix(result.trim_start_matches("0x"), 16).map_err(|error| Error::Decode(format!("Invalid {} result: {}", signature, error)))
+}
+
+// eth_feeHistory result. base_fee_per_gas has one entry more than the range: the base
+// fee of the block after newest_block. reward holds one row per block with a value per
+// requested percentile and is empty when no percentiles were asked for.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeHistory {
+    pub oldest_block: U64,
+    pub base_fee_per_gas: Vec<U256>,
+    pub gas_used_ratio: Vec<f64>,
+    #[serde(default)]
+    pub reward: Vec<Vec<U256>>,
+    // Present since Cancun
+    #[serde(default)]
+    pub base_fee_per_blob_gas: Vec<U256>,
+    #[serde(default)]
+    pub blob_gas_used_ratio: Vec<f64>,
+}
+
+impl FeeHistory {
+    // Base fee the next block will charge
+    pub fn next_base_fee(&self) -> Option<U256> {
+        self.base_fee_per_gas.last().copied()
+    }
+}
+
+// Blocks sampled per poll by watch_base_fee; after a longer gap only the latest counts
+pub const MAX_WATCH_BLOCKS: u64 = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Below,
+    Above,
+}
+
+// The base fee moved to the given side of a threshold. A fee equal to the threshold
+// counts as above.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaseFeeCrossing {
+    pub threshold: U256,
+    pub direction: Direction,
+    pub block_number: u64,
+    pub base_fee: U256,
+}
+
+// Samples the base fee of every new block and reports each threshold it crosses. The
+// first block reports which side of every threshold the fee starts on. Watching stops
+// when the receiver is dropped; RPC errors are retried on the next interval.
+pub fn watch_base_fee(client: &EthClient, thresholds: &[U256], interval: Duration) -> mpsc::UnboundedReceiver<BaseFeeCrossing> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let client = client.clone();
+    let thresholds = thresholds.to_vec();
+
+    tokio::spawn(async move {
+        let mut last_block: Option<u64> = None;
+        let mut sides: Vec<Option<Direction>> = vec![None; thresholds.len()];
+        while !sender.is_closed() {
+            if let Ok(latest) = client.get_block_number().await {
+                // A head behind the last seen block (a lagging node behind a load
+                // balancer, or a reorg to a shorter chain) has nothing new yet
+                let first = match last_block.map(|last| (last, latest.checked_sub(last))) {
+                    Some((last, Some(gap))) if gap <= MAX_WATCH_BLOCKS => last + 1,
+                    Some((_, None)) => latest + 1,
+                    _ => latest,
+                };
+                for number in first..=latest {
+                    let Ok(header) = get_header(&client, &format!("0x{:x}", number)).await else { break };
+                    last_block = Some(number);
+                    // Pre-London blocks have no base fee
+                    let Some(base_fee) = header.base_fee else { continue };
+                    for (threshold, side) in thresholds.iter().zip(sides.iter_mut()) {
+                        let direction = if base_fee < *threshold { Direction::Below } else { Direction::Above };
+                        if *side == Some(direction) {
+                            continue;
+                        }
+                        *side = Some(direction);
+                        let crossing = BaseFeeCrossing { threshold: *threshold, direction, block_number: number, base_fee };
+                        if sender.send(crossing).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+            tokio::time::sleep(jittered(interval)).await;
+        }
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn charges_intrinsic_gas_by_calldata_creation_and_access_list() {
+        assert_eq!(intrinsic_gas(&[], false, &[], 0), 21_000);
+        assert_eq!(intrinsic_gas(&[0, 0, 1], false, &[], 0), 21_000 + 2 * 4 + 16);
+        // 33 bytes of initcode are two words
+        assert_eq!(intrinsic_gas(&[1; 33], true, &[], 0), 21_000 + 33 * 16 + 32_000 + 2 * 2);
+        let access_list = [
+            AccessListItem { address: "0x1111111111111111111111111111111111111111".to_string(), storage_keys: vec!["0x01".to_string(), "0x02".to_string()] },
+            AccessListItem { address: "0x2222222222222222222222222222222222222222".to_string(), storage_keys: Vec::new() },
+        ];
+        assert_eq!(intrinsic_gas(&[], false, &access_list, 0), 21_000 + 2 * 2_400 + 2 * 1_900);
+        assert_eq!(intrinsic_gas(&[], false, &[], 2), 21_000 + 2 * 25_000);
+    }
+
+    #[test]
+    fn enforces_the_calldata_floor_and_block_limit() {
+        assert_eq!(calldata_floor_gas(&[]), 21_000);
+        assert_eq!(calldata_floor_gas(&[0, 1]), 21_000 + (1 + 4) * 10);
+
+        assert!(validate_gas_limit(21_000, &[], false, &[], 0, true, 30_000_000).is_ok());
+        assert!(matches!(validate_gas_limit(20_999, &[], false, &[], 0, true, 30_000_000), Err(Error::InvalidInput(message)) if message.contains("intrinsic")));
+        // 1000 non-zero bytes cost 37,000 intrinsic but 61,000 at the floor
+        let data = [1; 1000];
+        assert!(matches!(validate_gas_limit(40_000, &data, false, &[], 0, true, 30_000_000), Err(Error::InvalidInput(message)) if message.contains("EIP-7623")));
+        assert!(validate_gas_limit(61_000, &data, false, &[], 0, true, 30_000_000).is_ok());
+        assert!(validate_gas_limit(40_000, &data, false, &[], 0, false, 30_000_000).is_ok());
+        assert!(has_calldata_floor(11_155_111) && !has_calldata_floor(10));
+        assert!(validate_gas_limit(30_000_001, &[], false, &[], 0, true, 30_000_000).is_err());
+        assert!(validate_gas_limit(30_000_000, &[0; MAX_INITCODE_SIZE + 1], true, &[], 0, true, 30_000_000).is_err());
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use ethabi::ethereum_types::H256;
+use futures_util::future::try_join_all;
+use serde_json::json;
+
+use crate::blocks::BlockTag;
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::logs::{Filter, Log};
+use crate::utils::parse_quantity;
+
+// Transaction fields BlockFetcher can select. The hash and index always come along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionField {
+    From,
+    To,
+    Value,
+    Gas,
+    GasPrice,
+    Nonce,
+    Input,
+    // From the receipt, so a request per transaction without GraphQL
+    Status,
+}
+
+impl TransactionField {
+    pub const ALL: [TransactionField; 8] = [
+        TransactionField::From,
+        TransactionField::To,
+        TransactionField::Value,
+        TransactionField::Gas,
+        TransactionField::GasPrice,
+        TransactionField::Nonce,
+        TransactionField::Input,
+        TransactionField::Status,
+    ];
+
+    // Key in the JSON-RPC transaction object, used for both sources
+    pub fn name(self) -> &'static str {
+        match self {
+            TransactionField::From => "from",
+            TransactionField::To => "to",
+            TransactionField::Value => "value",
+            TransactionField::Gas => "gas",
+            TransactionField::GasPrice => "gasPrice",
+            TransactionField::Nonce => "nonce",
+            TransactionField::Input => "input",
+            TransactionField::Status => "status",
+        }
+    }
+
+    fn selection(self) -> &'static str {
+        match self {
+            TransactionField::From => "from { address }",
+            TransactionField::To => "to { address }",
+            TransactionField::Input => "inputData",
+            field => field.name(),
+        }
+    }
+}
+
+// A block with the selected fields of its transactions, each keyed as in JSON-RPC
+// with quantities as hex, and every log it emitted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockData {
+    pub number: u64,
+    pub hash: H256,
+    pub timestamp: u64,
+    pub transactions: Vec<serde_json::Value>,
+    pub logs: Vec<Log>,
+}
+
+// Fetches block ranges in one EIP-1767 GraphQL query (geth --graphql) when given an
+// endpoint, instead of a full block per number plus eth_getLogs. If the endpoint fails
+// or rejects the query, that and every later fetch use JSON-RPC instead; both give
+// the same BlockData.
+#[derive(Clone)]
+pub struct BlockFetcher {
+    client: EthClient,
+    fields: Vec<TransactionField>,
+    graphql_url: Option<String>,
+    graphql_failed: Arc<AtomicBool>,
+}
+
+impl BlockFetcher {
+    pub fn new(client: &EthClient, fields: &[TransactionField]) -> Self {
+        BlockFetcher {
+            client: client.clone(),
+            fields: fields.to_vec(),
+            graphql_url: None,
+            graphql_failed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn with_graphql(mut self, url: &str) -> Self {
+        self.graphql_url = Some(url.to_string());
+        self
+    }
+
+    pub fn client(&self) -> &EthClient {
+        &self.client
+    }
+
+    // Whether fetches still go to the GraphQL endpoint
+    pub fn uses_graphql(&self) -> bool {
+        self.graphql_url.is_some() && !self.graphql_failed.load(Ordering::Relaxed)
+    }
+
+    // Blocks `from` through `to`, inclusive, in order
+    pub async fn fetch(&self, from: u64, to: u64) -> Result<Vec<BlockData>> {
+        if let Some(url) = self.graphql_url.as_deref().filter(|_| self.uses_graphql()) {
+            match self.fetch_graphql(url, from, to).await {
+                Ok(blocks) => return Ok(blocks),
+                Err(error) => {
+                    tracing::warn!(%error, "GraphQL unavailable, falling back to JSON-RPC");
+                    self.graphql_failed.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+        self.fetch_json_rpc(from, to).await
+    }
+
+    fn query(&self) -> String {
+        let fields: Vec<&str> = self.fields.iter().map(|field| field.selection()).collect();
+        format!(
+            "query Blocks($from: Long!, $to: Long!) {{ blocks(from: $from, to: $to) {{ number hash timestamp transactions {{ hash index {} logs {{ index account {{ address }} topics data }} }} }} }}",
+            fields.join(" ")
+        )
+    }
+
+    async fn fetch_graphql(&self, url: &str, from: u64, to: u64) -> Result<Vec<BlockData>> {
+        let body = json!({"query": self.query(), "variables": {"from": from, "to": to}});
+        let response = self.client.http_client().post(url).json(&body).send().await.map_err(|error| Error::Transport(Box::new(error.without_url())))?;
+        if !response.status().is_success() {
+            return Err(Error::Unsupported(format!("GraphQL endpoint answered {}", response.status())));
+        }
+        let response: serde_json::Value = response.json().await.map_err(|error| Error::Decode(format!("Invalid GraphQL response: {}", error.without_url())))?;
+        if let Some(errors) = response.get("errors").filter(|errors| !errors.is_null()) {
+            return Err(Error::Unsupported(format!("GraphQL query failed: {}", errors)));
+        }
+        let blocks = response["data"]["blocks"].as_array().ok_or_else(|| Error::Decode("GraphQL response has no blocks".to_string()))?;
+        blocks.iter().map(|block| self.graphql_block(block)).collect()
+    }
+
+    fn graphql_block(&self, block: &serde_json::Value) -> Result<BlockData> {
+        let number = quantity(&block["number"]);
+        let hash = parse_hash(&block["hash"])?;
+        let mut transactions = Vec::new();
+        let mut logs = Vec::new();
+        for transaction in block["transactions"].as_array().into_iter().flatten() {
+            let mut record = json!({"hash": transaction["hash"], "transactionIndex": quantity(&transaction["index"])});
+            for field in &self.fields {
+                record[field.name()] = match field {
+                    TransactionField::From | TransactionField::To => transaction[field.name()]["address"].clone(),
+                    TransactionField::Input => transaction["inputData"].clone(),
+                    _ => quantity(&transaction[field.name()]),
+                };
+            }
+            for log in transaction["logs"].as_array().into_iter().flatten() {
+                let log = json!({
+                    "address": log["account"]["address"],
+                    "topics": log["topics"],
+                    "data": log["data"],
+                    "blockNumber": number,
+                    "blockHash": hash,
+                    "transactionHash": transaction["hash"],
+                    "transactionIndex": record["transactionIndex"],
+                    "logIndex": quantity(&log["index"]),
+                });
+                logs.push(serde_json::from_value(log).map_err(|error| Error::Decode(format!("Invalid GraphQL log: {}", error)))?);
+            }
+            transactions.push(record);
+        }
+        Ok(BlockData {
+            number: parse_quantity(&number)?,
+            hash,
+            timestamp: parse_quantity(&quantity(&block["timestamp"]))?,
+            transactions,
+            logs,
+        })
+    }
+
+    async fn fetch_json_rpc(&self, from: u64, to: u64) -> Result<Vec<BlockData>> {
+        let filter = Filter::new().with_from_block(BlockTag::Number(from)).with_to_block(BlockTag::Number(to));
+        let (blocks, logs) = tokio::try_join!(try_join_all((from..=to).map(|number| self.json_rpc_block(number))), self.client.get_logs(&filter))?;
+        let mut logs_by_block: BTreeMap<u64, Vec<Log>> = BTreeMap::new();
+        for log in logs {
+            logs_by_block.entry(log.block_number.map_or(0, |number| number.as_u64())).or_default().push(log);
+        }
+        Ok(blocks
+            .into_iter()
+            .map(|mut block| {
+                block.logs = logs_by_block.remove(&block.number).unwrap_or_default();
+                block
+            })
+            .collect())
+    }
+
+    async fn json_rpc_block(&self, number: u64) -> Result<BlockData> {
+        let block = self.client.send("eth_getBlockByNumber", vec![json!(format!("0x{:x}", number)), json!(true)]).await?;
+        if block.is_null() {
+            return Err(Error::InvalidInput(format!("Block {} does not exist yet", number)));
+        }
+        let transactions = block["transactions"].as_array().cloned().unwrap_or_default();
+        let transactions = try_join_all(transactions.into_iter().map(|transaction| async move {
+            let mut record = json!({"hash": transaction["hash"], "transactionIndex": transaction["transactionIndex"]});
+            for field in &self.fields {
+                record[field.name()] = match field {
+                    TransactionField::Status => self.client.send("eth_getTransactionReceipt", vec![transaction["hash"].clone()]).await?["status"].clone(),
+                    field => transaction[field.name()].clone(),
+                };
+            }
+            Ok::<_, Error>(record)
+        }))
+        .await?;
+        Ok(BlockData {
+            number,
+            hash: parse_hash(&block["hash"])?,
+            timestamp: parse_quantity(&block["timestamp"])?,
+            transactions,
+            logs: Vec::new(),
+        })
+    }
+}
+
+// GraphQL Long values are hex strings on current geth and plain numbers on older
+// versions; JSON-RPC always uses hex
+fn quantity(value: &serde_json::Value) -> serde_json::Value {
+    match value.as_u64() {
+        Some(number) => json!(format!("0x{:x}", number)),
+        None => value.clone(),
+    }
+}
+
+fn parse_hash(value: &serde_json::Value) -> Result<H256> {
+    serde_json::from_value(value.clone()).map_err(|_| Error::Decode(format!("Invalid block hash {}", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::net::TcpListener;
+
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Response, Server};
+
+    use super::*;
+    use crate::testing::{Failure, MockNode};
+
+    const TX: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+    const TOKEN: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const SENDER: &str = "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f";
+
+    // Block 16 holding one transaction that logged once, over JSON-RPC
+    fn node() -> EthClient {
+        MockNode::new(|method, _| match method {
+            "eth_getBlockByNumber" => Ok(json!({
+                "number": "0x10",
+                "hash": H256::from_low_u64_be(16),
+                "timestamp": "0x64",
+                "transactions": [{"hash": TX, "transactionIndex": "0x0", "from": SENDER, "to": TOKEN, "value": "0x0", "nonce": "0x7", "input": "0xa9059cbb"}],
+            })),
+            "eth_getTransactionReceipt" => Ok(json!({"status": "0x1"})),
+            "eth_getLogs" => Ok(json!([{
+                "address": TOKEN,
+                "topics": [H256::from_low_u64_be(1)],
+                "data": "0x",
+                "blockNumber": "0x10",
+                "blockHash": H256::from_low_u64_be(16),
+                "transactionHash": TX,
+                "transactionIndex": "0x0",
+                "logIndex": "0x0",
+            }])),
+            _ => Err(Failure::unexpected(method)),
+        })
+        .client()
+    }
+
+    // The same block from geth's GraphQL, with Long values as an older geth sends them
+    async fn graphql_server() -> String {
+        let block = json!({"data": {"blocks": [{
+            "number": 16,
+            "hash": H256::from_low_u64_be(16),
+            "timestamp": "0x64",
+            "transactions": [{
+                "hash": TX,
+                "index": 0,
+                "from": {"address": SENDER},
+                "nonce": "0x7",
+                "status": 1,
+                "logs": [{"index": 0, "account": {"address": TOKEN}, "topics": [H256::from_low_u64_be(1)], "data": "0x"}],
+            }],
+        }]}});
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/graphql", listener.local_addr().unwrap());
+        listener.set_nonblocking(true).unwrap();
+        let service = make_service_fn(move |_| {
+            let block = block.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |_| std::future::ready(Ok::<_, Infallible>(Response::new(Body::from(block.to_string())))))) }
+        });
+        tokio::spawn(Server::from_tcp(listener).unwrap().serve(service));
+        url
+    }
+
+    #[tokio::test]
+    async fn fetches_the_same_blocks_over_graphql_and_json_rpc() {
+        let client = node();
+        let fields = [TransactionField::From, TransactionField::Nonce, TransactionField::Status];
+        let graphql = BlockFetcher::new(&client, &fields).with_graphql(&graphql_server().await);
+        let from_graphql = graphql.fetch(16, 16).await.unwrap();
+        assert!(graphql.uses_graphql());
+
+        let from_json_rpc = BlockFetcher::new(&client, &fields).fetch(16, 16).await.unwrap();
+        assert_eq!(from_graphql, from_json_rpc);
+        let block = &from_graphql[0];
+        assert_eq!((block.number, block.timestamp, block.logs.len()), (16, 100, 1));
+        assert_eq!(block.transactions[0], json!({"hash": TX, "transactionIndex": "0x0", "from": SENDER, "nonce": "0x7", "status": "0x1"}));
+
+        // Nothing listening: falls back and stays on JSON-RPC
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let fallback = BlockFetcher::new(&client, &fields).with_graphql(&format!("http://{}/graphql", closed));
+        assert_eq!(fallback.fetch(16, 16).await.unwrap(), from_json_rpc);
+        assert!(!fallback.uses_graphql());
+    }
+}
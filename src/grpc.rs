@@ -0,0 +1,306 @@
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::time::Duration;
+
+use ethabi::ethereum_types::U256;
+use futures_util::stream::{self, Stream};
+use tonic::{Request, Response, Status};
+
+use crate::client::EthClient;
+use crate::crawler::DEFAULT_CHUNK_SIZE;
+use crate::error::{Error, Result};
+use crate::logs::{Filter, Log};
+use crate::portfolio::{chain_holdings, token_metadata};
+use crate::transaction::{TransactionRequest, TypedTransaction};
+use crate::wallet::Wallet;
+
+// Types and the server trait generated from proto/evm_json_rpc.proto
+pub mod proto {
+    tonic::include_proto!("evm_json_rpc.v1");
+}
+
+use proto::eth_server::{Eth, EthServer};
+use proto::send_transaction_request::Transaction;
+
+// Serves the crate's high-level API over gRPC, see proto/evm_json_rpc.proto. Without a
+// wallet SendTransaction only broadcasts transactions signed by the caller.
+#[derive(Clone)]
+pub struct EthService {
+    client: EthClient,
+    wallet: Option<Wallet>,
+    poll_interval: Duration,
+}
+
+impl EthService {
+    pub fn new(client: &EthClient) -> Self {
+        EthService {
+            client: client.clone(),
+            wallet: None,
+            poll_interval: Duration::from_secs(12),
+        }
+    }
+
+    // Signs and sends unsigned transactions from this wallet's key, for any caller that
+    // can reach the server
+    pub fn with_wallet(mut self, wallet: Wallet) -> Self {
+        self.wallet = Some(wallet);
+        self
+    }
+
+    // How often StreamEvents checks for new blocks once it has caught up
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn into_server(self) -> EthServer<Self> {
+        EthServer::new(self)
+    }
+
+    // Serves until the process is stopped
+    pub async fn serve(self, address: SocketAddr) -> Result<()> {
+        tonic::transport::Server::builder()
+            .add_service(self.into_server())
+            .serve(address)
+            .await
+            .map_err(|error| Error::Transport(Box::new(error)))
+    }
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<proto::Event, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Eth for EthService {
+    async fn get_token_metadata(&self, request: Request<proto::GetTokenMetadataRequest>) -> Result<Response<proto::TokenMetadata>, Status> {
+        let metadata = token_metadata(&self.client, &request.into_inner().token).await.map_err(status)?;
+        Ok(Response::new(proto::TokenMetadata {
+            token: metadata.token,
+            name: metadata.name,
+            symbol: metadata.symbol,
+            decimals: metadata.decimals,
+            total_supply: metadata.total_supply.to_string(),
+        }))
+    }
+
+    async fn get_balances(&self, request: Request<proto::GetBalancesRequest>) -> Result<Response<proto::Balances>, Status> {
+        let request = request.into_inner();
+        let (native, tokens) = chain_holdings(&self.client, &request.address, &request.tokens).await.map_err(status)?;
+        Ok(Response::new(proto::Balances {
+            address: request.address,
+            native: native.to_string(),
+            tokens: tokens
+                .into_iter()
+                .map(|token| proto::TokenBalance {
+                    token: token.token,
+                    symbol: token.symbol,
+                    decimals: token.decimals,
+                    balance: token.balance.to_string(),
+                })
+                .collect(),
+        }))
+    }
+
+    type StreamEventsStream = EventStream;
+
+    async fn stream_events(&self, request: Request<proto::StreamEventsRequest>) -> Result<Response<EventStream>, Status> {
+        let request = request.into_inner();
+        let mut filter = Filter::new();
+        for address in &request.addresses {
+            filter = filter.with_address(address);
+        }
+        for event in &request.events {
+            filter = filter.with_event(event);
+        }
+        Ok(Response::new(follow_logs(self.client.clone(), filter, request.from_block, self.poll_interval)))
+    }
+
+    async fn send_transaction(&self, request: Request<proto::SendTransactionRequest>) -> Result<Response<proto::SendTransactionResponse>, Status> {
+        let hash = match request.into_inner().transaction {
+            Some(Transaction::Raw(raw)) => {
+                let transaction = TypedTransaction::from_hex(&raw).map_err(status)?;
+                self.client.send_raw_transaction(&transaction).await
+            }
+            Some(Transaction::Unsigned(unsigned)) => {
+                let Some(wallet) = &self.wallet else {
+                    return Err(Status::failed_precondition("The server has no signing key; send a signed transaction"));
+                };
+                wallet.send(&unsigned_request(unsigned).map_err(status)?).await
+            }
+            None => return Err(Status::invalid_argument("No transaction")),
+        };
+        Ok(Response::new(proto::SendTransactionResponse {
+            hash: format!("{:?}", hash.map_err(status)?),
+        }))
+    }
+}
+
+fn unsigned_request(unsigned: proto::UnsignedTransaction) -> Result<TransactionRequest> {
+    let value = match unsigned.value.as_str() {
+        "" => None,
+        value => Some(U256::from_dec_str(value).map_err(|_| Error::InvalidInput(format!("Invalid value {}", value)))?),
+    };
+    Ok(TransactionRequest {
+        to: unsigned.to,
+        value,
+        data: Some(unsigned.data).filter(|data| !data.is_empty()),
+        gas: unsigned.gas.map(Into::into),
+        ..Default::default()
+    })
+}
+
+struct FollowState {
+    client: EthClient,
+    filter: Filter,
+    next: Option<u64>,
+    pending: VecDeque<Log>,
+    poll_interval: Duration,
+}
+
+// Logs matching `filter` from `from_block` (the head when None) onward, in chain order.
+// Transient failures are retried after the poll interval; any other error ends the
+// stream with its status. Logs are read up to the head as it is, so a log a reorg
+// later drops is not withdrawn.
+fn follow_logs(client: EthClient, filter: Filter, from_block: Option<u64>, poll_interval: Duration) -> EventStream {
+    let state = FollowState {
+        client,
+        filter,
+        next: from_block,
+        pending: VecDeque::new(),
+        poll_interval,
+    };
+    Box::pin(stream::unfold(Some(state), |state| async move {
+        let mut state = state?;
+        loop {
+            if let Some(log) = state.pending.pop_front() {
+                return Some((Ok(event(log)), Some(state)));
+            }
+            let current = &state;
+            let poll = move || async move {
+                match current.next_range().await {
+                    Err(error) if error.is_transient() => Ok(None),
+                    result => result,
+                }
+            };
+            match crate::poll::poll_until_ready(poll, state.poll_interval).await {
+                Ok((logs, next)) => {
+                    state.pending.extend(logs);
+                    state.next = Some(next);
+                }
+                Err(error) => return Some((Err(status(error)), None)),
+            }
+        }
+    }))
+}
+
+impl FollowState {
+    // The logs of the next range up to the head and the block after it; None when
+    // there is nothing new
+    async fn next_range(&self) -> Result<Option<(Vec<Log>, u64)>> {
+        let latest = self.client.get_block_number().await?;
+        let next = self.next.unwrap_or(latest);
+        if next > latest {
+            return Ok(None);
+        }
+        let to_block = next.saturating_add(DEFAULT_CHUNK_SIZE - 1).min(latest);
+        let filter = self.filter.clone().with_from_block(next).with_to_block(to_block);
+        Ok(Some((self.client.get_logs(&filter).await?, to_block + 1)))
+    }
+}
+
+fn event(log: Log) -> proto::Event {
+    proto::Event {
+        address: log.address,
+        topics: log.topics.iter().map(|topic| format!("{:?}", topic)).collect(),
+        data: log.data,
+        block_number: log.block_number.map_or(0, |number| number.as_u64()),
+        block_hash: log.block_hash.map(|hash| format!("{:?}", hash)).unwrap_or_default(),
+        transaction_hash: log.transaction_hash.map(|hash| format!("{:?}", hash)).unwrap_or_default(),
+        log_index: log.log_index.map_or(0, |index| index.as_u64()),
+    }
+}
+
+// The gRPC status for a crate error, so callers can tell bad input from an
+// unreachable node
+pub fn status(error: Error) -> Status {
+    let message = error.to_string();
+    match error {
+        _ if error.is_transient() => Status::unavailable(message),
+        Error::InvalidInput(_) | Error::Abi(_) | Error::Signature(_) => Status::invalid_argument(message),
+        Error::Transport(_) | Error::Timeout(_) => Status::unavailable(message),
+        Error::Rpc(_) => Status::failed_precondition(message),
+        Error::Unsupported(_) => Status::unimplemented(message),
+        Error::Declined(_) => Status::permission_denied(message),
+        Error::Config(_) | Error::ChainMismatch { .. } => Status::failed_precondition(message),
+        Error::Decode(_) | Error::Io(_) => Status::internal(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ethabi::Token;
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::abi::encode_call;
+    use crate::multicall::tests::Multicall;
+    use crate::testing::{Failure, MockNode};
+
+    #[tokio::test]
+    async fn serves_token_metadata() {
+        let usdc = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+        let word = |token: Token| format!("0x{}", hex::encode(ethabi::encode(&[token])));
+        let mut answers = HashMap::new();
+        answers.insert((usdc.to_string(), encode_call("name()", &[])), word(Token::String("USD Coin".to_string())));
+        answers.insert((usdc.to_string(), encode_call("symbol()", &[])), word(Token::String("USDC".to_string())));
+        answers.insert((usdc.to_string(), encode_call("decimals()", &[])), word(Token::Uint(6.into())));
+        answers.insert((usdc.to_string(), encode_call("totalSupply()", &[])), word(Token::Uint(U256::from(10).pow(16.into()))));
+        let service = EthService::new(&Multicall(answers).client());
+
+        let request = Request::new(proto::GetTokenMetadataRequest { token: usdc.to_string() });
+        let metadata = service.get_token_metadata(request).await.unwrap().into_inner();
+        assert_eq!((metadata.name.as_str(), metadata.symbol.as_str(), metadata.decimals), ("USD Coin", "USDC", 6));
+        assert_eq!(metadata.total_supply, "10000000000000000");
+
+        let request = Request::new(proto::GetTokenMetadataRequest { token: "not an address".to_string() });
+        assert_eq!(service.get_token_metadata(request).await.unwrap_err().code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn refuses_unsigned_transactions_without_a_wallet() {
+        let service = EthService::new(&Multicall(HashMap::new()).client());
+        let request = Request::new(proto::SendTransactionRequest {
+            transaction: Some(Transaction::Unsigned(proto::UnsignedTransaction::default())),
+        });
+        assert_eq!(service.send_transaction(request).await.unwrap_err().code(), tonic::Code::FailedPrecondition);
+    }
+
+    // Head at block 3, one log per block; anything else is an error the node rejects
+    fn node() -> EthClient {
+        MockNode::new(|method, params| {
+            let block = |field: &str| u64::from_str_radix(params[0][field].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+            match method {
+                "eth_blockNumber" => Ok(serde_json::json!("0x3")),
+                "eth_getLogs" => Ok((block("fromBlock")..=block("toBlock"))
+                    .map(|number| serde_json::json!({"address": "0x1111111111111111111111111111111111111111", "topics": [], "data": "0x", "blockNumber": format!("0x{:x}", number), "logIndex": "0x0"}))
+                    .collect()),
+                _ => Err(Failure::rpc(-32601, "method not found")),
+            }
+        })
+        .client()
+    }
+
+    #[tokio::test]
+    async fn streams_events_from_the_start_block() {
+        let service = EthService::new(&node()).with_poll_interval(Duration::from_millis(10));
+        let request = Request::new(proto::StreamEventsRequest {
+            from_block: Some(2),
+            ..Default::default()
+        });
+        let events = service.stream_events(request).await.unwrap().into_inner();
+        let blocks: Vec<u64> = events.take(2).map(|event| event.unwrap().block_number).collect().await;
+        assert_eq!(blocks, [2, 3]);
+    }
+}
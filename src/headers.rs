@@ -0,0 +1,275 @@
+use std::collections::VecDeque;
+
+use ethabi::ethereum_types::{H256, U256};
+use futures_util::future::try_join_all;
+
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::utils::{parse_quantity, parse_quantity_u256};
+
+pub const DEFAULT_MAX_HEADERS: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub number: u64,
+    pub hash: H256,
+    pub parent_hash: H256,
+    pub timestamp: u64,
+    pub gas_used: u64,
+    pub gas_limit: u64,
+    // None before London
+    pub base_fee: Option<U256>,
+}
+
+impl Header {
+    pub fn from_json(header: &serde_json::Value) -> Result<Self> {
+        Ok(Header {
+            number: parse_quantity(&header["number"])?,
+            hash: parse_hash(&header["hash"])?,
+            parent_hash: parse_hash(&header["parentHash"])?,
+            timestamp: parse_quantity(&header["timestamp"])?,
+            gas_used: parse_quantity(&header["gasUsed"])?,
+            gas_limit: parse_quantity(&header["gasLimit"])?,
+            base_fee: match header.get("baseFeePerGas") {
+                Some(base_fee) if !base_fee.is_null() => Some(parse_quantity_u256(base_fee)?),
+                _ => None,
+            },
+        })
+    }
+}
+
+fn parse_hash(value: &serde_json::Value) -> Result<H256> {
+    serde_json::from_value(value.clone()).map_err(|error| Error::Decode(format!("Invalid block hash {}: {}", value, error)))
+}
+
+// `block` is a tag such as "latest" or a hex block number
+pub async fn get_header(client: &EthClient, block: &str) -> Result<Header> {
+    let header = client.send("eth_getBlockByNumber", vec![serde_json::json!(block), serde_json::json!(false)]).await?;
+    if header.is_null() {
+        return Err(Error::Decode(format!("Block {} not found", block)));
+    }
+    Header::from_json(&header)
+}
+
+// Blocks that left the canonical chain during a sync, oldest first. A reorg deeper
+// than the cache is reported only as deep as the cache reached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reorg {
+    pub orphaned: Vec<Header>,
+    // Last cached block both chains share, if the cache reached back that far
+    pub common_ancestor: Option<u64>,
+}
+
+impl Reorg {
+    pub fn depth(&self) -> usize {
+        self.orphaned.len()
+    }
+}
+
+// Rolling window of the most recent canonical headers. Each sync fetches only the
+// blocks since the last one and checks every parent hash, so block number, timestamp
+// and base fee lookups within the window need no RPC calls.
+#[derive(Debug, Clone)]
+pub struct HeaderChain {
+    max_headers: usize,
+    headers: VecDeque<Header>,
+}
+
+impl HeaderChain {
+    pub fn new() -> Self {
+        Self::with_max_headers(DEFAULT_MAX_HEADERS)
+    }
+
+    pub fn with_max_headers(max_headers: usize) -> Self {
+        HeaderChain {
+            max_headers: max_headers.max(1),
+            headers: VecDeque::new(),
+        }
+    }
+
+    pub fn latest(&self) -> Option<&Header> {
+        self.headers.back()
+    }
+
+    pub fn block_number(&self) -> Option<u64> {
+        self.latest().map(|header| header.number)
+    }
+
+    pub fn get(&self, number: u64) -> Option<&Header> {
+        let first = self.headers.front()?.number;
+        self.headers.get(number.checked_sub(first)? as usize)
+    }
+
+    pub fn get_by_hash(&self, hash: &H256) -> Option<&Header> {
+        self.headers.iter().rev().find(|header| header.hash == *hash)
+    }
+
+    pub fn timestamp(&self, number: u64) -> Option<u64> {
+        self.get(number).map(|header| header.timestamp)
+    }
+
+    pub fn base_fee(&self, number: u64) -> Option<U256> {
+        self.get(number)?.base_fee
+    }
+
+    pub fn headers(&self) -> impl Iterator<Item = &Header> {
+        self.headers.iter()
+    }
+
+    // Brings the cache up to the node's latest block. Returns the reorg if cached
+    // blocks were replaced; the first sync only loads the latest header.
+    pub async fn sync(&mut self, client: &EthClient) -> Result<Option<Reorg>> {
+        let latest = get_header(client, "latest").await?;
+        if self.get(latest.number).is_some_and(|cached| cached.hash == latest.hash) {
+            return Ok(None);
+        }
+
+        let window_start = latest.number.saturating_sub(self.max_headers as u64 - 1);
+        let first = self.block_number().map_or(latest.number, |tip| (tip + 1).min(latest.number)).max(window_start);
+        let blocks: Vec<String> = (first..latest.number).map(|number| format!("0x{:x}", number)).collect();
+        let mut segment: VecDeque<Header> = try_join_all(blocks.iter().map(|block| get_header(client, block))).await?.into();
+        segment.push_back(latest);
+        check_links(segment.iter())?;
+
+        // The cache is only changed once everything is fetched, so a failed request
+        // leaves it as it was. `kept` counts the cached headers that survive.
+        let mut kept = self.headers.len();
+        // After a gap wider than the window nothing cached can link to the new blocks
+        if self.block_number().is_some_and(|tip| first > tip + 1) {
+            kept = 0;
+        }
+
+        // Cached blocks the new segment covers are replaced; any that differ were reorged
+        let mut orphaned = Vec::new();
+        while kept > 0 && self.headers[kept - 1].number >= first {
+            let stale = &self.headers[kept - 1];
+            if segment.get((stale.number - first) as usize).is_none_or(|header| header.hash != stale.hash) {
+                orphaned.push(stale.clone());
+            }
+            kept -= 1;
+        }
+
+        // Walk back until the segment links onto the cache, fetching the canonical
+        // replacement for each orphaned block
+        while kept > 0 {
+            let tip = &self.headers[kept - 1];
+            if tip.hash == segment[0].parent_hash {
+                break;
+            }
+            segment.push_front(get_header(client, &format!("0x{:x}", tip.number)).await?);
+            check_links(segment.iter().take(2))?;
+            orphaned.push(tip.clone());
+            kept -= 1;
+        }
+
+        let common_ancestor = kept.checked_sub(1).map(|index| self.headers[index].number);
+        self.headers.truncate(kept);
+        self.headers.extend(segment);
+        while self.headers.len() > self.max_headers {
+            self.headers.pop_front();
+        }
+
+        if orphaned.is_empty() {
+            return Ok(None);
+        }
+        orphaned.reverse();
+        Ok(Some(Reorg { orphaned, common_ancestor }))
+    }
+}
+
+impl Default for HeaderChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Headers fetched one by one can straddle a reorg on the node; the caller retries
+fn check_links<'a>(headers: impl Iterator<Item = &'a Header>) -> Result<()> {
+    let mut parent: Option<&Header> = None;
+    for header in headers {
+        if let Some(parent) = parent {
+            if header.number != parent.number + 1 || header.parent_hash != parent.hash {
+                return Err(Error::Decode(format!("Block {} does not link to block {}; the chain changed while syncing", header.number, parent.number)));
+            }
+        }
+        parent = Some(header);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{Failure, MockNode};
+
+    // A chain up to `tip` whose blocks from `fork` on have different hashes than before
+    #[derive(Clone, Copy)]
+    struct Chain {
+        tip: u64,
+        fork: u64,
+    }
+
+    impl Chain {
+        fn hash(&self, number: u64) -> H256 {
+            H256::from_low_u64_be(number * 1000 + u64::from(number >= self.fork))
+        }
+    }
+
+    fn node(chain: Arc<Mutex<Chain>>) -> MockNode {
+        MockNode::new(move |method, params| {
+            let chain = *chain.lock().unwrap();
+            let number = match params.first().and_then(|block| block.as_str()) {
+                Some("latest") => chain.tip,
+                Some(number) => u64::from_str_radix(number.trim_start_matches("0x"), 16).unwrap(),
+                None => return Err(Failure::unexpected(method)),
+            };
+            Ok(json!({
+                "number": format!("0x{:x}", number),
+                "hash": chain.hash(number),
+                "parentHash": chain.hash(number.saturating_sub(1)),
+                "timestamp": format!("0x{:x}", number * 12),
+                "gasUsed": "0x0",
+                "gasLimit": "0x1c9c380",
+                "baseFeePerGas": "0x7",
+            }))
+        })
+    }
+
+    #[tokio::test]
+    async fn follows_the_chain_and_reports_reorgs() {
+        let chain = Arc::new(Mutex::new(Chain { tip: 10, fork: u64::MAX }));
+        let node = node(chain.clone());
+        let client = node.client();
+        let mut headers = HeaderChain::with_max_headers(8);
+
+        // The first sync loads only the latest block
+        assert_eq!(headers.sync(&client).await.unwrap(), None);
+        assert_eq!((headers.block_number(), headers.headers().count()), (Some(10), 1));
+
+        chain.lock().unwrap().tip = 13;
+        assert_eq!(headers.sync(&client).await.unwrap(), None);
+        assert_eq!(headers.headers().map(|header| header.number).collect::<Vec<_>>(), [10, 11, 12, 13]);
+        assert_eq!((headers.timestamp(12), headers.base_fee(12)), (Some(144), Some(7.into())));
+        let calls = node.calls().len();
+        assert_eq!(headers.sync(&client).await.unwrap(), None);
+        assert_eq!(node.calls().len(), calls + 1);
+
+        // Blocks 12 and 13 are replaced as block 14 arrives
+        *chain.lock().unwrap() = Chain { tip: 14, fork: 12 };
+        let reorg = headers.sync(&client).await.unwrap().unwrap();
+        assert_eq!(reorg.orphaned.iter().map(|header| header.number).collect::<Vec<_>>(), [12, 13]);
+        assert_eq!((reorg.depth(), reorg.common_ancestor), (2, Some(11)));
+        let current = *chain.lock().unwrap();
+        assert!(headers.headers().all(|header| header.hash == current.hash(header.number)));
+        assert_eq!(headers.get_by_hash(&current.hash(13)).map(|header| header.number), Some(13));
+
+        // The window keeps only the most recent headers
+        chain.lock().unwrap().tip = 30;
+        assert_eq!(headers.sync(&client).await.unwrap(), None);
+        assert_eq!((headers.headers().next().unwrap().number, headers.get(22), headers.get(31)), (23, None, None));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::error::{Error, Result};
+use crate::graphql::BlockFetcher;
+use crate::headers::get_header;
+use crate::store::Store;
+
+pub const DEFAULT_CHUNK_SIZE: u64 = 100;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IndexStats {
+    // Blocks indexed by this sync; from > to when there was nothing new
+    pub from_block: u64,
+    pub to_block: u64,
+    pub logs: u64,
+    // Stored blocks replaced because the chain reorganised under them
+    pub reorged: u64,
+}
+
+// Copies blocks and their logs into a Store, resuming from the store's head. Before
+// each sync the stored head is checked against the node, and stored blocks that left
+// the canonical chain are rewound and indexed again.
+pub struct Indexer {
+    fetcher: BlockFetcher,
+    start_block: u64,
+    chunk_size: u64,
+    confirmations: u64,
+}
+
+impl Indexer {
+    // Starts at `start_block` when the store is empty
+    pub fn new(fetcher: BlockFetcher, start_block: u64) -> Self {
+        Indexer {
+            fetcher,
+            start_block,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            confirmations: 0,
+        }
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: u64) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    // Stays this many blocks behind the head, where reorgs are rare
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    // Indexes up to the current head, less confirmations
+    pub async fn sync(&self, store: &mut dyn Store) -> Result<IndexStats> {
+        let client = self.fetcher.client();
+        let reorged = self.rewind_reorged(store).await?;
+        let from_block = store.head().await?.map_or(self.start_block, |head| head.number + 1);
+        let to_block = client.get_block_number().await?.saturating_sub(self.confirmations);
+        let mut stats = IndexStats { from_block, to_block, logs: 0, reorged };
+
+        let mut next = from_block;
+        while next <= to_block {
+            let end = next.saturating_add(self.chunk_size - 1).min(to_block);
+            let blocks = self.fetcher.fetch(next, end).await?;
+            stats.logs += blocks.iter().map(|block| block.logs.len() as u64).sum::<u64>();
+            store.insert(&blocks).await?;
+            next = end + 1;
+        }
+        Ok(stats)
+    }
+
+    // Walks back from the stored head to the newest block the node still has, and
+    // rewinds the store to it. Returns how many blocks were dropped.
+    async fn rewind_reorged(&self, store: &mut dyn Store) -> Result<u64> {
+        let Some(head) = store.head().await? else { return Ok(0) };
+        let client = self.fetcher.client();
+        let mut number = head.number;
+        loop {
+            let Some(stored) = store.block(number).await? else { break };
+            if get_header(client, &format!("0x{:x}", number)).await?.hash == stored.hash {
+                break;
+            }
+            if number <= self.start_block {
+                return Err(Error::InvalidInput(format!("Every stored block from {} was reorged away", self.start_block)));
+            }
+            number -= 1;
+        }
+        if number < head.number {
+            tracing::warn!(from = number + 1, to = head.number, "rewinding reorged blocks");
+            store.rewind(number).await?;
+        }
+        Ok(head.number - number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    use ethabi::ethereum_types::H256;
+    use serde_json::json;
+
+    use super::*;
+    use crate::client::EthClient;
+    use crate::store::{tests::block, MemoryStore};
+    use crate::testing::{Failure, MockNode};
+
+    // A chain whose head is at block 5; blocks at or above the fork point carry the
+    // fork number in their hash, as in store::tests::block
+    fn chain(fork_from: Arc<AtomicU64>) -> EthClient {
+        MockNode::new(move |method, params| match method {
+            "eth_blockNumber" => Ok(json!("0x5")),
+            "eth_getBlockByNumber" => {
+                let number = u64::from_str_radix(params[0].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+                let hash = block(number, u64::from(number >= fork_from.load(Ordering::SeqCst))).hash;
+                Ok(json!({
+                    "number": params[0],
+                    "hash": hash,
+                    "parentHash": H256::zero(),
+                    "timestamp": format!("0x{:x}", number * 12),
+                    "gasUsed": "0x0",
+                    "gasLimit": "0x1c9c380",
+                    "transactions": [],
+                }))
+            }
+            "eth_getLogs" => Ok(json!([])),
+            _ => Err(Failure::unexpected(method)),
+        })
+        .client()
+    }
+
+    #[tokio::test]
+    async fn indexes_and_rewinds_reorged_blocks() {
+        let fork_from = Arc::new(AtomicU64::new(u64::MAX));
+        let client = chain(fork_from.clone());
+        let indexer = Indexer::new(BlockFetcher::new(&client, &[]), 1).with_chunk_size(2).with_confirmations(1);
+        let mut store = MemoryStore::default();
+
+        let stats = indexer.sync(&mut store).await.unwrap();
+        assert_eq!((stats.from_block, stats.to_block, stats.reorged), (1, 4, 0));
+        assert_eq!(store.head().await.unwrap().unwrap().hash, block(4, 0).hash);
+
+        // Blocks 3 and up are replaced
+        fork_from.store(3, Ordering::SeqCst);
+        let stats = indexer.sync(&mut store).await.unwrap();
+        assert_eq!((stats.from_block, stats.to_block, stats.reorged), (3, 4, 2));
+        assert_eq!(store.block(3).await.unwrap().unwrap().hash, block(3, 1).hash);
+        assert_eq!(store.block(2).await.unwrap().unwrap().hash, block(2, 0).hash);
+    }
+}
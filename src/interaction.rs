@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use ethabi::ethereum_types::U256;
+use ethabi::token::{LenientTokenizer, Tokenizer};
+use ethabi::{Contract, Function, ParamType, StateMutability, Token};
+
+use crate::abi::{decode_result, encode_call};
+use crate::artifacts::load_artifact;
+use crate::client::EthClient;
+use crate::config::{Interaction, Preset};
+use crate::error::{Error, Result};
+use crate::transaction::TransactionRequest;
+
+// A contract with named presets from the config's [interactions] table. Every preset
+// is checked against the ABI when the profile is built, so a typo in the config fails
+// up front rather than when the preset is first used.
+#[derive(Debug, Clone)]
+pub struct InteractionProfile {
+    pub address: String,
+    pub abi: Contract,
+    presets: BTreeMap<String, ResolvedPreset>,
+    address_book: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+struct ResolvedPreset {
+    function: Function,
+    args: Vec<String>,
+    value: Option<U256>,
+}
+
+impl InteractionProfile {
+    // Reads the ABI from `interaction.abi`: a bare ABI array or a Foundry/Hardhat artifact
+    pub fn load(interaction: &Interaction, address_book: &BTreeMap<String, String>) -> Result<Self> {
+        let abi = read_abi(&interaction.abi)?;
+        InteractionProfile::new(&interaction.address, abi, &interaction.presets, address_book)
+    }
+
+    pub fn new(address: &str, abi: Contract, presets: &BTreeMap<String, Preset>, address_book: &BTreeMap<String, String>) -> Result<Self> {
+        let address = address_book.get(address).cloned().unwrap_or_else(|| address.to_string());
+        if address.parse::<ethabi::Address>().is_err() {
+            return Err(Error::Config(format!("Invalid interaction address {}", address)));
+        }
+        let mut profile = InteractionProfile {
+            address,
+            abi,
+            presets: BTreeMap::new(),
+            address_book: address_book.clone(),
+        };
+        for (name, preset) in presets {
+            let resolved = profile.resolve(preset).map_err(|error| Error::Config(format!("Preset {}: {}", name, error)))?;
+            profile.presets.insert(name.clone(), resolved);
+        }
+        Ok(profile)
+    }
+
+    pub fn presets(&self) -> impl Iterator<Item = (&str, &Function)> {
+        self.presets.iter().map(|(name, preset)| (name.as_str(), &preset.function))
+    }
+
+    // True for view and pure functions, which are called rather than sent
+    pub fn is_read(&self, preset: &str) -> Result<bool> {
+        let function = &self.preset(preset)?.function;
+        Ok(matches!(function.state_mutability, StateMutability::View | StateMutability::Pure))
+    }
+
+    // Calldata for a preset. `args` replace the defaults by position; the rest of the
+    // defaults are kept.
+    pub fn encode(&self, preset: &str, args: &[&str]) -> Result<String> {
+        let preset = self.preset(preset)?;
+        let function = &preset.function;
+        if args.len() > function.inputs.len() {
+            return Err(Error::InvalidInput(format!("{} takes {} arguments, got {}", signature(function), function.inputs.len(), args.len())));
+        }
+        let tokens = function
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(index, input)| {
+                let value = args.get(index).copied().or_else(|| preset.args.get(index).map(String::as_str));
+                let value = value.ok_or_else(|| Error::InvalidInput(format!("{} needs a value for {}", signature(function), argument_name(&input.name, index))))?;
+                self.tokenize(&input.kind, value)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(encode_call(&signature(function), &tokens))
+    }
+
+    // Calls a preset and decodes its outputs
+    pub async fn call(&self, client: &EthClient, preset: &str, args: &[&str]) -> Result<Vec<Token>> {
+        let data = self.encode(preset, args)?;
+        let result = client.call(&self.address, &data).await?;
+        let outputs: Vec<ParamType> = self.preset(preset)?.function.outputs.iter().map(|output| output.kind.clone()).collect();
+        decode_result(&result, &outputs)
+    }
+
+    // Unsigned transaction for a preset, for Wallet::send
+    pub fn request(&self, preset: &str, args: &[&str]) -> Result<TransactionRequest> {
+        Ok(TransactionRequest {
+            to: Some(self.address.clone()),
+            data: Some(self.encode(preset, args)?),
+            value: self.preset(preset)?.value,
+            ..Default::default()
+        })
+    }
+
+    fn preset(&self, name: &str) -> Result<&ResolvedPreset> {
+        self.presets.get(name).ok_or_else(|| Error::InvalidInput(format!("Unknown preset {}", name)))
+    }
+
+    fn resolve(&self, preset: &Preset) -> Result<ResolvedPreset> {
+        let function = if preset.function.contains('(') {
+            let selector = &keccak_hash::keccak(preset.function.replace(' ', "").as_bytes())[..4];
+            self.abi.functions().find(|function| function.short_signature() == selector).ok_or_else(|| Error::Abi(format!("No function {} in the ABI", preset.function)))?
+        } else {
+            match self.abi.functions_by_name(&preset.function).map_err(|_| Error::Abi(format!("No function {} in the ABI", preset.function)))?.as_slice() {
+                [function] => function,
+                overloads => {
+                    let signatures: Vec<String> = overloads.iter().map(signature).collect();
+                    return Err(Error::Abi(format!("{} is overloaded, give one of {}", preset.function, signatures.join(", "))));
+                }
+            }
+        };
+        if preset.args.len() > function.inputs.len() {
+            return Err(Error::Abi(format!("{} takes {} arguments, got {}", signature(function), function.inputs.len(), preset.args.len())));
+        }
+        for (input, value) in function.inputs.iter().zip(&preset.args) {
+            self.tokenize(&input.kind, value)?;
+        }
+        let value = match &preset.value {
+            Some(value) if function.state_mutability != StateMutability::Payable => return Err(Error::Abi(format!("{} is not payable but has value {}", signature(function), value))),
+            Some(value) => Some(parse_wei(value)?),
+            None => None,
+        };
+        Ok(ResolvedPreset {
+            function: function.clone(),
+            args: preset.args.clone(),
+            value,
+        })
+    }
+
+    // Address arguments may be address book names
+    fn tokenize(&self, kind: &ParamType, value: &str) -> Result<Token> {
+        let value = value.trim();
+        let value = match kind {
+            ParamType::Address => self.address_book.get(value).map(String::as_str).unwrap_or(value),
+            _ => value,
+        };
+        LenientTokenizer::tokenize(kind, value).map_err(|error| Error::InvalidInput(format!("Invalid {} argument {}: {}", kind, value, error)))
+    }
+}
+
+// ethabi's Function::signature appends the outputs
+fn signature(function: &Function) -> String {
+    let inputs: Vec<String> = function.inputs.iter().map(|input| input.kind.to_string()).collect();
+    format!("{}({})", function.name, inputs.join(","))
+}
+
+fn argument_name(name: &str, index: usize) -> String {
+    if name.is_empty() {
+        format!("argument {}", index + 1)
+    } else {
+        name.to_string()
+    }
+}
+
+fn parse_wei(value: &str) -> Result<U256> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(value).ok(),
+    };
+    parsed.ok_or_else(|| Error::InvalidInput(format!("Invalid wei amount {}", value)))
+}
+
+fn read_abi(path: &Path) -> Result<Contract> {
+    let contents = std::fs::read_to_string(path).map_err(|error| std::io::Error::new(error.kind(), format!("Failed to read {}: {}", path.display(), error)))?;
+    if contents.trim_start().starts_with('[') {
+        return Contract::load(contents.as_bytes()).map_err(|error| Error::Abi(format!("Invalid ABI in {}: {}", path.display(), error)));
+    }
+    Ok(load_artifact(path)?.abi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockNode;
+
+    const ERC20_ABI: &str = r#"[
+        {"type": "function", "name": "balanceOf", "stateMutability": "view", "inputs": [{"name": "owner", "type": "address"}], "outputs": [{"name": "", "type": "uint256"}]},
+        {"type": "function", "name": "transfer", "stateMutability": "nonpayable", "inputs": [{"name": "to", "type": "address"}, {"name": "amount", "type": "uint256"}], "outputs": [{"name": "", "type": "bool"}]}
+    ]"#;
+
+    const TOKEN: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const TREASURY: &str = "0x3535353535353535353535353535353535353535";
+
+    fn preset(function: &str, args: &[&str]) -> Preset {
+        Preset {
+            function: function.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            value: None,
+        }
+    }
+
+    fn profile(presets: &[(&str, Preset)]) -> Result<InteractionProfile> {
+        let address_book = [("usdc".to_string(), TOKEN.to_string()), ("treasury".to_string(), TREASURY.to_string())].into_iter().collect();
+        let presets = presets.iter().map(|(name, preset)| (name.to_string(), preset.clone())).collect();
+        InteractionProfile::new("usdc", Contract::load(ERC20_ABI.as_bytes()).unwrap(), &presets, &address_book)
+    }
+
+    #[tokio::test]
+    async fn runs_presets_with_defaults_and_overrides() {
+        let profile = profile(&[("balance", preset("balanceOf", &["treasury"])), ("pay", preset("transfer(address,uint256)", &["treasury"]))]).unwrap();
+        assert_eq!(profile.address, TOKEN);
+        assert!(profile.is_read("balance").unwrap());
+        assert!(!profile.is_read("pay").unwrap());
+        let node = MockNode::with_results([("eth_call", serde_json::json!(format!("0x{:064x}", 1_500_000)))]);
+
+        let balance = profile.call(&node.client(), "balance", &[]).await.unwrap();
+        assert_eq!(node.params_of("eth_call")[0][0]["to"], TOKEN);
+        assert_eq!(balance, [Token::Uint(U256::from(1_500_000))]);
+
+        let request = profile.request("pay", &[TREASURY, "1000000"]).unwrap();
+        assert_eq!(request.to.as_deref(), Some(TOKEN));
+        assert_eq!(request.data.as_deref(), Some(format!("0xa9059cbb{:0>64}{:064x}", &TREASURY[2..], 1_000_000).as_str()));
+        // The amount has no default
+        assert!(matches!(profile.encode("pay", &[]), Err(Error::InvalidInput(message)) if message.contains("amount")));
+    }
+
+    #[test]
+    fn rejects_presets_that_do_not_match_the_abi() {
+        let invalid = |preset: Preset| match profile(&[("broken", preset)]) {
+            Err(Error::Config(message)) => message,
+            other => panic!("expected a config error, got {:?}", other.map(|_| ())),
+        };
+        assert!(invalid(preset("mint", &[])).contains("No function mint"));
+        assert!(invalid(preset("balanceOf", &["treasury", "1"])).contains("takes 1 arguments"));
+        assert!(invalid(preset("transfer", &["nobody"])).contains("Invalid address argument"));
+        assert!(invalid(Preset { value: Some("1".to_string()), ..preset("transfer", &[]) }).contains("not payable"));
+    }
+}
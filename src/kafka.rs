@@ -0,0 +1,90 @@
+use async_trait::async_trait;
+use rdkafka::config::ClientConfig;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::producer::{DeliveryFuture, FutureProducer, FutureRecord};
+
+use crate::bus::Encoding;
+use crate::error::{Error, Result};
+use crate::sink::Sink;
+
+// Publishes each record as a Kafka message on one topic. Writes only queue the message;
+// flush waits until every queued message is acknowledged, so a batch is durable once
+// its flush returns.
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+    encoding: Encoding,
+    key_field: Option<String>,
+    pending: Vec<DeliveryFuture>,
+}
+
+impl KafkaSink {
+    // `brokers` is a comma-separated bootstrap list, e.g. localhost:9092
+    pub fn new(brokers: &str, topic: &str, encoding: Encoding) -> Result<Self> {
+        Self::with_config(ClientConfig::new().set("bootstrap.servers", brokers), topic, encoding)
+    }
+
+    // For SASL, TLS and other producer settings
+    pub fn with_config(config: &ClientConfig, topic: &str, encoding: Encoding) -> Result<Self> {
+        let producer = config.create().map_err(|error| Error::Config(format!("Failed to create Kafka producer: {}", error)))?;
+        Ok(KafkaSink {
+            producer,
+            topic: topic.to_string(),
+            encoding,
+            key_field: None,
+            pending: Vec::new(),
+        })
+    }
+
+    // Keys each message by this field of the record, e.g. "address", so one key's
+    // records stay ordered within a partition
+    pub fn with_key_field(mut self, field: &str) -> Self {
+        self.key_field = Some(field.to_string());
+        self
+    }
+
+    async fn wait_pending(&mut self) -> Result<()> {
+        for delivery in self.pending.drain(..) {
+            match delivery.await {
+                Ok(Ok(_)) => {}
+                Ok(Err((error, _))) => return Err(kafka_error(error)),
+                Err(_) => return Err(Error::Transport("Kafka producer dropped a message".into())),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for KafkaSink {
+    async fn write(&mut self, record: &serde_json::Value) -> Result<()> {
+        let payload = self.encoding.encode(record)?;
+        let key = self.key_field.as_ref().and_then(|field| record.get(field)).map(|value| match value {
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        });
+        loop {
+            let mut message = FutureRecord::to(&self.topic).payload(&payload);
+            if let Some(key) = &key {
+                message = message.key(key);
+            }
+            match self.producer.send_result(message) {
+                Ok(delivery) => {
+                    self.pending.push(delivery);
+                    return Ok(());
+                }
+                // The local queue is full: wait for what's in flight, then retry
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), _)) if !self.pending.is_empty() => self.wait_pending().await?,
+                Err((error, _)) => return Err(kafka_error(error)),
+            }
+        }
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.wait_pending().await
+    }
+}
+
+fn kafka_error(error: KafkaError) -> Error {
+    Error::Transport(Box::new(error))
+}
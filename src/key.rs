@@ -0,0 +1,89 @@
+use std::fmt;
+
+use k256::ecdsa::SigningKey;
+use zeroize::Zeroizing;
+
+use crate::error::{Error, Result};
+use crate::signature::hash_message;
+
+// secp256k1 private key. The key material is zeroized on drop, never shows up in
+// Debug output, and only leaves through expose_bytes.
+#[derive(Clone)]
+pub struct SecretKey {
+    inner: SigningKey,
+}
+
+impl SecretKey {
+    pub fn random() -> Self {
+        SecretKey {
+            inner: SigningKey::random(&mut rand::rngs::OsRng),
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(SecretKey {
+            inner: SigningKey::from_slice(bytes).map_err(|_| Error::InvalidInput("Invalid secp256k1 private key".to_string()))?,
+        })
+    }
+
+    // Accepts 64 hex characters, with or without 0x
+    pub fn from_hex(key: &str) -> Result<Self> {
+        let bytes = Zeroizing::new(hex::decode(key.trim_start_matches("0x")).map_err(|_| Error::InvalidInput("Invalid private key hex".to_string()))?);
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn address(&self) -> String {
+        let public_key = self.inner.verifying_key().to_encoded_point(false);
+        let hash = keccak_hash::keccak(&public_key.as_bytes()[1..]);
+        format!("0x{}", hex::encode(&hash[12..]))
+    }
+
+    // 65-byte r || s || v signature with low s and v as 27/28
+    pub fn sign_hash(&self, digest: &[u8; 32]) -> Result<[u8; 65]> {
+        let (signature, recovery_id) = self.inner.sign_prehash_recoverable(digest).map_err(|error| Error::Signature(error.to_string()))?;
+        let mut signed = [0u8; 65];
+        signed[..64].copy_from_slice(&signature.to_bytes());
+        signed[64] = 27 + recovery_id.to_byte();
+        Ok(signed)
+    }
+
+    // EIP-191 personal_sign
+    pub fn sign_message(&self, message: &[u8]) -> Result<[u8; 65]> {
+        self.sign_hash(&hash_message(message))
+    }
+
+    // Explicit export of the raw key; the returned copy is zeroized when dropped
+    pub fn expose_bytes(&self) -> Zeroizing<[u8; 32]> {
+        Zeroizing::new(self.inner.to_bytes().into())
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretKey({}, key redacted)", self.address())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::recover_address;
+
+    #[test]
+    fn derives_the_address_and_signs_recoverably() {
+        let key = SecretKey::from_hex(&format!("0x{}", "46".repeat(32))).unwrap();
+        assert_eq!(key.address(), "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f");
+        assert_eq!(*key.expose_bytes(), [0x46; 32]);
+        assert_eq!(format!("{:?}", key), "SecretKey(0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f, key redacted)");
+
+        let signature = key.sign_message(b"hello").unwrap();
+        assert!(signature[64] == 27 || signature[64] == 28);
+        // Low s: the top bit of the first byte of s is never set
+        assert!(signature[32] < 0x80);
+        assert_eq!(recover_address(&hash_message(b"hello"), &signature).unwrap(), key.address());
+
+        assert!(matches!(SecretKey::from_hex("0x1234"), Err(Error::InvalidInput(_))));
+        assert!(matches!(SecretKey::from_hex(&"00".repeat(32)), Err(Error::InvalidInput(_))));
+        assert!(matches!(SecretKey::from_hex("zz"), Err(Error::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::bytecode::{get_code, get_implementation};
+use crate::client::EthClient;
+use crate::ens;
+use crate::error::Result;
+use crate::format;
+
+// (chain id, address, label); chain id None applies to every chain, for contracts
+// deployed at the same address everywhere
+const WELL_KNOWN: &[(Option<u64>, &str, &str)] = &[
+    (None, "0xcA11bde05977b3631167028862bE2a173976CA11", "Multicall3"),
+    (None, "0x000000000022D473030F116dDEE9F6B43aC78BA3", "Uniswap: Permit2"),
+    (None, "0x4e59b44847b379578588920cA78FbF26c0B4956C", "Deterministic Deployment Proxy"),
+    (None, "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789", "ERC-4337: EntryPoint v0.6"),
+    (None, "0x0000000071727De22E5E9d8BAf0edAc6f37da032", "ERC-4337: EntryPoint v0.7"),
+    (None, "0xA238CBeb142c10Ef7Ad8442C6D1f9E89e07e7761", "Safe: MultiSend 1.3.0"),
+    (None, "0x40A2aCCbd92BCA938b02010E17A5b8929b49130D", "Safe: MultiSendCallOnly 1.3.0"),
+    (None, "0x00000000000C2E074eC69A0dFb2997BA6C7d2e1e", "ENS: Registry"),
+    // Ethereum mainnet
+    (Some(1), "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", "WETH"),
+    (Some(1), "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", "USDC"),
+    (Some(1), "0xdAC17F958D2ee523a2206206994597C13D831ec7", "USDT"),
+    (Some(1), "0x6B175474E89094C44Da98b954EedeAC495271d0F", "DAI"),
+    (Some(1), "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D", "Uniswap V2: Router 2"),
+    (Some(1), "0xE592427A0AEce92De3Edee1F18E0157C05861564", "Uniswap V3: SwapRouter"),
+    (Some(1), "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45", "Uniswap V3: SwapRouter02"),
+    (Some(1), "0x3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD", "Uniswap: Universal Router"),
+    (Some(1), "0x1111111254EEB25477B68fb85Ed929f73A960582", "1inch: Aggregation Router v5"),
+    (Some(1), "0x111111125421cA6dc452d289314280a0f8842A65", "1inch: Aggregation Router v6"),
+    (Some(1), "0xDef1C0ded9bec7F1a1670819833240f027b25EfF", "0x: Exchange Proxy"),
+    (Some(1), "0x99C9fc46f92E8a1c0deC1b1747d010903E884bE1", "Optimism: L1 Standard Bridge"),
+    (Some(1), "0x3154Cf16ccdb4C6d922629664174b904d80F2C35", "Base: L1 Standard Bridge"),
+    (Some(1), "0x72Ce9c846789fdB6fC1f34aC4AD25Dd9ef7031ef", "Arbitrum: L1 Gateway Router"),
+    // Sepolia
+    (Some(11155111), "0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238", "USDC"),
+];
+
+pub struct LabelRegistry {
+    labels: HashMap<(Option<u64>, String), String>,
+}
+
+impl LabelRegistry {
+    // Registry preloaded with the embedded well-known contracts
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        for (chain_id, address, label) in WELL_KNOWN {
+            registry.labels.insert((*chain_id, address.to_lowercase()), label.to_string());
+        }
+        registry
+    }
+
+    pub fn empty() -> Self {
+        LabelRegistry {
+            labels: HashMap::new(),
+        }
+    }
+
+    // Runtime labels override embedded ones for the same chain and address
+    pub fn insert(&mut self, chain_id: u64, address: &str, label: &str) {
+        self.labels.insert((Some(chain_id), address.to_lowercase()), label.to_string());
+    }
+
+    pub fn insert_all_chains(&mut self, address: &str, label: &str) {
+        self.labels.insert((None, address.to_lowercase()), label.to_string());
+    }
+
+    // Chain-specific labels take precedence over all-chain ones
+    pub fn get(&self, chain_id: u64, address: &str) -> Option<&str> {
+        let address = address.to_lowercase();
+        self.labels
+            .get(&(Some(chain_id), address.clone()))
+            .or_else(|| self.labels.get(&(None, address)))
+            .map(String::as_str)
+    }
+
+    // "Label (0x...)" when known, the bare address otherwise, in the global address format
+    pub fn format_address(&self, chain_id: u64, address: &str) -> String {
+        let formatted = format::address(address);
+        match self.get(chain_id, address) {
+            Some(label) => format!("{} ({})", label, formatted),
+            None => formatted,
+        }
+    }
+}
+
+impl Default for LabelRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelSource {
+    Ens,
+    AddressBook,
+    WellKnown,
+    // Inferred from the account's code, e.g. "Safe proxy"
+    Heuristic,
+}
+
+impl fmt::Display for LabelSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LabelSource::Ens => "ens",
+            LabelSource::AddressBook => "address book",
+            LabelSource::WellKnown => "well-known",
+            LabelSource::Heuristic => "heuristic",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub name: String,
+    pub source: LabelSource,
+}
+
+// Minimal proxy runtime code around the 20-byte implementation address
+const EIP1167_PREFIX: &str = "363d3d373d3d3d363d73";
+const EIP1167_SUFFIX: &str = "5af43d82803e903d91602b57fd5bf3";
+// masterCopy() selector, which Safe proxies answer from their own tiny runtime
+const SAFE_PROXY_SELECTOR: &str = "a619486e";
+
+// Picks the best available label for an address: a verified ENS primary name, then
+// the address book, then well-known contracts, then what the account's code suggests
+pub struct LabelResolver {
+    client: EthClient,
+    chain_id: u64,
+    registry: LabelRegistry,
+    // Lowercase address -> name
+    address_book: HashMap<String, String>,
+    ens: bool,
+}
+
+impl LabelResolver {
+    pub fn new(client: &EthClient, chain_id: u64) -> Self {
+        LabelResolver {
+            client: client.clone(),
+            chain_id,
+            registry: LabelRegistry::new(),
+            address_book: HashMap::new(),
+            ens: true,
+        }
+    }
+
+    // Entries as in the config file, name -> address
+    pub fn with_address_book(mut self, address_book: &BTreeMap<String, String>) -> Self {
+        for (name, address) in address_book {
+            self.address_book.insert(address.to_lowercase(), name.clone());
+        }
+        self
+    }
+
+    pub fn with_registry(mut self, registry: LabelRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    // Skips ENS, e.g. on chains without a registry or to avoid the extra calls
+    pub fn without_ens(mut self) -> Self {
+        self.ens = false;
+        self
+    }
+
+    pub async fn label(&self, address: &str) -> Result<Option<Label>> {
+        Ok(self.labels(&[address]).await?.pop().flatten())
+    }
+
+    // Labels many addresses, with the ENS lookups batched through Multicall3
+    pub async fn labels(&self, addresses: &[&str]) -> Result<Vec<Option<Label>>> {
+        let ens_names = match self.ens {
+            true => ens::lookup_addresses(&self.client, addresses).await?,
+            false => vec![None; addresses.len()],
+        };
+        let mut labels = Vec::with_capacity(addresses.len());
+        for (address, ens_name) in addresses.iter().zip(ens_names) {
+            let label = match ens_name {
+                Some(name) => Some(Label { name, source: LabelSource::Ens }),
+                None => self.offline_label(address).or(self.heuristic_label(address).await?),
+            };
+            labels.push(label);
+        }
+        Ok(labels)
+    }
+
+    // "label (0x...)" when known, the bare address otherwise, in the global address format
+    pub async fn format_address(&self, address: &str) -> Result<String> {
+        let formatted = format::address(address);
+        Ok(match self.label(address).await? {
+            Some(label) => format!("{} ({})", label.name, formatted),
+            None => formatted,
+        })
+    }
+
+    fn offline_label(&self, address: &str) -> Option<Label> {
+        if let Some(name) = self.address_book.get(&address.to_lowercase()) {
+            return Some(Label { name: name.clone(), source: LabelSource::AddressBook });
+        }
+        let name = self.registry.get(self.chain_id, address)?;
+        Some(Label { name: name.to_string(), source: LabelSource::WellKnown })
+    }
+
+    async fn heuristic_label(&self, address: &str) -> Result<Option<Label>> {
+        let code = get_code(&self.client, address).await?;
+        let code = code.trim_start_matches("0x").to_lowercase();
+        if code.is_empty() {
+            return Ok(None);
+        }
+        let name = if let Some(implementation) = code.strip_prefix(EIP1167_PREFIX).and_then(|rest| rest.strip_suffix(EIP1167_SUFFIX)) {
+            format!("Minimal proxy to {}", self.registry.format_address(self.chain_id, &format!("0x{}", implementation)))
+        } else if code.len() < 400 && code.contains(SAFE_PROXY_SELECTOR) {
+            "Safe proxy".to_string()
+        } else {
+            match get_implementation(&self.client, address).await {
+                Ok(implementation) if implementation.trim_start_matches("0x").bytes().any(|byte| byte != b'0') => {
+                    format!("EIP-1967 proxy to {}", self.registry.format_address(self.chain_id, &implementation))
+                }
+                _ => return Ok(None),
+            }
+        };
+        Ok(Some(Label { name, source: LabelSource::Heuristic }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multicall::tests::Multicall;
+
+    const SAFE_PROXY_CODE: &str = "0x608060405273ffffffffffffffffffffffffffffffffffffffff600054167fa619486e0000000000000000000000000000000000000000000000000000000060003514156050578060005260206000f35b3660008037600080366000845af43d6000803e60008114156070573d6000fd5b3d6000f3fea2646970667358221220d1429297349653a4918076d650332de1a1068c5f3e07c5c82360c277770b955264736f6c63430007060033";
+
+    #[tokio::test]
+    async fn applies_label_precedence() {
+        let usdc = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+        let safe = "0x1111111111111111111111111111111111111111";
+        let clone = "0x2222222222222222222222222222222222222222";
+        let unknown = "0x3333333333333333333333333333333333333333";
+
+        let mut answers = HashMap::new();
+        answers.insert(("eth_getCode".to_string(), safe.to_string()), SAFE_PROXY_CODE.to_string());
+        answers.insert(("eth_getCode".to_string(), clone.to_string()), format!("0x{}{}{}", EIP1167_PREFIX, &usdc[2..].to_lowercase(), EIP1167_SUFFIX));
+        let client = Multicall(answers).client();
+
+        let mut address_book = BTreeMap::new();
+        address_book.insert("treasury".to_string(), safe.to_string());
+        let resolver = LabelResolver::new(&client, 1).with_address_book(&address_book).without_ens();
+
+        let labels = resolver.labels(&[usdc, safe, clone, unknown]).await.unwrap();
+        assert_eq!(labels[0], Some(Label { name: "USDC".to_string(), source: LabelSource::WellKnown }));
+        // The address book beats what the code suggests
+        assert_eq!(labels[1], Some(Label { name: "treasury".to_string(), source: LabelSource::AddressBook }));
+        assert_eq!(labels[2].as_ref().map(|label| label.source), Some(LabelSource::Heuristic));
+        assert!(labels[2].as_ref().unwrap().name.starts_with("Minimal proxy to USDC"));
+        assert_eq!(labels[3], None);
+
+        let resolver = LabelResolver::new(&client, 1).without_ens();
+        assert_eq!(resolver.label(safe).await.unwrap().unwrap().name, "Safe proxy");
+    }
+}
@@ -0,0 +1,99 @@
+pub mod abi;
+pub mod activity;
+pub mod artifacts;
+pub mod automation;
+pub mod batch;
+pub mod beacon;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod blocks;
+pub mod bloom;
+pub mod bridge;
+pub mod bus;
+pub mod bytecode;
+pub mod ccip;
+pub mod classify;
+pub mod client;
+pub mod config;
+pub mod confirm;
+pub mod context;
+pub mod crawler;
+pub mod dialect;
+pub mod eip681;
+pub mod eip712;
+pub mod enrich;
+pub mod ens;
+pub mod erc4337;
+pub mod error;
+pub mod etherscan;
+pub mod extension;
+pub mod ffi;
+pub mod format;
+pub mod gas;
+pub mod graphql;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod headers;
+pub mod indexer;
+pub mod interaction;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+pub mod key;
+pub mod labels;
+pub mod logs;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod middleware;
+#[cfg(feature = "mobile")]
+pub mod mobile;
+pub mod multicall;
+pub mod offline;
+#[cfg(feature = "nats")]
+pub mod nats;
+pub mod overrides;
+pub mod pending;
+pub mod poll;
+pub mod portfolio;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod progress;
+pub mod provider;
+pub mod proxy;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "qr")]
+pub mod qr;
+pub mod rlp;
+pub mod router;
+pub mod scheduler;
+pub mod signature;
+#[cfg(feature = "simulation")]
+pub mod simulation;
+pub mod sink;
+pub mod siwe;
+pub mod storage;
+pub mod store;
+pub mod stuck;
+#[cfg(test)]
+mod testing;
+pub mod trace;
+pub mod transaction;
+pub mod transport;
+pub mod trie;
+pub mod utils;
+pub mod vectors;
+pub mod wallet;
+pub mod zksync;
+
+pub use blocks::BlockTag;
+pub use client::{ClientBuilder, EthClient, JsonRpcResponse, RpcError};
+pub use error::{Error, Result};
+pub use key::SecretKey;
+pub use middleware::{AdaptiveConcurrencyLayer, CacheLayer, DedupLayer, Layer, Priority, PriorityLayer, RateLimitLayer, RetryLayer};
+pub use provider::{FallbackProvider, QuorumProvider};
+pub use router::ChainRouter;
+pub use transport::{HttpTransport, ResponseLimits, Transport, WsTransport};
+pub use wallet::Wallet;
+
+#[cfg(feature = "mobile")]
+uniffi::setup_scaffolding!("evm_json_rpc");
@@ -0,0 +1,241 @@
+use ethabi::ethereum_types::{H256, U64};
+use serde::{Deserialize, Serialize};
+
+use crate::blocks::{block_at_timestamp, BlockTag};
+use crate::bloom::Bloom;
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+
+// A log as returned by eth_getLogs and inside receipts. The block and transaction
+// fields are missing for pending logs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Log {
+    pub address: String,
+    pub topics: Vec<H256>,
+    // 0x-prefixed ABI-encoded non-indexed parameters
+    pub data: String,
+    pub block_number: Option<U64>,
+    pub block_hash: Option<H256>,
+    pub transaction_hash: Option<H256>,
+    pub transaction_index: Option<U64>,
+    pub log_index: Option<U64>,
+    // Set when a reorg dropped the log
+    #[serde(default)]
+    pub removed: bool,
+}
+
+impl Log {
+    pub fn data_bytes(&self) -> Result<Vec<u8>> {
+        hex::decode(self.data.trim_start_matches("0x")).map_err(|error| Error::Decode(format!("Invalid log data: {}", error)))
+    }
+
+    pub fn raw_log(&self) -> Result<ethabi::RawLog> {
+        Ok(ethabi::RawLog {
+            topics: self.topics.clone(),
+            data: self.data_bytes()?,
+        })
+    }
+}
+
+// eth_getLogs filter. A log matches when it comes from any of the addresses (or any
+// address when empty) and, at each topic position, has one of the listed topics.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    pub addresses: Vec<String>,
+    // Positions 0-3; None matches anything at that position
+    pub topics: [Option<Vec<H256>>; 4],
+    pub from_block: Option<BlockTag>,
+    pub to_block: Option<BlockTag>,
+    // Unix seconds, turned into block numbers by `resolve` before the filter is sent.
+    // When set they take the place of the block bound on the same side.
+    pub from_time: Option<u64>,
+    pub to_time: Option<u64>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Adds an emitting contract; call again to match several
+    pub fn with_address(mut self, address: &str) -> Self {
+        self.addresses.push(address.to_lowercase());
+        self
+    }
+
+    // Matches the event's topic0, e.g. "Transfer(address,address,uint256)"
+    pub fn with_event(self, signature: &str) -> Self {
+        self.with_topic(0, keccak_hash::keccak(signature.as_bytes()))
+    }
+
+    // Adds an alternative at `position` (0-3); out-of-range positions are ignored
+    pub fn with_topic(mut self, position: usize, topic: H256) -> Self {
+        if let Some(topics) = self.topics.get_mut(position) {
+            topics.get_or_insert_with(Vec::new).push(topic);
+        }
+        self
+    }
+
+    pub fn with_from_block(mut self, block: impl Into<BlockTag>) -> Self {
+        self.from_block = Some(block.into());
+        self
+    }
+
+    pub fn with_to_block(mut self, block: impl Into<BlockTag>) -> Self {
+        self.to_block = Some(block.into());
+        self
+    }
+
+    // Logs from blocks produced at or after `timestamp`
+    pub fn with_from_time(mut self, timestamp: u64) -> Self {
+        self.from_time = Some(timestamp);
+        self
+    }
+
+    // Logs from blocks produced at or before `timestamp`
+    pub fn with_to_time(mut self, timestamp: u64) -> Self {
+        self.to_time = Some(timestamp);
+        self
+    }
+
+    // The filter with its time bounds replaced by block numbers. The range is empty
+    // (from above to) when no block falls inside the time window.
+    pub async fn resolve(&self, client: &EthClient) -> Result<Filter> {
+        let mut filter = self.clone();
+        if let Some(timestamp) = filter.from_time.take() {
+            filter.from_block = Some(BlockTag::Number(block_at_timestamp(client, timestamp).await?));
+        }
+        if let Some(timestamp) = filter.to_time.take() {
+            // Last block at or before the timestamp; before genesis gives an empty range
+            match block_at_timestamp(client, timestamp.saturating_add(1)).await? {
+                0 => {
+                    filter.from_block = Some(BlockTag::Number(1));
+                    filter.to_block = Some(BlockTag::Number(0));
+                }
+                next => filter.to_block = Some(BlockTag::Number(next - 1)),
+            }
+        }
+        Ok(filter)
+    }
+
+    // True when both bounds are block numbers and from is past to
+    pub fn is_empty_range(&self) -> bool {
+        matches!((self.from_block, self.to_block), (Some(BlockTag::Number(from)), Some(BlockTag::Number(to))) if from > to)
+    }
+
+    pub fn matches(&self, log: &Log) -> bool {
+        let address = self.addresses.is_empty() || self.addresses.iter().any(|address| address.eq_ignore_ascii_case(&log.address));
+        address
+            && self.topics.iter().enumerate().all(|(position, topics)| match topics {
+                Some(topics) => log.topics.get(position).is_some_and(|topic| topics.contains(topic)),
+                None => true,
+            })
+    }
+
+    // False when the bloom rules out every matching log, e.g. for a block header's
+    // logsBloom; true may still be a false positive
+    pub fn might_match(&self, bloom: &Bloom) -> bool {
+        let address = self.addresses.is_empty() || self.addresses.iter().any(|address| bloom.might_contain_hex(address));
+        address && self.topics.iter().flatten().all(|topics| topics.iter().any(|topic| bloom.might_contain(topic.as_bytes())))
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut filter = serde_json::Map::new();
+        match self.addresses.as_slice() {
+            [] => {}
+            [address] => {
+                filter.insert("address".to_string(), address.as_str().into());
+            }
+            addresses => {
+                filter.insert("address".to_string(), serde_json::json!(addresses));
+            }
+        }
+        // Trailing wildcards are left out, and single alternatives aren't wrapped
+        let used = self.topics.iter().rposition(Option::is_some).map_or(0, |last| last + 1);
+        if used > 0 {
+            let topics: Vec<serde_json::Value> = self.topics[..used]
+                .iter()
+                .map(|topics| match topics.as_deref() {
+                    None => serde_json::Value::Null,
+                    Some([topic]) => serde_json::json!(topic),
+                    Some(topics) => serde_json::json!(topics),
+                })
+                .collect();
+            filter.insert("topics".to_string(), topics.into());
+        }
+        if let Some(block) = self.from_block {
+            filter.insert("fromBlock".to_string(), block.to_json());
+        }
+        if let Some(block) = self.to_block {
+            filter.insert("toBlock".to_string(), block.to_json());
+        }
+        serde_json::Value::Object(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_and_matches_filters() {
+        let transfer = keccak_hash::keccak(b"Transfer(address,address,uint256)");
+        let holder = H256::from_low_u64_be(7);
+        let filter = Filter::new().with_address("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").with_event("Transfer(address,address,uint256)").with_topic(2, holder).with_from_block(100).with_to_block(BlockTag::Latest);
+        assert_eq!(
+            filter.to_json(),
+            serde_json::json!({
+                "address": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+                "topics": [transfer, null, holder],
+                "fromBlock": "0x64",
+                "toBlock": "latest",
+            })
+        );
+
+        let log = Log {
+            address: "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string(),
+            topics: vec![transfer, H256::from_low_u64_be(1), holder],
+            data: "0x".to_string(),
+            block_number: None,
+            block_hash: None,
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+            removed: false,
+        };
+        assert!(filter.matches(&log));
+        let mut bloom = Bloom::empty();
+        bloom.accrue_log(&log.data_bytes().unwrap(), &[]);
+        assert!(!filter.might_match(&bloom));
+        let topics: Vec<&[u8]> = log.topics.iter().map(|topic| topic.as_bytes()).collect();
+        bloom.accrue_log(&hex::decode(&log.address[2..]).unwrap(), &topics);
+        assert!(filter.might_match(&bloom));
+        assert!(!filter.matches(&Log { topics: vec![transfer, holder, H256::from_low_u64_be(1)], ..log }));
+    }
+
+    // 101 blocks, 12 seconds apart from 1000; eth_getLogs echoes the filter it got
+    fn chain() -> EthClient {
+        crate::testing::MockNode::new(|method, params| match method {
+            "eth_blockNumber" => Ok(serde_json::json!("0x64")),
+            "eth_getBlockByNumber" => {
+                let number = u64::from_str_radix(params[0].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+                Ok(serde_json::json!({"number": params[0], "timestamp": format!("0x{:x}", 1000 + 12 * number)}))
+            }
+            "eth_getLogs" => Ok(serde_json::json!([{"address": params[0]["fromBlock"], "topics": [], "data": params[0]["toBlock"]}])),
+            _ => Err(crate::testing::Failure::unexpected(method)),
+        })
+        .client()
+    }
+
+    #[tokio::test]
+    async fn resolves_time_bounds_to_blocks() {
+        let client = chain();
+        let logs = client.get_logs(&Filter::new().with_from_time(1000 + 12 * 10 + 5).with_to_time(1000 + 12 * 20)).await.unwrap();
+        assert_eq!((logs[0].address.as_str(), logs[0].data.as_str()), ("0xb", "0x14"));
+
+        let resolved = Filter::new().with_from_time(1000 + 12 * 100 + 1).resolve(&client).await.unwrap();
+        assert_eq!((resolved.from_block, resolved.from_time), (Some(BlockTag::Number(101)), None));
+        assert!(client.get_logs(&Filter::new().with_to_time(999)).await.unwrap().is_empty());
+    }
+}
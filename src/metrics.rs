@@ -0,0 +1,158 @@
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, TextEncoder};
+pub use prometheus::Registry;
+
+use crate::context::RequestContext;
+use crate::error::{Error, Result};
+use crate::middleware::Layer;
+use crate::transport::Transport;
+
+// Prometheus collectors for RPC traffic. Register once, then add as a layer; put it
+// inside RetryLayer to measure each attempt, and give RetryLayer the same handle to
+// count retries. Requests, errors and latency are also labelled by the job of the
+// RequestContext they run in.
+#[derive(Clone)]
+pub struct Metrics {
+    requests: IntCounterVec,
+    errors: IntCounterVec,
+    duration: HistogramVec,
+    retries: IntCounterVec,
+    response_bytes: IntCounterVec,
+    tag_labels: Arc<Vec<String>>,
+}
+
+impl Metrics {
+    pub fn register(registry: &Registry) -> Result<Self> {
+        Self::register_with_tags(registry, &[])
+    }
+
+    // Adds a label for each of these RequestContext tags, empty where a request lacks
+    // it. Every combination of values is its own series, so pick low-cardinality tags.
+    pub fn register_with_tags(registry: &Registry, tags: &[&str]) -> Result<Self> {
+        let invalid = |error: prometheus::Error| Error::Config(format!("Failed to register metrics: {}", error));
+        let labels = |base: &[&'static str]| -> Vec<&str> { base.iter().copied().chain(["job"]).chain(tags.iter().copied()).collect() };
+        let metrics = Metrics {
+            requests: IntCounterVec::new(Opts::new("evm_rpc_requests_total", "JSON-RPC requests sent"), &labels(&["method"])).map_err(invalid)?,
+            errors: IntCounterVec::new(Opts::new("evm_rpc_errors_total", "Failed JSON-RPC requests by error code, or \"transport\""), &labels(&["method", "code"])).map_err(invalid)?,
+            duration: HistogramVec::new(HistogramOpts::new("evm_rpc_request_duration_seconds", "JSON-RPC request latency"), &labels(&["method"])).map_err(invalid)?,
+            retries: IntCounterVec::new(Opts::new("evm_rpc_retries_total", "JSON-RPC requests retried by RetryLayer"), &["method"]).map_err(invalid)?,
+            response_bytes: IntCounterVec::new(Opts::new("evm_rpc_response_bytes_total", "Size of JSON-RPC responses as serialized JSON"), &["method"]).map_err(invalid)?,
+            tag_labels: Arc::new(tags.iter().map(|tag| tag.to_string()).collect()),
+        };
+        registry.register(Box::new(metrics.requests.clone())).map_err(invalid)?;
+        registry.register(Box::new(metrics.errors.clone())).map_err(invalid)?;
+        registry.register(Box::new(metrics.duration.clone())).map_err(invalid)?;
+        registry.register(Box::new(metrics.retries.clone())).map_err(invalid)?;
+        registry.register(Box::new(metrics.response_bytes.clone())).map_err(invalid)?;
+        Ok(metrics)
+    }
+
+    pub fn record_retry(&self, method: &str) {
+        self.retries.with_label_values(&[method]).inc();
+    }
+
+    // `base` followed by the job and configured tags of the current RequestContext
+    fn label_values(&self, base: &[&str]) -> Vec<String> {
+        let context = RequestContext::current().unwrap_or_default();
+        let mut values: Vec<String> = base.iter().map(|value| value.to_string()).collect();
+        values.push(context.job.clone().unwrap_or_default());
+        values.extend(self.tag_labels.iter().map(|tag| context.tags.get(tag).cloned().unwrap_or_default()));
+        values
+    }
+}
+
+fn as_strs(values: &[String]) -> Vec<&str> {
+    values.iter().map(String::as_str).collect()
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Metrics")
+    }
+}
+
+// Text exposition format, for serving on a /metrics endpoint
+pub fn render(registry: &Registry) -> Result<String> {
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&registry.gather(), &mut buffer).map_err(|error| Error::Decode(format!("Failed to encode metrics: {}", error)))?;
+    String::from_utf8(buffer).map_err(|_| Error::Decode("Metrics are not valid UTF-8".to_string()))
+}
+
+impl Layer for Metrics {
+    fn layer(&self, inner: Arc<dyn Transport>) -> Arc<dyn Transport> {
+        Arc::new(Measured {
+            inner,
+            metrics: self.clone(),
+        })
+    }
+}
+
+struct Measured {
+    inner: Arc<dyn Transport>,
+    metrics: Metrics,
+}
+
+#[async_trait]
+impl Transport for Measured {
+    async fn request(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        let labels = self.metrics.label_values(&[method]);
+        self.metrics.requests.with_label_values(&as_strs(&labels)).inc();
+        let started = Instant::now();
+        let result = self.inner.request(method, params).await;
+        self.metrics.duration.with_label_values(&as_strs(&labels)).observe(started.elapsed().as_secs_f64());
+
+        match &result {
+            Ok(response) => {
+                let mut counter = ByteCounter(0);
+                if serde_json::to_writer(&mut counter, response).is_ok() {
+                    self.metrics.response_bytes.with_label_values(&[method]).inc_by(counter.0);
+                }
+                if let Some(error) = response.get("error") {
+                    let code = error["code"].as_i64().map_or_else(|| "unknown".to_string(), |code| code.to_string());
+                    self.metrics.errors.with_label_values(&as_strs(&self.metrics.label_values(&[method, &code]))).inc();
+                }
+            }
+            Err(_) => self.metrics.errors.with_label_values(&as_strs(&self.metrics.label_values(&[method, "transport"]))).inc(),
+        }
+        result
+    }
+}
+
+// Measures serialized size without allocating the JSON
+struct ByteCounter(u64);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{ClientBuilder, EthClient};
+    use crate::testing::MockNode;
+
+    #[tokio::test]
+    async fn labels_requests_by_context() {
+        let registry = Registry::new();
+        let metrics = Metrics::register_with_tags(&registry, &["tenant"]).unwrap();
+        let client: EthClient = ClientBuilder::with_transport(MockNode::with_results([("eth_blockNumber", serde_json::json!("0x1"))])).layer(metrics).build();
+        RequestContext::new("indexer").with_tag("tenant", "acme").scope(client.get_block_number()).await.unwrap();
+        client.get_block_number().await.unwrap();
+
+        let rendered = render(&registry).unwrap();
+        assert!(rendered.contains(r#"evm_rpc_requests_total{job="indexer",method="eth_blockNumber",tenant="acme"} 1"#));
+        assert!(rendered.contains(r#"evm_rpc_requests_total{job="",method="eth_blockNumber",tenant=""} 1"#));
+    }
+}
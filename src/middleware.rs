@@ -0,0 +1,989 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures_util::future::{BoxFuture, FutureExt, Shared};
+use tokio::sync::{oneshot, Notify};
+use tracing::Instrument;
+
+use crate::poll::jittered;
+use crate::transport::{Transport, TransportError};
+
+// JSON-RPC error codes providers use for rate limiting
+pub const RATE_LIMIT_ERROR_CODES: [i64; 2] = [-32005, 429];
+
+// Wraps a transport with cross-cutting behaviour (retries, caching, rate limiting,
+// metrics). The returned transport delegates to `inner` for the actual request.
+pub trait Layer {
+    fn layer(&self, inner: Arc<dyn Transport>) -> Arc<dyn Transport>;
+}
+
+impl<F> Layer for F
+where
+    F: Fn(Arc<dyn Transport>) -> Arc<dyn Transport>,
+{
+    fn layer(&self, inner: Arc<dyn Transport>) -> Arc<dyn Transport> {
+        self(inner)
+    }
+}
+
+// Retries transient failures (HTTP 429/5xx, timeouts, connection errors and
+// rate-limit error responses) with jittered exponential backoff
+#[derive(Debug, Clone)]
+pub struct RetryLayer {
+    // Including the first attempt
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    // Methods that are never retried, e.g. because resending is not idempotent
+    pub skip_methods: Vec<String>,
+    #[cfg(feature = "metrics")]
+    pub metrics: Option<crate::metrics::Metrics>,
+}
+
+impl Default for RetryLayer {
+    fn default() -> Self {
+        RetryLayer {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+            skip_methods: vec!["eth_sendTransaction".to_string(), "eth_sendRawTransaction".to_string()],
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+}
+
+impl Layer for RetryLayer {
+    fn layer(&self, inner: Arc<dyn Transport>) -> Arc<dyn Transport> {
+        Arc::new(Retry {
+            inner,
+            policy: self.clone(),
+        })
+    }
+}
+
+struct Retry {
+    inner: Arc<dyn Transport>,
+    policy: RetryLayer,
+}
+
+#[async_trait]
+impl Transport for Retry {
+    async fn request(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        let retryable = !self.policy.skip_methods.iter().any(|skipped| skipped == method);
+        let mut backoff = self.policy.initial_backoff;
+        let mut attempt = 1;
+        loop {
+            let span = tracing::debug_span!("rpc_attempt", method, attempt);
+            let result = self.inner.request(method, params.clone()).instrument(span).await;
+            let transient = match &result {
+                Ok(response) => is_rate_limited(response),
+                Err(error) => is_transient(error.as_ref()),
+            };
+            if !retryable || !transient || attempt >= self.policy.max_attempts {
+                return result;
+            }
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = &self.policy.metrics {
+                metrics.record_retry(method);
+            }
+            tokio::time::sleep(jittered(backoff)).await;
+            backoff = (backoff * 2).min(self.policy.max_backoff);
+            attempt += 1;
+        }
+    }
+}
+
+// Token bucket allowing `requests_per_second` on average and bursts of up to `burst`
+// requests. Every transport it wraps gets its own bucket, so wrapping each
+// FallbackProvider endpoint separately gives per-endpoint limits.
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+impl RateLimitLayer {
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        RateLimitLayer {
+            requests_per_second,
+            burst,
+        }
+    }
+}
+
+impl Layer for RateLimitLayer {
+    fn layer(&self, inner: Arc<dyn Transport>) -> Arc<dyn Transport> {
+        Arc::new(RateLimit {
+            inner,
+            policy: self.clone(),
+            bucket: Mutex::new((self.burst as f64, Instant::now())),
+        })
+    }
+}
+
+struct RateLimit {
+    inner: Arc<dyn Transport>,
+    policy: RateLimitLayer,
+    // Available tokens (negative when requests are queued) and the last refill time
+    bucket: Mutex<(f64, Instant)>,
+}
+
+impl RateLimit {
+    // Takes a token, returning how long the caller must wait for it. Reserving ahead
+    // keeps waiting requests in arrival order.
+    fn reserve(&self) -> Duration {
+        // A non-positive rate would never refill; treat it as unlimited
+        if self.policy.requests_per_second <= 0.0 {
+            return Duration::ZERO;
+        }
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let refilled = bucket.0 + now.duration_since(bucket.1).as_secs_f64() * self.policy.requests_per_second;
+        let tokens = refilled.min(self.policy.burst as f64) - 1.0;
+        *bucket = (tokens, now);
+        if tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-tokens / self.policy.requests_per_second)
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for RateLimit {
+    async fn request(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        let delay = self.reserve();
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        self.inner.request(method, params).await
+    }
+}
+
+// Coalesces concurrent identical requests (same method and params) into one upstream
+// call whose response is handed to every caller
+#[derive(Debug, Clone)]
+pub struct DedupLayer {
+    // Methods that must reach the node once per caller, e.g. because they create state
+    pub skip_methods: Vec<String>,
+}
+
+impl Default for DedupLayer {
+    fn default() -> Self {
+        let skip_methods = ["eth_newFilter", "eth_newBlockFilter", "eth_newPendingTransactionFilter", "eth_sendTransaction", "eth_sendRawTransaction"];
+        DedupLayer {
+            skip_methods: skip_methods.iter().map(|method| method.to_string()).collect(),
+        }
+    }
+}
+
+impl Layer for DedupLayer {
+    fn layer(&self, inner: Arc<dyn Transport>) -> Arc<dyn Transport> {
+        Arc::new(Dedup {
+            inner,
+            policy: self.clone(),
+            in_flight: Mutex::new(HashMap::new()),
+        })
+    }
+}
+
+type SharedResponse = Shared<BoxFuture<'static, Result<serde_json::Value, Arc<dyn std::error::Error + Send + Sync>>>>;
+
+// The upstream error handed to every coalesced caller. The original stays reachable
+// through source(), so is_transient and downcasts still see it.
+#[derive(Debug)]
+struct SharedError(Arc<dyn std::error::Error + Send + Sync>);
+
+impl fmt::Display for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for SharedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.0.as_ref())
+    }
+}
+
+struct Dedup {
+    inner: Arc<dyn Transport>,
+    policy: DedupLayer,
+    in_flight: Mutex<HashMap<String, SharedResponse>>,
+}
+
+// Removes the in-flight entry when its caller finishes or is cancelled, so an abandoned
+// request is never handed to later callers. Only the entry for this call is removed; a
+// newer identical request may have replaced it.
+struct InFlightGuard<'a> {
+    in_flight: &'a Mutex<HashMap<String, SharedResponse>>,
+    key: String,
+    response: SharedResponse,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if in_flight.get(&self.key).is_some_and(|current| current.ptr_eq(&self.response)) {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for Dedup {
+    async fn request(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        if self.policy.skip_methods.iter().any(|skipped| skipped == method) {
+            return self.inner.request(method, params).await;
+        }
+
+        let key = format!("{}{}", method, serde_json::Value::Array(params.clone()));
+        let response = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_insert_with(|| {
+                let inner = self.inner.clone();
+                let method = method.to_string();
+                async move { inner.request(&method, params).await.map_err(Arc::from) }.boxed().shared()
+            })
+            .clone();
+
+        let guard = InFlightGuard {
+            in_flight: &self.in_flight,
+            key,
+            response: response.clone(),
+        };
+        let result = response.await;
+        drop(guard);
+        result.map_err(|error| Box::new(SharedError(error)) as Box<dyn std::error::Error + Send + Sync>)
+    }
+}
+
+// Caches responses that can't change: the chain id, anything pinned to a block hash,
+// and calls, blocks and mined transactions at least `finality_depth` blocks behind the
+// head. A reorg can still replace anything newer, so those are only kept for
+// `recent_ttl`. The head comes from eth_blockNumber, fetched at most once per
+// `recent_ttl` when a request needs it.
+#[derive(Debug, Clone)]
+pub struct CacheLayer {
+    pub ttl: Duration,
+    pub max_entries: usize,
+    // Blocks this far behind the head are treated as final
+    pub finality_depth: u64,
+    pub recent_ttl: Duration,
+    // Also caches eth_call against "latest" for this long, e.g. for token metadata
+    // such as name() and symbol() that never changes in practice
+    pub latest_call_ttl: Option<Duration>,
+}
+
+impl Default for CacheLayer {
+    fn default() -> Self {
+        CacheLayer {
+            ttl: Duration::from_secs(3600),
+            max_entries: 10_000,
+            // Two epochs, when a mainnet block is finalized
+            finality_depth: 64,
+            recent_ttl: Duration::from_secs(2),
+            latest_call_ttl: None,
+        }
+    }
+}
+
+impl CacheLayer {
+    // How long a response may be cached, given the chain head if known. Transactions
+    // and receipts get `recent_ttl` here, extended once their block is known to be final.
+    fn ttl_for(&self, method: &str, params: &[serde_json::Value], head: Option<u64>) -> Option<Duration> {
+        match method {
+            "eth_chainId" | "eth_getBlockByHash" | "eth_getBlockTransactionCountByHash" => Some(self.ttl),
+            "eth_getTransactionByHash" | "eth_getTransactionReceipt" => Some(self.recent_ttl),
+            "eth_call" if params.get(1).is_none_or(|block| block == "latest") => self.latest_call_ttl,
+            _ => params.get(block_param(method)?).and_then(|block| self.block_ttl(block, head)),
+        }
+    }
+
+    // A hex block number or an EIP-1898 {blockHash} / {blockNumber} object; tags move
+    fn block_ttl(&self, block: &serde_json::Value, head: Option<u64>) -> Option<Duration> {
+        let number = match block {
+            serde_json::Value::Object(block) if block.contains_key("blockHash") => return Some(self.ttl),
+            serde_json::Value::Object(block) => block.get("blockNumber")?,
+            block => block,
+        };
+        Some(self.number_ttl(parse_block_number(number)?, head))
+    }
+
+    fn number_ttl(&self, number: u64, head: Option<u64>) -> Duration {
+        match head {
+            Some(head) if number.saturating_add(self.finality_depth) <= head => self.ttl,
+            _ => self.recent_ttl,
+        }
+    }
+
+    // Whether the TTL depends on how far the block is behind the head
+    fn needs_head(method: &str, params: &[serde_json::Value]) -> bool {
+        if matches!(method, "eth_getTransactionByHash" | "eth_getTransactionReceipt") {
+            return true;
+        }
+        let block = block_param(method).and_then(|index| params.get(index));
+        block.and_then(|block| block.get("blockNumber").or(Some(block))).and_then(parse_block_number).is_some()
+    }
+}
+
+// Position of the block parameter, for methods that take one
+fn block_param(method: &str) -> Option<usize> {
+    match method {
+        "eth_getBlockByNumber" | "eth_getBlockTransactionCountByNumber" => Some(0),
+        "eth_getBalance" | "eth_getCode" | "eth_getTransactionCount" | "eth_call" => Some(1),
+        "eth_getStorageAt" => Some(2),
+        _ => None,
+    }
+}
+
+fn parse_block_number(block: &serde_json::Value) -> Option<u64> {
+    u64::from_str_radix(block.as_str()?.strip_prefix("0x")?, 16).ok()
+}
+
+impl Layer for CacheLayer {
+    fn layer(&self, inner: Arc<dyn Transport>) -> Arc<dyn Transport> {
+        Arc::new(Cache {
+            inner,
+            policy: self.clone(),
+            entries: Mutex::new(HashMap::new()),
+            head: Mutex::new(None),
+        })
+    }
+}
+
+struct Cache {
+    inner: Arc<dyn Transport>,
+    policy: CacheLayer,
+    // Response and its expiry, keyed by method and params
+    entries: Mutex<HashMap<String, (serde_json::Value, Instant)>>,
+    // Latest head seen and when
+    head: Mutex<Option<(u64, Instant)>>,
+}
+
+impl Cache {
+    fn get(&self, key: &str) -> Option<serde_json::Value> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((response, expires)) if *expires > Instant::now() => Some(response.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: String, response: serde_json::Value, ttl: Duration) {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        if entries.len() >= self.policy.max_entries && !entries.contains_key(&key) {
+            entries.retain(|_, (_, expires)| *expires > now);
+            // Still full: drop whatever expires soonest
+            if entries.len() >= self.policy.max_entries {
+                let soonest = entries.iter().min_by_key(|(_, (_, expires))| *expires).map(|(key, _)| key.clone());
+                if let Some(soonest) = soonest {
+                    entries.remove(&soonest);
+                }
+            }
+        }
+        if self.policy.max_entries > 0 {
+            entries.insert(key, (response, now + ttl));
+        }
+    }
+
+    fn observe_head(&self, response: &serde_json::Value) {
+        if let Some(number) = parse_block_number(&response["result"]) {
+            *self.head.lock().unwrap() = Some((number, Instant::now()));
+        }
+    }
+
+    // The head, asking the node when the last one seen is older than `recent_ttl`.
+    // Unknown if that fails, which only means nothing is cached for long.
+    async fn head(&self) -> Option<u64> {
+        let seen = *self.head.lock().unwrap();
+        if let Some((head, _)) = seen.filter(|(_, at)| at.elapsed() < self.policy.recent_ttl) {
+            return Some(head);
+        }
+        let response = self.inner.request("eth_blockNumber", Vec::new()).await.ok()?;
+        self.observe_head(&response);
+        parse_block_number(&response["result"])
+    }
+}
+
+#[async_trait]
+impl Transport for Cache {
+    async fn request(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        if method == "eth_blockNumber" {
+            let response = self.inner.request(method, params).await?;
+            self.observe_head(&response);
+            return Ok(response);
+        }
+        let head = match CacheLayer::needs_head(method, &params) {
+            true => self.head().await,
+            false => None,
+        };
+        let mut ttl = match self.policy.ttl_for(method, &params, head) {
+            Some(ttl) => ttl,
+            None => return self.inner.request(method, params).await,
+        };
+        let key = format!("{}{}", method, serde_json::Value::Array(params.clone()));
+        if let Some(response) = self.get(&key) {
+            return Ok(response);
+        }
+
+        let response = self.inner.request(method, params).await?;
+        // Errors, missing results and not-yet-mined transactions may change later
+        let result = &response["result"];
+        let transaction = matches!(method, "eth_getTransactionByHash" | "eth_getTransactionReceipt");
+        if transaction {
+            if let Some(number) = parse_block_number(&result["blockNumber"]) {
+                ttl = self.policy.number_ttl(number, head);
+            }
+        }
+        let pending = transaction && result["blockHash"].is_null();
+        if response.get("error").is_none() && !result.is_null() && !pending {
+            self.insert(key, response.clone(), ttl);
+        }
+        Ok(response)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+// Caps concurrent upstream requests and, when they back up, sends higher-priority
+// methods first so sends and nonce fetches don't queue behind a backfill. A waiting
+// lower-priority request is let through after `max_skips` others jumped ahead of it.
+#[derive(Debug, Clone)]
+pub struct PriorityLayer {
+    pub max_concurrent: usize,
+    pub max_skips: u32,
+    // Methods not listed here are Normal
+    pub methods: HashMap<String, Priority>,
+}
+
+impl Default for PriorityLayer {
+    fn default() -> Self {
+        let high = ["eth_sendRawTransaction", "eth_sendTransaction", "eth_getTransactionCount", "eth_chainId"];
+        let low = ["eth_getLogs", "eth_getBlockReceipts", "debug_traceTransaction", "debug_traceBlockByNumber", "trace_block", "trace_filter"];
+        let methods = high.iter().map(|method| (method.to_string(), Priority::High)).chain(low.iter().map(|method| (method.to_string(), Priority::Low)));
+        PriorityLayer {
+            max_concurrent: 8,
+            max_skips: 16,
+            methods: methods.collect(),
+        }
+    }
+}
+
+impl PriorityLayer {
+    pub fn with_priority(mut self, method: &str, priority: Priority) -> Self {
+        self.methods.insert(method.to_string(), priority);
+        self
+    }
+
+    fn priority(&self, method: &str) -> Priority {
+        self.methods.get(method).copied().unwrap_or(Priority::Normal)
+    }
+}
+
+impl Layer for PriorityLayer {
+    fn layer(&self, inner: Arc<dyn Transport>) -> Arc<dyn Transport> {
+        Arc::new(Prioritized {
+            inner,
+            policy: self.clone(),
+            scheduler: Arc::new(Mutex::new(Schedule::default())),
+        })
+    }
+}
+
+#[derive(Default)]
+struct Schedule {
+    in_flight: usize,
+    // Indexed by Priority, each with the times it was skipped since last served
+    queues: [(VecDeque<oneshot::Sender<Permit>>, u32); 3],
+}
+
+impl Schedule {
+    // A starved queue first, lowest priority first, then the highest waiting
+    fn next(&mut self, max_skips: u32) -> Option<oneshot::Sender<Permit>> {
+        let starved = (0..3).find(|&index| !self.queues[index].0.is_empty() && self.queues[index].1 >= max_skips);
+        let index = starved.or_else(|| (0..3).rev().find(|&index| !self.queues[index].0.is_empty()))?;
+        for (lower, (queue, skips)) in self.queues.iter_mut().enumerate() {
+            if lower != index && !queue.is_empty() {
+                *skips += 1;
+            }
+        }
+        self.queues[index].1 = 0;
+        self.queues[index].0.pop_front()
+    }
+}
+
+type Scheduler = Arc<Mutex<Schedule>>;
+
+// A slot for one upstream request, handed to the next waiter when dropped. A waiter
+// cancelled after being granted one drops it unused, which passes it on.
+struct Permit {
+    scheduler: Option<(Scheduler, u32)>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let Some((scheduler, max_skips)) = self.scheduler.take() else { return };
+        loop {
+            let waiter = {
+                let mut schedule = scheduler.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                match schedule.next(max_skips) {
+                    Some(waiter) => waiter,
+                    None => {
+                        schedule.in_flight -= 1;
+                        return;
+                    }
+                }
+            };
+            match waiter.send(Permit { scheduler: Some((scheduler.clone(), max_skips)) }) {
+                Ok(()) => return,
+                // The waiter is gone; disarm the permit and try the next one
+                Err(mut permit) => permit.scheduler = None,
+            }
+        }
+    }
+}
+
+struct Prioritized {
+    inner: Arc<dyn Transport>,
+    policy: PriorityLayer,
+    scheduler: Scheduler,
+}
+
+impl Prioritized {
+    async fn acquire(&self, priority: Priority) -> Permit {
+        let permit = || Permit {
+            scheduler: Some((self.scheduler.clone(), self.policy.max_skips)),
+        };
+        let waiting = {
+            let mut schedule = self.scheduler.lock().unwrap();
+            let queued = schedule.queues.iter().any(|(queue, _)| !queue.is_empty());
+            if schedule.in_flight < self.policy.max_concurrent.max(1) && !queued {
+                schedule.in_flight += 1;
+                return permit();
+            }
+            let (sender, receiver) = oneshot::channel();
+            schedule.queues[priority as usize].0.push_back(sender);
+            receiver
+        };
+        // Senders are only dropped after a successful send
+        waiting.await.expect("queued requests are always granted a permit")
+    }
+}
+
+#[async_trait]
+impl Transport for Prioritized {
+    async fn request(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        let _permit = self.acquire(self.policy.priority(method)).await;
+        self.inner.request(method, params).await
+    }
+}
+
+// AIMD concurrency limit: grows by one for each window of healthy responses and is
+// cut by `decrease_factor` on rate limiting or a transient failure, so batch jobs
+// settle at whatever the provider allows. Clones share the same limit.
+#[derive(Debug, Clone)]
+pub struct AdaptiveConcurrencyLayer {
+    pub min_concurrency: usize,
+    pub max_concurrency: usize,
+    pub decrease_factor: f64,
+    state: Arc<Mutex<Adaptive>>,
+    released: Arc<Notify>,
+}
+
+#[derive(Debug)]
+struct Adaptive {
+    limit: f64,
+    in_flight: usize,
+    // Bumped on every decrease, so one burst of failures only counts once
+    epoch: u64,
+}
+
+impl AdaptiveConcurrencyLayer {
+    pub fn new(initial: usize, min_concurrency: usize, max_concurrency: usize) -> Self {
+        let min_concurrency = min_concurrency.max(1);
+        AdaptiveConcurrencyLayer {
+            min_concurrency,
+            max_concurrency: max_concurrency.max(min_concurrency),
+            decrease_factor: 0.5,
+            state: Arc::new(Mutex::new(Adaptive {
+                limit: initial.clamp(min_concurrency, max_concurrency.max(min_concurrency)) as f64,
+                in_flight: 0,
+                epoch: 0,
+            })),
+            released: Arc::new(Notify::new()),
+        }
+    }
+
+    // Requests currently allowed in flight
+    pub fn limit(&self) -> usize {
+        self.state.lock().unwrap().limit as usize
+    }
+
+    async fn acquire(&self) -> u64 {
+        loop {
+            let released = self.released.notified();
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.in_flight < state.limit as usize {
+                    state.in_flight += 1;
+                    return state.epoch;
+                }
+            }
+            released.await;
+        }
+    }
+
+    fn release(&self, epoch: u64, overloaded: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.in_flight -= 1;
+        if overloaded {
+            // Requests started before the last cut saw the old limit; don't cut again for them
+            if epoch == state.epoch {
+                state.limit = (state.limit * self.decrease_factor).max(self.min_concurrency as f64);
+                state.epoch += 1;
+            }
+        } else {
+            state.limit = (state.limit + 1.0 / state.limit).min(self.max_concurrency as f64);
+        }
+        drop(state);
+        self.released.notify_waiters();
+    }
+}
+
+impl Default for AdaptiveConcurrencyLayer {
+    fn default() -> Self {
+        Self::new(4, 1, 64)
+    }
+}
+
+impl Layer for AdaptiveConcurrencyLayer {
+    fn layer(&self, inner: Arc<dyn Transport>) -> Arc<dyn Transport> {
+        Arc::new(Adapted {
+            inner,
+            policy: self.clone(),
+        })
+    }
+}
+
+struct Adapted {
+    inner: Arc<dyn Transport>,
+    policy: AdaptiveConcurrencyLayer,
+}
+
+// Releases the slot even when the request is cancelled, without judging the provider
+struct Slot<'a> {
+    policy: &'a AdaptiveConcurrencyLayer,
+    epoch: u64,
+    overloaded: Option<bool>,
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        match self.overloaded {
+            Some(overloaded) => self.policy.release(self.epoch, overloaded),
+            None => {
+                self.policy.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).in_flight -= 1;
+                self.policy.released.notify_waiters();
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for Adapted {
+    async fn request(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        let mut slot = Slot {
+            policy: &self.policy,
+            epoch: self.policy.acquire().await,
+            overloaded: None,
+        };
+        let result = self.inner.request(method, params).await;
+        slot.overloaded = Some(match &result {
+            Ok(response) => is_rate_limited(response),
+            Err(error) => is_transient(error.as_ref()),
+        });
+        result
+    }
+}
+
+pub fn is_rate_limited(response: &serde_json::Value) -> bool {
+    response["error"]["code"].as_i64().is_some_and(|code| RATE_LIMIT_ERROR_CODES.contains(&code))
+}
+
+// Whether retrying may succeed: connection failures, timeouts, HTTP 429/5xx, dropped
+// WebSockets and rate limiting, from any transport. Wrapped errors are unwrapped through
+// their source chain.
+pub fn is_transient(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(error);
+    while let Some(error) = current {
+        if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+            let retryable_status = error.status().is_some_and(|status| status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error());
+            return retryable_status || error.is_timeout() || error.is_connect() || error.is_request();
+        }
+        if let Some(error) = error.downcast_ref::<TransportError>() {
+            return match error {
+                TransportError::Exhausted(last) => is_transient(last.as_ref()),
+                _ => true,
+            };
+        }
+        if let Some(error) = error.downcast_ref::<tokio_tungstenite::tungstenite::Error>() {
+            use tokio_tungstenite::tungstenite::Error as WsError;
+            return matches!(error, WsError::ConnectionClosed | WsError::AlreadyClosed | WsError::Io(_));
+        }
+        if let Some(error) = error.downcast_ref::<crate::error::Error>() {
+            return error.is_transient();
+        }
+        if error.downcast_ref::<std::io::Error>().is_some() {
+            return true;
+        }
+        current = error.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::client::{ClientBuilder, EthClient};
+    use crate::testing::{Failure, MockNode};
+
+    #[tokio::test]
+    async fn sends_urgent_methods_first_without_starving_bulk_ones() {
+        // Each request takes a few milliseconds at the node
+        let node = MockNode::new(|_, _| Ok(serde_json::json!("0x1"))).with_latency(Duration::from_millis(20));
+        let layer = PriorityLayer {
+            max_concurrent: 1,
+            max_skips: 2,
+            ..PriorityLayer::default()
+        };
+        let client = ClientBuilder::with_transport(node.clone()).layer(layer).build();
+        let send = |client: &EthClient, method: &'static str, param: u64| {
+            let client = client.clone();
+            tokio::spawn(async move { client.send(method, vec![param.into()]).await })
+        };
+
+        let mut requests = vec![send(&client, "eth_getLogs", 0)];
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        for (method, param) in [("eth_getLogs", 1), ("eth_sendRawTransaction", 2), ("eth_getTransactionCount", 3), ("eth_sendRawTransaction", 4), ("eth_sendRawTransaction", 5)] {
+            requests.push(send(&client, method, param));
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        for request in requests {
+            request.await.unwrap().unwrap();
+        }
+        let order: Vec<String> = node.calls().into_iter().map(|(method, params)| format!("{}{}", method, params[0])).collect();
+        assert_eq!(order, vec!["eth_getLogs0", "eth_sendRawTransaction2", "eth_getTransactionCount3", "eth_getLogs1", "eth_sendRawTransaction4", "eth_sendRawTransaction5"]);
+    }
+
+    // Rate limits any request beyond `capacity` in flight, tracking the peak it saw
+    #[derive(Clone)]
+    struct Provider {
+        capacity: usize,
+        in_flight: Arc<Mutex<(usize, usize)>>,
+    }
+
+    #[async_trait]
+    impl Transport for Provider {
+        async fn request(&self, _method: &str, _params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+            let admitted = {
+                let mut in_flight = self.in_flight.lock().unwrap();
+                in_flight.0 += 1;
+                in_flight.1 = in_flight.1.max(in_flight.0);
+                in_flight.0 <= self.capacity
+            };
+            tokio::time::sleep(Duration::from_millis(2)).await;
+            self.in_flight.lock().unwrap().0 -= 1;
+            Ok(match admitted {
+                true => serde_json::json!({"jsonrpc": "2.0", "id": 1, "result": "0x1"}),
+                false => serde_json::json!({"jsonrpc": "2.0", "id": 1, "error": {"code": 429, "message": "too many requests"}}),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrency_backs_off_on_rate_limits_and_grows_when_healthy() {
+        let provider = Provider {
+            capacity: 4,
+            in_flight: Arc::default(),
+        };
+        let layer = AdaptiveConcurrencyLayer::new(32, 1, 32);
+        let client = ClientBuilder::with_transport(provider.clone()).layer(layer.clone()).build();
+
+        let burst = (0..64).map(|_| client.send_raw("eth_blockNumber", Vec::new()));
+        futures_util::future::join_all(burst).await;
+        let backed_off = layer.limit();
+        assert!(backed_off < 32, "limit stayed at {}", backed_off);
+
+        // Sequential healthy requests grow the limit back by about one per window
+        for _ in 0..64 {
+            client.send("eth_blockNumber", Vec::new()).await.unwrap();
+        }
+        assert!(layer.limit() > backed_off);
+        assert!(provider.in_flight.lock().unwrap().1 <= 32);
+    }
+
+    #[test]
+    fn caches_by_how_final_the_block_is() {
+        let cache = CacheLayer {
+            latest_call_ttl: Some(Duration::from_secs(60)),
+            ..CacheLayer::default()
+        };
+        let (long, short) = (Some(cache.ttl), Some(cache.recent_ttl));
+        let head = Some(1000);
+        let ttl = |method: &str, params: serde_json::Value, head: Option<u64>| cache.ttl_for(method, params.as_array().unwrap(), head);
+
+        assert_eq!(ttl("eth_chainId", json!([]), None), long);
+        assert_eq!(ttl("eth_getBlockByHash", json!(["0xabc", false]), None), long);
+        // 64 blocks behind the head is final, 63 is not
+        assert_eq!(ttl("eth_getBlockByNumber", json!(["0x3a8", false]), head), long);
+        assert_eq!(ttl("eth_getBlockByNumber", json!(["0x3a9", false]), head), short);
+        assert_eq!(ttl("eth_getBalance", json!(["0x1", "0x10"]), None), short);
+        assert_eq!(ttl("eth_getStorageAt", json!(["0x1", "0x0", {"blockNumber": "0x10"}]), head), long);
+        assert_eq!(ttl("eth_call", json!([{}, {"blockHash": "0xabc"}]), None), long);
+        assert_eq!(ttl("eth_call", json!([{}, "latest"]), head), Some(Duration::from_secs(60)));
+        assert_eq!(ttl("eth_call", json!([{}]), head), Some(Duration::from_secs(60)));
+        assert_eq!(ttl("eth_getBalance", json!(["0x1", "finalized"]), head), None);
+        assert_eq!(ttl("eth_getTransactionReceipt", json!(["0xabc"]), head), short);
+        assert_eq!(ttl("eth_blockNumber", json!([]), head), None);
+
+        assert!(CacheLayer::needs_head("eth_getCode", &[json!("0x1"), json!({"blockNumber": "0x10"})]));
+        assert!(!CacheLayer::needs_head("eth_getCode", &[json!("0x1"), json!({"blockHash": "0xabc"})]));
+        assert!(!CacheLayer::needs_head("eth_getCode", &[json!("0x1"), json!("latest")]));
+    }
+
+    #[tokio::test]
+    async fn keeps_final_blocks_and_refetches_recent_ones() {
+        let node = MockNode::new(|method, params| match method {
+            "eth_blockNumber" => Ok(json!("0x100")),
+            "eth_getBalance" => Ok(params[1].clone()),
+            "eth_getTransactionReceipt" => Ok(json!({"blockHash": "0xabc", "blockNumber": params[0]})),
+            _ => Err(Failure::unexpected(method)),
+        });
+        let cache = CacheLayer {
+            recent_ttl: Duration::ZERO,
+            ..CacheLayer::default()
+        }
+        .layer(Arc::new(node.clone()));
+        for block in ["0x10", "0x10", "0xff", "0xff"] {
+            let response = cache.request("eth_getBalance", vec![json!("0x1"), json!(block)]).await.unwrap();
+            assert_eq!(response["result"], block);
+        }
+        let blocks: Vec<serde_json::Value> = node.params_of("eth_getBalance").into_iter().map(|params| params[1].clone()).collect();
+        assert_eq!(blocks, ["0x10", "0xff", "0xff"]);
+
+        // Receipts go by the block they were mined in
+        for block in ["0x10", "0x10", "0xff", "0xff"] {
+            cache.request("eth_getTransactionReceipt", vec![json!(block)]).await.unwrap();
+        }
+        assert_eq!(node.params_of("eth_getTransactionReceipt").len(), 3);
+    }
+
+    #[tokio::test]
+    async fn retries_only_transient_failures() {
+        // Rate limited twice, then answers
+        let limited = AtomicU64::new(0);
+        let node = MockNode::new(move |method, _| match method {
+            "eth_blockNumber" if limited.fetch_add(1, Ordering::SeqCst) < 2 => Err(Failure::rpc(429, "too many requests")),
+            "eth_blockNumber" => Ok(json!("0x1")),
+            "eth_sendRawTransaction" => Err(Failure::rpc(-32005, "limit exceeded")),
+            _ => Err(Failure::unexpected(method)),
+        });
+        let retry = RetryLayer {
+            initial_backoff: Duration::from_millis(1),
+            ..RetryLayer::default()
+        };
+        let transport = retry.layer(Arc::new(node.clone()));
+        assert_eq!(transport.request("eth_blockNumber", Vec::new()).await.unwrap()["result"], "0x1");
+        assert_eq!(node.params_of("eth_blockNumber").len(), 3);
+
+        // Sends are never resent, and an error that isn't transient is returned at once
+        assert_eq!(transport.request("eth_sendRawTransaction", vec![json!("0x00")]).await.unwrap()["error"]["code"], -32005);
+        assert_eq!(node.params_of("eth_sendRawTransaction").len(), 1);
+        assert!(transport.request("eth_gasPrice", Vec::new()).await.is_err());
+        assert_eq!(node.params_of("eth_gasPrice").len(), 1);
+
+        // Still rate limited after the last attempt: the response is handed back
+        let limited = MockNode::new(|_, _| Err(Failure::rpc(429, "too many requests")));
+        let transport = RetryLayer { max_attempts: 2, ..retry }.layer(Arc::new(limited.clone()));
+        assert_eq!(transport.request("eth_chainId", Vec::new()).await.unwrap()["error"]["code"], 429);
+        assert_eq!(limited.calls().len(), 2);
+    }
+
+    #[test]
+    fn classifies_transient_errors() {
+        let closed: Box<dyn std::error::Error + Send + Sync> = Box::new(TransportError::Closed);
+        assert!(is_transient(closed.as_ref()));
+        assert!(is_transient(&std::io::Error::from(std::io::ErrorKind::ConnectionReset)));
+        assert!(is_transient(&SharedError(Arc::new(TransportError::Closed))));
+        assert!(is_transient(&crate::error::Error::Timeout("eth_call".to_string())));
+        assert!(!is_transient(&crate::error::Error::Decode("bad hex".to_string())));
+        assert!(!is_transient(&TransportError::Exhausted("bad request".into())));
+        assert!(is_transient(&TransportError::Exhausted(Box::new(TransportError::Closed))));
+        let plain: Box<dyn std::error::Error + Send + Sync> = "bad request".into();
+        assert!(!is_transient(plain.as_ref()));
+
+        assert!(is_rate_limited(&json!({"error": {"code": -32005, "message": "limit exceeded"}})));
+        assert!(!is_rate_limited(&json!({"error": {"code": 3, "message": "execution reverted"}})));
+        assert!(!is_rate_limited(&json!({"result": "0x1"})));
+    }
+
+    #[test]
+    fn paces_requests_past_the_burst() {
+        let limit = |requests_per_second: f64| RateLimit {
+            inner: Arc::new(MockNode::new(|_, _| Ok(json!(null)))),
+            policy: RateLimitLayer::new(requests_per_second, 2),
+            bucket: Mutex::new((2.0, Instant::now())),
+        };
+        let limit_10 = limit(10.0);
+        assert_eq!(limit_10.reserve(), Duration::ZERO);
+        assert_eq!(limit_10.reserve(), Duration::ZERO);
+        // Each reservation past the burst waits another tenth of a second
+        let third = limit_10.reserve();
+        assert!(third > Duration::from_millis(90) && third <= Duration::from_millis(100), "{:?}", third);
+        let fourth = limit_10.reserve();
+        assert!(fourth > Duration::from_millis(190) && fourth <= Duration::from_millis(200), "{:?}", fourth);
+
+        let unlimited = limit(0.0);
+        assert!((0..10).all(|_| unlimited.reserve().is_zero()));
+    }
+
+    #[tokio::test]
+    async fn coalesces_identical_requests_in_flight() {
+        let node = MockNode::new(|_, _| Ok(json!("0x1"))).with_latency(Duration::from_millis(20));
+        let transport = DedupLayer::default().layer(Arc::new(node.clone()));
+        let transport = &transport;
+        let requests = |method: &'static str, param: &'static str| (0..3).map(move |_| transport.request(method, vec![json!(param)]));
+
+        let responses = futures_util::future::join_all(requests("eth_getBalance", "0xa").chain(requests("eth_getBalance", "0xb"))).await;
+        assert!(responses.iter().all(|response| response.as_ref().unwrap()["result"] == "0x1"));
+        assert_eq!(node.params_of("eth_getBalance"), [[json!("0xa")], [json!("0xb")]]);
+
+        // Sends reach the node once per caller, and nothing is shared after completion
+        futures_util::future::join_all(requests("eth_sendRawTransaction", "0x00")).await;
+        assert_eq!(node.params_of("eth_sendRawTransaction").len(), 3);
+        transport.request("eth_getBalance", vec![json!("0xa")]).await.unwrap();
+        assert_eq!(node.params_of("eth_getBalance").len(), 3);
+    }
+}
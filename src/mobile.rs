@@ -0,0 +1,236 @@
+// UniFFI bindings for wallet apps on iOS and Android. Kotlin and Swift are generated
+// from the built library, e.g.
+//   cargo build --release --features mobile
+//   uniffi-bindgen generate --library target/release/libevm_json_rpc.so --language kotlin --out-dir out
+// with uniffi-bindgen 0.28. Network calls block on a shared runtime, so apps make
+// them off the main thread. Amounts cross as decimal strings in wei.
+
+use std::sync::Arc;
+
+use ethabi::ethereum_types::U256;
+use zeroize::Zeroizing;
+
+use crate::blocks::BlockTag;
+use crate::client::EthClient;
+use crate::error::Error;
+use crate::ffi::runtime;
+use crate::gas::FeeHistory;
+use crate::key::SecretKey;
+use crate::transaction::TransactionRequest;
+use crate::wallet::Wallet;
+
+// Blocks sampled for fee suggestions, and the tip percentiles of the three speeds
+pub const FEE_HISTORY_BLOCKS: u64 = 10;
+pub const FEE_PERCENTILES: [f64; 3] = [10.0, 50.0, 90.0];
+
+// Flattened to its message on the foreign side, with the variant as the exception type
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum MobileError {
+    #[error("{0}")]
+    InvalidInput(String),
+    // The node or network failed or refused the request; may succeed on retry
+    #[error("{0}")]
+    Network(String),
+    #[error("{0}")]
+    Signing(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<Error> for MobileError {
+    fn from(error: Error) -> Self {
+        let message = error.to_string();
+        match error {
+            Error::InvalidInput(_) | Error::Abi(_) => MobileError::InvalidInput(message),
+            Error::Transport(_) | Error::Rpc(_) | Error::Timeout(_) | Error::ChainMismatch { .. } => MobileError::Network(message),
+            Error::Signature(_) => MobileError::Signing(message),
+            _ => MobileError::Other(message),
+        }
+    }
+}
+
+type MobileResult<T> = std::result::Result<T, MobileError>;
+
+fn parse_wei(value: &str) -> MobileResult<U256> {
+    U256::from_dec_str(value).map_err(|_| MobileError::InvalidInput(format!("Invalid wei amount {}", value)))
+}
+
+// A transaction as the app describes it; whatever is unset is filled in when sending
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct TransactionDraft {
+    // None deploys a contract
+    pub to: Option<String>,
+    pub value_wei: String,
+    // 0x-prefixed calldata
+    pub data: Option<String>,
+    pub gas_limit: Option<u64>,
+}
+
+impl TransactionDraft {
+    fn to_request(&self, fees: Option<&FeeLevel>) -> MobileResult<TransactionRequest> {
+        let mut request = TransactionRequest {
+            to: self.to.clone(),
+            value: Some(parse_wei(&self.value_wei)?),
+            data: self.data.clone(),
+            gas: self.gas_limit.map(Into::into),
+            ..Default::default()
+        };
+        if let Some(fees) = fees {
+            request.max_fee_per_gas = Some(parse_wei(&fees.max_fee_per_gas_wei)?);
+            request.max_priority_fee_per_gas = Some(parse_wei(&fees.max_priority_fee_per_gas_wei)?);
+        }
+        Ok(request)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FeeLevel {
+    pub max_fee_per_gas_wei: String,
+    pub max_priority_fee_per_gas_wei: String,
+}
+
+// EIP-1559 fees for a slow, normal and fast transaction, from the tips recently paid
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FeeEstimate {
+    pub base_fee_wei: String,
+    pub slow: FeeLevel,
+    pub normal: FeeLevel,
+    pub fast: FeeLevel,
+}
+
+impl FeeEstimate {
+    // Each speed tips the average of its percentile over the sampled blocks, and caps
+    // the fee with room for the base fee to double, like Wallet::prepare
+    pub fn from_history(history: &FeeHistory) -> MobileResult<Self> {
+        let base_fee = history.next_base_fee().ok_or_else(|| MobileError::Network("The chain reports no base fee; it may not support EIP-1559".to_string()))?;
+        let level = |column: usize| {
+            let tips: Vec<U256> = history.reward.iter().filter_map(|row| row.get(column).copied()).collect();
+            let tip = if tips.is_empty() { U256::zero() } else { tips.iter().fold(U256::zero(), |sum, tip| sum.saturating_add(*tip)) / tips.len() };
+            FeeLevel {
+                max_fee_per_gas_wei: (base_fee.saturating_mul(2.into()).saturating_add(tip)).to_string(),
+                max_priority_fee_per_gas_wei: tip.to_string(),
+            }
+        };
+        Ok(FeeEstimate {
+            base_fee_wei: base_fee.to_string(),
+            slow: level(0),
+            normal: level(1),
+            fast: level(2),
+        })
+    }
+}
+
+// A secp256k1 key held in memory; apps keep the hex in the platform keystore
+#[derive(uniffi::Object)]
+pub struct Key {
+    key: SecretKey,
+}
+
+#[uniffi::export]
+impl Key {
+    #[uniffi::constructor]
+    pub fn generate() -> Arc<Self> {
+        Arc::new(Key { key: SecretKey::random() })
+    }
+
+    #[uniffi::constructor]
+    pub fn from_hex(hex: String) -> MobileResult<Arc<Self>> {
+        let hex = Zeroizing::new(hex);
+        Ok(Arc::new(Key { key: SecretKey::from_hex(&hex)? }))
+    }
+
+    // For saving to the keystore; the app is responsible for the copy it gets
+    pub fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(*self.key.expose_bytes()))
+    }
+
+    pub fn address(&self) -> String {
+        self.key.address()
+    }
+
+    // EIP-191 personal_sign, as 65 bytes r || s || v
+    pub fn sign_message(&self, message: Vec<u8>) -> MobileResult<Vec<u8>> {
+        Ok(self.key.sign_message(&message)?.to_vec())
+    }
+}
+
+// A key connected to an RPC endpoint
+#[derive(uniffi::Object)]
+pub struct MobileWallet {
+    wallet: Wallet,
+}
+
+#[uniffi::export]
+impl MobileWallet {
+    #[uniffi::constructor]
+    pub fn new(rpc_url: String, key: Arc<Key>) -> Arc<Self> {
+        Arc::new(MobileWallet {
+            wallet: Wallet::new(&EthClient::new(&rpc_url), key.key.clone()),
+        })
+    }
+
+    pub fn address(&self) -> String {
+        self.wallet.address()
+    }
+
+    pub fn chain_id(&self) -> MobileResult<u64> {
+        Ok(runtime().block_on(self.client().chain_id())?)
+    }
+
+    pub fn balance_wei(&self) -> MobileResult<String> {
+        Ok(runtime().block_on(self.client().get_balance(&self.wallet.address(), BlockTag::Latest))?.to_string())
+    }
+
+    pub fn estimate_fees(&self) -> MobileResult<FeeEstimate> {
+        let history = runtime().block_on(self.client().fee_history(FEE_HISTORY_BLOCKS, BlockTag::Latest, &FEE_PERCENTILES))?;
+        FeeEstimate::from_history(&history)
+    }
+
+    pub fn estimate_gas(&self, draft: TransactionDraft) -> MobileResult<u64> {
+        let request = draft.to_request(None)?.with_from(&self.wallet.address());
+        Ok(runtime().block_on(self.client().estimate_gas(&request, BlockTag::Pending))?)
+    }
+
+    // Signs and broadcasts, returning the transaction hash. Without `fees` the node's
+    // suggestion is used.
+    pub fn send(&self, draft: TransactionDraft, fees: Option<FeeLevel>) -> MobileResult<String> {
+        let request = draft.to_request(fees.as_ref())?;
+        Ok(format!("{:?}", runtime().block_on(self.wallet.send(&request))?))
+    }
+}
+
+impl MobileWallet {
+    fn client(&self) -> &EthClient {
+        self.wallet.client()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_fees_from_recent_tips() {
+        let history: FeeHistory = serde_json::from_value(serde_json::json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": ["0x64", "0x64", "0x64"],
+            "gasUsedRatio": [0.5, 0.5],
+            "reward": [["0x1", "0x2", "0x6"], ["0x3", "0x4", "0xa"]],
+        }))
+        .unwrap();
+        let estimate = FeeEstimate::from_history(&history).unwrap();
+        assert_eq!(estimate.base_fee_wei, "100");
+        assert_eq!(estimate.slow.max_priority_fee_per_gas_wei, "2");
+        assert_eq!(estimate.normal.max_fee_per_gas_wei, "203");
+        assert_eq!(estimate.fast.max_priority_fee_per_gas_wei, "8");
+
+        let draft = TransactionDraft {
+            to: None,
+            value_wei: "1.5".to_string(),
+            data: None,
+            gas_limit: None,
+        };
+        assert!(matches!(draft.to_request(None), Err(MobileError::InvalidInput(_))));
+    }
+}
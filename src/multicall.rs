@@ -0,0 +1,131 @@
+use ethabi::{ParamType, Token};
+
+use crate::abi::{decode_result, encode_call};
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+
+// Multicall3, deployed at the same address on nearly every EVM chain
+pub const MULTICALL3: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
+// Calls per eth_call, keeping each request well under node gas and size limits
+pub const MAX_BATCH_SIZE: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    pub target: String,
+    // 0x-prefixed calldata
+    pub data: String,
+}
+
+impl Call {
+    pub fn new(target: &str, data: &str) -> Self {
+        Call {
+            target: target.to_string(),
+            data: data.to_string(),
+        }
+    }
+}
+
+// Runs the calls through Multicall3.aggregate3 with failures allowed, returning each
+// call's return data as 0x hex, or None where it reverted. Large lists are split
+// into MAX_BATCH_SIZE chunks.
+pub async fn aggregate(client: &EthClient, calls: &[Call]) -> Result<Vec<Option<String>>> {
+    let mut results = Vec::with_capacity(calls.len());
+    for batch in calls.chunks(MAX_BATCH_SIZE) {
+        results.extend(aggregate_batch(client, batch).await?);
+    }
+    Ok(results)
+}
+
+async fn aggregate_batch(client: &EthClient, calls: &[Call]) -> Result<Vec<Option<String>>> {
+    let mut encoded = Vec::with_capacity(calls.len());
+    for call in calls {
+        let target = call.target.trim_start_matches("0x").parse().map_err(|_| Error::InvalidInput(format!("Invalid call target {}", call.target)))?;
+        let data = hex::decode(call.data.trim_start_matches("0x")).map_err(|error| Error::InvalidInput(format!("Invalid calldata: {}", error)))?;
+        encoded.push(Token::Tuple(vec![Token::Address(target), Token::Bool(true), Token::Bytes(data)]));
+    }
+    let data = encode_call("aggregate3((address,bool,bytes)[])", &[Token::Array(encoded)]);
+    let result = client.call(MULTICALL3, &data).await?;
+
+    let output = ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Bool, ParamType::Bytes])));
+    let Some(Token::Array(results)) = decode_result(&result, &[output])?.pop() else {
+        return Err(Error::Decode("Invalid aggregate3 response".to_string()));
+    };
+    if results.len() != calls.len() {
+        return Err(Error::Decode(format!("aggregate3 returned {} results for {} calls", results.len(), calls.len())));
+    }
+    results
+        .into_iter()
+        .map(|result| match result {
+            Token::Tuple(fields) => match fields.as_slice() {
+                [Token::Bool(true), Token::Bytes(data)] => Ok(Some(format!("0x{}", hex::encode(data)))),
+                [Token::Bool(false), _] => Ok(None),
+                _ => Err(Error::Decode("Invalid aggregate3 result".to_string())),
+            },
+            _ => Err(Error::Decode("Invalid aggregate3 result".to_string())),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::testing::{Failure, MockNode, Reply};
+
+    // Answers aggregate3 from a table of (target, calldata) -> return data; anything
+    // missing reverts. Other methods are looked up as (method, first param).
+    pub(crate) struct Multicall(pub HashMap<(String, String), String>);
+
+    impl Multicall {
+        pub(crate) fn answer(&self, target: &str, data: &str) -> Option<&String> {
+            self.0.get(&(target.to_lowercase(), data.to_lowercase()))
+        }
+
+        pub(crate) fn client(self) -> EthClient {
+            MockNode::new(move |method, params| self.reply(method, params)).client()
+        }
+
+        pub(crate) fn reply(&self, method: &str, params: &[serde_json::Value]) -> Reply {
+            if method != "eth_call" {
+                let key = (method.to_string(), params[0].as_str().unwrap_or_default().to_lowercase());
+                return Ok(serde_json::json!(self.0.get(&key).cloned().unwrap_or_else(|| "0x".to_string())));
+            }
+            let target = params[0]["to"].as_str().unwrap();
+            let data = params[0]["data"].as_str().unwrap();
+            if !target.eq_ignore_ascii_case(MULTICALL3) {
+                return self.answer(target, data).map(|result| serde_json::json!(result)).ok_or_else(Failure::reverted);
+            }
+
+            let input = ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Address, ParamType::Bool, ParamType::Bytes])));
+            let calldata = hex::decode(&data[10..]).unwrap();
+            let Token::Array(calls) = ethabi::decode(&[input], &calldata).unwrap().pop().unwrap() else { unreachable!() };
+            let results: Vec<Token> = calls
+                .into_iter()
+                .map(|call| {
+                    let Token::Tuple(fields) = call else { unreachable!() };
+                    let (Token::Address(target), Token::Bytes(data)) = (&fields[0], &fields[2]) else { unreachable!() };
+                    match self.answer(&format!("{:?}", target), &format!("0x{}", hex::encode(data))) {
+                        Some(result) => Token::Tuple(vec![Token::Bool(true), Token::Bytes(hex::decode(&result[2..]).unwrap())]),
+                        None => Token::Tuple(vec![Token::Bool(false), Token::Bytes(Vec::new())]),
+                    }
+                })
+                .collect();
+            Ok(serde_json::json!(format!("0x{}", hex::encode(ethabi::encode(&[Token::Array(results)])))))
+        }
+    }
+
+    #[tokio::test]
+    async fn aggregates_calls_and_reports_failures() {
+        let token = "0x1c7d4b196cb0c7b01d743fbc6116a902379c7238";
+        let decimals = encode_call("decimals()", &[]);
+        let mut answers = HashMap::new();
+        answers.insert((token.to_string(), decimals.clone()), format!("0x{:064x}", 6));
+        let client = Multicall(answers).client();
+
+        let calls = vec![Call::new(token, &decimals), Call::new(token, &encode_call("symbol()", &[]))];
+        let results = aggregate(&client, &calls).await.unwrap();
+        assert_eq!(results, vec![Some(format!("0x{:064x}", 6)), None]);
+        assert!(aggregate(&client, &[]).await.unwrap().is_empty());
+    }
+}
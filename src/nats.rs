@@ -0,0 +1,41 @@
+use async_trait::async_trait;
+
+use crate::bus::Encoding;
+use crate::error::{Error, Result};
+use crate::sink::Sink;
+
+// Publishes each record as a NATS message on one subject. Core NATS has no
+// acknowledgements; flush only waits until the server has received every message.
+pub struct NatsSink {
+    client: async_nats::Client,
+    subject: String,
+    encoding: Encoding,
+}
+
+impl NatsSink {
+    // `url` is a server URL, e.g. nats://localhost:4222, with credentials if any
+    pub async fn connect(url: &str, subject: &str, encoding: Encoding) -> Result<Self> {
+        let client = async_nats::connect(url).await.map_err(|error| Error::Config(format!("Failed to connect to NATS: {}", error)))?;
+        Ok(Self::new(client, subject, encoding))
+    }
+
+    pub fn new(client: async_nats::Client, subject: &str, encoding: Encoding) -> Self {
+        NatsSink {
+            client,
+            subject: subject.to_string(),
+            encoding,
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for NatsSink {
+    async fn write(&mut self, record: &serde_json::Value) -> Result<()> {
+        let payload = self.encoding.encode(record)?;
+        self.client.publish(self.subject.clone(), payload.into()).await.map_err(|error| Error::Transport(Box::new(error)))
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.client.flush().await.map_err(|error| Error::Transport(Box::new(error)))
+    }
+}
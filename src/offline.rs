@@ -0,0 +1,161 @@
+use ethabi::ethereum_types::H256;
+use serde::{Deserialize, Serialize};
+
+use crate::blocks::BlockTag;
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::key::SecretKey;
+use crate::transaction::{TransactionRequest, TypedTransaction};
+use crate::wallet::fill_fees;
+
+pub const PAYLOAD_VERSION: u32 = 1;
+
+// A transaction carried between an online machine and an airgapped signer, as compact
+// JSON that fits in a QR code for most calls. The online side prepares it unsigned,
+// the signer signs it without a network connection and the online side broadcasts
+// the result. Only the RLP is trusted: the signer shows what it decodes to, not any
+// description that came with it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfflineTransaction {
+    pub version: u32,
+    // Whose nonce the transaction was prepared with, so only that key may sign it
+    pub from: String,
+    pub signed: bool,
+    // 0x RLP, with an empty signature until signed
+    pub transaction: String,
+}
+
+impl OfflineTransaction {
+    pub fn unsigned(from: &str, transaction: &TypedTransaction) -> Self {
+        OfflineTransaction {
+            version: PAYLOAD_VERSION,
+            from: from.to_lowercase(),
+            signed: false,
+            transaction: transaction.to_hex(),
+        }
+    }
+
+    // Fills the request for `from` as Wallet::prepare would, taking the nonce from the
+    // node's pending count. Nothing is reserved, so prepare one transaction per nonce
+    // or set the nonces explicitly.
+    pub async fn prepare(client: &EthClient, from: &str, request: &TransactionRequest) -> Result<Self> {
+        let request = TransactionRequest {
+            from: Some(from.to_string()),
+            ..request.clone()
+        };
+        let mut request = fill_fees(client, request).await?;
+        if request.nonce.is_none() {
+            request.nonce = Some(client.get_transaction_count(from, BlockTag::Pending).await?.into());
+        }
+        Ok(OfflineTransaction::unsigned(from, &TypedTransaction::from_request(&request)?))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("OfflineTransaction serializes")
+    }
+
+    // The JSON payload, or a bare raw signed transaction as other tools export them
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        if text.starts_with("0x") {
+            let transaction = TypedTransaction::from_hex(text)?;
+            return Ok(OfflineTransaction {
+                version: PAYLOAD_VERSION,
+                from: transaction.recover_sender()?.to_lowercase(),
+                signed: true,
+                transaction: transaction.to_hex(),
+            });
+        }
+        let payload: OfflineTransaction = serde_json::from_str(text).map_err(|error| Error::Decode(format!("Invalid offline transaction: {}", error)))?;
+        if payload.version != PAYLOAD_VERSION {
+            return Err(Error::Unsupported(format!("Offline transaction version {}, expected {}", payload.version, PAYLOAD_VERSION)));
+        }
+        Ok(payload)
+    }
+
+    pub fn decode(&self) -> Result<TypedTransaction> {
+        TypedTransaction::from_hex(&self.transaction)
+    }
+
+    // Signs on the offline machine. Refuses a key other than the one the transaction
+    // was prepared for, whose nonce would be wrong.
+    pub fn sign(&self, key: &SecretKey) -> Result<Self> {
+        if self.signed {
+            return Err(Error::InvalidInput("Transaction is already signed".to_string()));
+        }
+        if !key.address().eq_ignore_ascii_case(&self.from) {
+            return Err(Error::InvalidInput(format!("Transaction was prepared for {}, not this key's {}", self.from, key.address())));
+        }
+        let signed = self.decode()?.sign(key)?;
+        Ok(OfflineTransaction {
+            signed: true,
+            transaction: signed.to_hex(),
+            ..self.clone()
+        })
+    }
+
+    // The signed transaction, checked to be signed by `from`
+    pub fn signed_transaction(&self) -> Result<TypedTransaction> {
+        if !self.signed {
+            return Err(Error::InvalidInput("Transaction is not signed yet".to_string()));
+        }
+        let transaction = self.decode()?;
+        let signer = transaction.recover_sender()?;
+        if !signer.eq_ignore_ascii_case(&self.from) {
+            return Err(Error::Signature(format!("Transaction is signed by {}, not {}", signer, self.from)));
+        }
+        Ok(transaction)
+    }
+
+    // Sends the signed transaction, after checking it is for the endpoint's chain
+    pub async fn broadcast(&self, client: &EthClient) -> Result<H256> {
+        let transaction = self.signed_transaction()?;
+        let actual = client.chain_id().await?;
+        if let Some(expected) = transaction.chain_id() {
+            if expected != actual {
+                return Err(Error::ChainMismatch { expected, actual });
+            }
+        }
+        client.send_raw_transaction(&transaction).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethabi::ethereum_types::U256;
+
+    use super::*;
+    use crate::wallet::tests::{node, sent};
+
+    #[tokio::test]
+    async fn prepares_signs_offline_and_broadcasts() {
+        let node = node();
+        let client = node.client();
+        let key = SecretKey::from_hex(&"46".repeat(32)).unwrap();
+        let request = TransactionRequest {
+            to: Some("0x3535353535353535353535353535353535353535".to_string()),
+            ..Default::default()
+        }
+        .with_value(U256::exp10(18));
+
+        let unsigned = OfflineTransaction::prepare(&client, &key.address(), &request).await.unwrap();
+        let unsigned = OfflineTransaction::parse(&unsigned.to_json()).unwrap();
+        assert!(unsigned.signed_transaction().is_err());
+        let TypedTransaction::Eip1559(transaction) = unsigned.decode().unwrap() else { panic!("expected EIP-1559") };
+        assert_eq!((transaction.chain_id, transaction.nonce, transaction.gas_limit), (1, 7, 21_000));
+
+        let other = SecretKey::from_hex(&"47".repeat(32)).unwrap();
+        assert!(matches!(unsigned.sign(&other), Err(Error::InvalidInput(_))));
+        let signed = unsigned.sign(&key).unwrap();
+        assert!(signed.sign(&key).is_err());
+
+        let hash = signed.broadcast(&client).await.unwrap();
+        assert_eq!(sent(&node), [signed.signed_transaction().unwrap()]);
+        assert_eq!(OfflineTransaction::parse(&signed.transaction).unwrap(), signed);
+        assert_eq!(signed.signed_transaction().unwrap().hash(), hash);
+
+        let forged = OfflineTransaction { from: other.address(), ..signed };
+        assert!(matches!(forged.signed_transaction(), Err(Error::Signature(_))));
+    }
+}
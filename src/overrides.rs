@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+
+use ethabi::ethereum_types::{H160, H256, U256, U64};
+use serde::Serialize;
+
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::transaction::TransactionRequest;
+
+// Mapping slots tried by the balance slot finder
+pub const MAX_BALANCE_SLOT: u64 = 100;
+
+// Per-account state override in the format eth_call and eth_simulateV1 accept.
+// `state` replaces the whole storage, `state_diff` only the given slots.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U64>,
+    // 0x-prefixed runtime bytecode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<BTreeMap<H256, H256>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_diff: Option<BTreeMap<H256, H256>>,
+}
+
+// Builds the state override object. Serializes to {address: AccountOverride}.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Overrides {
+    accounts: BTreeMap<H160, AccountOverride>,
+}
+
+impl Overrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn accounts(&self) -> &BTreeMap<H160, AccountOverride> {
+        &self.accounts
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    // Sets the balance, in wei
+    pub fn fund(mut self, address: &str, wei: U256) -> Result<Self> {
+        self.account(address)?.balance = Some(wei);
+        Ok(self)
+    }
+
+    pub fn set_nonce(mut self, address: &str, nonce: u64) -> Result<Self> {
+        self.account(address)?.nonce = Some(nonce.into());
+        Ok(self)
+    }
+
+    pub fn set_code(mut self, address: &str, bytecode: &str) -> Result<Self> {
+        hex::decode(bytecode.trim_start_matches("0x")).map_err(|error| Error::InvalidInput(format!("Invalid bytecode: {}", error)))?;
+        self.account(address)?.code = Some(format!("0x{}", bytecode.trim_start_matches("0x")));
+        Ok(self)
+    }
+
+    pub fn set_storage(mut self, address: &str, slot: H256, value: H256) -> Result<Self> {
+        let account = self.account(address)?;
+        match &mut account.state {
+            Some(state) => state.insert(slot, value),
+            None => account.state_diff.get_or_insert_with(BTreeMap::new).insert(slot, value),
+        };
+        Ok(self)
+    }
+
+    // Replaces all of the account's storage with `state`
+    pub fn replace_storage(mut self, address: &str, state: BTreeMap<H256, H256>) -> Result<Self> {
+        let account = self.account(address)?;
+        let mut state = state;
+        state.extend(account.state_diff.take().unwrap_or_default());
+        account.state = Some(state);
+        Ok(self)
+    }
+
+    // Writes the holder's balance straight into the token's storage. The slot is found
+    // with find_balance_slot, so tokens that compute balances (rebasing, shares) fail.
+    pub async fn set_erc20_balance(self, client: &EthClient, token: &str, holder: &str, amount: U256) -> Result<Self> {
+        let slot = find_balance_slot(client, token, holder).await?;
+        self.set_storage(token, slot.key(parse_address(holder)?), u256_to_h256(amount))
+    }
+
+    fn account(&mut self, address: &str) -> Result<&mut AccountOverride> {
+        Ok(self.accounts.entry(parse_address(address)?).or_default())
+    }
+}
+
+// Solidity hashes the key before the slot; Vyper the other way round
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingLayout {
+    Solidity,
+    Vyper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceSlot {
+    pub slot: u64,
+    pub layout: MappingLayout,
+}
+
+impl BalanceSlot {
+    // Storage key of `holder`'s entry in the mapping
+    pub fn key(&self, holder: H160) -> H256 {
+        let mut preimage = [0u8; 64];
+        let (key, slot) = match self.layout {
+            MappingLayout::Solidity => (0, 32),
+            MappingLayout::Vyper => (32, 0),
+        };
+        preimage[key + 12..key + 32].copy_from_slice(holder.as_bytes());
+        preimage[slot + 24..slot + 32].copy_from_slice(&self.slot.to_be_bytes());
+        H256::from_slice(keccak_hash::keccak(preimage).as_bytes())
+    }
+}
+
+// Finds the storage slot of a token's balance mapping with a single eth_call: every
+// candidate key is overridden with a distinct marker value and balanceOf reports
+// which one it read
+pub async fn find_balance_slot(client: &EthClient, token: &str, holder: &str) -> Result<BalanceSlot> {
+    let holder_address = parse_address(holder)?;
+    let marker = U256::from(0xba1a_5107u64) << 128;
+    let candidates: Vec<BalanceSlot> = (0..MAX_BALANCE_SLOT)
+        .flat_map(|slot| [MappingLayout::Solidity, MappingLayout::Vyper].map(|layout| BalanceSlot { slot, layout }))
+        .collect();
+
+    let mut overrides = Overrides::new();
+    for (index, candidate) in candidates.iter().enumerate() {
+        overrides = overrides.set_storage(token, candidate.key(holder_address), u256_to_h256(marker + index))?;
+    }
+    let transaction = TransactionRequest::call(token, "balanceOf(address)", vec![format!("{:0>64}", holder.trim_start_matches("0x"))])?;
+    let result = call_with_overrides(client, &transaction, "latest", &overrides).await?;
+    let balance = U256::from_str_radix(result.trim_start_matches("0x"), 16).map_err(|_| Error::Decode(format!("Invalid balanceOf result {}", result)))?;
+
+    balance
+        .checked_sub(marker)
+        .filter(|index| *index < U256::from(candidates.len()))
+        .map(|index| candidates[index.as_usize()])
+        .ok_or_else(|| Error::Unsupported(format!("No balance mapping found for {} in the first {} slots", token, MAX_BALANCE_SLOT)))
+}
+
+// eth_call with a state override set, returning the raw hex result
+pub async fn call_with_overrides(client: &EthClient, transaction: &TransactionRequest, block: &str, overrides: &Overrides) -> Result<String> {
+    let params = vec![serde_json::json!(transaction), serde_json::json!(block), serde_json::json!(overrides)];
+    let result = client.send("eth_call", params).await?;
+    Ok(result.as_str().ok_or_else(|| Error::Decode(format!("Invalid eth_call response: {}", result)))?.to_string())
+}
+
+fn parse_address(address: &str) -> Result<H160> {
+    let bytes = hex::decode(address.trim_start_matches("0x")).map_err(|_| Error::InvalidInput(format!("Invalid address {}", address)))?;
+    if bytes.len() != 20 {
+        return Err(Error::InvalidInput(format!("Invalid address {}", address)));
+    }
+    Ok(H160::from_slice(&bytes))
+}
+
+fn u256_to_h256(value: U256) -> H256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    H256(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::testing::{Failure, MockNode};
+
+    const TOKEN: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const HOLDER: &str = "0x1111111111111111111111111111111111111111";
+
+    #[test]
+    fn builds_the_override_object() {
+        let slot = H256::from_low_u64_be(1);
+        let overrides = Overrides::new().fund(HOLDER, 5.into()).unwrap().set_nonce(HOLDER, 2).unwrap().set_code(TOKEN, "6080").unwrap().set_storage(TOKEN, slot, H256::from_low_u64_be(9)).unwrap();
+        assert_eq!(
+            json!(overrides),
+            json!({
+                HOLDER: {"balance": "0x5", "nonce": "0x2"},
+                TOKEN: {"code": "0x6080", "stateDiff": {format!("{:?}", slot): format!("{:?}", H256::from_low_u64_be(9))}},
+            })
+        );
+
+        // Replacing the storage keeps slots set before, and later ones join the full state
+        let replaced = overrides.replace_storage(TOKEN, BTreeMap::new()).unwrap().set_storage(TOKEN, H256::zero(), H256::zero()).unwrap();
+        let account = &replaced.accounts()[&parse_address(TOKEN).unwrap()];
+        assert_eq!((account.state.as_ref().map(BTreeMap::len), account.state_diff.as_ref()), (Some(2), None));
+
+        assert!(matches!(Overrides::new().fund("0x12", U256::one()), Err(Error::InvalidInput(_))));
+        assert!(matches!(Overrides::new().set_code(TOKEN, "0xzz"), Err(Error::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn finds_a_balance_mapping_and_overrides_it() {
+        // A token keeping balances in a Solidity mapping at slot 9
+        let real = BalanceSlot { slot: 9, layout: MappingLayout::Solidity };
+        let key = format!("{:?}", real.key(parse_address(HOLDER).unwrap()));
+        let node = MockNode::new(move |method, params| match method {
+            "eth_call" => Ok(params[2][TOKEN]["stateDiff"].get(&key).cloned().unwrap_or(json!(format!("0x{:064x}", 0)))),
+            _ => Err(Failure::unexpected(method)),
+        });
+        let client = node.client();
+        assert_eq!(find_balance_slot(&client, TOKEN, HOLDER).await.unwrap(), real);
+        let call = &node.params_of("eth_call")[0];
+        assert_eq!(call[2][TOKEN]["stateDiff"].as_object().unwrap().len(), 2 * MAX_BALANCE_SLOT as usize);
+
+        let overrides = Overrides::new().set_erc20_balance(&client, TOKEN, HOLDER, U256::exp10(6)).await.unwrap();
+        let written: Value = json!(overrides)[TOKEN]["stateDiff"].clone();
+        assert_eq!(written, json!({format!("{:?}", real.key(parse_address(HOLDER).unwrap())): format!("0x{:064x}", 1_000_000)}));
+
+        // A token that ignores its storage isn't supported
+        let computed = MockNode::with_results([("eth_call", json!(format!("0x{:064x}", 7)))]).client();
+        assert!(matches!(find_balance_slot(&computed, TOKEN, HOLDER).await, Err(Error::Unsupported(_))));
+    }
+}
@@ -0,0 +1,164 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ethabi::ethereum_types::H256;
+use tokio::sync::mpsc;
+
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::poll::{jittered, poll_until_woken};
+use crate::transaction::TransactionReceipt;
+use crate::transport::WsTransport;
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+// A broadcast transaction waiting to be mined. The receipt is polled on a timer, or,
+// with a WebSocket attached, once per new head: a receipt can only appear with a new
+// block, so that is both sooner and fewer requests than a fixed interval.
+pub struct PendingTransaction {
+    client: EthClient,
+    hash: H256,
+    confirmations: u64,
+    interval: Duration,
+    timeout: Duration,
+    heads: Option<Arc<WsTransport>>,
+}
+
+impl PendingTransaction {
+    pub fn new(client: &EthClient, hash: H256) -> Self {
+        PendingTransaction {
+            client: client.clone(),
+            hash,
+            confirmations: 1,
+            interval: DEFAULT_POLL_INTERVAL,
+            timeout: DEFAULT_TIMEOUT,
+            heads: None,
+        }
+    }
+
+    pub fn hash(&self) -> H256 {
+        self.hash
+    }
+
+    // Blocks including the one the transaction is mined in; 1 returns on inclusion
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations.max(1);
+        self
+    }
+
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    // Polls on each newHeads notification from `ws` instead of on the timer. The
+    // receipt still comes from the client; if the subscription fails or drops, waiting
+    // carries on with timer polling.
+    pub fn with_heads(mut self, ws: Arc<WsTransport>) -> Self {
+        self.heads = Some(ws);
+        self
+    }
+
+    // The receipt once the transaction has enough confirmations, reverted or not
+    pub async fn wait(self) -> Result<TransactionReceipt> {
+        let deadline = Instant::now() + self.timeout;
+        let mut subscription = match &self.heads {
+            Some(ws) => ws.subscribe(vec![serde_json::json!("newHeads")]).await.ok(),
+            None => None,
+        };
+        let result = self.wait_until(deadline, subscription.as_mut().map(|(_, heads)| heads)).await;
+        if let (Some(ws), Some((id, _))) = (&self.heads, subscription) {
+            let _ = ws.unsubscribe(&id).await;
+        }
+        result
+    }
+
+    async fn wait_until(&self, deadline: Instant, heads: Option<&mut mpsc::UnboundedReceiver<serde_json::Value>>) -> Result<TransactionReceipt> {
+        let hash = format!("{:?}", self.hash);
+        // The head from the last newHeads notification, saving an eth_blockNumber call
+        let (head, heads) = (&std::sync::Mutex::new(None), &tokio::sync::Mutex::new(heads));
+        let poll = || async {
+            let seen = head.lock().unwrap().take();
+            let Some(receipt) = self.client.get_transaction_receipt(&hash).await? else {
+                return Ok(None);
+            };
+            if self.confirmations == 1 {
+                return Ok(Some(receipt));
+            }
+            let head = match seen {
+                Some(head) => head,
+                None => self.client.get_block_number().await?,
+            };
+            Ok((head + 1 >= receipt.block_number.as_u64() + self.confirmations).then_some(receipt))
+        };
+        let wake = move |remaining: Duration| async move {
+            let mut heads = heads.lock().await;
+            let Some(receiver) = heads.as_deref_mut() else {
+                tokio::time::sleep(jittered(self.interval).min(remaining)).await;
+                return;
+            };
+            match tokio::time::timeout(remaining, receiver.recv()).await {
+                Ok(Some(header)) => *head.lock().unwrap() = header["number"].as_str().and_then(|number| u64::from_str_radix(number.trim_start_matches("0x"), 16).ok()),
+                // Subscription ended with the connection; fall back to the timer
+                Ok(None) => *heads = None,
+                Err(_) => {}
+            }
+        };
+        poll_until_woken(poll, wake, deadline).await.map_err(|error| match error {
+            Error::Timeout(_) => Error::Timeout(format!("{} was not confirmed in time", hash)),
+            error => error,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+    use crate::testing::{Failure, MockNode};
+
+    // Mines the transaction in block 5 on the third receipt request; the head moves one
+    // block per eth_blockNumber call from there
+    fn node() -> MockNode {
+        let (receipts, heads) = (AtomicU64::new(0), AtomicU64::new(0));
+        MockNode::new(move |method, params| match method {
+            "eth_getTransactionReceipt" if receipts.fetch_add(1, Ordering::SeqCst) < 2 => Ok(serde_json::Value::Null),
+            "eth_getTransactionReceipt" => Ok(serde_json::json!({
+                "transactionHash": params[0],
+                "transactionIndex": "0x0",
+                "blockHash": H256::zero(),
+                "blockNumber": "0x5",
+                "from": "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+                "to": null,
+                "cumulativeGasUsed": "0x5208",
+                "gasUsed": "0x5208",
+                "contractAddress": null,
+                "logs": [],
+                "status": "0x1",
+            })),
+            "eth_blockNumber" => Ok(serde_json::json!(format!("0x{:x}", 5 + heads.fetch_add(1, Ordering::SeqCst)))),
+            _ => Err(Failure::unexpected(method)),
+        })
+    }
+
+    #[tokio::test]
+    async fn waits_for_inclusion_and_confirmations() {
+        let node = node();
+        let client = node.client();
+        let pending = PendingTransaction::new(&client, H256::from_low_u64_be(1)).with_poll_interval(Duration::from_millis(5)).with_confirmations(3);
+        let receipt = pending.wait().await.unwrap();
+        assert_eq!(receipt.block_number.as_u64(), 5);
+        // Heads 5, 6 and 7 were seen before block 5 had three confirmations
+        assert_eq!(node.params_of("eth_blockNumber").len(), 3);
+
+        let timeout = PendingTransaction::new(&client, H256::zero()).with_timeout(Duration::ZERO).with_confirmations(100);
+        assert!(matches!(timeout.wait().await, Err(Error::Timeout(_))));
+    }
+}
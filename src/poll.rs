@@ -0,0 +1,131 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::error::{Error, Result};
+
+// Spread of the random jitter applied to each interval (+/- 20%)
+pub const JITTER_FRACTION: f64 = 0.2;
+
+// Calls `poll` until it yields Some, sleeping a jittered `interval` between attempts.
+// The last attempt happens at the deadline; after that it fails with a timeout. Dropping
+// the returned future cancels polling cleanly between attempts.
+pub async fn poll_until<T, F, Fut>(poll: F, interval: Duration, deadline: Instant) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<T>>>,
+{
+    poll_until_woken(poll, |remaining| tokio::time::sleep(jittered(interval).min(remaining)), deadline).await
+}
+
+// As poll_until, but between attempts waits on `wake`, which is given the time left
+// before the deadline, e.g. for the next block from a subscription. It should return
+// by then; the deadline is checked after every attempt.
+pub async fn poll_until_woken<T, F, Fut, W, WakeFut>(mut poll: F, mut wake: W, deadline: Instant) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<T>>>,
+    W: FnMut(Duration) -> WakeFut,
+    WakeFut: Future<Output = ()>,
+{
+    loop {
+        if let Some(value) = poll().await? {
+            return Ok(value);
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(Error::Timeout("Polling timed out before the condition was met".to_string()));
+        }
+        wake(deadline - now).await;
+    }
+}
+
+// As poll_until with no deadline, for watchers that run until they are dropped
+pub async fn poll_until_ready<T, F, Fut>(mut poll: F, interval: Duration) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<T>>>,
+{
+    loop {
+        if let Some(value) = poll().await? {
+            return Ok(value);
+        }
+        tokio::time::sleep(jittered(interval)).await;
+    }
+}
+
+// Randomizes an interval so many pollers started together don't hit the node in lockstep
+pub fn jittered(interval: Duration) -> Duration {
+    let factor = rand::thread_rng().gen_range(1.0 - JITTER_FRACTION..=1.0 + JITTER_FRACTION);
+    interval.mul_f64(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn jitters_within_a_fifth() {
+        for _ in 0..100 {
+            let interval = jittered(Duration::from_millis(100));
+            assert!(interval >= Duration::from_millis(80) && interval <= Duration::from_millis(120), "{:?}", interval);
+        }
+    }
+
+    #[tokio::test]
+    async fn polls_until_the_condition_holds() {
+        let attempts = AtomicU32::new(0);
+        let poll = || async { Ok((attempts.fetch_add(1, Ordering::SeqCst) == 2).then_some("done")) };
+        let value = poll_until(poll, Duration::from_millis(1), Instant::now() + Duration::from_secs(5)).await.unwrap();
+        assert_eq!((value, attempts.load(Ordering::SeqCst)), ("done", 3));
+    }
+
+    #[tokio::test]
+    async fn tries_once_more_at_the_deadline_then_times_out() {
+        let attempts = AtomicU32::new(0);
+        let started = Instant::now();
+        let poll = || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Ok(None::<()>)
+        };
+        let result = poll_until(poll, Duration::from_secs(60), started + Duration::from_millis(30)).await;
+        assert!(matches!(result, Err(Error::Timeout(_))));
+        // The long interval is cut short by the deadline
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        // An expired deadline still gets one attempt
+        let value = poll_until(|| async { Ok(Some(1)) }, Duration::from_secs(60), started).await.unwrap();
+        assert_eq!(value, 1);
+    }
+
+    #[tokio::test]
+    async fn stops_at_the_first_error() {
+        let attempts = AtomicU32::new(0);
+        let poll = || async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 => Ok(None::<()>),
+                _ => Err(Error::Decode("bad response".to_string())),
+            }
+        };
+        let result = poll_until_ready(poll, Duration::from_millis(1)).await;
+        assert!(matches!(result, Err(Error::Decode(message)) if message == "bad response"));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn waits_on_the_wake_future_between_attempts() {
+        let (wakes, attempts) = (AtomicU32::new(0), AtomicU32::new(0));
+        let poll = || async { Ok((attempts.fetch_add(1, Ordering::SeqCst) == 3).then_some(())) };
+        let wake = |remaining: Duration| {
+            assert!(remaining <= Duration::from_secs(5));
+            wakes.fetch_add(1, Ordering::SeqCst);
+            std::future::ready(())
+        };
+        poll_until_woken(poll, wake, Instant::now() + Duration::from_secs(5)).await.unwrap();
+        assert_eq!(wakes.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+
+use ethabi::ethereum_types::U256;
+use ethabi::{ParamType, Token};
+
+use crate::abi::{decode_result, encode_call};
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::format::TokenAmount;
+use crate::multicall::{self, Call};
+use crate::router::ChainRouter;
+
+pub const NATIVE_DECIMALS: u32 = 18;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenBalance {
+    pub token: String,
+    pub symbol: String,
+    pub decimals: u32,
+    pub balance: U256,
+}
+
+impl TokenBalance {
+    pub fn amount(&self) -> TokenAmount {
+        TokenAmount::new(self.balance, self.decimals).with_symbol(&self.symbol)
+    }
+}
+
+#[derive(Debug)]
+pub struct ChainHoldings {
+    pub chain_id: u64,
+    pub name: String,
+    pub native: U256,
+    pub tokens: Vec<TokenBalance>,
+}
+
+// Holdings of one address across every chain of a router. A chain that fails is
+// reported in `errors` and left out of the totals.
+#[derive(Debug)]
+pub struct Portfolio {
+    pub address: String,
+    pub chains: Vec<ChainHoldings>,
+    pub errors: Vec<(u64, Error)>,
+}
+
+impl Portfolio {
+    // Balances summed across chains by symbol, with the native currency as "ETH". A
+    // symbol with different decimals on different chains is kept apart per decimals.
+    pub fn totals(&self) -> Vec<TokenBalance> {
+        let mut totals: BTreeMap<(String, u32), U256> = BTreeMap::new();
+        for chain in &self.chains {
+            let native = totals.entry(("ETH".to_string(), NATIVE_DECIMALS)).or_default();
+            *native = native.saturating_add(chain.native);
+            for token in &chain.tokens {
+                let total = totals.entry((token.symbol.clone(), token.decimals)).or_default();
+                *total = total.saturating_add(token.balance);
+            }
+        }
+        totals
+            .into_iter()
+            .map(|((symbol, decimals), balance)| TokenBalance {
+                token: String::new(),
+                symbol,
+                decimals,
+                balance,
+            })
+            .collect()
+    }
+}
+
+// Queries every chain concurrently. `tokens` lists the ERC-20 contracts to include
+// per chain id; chains without an entry report only the native balance.
+pub async fn portfolio(router: &ChainRouter, address: &str, tokens: &BTreeMap<u64, Vec<String>>) -> Result<Portfolio> {
+    let results = router
+        .query_all(|chain_id, client| {
+            let tokens = tokens.get(&chain_id).cloned().unwrap_or_default();
+            async move { chain_holdings(&client, address, &tokens).await }
+        })
+        .await;
+
+    let mut portfolio = Portfolio {
+        address: address.to_string(),
+        chains: Vec::new(),
+        errors: Vec::new(),
+    };
+    for (chain_id, result) in results {
+        match result {
+            Ok((native, tokens)) => portfolio.chains.push(ChainHoldings {
+                chain_id,
+                name: router.name(chain_id).unwrap_or_default().to_string(),
+                native,
+                tokens,
+            }),
+            Err(error) => portfolio.errors.push((chain_id, error)),
+        }
+    }
+    Ok(portfolio)
+}
+
+// Native balance plus symbol, decimals and balance of each token in one multicall
+pub async fn chain_holdings(client: &EthClient, address: &str, tokens: &[String]) -> Result<(U256, Vec<TokenBalance>)> {
+    let holder = Token::Address(address.trim_start_matches("0x").parse().map_err(|_| Error::InvalidInput(format!("Invalid address {}", address)))?);
+    let native = client.get_balance(address, crate::BlockTag::Latest).await?;
+    if tokens.is_empty() {
+        return Ok((native, Vec::new()));
+    }
+
+    let balance_of = encode_call("balanceOf(address)", &[holder]);
+    let mut calls = Vec::with_capacity(tokens.len() * 3);
+    for token in tokens {
+        calls.push(Call::new(token, &encode_call("symbol()", &[])));
+        calls.push(Call::new(token, &encode_call("decimals()", &[])));
+        calls.push(Call::new(token, &balance_of));
+    }
+    let results = multicall::aggregate(client, &calls).await?;
+
+    let mut balances = Vec::with_capacity(tokens.len());
+    for (token, results) in tokens.iter().zip(results.chunks(3)) {
+        let uint = |result: &Option<String>| match decode_result(result.as_deref()?, &[ParamType::Uint(256)]).ok()?.pop() {
+            Some(Token::Uint(value)) => Some(value),
+            _ => None,
+        };
+        let (Some(decimals), Some(balance)) = (uint(&results[1]), uint(&results[2])) else {
+            return Err(Error::Decode(format!("{} does not look like an ERC-20 token", token)));
+        };
+        balances.push(TokenBalance {
+            token: token.clone(),
+            symbol: results[0].as_deref().map(decode_symbol).unwrap_or_default(),
+            decimals: decimals.min(U256::from(77)).as_u32(),
+            balance,
+        });
+    }
+    Ok((native, balances))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenMetadata {
+    pub token: String,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u32,
+    pub total_supply: U256,
+}
+
+// Name, symbol, decimals and total supply of an ERC-20 token in one multicall
+pub async fn token_metadata(client: &EthClient, token: &str) -> Result<TokenMetadata> {
+    let calls: Vec<Call> = ["name()", "symbol()", "decimals()", "totalSupply()"].iter().map(|signature| Call::new(token, &encode_call(signature, &[]))).collect();
+    let results = multicall::aggregate(client, &calls).await?;
+    let uint = |result: &Option<String>| match decode_result(result.as_deref()?, &[ParamType::Uint(256)]).ok()?.pop() {
+        Some(Token::Uint(value)) => Some(value),
+        _ => None,
+    };
+    let (Some(decimals), Some(total_supply)) = (uint(&results[2]), uint(&results[3])) else {
+        return Err(Error::Decode(format!("{} does not look like an ERC-20 token", token)));
+    };
+    Ok(TokenMetadata {
+        token: token.to_string(),
+        name: results[0].as_deref().map(decode_symbol).unwrap_or_default(),
+        symbol: results[1].as_deref().map(decode_symbol).unwrap_or_default(),
+        decimals: decimals.min(U256::from(77)).as_u32(),
+        total_supply,
+    })
+}
+
+// Most tokens return a string; some early ones (MKR, SAI) return bytes32
+pub(crate) fn decode_symbol(result: &str) -> String {
+    if let Ok(Some(Token::String(symbol))) = decode_result(result, &[ParamType::String]).map(|mut tokens| tokens.pop()) {
+        return symbol;
+    }
+    match decode_result(result, &[ParamType::FixedBytes(32)]).map(|mut tokens| tokens.pop()) {
+        Ok(Some(Token::FixedBytes(bytes))) => String::from_utf8_lossy(&bytes).trim_end_matches('\0').to_string(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::multicall::tests::Multicall;
+
+    #[tokio::test]
+    async fn totals_balances_across_chains() {
+        let holder = "0x1111111111111111111111111111111111111111";
+        let usdc = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+        let mkr = "0x9f8f72aa9304c8b593d555f12ef6589cc3a579a2";
+        let word = |token: Token| format!("0x{}", hex::encode(ethabi::encode(&[token])));
+        let balance_of = encode_call("balanceOf(address)", &[Token::Address(holder[2..].parse().unwrap())]);
+
+        let chain = |native: u64, usdc_balance: u64, with_mkr: bool| {
+            let mut answers = HashMap::new();
+            answers.insert(("eth_getBalance".to_string(), holder.to_string()), format!("0x{:x}", native));
+            answers.insert((usdc.to_string(), encode_call("symbol()", &[])), word(Token::String("USDC".to_string())));
+            answers.insert((usdc.to_string(), encode_call("decimals()", &[])), word(Token::Uint(6.into())));
+            answers.insert((usdc.to_string(), balance_of.clone()), word(Token::Uint(usdc_balance.into())));
+            if with_mkr {
+                let mut symbol = b"MKR".to_vec();
+                symbol.resize(32, 0);
+                answers.insert((mkr.to_string(), encode_call("symbol()", &[])), word(Token::FixedBytes(symbol)));
+                answers.insert((mkr.to_string(), encode_call("decimals()", &[])), word(Token::Uint(18.into())));
+                answers.insert((mkr.to_string(), balance_of.clone()), word(Token::Uint(5.into())));
+            }
+            Multicall(answers).client()
+        };
+        let router = ChainRouter::new().with_chain(1, "mainnet", chain(100, 2_500_000, true)).with_chain(10, "optimism", chain(50, 500_000, false));
+        let mut tokens = BTreeMap::new();
+        tokens.insert(1, vec![usdc.to_string(), mkr.to_string()]);
+        tokens.insert(10, vec![usdc.to_string()]);
+
+        let portfolio = portfolio(&router, holder, &tokens).await.unwrap();
+        assert!(portfolio.errors.is_empty());
+        assert_eq!(portfolio.chains[0].tokens[1].symbol, "MKR");
+        let totals: Vec<(String, String)> = portfolio.totals().iter().map(|total| (total.symbol.clone(), total.balance.to_string())).collect();
+        assert_eq!(totals, vec![("ETH".to_string(), "150".to_string()), ("MKR".to_string(), "5".to_string()), ("USDC".to_string(), "3000000".to_string())]);
+        assert_eq!(portfolio.totals()[2].amount().to_string(), "3 USDC");
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc;
+
+// Where a long operation (crawl, export, batch send) stands after its latest step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub done: u64,
+    // None when the size isn't known up front
+    pub total: Option<u64>,
+    // Last block reached, for block scans
+    pub block: Option<u64>,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> Option<f64> {
+        self.total.map(|total| if total == 0 { 1.0 } else { self.done.min(total) as f64 / total as f64 })
+    }
+
+    // Time left at the average rate so far
+    pub fn eta(&self) -> Option<Duration> {
+        let total = self.total?;
+        if self.done == 0 {
+            return None;
+        }
+        Some(self.elapsed.mul_f64(total.saturating_sub(self.done) as f64 / self.done as f64))
+    }
+}
+
+pub type ProgressFn = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+// Stamps elapsed time on each step and hands it to the callback, if any
+#[derive(Clone)]
+pub struct ProgressTracker {
+    started: Instant,
+    total: Option<u64>,
+    callback: Option<ProgressFn>,
+}
+
+impl ProgressTracker {
+    pub fn new(total: Option<u64>, callback: Option<ProgressFn>) -> Self {
+        ProgressTracker { started: Instant::now(), total, callback }
+    }
+
+    pub fn report(&self, done: u64, block: Option<u64>) -> Progress {
+        let progress = Progress {
+            done,
+            total: self.total,
+            block,
+            elapsed: self.started.elapsed(),
+        };
+        if let Some(callback) = &self.callback {
+            callback(&progress);
+        }
+        progress
+    }
+}
+
+// A callback that forwards every update to a receiver, for consuming progress as a
+// stream from another task. Updates are dropped once the receiver is gone.
+pub fn channel() -> (ProgressFn, mpsc::UnboundedReceiver<Progress>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let callback: ProgressFn = Arc::new(move |progress: &Progress| {
+        let _ = sender.send(*progress);
+    });
+    (callback, receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_time_left_from_the_average_rate() {
+        let progress = Progress { done: 25, total: Some(100), block: None, elapsed: Duration::from_secs(10) };
+        assert_eq!((progress.fraction(), progress.eta()), (Some(0.25), Some(Duration::from_secs(30))));
+        assert_eq!(Progress { done: 0, ..progress }.eta(), None);
+        assert_eq!(Progress { total: None, ..progress }.eta(), None);
+        assert_eq!(Progress { done: 120, ..progress }.fraction(), Some(1.0));
+
+        let (callback, mut receiver) = channel();
+        let tracker = ProgressTracker::new(Some(2), Some(callback));
+        tracker.report(1, Some(7));
+        let received = receiver.try_recv().unwrap();
+        assert_eq!((received.done, received.total, received.block), (1, Some(2), Some(7)));
+    }
+}
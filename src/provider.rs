@@ -0,0 +1,335 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures_util::stream::{FuturesUnordered, StreamExt};
+
+use crate::middleware::{is_rate_limited, is_transient};
+use crate::transport::{HttpTransport, Transport, TransportError};
+
+struct Endpoint {
+    transport: Arc<dyn Transport>,
+    // Set after a failure; the endpoint is skipped until then
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+impl Endpoint {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.unhealthy_until.lock().unwrap().is_none_or(|until| now >= until)
+    }
+}
+
+// Sends each request to the first healthy endpoint in order, failing over to the next
+// on transient errors: connection failures, timeouts or rate limiting. A failed endpoint
+// sits out for the cooldown and is then tried first again, so the primary recovers
+// automatically. Other errors say nothing about the endpoint and are returned as they are.
+pub struct FallbackProvider {
+    endpoints: Vec<Endpoint>,
+    timeout: Duration,
+    cooldown: Duration,
+}
+
+impl FallbackProvider {
+    pub fn new(rpc_urls: &[&str]) -> Self {
+        Self::from_transports(rpc_urls.iter().map(|url| Arc::new(HttpTransport::new(url)) as Arc<dyn Transport>).collect())
+    }
+
+    pub fn from_transports(transports: Vec<Arc<dyn Transport>>) -> Self {
+        FallbackProvider {
+            endpoints: transports
+                .into_iter()
+                .map(|transport| Endpoint {
+                    transport,
+                    unhealthy_until: Mutex::new(None),
+                })
+                .collect(),
+            timeout: Duration::from_secs(10),
+            cooldown: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    // Health of each endpoint, in priority order
+    pub fn health(&self) -> Vec<bool> {
+        let now = Instant::now();
+        self.endpoints.iter().map(|endpoint| endpoint.is_healthy(now)).collect()
+    }
+}
+
+#[async_trait]
+impl Transport for FallbackProvider {
+    async fn request(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        // Healthy endpoints first; unhealthy ones are still a last resort
+        let now = Instant::now();
+        let (healthy, unhealthy): (Vec<&Endpoint>, Vec<&Endpoint>) = self.endpoints.iter().partition(|endpoint| endpoint.is_healthy(now));
+
+        let mut last_error = None;
+        for endpoint in healthy.into_iter().chain(unhealthy) {
+            let error = match tokio::time::timeout(self.timeout, endpoint.transport.request(method, params.clone())).await {
+                Ok(Ok(response)) if !is_rate_limited(&response) => {
+                    *endpoint.unhealthy_until.lock().unwrap() = None;
+                    return Ok(response);
+                }
+                Ok(Ok(response)) => Box::new(TransportError::RateLimited {
+                    method: method.to_string(),
+                    error: response["error"].clone(),
+                }),
+                Ok(Err(error)) if is_transient(error.as_ref()) => error,
+                Ok(Err(error)) => return Err(error),
+                Err(_) => Box::new(TransportError::Timeout {
+                    method: method.to_string(),
+                    timeout: self.timeout,
+                }),
+            };
+            *endpoint.unhealthy_until.lock().unwrap() = Some(Instant::now() + self.cooldown);
+            last_error = Some(error);
+        }
+
+        match last_error {
+            Some(error) => Err(Box::new(TransportError::Exhausted(error))),
+            None => Err("No RPC endpoints configured".into()),
+        }
+    }
+}
+
+// Methods whose answers QuorumProvider cross-checks by default
+pub const QUORUM_METHODS: [&str; 5] = ["eth_call", "eth_getBalance", "eth_getCode", "eth_getStorageAt", "eth_getTransactionCount"];
+
+// Responses seen when providers disagreed, indexed by provider
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub method: String,
+    pub responses: Vec<(usize, Result<serde_json::Value, String>)>,
+}
+
+type DivergenceHandler = Box<dyn Fn(&Divergence) + Send + Sync>;
+
+// Sends cross-checked methods to every provider and returns once `quorum` of them agree
+// on the result (or error). Other methods go to one provider at a time, failing over as
+// FallbackProvider does. Providers at different heads disagree on "latest", so pin a
+// block number for important reads.
+pub struct QuorumProvider {
+    transports: Vec<Arc<dyn Transport>>,
+    fallback: FallbackProvider,
+    quorum: usize,
+    methods: Vec<String>,
+    timeout: Duration,
+    on_divergence: Option<DivergenceHandler>,
+}
+
+impl QuorumProvider {
+    pub fn new(rpc_urls: &[&str]) -> Self {
+        Self::from_transports(rpc_urls.iter().map(|url| Arc::new(HttpTransport::new(url)) as Arc<dyn Transport>).collect())
+    }
+
+    // Defaults to a simple majority
+    pub fn from_transports(transports: Vec<Arc<dyn Transport>>) -> Self {
+        QuorumProvider {
+            quorum: transports.len() / 2 + 1,
+            fallback: FallbackProvider::from_transports(transports.clone()),
+            transports,
+            methods: QUORUM_METHODS.iter().map(|method| method.to_string()).collect(),
+            timeout: Duration::from_secs(10),
+            on_divergence: None,
+        }
+    }
+
+    pub fn with_quorum(mut self, quorum: usize) -> Self {
+        self.quorum = quorum;
+        self
+    }
+
+    pub fn with_methods(mut self, methods: &[&str]) -> Self {
+        self.methods = methods.iter().map(|method| method.to_string()).collect();
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self.fallback = self.fallback.with_timeout(timeout);
+        self
+    }
+
+    // Called whenever a cross-checked request sees disagreeing or failed providers
+    pub fn on_divergence(mut self, handler: impl Fn(&Divergence) + Send + Sync + 'static) -> Self {
+        self.on_divergence = Some(Box::new(handler));
+        self
+    }
+
+    fn flag(&self, method: &str, responses: Vec<(usize, Result<serde_json::Value, String>)>) {
+        if let Some(handler) = &self.on_divergence {
+            handler(&Divergence {
+                method: method.to_string(),
+                responses,
+            });
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for QuorumProvider {
+    async fn request(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        if !self.methods.iter().any(|checked| checked == method) {
+            return self.fallback.request(method, params).await;
+        }
+
+        let mut pending: FuturesUnordered<_> = self
+            .transports
+            .iter()
+            .enumerate()
+            .map(|(index, transport)| {
+                let params = params.clone();
+                async move { (index, tokio::time::timeout(self.timeout, transport.request(method, params)).await) }
+            })
+            .collect();
+
+        // Agreeing responses grouped by their result/error payload
+        let mut groups: Vec<(serde_json::Value, serde_json::Value, usize)> = Vec::new();
+        let mut responses = Vec::new();
+        while let Some((index, outcome)) = pending.next().await {
+            let response = match outcome {
+                Ok(Ok(response)) => response,
+                Ok(Err(error)) => {
+                    responses.push((index, Err(error.to_string())));
+                    continue;
+                }
+                Err(_) => {
+                    responses.push((index, Err(format!("Timed out after {:?}", self.timeout))));
+                    continue;
+                }
+            };
+            responses.push((index, Ok(response.clone())));
+
+            let key = serde_json::json!({"result": response.get("result"), "error": response.get("error")});
+            let position = match groups.iter().position(|(group_key, _, _)| *group_key == key) {
+                Some(position) => position,
+                None => {
+                    groups.push((key, response, 0));
+                    groups.len() - 1
+                }
+            };
+            groups[position].2 += 1;
+            let count = groups[position].2;
+            if count >= self.quorum {
+                if responses.len() > count {
+                    self.flag(method, responses);
+                }
+                return Ok(groups.swap_remove(position).1);
+            }
+        }
+
+        let best = groups.iter().map(|group| group.2).max().unwrap_or(0);
+        self.flag(method, responses);
+        Err(format!("No quorum for {}: at most {} of {} providers agreed, {} required", method, best, self.transports.len(), self.quorum).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{Failure, MockNode};
+
+    // Answers every method with `result`
+    fn node(result: &'static str) -> MockNode {
+        MockNode::new(move |_, _| Ok(json!(result)))
+    }
+
+    fn transports(nodes: &[&MockNode]) -> Vec<Arc<dyn Transport>> {
+        nodes.iter().map(|node| Arc::new((*node).clone()) as Arc<dyn Transport>).collect()
+    }
+
+    #[tokio::test]
+    async fn fails_over_and_recovers_the_primary_after_the_cooldown() {
+        let limited = Arc::new(AtomicBool::new(true));
+        let primary = {
+            let limited = limited.clone();
+            MockNode::new(move |method, _| match method {
+                "eth_sign" => Err(Failure::unexpected(method)),
+                _ if limited.load(Ordering::SeqCst) => Err(Failure::rpc(429, "too many requests")),
+                _ => Ok(json!("primary")),
+            })
+        };
+        let backup = node("backup");
+        let provider = FallbackProvider::from_transports(transports(&[&primary, &backup])).with_cooldown(Duration::from_millis(50));
+
+        assert_eq!(provider.request("eth_blockNumber", Vec::new()).await.unwrap()["result"], "backup");
+        assert_eq!(provider.health(), [false, true]);
+        // The primary sits out the cooldown
+        assert_eq!(provider.request("eth_blockNumber", Vec::new()).await.unwrap()["result"], "backup");
+        assert_eq!(primary.calls().len(), 1);
+
+        limited.store(false, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(provider.request("eth_blockNumber", Vec::new()).await.unwrap()["result"], "primary");
+        assert_eq!(provider.health(), [true, true]);
+
+        // An error that isn't transient is the request's fault, not the endpoint's
+        assert!(provider.request("eth_sign", Vec::new()).await.is_err());
+        assert_eq!(provider.health(), [true, true]);
+        assert!(backup.params_of("eth_sign").is_empty());
+    }
+
+    #[tokio::test]
+    async fn fails_over_on_timeouts_until_the_endpoints_run_out() {
+        let slow = node("slow").with_latency(Duration::from_millis(200));
+        let provider = FallbackProvider::from_transports(transports(&[&slow, &slow])).with_timeout(Duration::from_millis(10));
+        let error = provider.request("eth_blockNumber", Vec::new()).await.unwrap_err();
+        assert!(is_transient(error.as_ref()), "{}", error);
+        assert_eq!((slow.calls().len(), provider.health()), (2, vec![false, false]));
+    }
+
+    #[tokio::test]
+    async fn returns_the_result_a_quorum_agrees_on() {
+        let (one, other, odd) = (node("0x1"), node("0x1"), node("0x2").with_latency(Duration::from_millis(20)));
+        let divergences = Arc::new(Mutex::new(Vec::new()));
+        let seen = divergences.clone();
+        let provider = QuorumProvider::from_transports(transports(&[&odd, &one, &other])).on_divergence(move |divergence| seen.lock().unwrap().push(divergence.clone()));
+
+        assert_eq!(provider.request("eth_call", Vec::new()).await.unwrap()["result"], "0x1");
+        // The two agreeing answers came before the odd one, so nothing diverged yet
+        assert!(divergences.lock().unwrap().is_empty());
+        assert_eq!([odd.calls().len(), one.calls().len(), other.calls().len()], [1, 1, 1]);
+
+        // Methods that aren't cross-checked go to one provider
+        provider.request("eth_blockNumber", Vec::new()).await.unwrap();
+        assert_eq!([odd.calls().len(), one.calls().len()], [2, 1]);
+    }
+
+    #[tokio::test]
+    async fn reports_providers_that_disagree() {
+        let (one, two, three) = (node("0x1"), node("0x2"), MockNode::new(|_, _| Err(Failure::Transport("down".to_string()))));
+        let divergences = Arc::new(Mutex::new(Vec::new()));
+        let seen = divergences.clone();
+        let provider = QuorumProvider::from_transports(transports(&[&one, &two, &three])).on_divergence(move |divergence| seen.lock().unwrap().push(divergence.clone()));
+
+        let error = provider.request("eth_getBalance", Vec::new()).await.unwrap_err();
+        assert_eq!(error.to_string(), "No quorum for eth_getBalance: at most 1 of 3 providers agreed, 2 required");
+        let divergences = divergences.lock().unwrap();
+        let mut responses = divergences[0].responses.clone();
+        responses.sort_by_key(|(index, _)| *index);
+        assert_eq!(responses[0].1.as_ref().unwrap()["result"], "0x1");
+        assert_eq!(responses[1].1.as_ref().unwrap()["result"], "0x2");
+        assert_eq!(responses[2].1, Err("down".to_string()));
+    }
+
+    #[tokio::test]
+    async fn fails_over_for_methods_it_does_not_cross_check() {
+        let (limited, backup) = (MockNode::new(|_, _| Err(Failure::rpc(429, "too many requests"))), node("0x5"));
+        let provider = QuorumProvider::from_transports(transports(&[&limited, &backup]));
+        assert_eq!(provider.request("eth_blockNumber", Vec::new()).await.unwrap()["result"], "0x5");
+        assert_eq!((limited.calls().len(), backup.calls().len()), (1, 1));
+    }
+}
@@ -0,0 +1,462 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::stream::FuturesUnordered;
+use futures_util::StreamExt;
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::client::EthClient;
+use crate::context::RequestContext;
+use crate::dialect::METHOD_NOT_FOUND;
+use crate::error::{Error, Result};
+use crate::middleware::RATE_LIMIT_ERROR_CODES;
+
+// Largest request body accepted over HTTP
+pub const MAX_REQUEST_BYTES: usize = 5 * 1024 * 1024;
+// Most requests one batch may hold, so a single body can't fan out into thousands of
+// upstream calls
+pub const MAX_BATCH_SIZE: usize = 100;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+// Methods that only read chain state, for exposing a node to consumers that must not
+// send transactions, open subscriptions or reach the admin, debug and txpool APIs
+pub const READ_METHODS: &[&str] = &[
+    "eth_chainId",
+    "net_version",
+    "eth_syncing",
+    "eth_blockNumber",
+    "eth_gasPrice",
+    "eth_maxPriorityFeePerGas",
+    "eth_feeHistory",
+    "eth_getBalance",
+    "eth_getCode",
+    "eth_getStorageAt",
+    "eth_getTransactionCount",
+    "eth_getProof",
+    "eth_call",
+    "eth_estimateGas",
+    "eth_getBlockByNumber",
+    "eth_getBlockByHash",
+    "eth_getBlockTransactionCountByNumber",
+    "eth_getBlockTransactionCountByHash",
+    "eth_getTransactionByHash",
+    "eth_getTransactionReceipt",
+    "eth_getLogs",
+];
+
+// A client of the HTTP proxy, identified by the key it sends in an X-API-Key or
+// `Authorization: Bearer` header. Its requests run under a RequestContext named after
+// it, so metrics and traces are broken down by consumer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Consumer {
+    pub name: String,
+    pub api_key: String,
+    pub quota: Option<Quota>,
+}
+
+// At most `requests` per `period`, counting each request of a batch, in fixed windows
+// starting at the consumer's first request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    pub requests: u64,
+    pub period: Duration,
+}
+
+struct ConsumerState {
+    name: String,
+    quota: Option<Quota>,
+    // Start of the current window and requests made in it
+    usage: Mutex<Option<(Instant, u64)>>,
+}
+
+impl ConsumerState {
+    // Counts `requests` against the quota, or refuses them all if they don't fit
+    fn take(&self, requests: u64) -> bool {
+        let Some(quota) = self.quota else { return true };
+        let mut usage = self.usage.lock().unwrap();
+        let now = Instant::now();
+        let (start, used) = match *usage {
+            Some((start, used)) if now.duration_since(start) < quota.period => (start, used),
+            _ => (now, 0),
+        };
+        if used + requests > quota.requests {
+            return false;
+        }
+        *usage = Some((start, used + requests));
+        true
+    }
+}
+
+// Serves JSON-RPC to other tools by forwarding each request through `client`, so they
+// get whatever middleware it was built with (caching, rate limiting, retries,
+// metrics). Node errors are passed through unchanged; requests the upstream can't be
+// reached for get an internal error without the upstream's details, which may carry
+// an API key.
+#[derive(Clone)]
+pub struct Proxy {
+    client: EthClient,
+    allowlist: Option<Arc<HashSet<String>>>,
+    max_batch: usize,
+    // By SHA-256 of the API key; empty leaves the HTTP server open
+    consumers: Arc<HashMap<[u8; 32], Arc<ConsumerState>>>,
+    #[cfg(feature = "metrics")]
+    registry: Option<crate::metrics::Registry>,
+}
+
+impl Proxy {
+    pub fn new(client: &EthClient) -> Self {
+        Proxy {
+            client: client.clone(),
+            allowlist: None,
+            max_batch: MAX_BATCH_SIZE,
+            consumers: Arc::new(HashMap::new()),
+            #[cfg(feature = "metrics")]
+            registry: None,
+        }
+    }
+
+    // Answers any other method with a method-not-found error, e.g. READ_METHODS. Without
+    // one, stdio forwards every method and HTTP only READ_METHODS.
+    pub fn with_allowlist<S: AsRef<str>>(mut self, methods: impl IntoIterator<Item = S>) -> Self {
+        self.allowlist = Some(Arc::new(methods.into_iter().map(|method| method.as_ref().to_string()).collect()));
+        self
+    }
+
+    // Refuses batches of more than `max_batch` requests as a whole
+    pub fn with_max_batch(mut self, max_batch: usize) -> Self {
+        self.max_batch = max_batch.max(1);
+        self
+    }
+
+    // Requires one of these consumers' API keys on every HTTP request. Stdio is not
+    // affected: whoever runs the process already has the profile.
+    pub fn with_consumers(mut self, consumers: impl IntoIterator<Item = Consumer>) -> Self {
+        let consumers = consumers.into_iter().map(|consumer| {
+            let state = ConsumerState {
+                name: consumer.name,
+                quota: consumer.quota,
+                usage: Mutex::new(None),
+            };
+            (key_digest(&consumer.api_key), Arc::new(state))
+        });
+        self.consumers = Arc::new(consumers.collect());
+        self
+    }
+
+    // Serves `registry` at GET /metrics
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, registry: crate::metrics::Registry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    // Response to a request or batch body; None when it held only notifications
+    pub async fn handle(&self, body: &[u8]) -> Option<serde_json::Value> {
+        match parse(body) {
+            Ok(request) => self.dispatch(request).await,
+            Err(response) => Some(response),
+        }
+    }
+
+    async fn dispatch(&self, request: serde_json::Value) -> Option<serde_json::Value> {
+        match request {
+            serde_json::Value::Array(requests) if requests.is_empty() => Some(error_response(serde_json::Value::Null, INVALID_REQUEST, "Empty batch")),
+            serde_json::Value::Array(requests) if requests.len() > self.max_batch => {
+                Some(error_response(serde_json::Value::Null, INVALID_REQUEST, &format!("Batch of {} requests is over the limit of {}", requests.len(), self.max_batch)))
+            }
+            serde_json::Value::Array(requests) => {
+                let responses: Vec<_> = futures_util::future::join_all(requests.into_iter().map(|request| self.forward(request))).await.into_iter().flatten().collect();
+                (!responses.is_empty()).then_some(serde_json::Value::Array(responses))
+            }
+            request => self.forward(request).await,
+        }
+    }
+
+    async fn forward(&self, request: serde_json::Value) -> Option<serde_json::Value> {
+        let id = request.get("id").cloned();
+        let respond = |response: serde_json::Value| id.clone().map(|_| response);
+        let Some(method) = request["method"].as_str().filter(|_| request["jsonrpc"] == "2.0") else {
+            return Some(error_response(id.unwrap_or_default(), INVALID_REQUEST, "Invalid request"));
+        };
+        if self.allowlist.as_ref().is_some_and(|allowlist| !allowlist.contains(method)) {
+            return respond(error_response(id.clone().unwrap_or_default(), METHOD_NOT_FOUND, &format!("{} is not available through this proxy", method)));
+        }
+        let params = match request.get("params") {
+            None | Some(serde_json::Value::Null) => Vec::new(),
+            Some(serde_json::Value::Array(params)) => params.clone(),
+            Some(_) => return respond(error_response(id.clone().unwrap_or_default(), INVALID_PARAMS, "Only positional params are supported")),
+        };
+        match self.client.send_raw(method, params).await {
+            Ok(mut response) => {
+                response["id"] = id.clone().unwrap_or_default();
+                respond(response)
+            }
+            Err(error) => {
+                tracing::warn!(method, %error, "proxied request failed");
+                respond(error_response(id.clone().unwrap_or_default(), INTERNAL_ERROR, "Upstream request failed"))
+            }
+        }
+    }
+
+    // One request or batch per line in, one response per line out. Requests run
+    // concurrently, so responses can come back in a different order; match them by id.
+    pub async fn serve_lines(&self, reader: impl AsyncBufRead + Unpin, mut writer: impl AsyncWrite + Unpin) -> Result<()> {
+        let mut lines = reader.lines();
+        let mut pending = FuturesUnordered::new();
+        let mut open = true;
+        while open || !pending.is_empty() {
+            tokio::select! {
+                line = lines.next_line(), if open => match line? {
+                    Some(line) if line.trim().is_empty() => {}
+                    Some(line) => pending.push(async move { self.handle(line.as_bytes()).await }),
+                    None => open = false,
+                },
+                Some(response) = pending.next(), if !pending.is_empty() => {
+                    if let Some(response) = response {
+                        writer.write_all(format!("{}\n", response).as_bytes()).await?;
+                        writer.flush().await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn serve_stdio(&self) -> Result<()> {
+        self.serve_lines(tokio::io::BufReader::new(tokio::io::stdin()), tokio::io::stdout()).await
+    }
+
+    // JSON-RPC over HTTP POST on any path, until the listener fails. Only READ_METHODS
+    // are forwarded unless an allowlist was set, as HTTP consumers are not trusted to
+    // send transactions or reach the admin and debug APIs. Consumers that run out of
+    // quota get HTTP 429 with a rate-limit error, which RetryLayer and most clients
+    // back off on.
+    pub async fn serve_http(&self, listener: TcpListener) -> Result<()> {
+        listener.set_nonblocking(true)?;
+        let proxy = match self.allowlist {
+            Some(_) => self.clone(),
+            None => self.clone().with_allowlist(READ_METHODS),
+        };
+        let service = make_service_fn(move |_| {
+            let proxy = proxy.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let proxy = proxy.clone();
+                    async move { Ok::<_, Infallible>(proxy.respond(request).await) }
+                }))
+            }
+        });
+        let server = Server::from_tcp(listener).map_err(|error| Error::Config(format!("Failed to listen: {}", error)))?;
+        server.serve(service).await.map_err(|error| Error::Io(std::io::Error::other(error)))
+    }
+
+    async fn respond(&self, request: Request<Body>) -> Response<Body> {
+        #[cfg(feature = "metrics")]
+        if let (Some(registry), &Method::GET, "/metrics") = (&self.registry, request.method(), request.uri().path()) {
+            return match crate::metrics::render(registry) {
+                Ok(text) => Response::new(Body::from(text)),
+                Err(error) => status(StatusCode::INTERNAL_SERVER_ERROR, &error.to_string()),
+            };
+        }
+        if request.method() != Method::POST {
+            return status(StatusCode::METHOD_NOT_ALLOWED, "JSON-RPC requests must be POSTed");
+        }
+        let consumer = if self.consumers.is_empty() {
+            None
+        } else {
+            match api_key(&request).and_then(|key| self.consumers.get(&key_digest(key))) {
+                Some(consumer) => Some(consumer.clone()),
+                None => return status(StatusCode::UNAUTHORIZED, "Missing or unknown API key"),
+            }
+        };
+        let Some(body) = read_body(request.into_body(), MAX_REQUEST_BYTES).await else {
+            return status(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large");
+        };
+        let request = match parse(&body) {
+            Ok(request) => request,
+            Err(response) => return json_response(StatusCode::OK, &response),
+        };
+
+        let response = match consumer {
+            Some(consumer) => {
+                let requests = request.as_array().map_or(1, Vec::len) as u64;
+                if !consumer.take(requests) {
+                    let error = error_response(serde_json::Value::Null, RATE_LIMIT_ERROR_CODES[0], &format!("Quota exceeded for {}", consumer.name));
+                    return json_response(StatusCode::TOO_MANY_REQUESTS, &error);
+                }
+                RequestContext::new(&consumer.name).scope(self.dispatch(request)).await
+            }
+            None => self.dispatch(request).await,
+        };
+        match response {
+            Some(response) => json_response(StatusCode::OK, &response),
+            None => status(StatusCode::NO_CONTENT, ""),
+        }
+    }
+}
+
+pub fn error_response(id: serde_json::Value, code: i64, message: &str) -> serde_json::Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+fn parse(body: &[u8]) -> std::result::Result<serde_json::Value, serde_json::Value> {
+    serde_json::from_slice(body).map_err(|error| error_response(serde_json::Value::Null, PARSE_ERROR, &format!("Parse error: {}", error)))
+}
+
+fn key_digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+fn api_key(request: &Request<Body>) -> Option<&str> {
+    let header = |name: &str| request.headers().get(name).and_then(|value| value.to_str().ok());
+    header("x-api-key").or_else(|| header("authorization").and_then(|value| value.strip_prefix("Bearer ")))
+}
+
+fn json_response(code: StatusCode, response: &serde_json::Value) -> Response<Body> {
+    let mut response = Response::new(Body::from(response.to_string()));
+    *response.status_mut() = code;
+    response.headers_mut().insert("content-type", hyper::header::HeaderValue::from_static("application/json"));
+    response
+}
+
+fn status(code: StatusCode, message: &str) -> Response<Body> {
+    let mut response = Response::new(Body::from(message.to_string()));
+    *response.status_mut() = code;
+    response
+}
+
+// None when the body is over `limit` or the connection fails partway
+async fn read_body(mut body: Body, limit: usize) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        bytes.extend_from_slice(&chunk.ok()?);
+        if bytes.len() > limit {
+            return None;
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Failure, MockNode};
+
+    // Answers eth_blockNumber with 0x10, reverts eth_call and fails everything else
+    fn upstream() -> EthClient {
+        MockNode::new(|method, _| match method {
+            "eth_blockNumber" => Ok(json!("0x10")),
+            "eth_call" => Err(Failure::reverted()),
+            _ => Err(Failure::Transport("connection refused by https://node.example/v2/secret".to_string())),
+        })
+        .client()
+    }
+
+    #[tokio::test]
+    async fn forwards_requests_and_batches() {
+        let proxy = Proxy::new(&upstream());
+        let response = proxy.handle(br#"{"jsonrpc":"2.0","id":"a","method":"eth_blockNumber","params":[]}"#).await.unwrap();
+        assert_eq!(response, json!({"jsonrpc": "2.0", "id": "a", "result": "0x10"}));
+
+        let batch = proxy
+            .handle(br#"[{"jsonrpc":"2.0","id":1,"method":"eth_call","params":[{},"latest"]},{"jsonrpc":"2.0","method":"eth_blockNumber"},{"jsonrpc":"2.0","id":2,"method":"eth_gasPrice"},{"id":3}]"#)
+            .await
+            .unwrap();
+        // The notification gets no response; node errors pass through, transport errors don't leak
+        assert_eq!(batch.as_array().unwrap().len(), 3);
+        assert_eq!(batch[0]["error"]["code"], 3);
+        assert_eq!(batch[1], error_response(json!(2), INTERNAL_ERROR, "Upstream request failed"));
+        assert_eq!(batch[2]["error"]["code"], INVALID_REQUEST);
+
+        assert_eq!(proxy.handle(b"{").await.unwrap()["error"]["code"], PARSE_ERROR);
+        assert!(proxy.handle(br#"{"jsonrpc":"2.0","method":"eth_blockNumber"}"#).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn serves_lines_and_http() {
+        let proxy = Proxy::new(&upstream());
+        let input = "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"eth_blockNumber\"}\n\n{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"eth_blockNumber\"}\n";
+        let mut output = Vec::new();
+        proxy.serve_lines(input.as_bytes(), &mut output).await.unwrap();
+        let mut ids: Vec<u64> = String::from_utf8(output).unwrap().lines().map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["id"].as_u64().unwrap()).collect();
+        ids.sort();
+        assert_eq!(ids, [1, 2]);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { proxy.serve_http(listener).await });
+        let http = reqwest::Client::new();
+        let response: serde_json::Value = http.post(&url).body(r#"{"jsonrpc":"2.0","id":7,"method":"eth_blockNumber"}"#).send().await.unwrap().json().await.unwrap();
+        assert_eq!(response["result"], "0x10");
+        assert_eq!(http.get(&url).send().await.unwrap().status(), reqwest::StatusCode::METHOD_NOT_ALLOWED);
+
+        // Without an allowlist HTTP still only forwards reads
+        let response: serde_json::Value = http.post(&url).body(r#"{"jsonrpc":"2.0","id":8,"method":"eth_sendRawTransaction","params":["0x00"]}"#).send().await.unwrap().json().await.unwrap();
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        let response: serde_json::Value = http.post(&url).body(r#"{"jsonrpc":"2.0","id":9,"method":"debug_traceTransaction","params":["0x00"]}"#).send().await.unwrap().json().await.unwrap();
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn caps_batch_size() {
+        let proxy = Proxy::new(&upstream()).with_max_batch(2);
+        let request = |count: usize| serde_json::Value::Array(vec![json!({"jsonrpc": "2.0", "id": 1, "method": "eth_blockNumber"}); count]).to_string();
+        assert_eq!(proxy.handle(request(2).as_bytes()).await.unwrap().as_array().unwrap().len(), 2);
+        let refused = proxy.handle(request(3).as_bytes()).await.unwrap();
+        assert_eq!(refused["error"]["code"], INVALID_REQUEST);
+        assert!(refused["error"]["message"].as_str().unwrap().contains("limit of 2"));
+    }
+
+    #[tokio::test]
+    async fn restricts_methods_and_meters_consumers() {
+        let consumer = Consumer {
+            name: "dashboard".to_string(),
+            api_key: "key-1".to_string(),
+            quota: Some(Quota { requests: 3, period: Duration::from_secs(3600) }),
+        };
+        let proxy = Proxy::new(&upstream()).with_allowlist(READ_METHODS).with_consumers([consumer]);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { proxy.serve_http(listener).await });
+
+        let http = reqwest::Client::new();
+        let post = |key: Option<&str>, body: &'static str| {
+            let request = http.post(&url).body(body);
+            match key {
+                Some(key) => request.header("authorization", format!("Bearer {}", key)),
+                None => request,
+            }
+        };
+        let block_number = r#"{"jsonrpc":"2.0","id":1,"method":"eth_blockNumber"}"#;
+        assert_eq!(post(None, block_number).send().await.unwrap().status(), reqwest::StatusCode::UNAUTHORIZED);
+        assert_eq!(post(Some("key-2"), block_number).send().await.unwrap().status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let batch: serde_json::Value = post(Some("key-1"), r#"[{"jsonrpc":"2.0","id":1,"method":"eth_blockNumber"},{"jsonrpc":"2.0","id":2,"method":"eth_sendRawTransaction","params":["0x00"]}]"#)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(batch[0]["result"], "0x10");
+        assert_eq!(batch[1]["error"]["code"], METHOD_NOT_FOUND);
+
+        // Two of three requests used; a batch of two no longer fits but one request does
+        let over = post(Some("key-1"), r#"[{"jsonrpc":"2.0","id":1,"method":"eth_blockNumber"},{"jsonrpc":"2.0","id":2,"method":"eth_blockNumber"}]"#).send().await.unwrap();
+        assert_eq!(over.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(post(Some("key-1"), block_number).send().await.unwrap().status(), reqwest::StatusCode::OK);
+        assert_eq!(post(Some("key-1"), block_number).send().await.unwrap().status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    }
+}
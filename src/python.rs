@@ -0,0 +1,296 @@
+// Python bindings, built as the evm_json_rpc extension module with maturin:
+//   maturin develop --features python,pyo3/extension-module
+// Calls block on a shared runtime with the GIL released, so Python threads can run
+// requests concurrently. Integers cross as Python ints, addresses and bytes as 0x hex.
+
+use ethabi::ethereum_types::U256;
+use ethabi::param_type::Reader;
+use ethabi::Token;
+use pyo3::exceptions::{PyException, PyTimeoutError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyList, PyTuple};
+
+use crate::abi::{decode_result, encode_call, encode_call_args};
+use crate::blocks::BlockTag;
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::ffi::runtime;
+use crate::multicall::{self, Call};
+use crate::portfolio::decode_symbol;
+
+pyo3::create_exception!(evm_json_rpc, RpcError, PyException, "Raised for node, transport and decoding failures");
+
+fn py_error(error: Error) -> PyErr {
+    match error {
+        Error::InvalidInput(_) | Error::Abi(_) => PyValueError::new_err(error.to_string()),
+        Error::Timeout(_) => PyTimeoutError::new_err(error.to_string()),
+        error => RpcError::new_err(error.to_string()),
+    }
+}
+
+// Runs a request without holding the GIL
+fn block_on<T: Send>(py: Python<'_>, future: impl std::future::Future<Output = Result<T>> + Send) -> PyResult<T> {
+    py.allow_threads(|| runtime().block_on(future)).map_err(py_error)
+}
+
+fn to_int(py: Python<'_>, value: U256) -> PyResult<PyObject> {
+    Ok(py.import("builtins")?.getattr("int")?.call1((value.to_string(),))?.unbind())
+}
+
+fn to_u256(value: &Bound<'_, PyAny>) -> PyResult<U256> {
+    let text = value.str()?.to_string();
+    U256::from_dec_str(&text).map_err(|_| PyValueError::new_err(format!("{} is not an unsigned 256-bit integer", text)))
+}
+
+// Converts through Python's json module, which keeps big integers exact
+fn to_py(py: Python<'_>, value: &serde_json::Value) -> PyResult<PyObject> {
+    Ok(py.import("json")?.call_method1("loads", (value.to_string(),))?.unbind())
+}
+
+fn from_py(value: &Bound<'_, PyAny>) -> PyResult<serde_json::Value> {
+    let text: String = value.py().import("json")?.call_method1("dumps", (value,))?.extract()?;
+    serde_json::from_str(&text).map_err(|error| PyValueError::new_err(error.to_string()))
+}
+
+fn token_to_py(py: Python<'_>, token: &Token) -> PyResult<PyObject> {
+    Ok(match token {
+        Token::Address(address) => crate::format::address(&format!("{:?}", address)).into_pyobject(py)?.into_any().unbind(),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => format!("0x{}", hex::encode(bytes)).into_pyobject(py)?.into_any().unbind(),
+        Token::Uint(value) => to_int(py, *value)?,
+        // Two's complement, as ethabi stores it
+        Token::Int(value) if value.bit(255) => {
+            let magnitude = to_int(py, (!*value).overflowing_add(U256::one()).0)?;
+            magnitude.call_method0(py, "__neg__")?
+        }
+        Token::Int(value) => to_int(py, *value)?,
+        Token::Bool(value) => value.into_pyobject(py)?.to_owned().into_any().unbind(),
+        Token::String(value) => value.into_pyobject(py)?.into_any().unbind(),
+        Token::Array(tokens) | Token::FixedArray(tokens) => PyList::new(py, tokens.iter().map(|token| token_to_py(py, token)).collect::<PyResult<Vec<_>>>()?)?.into_any().unbind(),
+        Token::Tuple(tokens) => PyTuple::new(py, tokens.iter().map(|token| token_to_py(py, token)).collect::<PyResult<Vec<_>>>()?)?.into_any().unbind(),
+    })
+}
+
+// Calldata for a signature such as "transfer(address,uint256)" with the arguments as
+// text: decimal or 0x numbers, 0x addresses and bytes, [a,b] arrays
+#[pyfunction]
+#[pyo3(name = "encode_call")]
+fn py_encode_call(signature: &str, args: Vec<String>) -> PyResult<String> {
+    encode_call_args(signature, &args.iter().map(String::as_str).collect::<Vec<_>>()).map_err(py_error)
+}
+
+// Decodes return data into a list of values, e.g. decode_result(["uint256", "address"], data)
+#[pyfunction]
+#[pyo3(name = "decode_result")]
+fn py_decode_result(py: Python<'_>, types: Vec<String>, data: &str) -> PyResult<Vec<PyObject>> {
+    let types = types.iter().map(|kind| Reader::read(kind).map_err(|_| PyValueError::new_err(format!("Invalid ABI type {}", kind)))).collect::<PyResult<Vec<_>>>()?;
+    let tokens = decode_result(data, &types).map_err(py_error)?;
+    tokens.iter().map(|token| token_to_py(py, token)).collect()
+}
+
+#[pyclass(name = "Client", module = "evm_json_rpc")]
+#[derive(Clone)]
+struct PyClient {
+    inner: EthClient,
+}
+
+#[pymethods]
+impl PyClient {
+    #[new]
+    fn new(rpc_url: &str) -> Self {
+        PyClient { inner: EthClient::new(rpc_url) }
+    }
+
+    // Any JSON-RPC method; params and the result are plain Python values
+    #[pyo3(signature = (method, params = None))]
+    fn send(&self, py: Python<'_>, method: &str, params: Option<&Bound<'_, PyList>>) -> PyResult<PyObject> {
+        let params = match params {
+            Some(params) => params.iter().map(|param| from_py(&param)).collect::<PyResult<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        let result = block_on(py, self.inner.send(method, params))?;
+        to_py(py, &result)
+    }
+
+    // eth_call against the latest block, following CCIP-Read lookups
+    fn call(&self, py: Python<'_>, to: &str, data: &str) -> PyResult<String> {
+        block_on(py, self.inner.call(to, data))
+    }
+
+    fn block_number(&self, py: Python<'_>) -> PyResult<u64> {
+        block_on(py, self.inner.get_block_number())
+    }
+
+    fn chain_id(&self, py: Python<'_>) -> PyResult<u64> {
+        block_on(py, self.inner.chain_id())
+    }
+
+    // `block` is a tag such as "latest" or "finalized", or a block number
+    #[pyo3(signature = (address, block = "latest"))]
+    fn get_balance(&self, py: Python<'_>, address: &str, block: &str) -> PyResult<PyObject> {
+        let block: BlockTag = block.parse().map_err(py_error)?;
+        to_int(py, block_on(py, self.inner.get_balance(address, block))?)
+    }
+
+    // (target, calldata) pairs run through Multicall3 in as few eth_calls as possible;
+    // each result is the return data, or None where the call reverted
+    fn multicall(&self, py: Python<'_>, calls: Vec<(String, String)>) -> PyResult<Vec<Option<String>>> {
+        let calls: Vec<Call> = calls.iter().map(|(target, data)| Call::new(target, data)).collect();
+        block_on(py, multicall::aggregate(&self.inner, &calls))
+    }
+}
+
+// Reads one uint256 from each call's result in a single multicall; None where it reverted
+fn multicall_uints(py: Python<'_>, client: &EthClient, calls: Vec<Call>) -> PyResult<Vec<Option<PyObject>>> {
+    let results = block_on(py, multicall::aggregate(client, &calls))?;
+    results
+        .iter()
+        .map(|result| match result.as_deref().map(|result| decode_result(result, &[ethabi::ParamType::Uint(256)])) {
+            Some(Ok(tokens)) => match tokens.first() {
+                Some(Token::Uint(value)) => Ok(Some(to_int(py, *value)?)),
+                _ => Ok(None),
+            },
+            _ => Ok(None),
+        })
+        .collect()
+}
+
+fn call_uint(py: Python<'_>, client: &EthClient, to: &str, data: &str) -> PyResult<PyObject> {
+    let result = block_on(py, client.call(to, data))?;
+    match decode_result(&result, &[ethabi::ParamType::Uint(256)]).map_err(py_error)?.pop() {
+        Some(Token::Uint(value)) => to_int(py, value),
+        _ => Err(RpcError::new_err("Expected a uint256 result")),
+    }
+}
+
+fn parse_address(address: &str) -> PyResult<Token> {
+    let parsed = address.trim_start_matches("0x").parse().map_err(|_| PyValueError::new_err(format!("Invalid address {}", address)))?;
+    Ok(Token::Address(parsed))
+}
+
+#[pyclass(module = "evm_json_rpc")]
+struct Erc20 {
+    client: EthClient,
+    #[pyo3(get)]
+    address: String,
+}
+
+#[pymethods]
+impl Erc20 {
+    #[new]
+    fn new(client: &PyClient, address: &str) -> Self {
+        Erc20 {
+            client: client.inner.clone(),
+            address: address.to_string(),
+        }
+    }
+
+    fn name(&self, py: Python<'_>) -> PyResult<String> {
+        Ok(decode_symbol(&block_on(py, self.client.call(&self.address, &encode_call("name()", &[])))?))
+    }
+
+    // Also reads the bytes32 symbols of early tokens such as MKR
+    fn symbol(&self, py: Python<'_>) -> PyResult<String> {
+        Ok(decode_symbol(&block_on(py, self.client.call(&self.address, &encode_call("symbol()", &[])))?))
+    }
+
+    fn decimals(&self, py: Python<'_>) -> PyResult<u64> {
+        block_on(py, self.client.call_uint(&self.address, "decimals()", vec![]))
+    }
+
+    fn total_supply(&self, py: Python<'_>) -> PyResult<PyObject> {
+        call_uint(py, &self.client, &self.address, &encode_call("totalSupply()", &[]))
+    }
+
+    fn balance_of(&self, py: Python<'_>, owner: &str) -> PyResult<PyObject> {
+        call_uint(py, &self.client, &self.address, &encode_call("balanceOf(address)", &[parse_address(owner)?]))
+    }
+
+    fn allowance(&self, py: Python<'_>, owner: &str, spender: &str) -> PyResult<PyObject> {
+        call_uint(py, &self.client, &self.address, &encode_call("allowance(address,address)", &[parse_address(owner)?, parse_address(spender)?]))
+    }
+
+    // Balances of many holders through Multicall3, in the order given
+    fn balances_of(&self, py: Python<'_>, owners: Vec<String>) -> PyResult<Vec<Option<PyObject>>> {
+        let calls = owners.iter().map(|owner| Ok(Call::new(&self.address, &encode_call("balanceOf(address)", &[parse_address(owner)?])))).collect::<PyResult<Vec<_>>>()?;
+        multicall_uints(py, &self.client, calls)
+    }
+}
+
+#[pyclass(module = "evm_json_rpc")]
+struct Erc721 {
+    client: EthClient,
+    #[pyo3(get)]
+    address: String,
+}
+
+#[pymethods]
+impl Erc721 {
+    #[new]
+    fn new(client: &PyClient, address: &str) -> Self {
+        Erc721 {
+            client: client.inner.clone(),
+            address: address.to_string(),
+        }
+    }
+
+    fn name(&self, py: Python<'_>) -> PyResult<String> {
+        block_on(py, self.client.call_string(&self.address, "name()", vec![]))
+    }
+
+    fn symbol(&self, py: Python<'_>) -> PyResult<String> {
+        block_on(py, self.client.call_string(&self.address, "symbol()", vec![]))
+    }
+
+    fn balance_of(&self, py: Python<'_>, owner: &str) -> PyResult<PyObject> {
+        call_uint(py, &self.client, &self.address, &encode_call("balanceOf(address)", &[parse_address(owner)?]))
+    }
+
+    fn owner_of(&self, py: Python<'_>, token_id: &Bound<'_, PyAny>) -> PyResult<String> {
+        let result = block_on(py, self.client.call(&self.address, &encode_call("ownerOf(uint256)", &[Token::Uint(to_u256(token_id)?)])))?;
+        crate::abi::decode_address(&result).map_err(py_error)
+    }
+
+    fn token_uri(&self, py: Python<'_>, token_id: &Bound<'_, PyAny>) -> PyResult<String> {
+        let result = block_on(py, self.client.call(&self.address, &encode_call("tokenURI(uint256)", &[Token::Uint(to_u256(token_id)?)])))?;
+        crate::abi::decode_string(&result).map_err(py_error)
+    }
+
+    // Owners of many tokens through Multicall3, in the order given; None for tokens
+    // that don't exist
+    fn owners_of(&self, py: Python<'_>, token_ids: Vec<Bound<'_, PyAny>>) -> PyResult<Vec<Option<String>>> {
+        let calls = token_ids.iter().map(|id| Ok(Call::new(&self.address, &encode_call("ownerOf(uint256)", &[Token::Uint(to_u256(id)?)])))).collect::<PyResult<Vec<_>>>()?;
+        let results = block_on(py, multicall::aggregate(&self.client, &calls))?;
+        Ok(results.iter().map(|result| result.as_deref().and_then(|result| crate::abi::decode_address(result).ok())).collect())
+    }
+}
+
+#[pymodule]
+fn evm_json_rpc(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyClient>()?;
+    module.add_class::<Erc20>()?;
+    module.add_class::<Erc721>()?;
+    module.add_function(wrap_pyfunction!(py_encode_call, module)?)?;
+    module.add_function(wrap_pyfunction!(py_decode_result, module)?)?;
+    module.add("RpcError", module.py().get_type::<RpcError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_results_into_python_values() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let data = format!("0x{}", hex::encode(ethabi::encode(&[Token::Int(U256::MAX), Token::Uint(U256::MAX), Token::Array(vec![Token::Bool(true)])])));
+            let values = py_decode_result(py, vec!["int256".to_string(), "uint256".to_string(), "bool[]".to_string()], &data).unwrap();
+            let values: Vec<String> = values.iter().map(|value| value.bind(py).repr().unwrap().to_string()).collect();
+            assert_eq!(values, ["-1", &U256::MAX.to_string(), "[True]"]);
+
+            let calldata = py_encode_call("transfer(address,uint256)", vec!["0x1111111111111111111111111111111111111111".to_string(), "5".to_string()]).unwrap();
+            assert!(calldata.starts_with("0xa9059cbb"));
+            assert!(py_encode_call("transfer(address,uint256)", vec![]).unwrap_err().is_instance_of::<PyValueError>(py));
+        });
+    }
+}
@@ -0,0 +1,26 @@
+use std::path::Path;
+
+use image::Luma;
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+
+use crate::eip681::PaymentRequest;
+use crate::error::{Error, Result};
+
+// Wallets scan addresses as bare EIP-681 payment URIs
+pub fn address_payload(address: &str, chain_id: Option<u64>) -> String {
+    PaymentRequest { target: address.to_string(), chain_id, ..Default::default() }.to_string()
+}
+
+// Two modules per character cell so the code stays square in most terminal fonts.
+// Colors are inverted for dark backgrounds, where scanners expect a light border.
+pub fn render_terminal(payload: &str) -> Result<String> {
+    let code = QrCode::new(payload.as_bytes()).map_err(|error| Error::InvalidInput(format!("Payload doesn't fit in a QR code: {}", error)))?;
+    Ok(code.render::<Dense1x2>().dark_color(Dense1x2::Light).light_color(Dense1x2::Dark).build())
+}
+
+pub fn save_png(payload: &str, path: impl AsRef<Path>) -> Result<()> {
+    let code = QrCode::new(payload.as_bytes()).map_err(|error| Error::InvalidInput(format!("Payload doesn't fit in a QR code: {}", error)))?;
+    code.render::<Luma<u8>>().min_dimensions(256, 256).build().save(path).map_err(std::io::Error::other)?;
+    Ok(())
+}
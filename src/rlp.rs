@@ -0,0 +1,435 @@
+use ethabi::ethereum_types::{H160, H256, U256};
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    Bytes(Vec<u8>),
+    List(Vec<Item>),
+}
+
+impl Item {
+    pub fn as_bytes(&self) -> Result<&[u8]> {
+        match self {
+            Item::Bytes(bytes) => Ok(bytes),
+            Item::List(_) => Err(Error::Decode("Expected RLP bytes, found list".to_string())),
+        }
+    }
+
+    pub fn as_list(&self) -> Result<&[Item]> {
+        match self {
+            Item::List(items) => Ok(items),
+            Item::Bytes(_) => Err(Error::Decode("Expected RLP list, found bytes".to_string())),
+        }
+    }
+}
+
+pub trait Encodable {
+    fn rlp_append(&self, out: &mut Vec<u8>);
+}
+
+pub trait Decodable: Sized {
+    fn rlp_decode(item: &Item) -> Result<Self>;
+}
+
+pub fn encode<T: Encodable + ?Sized>(value: &T) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.rlp_append(&mut out);
+    out
+}
+
+pub fn decode<T: Decodable>(bytes: &[u8]) -> Result<T> {
+    T::rlp_decode(&decode_item(bytes)?)
+}
+
+// Decodes exactly one item spanning the whole input
+pub fn decode_item(bytes: &[u8]) -> Result<Item> {
+    let (item, consumed) = decode_prefix(bytes)?;
+    if consumed != bytes.len() {
+        return Err(Error::Decode("Trailing bytes after RLP item".to_string()));
+    }
+    Ok(item)
+}
+
+// Deepest list nesting accepted, so crafted input can't exhaust the stack
+pub const MAX_DEPTH: usize = 128;
+
+// Decodes the first item of the input and returns it with the number of bytes it used
+pub fn decode_prefix(bytes: &[u8]) -> Result<(Item, usize)> {
+    decode_nested(bytes, 0)
+}
+
+fn decode_nested(bytes: &[u8], depth: usize) -> Result<(Item, usize)> {
+    let first = *bytes.first().ok_or_else(|| Error::Decode("Empty RLP input".to_string()))?;
+    match first {
+        0x00..=0x7f => Ok((Item::Bytes(vec![first]), 1)),
+        0x80..=0xbf => {
+            let (offset, length) = read_length(bytes, 0x80)?;
+            let data = &bytes[offset..offset + length];
+            if length == 1 && data[0] < 0x80 {
+                return Err(Error::Decode("Non-canonical RLP: single byte should encode as itself".to_string()));
+            }
+            Ok((Item::Bytes(data.to_vec()), offset + length))
+        }
+        0xc0..=0xff => {
+            if depth >= MAX_DEPTH {
+                return Err(Error::Decode(format!("RLP lists nested deeper than {}", MAX_DEPTH)));
+            }
+            let (offset, length) = read_length(bytes, 0xc0)?;
+            let mut payload = &bytes[offset..offset + length];
+            let mut items = Vec::new();
+            while !payload.is_empty() {
+                let (item, consumed) = decode_nested(payload, depth + 1)?;
+                items.push(item);
+                payload = &payload[consumed..];
+            }
+            Ok((Item::List(items), offset + length))
+        }
+    }
+}
+
+// Returns (header length, payload length) for a string (0x80) or list (0xc0) prefix
+fn read_length(bytes: &[u8], base: u8) -> Result<(usize, usize)> {
+    let prefix = bytes[0] - base;
+    let (offset, length) = if prefix < 56 {
+        (1, prefix as usize)
+    } else {
+        let length_of_length = (prefix - 55) as usize;
+        let length_bytes = bytes.get(1..1 + length_of_length).ok_or_else(|| Error::Decode("Truncated RLP length".to_string()))?;
+        if length_bytes[0] == 0 || length_of_length > std::mem::size_of::<usize>() {
+            return Err(Error::Decode("Non-canonical RLP length".to_string()));
+        }
+        let length = length_bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        if length < 56 {
+            return Err(Error::Decode("Non-canonical RLP: long form used for short payload".to_string()));
+        }
+        (1 + length_of_length, length)
+    };
+    if bytes.len() - offset < length {
+        return Err(Error::Decode("Truncated RLP payload".to_string()));
+    }
+    Ok((offset, length))
+}
+
+fn append_header(base: u8, length: usize, out: &mut Vec<u8>) {
+    if length < 56 {
+        out.push(base + length as u8);
+    } else {
+        let length_bytes = trim_leading_zeros(&length.to_be_bytes()).to_vec();
+        out.push(base + 55 + length_bytes.len() as u8);
+        out.extend_from_slice(&length_bytes);
+    }
+}
+
+pub fn append_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        out.push(bytes[0]);
+    } else {
+        append_header(0x80, bytes.len(), out);
+        out.extend_from_slice(bytes);
+    }
+}
+
+// Wraps already-encoded items in a list header
+pub fn append_list_payload(payload: &[u8], out: &mut Vec<u8>) {
+    append_header(0xc0, payload.len(), out);
+    out.extend_from_slice(payload);
+}
+
+fn trim_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+// Integers are encoded big-endian with no leading zeros, so zero is the empty string
+fn decode_uint_bytes(item: &Item, max_length: usize) -> Result<&[u8]> {
+    let bytes = item.as_bytes()?;
+    if bytes.len() > max_length {
+        return Err(Error::Decode("RLP integer overflow".to_string()));
+    }
+    if bytes.first() == Some(&0) {
+        return Err(Error::Decode("Non-canonical RLP integer with leading zeros".to_string()));
+    }
+    Ok(bytes)
+}
+
+macro_rules! impl_uint {
+    ($($ty:ty),*) => {
+        $(
+            impl Encodable for $ty {
+                fn rlp_append(&self, out: &mut Vec<u8>) {
+                    append_bytes(trim_leading_zeros(&self.to_be_bytes()), out);
+                }
+            }
+
+            impl Decodable for $ty {
+                fn rlp_decode(item: &Item) -> Result<Self> {
+                    let bytes = decode_uint_bytes(item, std::mem::size_of::<$ty>())?;
+                    Ok(bytes.iter().fold(0, |acc, b| (acc << 8) | *b as $ty))
+                }
+            }
+        )*
+    };
+}
+
+impl_uint!(u16, u32, u64, u128, usize);
+
+impl Encodable for bool {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        (*self as u64).rlp_append(out);
+    }
+}
+
+impl Decodable for bool {
+    fn rlp_decode(item: &Item) -> Result<Self> {
+        match u64::rlp_decode(item)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::Decode("Invalid RLP boolean".to_string())),
+        }
+    }
+}
+
+impl Encodable for U256 {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let mut bytes = [0u8; 32];
+        self.to_big_endian(&mut bytes);
+        append_bytes(trim_leading_zeros(&bytes), out);
+    }
+}
+
+impl Decodable for U256 {
+    fn rlp_decode(item: &Item) -> Result<Self> {
+        Ok(U256::from_big_endian(decode_uint_bytes(item, 32)?))
+    }
+}
+
+impl Encodable for H160 {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        append_bytes(self.as_bytes(), out);
+    }
+}
+
+impl Decodable for H160 {
+    fn rlp_decode(item: &Item) -> Result<Self> {
+        let bytes = item.as_bytes()?;
+        if bytes.len() != 20 {
+            return Err(Error::Decode("Expected 20-byte RLP address".to_string()));
+        }
+        Ok(H160::from_slice(bytes))
+    }
+}
+
+// Transaction recipients, where the empty string means contract creation
+impl Encodable for Option<H160> {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        match self {
+            Some(address) => address.rlp_append(out),
+            None => append_bytes(&[], out),
+        }
+    }
+}
+
+impl Decodable for Option<H160> {
+    fn rlp_decode(item: &Item) -> Result<Self> {
+        if item.as_bytes()?.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(H160::rlp_decode(item)?))
+    }
+}
+
+impl Encodable for H256 {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        append_bytes(self.as_bytes(), out);
+    }
+}
+
+impl Decodable for H256 {
+    fn rlp_decode(item: &Item) -> Result<Self> {
+        let bytes = item.as_bytes()?;
+        if bytes.len() != 32 {
+            return Err(Error::Decode("Expected 32-byte RLP hash".to_string()));
+        }
+        Ok(H256::from_slice(bytes))
+    }
+}
+
+impl Encodable for [u8] {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        append_bytes(self, out);
+    }
+}
+
+impl Encodable for Vec<u8> {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        append_bytes(self, out);
+    }
+}
+
+impl Decodable for Vec<u8> {
+    fn rlp_decode(item: &Item) -> Result<Self> {
+        Ok(item.as_bytes()?.to_vec())
+    }
+}
+
+impl Encodable for str {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        append_bytes(self.as_bytes(), out);
+    }
+}
+
+impl Encodable for String {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        append_bytes(self.as_bytes(), out);
+    }
+}
+
+impl Decodable for String {
+    fn rlp_decode(item: &Item) -> Result<Self> {
+        String::from_utf8(item.as_bytes()?.to_vec()).map_err(|_| Error::Decode("RLP string is not valid UTF-8".to_string()))
+    }
+}
+
+impl<T: Encodable> Encodable for [T] {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        for value in self {
+            value.rlp_append(&mut payload);
+        }
+        append_list_payload(&payload, out);
+    }
+}
+
+impl<T: Encodable> Encodable for Vec<T> {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        self.as_slice().rlp_append(out);
+    }
+}
+
+impl<T: Decodable> Decodable for Vec<T> {
+    fn rlp_decode(item: &Item) -> Result<Self> {
+        item.as_list()?.iter().map(T::rlp_decode).collect()
+    }
+}
+
+impl<T: Encodable + ?Sized> Encodable for &T {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        (**self).rlp_append(out);
+    }
+}
+
+impl Encodable for Item {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        match self {
+            Item::Bytes(bytes) => append_bytes(bytes, out),
+            Item::List(items) => items.rlp_append(out),
+        }
+    }
+}
+
+impl Decodable for Item {
+    fn rlp_decode(item: &Item) -> Result<Self> {
+        Ok(item.clone())
+    }
+}
+
+// Implements Encodable and Decodable for a struct as an RLP list of the given fields, in order:
+// impl_rlp!(Header { parent_hash, number, gas_limit });
+#[macro_export]
+macro_rules! impl_rlp {
+    ($name:ident { $($field:ident),* $(,)? }) => {
+        impl $crate::rlp::Encodable for $name {
+            fn rlp_append(&self, out: &mut Vec<u8>) {
+                let mut payload = Vec::new();
+                $( $crate::rlp::Encodable::rlp_append(&self.$field, &mut payload); )*
+                $crate::rlp::append_list_payload(&payload, out);
+            }
+        }
+
+        impl $crate::rlp::Decodable for $name {
+            fn rlp_decode(item: &$crate::rlp::Item) -> $crate::error::Result<Self> {
+                let mut items = item.as_list()?.iter();
+                let value = $name {
+                    $( $field: $crate::rlp::Decodable::rlp_decode(
+                        items.next().ok_or_else(|| $crate::error::Error::Decode(concat!("RLP list is missing field ", stringify!($field)).to_string()))?,
+                    )?, )*
+                };
+                if items.next().is_some() {
+                    return Err($crate::error::Error::Decode(concat!("RLP list has extra fields for ", stringify!($name)).to_string()));
+                }
+                Ok(value)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(hex: &str) -> Vec<u8> {
+        hex::decode(hex).unwrap()
+    }
+
+    // Examples from the RLP section of the Ethereum yellow paper and wiki
+    #[test]
+    fn encodes_spec_examples() {
+        assert_eq!(encode("dog"), bytes("83646f67"));
+        assert_eq!(encode(&vec!["cat", "dog"]), bytes("c88363617483646f67"));
+        assert_eq!(encode(""), bytes("80"));
+        assert_eq!(encode(&Vec::<Vec<u8>>::new()), bytes("c0"));
+        assert_eq!(encode(&0u64), bytes("80"));
+        assert_eq!(encode(&[0u8][..]), bytes("00"));
+        assert_eq!(encode(&15u64), bytes("0f"));
+        assert_eq!(encode(&1024u64), bytes("820400"));
+        let lorem = "Lorem ipsum dolor sit amet, consectetur adipisicing elit";
+        assert_eq!(encode(lorem), [bytes("b838"), lorem.as_bytes().to_vec()].concat());
+    }
+
+    // [ [], [[]], [ [], [[]] ] ]
+    #[test]
+    fn round_trips_set_theoretic_representation_of_three() {
+        let empty = Item::List(vec![]);
+        let one = Item::List(vec![empty.clone()]);
+        let three = Item::List(vec![empty.clone(), one.clone(), Item::List(vec![empty, one])]);
+        assert_eq!(encode(&three), bytes("c7c0c1c0c3c0c1c0"));
+        assert_eq!(decode_item(&bytes("c7c0c1c0c3c0c1c0")).unwrap(), three);
+    }
+
+    #[test]
+    fn decodes_values() {
+        assert_eq!(decode::<String>(&bytes("83646f67")).unwrap(), "dog");
+        assert_eq!(decode::<Vec<String>>(&bytes("c88363617483646f67")).unwrap(), vec!["cat", "dog"]);
+        assert_eq!(decode::<u64>(&bytes("820400")).unwrap(), 1024);
+        assert_eq!(decode::<u64>(&bytes("80")).unwrap(), 0);
+        let value = U256::from_dec_str("115792089237316195423570985008687907853269984665640564039457584007913129639935").unwrap();
+        assert_eq!(decode::<U256>(&encode(&value)).unwrap(), value);
+    }
+
+    #[test]
+    fn rejects_non_canonical_and_malformed_input() {
+        // Single byte below 0x80 wrapped in a string header
+        assert!(decode_item(&bytes("8105")).is_err());
+        // Long form for a 3-byte payload
+        assert!(decode_item(&bytes("b803646f67")).is_err());
+        // Integer with a leading zero
+        assert!(decode::<u64>(&bytes("820004")).is_err());
+        assert!(decode_item(&bytes("83646f")).is_err());
+        assert!(decode_item(&bytes("83646f6767")).is_err());
+        assert!(decode_item(&[]).is_err());
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        let nested = |depth: usize| {
+            let mut item = Item::List(vec![]);
+            for _ in 0..depth {
+                item = Item::List(vec![item]);
+            }
+            encode(&item)
+        };
+        assert!(decode_item(&nested(MAX_DEPTH - 1)).is_ok());
+        assert!(decode_item(&nested(MAX_DEPTH)).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+
+use futures_util::future::{join_all, try_join_all};
+
+use crate::client::EthClient;
+use crate::config::Config;
+use crate::error::{Error, Result};
+
+struct Chain {
+    name: String,
+    client: EthClient,
+}
+
+// Clients for several chains behind one handle, looked up by chain id or name. Each
+// client may itself sit on a FallbackProvider or QuorumProvider.
+#[derive(Default)]
+pub struct ChainRouter {
+    chains: BTreeMap<u64, Chain>,
+}
+
+impl ChainRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replaces any client already registered for the chain id
+    pub fn with_chain(mut self, chain_id: u64, name: &str, client: EthClient) -> Self {
+        self.insert(chain_id, name, client);
+        self
+    }
+
+    pub fn insert(&mut self, chain_id: u64, name: &str, client: EthClient) {
+        self.chains.insert(chain_id, Chain {
+            name: name.to_string(),
+            client,
+        });
+    }
+
+    // One chain per profile with a URL, named after the profile. Profiles with a
+    // chain_id are verified against their endpoint; the others are asked for theirs.
+    pub async fn from_config(config: &Config) -> Result<Self> {
+        let connections = config.profiles.iter().filter(|(_, profile)| profile.url.is_some()).map(|(name, profile)| async move {
+            let client = profile.connect().await?;
+            let chain_id = match profile.chain_id {
+                Some(chain_id) => chain_id,
+                None => client.chain_id().await?,
+            };
+            Ok::<_, Error>((chain_id, name, client))
+        });
+        let mut router = ChainRouter::new();
+        for (chain_id, name, client) in try_join_all(connections).await? {
+            if let Some(existing) = router.chains.get(&chain_id) {
+                return Err(Error::Config(format!("Profiles {} and {} both serve chain {}", existing.name, name, chain_id)));
+            }
+            router.insert(chain_id, name, client);
+        }
+        Ok(router)
+    }
+
+    pub fn client(&self, chain_id: u64) -> Option<&EthClient> {
+        self.chains.get(&chain_id).map(|chain| &chain.client)
+    }
+
+    // Accepts a chain name or a decimal chain id
+    pub fn get(&self, chain: &str) -> Result<&EthClient> {
+        let by_name = self.chains.values().find(|entry| entry.name == chain);
+        let by_id = || chain.parse().ok().and_then(|chain_id| self.chains.get(&chain_id));
+        by_name.or_else(by_id).map(|entry| &entry.client).ok_or_else(|| Error::Config(format!("Unknown chain {}", chain)))
+    }
+
+    pub fn name(&self, chain_id: u64) -> Option<&str> {
+        self.chains.get(&chain_id).map(|chain| chain.name.as_str())
+    }
+
+    // (chain id, name, client), ordered by chain id
+    pub fn chains(&self) -> impl Iterator<Item = (u64, &str, &EthClient)> {
+        self.chains.iter().map(|(chain_id, chain)| (*chain_id, chain.name.as_str(), &chain.client))
+    }
+
+    pub fn len(&self) -> usize {
+        self.chains.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chains.is_empty()
+    }
+
+    // Runs `query` against every chain concurrently. One chain failing doesn't fail
+    // the others; results come back ordered by chain id.
+    pub async fn query_all<T, F, Fut>(&self, query: F) -> Vec<(u64, Result<T>)>
+    where
+        F: Fn(u64, EthClient) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let queries = self.chains.iter().map(|(chain_id, chain)| {
+            let query = query(*chain_id, chain.client.clone());
+            async move { (*chain_id, query.await) }
+        });
+        join_all(queries).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{Failure, MockNode};
+
+    // Answers eth_chainId, and eth_blockNumber when the head is known
+    fn endpoint(chain_id: u64, head: u64) -> EthClient {
+        MockNode::new(move |method, _| match method {
+            "eth_chainId" => Ok(json!(format!("0x{:x}", chain_id))),
+            "eth_blockNumber" if head > 0 => Ok(json!(format!("0x{:x}", head))),
+            _ => Err(Failure::Transport("unavailable".to_string())),
+        })
+        .client()
+    }
+
+    #[tokio::test]
+    async fn routes_by_id_and_name_and_queries_every_chain() {
+        let router = ChainRouter::new()
+            .with_chain(10, "optimism", endpoint(10, 120))
+            .with_chain(1, "mainnet", endpoint(1, 20))
+            .with_chain(8453, "base", endpoint(8453, 0));
+
+        assert_eq!(router.get("optimism").unwrap().chain_id().await.unwrap(), 10);
+        assert_eq!(router.get("8453").unwrap().chain_id().await.unwrap(), 8453);
+        assert!(router.get("arbitrum").is_err());
+        assert_eq!(router.name(1), Some("mainnet"));
+
+        let heights = router.query_all(|_, client| async move { client.get_block_number().await }).await;
+        let heights: Vec<(u64, Option<u64>)> = heights.into_iter().map(|(chain_id, height)| (chain_id, height.ok())).collect();
+        assert_eq!(heights, vec![(1, Some(20)), (10, Some(120)), (8453, None)]);
+    }
+}
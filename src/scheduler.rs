@@ -0,0 +1,239 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ethabi::ethereum_types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::headers::{get_header, Header};
+use crate::transaction::TypedTransaction;
+
+// When a scheduled transaction becomes due, checked against the latest block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    // Once the chain reaches this block number
+    AtBlock(u64),
+    // Once a block's timestamp (unix seconds) reaches this
+    AtTimestamp(u64),
+    // Once the latest base fee, in wei, drops below this
+    BaseFeeBelow(U256),
+}
+
+impl Condition {
+    pub fn is_met(&self, header: &Header) -> bool {
+        match self {
+            Condition::AtBlock(number) => header.number >= *number,
+            Condition::AtTimestamp(timestamp) => header.timestamp >= *timestamp,
+            Condition::BaseFeeBelow(threshold) => header.base_fee.is_some_and(|base_fee| base_fee < *threshold),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    // Signed transaction, as passed to eth_sendRawTransaction
+    pub raw_transaction: String,
+    pub condition: Condition,
+}
+
+#[derive(Debug)]
+pub struct Submission {
+    pub job: Job,
+    // Transaction hash, or the node's reason for rejecting it
+    pub result: Result<String, String>,
+}
+
+// Holds signed transactions until their condition is met, then broadcasts them. With a
+// path every change is written to disk, so jobs survive restarts. Rejected transactions
+// (nonce too low, underpriced, ...) are reported and dropped; when the node can't be
+// reached the job stays and is retried on the next tick.
+pub struct Scheduler {
+    path: Option<PathBuf>,
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    // In-memory scheduler
+    pub fn new() -> Self {
+        Scheduler {
+            path: None,
+            jobs: Vec::new(),
+        }
+    }
+
+    // Loads the jobs saved at `path`; a missing file starts empty
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let jobs = match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|error| Error::Decode(format!("Invalid scheduler file {}: {}", path.display(), error)))?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(std::io::Error::new(error.kind(), format!("Failed to read {}: {}", path.display(), error)).into()),
+        };
+        Ok(Scheduler {
+            path: Some(path.to_path_buf()),
+            jobs,
+        })
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    // Returns the job id
+    pub fn schedule(&mut self, raw_transaction: &str, condition: Condition) -> Result<u64> {
+        TypedTransaction::from_hex(raw_transaction)?;
+        let id = self.jobs.iter().map(|job| job.id + 1).max().unwrap_or(1);
+        self.jobs.push(Job {
+            id,
+            raw_transaction: format!("0x{}", raw_transaction.trim_start_matches("0x")),
+            condition,
+        });
+        self.save()?;
+        Ok(id)
+    }
+
+    // Returns false if no job has the id
+    pub fn cancel(&mut self, id: u64) -> Result<bool> {
+        let count = self.jobs.len();
+        self.jobs.retain(|job| job.id != id);
+        if self.jobs.len() == count {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    // Checks every job against the latest block and submits the due ones
+    pub async fn tick(&mut self, client: &EthClient) -> Result<Vec<Submission>> {
+        if self.jobs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let header = get_header(client, "latest").await?;
+
+        let mut submissions = Vec::new();
+        let mut pending = Vec::new();
+        for job in std::mem::take(&mut self.jobs) {
+            if !job.condition.is_met(&header) {
+                pending.push(job);
+                continue;
+            }
+            match client.send("eth_sendRawTransaction", vec![serde_json::json!(job.raw_transaction)]).await {
+                Ok(hash) => {
+                    let result = hash.as_str().map(str::to_string).ok_or_else(|| format!("Invalid eth_sendRawTransaction response: {}", hash));
+                    submissions.push(Submission { job, result });
+                }
+                Err(Error::Rpc(error)) => submissions.push(Submission { job, result: Err(error.to_string()) }),
+                Err(_) => pending.push(job),
+            }
+        }
+        self.jobs = pending;
+        if !submissions.is_empty() {
+            self.save()?;
+        }
+        Ok(submissions)
+    }
+
+    // Ticks until no jobs are left, handing each submission to `on_submission`. Failing
+    // to reach the node is logged and retried on the next tick; only failing to save
+    // the jobs stops the loop.
+    pub async fn run(&mut self, client: &EthClient, interval: Duration, on_submission: impl Fn(Submission)) -> Result<()> {
+        while !self.jobs.is_empty() {
+            match self.tick(client).await {
+                Ok(submissions) => submissions.into_iter().for_each(&on_submission),
+                Err(error @ Error::Io(_)) => return Err(error),
+                Err(error) => tracing::warn!(%error, "scheduler tick failed"),
+            }
+            if !self.jobs.is_empty() {
+                tokio::time::sleep(crate::poll::jittered(interval)).await;
+            }
+        }
+        Ok(())
+    }
+
+    // Writes a temporary file and renames it over the old one, so a crash mid-write
+    // never leaves a truncated job list
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else { return Ok(()) };
+        let temporary = path.with_extension("tmp");
+        let contents = serde_json::to_string_pretty(&self.jobs).map_err(|error| Error::Decode(format!("Failed to serialize jobs: {}", error)))?;
+        std::fs::write(&temporary, contents).map_err(|error| std::io::Error::new(error.kind(), format!("Failed to write {}: {}", temporary.display(), error)))?;
+        std::fs::rename(&temporary, path)?;
+        Ok(())
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{london_header, Failure, MockNode};
+
+    // The EIP-155 example transaction
+    const RAW: &str = "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+
+    #[test]
+    fn checks_conditions_against_the_latest_header() {
+        let header = Header::from_json(&london_header("0x64")).unwrap();
+        assert!(Condition::AtBlock(1).is_met(&header) && !Condition::AtBlock(2).is_met(&header));
+        assert!(Condition::AtTimestamp(0).is_met(&header) && !Condition::AtTimestamp(1).is_met(&header));
+        assert!(Condition::BaseFeeBelow(101.into()).is_met(&header) && !Condition::BaseFeeBelow(100.into()).is_met(&header));
+        let legacy = Header { base_fee: None, ..header };
+        assert!(!Condition::BaseFeeBelow(U256::MAX).is_met(&legacy));
+    }
+
+    #[tokio::test]
+    async fn submits_due_jobs_and_keeps_them_across_restarts() {
+        let path = std::env::temp_dir().join(format!("evm-json-rpc-scheduler-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut scheduler = Scheduler::open(&path).unwrap();
+        assert!(scheduler.schedule("0x1234", Condition::AtBlock(1)).is_err());
+        let due = scheduler.schedule(RAW, Condition::AtBlock(1)).unwrap();
+        let later = scheduler.schedule(RAW.trim_start_matches("0x"), Condition::AtBlock(2)).unwrap();
+        let cancelled = scheduler.schedule(RAW, Condition::AtTimestamp(0)).unwrap();
+        assert!(scheduler.cancel(cancelled).unwrap());
+        assert!(!scheduler.cancel(cancelled).unwrap());
+        assert_eq!(Scheduler::open(&path).unwrap().jobs(), scheduler.jobs());
+
+        let node = MockNode::new(|method, _| match method {
+            "eth_getBlockByNumber" => Ok(london_header("0x7")),
+            "eth_sendRawTransaction" => Ok(json!(format!("0x{}", "cd".repeat(32)))),
+            _ => Err(Failure::unexpected(method)),
+        });
+        let submissions = scheduler.tick(&node.client()).await.unwrap();
+        assert_eq!(submissions.len(), 1);
+        assert_eq!((submissions[0].job.id, submissions[0].result.clone()), (due, Ok(format!("0x{}", "cd".repeat(32)))));
+        assert_eq!(node.params_of("eth_sendRawTransaction"), [vec![json!(RAW)]]);
+
+        // A rejected job is dropped; one the node never answered stays
+        let restarted = Scheduler::open(&path).unwrap();
+        assert_eq!(restarted.jobs().iter().map(|job| job.id).collect::<Vec<_>>(), [later]);
+        let mut rejected = Scheduler::new();
+        rejected.schedule(RAW, Condition::AtBlock(1)).unwrap();
+        let rejecting = MockNode::new(|method, _| match method {
+            "eth_getBlockByNumber" => Ok(london_header("0x7")),
+            _ => Err(Failure::rpc(-32000, "nonce too low")),
+        });
+        let submissions = rejected.tick(&rejecting.client()).await.unwrap();
+        assert!(matches!(&submissions[0].result, Err(message) if message.contains("nonce too low")));
+        assert!(rejected.jobs().is_empty());
+        let mut unreachable = Scheduler::new();
+        unreachable.schedule(RAW, Condition::AtBlock(1)).unwrap();
+        let down = MockNode::new(|method, _| match method {
+            "eth_getBlockByNumber" => Ok(london_header("0x7")),
+            _ => Err(Failure::Transport("connection reset".to_string())),
+        });
+        assert!(unreachable.tick(&down.client()).await.unwrap().is_empty());
+        assert_eq!(unreachable.jobs().len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,279 @@
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+
+// bytes4(keccak256("isValidSignature(bytes32,bytes)"))
+pub const EIP1271_MAGIC_VALUE: &str = "1626ba7e";
+
+// EIP-191 personal_sign digest
+pub fn hash_message(message: &[u8]) -> [u8; 32] {
+    let mut prefixed = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    prefixed.extend_from_slice(message);
+    keccak_hash::keccak(prefixed).0
+}
+
+// Recovers the signer address from a 65-byte r || s || v signature (v as 0/1 or 27/28)
+pub fn recover_address(digest: &[u8; 32], signature: &[u8]) -> Result<String> {
+    if signature.len() != 65 {
+        return Err(Error::Signature(format!("Expected a 65-byte signature, got {} bytes", signature.len())));
+    }
+    let v = match signature[64] {
+        0 | 27 => 0,
+        1 | 28 => 1,
+        v => return Err(Error::Signature(format!("Invalid signature recovery id {}", v))),
+    };
+
+    let mut ecdsa = Signature::from_slice(&signature[..64]).map_err(|error| Error::Signature(error.to_string()))?;
+    let mut recovery_id = RecoveryId::from_byte(v).ok_or_else(|| Error::Signature("Invalid signature recovery id".to_string()))?;
+    // Flipping s to the lower half of the curve order flips the parity of R as well
+    if let Some(normalized) = ecdsa.normalize_s() {
+        ecdsa = normalized;
+        recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+    }
+
+    let key = VerifyingKey::recover_from_prehash(digest, &ecdsa, recovery_id).map_err(|error| Error::Signature(error.to_string()))?;
+    let public_key = key.to_encoded_point(false);
+    let hash = keccak_hash::keccak(&public_key.as_bytes()[1..]);
+    Ok(format!("0x{}", hex::encode(&hash[12..])))
+}
+
+// Asks a contract wallet whether it accepts the signature for the digest
+pub async fn is_valid_signature(client: &EthClient, wallet: &str, digest: &[u8; 32], signature: &[u8]) -> Result<bool> {
+    let params = ethabi::encode(&[
+        ethabi::Token::FixedBytes(digest.to_vec()),
+        ethabi::Token::Bytes(signature.to_vec()),
+    ]);
+    let data = format!("0x{}{}", EIP1271_MAGIC_VALUE, hex::encode(params));
+    let params = vec![
+        serde_json::json!({
+            "to": wallet,
+            "data": data,
+        }),
+        serde_json::json!("latest"),
+    ];
+    returns_magic_value(client, params).await
+}
+
+// Whether the eth_call returned the EIP-1271 magic value. A revert, or an account without
+// code returning 0x, rejects the signature; any other failure is the node's and is returned.
+async fn returns_magic_value(client: &EthClient, params: Vec<serde_json::Value>) -> Result<bool> {
+    match client.send("eth_call", params).await {
+        Ok(result) => Ok(result.as_str().unwrap_or_default().trim_start_matches("0x").starts_with(EIP1271_MAGIC_VALUE)),
+        Err(error) if error.is_revert() => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+// Suffix marking an ERC-6492 wrapped signature for a not-yet-deployed contract wallet
+pub const ERC6492_MAGIC_SUFFIX: &str = "6492649264926492649264926492649264926492649264926492649264926492";
+
+// Verifies a signature for any kind of signer: ERC-6492 wrapped signatures are checked
+// against the counterfactual wallet, everything else through ECDSA recovery and, for
+// accounts with code, EIP-1271
+pub async fn verify_signature(client: &EthClient, signer: &str, digest: &[u8; 32], signature: &[u8]) -> Result<bool> {
+    let suffix = hex::decode(ERC6492_MAGIC_SUFFIX).unwrap();
+    if let Some(wrapped) = signature.strip_suffix(suffix.as_slice()) {
+        let decoded = ethabi::decode(&[ethabi::ParamType::Address, ethabi::ParamType::Bytes, ethabi::ParamType::Bytes], wrapped).map_err(|error| Error::Signature(format!("Invalid ERC-6492 signature wrapper: {}", error)))?;
+        let (factory, factory_calldata, inner_signature) = match decoded.as_slice() {
+            [ethabi::Token::Address(factory), ethabi::Token::Bytes(calldata), ethabi::Token::Bytes(signature)] => (*factory, calldata, signature),
+            _ => return Err(Error::Signature("Invalid ERC-6492 signature wrapper".to_string())),
+        };
+        let signer = signer.trim_start_matches("0x").parse::<ethabi::Address>().map_err(|_| Error::InvalidInput(format!("Invalid signer address {}", signer)))?;
+        return verify_counterfactual(client, signer, factory, factory_calldata, digest, inner_signature).await;
+    }
+
+    // A matching recovery is conclusive, since only a key holder can produce it
+    if let Ok(recovered) = recover_address(digest, signature) {
+        if recovered.eq_ignore_ascii_case(signer) {
+            return Ok(true);
+        }
+    }
+
+    let code = crate::bytecode::get_code(client, signer).await?;
+    if code.trim_start_matches("0x").is_empty() {
+        return Ok(false);
+    }
+    is_valid_signature(client, signer, digest, signature).await
+}
+
+// Runs a deployless eth_call whose constructor calls the factory, then isValidSignature
+// on the freshly deployed wallet, and returns that call's result
+async fn verify_counterfactual(client: &EthClient, signer: ethabi::Address, factory: ethabi::Address, factory_calldata: &[u8], digest: &[u8; 32], signature: &[u8]) -> Result<bool> {
+    let mut validation_calldata = hex::decode(EIP1271_MAGIC_VALUE).unwrap();
+    validation_calldata.extend(ethabi::encode(&[
+        ethabi::Token::FixedBytes(digest.to_vec()),
+        ethabi::Token::Bytes(signature.to_vec()),
+    ]));
+
+    let initcode = counterfactual_validator(signer, factory, factory_calldata, &validation_calldata)?;
+    let params = vec![
+        serde_json::json!({
+            "data": format!("0x{}", hex::encode(initcode)),
+        }),
+        serde_json::json!("latest"),
+    ];
+    returns_magic_value(client, params).await
+}
+
+fn counterfactual_validator(signer: ethabi::Address, factory: ethabi::Address, factory_calldata: &[u8], validation_calldata: &[u8]) -> Result<Vec<u8>> {
+    // Every offset and length is pushed with PUSH2, so the code length is fixed
+    let assemble = |code_length: usize| -> Result<Vec<u8>> {
+        let push2 = |code: &mut Vec<u8>, value: usize| -> Result<()> {
+            let value = u16::try_from(value).map_err(|_| Error::InvalidInput("ERC-6492 payload too large".to_string()))?;
+            code.push(0x61);
+            code.extend_from_slice(&value.to_be_bytes());
+            Ok(())
+        };
+        let factory_offset = code_length;
+        let validation_offset = code_length + factory_calldata.len();
+        // Return data goes past both payloads so stale calldata can never read as a result
+        let result_offset = factory_calldata.len().max(validation_calldata.len()).next_multiple_of(32);
+
+        let mut code = Vec::new();
+        // codecopy(0, factory_offset, len) then call(gas, factory, 0, 0, len, 0, 0), result ignored
+        push2(&mut code, factory_calldata.len())?;
+        push2(&mut code, factory_offset)?;
+        push2(&mut code, 0)?;
+        code.push(0x39);
+        push2(&mut code, 0)?;
+        push2(&mut code, 0)?;
+        push2(&mut code, factory_calldata.len())?;
+        push2(&mut code, 0)?;
+        push2(&mut code, 0)?;
+        code.push(0x73);
+        code.extend_from_slice(factory.as_bytes());
+        code.extend_from_slice(&[0x5a, 0xf1, 0x50]);
+        // codecopy(0, validation_offset, len) then staticcall(gas, signer, 0, len, result_offset, 32)
+        push2(&mut code, validation_calldata.len())?;
+        push2(&mut code, validation_offset)?;
+        push2(&mut code, 0)?;
+        code.push(0x39);
+        push2(&mut code, 32)?;
+        push2(&mut code, result_offset)?;
+        push2(&mut code, validation_calldata.len())?;
+        push2(&mut code, 0)?;
+        code.push(0x73);
+        code.extend_from_slice(signer.as_bytes());
+        code.extend_from_slice(&[0x5a, 0xfa]);
+        // mstore(0, success * mload(result_offset)) and return(0, 32)
+        push2(&mut code, result_offset)?;
+        code.extend_from_slice(&[0x51, 0x02]);
+        push2(&mut code, 0)?;
+        code.push(0x52);
+        push2(&mut code, 32)?;
+        push2(&mut code, 0)?;
+        code.push(0xf3);
+        Ok(code)
+    };
+
+    let code_length = assemble(0)?.len();
+    let mut initcode = assemble(code_length)?;
+    initcode.extend_from_slice(factory_calldata);
+    initcode.extend_from_slice(validation_calldata);
+    Ok(initcode)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::testing::{Failure, MockNode};
+
+    const WALLET: &str = "0x1111111111111111111111111111111111111111";
+
+    // isValidSignature's return value, accepting or not
+    fn validity(valid: bool) -> Value {
+        let magic = if valid { EIP1271_MAGIC_VALUE } else { "ffffffff" };
+        json!(format!("0x{}{}", magic, "0".repeat(56)))
+    }
+
+    #[test]
+    fn recovers_a_personal_sign_signer() {
+        // web3.eth.accounts.sign("Some data", "0x4c0883a6...")
+        let signature = hex::decode("b91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a0291c").unwrap();
+        let digest = hash_message(b"Some data");
+        assert_eq!(hex::encode(digest), "1da44b586eb0729ff70a73c326926f6ed5a25f5b056e7f47fbc6e58d86871655");
+        assert_eq!(recover_address(&digest, &signature).unwrap(), "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23");
+
+        // v as a recovery id rather than 27/28
+        let mut raw = signature.clone();
+        raw[64] -= 27;
+        assert_eq!(recover_address(&digest, &raw).unwrap(), "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23");
+        assert!(matches!(recover_address(&digest, &signature[..64]), Err(Error::Signature(_))));
+        raw[64] = 29;
+        assert!(matches!(recover_address(&digest, &raw), Err(Error::Signature(_))));
+    }
+
+    #[tokio::test]
+    async fn asks_contract_wallets_through_eip1271() {
+        let digest = hash_message(b"hello");
+        let signature = [7u8; 65];
+        let node = MockNode::new(move |method, params| match method {
+            "eth_getCode" if params[0] == WALLET => Ok(json!("0x6080")),
+            "eth_getCode" => Ok(json!("0x")),
+            "eth_call" => Ok(validity(params[0]["data"].as_str().unwrap().contains(&hex::encode(digest)))),
+            _ => Err(Failure::unexpected(method)),
+        });
+        let client = node.client();
+
+        assert!(verify_signature(&client, WALLET, &digest, &signature).await.unwrap());
+        assert!(!verify_signature(&client, WALLET, &hash_message(b"other"), &signature).await.unwrap());
+        let expected = format!("0x{}{}", EIP1271_MAGIC_VALUE, hex::encode(ethabi::encode(&[ethabi::Token::FixedBytes(digest.to_vec()), ethabi::Token::Bytes(signature.to_vec())])));
+        assert_eq!(node.params_of("eth_call")[0], [json!({"to": WALLET, "data": expected}), json!("latest")]);
+
+        // An account without code is never asked
+        let calls = node.params_of("eth_call").len();
+        assert!(!verify_signature(&client, "0x2222222222222222222222222222222222222222", &digest, &signature).await.unwrap());
+        assert_eq!(node.params_of("eth_call").len(), calls);
+    }
+
+    #[tokio::test]
+    async fn reports_node_errors_instead_of_rejecting() {
+        let digest = hash_message(b"hello");
+        let node = |failure: Failure| MockNode::new(move |_, _| Err(failure.clone())).client();
+
+        assert!(!is_valid_signature(&node(Failure::reverted()), WALLET, &digest, &[7; 65]).await.unwrap());
+        assert!(matches!(is_valid_signature(&node(Failure::rpc(-32005, "rate limited")), WALLET, &digest, &[7; 65]).await, Err(Error::Rpc(_))));
+        assert!(matches!(is_valid_signature(&node(Failure::Transport("connection refused".to_string())), WALLET, &digest, &[7; 65]).await, Err(Error::Transport(_))));
+    }
+
+    #[tokio::test]
+    async fn validates_erc6492_signatures_with_a_deployless_call() {
+        let digest = hash_message(b"hello");
+        let inner = vec![9u8; 65];
+        let factory = ethabi::Address::repeat_byte(0xfa);
+        let factory_calldata = vec![0xab; 36];
+        let mut wrapped = ethabi::encode(&[ethabi::Token::Address(factory), ethabi::Token::Bytes(factory_calldata.clone()), ethabi::Token::Bytes(inner.clone())]);
+        wrapped.extend(hex::decode(ERC6492_MAGIC_SUFFIX).unwrap());
+        let node = MockNode::new(|method, _| match method {
+            "eth_call" => Ok(validity(true)),
+            _ => Err(Failure::unexpected(method)),
+        });
+
+        assert!(verify_signature(&node.client(), WALLET, &digest, &wrapped).await.unwrap());
+        // No code lookup: the wallet need not exist yet
+        let calls = node.calls();
+        assert_eq!(calls.len(), 1);
+        let params = &calls[0].1;
+        assert_eq!(params[1], json!("latest"));
+        assert!(params[0].get("to").is_none());
+
+        // The initcode is followed by the factory calldata, then isValidSignature on the
+        // inner signature with the wrapper and magic suffix stripped
+        let validation = format!("{}{}", EIP1271_MAGIC_VALUE, hex::encode(ethabi::encode(&[ethabi::Token::FixedBytes(digest.to_vec()), ethabi::Token::Bytes(inner)])));
+        let data = params[0]["data"].as_str().unwrap();
+        let expected = counterfactual_validator(ethabi::Address::repeat_byte(0x11), factory, &factory_calldata, &hex::decode(&validation).unwrap()).unwrap();
+        assert_eq!(data, format!("0x{}", hex::encode(expected)));
+        assert!(data.ends_with(&format!("{}{}", hex::encode(&factory_calldata), validation)));
+        assert!(!data.contains(ERC6492_MAGIC_SUFFIX));
+        // The code calls the factory, then staticcalls the wallet
+        assert!(data.contains(&format!("73{}5af150", "fa".repeat(20))));
+        assert!(data.contains(&format!("73{}5afa", "11".repeat(20))));
+
+        let mut truncated = vec![0u8; 32];
+        truncated.extend(hex::decode(ERC6492_MAGIC_SUFFIX).unwrap());
+        assert!(matches!(verify_signature(&node.client(), WALLET, &digest, &truncated).await, Err(Error::Signature(_))));
+    }
+}
@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use revm::db::{CacheDB, DatabaseRef};
+use revm::primitives::{AccountInfo, Address, BlockEnv, Bytecode, Bytes, EVMError, ExecutionResult, SpecId, TxEnv, TxKind, B256, U256};
+use revm::Evm;
+use tokio::runtime::Runtime;
+
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::overrides::Overrides;
+use crate::transaction::TransactionRequest;
+
+// Gas limit for calls that don't set one, as most nodes use for eth_call
+pub const DEFAULT_CALL_GAS: u64 = 30_000_000;
+
+// Reads chain state at one block over RPC as revm asks for it. The EVM is synchronous,
+// so requests block on an internal runtime: don't use it from inside another runtime
+// (wrap the work in spawn_blocking instead).
+pub struct ForkDb {
+    client: EthClient,
+    block: String,
+    runtime: Arc<Runtime>,
+}
+
+impl ForkDb {
+    fn send(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value> {
+        self.runtime.block_on(self.client.send(method, params))
+    }
+
+    fn quantity(&self, method: &str, address: Address) -> Result<U256> {
+        let value = self.send(method, vec![serde_json::json!(address), serde_json::json!(self.block)])?;
+        parse_u256(&value)
+    }
+}
+
+impl DatabaseRef for ForkDb {
+    type Error = Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let balance = self.quantity("eth_getBalance", address)?;
+        let nonce = self.quantity("eth_getTransactionCount", address)?;
+        let code = self.send("eth_getCode", vec![serde_json::json!(address), serde_json::json!(self.block)])?;
+        let code = code.as_str().ok_or_else(|| Error::Decode(format!("Invalid eth_getCode response: {}", code)))?;
+        let code = hex::decode(code.trim_start_matches("0x")).map_err(|error| Error::Decode(format!("Invalid eth_getCode response: {}", error)))?;
+        let code = Bytecode::new_raw(Bytes::from(code));
+        Ok(Some(AccountInfo::new(balance, nonce.try_into().map_err(|_| Error::Decode(format!("Nonce {} overflows u64", nonce)))?, code.hash_slow(), code)))
+    }
+
+    // basic_ref always returns the code, so revm only asks for it by hash for empty accounts
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        Err(Error::Unsupported(format!("Code for hash {} was not loaded with its account", code_hash)))
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        let slot = format!("0x{:x}", index);
+        let value = self.send("eth_getStorageAt", vec![serde_json::json!(address), serde_json::json!(slot), serde_json::json!(self.block)])?;
+        parse_u256(&value)
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        let block = self.send("eth_getBlockByNumber", vec![serde_json::json!(format!("0x{:x}", number)), serde_json::json!(false)])?;
+        serde_json::from_value(block["hash"].clone()).map_err(|_| Error::Decode(format!("Block {} not found", number)))
+    }
+}
+
+fn parse_u256(value: &serde_json::Value) -> Result<U256> {
+    let quantity = value.as_str().ok_or_else(|| Error::Decode(format!("Expected a hex quantity, got {}", value)))?;
+    U256::from_str_radix(quantity.trim_start_matches("0x"), 16).map_err(|error| Error::Decode(format!("Invalid quantity {}: {}", quantity, error)))
+}
+
+// Database errors are our own; anything else means revm rejected the transaction
+fn evm_error(error: EVMError<Error>) -> Error {
+    match error {
+        EVMError::Database(error) => error,
+        error => Error::InvalidInput(format!("Transaction rejected by the EVM: {}", error)),
+    }
+}
+
+// Local EVM forked from a block. State is fetched lazily and cached, so repeated
+// simulations against the same contracts only hit the RPC once per account and slot.
+// transact() keeps its state changes for later calls; call() discards them.
+pub struct Simulator {
+    db: CacheDB<ForkDb>,
+    chain_id: u64,
+    block_env: BlockEnv,
+    spec_id: SpecId,
+}
+
+impl Simulator {
+    // `block` is a tag such as "latest" or a hex block number; tags are pinned to the
+    // block they resolve to now
+    pub fn fork(client: &EthClient, block: &str) -> Result<Self> {
+        let runtime = Arc::new(tokio::runtime::Builder::new_current_thread().enable_all().build()?);
+        let header = runtime.block_on(client.send("eth_getBlockByNumber", vec![serde_json::json!(block), serde_json::json!(false)]))?;
+        if header.is_null() {
+            return Err(Error::Decode(format!("Block {} not found", block)));
+        }
+        let chain_id = runtime.block_on(client.send("eth_chainId", vec![]))?;
+
+        let block_env = BlockEnv {
+            number: parse_u256(&header["number"])?,
+            coinbase: serde_json::from_value(header["miner"].clone()).map_err(|error| Error::Decode(format!("Invalid block miner: {}", error)))?,
+            timestamp: parse_u256(&header["timestamp"])?,
+            gas_limit: parse_u256(&header["gasLimit"])?,
+            basefee: header.get("baseFeePerGas").filter(|fee| !fee.is_null()).map(parse_u256).transpose()?.unwrap_or_default(),
+            difficulty: parse_u256(&header["difficulty"]).unwrap_or_default(),
+            prevrandao: serde_json::from_value(header["mixHash"].clone()).ok(),
+            ..Default::default()
+        };
+        let db = ForkDb {
+            client: client.clone(),
+            block: format!("0x{:x}", block_env.number),
+            runtime,
+        };
+        Ok(Simulator {
+            db: CacheDB::new(db),
+            chain_id: parse_u256(&chain_id)?.try_into().map_err(|_| Error::Decode(format!("Chain id {} overflows u64", chain_id)))?,
+            block_env,
+            spec_id: SpecId::CANCUN,
+        })
+    }
+
+    // Hardfork rules to execute with; Cancun by default
+    pub fn with_spec(mut self, spec_id: SpecId) -> Self {
+        self.spec_id = spec_id;
+        self
+    }
+
+    pub fn block_env(&self) -> &BlockEnv {
+        &self.block_env
+    }
+
+    // Overrides an account locally, e.g. to fund a sender
+    pub fn set_balance(&mut self, address: Address, balance: U256) -> Result<()> {
+        let mut info = self.db.basic_ref(address)?.unwrap_or_default();
+        info.balance = balance;
+        self.db.insert_account_info(address, info);
+        Ok(())
+    }
+
+    pub fn set_storage(&mut self, address: Address, slot: U256, value: U256) -> Result<()> {
+        self.db.insert_account_storage(address, slot, value)?;
+        Ok(())
+    }
+
+    // Applies the same overrides eth_call would take to the local state
+    pub fn apply_overrides(&mut self, overrides: &Overrides) -> Result<()> {
+        for (address, account) in overrides.accounts() {
+            let address = Address::from_slice(address.as_bytes());
+            let mut info = self.db.basic_ref(address)?.unwrap_or_default();
+            if let Some(balance) = account.balance {
+                info.balance = to_u256(balance);
+            }
+            if let Some(nonce) = account.nonce {
+                info.nonce = nonce.as_u64();
+            }
+            if let Some(code) = &account.code {
+                let code = hex::decode(code.trim_start_matches("0x")).map_err(|error| Error::InvalidInput(format!("Invalid override code: {}", error)))?;
+                let code = Bytecode::new_raw(Bytes::from(code));
+                info.code_hash = code.hash_slow();
+                info.code = Some(code);
+            }
+            self.db.insert_account_info(address, info);
+            if let Some(state) = &account.state {
+                let state = state.iter().map(|(slot, value)| (U256::from_be_bytes(slot.0), U256::from_be_bytes(value.0))).collect();
+                self.db.replace_account_storage(address, state)?;
+            }
+            for (slot, value) in account.state_diff.iter().flatten() {
+                self.db.insert_account_storage(address, U256::from_be_bytes(slot.0), U256::from_be_bytes(value.0))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Like eth_call: balance, base fee and nonce checks are skipped and state is discarded
+    pub fn call(&mut self, transaction: &TransactionRequest) -> Result<ExecutionResult> {
+        let tx_env = self.tx_env(transaction)?;
+        let mut evm = Evm::builder()
+            .with_db(&mut self.db)
+            .with_spec_id(self.spec_id)
+            .modify_cfg_env(|cfg| {
+                cfg.chain_id = self.chain_id;
+                cfg.disable_balance_check = true;
+                cfg.disable_base_fee = true;
+                cfg.disable_block_gas_limit = true;
+            })
+            .modify_block_env(|block_env| *block_env = self.block_env.clone())
+            .modify_tx_env(|tx| *tx = tx_env)
+            .build();
+        Ok(evm.transact().map_err(evm_error)?.result)
+    }
+
+    // Executes with full validation and keeps the resulting state
+    pub fn transact(&mut self, transaction: &TransactionRequest) -> Result<ExecutionResult> {
+        let tx_env = self.tx_env(transaction)?;
+        let mut evm = Evm::builder()
+            .with_db(&mut self.db)
+            .with_spec_id(self.spec_id)
+            .modify_cfg_env(|cfg| cfg.chain_id = self.chain_id)
+            .modify_block_env(|block_env| *block_env = self.block_env.clone())
+            .modify_tx_env(|tx| *tx = tx_env)
+            .build();
+        evm.transact_commit().map_err(evm_error)
+    }
+
+    fn tx_env(&self, transaction: &TransactionRequest) -> Result<TxEnv> {
+        let parse_address = |address: &Option<String>| -> Result<Option<Address>> {
+            address.as_deref().map(|address| address.parse().map_err(|_| Error::InvalidInput(format!("Invalid address {}", address)))).transpose()
+        };
+        let data = transaction.data.as_deref().unwrap_or("0x");
+
+        Ok(TxEnv {
+            caller: parse_address(&transaction.from)?.unwrap_or_default(),
+            gas_limit: transaction.gas.map_or(DEFAULT_CALL_GAS, |gas| gas.as_u64()),
+            gas_price: transaction.max_fee_per_gas.or(transaction.gas_price).map(to_u256).unwrap_or_default(),
+            gas_priority_fee: transaction.max_priority_fee_per_gas.map(to_u256),
+            transact_to: parse_address(&transaction.to)?.map_or(TxKind::Create, TxKind::Call),
+            value: transaction.value.map(to_u256).unwrap_or_default(),
+            data: Bytes::from(hex::decode(data.trim_start_matches("0x")).map_err(|error| Error::InvalidInput(format!("Invalid calldata: {}", error)))?),
+            nonce: transaction.nonce.map(|nonce| nonce.as_u64()),
+            chain_id: Some(self.chain_id),
+            ..Default::default()
+        })
+    }
+}
+
+fn to_u256(value: ethabi::ethereum_types::U256) -> U256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    U256::from_be_bytes(bytes)
+}
@@ -0,0 +1,67 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+
+use crate::error::{Error, Result};
+
+// Destination for records produced by crawlers and indexers, written as they arrive
+// so a scan never holds more than one batch in memory
+#[async_trait]
+pub trait Sink: Send {
+    async fn write(&mut self, record: &serde_json::Value) -> Result<()>;
+
+    // Makes everything written so far durable; called at the end of each batch
+    async fn flush(&mut self) -> Result<()>;
+}
+
+// Collects records in memory, for small scans and tests
+#[async_trait]
+impl Sink for Vec<serde_json::Value> {
+    async fn write(&mut self, record: &serde_json::Value) -> Result<()> {
+        self.push(record.clone());
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Newline-delimited JSON on disk, one record per line. Appends to an existing file, so
+// an interrupted scan can resume where it stopped.
+pub struct NdjsonSink {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl NdjsonSink {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new().create(true).append(true).open(path).map_err(|error| std::io::Error::new(error.kind(), format!("Failed to open {}: {}", path.display(), error)))?;
+        Ok(NdjsonSink {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[async_trait]
+impl Sink for NdjsonSink {
+    async fn write(&mut self, record: &serde_json::Value) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, record).map_err(|error| Error::Decode(format!("Failed to serialize record: {}", error)))?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_data()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::signature;
+use crate::utils::{format_rfc3339, is_checksum_address, parse_rfc3339, to_checksum_address};
+
+const PREAMBLE: &str = " wants you to sign in with your Ethereum account:";
+
+// EIP-4361 Sign-In With Ethereum message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiweMessage {
+    pub scheme: Option<String>,
+    pub domain: String,
+    // EIP-55 checksummed
+    pub address: String,
+    pub statement: Option<String>,
+    pub uri: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub nonce: String,
+    pub issued_at: String,
+    pub expiration_time: Option<String>,
+    pub not_before: Option<String>,
+    pub request_id: Option<String>,
+    pub resources: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyOptions {
+    // Defaults to the current time
+    pub time: Option<SystemTime>,
+}
+
+impl SiweMessage {
+    // The address may be in any case; the message carries its EIP-55 form
+    pub fn new(domain: &str, address: &str, uri: &str, chain_id: u64) -> Result<Self> {
+        let digits = address.trim_start_matches("0x");
+        if digits.len() != 40 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::InvalidInput(format!("Invalid SIWE address {}", address)));
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        Ok(SiweMessage {
+            scheme: None,
+            domain: domain.to_string(),
+            address: to_checksum_address(digits),
+            statement: None,
+            uri: uri.to_string(),
+            version: "1".to_string(),
+            chain_id,
+            nonce: generate_nonce(),
+            issued_at: format_rfc3339(now as i64),
+            expiration_time: None,
+            not_before: None,
+            request_id: None,
+            resources: Vec::new(),
+        })
+    }
+
+    // Checks the message was made for this domain and session nonce, that the client is
+    // on the message's chain, the validity window, and then the signature for EOAs,
+    // contract wallets and ERC-6492 counterfactual wallets alike
+    pub async fn verify(&self, client: &EthClient, signature: &[u8], domain: &str, nonce: &str, options: &VerifyOptions) -> Result<()> {
+        if domain != self.domain {
+            return Err(Error::Signature(format!("SIWE domain mismatch: expected {}, got {}", domain, self.domain)));
+        }
+        if nonce != self.nonce {
+            return Err(Error::Signature("SIWE nonce mismatch".to_string()));
+        }
+
+        let now = options.time.unwrap_or_else(SystemTime::now).duration_since(UNIX_EPOCH).map_err(|_| Error::InvalidInput("Verification time is before 1970".to_string()))?.as_secs() as i64;
+        if let Some(expiration_time) = &self.expiration_time {
+            if now >= parse_rfc3339(expiration_time)? {
+                return Err(Error::Signature("SIWE message has expired".to_string()));
+            }
+        }
+        if let Some(not_before) = &self.not_before {
+            if now < parse_rfc3339(not_before)? {
+                return Err(Error::Signature("SIWE message is not yet valid".to_string()));
+            }
+        }
+
+        // Contract wallet signatures only mean something on the chain they were made for
+        let chain_id = client.chain_id().await?;
+        if chain_id != self.chain_id {
+            return Err(Error::ChainMismatch { expected: self.chain_id, actual: chain_id });
+        }
+
+        let digest = signature::hash_message(self.to_string().as_bytes());
+        if !signature::verify_signature(client, &self.address, &digest, signature).await? {
+            return Err(Error::Signature("SIWE signature does not match the message address".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+// At least 8 alphanumeric characters, as required by EIP-4361
+pub fn generate_nonce() -> String {
+    rand::thread_rng().sample_iter(&Alphanumeric).take(17).map(char::from).collect()
+}
+
+impl fmt::Display for SiweMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(scheme) = &self.scheme {
+            write!(f, "{}://", scheme)?;
+        }
+        writeln!(f, "{}{}", self.domain, PREAMBLE)?;
+        writeln!(f, "{}", self.address)?;
+        writeln!(f)?;
+        if let Some(statement) = &self.statement {
+            writeln!(f, "{}", statement)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "URI: {}", self.uri)?;
+        writeln!(f, "Version: {}", self.version)?;
+        writeln!(f, "Chain ID: {}", self.chain_id)?;
+        writeln!(f, "Nonce: {}", self.nonce)?;
+        write!(f, "Issued At: {}", self.issued_at)?;
+        if let Some(expiration_time) = &self.expiration_time {
+            write!(f, "\nExpiration Time: {}", expiration_time)?;
+        }
+        if let Some(not_before) = &self.not_before {
+            write!(f, "\nNot Before: {}", not_before)?;
+        }
+        if let Some(request_id) = &self.request_id {
+            write!(f, "\nRequest ID: {}", request_id)?;
+        }
+        if !self.resources.is_empty() {
+            write!(f, "\nResources:")?;
+            for resource in &self.resources {
+                write!(f, "\n- {}", resource)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SiweMessage {
+    type Err = Error;
+
+    fn from_str(message: &str) -> Result<Self> {
+        let mut lines = message.split('\n');
+        let mut next = || lines.next().ok_or_else(|| Error::Decode("Unexpected end of SIWE message".to_string()));
+
+        let header = next()?.strip_suffix(PREAMBLE).ok_or_else(|| Error::Decode("Missing SIWE preamble".to_string()))?;
+        let (scheme, domain) = match header.split_once("://") {
+            Some((scheme, domain)) => (Some(scheme.to_string()), domain.to_string()),
+            None => (None, header.to_string()),
+        };
+        if domain.is_empty() {
+            return Err(Error::Decode("Missing SIWE domain".to_string()));
+        }
+
+        let address = next()?.to_string();
+        if !is_checksum_address(&address) {
+            return Err(Error::Decode("SIWE address must be EIP-55 checksummed".to_string()));
+        }
+        if !next()?.is_empty() {
+            return Err(Error::Decode("Expected blank line after SIWE address".to_string()));
+        }
+        let statement = match next()? {
+            "" => None,
+            statement => {
+                if !next()?.is_empty() {
+                    return Err(Error::Decode("Expected blank line after SIWE statement".to_string()));
+                }
+                Some(statement.to_string())
+            }
+        };
+
+        let mut field = |name: &str| -> Result<String> {
+            let line = next()?;
+            let value = line.strip_prefix(name).and_then(|rest| rest.strip_prefix(": "));
+            Ok(value.ok_or_else(|| Error::Decode(format!("Expected SIWE field {}", name)))?.to_string())
+        };
+        let uri = field("URI")?;
+        let version = field("Version")?;
+        if version != "1" {
+            return Err(Error::Decode(format!("Unsupported SIWE version {}", version)));
+        }
+        let chain_id = field("Chain ID")?.parse().map_err(|_| Error::Decode("Invalid SIWE chain id".to_string()))?;
+        let nonce = field("Nonce")?;
+        if nonce.len() < 8 || !nonce.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(Error::Decode("SIWE nonce must be at least 8 alphanumeric characters".to_string()));
+        }
+        let issued_at = field("Issued At")?;
+        parse_rfc3339(&issued_at)?;
+
+        let mut parsed = SiweMessage {
+            scheme,
+            domain,
+            address,
+            statement,
+            uri,
+            version,
+            chain_id,
+            nonce,
+            issued_at,
+            expiration_time: None,
+            not_before: None,
+            request_id: None,
+            resources: Vec::new(),
+        };
+
+        // Optional fields must appear in this order
+        let mut remaining = lines.peekable();
+        if let Some(value) = remaining.peek().and_then(|line| line.strip_prefix("Expiration Time: ")) {
+            parse_rfc3339(value)?;
+            parsed.expiration_time = Some(value.to_string());
+            remaining.next();
+        }
+        if let Some(value) = remaining.peek().and_then(|line| line.strip_prefix("Not Before: ")) {
+            parse_rfc3339(value)?;
+            parsed.not_before = Some(value.to_string());
+            remaining.next();
+        }
+        if let Some(value) = remaining.peek().and_then(|line| line.strip_prefix("Request ID: ")) {
+            parsed.request_id = Some(value.to_string());
+            remaining.next();
+        }
+        if remaining.peek() == Some(&"Resources:") {
+            remaining.next();
+            for line in remaining.by_ref() {
+                let resource = line.strip_prefix("- ").ok_or_else(|| Error::Decode("Invalid SIWE resource line".to_string()))?;
+                parsed.resources.push(resource.to_string());
+            }
+        }
+        if remaining.next().is_some() {
+            return Err(Error::Decode("Unexpected trailing content in SIWE message".to_string()));
+        }
+
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example message from EIP-4361
+    const EXAMPLE: &str = "service.invalid wants you to sign in with your Ethereum account:
+0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2
+
+I accept the ServiceOrg Terms of Service: https://service.invalid/tos
+
+URI: https://service.invalid/login
+Version: 1
+Chain ID: 1
+Nonce: 32891756
+Issued At: 2021-09-30T16:25:24Z
+Resources:
+- ipfs://bafybeiemxf5abjwjbikoz4mc3a3dla6ual3jsgpdr4cjr3oz3evfyavhwq/
+- https://example.com/my-web2-claim.json";
+
+    #[test]
+    fn parses_and_renders_the_eip_example() {
+        let message: SiweMessage = EXAMPLE.parse().unwrap();
+        assert_eq!(message.scheme, None);
+        assert_eq!(message.domain, "service.invalid");
+        assert_eq!(message.address, "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        assert_eq!(message.statement.as_deref(), Some("I accept the ServiceOrg Terms of Service: https://service.invalid/tos"));
+        assert_eq!(message.uri, "https://service.invalid/login");
+        assert_eq!(message.chain_id, 1);
+        assert_eq!(message.nonce, "32891756");
+        assert_eq!(message.issued_at, "2021-09-30T16:25:24Z");
+        assert_eq!(message.resources.len(), 2);
+        assert_eq!(message.to_string(), EXAMPLE);
+    }
+
+    #[test]
+    fn round_trips_scheme_and_optional_fields() {
+        let mut message = SiweMessage::new("example.com", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", "https://example.com", 10).unwrap();
+        message.scheme = Some("https".to_string());
+        message.statement = None;
+        message.expiration_time = Some("2030-01-01T00:00:00Z".to_string());
+        message.not_before = Some("2021-01-01T00:00:00.000Z".to_string());
+        message.request_id = Some("abc".to_string());
+        assert_eq!(message.to_string().parse::<SiweMessage>().unwrap(), message);
+    }
+
+    #[test]
+    fn new_checksums_the_address() {
+        let message = SiweMessage::new("service.invalid", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", "https://service.invalid/login", 1).unwrap();
+        assert_eq!(message.address, "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        assert!(message.nonce.len() >= 8);
+        assert!(SiweMessage::new("service.invalid", "0xc02aaa39", "https://service.invalid/login", 1).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_messages() {
+        let lowercase = EXAMPLE.replace("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        assert!(lowercase.parse::<SiweMessage>().is_err());
+        assert!(EXAMPLE.replace("Nonce: 32891756", "Nonce: 1234").parse::<SiweMessage>().is_err());
+        assert!(EXAMPLE.replace("Version: 1", "Version: 2").parse::<SiweMessage>().is_err());
+        assert!(EXAMPLE.replace("2021-09-30T16:25:24Z", "yesterday").parse::<SiweMessage>().is_err());
+        assert!(format!("{}\nextra", EXAMPLE).parse::<SiweMessage>().is_err());
+    }
+}
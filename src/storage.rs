@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+
+use ethabi::ethereum_types::{H256, U256};
+
+use crate::artifacts::StorageLayout;
+use crate::blocks::BlockTag;
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::format;
+use crate::utils::parse_quantity_u256;
+
+// Elements read from a static array; longer arrays are cut off
+pub const MAX_ARRAY_ELEMENTS: usize = 256;
+// Longest bytes/string value read, in bytes
+pub const MAX_BYTES_LENGTH: usize = 4_096;
+
+// A storage variable at one block, rendered as text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageValue {
+    // Struct members and array elements are flattened: "config.fee", "owners[2]"
+    pub label: String,
+    pub slot: H256,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateChange {
+    pub label: String,
+    pub slot: H256,
+    pub before: String,
+    pub after: String,
+}
+
+enum Variable {
+    Value { label: String, slot: U256, offset: usize, size: usize, type_label: String },
+    Bytes { label: String, slot: U256, is_string: bool },
+}
+
+impl Variable {
+    fn label(&self) -> &str {
+        match self {
+            Variable::Value { label, .. } | Variable::Bytes { label, .. } => label,
+        }
+    }
+
+    fn slot(&self) -> U256 {
+        match self {
+            Variable::Value { slot, .. } | Variable::Bytes { slot, .. } => *slot,
+        }
+    }
+}
+
+// Reads every variable the storage layout places statically: value types, struct
+// members, static array elements, lengths of dynamic arrays and bytes/strings.
+// Mapping entries can't be enumerated and are skipped.
+pub async fn read_state(client: &EthClient, contract: &str, block: impl Into<BlockTag>, layout: &StorageLayout) -> Result<Vec<StorageValue>> {
+    let mut reader = SlotReader { client, contract, block: block.into(), slots: HashMap::new() };
+    let mut values = Vec::new();
+    for variable in variables(layout)? {
+        values.push(StorageValue {
+            label: variable.label().to_string(),
+            slot: to_h256(variable.slot()),
+            value: reader.render(&variable).await?,
+        });
+    }
+    Ok(values)
+}
+
+// The layout's variables whose value differs between the two blocks, e.g. before and
+// after an upgrade. The layout should match the implementation at both blocks.
+pub async fn diff_state(client: &EthClient, contract: &str, block_a: impl Into<BlockTag>, block_b: impl Into<BlockTag>, layout: &StorageLayout) -> Result<Vec<StateChange>> {
+    let before = read_state(client, contract, block_a, layout).await?;
+    let after = read_state(client, contract, block_b, layout).await?;
+    Ok(before
+        .into_iter()
+        .zip(after)
+        .filter(|(before, after)| before.value != after.value)
+        .map(|(before, after)| StateChange {
+            label: before.label,
+            slot: before.slot,
+            before: before.value,
+            after: after.value,
+        })
+        .collect())
+}
+
+fn variables(layout: &StorageLayout) -> Result<Vec<Variable>> {
+    let mut variables = Vec::new();
+    for entry in &layout.storage {
+        let slot = U256::from_dec_str(&entry.slot).map_err(|_| Error::Decode(format!("Invalid slot {} for {}", entry.slot, entry.label)))?;
+        collect(layout, &entry.type_name, entry.label.clone(), slot, entry.offset as usize, &mut variables)?;
+    }
+    Ok(variables)
+}
+
+fn collect(layout: &StorageLayout, type_name: &str, label: String, slot: U256, offset: usize, out: &mut Vec<Variable>) -> Result<()> {
+    let storage_type = layout.types.get(type_name).ok_or_else(|| Error::Decode(format!("Unknown storage type {}", type_name)))?;
+    let size: usize = storage_type.number_of_bytes.parse().map_err(|_| Error::Decode(format!("Invalid size for {}", type_name)))?;
+
+    match storage_type.encoding.as_str() {
+        "inplace" => {
+            if let Some(members) = &storage_type.members {
+                for member in members {
+                    let member_slot = U256::from_dec_str(&member.slot).map_err(|_| Error::Decode(format!("Invalid slot {} for {}", member.slot, member.label)))?;
+                    collect(layout, &member.type_name, format!("{}.{}", label, member.label), slot + member_slot, member.offset as usize, out)?;
+                }
+            } else if let Some(base) = &storage_type.base {
+                let element_type = layout.types.get(base).ok_or_else(|| Error::Decode(format!("Unknown storage type {}", base)))?;
+                let element_size: usize = element_type.number_of_bytes.parse().map_err(|_| Error::Decode(format!("Invalid size for {}", base)))?;
+                let length = static_array_length(&storage_type.label).ok_or_else(|| Error::Decode(format!("Invalid array type {}", storage_type.label)))?;
+                for index in 0..length.min(MAX_ARRAY_ELEMENTS) {
+                    // Elements under 32 bytes are packed; larger ones start a new slot each
+                    let (element_slot, element_offset) = if element_size >= 32 {
+                        (slot + index * element_size.div_ceil(32), 0)
+                    } else {
+                        let per_slot = 32 / element_size;
+                        (slot + index / per_slot, (index % per_slot) * element_size)
+                    };
+                    collect(layout, base, format!("{}[{}]", label, index), element_slot, element_offset, out)?;
+                }
+            } else {
+                out.push(Variable::Value { label, slot, offset, size: size.min(32), type_label: storage_type.label.clone() });
+            }
+        }
+        "bytes" => out.push(Variable::Bytes { label, slot, is_string: storage_type.label == "string" }),
+        "dynamic_array" => out.push(Variable::Value { label: format!("{}.length", label), slot, offset: 0, size: 32, type_label: "uint256".to_string() }),
+        // Entries live at hashed keys that can't be listed
+        "mapping" => {}
+        encoding => return Err(Error::Unsupported(format!("Storage encoding {} for {}", encoding, label))),
+    }
+    Ok(())
+}
+
+// "uint256[3]" -> 3
+fn static_array_length(label: &str) -> Option<usize> {
+    let (_, length) = label.strip_suffix(']')?.rsplit_once('[')?;
+    length.parse().ok()
+}
+
+struct SlotReader<'a> {
+    client: &'a EthClient,
+    contract: &'a str,
+    block: BlockTag,
+    // Packed variables share slots, so each is fetched once
+    slots: HashMap<U256, [u8; 32]>,
+}
+
+impl SlotReader<'_> {
+    async fn read(&mut self, slot: U256) -> Result<[u8; 32]> {
+        if let Some(word) = self.slots.get(&slot) {
+            return Ok(*word);
+        }
+        let params = vec![serde_json::json!(self.contract), serde_json::json!(format!("0x{:x}", slot)), self.block.to_json()];
+        let value = self.client.send("eth_getStorageAt", params).await?;
+        let value = parse_quantity_u256(&value).map_err(|_| Error::Decode(format!("Invalid eth_getStorageAt response: {}", value)))?;
+        let word = word_of(value);
+        self.slots.insert(slot, word);
+        Ok(word)
+    }
+
+    async fn render(&mut self, variable: &Variable) -> Result<String> {
+        match variable {
+            Variable::Value { slot, offset, size, type_label, .. } => {
+                let word = self.read(*slot).await?;
+                // Packed values are right-aligned: offset counts bytes from the low end
+                let end = 32usize.checked_sub(*offset).ok_or_else(|| Error::Decode("Invalid storage offset".to_string()))?;
+                let start = end.checked_sub(*size).ok_or_else(|| Error::Decode("Invalid storage offset".to_string()))?;
+                Ok(render_value(&word[start..end], type_label))
+            }
+            Variable::Bytes { slot, is_string, .. } => {
+                let word = self.read(*slot).await?;
+                // Short values (under 32 bytes) sit in the slot with length * 2 in the
+                // last byte; long ones store length * 2 + 1 and the data at keccak(slot)
+                let data = if word[31] & 1 == 0 {
+                    word[..(word[31] / 2).min(31) as usize].to_vec()
+                } else {
+                    let length = U256::from_big_endian(&word) >> 1;
+                    if length > U256::from(MAX_BYTES_LENGTH) {
+                        return Ok(format!("<{} bytes>", length));
+                    }
+                    let length = length.as_usize();
+                    let data_slot = U256::from_big_endian(keccak_hash::keccak(word_of(*slot)).as_bytes());
+                    let mut data = Vec::with_capacity(length);
+                    for index in 0..length.div_ceil(32) {
+                        data.extend_from_slice(&self.read(data_slot + index).await?);
+                    }
+                    data.truncate(length);
+                    data
+                };
+                match std::str::from_utf8(&data) {
+                    Ok(text) if *is_string => Ok(format!("{:?}", text)),
+                    _ => Ok(format!("0x{}", hex::encode(data))),
+                }
+            }
+        }
+    }
+}
+
+fn render_value(bytes: &[u8], type_label: &str) -> String {
+    let value = U256::from_big_endian(bytes);
+    if type_label == "address" || type_label.starts_with("address ") || type_label.starts_with("contract ") {
+        return format::address(&format!("0x{}", hex::encode(bytes)));
+    }
+    if type_label == "bool" {
+        return (!value.is_zero()).to_string();
+    }
+    if type_label.starts_with("uint") || type_label.starts_with("enum ") {
+        return value.to_string();
+    }
+    if type_label.starts_with("int") {
+        let bits = bytes.len() * 8;
+        if bits > 0 && value.bit(bits - 1) {
+            // Two's complement within the type's width
+            let modulus = if bits == 256 { U256::MAX } else { (U256::one() << bits) - 1 };
+            return format!("-{}", (modulus - value) + 1);
+        }
+        return value.to_string();
+    }
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn word_of(value: U256) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    value.to_big_endian(&mut word);
+    word
+}
+
+fn to_h256(value: U256) -> H256 {
+    H256(word_of(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Failure, MockNode};
+
+    // solc's layout for:
+    //   address owner; bool paused; uint8 decimals; int16 delta;  // packed into slot 0
+    //   uint256 total; string name; bytes data;
+    //   struct Config { uint128 fee; uint64 limit; } config;
+    //   uint32[3] ids; mapping(address => uint256) balances; uint256[] list;
+    const LAYOUT: &str = r#"{
+        "storage": [
+            {"label": "owner", "slot": "0", "offset": 0, "type": "t_address"},
+            {"label": "paused", "slot": "0", "offset": 20, "type": "t_bool"},
+            {"label": "decimals", "slot": "0", "offset": 21, "type": "t_uint8"},
+            {"label": "delta", "slot": "0", "offset": 22, "type": "t_int16"},
+            {"label": "total", "slot": "1", "offset": 0, "type": "t_uint256"},
+            {"label": "name", "slot": "2", "offset": 0, "type": "t_string_storage"},
+            {"label": "data", "slot": "3", "offset": 0, "type": "t_bytes_storage"},
+            {"label": "config", "slot": "4", "offset": 0, "type": "t_struct(Config)10_storage"},
+            {"label": "ids", "slot": "5", "offset": 0, "type": "t_array(t_uint32)3_storage"},
+            {"label": "balances", "slot": "6", "offset": 0, "type": "t_mapping(t_address,t_uint256)"},
+            {"label": "list", "slot": "7", "offset": 0, "type": "t_array(t_uint256)dyn_storage"}
+        ],
+        "types": {
+            "t_address": {"encoding": "inplace", "label": "address", "numberOfBytes": "20"},
+            "t_bool": {"encoding": "inplace", "label": "bool", "numberOfBytes": "1"},
+            "t_uint8": {"encoding": "inplace", "label": "uint8", "numberOfBytes": "1"},
+            "t_int16": {"encoding": "inplace", "label": "int16", "numberOfBytes": "2"},
+            "t_uint32": {"encoding": "inplace", "label": "uint32", "numberOfBytes": "4"},
+            "t_uint64": {"encoding": "inplace", "label": "uint64", "numberOfBytes": "8"},
+            "t_uint128": {"encoding": "inplace", "label": "uint128", "numberOfBytes": "16"},
+            "t_uint256": {"encoding": "inplace", "label": "uint256", "numberOfBytes": "32"},
+            "t_string_storage": {"encoding": "bytes", "label": "string", "numberOfBytes": "32"},
+            "t_bytes_storage": {"encoding": "bytes", "label": "bytes", "numberOfBytes": "32"},
+            "t_struct(Config)10_storage": {"encoding": "inplace", "label": "struct C.Config", "numberOfBytes": "32", "members": [
+                {"label": "fee", "slot": "0", "offset": 0, "type": "t_uint128"},
+                {"label": "limit", "slot": "0", "offset": 16, "type": "t_uint64"}
+            ]},
+            "t_array(t_uint32)3_storage": {"encoding": "inplace", "label": "uint32[3]", "numberOfBytes": "32", "base": "t_uint32"},
+            "t_mapping(t_address,t_uint256)": {"encoding": "mapping", "label": "mapping(address => uint256)", "numberOfBytes": "32", "key": "t_address", "value": "t_uint256"},
+            "t_array(t_uint256)dyn_storage": {"encoding": "dynamic_array", "label": "uint256[]", "numberOfBytes": "32", "base": "t_uint256"}
+        }
+    }"#;
+
+    // Serves eth_getStorageAt from a fixed set of slots; block 0x2 has a different total
+    fn slots(slots: HashMap<U256, [u8; 32]>) -> EthClient {
+        MockNode::new(move |method, params| {
+            assert_eq!(method, "eth_getStorageAt");
+            let slot = parse_quantity_u256(&params[1]).map_err(|error| Failure::Transport(error.to_string()))?;
+            let mut word = slots.get(&slot).copied().unwrap_or_default();
+            if slot == U256::one() && params[2] == "0x2" {
+                word = word_of(U256::from(2) * U256::exp10(18));
+            }
+            Ok(serde_json::json!(format!("0x{}", hex::encode(word))))
+        })
+        .client()
+    }
+
+    fn client() -> EthClient {
+        let mut slots = HashMap::new();
+        let mut packed = [0u8; 32];
+        packed[12..].copy_from_slice(&hex::decode("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap());
+        packed[11] = 1;
+        packed[10] = 18;
+        packed[8..10].copy_from_slice(&[0xff, 0xfe]);
+        slots.insert(U256::zero(), packed);
+        slots.insert(U256::one(), word_of(U256::exp10(18)));
+
+        let mut name = [0u8; 32];
+        name[..5].copy_from_slice(b"hello");
+        name[31] = 10;
+        slots.insert(U256::from(2), name);
+
+        // 40 bytes spill into keccak(slot) and the slot after it
+        slots.insert(U256::from(3), word_of(U256::from(40 * 2 + 1)));
+        let data_slot = U256::from_big_endian(keccak_hash::keccak(word_of(U256::from(3))).as_bytes());
+        slots.insert(data_slot, [0xaa; 32]);
+        let mut tail = [0u8; 32];
+        tail[..8].copy_from_slice(&[0xbb; 8]);
+        slots.insert(data_slot + 1, tail);
+
+        let mut config = [0u8; 32];
+        config[16..].copy_from_slice(&1000u128.to_be_bytes());
+        config[8..16].copy_from_slice(&7u64.to_be_bytes());
+        slots.insert(U256::from(4), config);
+
+        let mut ids = [0u8; 32];
+        ids[28..].copy_from_slice(&1u32.to_be_bytes());
+        ids[24..28].copy_from_slice(&2u32.to_be_bytes());
+        ids[20..24].copy_from_slice(&3u32.to_be_bytes());
+        slots.insert(U256::from(5), ids);
+        slots.insert(U256::from(7), word_of(U256::from(2)));
+        self::slots(slots)
+    }
+
+    fn layout() -> StorageLayout {
+        serde_json::from_str(LAYOUT).unwrap()
+    }
+
+    #[tokio::test]
+    async fn reads_every_static_variable() {
+        let values = read_state(&client(), "0x0000000000000000000000000000000000000001", 1u64, &layout()).await.unwrap();
+        let rendered: Vec<(&str, &str)> = values.iter().map(|value| (value.label.as_str(), value.value.as_str())).collect();
+        assert_eq!(
+            rendered,
+            vec![
+                ("owner", "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"),
+                ("paused", "true"),
+                ("decimals", "18"),
+                ("delta", "-2"),
+                ("total", "1000000000000000000"),
+                ("name", "\"hello\""),
+                ("data", &format!("0x{}{}", "aa".repeat(32), "bb".repeat(8))),
+                ("config.fee", "1000"),
+                ("config.limit", "7"),
+                ("ids[0]", "1"),
+                ("ids[1]", "2"),
+                ("ids[2]", "3"),
+                ("list.length", "2"),
+            ]
+        );
+        assert_eq!(values[9].slot, H256::from_low_u64_be(5));
+    }
+
+    #[tokio::test]
+    async fn diffs_two_blocks() {
+        let changes = diff_state(&client(), "0x0000000000000000000000000000000000000001", 1u64, 2u64, &layout()).await.unwrap();
+        assert_eq!(
+            changes,
+            vec![StateChange {
+                label: "total".to_string(),
+                slot: H256::from_low_u64_be(1),
+                before: "1000000000000000000".to_string(),
+                after: "2000000000000000000".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn renders_signed_values_in_their_width() {
+        assert_eq!(render_value(&[0x80], "int8"), "-128");
+        assert_eq!(render_value(&[0x7f], "int8"), "127");
+        assert_eq!(render_value(&[0xff; 32], "int256"), "-1");
+        assert_eq!(render_value(&[0xff; 32], "uint256"), U256::MAX.to_string());
+        assert_eq!(render_value(&[0x01, 0x02], "bytes2"), "0x0102");
+        assert_eq!(static_array_length("uint256[3]"), Some(3));
+        assert_eq!(static_array_length("uint256[]"), None);
+    }
+
+    #[test]
+    fn rejects_unknown_types() {
+        let mut layout = layout();
+        layout.storage[0].type_name = "t_missing".to_string();
+        assert!(variables(&layout).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use ethabi::ethereum_types::H256;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::error::{Error, Result};
+use crate::graphql::BlockData;
+use crate::logs::Log;
+
+// A block an indexer has stored, enough to resume from and to detect reorgs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoredBlock {
+    pub number: u64,
+    pub hash: H256,
+}
+
+// Where an Indexer keeps the blocks it has processed and their logs. Implement it to
+// index into your own database; fetching, chunking and reorg handling stay in Indexer.
+// Each call must apply entirely or not at all, so a crash never leaves a block
+// without its logs.
+#[async_trait]
+pub trait Store: Send {
+    // The highest block stored
+    async fn head(&mut self) -> Result<Option<StoredBlock>>;
+
+    async fn block(&mut self, number: u64) -> Result<Option<StoredBlock>>;
+
+    // Stores consecutive blocks with their transactions and logs, continuing the head
+    async fn insert(&mut self, blocks: &[BlockData]) -> Result<()>;
+
+    // Deletes every block above `number` and their logs, after a reorg
+    async fn rewind(&mut self, number: u64) -> Result<()>;
+
+    // Logs of blocks `from` through `to`, in chain order
+    async fn logs(&mut self, from: u64, to: u64) -> Result<Vec<Log>>;
+}
+
+// Keeps everything in memory, for tests and short-lived indexing
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    blocks: BTreeMap<u64, BlockData>,
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn head(&mut self) -> Result<Option<StoredBlock>> {
+        Ok(self.blocks.values().next_back().map(stored))
+    }
+
+    async fn block(&mut self, number: u64) -> Result<Option<StoredBlock>> {
+        Ok(self.blocks.get(&number).map(stored))
+    }
+
+    async fn insert(&mut self, blocks: &[BlockData]) -> Result<()> {
+        self.blocks.extend(blocks.iter().map(|block| (block.number, block.clone())));
+        Ok(())
+    }
+
+    async fn rewind(&mut self, number: u64) -> Result<()> {
+        self.blocks.split_off(&(number + 1));
+        Ok(())
+    }
+
+    async fn logs(&mut self, from: u64, to: u64) -> Result<Vec<Log>> {
+        Ok(self.blocks.range(from..=to).flat_map(|(_, block)| block.logs.iter().cloned()).collect())
+    }
+}
+
+fn stored(block: &BlockData) -> StoredBlock {
+    StoredBlock {
+        number: block.number,
+        hash: block.hash,
+    }
+}
+
+const SQLITE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS blocks (
+        number INTEGER PRIMARY KEY,
+        hash TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        transactions TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS logs (
+        block_number INTEGER NOT NULL,
+        block_hash TEXT NOT NULL,
+        log_index INTEGER NOT NULL,
+        transaction_hash TEXT,
+        transaction_index INTEGER,
+        address TEXT NOT NULL,
+        topics TEXT NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (block_hash, log_index)
+    );
+    CREATE INDEX IF NOT EXISTS logs_block_number ON logs (block_number);
+";
+
+// SQLite file, the built-in store. Hashes and addresses are 0x hex text, topics and
+// transactions JSON. Calls block the task briefly; SQLite is local and each insert is
+// one transaction.
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    // Creates the file and tables when missing
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let connection = Connection::open(path).map_err(|error| Error::Config(format!("Failed to open {}: {}", path.display(), error)))?;
+        Self::with_connection(connection)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory().map_err(sqlite_error)?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SQLITE_SCHEMA).map_err(sqlite_error)?;
+        Ok(SqliteStore { connection: Mutex::new(connection) })
+    }
+
+    fn query_block(&self, sql: &str, params: impl rusqlite::Params) -> Result<Option<StoredBlock>> {
+        let connection = self.connection.lock().unwrap();
+        let row = connection.query_row(sql, params, |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))).optional().map_err(sqlite_error)?;
+        row.map(|(number, hash)| Ok(StoredBlock { number: number as u64, hash: parse_hash(&hash)? })).transpose()
+    }
+}
+
+#[async_trait]
+impl Store for SqliteStore {
+    async fn head(&mut self) -> Result<Option<StoredBlock>> {
+        self.query_block("SELECT number, hash FROM blocks ORDER BY number DESC LIMIT 1", [])
+    }
+
+    async fn block(&mut self, number: u64) -> Result<Option<StoredBlock>> {
+        self.query_block("SELECT number, hash FROM blocks WHERE number = ?1", [number as i64])
+    }
+
+    async fn insert(&mut self, blocks: &[BlockData]) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction().map_err(sqlite_error)?;
+        for block in blocks {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO blocks (number, hash, timestamp, transactions) VALUES (?1, ?2, ?3, ?4)",
+                    params![block.number as i64, format!("{:?}", block.hash), block.timestamp as i64, serde_json::Value::from(block.transactions.clone()).to_string()],
+                )
+                .map_err(sqlite_error)?;
+            for log in &block.logs {
+                transaction
+                    .execute(
+                        "INSERT OR REPLACE INTO logs (block_number, block_hash, log_index, transaction_hash, transaction_index, address, topics, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                        params![
+                            block.number as i64,
+                            format!("{:?}", block.hash),
+                            log.log_index.map_or(0, |index| index.as_u64()) as i64,
+                            log.transaction_hash.map(|hash| format!("{:?}", hash)),
+                            log.transaction_index.map(|index| index.as_u64() as i64),
+                            log.address.to_lowercase(),
+                            serde_json::to_string(&log.topics).map_err(|error| Error::Decode(error.to_string()))?,
+                            log.data,
+                        ],
+                    )
+                    .map_err(sqlite_error)?;
+            }
+        }
+        transaction.commit().map_err(sqlite_error)
+    }
+
+    async fn rewind(&mut self, number: u64) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction().map_err(sqlite_error)?;
+        transaction.execute("DELETE FROM logs WHERE block_number > ?1", [number as i64]).map_err(sqlite_error)?;
+        transaction.execute("DELETE FROM blocks WHERE number > ?1", [number as i64]).map_err(sqlite_error)?;
+        transaction.commit().map_err(sqlite_error)
+    }
+
+    async fn logs(&mut self, from: u64, to: u64) -> Result<Vec<Log>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT block_number, block_hash, log_index, transaction_hash, transaction_index, address, topics, data FROM logs WHERE block_number BETWEEN ?1 AND ?2 ORDER BY block_number, log_index")
+            .map_err(sqlite_error)?;
+        let rows = statement
+            .query_map([from as i64, to as i64], |row| {
+                Ok(serde_json::json!({
+                    "blockNumber": format!("0x{:x}", row.get::<_, i64>(0)?),
+                    "blockHash": row.get::<_, String>(1)?,
+                    "logIndex": format!("0x{:x}", row.get::<_, i64>(2)?),
+                    "transactionHash": row.get::<_, Option<String>>(3)?,
+                    "transactionIndex": row.get::<_, Option<i64>>(4)?.map(|index| format!("0x{:x}", index)),
+                    "address": row.get::<_, String>(5)?,
+                    "topics": serde_json::from_str::<serde_json::Value>(&row.get::<_, String>(6)?).unwrap_or_default(),
+                    "data": row.get::<_, String>(7)?,
+                }))
+            })
+            .map_err(sqlite_error)?;
+        rows.map(|row| serde_json::from_value(row.map_err(sqlite_error)?).map_err(|error| Error::Decode(format!("Invalid stored log: {}", error)))).collect()
+    }
+}
+
+fn sqlite_error(error: rusqlite::Error) -> Error {
+    Error::Io(std::io::Error::other(error))
+}
+
+fn parse_hash(hash: &str) -> Result<H256> {
+    hash.parse().map_err(|_| Error::Decode(format!("Invalid stored hash {}", hash)))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use ethabi::ethereum_types::U64;
+
+    use super::*;
+
+    pub(crate) fn block(number: u64, fork: u64) -> BlockData {
+        let hash = H256::from_low_u64_be(number * 100 + fork);
+        BlockData {
+            number,
+            hash,
+            timestamp: number * 12,
+            transactions: vec![serde_json::json!({"hash": H256::from_low_u64_be(number), "transactionIndex": "0x0"})],
+            logs: vec![Log {
+                address: "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string(),
+                topics: vec![H256::from_low_u64_be(1)],
+                data: "0x".to_string(),
+                block_number: Some(U64::from(number)),
+                block_hash: Some(hash),
+                transaction_hash: Some(H256::from_low_u64_be(number)),
+                transaction_index: Some(U64::zero()),
+                log_index: Some(U64::zero()),
+                removed: false,
+            }],
+        }
+    }
+
+    async fn exercise(store: &mut dyn Store) {
+        assert_eq!(store.head().await.unwrap(), None);
+        store.insert(&[block(1, 0), block(2, 0), block(3, 0)]).await.unwrap();
+        assert_eq!(store.head().await.unwrap().map(|head| head.number), Some(3));
+        assert_eq!(store.logs(2, 3).await.unwrap(), [block(2, 0).logs, block(3, 0).logs].concat());
+
+        store.rewind(1).await.unwrap();
+        store.insert(&[block(2, 1)]).await.unwrap();
+        assert_eq!(store.head().await.unwrap(), Some(StoredBlock { number: 2, hash: block(2, 1).hash }));
+        assert_eq!(store.block(3).await.unwrap(), None);
+        assert_eq!(store.logs(1, 3).await.unwrap(), [block(1, 0).logs, block(2, 1).logs].concat());
+    }
+
+    #[tokio::test]
+    async fn stores_blocks_and_rewinds() {
+        exercise(&mut MemoryStore::default()).await;
+        exercise(&mut SqliteStore::open_in_memory().unwrap()).await;
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+
+use ethabi::ethereum_types::{H256, U256};
+use serde::Deserialize;
+
+use crate::blocks::BlockTag;
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::headers::get_header;
+use crate::transaction::{TransactionRequest, TypedTransaction};
+use crate::wallet::Wallet;
+
+// Fee increase nodes require to accept a replacement at the same nonce. Geth asks for
+// 10%; a little more covers rounding and stricter pools.
+pub const REPLACEMENT_BUMP_PERCENT: u64 = 12;
+
+// A nonce the node has seen sent but not mined. Fees are known when the node exposes
+// its txpool; queued transactions wait behind a missing lower nonce.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StuckTransaction {
+    pub nonce: u64,
+    pub hash: Option<H256>,
+    pub gas_price: Option<U256>,
+    pub max_fee_per_gas: Option<U256>,
+    pub max_priority_fee_per_gas: Option<U256>,
+    pub queued: bool,
+}
+
+impl StuckTransaction {
+    // Fee cap and tip a replacement must beat, reading a legacy gas price as both
+    pub fn fees(&self) -> Option<(U256, U256)> {
+        match (self.max_fee_per_gas, self.max_priority_fee_per_gas, self.gas_price) {
+            (Some(max_fee), Some(tip), _) => Some((max_fee, tip)),
+            (_, _, Some(gas_price)) => Some((gas_price, gas_price)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PoolTransaction {
+    hash: Option<H256>,
+    gas_price: Option<U256>,
+    max_fee_per_gas: Option<U256>,
+    max_priority_fee_per_gas: Option<U256>,
+}
+
+#[derive(Deserialize, Default)]
+struct PoolContent {
+    #[serde(default)]
+    pending: BTreeMap<String, PoolTransaction>,
+    #[serde(default)]
+    queued: BTreeMap<String, PoolTransaction>,
+}
+
+// Lists `address`'s unmined nonces: those between its latest and pending transaction
+// counts, plus whatever txpool_contentFrom reports (geth, erigon and reth; other nodes
+// only give the nonces)
+pub async fn stuck_transactions(client: &EthClient, address: &str) -> Result<Vec<StuckTransaction>> {
+    let mined = client.get_transaction_count(address, BlockTag::Latest).await?;
+    let pending = client.get_transaction_count(address, BlockTag::Pending).await?;
+    let mut stuck: BTreeMap<u64, StuckTransaction> = (mined..pending).map(|nonce| (nonce, StuckTransaction { nonce, ..Default::default() })).collect();
+
+    let content = match client.send("txpool_contentFrom", vec![serde_json::json!(address)]).await {
+        Ok(content) => serde_json::from_value::<PoolContent>(content).unwrap_or_default(),
+        Err(_) => PoolContent::default(),
+    };
+    let pool = content.pending.into_iter().map(|entry| (entry, false)).chain(content.queued.into_iter().map(|entry| (entry, true)));
+    for ((nonce, transaction), queued) in pool {
+        let Ok(nonce) = nonce.parse::<u64>() else { continue };
+        if nonce < mined {
+            continue;
+        }
+        stuck.insert(
+            nonce,
+            StuckTransaction {
+                nonce,
+                hash: transaction.hash,
+                gas_price: transaction.gas_price,
+                max_fee_per_gas: transaction.max_fee_per_gas,
+                max_priority_fee_per_gas: transaction.max_priority_fee_per_gas,
+                queued,
+            },
+        );
+    }
+    Ok(stuck.into_values().collect())
+}
+
+// Nonces from `mined` through `through` (default: the highest one seen) that nothing
+// is pending at. Transactions queued above a gap never mine until it is filled.
+pub fn nonce_gaps(mined: u64, stuck: &[StuckTransaction], through: Option<u64>) -> Vec<u64> {
+    let Some(last) = through.or_else(|| stuck.iter().map(|stuck| stuck.nonce).max()) else {
+        return Vec::new();
+    };
+    (mined..=last).filter(|nonce| !stuck.iter().any(|stuck| stuck.nonce == *nonce)).collect()
+}
+
+// Fills the wallet's nonce gaps with empty self-transfers, each through the wallet's
+// confirmation hook. Fees are the node's suggested tip over the current base fee with
+// room for one block of increase, enough to mine without overpaying for a no-op.
+// Returns the nonces filled with their transaction hashes.
+pub async fn repair_nonces(wallet: &Wallet, through: Option<u64>) -> Result<Vec<(u64, H256)>> {
+    let client = wallet.client();
+    let address = wallet.address();
+    let mined = client.get_transaction_count(&address, BlockTag::Latest).await?;
+    let gaps = nonce_gaps(mined, &stuck_transactions(client, &address).await?, through);
+    if gaps.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut request = TransactionRequest {
+        to: Some(address.clone()),
+        ..Default::default()
+    }
+    .with_gas(21_000);
+    match get_header(client, "latest").await?.base_fee {
+        Some(base_fee) => {
+            let tip = client.max_priority_fee().await?;
+            request.max_priority_fee_per_gas = Some(tip);
+            let max_fee = base_fee.checked_mul(9.into()).and_then(|fee| (fee / 8).checked_add(tip));
+            request.max_fee_per_gas = Some(max_fee.ok_or_else(|| Error::Decode(format!("Max fee overflows with base fee {} and tip {}", base_fee, tip)))?);
+        }
+        None => request.gas_price = Some(client.gas_price().await?),
+    }
+
+    let mut filled = Vec::with_capacity(gaps.len());
+    for nonce in gaps {
+        let request = TransactionRequest { nonce: Some(nonce.into()), ..request.clone() };
+        let hash = wallet.send(&request).await.map_err(|error| match filled.len() {
+            0 => error,
+            done => Error::InvalidInput(format!("Filled {} gaps, then nonce {} failed: {}", done, nonce, error)),
+        })?;
+        filled.push((nonce, hash));
+    }
+    Ok(filled)
+}
+
+// Raises an unsigned transaction's fees to at least REPLACEMENT_BUMP_PERCENT over
+// `fees` (fee cap, tip), keeping them if already higher. Fails if a bumped fee doesn't
+// fit in a U256, as only a bogus pool entry would need.
+pub fn bump_fees(transaction: &mut TypedTransaction, fees: (U256, U256)) -> Result<()> {
+    let bump = |fee: U256| {
+        let bumped = fee.checked_mul((100 + REPLACEMENT_BUMP_PERCENT).into()).and_then(|fee| (fee / 100).checked_add(1.into()));
+        bumped.ok_or_else(|| Error::InvalidInput(format!("Fee {} is too high to bump", fee)))
+    };
+    let (max_fee, tip) = (bump(fees.0)?, bump(fees.1)?);
+    match transaction {
+        TypedTransaction::Legacy(transaction) => transaction.gas_price = transaction.gas_price.max(max_fee),
+        TypedTransaction::Eip2930(transaction) => transaction.gas_price = transaction.gas_price.max(max_fee),
+        TypedTransaction::Eip1559(transaction) => {
+            transaction.max_priority_fee_per_gas = transaction.max_priority_fee_per_gas.max(tip);
+            transaction.max_fee_per_gas = transaction.max_fee_per_gas.max(max_fee).max(transaction.max_priority_fee_per_gas);
+        }
+        TypedTransaction::Eip4844(transaction, _) => {
+            transaction.max_priority_fee_per_gas = transaction.max_priority_fee_per_gas.max(tip);
+            transaction.max_fee_per_gas = transaction.max_fee_per_gas.max(max_fee).max(transaction.max_priority_fee_per_gas);
+        }
+        TypedTransaction::Eip7702(transaction) => {
+            transaction.max_priority_fee_per_gas = transaction.max_priority_fee_per_gas.max(tip);
+            transaction.max_fee_per_gas = transaction.max_fee_per_gas.max(max_fee).max(transaction.max_priority_fee_per_gas);
+        }
+        TypedTransaction::Deposit(_) => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::testing::{london_header, Failure, MockNode};
+
+    // Account with 3 mined transactions, nonces 3 and 4 pending, and 7 queued behind
+    // gaps at 5 and 6, on a node with or without the txpool namespace.
+    // Nonce 4 pays legacy 20 gwei, the others EIP-1559 at 30 gwei cap and 2 gwei tip.
+    pub(crate) fn pool(txpool: bool) -> MockNode {
+        MockNode::new(move |method, params| {
+            let dynamic = |nonce: u64| serde_json::json!({"hash": H256::from_low_u64_be(nonce), "maxFeePerGas": "0x6fc23ac00", "maxPriorityFeePerGas": "0x77359400"});
+            match method {
+                "eth_getTransactionCount" if params[1] == "latest" => Ok(serde_json::json!("0x3")),
+                "eth_getTransactionCount" => Ok(serde_json::json!("0x5")),
+                "txpool_contentFrom" if txpool => Ok(serde_json::json!({
+                    "pending": {"3": dynamic(3), "4": {"hash": H256::from_low_u64_be(4), "gasPrice": "0x4a817c800"}},
+                    "queued": {"7": dynamic(7)},
+                })),
+                "txpool_contentFrom" => Err(Failure::rpc(-32601, "the method txpool_contentFrom does not exist")),
+                "eth_chainId" => Ok(serde_json::json!("0x1")),
+                "eth_maxPriorityFeePerGas" => Ok(serde_json::json!("0x3b9aca00")),
+                "eth_getBlockByNumber" => Ok(london_header("0x3b9aca00")),
+                "eth_sendRawTransaction" => Ok(serde_json::json!(TypedTransaction::from_hex(params[0].as_str().unwrap()).unwrap().hash())),
+                _ => Err(Failure::unexpected(method)),
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn lists_unmined_nonces() {
+        let address = "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f";
+        let stuck = stuck_transactions(&pool(true).client(), address).await.unwrap();
+        assert_eq!(stuck.iter().map(|stuck| (stuck.nonce, stuck.queued)).collect::<Vec<_>>(), [(3, false), (4, false), (7, true)]);
+        assert_eq!(stuck[1].fees(), Some((U256::from(20_000_000_000u64), U256::from(20_000_000_000u64))));
+        assert_eq!(stuck[2].hash, Some(H256::from_low_u64_be(7)));
+
+        let bare = stuck_transactions(&pool(false).client(), address).await.unwrap();
+        assert_eq!(bare, [StuckTransaction { nonce: 3, ..Default::default() }, StuckTransaction { nonce: 4, ..Default::default() }]);
+
+        assert_eq!(nonce_gaps(3, &stuck, None), [5, 6]);
+        assert_eq!(nonce_gaps(3, &bare, Some(6)), [5, 6]);
+        assert!(nonce_gaps(3, &[], None).is_empty());
+    }
+
+    #[test]
+    fn bumps_fees_and_rejects_overflow() {
+        let request = TransactionRequest {
+            chain_id: Some(1.into()),
+            nonce: Some(4.into()),
+            max_fee_per_gas: Some(U256::from(50)),
+            max_priority_fee_per_gas: Some(U256::from(1)),
+            ..Default::default()
+        }
+        .with_gas(21_000);
+        let mut transaction = TypedTransaction::from_request(&request).unwrap();
+        bump_fees(&mut transaction, (U256::from(100), U256::from(10))).unwrap();
+        assert_eq!(transaction.max_fee_per_gas(), U256::from(113));
+
+        assert!(matches!(bump_fees(&mut transaction, (U256::MAX / 2, U256::one())), Err(Error::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn fills_gaps_with_cheap_self_transfers() {
+        let key = crate::key::SecretKey::from_hex(&"46".repeat(32)).unwrap();
+        let wallet = Wallet::new(&pool(true).client(), key);
+        let filled = wallet.clone().with_confirmation(std::sync::Arc::new(|summary: &crate::confirm::TransactionSummary| {
+            // 1 gwei base fee plus an eighth, and the 1 gwei tip
+            summary.to.as_deref() == Some(summary.from.as_str()) && summary.max_fee_per_gas == U256::from(2_125_000_000u64) && summary.gas_limit == 21_000
+        }));
+        let filled = repair_nonces(&filled, None).await.unwrap();
+        assert_eq!(filled.iter().map(|(nonce, _)| *nonce).collect::<Vec<_>>(), [5, 6]);
+    }
+}
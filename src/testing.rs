@@ -0,0 +1,112 @@
+// Fixtures shared by the unit tests
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ethabi::ethereum_types::H256;
+use serde_json::{json, Value};
+
+use crate::client::EthClient;
+use crate::transport::Transport;
+
+// How a mock node fails a request: with a JSON-RPC error object, or with no response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Failure {
+    Rpc(i64, String),
+    Transport(String),
+}
+
+impl Failure {
+    pub(crate) fn rpc(code: i64, message: &str) -> Self {
+        Failure::Rpc(code, message.to_string())
+    }
+
+    pub(crate) fn reverted() -> Self {
+        Failure::rpc(3, "execution reverted")
+    }
+
+    // For methods a test doesn't expect to be called
+    pub(crate) fn unexpected(method: &str) -> Self {
+        Failure::Transport(format!("unexpected {}", method))
+    }
+}
+
+pub(crate) type Reply = Result<Value, Failure>;
+
+type Handler = dyn Fn(&str, &[Value]) -> Reply + Send + Sync;
+
+type Calls = Arc<Mutex<Vec<(String, Vec<Value>)>>>;
+
+// A node answering each request from a closure of method and params, with the result or
+// error wrapped in a JSON-RPC envelope. Every call is recorded; clones share the handler
+// and the record.
+#[derive(Clone)]
+pub(crate) struct MockNode {
+    handler: Arc<Handler>,
+    calls: Calls,
+    latency: Duration,
+}
+
+impl MockNode {
+    pub(crate) fn new(handler: impl Fn(&str, &[Value]) -> Reply + Send + Sync + 'static) -> Self {
+        MockNode {
+            handler: Arc::new(handler),
+            calls: Arc::default(),
+            latency: Duration::ZERO,
+        }
+    }
+
+    // How long each response takes, after the call is recorded
+    pub(crate) fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    // Fixed results by method; any other method fails
+    pub(crate) fn with_results(results: impl IntoIterator<Item = (&'static str, Value)>) -> Self {
+        let results: Vec<(&str, Value)> = results.into_iter().collect();
+        MockNode::new(move |method, _| results.iter().find(|(name, _)| *name == method).map(|(_, result)| result.clone()).ok_or_else(|| Failure::unexpected(method)))
+    }
+
+    pub(crate) fn client(&self) -> EthClient {
+        EthClient::with_transport(self.clone())
+    }
+
+    pub(crate) fn calls(&self) -> Vec<(String, Vec<Value>)> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    // Params of every call to `method`, in order
+    pub(crate) fn params_of(&self, method: &str) -> Vec<Vec<Value>> {
+        self.calls().into_iter().filter(|(name, _)| name == method).map(|(_, params)| params).collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for MockNode {
+    async fn request(&self, method: &str, params: Vec<Value>) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        let reply = (self.handler)(method, &params);
+        self.calls.lock().unwrap().push((method.to_string(), params));
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+        match reply {
+            Ok(result) => Ok(json!({"jsonrpc": "2.0", "id": 1, "result": result})),
+            Err(Failure::Rpc(code, message)) => Ok(json!({"jsonrpc": "2.0", "id": 1, "error": {"code": code, "message": message}})),
+            Err(Failure::Transport(message)) => Err(message.into()),
+        }
+    }
+}
+
+// Header of block 1 at the given base fee, for fee filling
+pub(crate) fn london_header(base_fee: &str) -> Value {
+    json!({
+        "number": "0x1",
+        "hash": H256::zero(),
+        "parentHash": H256::zero(),
+        "timestamp": "0x0",
+        "gasUsed": "0x0",
+        "gasLimit": "0x1c9c380",
+        "baseFeePerGas": base_fee,
+    })
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use ethabi::ethereum_types::{U256, U64};
+use ethabi::{Function, Token};
+use serde::Deserialize;
+
+use crate::client::EthClient;
+use crate::error::{Error, Result};
+use crate::labels::LabelRegistry;
+
+const RED: &str = "\x1b[31m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+// One frame of geth's callTracer output
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    // CALL, STATICCALL, DELEGATECALL, CREATE, ...
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub from: String,
+    // Missing for a failed CREATE
+    pub to: Option<String>,
+    pub value: Option<U256>,
+    pub gas: U64,
+    pub gas_used: U64,
+    pub input: String,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub revert_reason: Option<String>,
+    #[serde(default)]
+    pub calls: Vec<CallFrame>,
+}
+
+impl CallFrame {
+    pub fn is_reverted(&self) -> bool {
+        self.error.is_some()
+    }
+}
+
+// Needs a node with the debug namespace enabled
+pub async fn trace_transaction(client: &EthClient, tx_hash: &str) -> Result<CallFrame> {
+    let trace = client.send("debug_traceTransaction", vec![serde_json::json!(tx_hash), serde_json::json!({ "tracer": "callTracer" })]).await?;
+    serde_json::from_value(trace).map_err(|error| Error::Decode(format!("Invalid callTracer output: {}", error)))
+}
+
+// Renders call traces as an indented tree:
+//
+// [46109] USDC (0xa0b8...)::transfer(to: 0x1234..., value: 1000)
+// ├─ [38926] 0x43506849...::transfer(to: 0x1234..., value: 1000) [delegatecall]
+// │  └─ ← (true)
+// └─ ← (true)
+//
+// Functions are decoded with the registered ABIs; unknown calls show the raw calldata.
+pub struct TracePrinter {
+    chain_id: u64,
+    labels: LabelRegistry,
+    functions: HashMap<[u8; 4], Function>,
+    color: bool,
+}
+
+impl TracePrinter {
+    pub fn new(chain_id: u64) -> Self {
+        TracePrinter {
+            chain_id,
+            labels: LabelRegistry::new(),
+            functions: HashMap::new(),
+            color: false,
+        }
+    }
+
+    pub fn with_labels(mut self, labels: LabelRegistry) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    pub fn with_abi(mut self, abi: &ethabi::Contract) -> Self {
+        for function in abi.functions() {
+            self.functions.insert(function.short_signature(), function.clone());
+        }
+        self
+    }
+
+    // ANSI colors: reverts in red, gas dimmed
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn format(&self, frame: &CallFrame) -> String {
+        let mut out = String::new();
+        self.format_frame(frame, "", "", &mut out);
+        out
+    }
+
+    fn format_frame(&self, frame: &CallFrame, first_prefix: &str, prefix: &str, out: &mut String) {
+        let input = decode_hex(&frame.input);
+        let function = input.get(..4).and_then(|selector| self.functions.get(selector));
+        let target = frame.to.as_deref().map_or_else(|| "<create>".to_string(), |to| self.labels.format_address(self.chain_id, to));
+
+        let call = if frame.call_type.starts_with("CREATE") {
+            format!("new {}", target)
+        } else {
+            match function {
+                Some(function) => {
+                    let arguments = match function.decode_input(&input[4..]) {
+                        Ok(tokens) => function.inputs.iter().zip(&tokens).map(|(param, token)| self.named_token(&param.name, token)).collect::<Vec<_>>().join(", "),
+                        Err(_) => format!("0x{}", hex::encode(&input[4..])),
+                    };
+                    format!("{}::{}({})", target, function.name, arguments)
+                }
+                None if input.is_empty() => format!("{}::fallback()", target),
+                None => format!("{}::0x{}(0x{})", target, hex::encode(&input[..4.min(input.len())]), hex::encode(input.get(4..).unwrap_or_default())),
+            }
+        };
+
+        let _ = write!(out, "{}{} {}", first_prefix, self.paint(DIM, &format!("[{}]", frame.gas_used)), call);
+        if let Some(value) = frame.value.filter(|value| !value.is_zero()) {
+            let _ = write!(out, " {{value: {}}}", value);
+        }
+        if !matches!(frame.call_type.as_str(), "CALL" | "CREATE" | "CREATE2") {
+            let _ = write!(out, " [{}]", frame.call_type.to_lowercase());
+        }
+        out.push('\n');
+
+        let child_prefix = format!("{}│  ", prefix);
+        for call in &frame.calls {
+            self.format_frame(call, &format!("{}├─ ", prefix), &child_prefix, out);
+        }
+
+        let result = match (&frame.error, function) {
+            (Some(error), _) => self.paint(RED, &format!("[Revert] {}", frame.revert_reason.as_deref().unwrap_or(error))),
+            (None, Some(function)) => {
+                let output = decode_hex(frame.output.as_deref().unwrap_or_default());
+                match function.decode_output(&output) {
+                    Ok(tokens) => format!("({})", function.outputs.iter().zip(&tokens).map(|(param, token)| self.named_token(&param.name, token)).collect::<Vec<_>>().join(", ")),
+                    Err(_) => format!("0x{}", hex::encode(output)),
+                }
+            }
+            (None, None) => frame.output.clone().unwrap_or_else(|| "()".to_string()),
+        };
+        let _ = writeln!(out, "{}└─ ← {}", prefix, result);
+    }
+
+    fn named_token(&self, name: &str, token: &Token) -> String {
+        if name.is_empty() {
+            return self.format_token(token);
+        }
+        format!("{}: {}", name, self.format_token(token))
+    }
+
+    fn format_token(&self, token: &Token) -> String {
+        match token {
+            Token::Address(address) => self.labels.format_address(self.chain_id, &format!("{:?}", address)),
+            Token::Uint(value) => value.to_string(),
+            Token::Int(value) if value.bit(255) => format!("-{}", (!*value).overflowing_add(U256::one()).0),
+            Token::Int(value) => value.to_string(),
+            Token::Bool(value) => value.to_string(),
+            Token::String(value) => format!("{:?}", value),
+            Token::Bytes(bytes) | Token::FixedBytes(bytes) => format!("0x{}", hex::encode(bytes)),
+            Token::Array(tokens) | Token::FixedArray(tokens) => format!("[{}]", tokens.iter().map(|token| self.format_token(token)).collect::<Vec<_>>().join(", ")),
+            Token::Tuple(tokens) => format!("({})", tokens.iter().map(|token| self.format_token(token)).collect::<Vec<_>>().join(", ")),
+        }
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+fn decode_hex(value: &str) -> Vec<u8> {
+    hex::decode(value.trim_start_matches("0x")).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const TOKEN: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const IMPLEMENTATION: &str = "0x43506849d7c04f9138d1a2050bbf3a0c054402dd";
+
+    fn transfer() -> ethabi::Contract {
+        serde_json::from_value(json!([{
+            "type": "function",
+            "name": "transfer",
+            "inputs": [{"name": "to", "type": "address"}, {"name": "value", "type": "uint256"}],
+            "outputs": [{"name": "", "type": "bool"}],
+            "stateMutability": "nonpayable",
+        }]))
+        .unwrap()
+    }
+
+    fn frame(call_type: &str, to: &str, gas_used: u64, calls: Vec<CallFrame>) -> CallFrame {
+        let input = transfer().function("transfer").unwrap().encode_input(&[Token::Address(ethabi::Address::repeat_byte(0x12)), Token::Uint(1000.into())]).unwrap();
+        let mut frame: CallFrame = serde_json::from_value(json!({
+            "type": call_type,
+            "from": "0x1111111111111111111111111111111111111111",
+            "to": to,
+            "gas": "0x10000",
+            "gasUsed": format!("0x{:x}", gas_used),
+            "input": format!("0x{}", hex::encode(input)),
+            "output": format!("0x{:064x}", 1),
+        }))
+        .unwrap();
+        frame.calls = calls;
+        frame
+    }
+
+    #[test]
+    fn prints_decoded_call_trees() {
+        let trace = frame("CALL", TOKEN, 46109, vec![frame("DELEGATECALL", IMPLEMENTATION, 38926, Vec::new())]);
+        let printed = TracePrinter::new(1).with_abi(&transfer()).format(&trace);
+        let to = LabelRegistry::new().format_address(1, "0x1212121212121212121212121212121212121212");
+        let (token, implementation) = (LabelRegistry::new().format_address(1, TOKEN), LabelRegistry::new().format_address(1, IMPLEMENTATION));
+        let expected = format!(
+            "[46109] {token}::transfer(to: {to}, value: 1000)\n├─ [38926] {implementation}::transfer(to: {to}, value: 1000) [delegatecall]\n│  └─ ← (true)\n└─ ← (true)\n",
+        );
+        assert_eq!(printed, expected);
+
+        // Without the ABI the raw calldata shows, and a revert shows its reason
+        let mut reverted = frame("CALL", TOKEN, 100, Vec::new());
+        (reverted.error, reverted.revert_reason) = (Some("execution reverted".to_string()), Some("ERC20: insufficient balance".to_string()));
+        assert!(reverted.is_reverted());
+        let printed = TracePrinter::new(1).with_color(true).format(&reverted);
+        assert!(printed.contains("::0xa9059cbb(0x"), "{}", printed);
+        assert!(printed.ends_with(&format!("└─ ← {}[Revert] ERC20: insufficient balance{}\n", RED, RESET)), "{}", printed);
+    }
+}
@@ -0,0 +1,711 @@
+use ethabi::ethereum_types::{H160, H256, U256, U64};
+use serde::{Deserialize, Serialize};
+
+use crate::abi::encode_function_call;
+use crate::error::{Error, Result};
+use crate::gas::AccessListItem;
+use crate::impl_rlp;
+use crate::key::SecretKey;
+use crate::logs::Log;
+use crate::rlp::{self, Decodable, Encodable, Item};
+use crate::signature::recover_address;
+
+pub const LEGACY_TX_TYPE: u8 = 0x00;
+pub const EIP2930_TX_TYPE: u8 = 0x01;
+pub const EIP1559_TX_TYPE: u8 = 0x02;
+pub const EIP4844_TX_TYPE: u8 = 0x03;
+pub const EIP7702_TX_TYPE: u8 = 0x04;
+// OP-stack L1-to-L2 deposits
+pub const DEPOSIT_TX_TYPE: u8 = 0x7e;
+
+// Call/transaction object as accepted by eth_call, eth_estimateGas and eth_sendTransaction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    // None for contract creation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas: Option<U64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_price: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_priority_fee_per_gas: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<U256>,
+    // 0x-prefixed calldata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<U64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_list: Option<Vec<AccessListItem>>,
+}
+
+impl TransactionRequest {
+    // Contract call with calldata from encode_function_call, e.g.
+    // TransactionRequest::call(token, "transfer(address,uint256)", vec![to, amount])
+    pub fn call(to: &str, method_signature: &str, params: Vec<String>) -> crate::Result<Self> {
+        Ok(TransactionRequest {
+            to: Some(to.to_string()),
+            data: Some(encode_function_call(method_signature, params)?),
+            ..Default::default()
+        })
+    }
+
+    pub fn with_from(mut self, from: &str) -> Self {
+        self.from = Some(from.to_string());
+        self
+    }
+
+    pub fn with_value(mut self, value: U256) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    pub fn with_gas(mut self, gas: u64) -> Self {
+        self.gas = Some(gas.into());
+        self
+    }
+}
+
+// eth_getTransactionReceipt result
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionReceipt {
+    pub transaction_hash: H256,
+    pub transaction_index: U64,
+    pub block_hash: H256,
+    pub block_number: U64,
+    pub from: String,
+    pub to: Option<String>,
+    pub cumulative_gas_used: U256,
+    pub gas_used: U256,
+    pub effective_gas_price: Option<U256>,
+    pub contract_address: Option<String>,
+    pub logs: Vec<Log>,
+    // 1 for success, 0 for failure; missing before Byzantium
+    pub status: Option<U64>,
+}
+
+impl TransactionReceipt {
+    pub fn is_success(&self) -> bool {
+        self.status.is_none_or(|status| status == U64::one())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessListEntry {
+    pub address: H160,
+    pub storage_keys: Vec<H256>,
+}
+
+impl_rlp!(AccessListEntry { address, storage_keys });
+
+// v is 27/28, or chain_id * 2 + 35/36 under EIP-155
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyTransaction {
+    pub nonce: u64,
+    pub gas_price: U256,
+    pub gas_limit: u64,
+    pub to: Option<H160>,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub v: u64,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl_rlp!(LegacyTransaction { nonce, gas_price, gas_limit, to, value, data, v, r, s });
+
+impl LegacyTransaction {
+    // EIP-155 chain id; before signing, v holds the chain id itself
+    pub fn chain_id(&self) -> Option<u64> {
+        match self.v {
+            chain_id if self.r.is_zero() => (chain_id != 0).then_some(chain_id),
+            v if v >= 35 => Some((v - 35) / 2),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Eip2930Transaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub gas_price: U256,
+    pub gas_limit: u64,
+    pub to: Option<H160>,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub access_list: Vec<AccessListEntry>,
+    pub y_parity: bool,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl_rlp!(Eip2930Transaction { chain_id, nonce, gas_price, gas_limit, to, value, data, access_list, y_parity, r, s });
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Eip1559Transaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: u64,
+    pub to: Option<H160>,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub access_list: Vec<AccessListEntry>,
+    pub y_parity: bool,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl_rlp!(Eip1559Transaction { chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas_limit, to, value, data, access_list, y_parity, r, s });
+
+// Blob transactions cannot create contracts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Eip4844Transaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: u64,
+    pub to: H160,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub access_list: Vec<AccessListEntry>,
+    pub max_fee_per_blob_gas: U256,
+    pub blob_versioned_hashes: Vec<H256>,
+    pub y_parity: bool,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl_rlp!(Eip4844Transaction { chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas_limit, to, value, data, access_list, max_fee_per_blob_gas, blob_versioned_hashes, y_parity, r, s });
+
+// Blob data gossiped alongside a blob transaction. wrapper_version is only present in
+// the EIP-7594 form, which carries cell proofs instead of one proof per blob.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobSidecar {
+    pub wrapper_version: Option<u64>,
+    pub blobs: Vec<Vec<u8>>,
+    pub commitments: Vec<Vec<u8>>,
+    pub proofs: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Authorization {
+    pub chain_id: U256,
+    pub address: H160,
+    pub nonce: u64,
+    pub y_parity: u64,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl_rlp!(Authorization { chain_id, address, nonce, y_parity, r, s });
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Eip7702Transaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: u64,
+    pub to: H160,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub access_list: Vec<AccessListEntry>,
+    pub authorization_list: Vec<Authorization>,
+    pub y_parity: bool,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl_rlp!(Eip7702Transaction { chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas_limit, to, value, data, access_list, authorization_list, y_parity, r, s });
+
+// Deposits are unsigned; the sequencer derives them from L1 events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepositTransaction {
+    pub source_hash: H256,
+    pub from: H160,
+    pub to: Option<H160>,
+    pub mint: U256,
+    pub value: U256,
+    pub gas_limit: u64,
+    pub is_system_tx: bool,
+    pub data: Vec<u8>,
+}
+
+impl_rlp!(DepositTransaction { source_hash, from, to, mint, value, gas_limit, is_system_tx, data });
+
+// A raw transaction as broadcast with eth_sendRawTransaction. Decoding only accepts
+// canonical RLP, so encode() reproduces the input byte for byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypedTransaction {
+    Legacy(LegacyTransaction),
+    Eip2930(Eip2930Transaction),
+    Eip1559(Eip1559Transaction),
+    Eip4844(Eip4844Transaction, Option<BlobSidecar>),
+    Eip7702(Eip7702Transaction),
+    Deposit(DepositTransaction),
+}
+
+impl TypedTransaction {
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let (&tx_type, payload) = bytes.split_first().ok_or_else(|| Error::Decode("Empty transaction".to_string()))?;
+        if tx_type >= 0xc0 {
+            return Ok(TypedTransaction::Legacy(rlp::decode(bytes)?));
+        }
+        match tx_type {
+            EIP2930_TX_TYPE => Ok(TypedTransaction::Eip2930(rlp::decode(payload)?)),
+            EIP1559_TX_TYPE => Ok(TypedTransaction::Eip1559(rlp::decode(payload)?)),
+            EIP4844_TX_TYPE => decode_blob_transaction(payload),
+            EIP7702_TX_TYPE => Ok(TypedTransaction::Eip7702(rlp::decode(payload)?)),
+            DEPOSIT_TX_TYPE => Ok(TypedTransaction::Deposit(rlp::decode(payload)?)),
+            _ => Err(Error::Decode(format!("Unknown transaction type 0x{:02x}", tx_type))),
+        }
+    }
+
+    pub fn from_hex(raw: &str) -> Result<Self> {
+        Self::decode(&hex::decode(raw.trim_start_matches("0x")).map_err(|error| Error::Decode(format!("Invalid transaction hex: {}", error)))?)
+    }
+
+    pub fn tx_type(&self) -> u8 {
+        match self {
+            TypedTransaction::Legacy(_) => LEGACY_TX_TYPE,
+            TypedTransaction::Eip2930(_) => EIP2930_TX_TYPE,
+            TypedTransaction::Eip1559(_) => EIP1559_TX_TYPE,
+            TypedTransaction::Eip4844(..) => EIP4844_TX_TYPE,
+            TypedTransaction::Eip7702(_) => EIP7702_TX_TYPE,
+            TypedTransaction::Deposit(_) => DEPOSIT_TX_TYPE,
+        }
+    }
+
+    // Builds the unsigned transaction a fully filled request describes: EIP-1559 when it
+    // sets max_fee_per_gas, EIP-2930 for a gas price with an access list, EIP-155 legacy
+    // for a bare gas price. Chain id, nonce and gas must be set.
+    pub fn from_request(request: &TransactionRequest) -> Result<Self> {
+        let missing = |field: &str| Error::InvalidInput(format!("Transaction request has no {}", field));
+        let chain_id = request.chain_id.ok_or_else(|| missing("chainId"))?.as_u64();
+        let nonce = request.nonce.ok_or_else(|| missing("nonce"))?.as_u64();
+        let gas_limit = request.gas.ok_or_else(|| missing("gas"))?.as_u64();
+        let to = request.to.as_deref().map(parse_address).transpose()?;
+        let value = request.value.unwrap_or_default();
+        let data = match &request.data {
+            Some(data) => hex::decode(data.trim_start_matches("0x")).map_err(|_| Error::InvalidInput(format!("Invalid calldata {}", data)))?,
+            None => Vec::new(),
+        };
+        let access_list = request
+            .access_list
+            .iter()
+            .flatten()
+            .map(|item| {
+                let storage_keys = item.storage_keys.iter().map(|key| key.parse().map_err(|_| Error::InvalidInput(format!("Invalid storage key {}", key)))).collect::<Result<_>>()?;
+                Ok(AccessListEntry { address: parse_address(&item.address)?, storage_keys })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(match (request.max_fee_per_gas, request.gas_price) {
+            (Some(max_fee_per_gas), _) => TypedTransaction::Eip1559(Eip1559Transaction {
+                chain_id,
+                nonce,
+                max_priority_fee_per_gas: request.max_priority_fee_per_gas.ok_or_else(|| missing("maxPriorityFeePerGas"))?,
+                max_fee_per_gas,
+                gas_limit,
+                to,
+                value,
+                data,
+                access_list,
+                y_parity: false,
+                r: U256::zero(),
+                s: U256::zero(),
+            }),
+            (None, Some(gas_price)) if !access_list.is_empty() => TypedTransaction::Eip2930(Eip2930Transaction {
+                chain_id,
+                nonce,
+                gas_price,
+                gas_limit,
+                to,
+                value,
+                data,
+                access_list,
+                y_parity: false,
+                r: U256::zero(),
+                s: U256::zero(),
+            }),
+            (None, Some(gas_price)) => TypedTransaction::Legacy(LegacyTransaction {
+                nonce,
+                gas_price,
+                gas_limit,
+                to,
+                value,
+                data,
+                v: chain_id,
+                r: U256::zero(),
+                s: U256::zero(),
+            }),
+            (None, None) => return Err(missing("gasPrice or maxFeePerGas")),
+        })
+    }
+
+    pub fn chain_id(&self) -> Option<u64> {
+        match self {
+            TypedTransaction::Legacy(transaction) => transaction.chain_id(),
+            TypedTransaction::Eip2930(transaction) => Some(transaction.chain_id),
+            TypedTransaction::Eip1559(transaction) => Some(transaction.chain_id),
+            TypedTransaction::Eip4844(transaction, _) => Some(transaction.chain_id),
+            TypedTransaction::Eip7702(transaction) => Some(transaction.chain_id),
+            TypedTransaction::Deposit(_) => None,
+        }
+    }
+
+    // Deposits have no nonce of their own
+    pub fn nonce(&self) -> Option<u64> {
+        match self {
+            TypedTransaction::Legacy(transaction) => Some(transaction.nonce),
+            TypedTransaction::Eip2930(transaction) => Some(transaction.nonce),
+            TypedTransaction::Eip1559(transaction) => Some(transaction.nonce),
+            TypedTransaction::Eip4844(transaction, _) => Some(transaction.nonce),
+            TypedTransaction::Eip7702(transaction) => Some(transaction.nonce),
+            TypedTransaction::Deposit(_) => None,
+        }
+    }
+
+    pub fn to(&self) -> Option<H160> {
+        match self {
+            TypedTransaction::Legacy(transaction) => transaction.to,
+            TypedTransaction::Eip2930(transaction) => transaction.to,
+            TypedTransaction::Eip1559(transaction) => transaction.to,
+            TypedTransaction::Eip4844(transaction, _) => Some(transaction.to),
+            TypedTransaction::Eip7702(transaction) => Some(transaction.to),
+            TypedTransaction::Deposit(transaction) => transaction.to,
+        }
+    }
+
+    pub fn value(&self) -> U256 {
+        match self {
+            TypedTransaction::Legacy(transaction) => transaction.value,
+            TypedTransaction::Eip2930(transaction) => transaction.value,
+            TypedTransaction::Eip1559(transaction) => transaction.value,
+            TypedTransaction::Eip4844(transaction, _) => transaction.value,
+            TypedTransaction::Eip7702(transaction) => transaction.value,
+            TypedTransaction::Deposit(transaction) => transaction.value,
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        match self {
+            TypedTransaction::Legacy(transaction) => &transaction.data,
+            TypedTransaction::Eip2930(transaction) => &transaction.data,
+            TypedTransaction::Eip1559(transaction) => &transaction.data,
+            TypedTransaction::Eip4844(transaction, _) => &transaction.data,
+            TypedTransaction::Eip7702(transaction) => &transaction.data,
+            TypedTransaction::Deposit(transaction) => &transaction.data,
+        }
+    }
+
+    pub fn gas_limit(&self) -> u64 {
+        match self {
+            TypedTransaction::Legacy(transaction) => transaction.gas_limit,
+            TypedTransaction::Eip2930(transaction) => transaction.gas_limit,
+            TypedTransaction::Eip1559(transaction) => transaction.gas_limit,
+            TypedTransaction::Eip4844(transaction, _) => transaction.gas_limit,
+            TypedTransaction::Eip7702(transaction) => transaction.gas_limit,
+            TypedTransaction::Deposit(transaction) => transaction.gas_limit,
+        }
+    }
+
+    // Most the sender can pay per gas: the gas price, or the EIP-1559 fee cap (blob gas
+    // not included). Zero for deposits, which are paid for on L1.
+    pub fn max_fee_per_gas(&self) -> U256 {
+        match self {
+            TypedTransaction::Legacy(transaction) => transaction.gas_price,
+            TypedTransaction::Eip2930(transaction) => transaction.gas_price,
+            TypedTransaction::Eip1559(transaction) => transaction.max_fee_per_gas,
+            TypedTransaction::Eip4844(transaction, _) => transaction.max_fee_per_gas,
+            TypedTransaction::Eip7702(transaction) => transaction.max_fee_per_gas,
+            TypedTransaction::Deposit(_) => U256::zero(),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        if let TypedTransaction::Eip4844(transaction, Some(sidecar)) = self {
+            let mut payload = Vec::new();
+            transaction.rlp_append(&mut payload);
+            if let Some(version) = sidecar.wrapper_version {
+                version.rlp_append(&mut payload);
+            }
+            sidecar.blobs.rlp_append(&mut payload);
+            sidecar.commitments.rlp_append(&mut payload);
+            sidecar.proofs.rlp_append(&mut payload);
+            let mut out = vec![EIP4844_TX_TYPE];
+            rlp::append_list_payload(&payload, &mut out);
+            return out;
+        }
+        self.encode_canonical()
+    }
+
+    pub fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.encode()))
+    }
+
+    // Digest the sender signs: the payload without its signature fields. An unsigned
+    // legacy transaction carries its EIP-155 chain id in v (0 for none).
+    pub fn signature_hash(&self) -> Result<H256> {
+        let mut out = Vec::new();
+        match self {
+            TypedTransaction::Legacy(transaction) => {
+                let item = rlp::decode_item(&rlp::encode(transaction))?;
+                let mut fields = item.as_list()?[..6].to_vec();
+                if let Some(chain_id) = transaction.chain_id() {
+                    fields.extend([rlp::decode_item(&rlp::encode(&chain_id))?, Item::Bytes(Vec::new()), Item::Bytes(Vec::new())]);
+                }
+                Item::List(fields).rlp_append(&mut out);
+            }
+            TypedTransaction::Deposit(_) => return Err(Error::Unsupported("Deposit transactions are not signed".to_string())),
+            _ => {
+                let canonical = self.encode_canonical();
+                let item = rlp::decode_item(&canonical[1..])?;
+                let fields = item.as_list()?;
+                out.push(self.tx_type());
+                Item::List(fields[..fields.len() - 3].to_vec()).rlp_append(&mut out);
+            }
+        }
+        Ok(H256::from_slice(keccak_hash::keccak(out).as_bytes()))
+    }
+
+    // Signs with `key`, replacing any existing signature
+    pub fn sign(mut self, key: &SecretKey) -> Result<Self> {
+        let signature = key.sign_hash(&self.signature_hash()?.0)?;
+        let (r, s, odd) = (U256::from_big_endian(&signature[..32]), U256::from_big_endian(&signature[32..64]), signature[64] == 28);
+        match &mut self {
+            TypedTransaction::Legacy(transaction) => {
+                transaction.v = match transaction.chain_id() {
+                    Some(chain_id) => chain_id * 2 + 35 + odd as u64,
+                    None => 27 + odd as u64,
+                };
+                (transaction.r, transaction.s) = (r, s);
+            }
+            TypedTransaction::Eip2930(transaction) => (transaction.y_parity, transaction.r, transaction.s) = (odd, r, s),
+            TypedTransaction::Eip1559(transaction) => (transaction.y_parity, transaction.r, transaction.s) = (odd, r, s),
+            TypedTransaction::Eip4844(transaction, _) => (transaction.y_parity, transaction.r, transaction.s) = (odd, r, s),
+            TypedTransaction::Eip7702(transaction) => (transaction.y_parity, transaction.r, transaction.s) = (odd, r, s),
+            TypedTransaction::Deposit(_) => return Err(Error::Unsupported("Deposit transactions are not signed".to_string())),
+        }
+        Ok(self)
+    }
+
+    // Sender recovered from the signature; deposits name theirs
+    pub fn recover_sender(&self) -> Result<String> {
+        let (r, s, odd) = match self {
+            TypedTransaction::Legacy(transaction) => (transaction.r, transaction.s, if transaction.v >= 35 { (transaction.v - 35) % 2 == 1 } else { transaction.v == 28 }),
+            TypedTransaction::Eip2930(transaction) => (transaction.r, transaction.s, transaction.y_parity),
+            TypedTransaction::Eip1559(transaction) => (transaction.r, transaction.s, transaction.y_parity),
+            TypedTransaction::Eip4844(transaction, _) => (transaction.r, transaction.s, transaction.y_parity),
+            TypedTransaction::Eip7702(transaction) => (transaction.r, transaction.s, transaction.y_parity),
+            TypedTransaction::Deposit(transaction) => return Ok(format!("{:?}", transaction.from)),
+        };
+        if r.is_zero() {
+            return Err(Error::Signature("Transaction is not signed".to_string()));
+        }
+        let mut signature = [0u8; 65];
+        r.to_big_endian(&mut signature[..32]);
+        s.to_big_endian(&mut signature[32..64]);
+        signature[64] = odd as u8;
+        recover_address(&self.signature_hash()?.0, &signature)
+    }
+
+    // Transaction hash; blob sidecars are not part of it
+    pub fn hash(&self) -> H256 {
+        H256::from_slice(keccak_hash::keccak(self.encode_canonical()).as_bytes())
+    }
+
+    fn encode_canonical(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if self.tx_type() != LEGACY_TX_TYPE {
+            out.push(self.tx_type());
+        }
+        match self {
+            TypedTransaction::Legacy(transaction) => transaction.rlp_append(&mut out),
+            TypedTransaction::Eip2930(transaction) => transaction.rlp_append(&mut out),
+            TypedTransaction::Eip1559(transaction) => transaction.rlp_append(&mut out),
+            TypedTransaction::Eip4844(transaction, _) => transaction.rlp_append(&mut out),
+            TypedTransaction::Eip7702(transaction) => transaction.rlp_append(&mut out),
+            TypedTransaction::Deposit(transaction) => transaction.rlp_append(&mut out),
+        }
+        out
+    }
+}
+
+fn parse_address(address: &str) -> Result<H160> {
+    address.trim_start_matches("0x").parse().map_err(|_| Error::InvalidInput(format!("Invalid address {}", address)))
+}
+
+// Blob transactions come either bare or, in the network form, wrapped in a list whose
+// first element is the transaction itself
+fn decode_blob_transaction(payload: &[u8]) -> Result<TypedTransaction> {
+    let item = rlp::decode_item(payload)?;
+    let fields = item.as_list()?;
+    if !matches!(fields.first(), Some(Item::List(_))) {
+        return Ok(TypedTransaction::Eip4844(Eip4844Transaction::rlp_decode(&item)?, None));
+    }
+    let (wrapper_version, sidecar) = match fields {
+        [_, blobs, commitments, proofs] => (None, [blobs, commitments, proofs]),
+        [_, version, blobs, commitments, proofs] => (Some(u64::rlp_decode(version)?), [blobs, commitments, proofs]),
+        _ => return Err(Error::Decode("Unexpected blob transaction wrapper length".to_string())),
+    };
+    let sidecar = BlobSidecar {
+        wrapper_version,
+        blobs: Vec::rlp_decode(sidecar[0])?,
+        commitments: Vec::rlp_decode(sidecar[1])?,
+        proofs: Vec::rlp_decode(sidecar[2])?,
+    };
+    Ok(TypedTransaction::Eip4844(Eip4844Transaction::rlp_decode(&fields[0])?, Some(sidecar)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The legacy transaction is the EIP-155 example. The typed ones are signed with the
+    // same key (0x4646...46) and were produced with alloy-consensus 2.5, an independent
+    // implementation of EIP-2718, to check encoding and hashing against.
+    const LEGACY: (&str, &str) = (
+        "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+        "33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788",
+    );
+    const EIP2930: (&str, &str) = (
+        "01f8a501018506fc23ac0082c3509435353535353535353535353535353535353535358204d284deadbeeff838f7943535353535353535353535353535353535353535e1a0000000000000000000000000000000000000000000000000000000000000000180a03ee03b0b8eba9255a73ee1afbc046bb4beece34b831604c07a2de228d0457d6da05eafd3bade415adc69075527699d75fcebdf3f35e68f7c2e54b1f603a1606a82",
+        "b56417c84a54b9ae251ed7e32fc56f9033f59a75ee180fdce7e2f13b4ae4a7dc",
+    );
+    const EIP1559: (&str, &str) = (
+        "02f85d01028459682f008509502f9000830186a08080856080604052c001a01eae835e4c09111184cab46231bea8af5eaf57dad1943fb1af0109f9bbec33a7a018de1974a69c48e36e82239751bf64cdf8933510697a3c5ca9d21cf9c0310957",
+        "42289f8d0a92495a5d5f53bffa49b4f2cf8fdc6b19ffa8861e5df0dbde20f46a",
+    );
+    const EIP4844: (&str, &str) = (
+        "03f8ca0103843b9aca008506fc23ac008252089435353535353535353535353535353535353535358080f838f7943535353535353535353535353535353535353535e1a00000000000000000000000000000000000000000000000000000000000000001830f4240e1a001a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d801a0dcf1f8ebda459c27d84954d4327b7261272dabb0b17f7334bdbb330c2ac42c28a006d219ecc0edbbdc83f7907e63a24a4414c5550992665cff641c244e8e6b1cc3",
+        "f84812d311b7d9ef61bb061374696f4e314898dc8af5db6626dc823e10080c16",
+    );
+    const EIP7702: (&str, &str) = (
+        "04f8c90104843b9aca008506fc23ac0082ea609435353535353535353535353535353535353535358080c0f85cf85a019411111111111111111111111111111111111111110580a082bfa0c6a4feb6b4f7fcd89db199c41359fd321ac2242eda540e92d7b289bf2fa0086e50e470abf8af6797f930696d31a80a0ab57e410edf164fbcc14c0a5e397301a028d466781b382fa7cfa5deee087e9ab0a417535ac1d410f62eb38be1592249d7a0623c2617d347607bbf5cdcc8c24963032e339a723095ee7178379fe692fcc627",
+        "1d59ec354043115291543774ff2261e6be72fcd27d253712659adcb0c55eeb2e",
+    );
+
+    fn round_trip((raw, hash): (&str, &str), tx_type: u8) -> TypedTransaction {
+        let transaction = TypedTransaction::from_hex(raw).unwrap();
+        assert_eq!(transaction.tx_type(), tx_type);
+        assert_eq!(transaction.to_hex(), format!("0x{}", raw));
+        assert_eq!(transaction.hash(), H256::from_slice(&hex::decode(hash).unwrap()));
+        transaction
+    }
+
+    fn address(byte: u8) -> H160 {
+        H160::repeat_byte(byte)
+    }
+
+    #[test]
+    fn round_trips_legacy() {
+        let TypedTransaction::Legacy(transaction) = round_trip(LEGACY, LEGACY_TX_TYPE) else { panic!("expected legacy") };
+        assert_eq!(transaction.nonce, 9);
+        assert_eq!(transaction.gas_price, U256::from(20_000_000_000u64));
+        assert_eq!(transaction.to, Some(address(0x35)));
+        assert_eq!(transaction.value, U256::exp10(18));
+        // Chain id 1 under EIP-155
+        assert_eq!(transaction.v, 37);
+    }
+
+    #[test]
+    fn round_trips_eip2930() {
+        let TypedTransaction::Eip2930(transaction) = round_trip(EIP2930, EIP2930_TX_TYPE) else { panic!("expected EIP-2930") };
+        assert_eq!(transaction.data, vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(transaction.access_list.len(), 1);
+        assert_eq!(transaction.access_list[0].storage_keys, vec![H256::from_low_u64_be(1)]);
+        assert!(!transaction.y_parity);
+    }
+
+    #[test]
+    fn round_trips_eip1559_contract_creation() {
+        let TypedTransaction::Eip1559(transaction) = round_trip(EIP1559, EIP1559_TX_TYPE) else { panic!("expected EIP-1559") };
+        assert_eq!(transaction.to, None);
+        assert_eq!(transaction.max_priority_fee_per_gas, U256::from(1_500_000_000u64));
+        assert_eq!(transaction.max_fee_per_gas, U256::from(40_000_000_000u64));
+        assert!(transaction.y_parity);
+    }
+
+    #[test]
+    fn round_trips_eip4844() {
+        let TypedTransaction::Eip4844(transaction, sidecar) = round_trip(EIP4844, EIP4844_TX_TYPE) else { panic!("expected EIP-4844") };
+        assert!(sidecar.is_none());
+        assert_eq!(transaction.max_fee_per_blob_gas, U256::from(1_000_000u64));
+        assert_eq!(transaction.blob_versioned_hashes.len(), 1);
+    }
+
+    #[test]
+    fn round_trips_eip7702() {
+        let TypedTransaction::Eip7702(transaction) = round_trip(EIP7702, EIP7702_TX_TYPE) else { panic!("expected EIP-7702") };
+        assert_eq!(transaction.authorization_list.len(), 1);
+        assert_eq!(transaction.authorization_list[0].address, address(0x11));
+        assert_eq!(transaction.authorization_list[0].nonce, 5);
+    }
+
+    #[test]
+    fn round_trips_blob_network_form() {
+        let TypedTransaction::Eip4844(transaction, _) = TypedTransaction::from_hex(EIP4844.0).unwrap() else { panic!("expected EIP-4844") };
+        let sidecar = BlobSidecar {
+            wrapper_version: None,
+            blobs: vec![vec![0u8; 64]],
+            commitments: vec![vec![1u8; 48]],
+            proofs: vec![vec![2u8; 48]],
+        };
+        let wrapped = TypedTransaction::Eip4844(transaction, Some(sidecar));
+        let decoded = TypedTransaction::decode(&wrapped.encode()).unwrap();
+        assert_eq!(decoded, wrapped);
+        // The sidecar is not part of the hash
+        assert_eq!(decoded.hash(), H256::from_slice(&hex::decode(EIP4844.1).unwrap()));
+    }
+
+    // Clearing the signature and signing again with the vectors' key must reproduce
+    // them exactly, since RFC 6979 nonces make signing deterministic
+    #[test]
+    fn signs_every_type_reproducibly() {
+        let key = SecretKey::from_hex(&"46".repeat(32)).unwrap();
+        for vector in [LEGACY, EIP2930, EIP1559, EIP4844, EIP7702] {
+            let signed = TypedTransaction::from_hex(vector.0).unwrap();
+            assert_eq!(signed.recover_sender().unwrap(), key.address());
+
+            let mut unsigned = signed.clone();
+            match &mut unsigned {
+                TypedTransaction::Legacy(transaction) => (transaction.v, transaction.r, transaction.s) = (1, U256::zero(), U256::zero()),
+                TypedTransaction::Eip2930(transaction) => (transaction.y_parity, transaction.r, transaction.s) = (false, U256::zero(), U256::zero()),
+                TypedTransaction::Eip1559(transaction) => (transaction.y_parity, transaction.r, transaction.s) = (false, U256::zero(), U256::zero()),
+                TypedTransaction::Eip4844(transaction, _) => (transaction.y_parity, transaction.r, transaction.s) = (false, U256::zero(), U256::zero()),
+                TypedTransaction::Eip7702(transaction) => (transaction.y_parity, transaction.r, transaction.s) = (false, U256::zero(), U256::zero()),
+                TypedTransaction::Deposit(_) => unreachable!(),
+            }
+            assert_eq!(unsigned.signature_hash().unwrap(), signed.signature_hash().unwrap());
+            assert!(unsigned.recover_sender().is_err());
+            assert_eq!(unsigned.sign(&key).unwrap(), signed);
+        }
+
+        // Signing hash from the EIP-155 example
+        let TypedTransaction::Legacy(legacy) = TypedTransaction::from_hex(LEGACY.0).unwrap() else { unreachable!() };
+        assert_eq!(legacy.chain_id(), Some(1));
+        assert_eq!(format!("{:?}", TypedTransaction::Legacy(legacy).signature_hash().unwrap()), "0xdaf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53");
+    }
+
+    #[test]
+    fn rejects_unknown_types_and_trailing_bytes() {
+        assert!(TypedTransaction::from_hex("05c0").is_err());
+        assert!(TypedTransaction::from_hex(&format!("{}00", LEGACY.0)).is_err());
+        assert!(TypedTransaction::from_hex("").is_err());
+    }
+}
@@ -0,0 +1,526 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::Instrument;
+
+use crate::client::{check_response_id, next_request_id, JsonRpcRequest, JsonRpcResponse};
+use crate::context::RequestContext;
+use crate::error::{Error, Result};
+
+// Carries a JSON-RPC request to a node. Implementations return the whole response
+// object, so node errors (reverts, OffchainLookup) are left for the caller to inspect.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn request(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+// Failures of the connection rather than of the request. Every variant is worth
+// retrying, on the same endpoint or another one; see Error::is_transient.
+#[derive(Debug, thiserror::Error)]
+pub enum TransportError {
+    #[error("WebSocket connection closed")]
+    Closed,
+    #[error("{method} timed out after {timeout:?}")]
+    Timeout { method: String, timeout: Duration },
+    // A response carrying a rate-limit error code
+    #[error("{method} rate limited: {error}")]
+    RateLimited { method: String, error: serde_json::Value },
+    // FallbackProvider ran out of endpoints; transient if the last failure was
+    #[error("All RPC endpoints failed, last error: {0}")]
+    Exhausted(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+// Bounds on what a provider may send back, so a broken or malicious endpoint can't
+// exhaust memory with a huge or deeply nested payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseLimits {
+    pub max_size: usize,
+    // serde_json refuses anything nested deeper than 128 regardless
+    pub max_depth: usize,
+}
+
+impl Default for ResponseLimits {
+    fn default() -> Self {
+        ResponseLimits {
+            max_size: 100 * 1024 * 1024,
+            max_depth: 64,
+        }
+    }
+}
+
+impl ResponseLimits {
+    // Checks size and nesting before the payload is handed to serde_json
+    pub fn parse(&self, body: &[u8]) -> Result<serde_json::Value> {
+        if body.len() > self.max_size {
+            return Err(Error::Decode(format!("Response of {} bytes exceeds the {} byte limit", body.len(), self.max_size)));
+        }
+        if json_depth(body) > self.max_depth {
+            return Err(Error::Decode(format!("Response nesting exceeds the depth limit of {}", self.max_depth)));
+        }
+        serde_json::from_slice(body).map_err(|error| Error::Decode(format!("Invalid JSON-RPC response: {}", error)))
+    }
+}
+
+// Maximum nesting of arrays and objects, ignoring brackets inside strings
+pub fn json_depth(body: &[u8]) -> usize {
+    let (mut depth, mut max_depth) = (0usize, 0usize);
+    let (mut in_string, mut escaped) = (false, false);
+    for byte in body {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    max_depth
+}
+
+// Short hash identifying a request's params in logs without printing them
+pub fn params_hash(params: &[serde_json::Value]) -> String {
+    let serialized = serde_json::Value::Array(params.to_vec()).to_string();
+    hex::encode(&keccak_hash::keccak(serialized.as_bytes())[..4])
+}
+
+// Scheme, host and port only, since provider URLs often carry an API key in the path
+pub fn redact_endpoint(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) => match parsed.port() {
+            Some(port) => format!("{}://{}:{}", parsed.scheme(), parsed.host_str().unwrap_or_default(), port),
+            None => format!("{}://{}", parsed.scheme(), parsed.host_str().unwrap_or_default()),
+        },
+        Err(_) => "invalid-url".to_string(),
+    }
+}
+
+// Runs a request inside an "rpc" span and records how it went
+async fn traced<F>(method: &str, endpoint: &str, params: &[serde_json::Value], log_bodies: bool, request: F) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>>
+where
+    F: std::future::Future<Output = Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>>>,
+{
+    let context = RequestContext::current().unwrap_or_default();
+    let span = tracing::debug_span!("rpc", method, endpoint, params_hash = %params_hash(params), job = context.job.as_deref(), tags = %context);
+    if log_bodies {
+        span.in_scope(|| tracing::debug!(params = %serde_json::Value::Array(params.to_vec()), "rpc request"));
+    }
+
+    let started = Instant::now();
+    let result = request.instrument(span.clone()).await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+    span.in_scope(|| match &result {
+        Ok(response) if log_bodies => tracing::debug!(duration_ms, %response, "rpc response"),
+        Ok(_) => tracing::debug!(duration_ms, "rpc response"),
+        Err(error) => tracing::warn!(duration_ms, %error, "rpc failed"),
+    });
+    result
+}
+
+#[derive(Clone)]
+pub struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+    endpoint: String,
+    limits: ResponseLimits,
+    log_bodies: bool,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str) -> Self {
+        Self::with_client(reqwest::Client::new(), url)
+    }
+
+    pub fn with_client(client: reqwest::Client, url: &str) -> Self {
+        HttpTransport {
+            client,
+            url: url.to_string(),
+            endpoint: redact_endpoint(url),
+            limits: ResponseLimits::default(),
+            log_bodies: false,
+        }
+    }
+
+    // Logs full request params and responses at debug level; they may contain signed
+    // transactions or other data you would rather keep out of logs
+    pub fn with_body_logging(mut self, log_bodies: bool) -> Self {
+        self.log_bodies = log_bodies;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: ResponseLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    async fn request(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        let logged = params.clone();
+        traced(method, &self.endpoint, &logged, self.log_bodies, self.post(method, params)).await
+    }
+}
+
+impl HttpTransport {
+    async fn post(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        let id = next_request_id();
+        let request_body = JsonRpcRequest {
+            id,
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+        };
+        let mut request = self.client.post(&self.url).json(&request_body).header("accept", "application/json").header("content-type", "application/json");
+        if let Some(context) = RequestContext::current() {
+            for (name, value) in &context.headers {
+                request = request.header(name.as_str(), value.as_str());
+            }
+        }
+        let response = request.send().await.map_err(reqwest::Error::without_url)?;
+        // Rate limiting and server failures become reqwest errors so layers can retry them
+        let status = response.status();
+        let mut response = if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() { response.error_for_status().map_err(reqwest::Error::without_url)? } else { response };
+
+        // Stream the body so an oversized response is rejected without buffering it all
+        let max_size = self.limits.max_size;
+        if response.content_length().is_some_and(|length| length as usize > max_size) {
+            return Err(format!("Response exceeds the {} byte limit", max_size).into());
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(reqwest::Error::without_url)? {
+            if body.len() + chunk.len() > max_size {
+                return Err(format!("Response exceeds the {} byte limit", max_size).into());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        let result = self.limits.parse(&body)?;
+        check_response_id(&result, id)?;
+        Ok(result)
+    }
+}
+
+enum Pending {
+    Request(oneshot::Sender<serde_json::Value>),
+    Subscribe(oneshot::Sender<(serde_json::Value, Option<mpsc::UnboundedReceiver<serde_json::Value>>)>),
+}
+
+#[derive(Default)]
+struct Shared {
+    pending: HashMap<u64, Pending>,
+    subscriptions: HashMap<String, mpsc::UnboundedSender<serde_json::Value>>,
+    closed: bool,
+}
+
+// One persistent WebSocket connection shared by concurrent requests, which are
+// matched to their responses by id
+pub struct WsTransport {
+    endpoint: String,
+    outgoing: mpsc::UnboundedSender<Message>,
+    shared: Arc<Mutex<Shared>>,
+    timeout: Duration,
+}
+
+impl WsTransport {
+    pub async fn connect(url: &str) -> Result<Self> {
+        Self::connect_with_limits(url, ResponseLimits::default()).await
+    }
+
+    // Messages over the size or depth limit close the connection, failing pending requests
+    pub async fn connect_with_limits(url: &str, limits: ResponseLimits) -> Result<Self> {
+        let config = WebSocketConfig {
+            max_message_size: Some(limits.max_size),
+            max_frame_size: Some(limits.max_size),
+            ..WebSocketConfig::default()
+        };
+        let (socket, _) = tokio_tungstenite::connect_async_with_config(url, Some(config), false).await.map_err(|error| Error::Transport(Box::new(error)))?;
+        let (mut sink, mut stream) = socket.split();
+        let (outgoing, mut outgoing_rx) = mpsc::unbounded_channel::<Message>();
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        tokio::spawn(async move {
+            while let Some(message) = outgoing_rx.recv().await {
+                if sink.send(message).await.is_err() {
+                    return;
+                }
+            }
+            let _ = sink.close().await;
+        });
+
+        let reader_shared = shared.clone();
+        tokio::spawn(async move {
+            while let Some(Ok(message)) = stream.next().await {
+                let text = match message {
+                    Message::Text(text) => text,
+                    Message::Close(_) => break,
+                    _ => continue,
+                };
+                // Size is already enforced by the WebSocket config
+                if json_depth(text.as_bytes()) > limits.max_depth {
+                    break;
+                }
+                if let Ok(response) = serde_json::from_str(&text) {
+                    dispatch(&reader_shared, response);
+                }
+            }
+
+            // Dropping the senders fails in-flight requests and ends subscriptions
+            let mut shared = reader_shared.lock().unwrap();
+            shared.closed = true;
+            shared.pending.clear();
+            shared.subscriptions.clear();
+        });
+
+        Ok(WsTransport {
+            endpoint: redact_endpoint(url),
+            outgoing,
+            shared,
+            timeout: Duration::from_secs(30),
+        })
+    }
+
+    // How long a request or subscription waits for its response before giving up
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    // eth_subscribe; the receiver yields each notification's result until the
+    // subscription is cancelled or the connection drops
+    pub async fn subscribe(&self, params: Vec<serde_json::Value>) -> Result<(String, mpsc::UnboundedReceiver<serde_json::Value>)> {
+        let (sender, receiver) = oneshot::channel();
+        let id = self.enqueue("eth_subscribe", params, Pending::Subscribe(sender)).map_err(Error::Transport)?;
+        let (response, notifications) = self.wait("eth_subscribe", id, receiver).await.map_err(Error::Transport)?;
+        let subscription_id: String = JsonRpcResponse::from_value(response)?.result?;
+        let notifications = notifications.ok_or_else(|| Error::Decode("eth_subscribe returned no subscription id".to_string()))?;
+        Ok((subscription_id, notifications))
+    }
+
+    pub async fn unsubscribe(&self, subscription_id: &str) -> Result<bool> {
+        self.shared.lock().unwrap().subscriptions.remove(subscription_id);
+        let response = self.request("eth_unsubscribe", vec![serde_json::json!(subscription_id)]).await.map_err(Error::Transport)?;
+        Ok(JsonRpcResponse::<Option<bool>>::from_value(response)?.result?.unwrap_or(false))
+    }
+
+    // Returns the request id
+    fn enqueue(&self, method: &str, params: Vec<serde_json::Value>, pending: Pending) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let id = next_request_id();
+        let request_body = JsonRpcRequest {
+            id,
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+        };
+
+        // Registered before sending so the response can't arrive first
+        let mut shared = self.shared.lock().unwrap();
+        if shared.closed {
+            return Err(Box::new(TransportError::Closed));
+        }
+        shared.pending.insert(id, pending);
+        drop(shared);
+
+        self.outgoing.send(Message::Text(serde_json::to_string(&request_body)?)).map_err(|_| TransportError::Closed)?;
+        Ok(id)
+    }
+
+    // A response that never arrives would otherwise leave its entry pending forever
+    async fn wait<T>(&self, method: &str, id: u64, receiver: oneshot::Receiver<T>) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        match tokio::time::timeout(self.timeout, receiver).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(Box::new(TransportError::Closed)),
+            Err(_) => {
+                self.shared.lock().unwrap().pending.remove(&id);
+                Err(Box::new(TransportError::Timeout {
+                    method: method.to_string(),
+                    timeout: self.timeout,
+                }))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for WsTransport {
+    async fn request(&self, method: &str, params: Vec<serde_json::Value>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        let logged = params.clone();
+        let request = async move {
+            let (sender, receiver) = oneshot::channel();
+            let id = self.enqueue(method, params, Pending::Request(sender))?;
+            self.wait(method, id, receiver).await
+        };
+        traced(method, &self.endpoint, &logged, false, request).await
+    }
+}
+
+fn dispatch(shared: &Mutex<Shared>, response: serde_json::Value) {
+    let mut shared = shared.lock().unwrap();
+
+    // Subscription notifications carry no id
+    if response["method"] == "eth_subscription" {
+        let params = &response["params"];
+        if let Some(sender) = params["subscription"].as_str().and_then(|id| shared.subscriptions.get(id)) {
+            let _ = sender.send(params["result"].clone());
+        }
+        return;
+    }
+
+    // An error with a null id means the node couldn't read some request, and only a lone
+    // pending request is known to be that one. Otherwise it is dropped, and the request
+    // it was meant for times out rather than failing the others with it.
+    if response["id"].is_null() && response.get("error").is_some() {
+        let lone = if shared.pending.len() == 1 { shared.pending.keys().next().copied() } else { None };
+        match lone.and_then(|id| shared.pending.remove(&id)) {
+            Some(Pending::Request(sender)) => {
+                let _ = sender.send(response);
+            }
+            Some(Pending::Subscribe(sender)) => {
+                let _ = sender.send((response, None));
+            }
+            None => tracing::warn!(error = %response["error"], pending = shared.pending.len(), "dropped an error response with no id"),
+        }
+        return;
+    }
+
+    // Unknown ids, including repeats of an already answered one, are dropped
+    let id = match response["id"].as_u64() {
+        Some(id) => id,
+        None => return,
+    };
+    match shared.pending.remove(&id) {
+        Some(Pending::Request(sender)) => {
+            let _ = sender.send(response);
+        }
+        Some(Pending::Subscribe(sender)) => {
+            // Registered while still holding the lock so no early notification is lost
+            let notifications = response["result"].as_str().map(|subscription_id| {
+                let (notification_sender, notifications) = mpsc::unbounded_channel();
+                shared.subscriptions.insert(subscription_id.to_string(), notification_sender);
+                notifications
+            });
+            let _ = sender.send((response, notifications));
+        }
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    // A node that answers each pair of requests in reverse order and, on eth_subscribe,
+    // sends two notifications for the new subscription and one for an unknown one
+    async fn serve() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let mut held = Vec::new();
+            while let Some(Ok(Message::Text(text))) = socket.next().await {
+                let request: Value = serde_json::from_str(&text).unwrap();
+                if request["method"] == "eth_subscribe" {
+                    socket.send(Message::Text(json!({"jsonrpc": "2.0", "id": request["id"], "result": "0xabc"}).to_string())).await.unwrap();
+                    for (subscription, number) in [("0xabc", "0x1"), ("0xdef", "0x9"), ("0xabc", "0x2")] {
+                        let notification = json!({"jsonrpc": "2.0", "method": "eth_subscription", "params": {"subscription": subscription, "result": {"number": number}}});
+                        socket.send(Message::Text(notification.to_string())).await.unwrap();
+                    }
+                    continue;
+                }
+                held.push(request);
+                if held.len() == 2 {
+                    for request in held.drain(..).rev() {
+                        let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": request["params"][0]});
+                        socket.send(Message::Text(response.to_string())).await.unwrap();
+                    }
+                }
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn matches_concurrent_responses_and_routes_notifications() {
+        let ws = WsTransport::connect(&serve().await).await.unwrap().with_timeout(Duration::from_secs(5));
+        let (first, second) = tokio::join!(ws.request("echo", vec![json!("first")]), ws.request("echo", vec![json!("second")]));
+        assert_eq!(first.unwrap()["result"], "first");
+        assert_eq!(second.unwrap()["result"], "second");
+
+        let (id, mut heads) = ws.subscribe(vec![json!("newHeads")]).await.unwrap();
+        assert_eq!(id, "0xabc");
+        assert_eq!(heads.recv().await.unwrap(), json!({"number": "0x1"}));
+        assert_eq!(heads.recv().await.unwrap(), json!({"number": "0x2"}));
+    }
+
+    fn pending(shared: &Mutex<Shared>, id: u64) -> oneshot::Receiver<Value> {
+        let (sender, receiver) = oneshot::channel();
+        shared.lock().unwrap().pending.insert(id, Pending::Request(sender));
+        receiver
+    }
+
+    #[test]
+    fn fails_only_the_request_a_null_id_error_can_belong_to() {
+        let error = json!({"jsonrpc": "2.0", "id": null, "error": {"code": -32700, "message": "Parse error"}});
+        let shared = Mutex::new(Shared::default());
+        let (mut first, mut second) = (pending(&shared, 1), pending(&shared, 2));
+        // Ambiguous between two requests: dropped
+        dispatch(&shared, error.clone());
+        assert!(first.try_recv().is_err() && second.try_recv().is_err());
+        dispatch(&shared, json!({"jsonrpc": "2.0", "id": 1, "result": "0x1"}));
+        assert_eq!(first.try_recv().unwrap()["result"], "0x1");
+        // Only the second is left, so it must be the one
+        dispatch(&shared, error.clone());
+        assert_eq!(second.try_recv().unwrap(), error);
+        assert!(shared.lock().unwrap().pending.is_empty());
+    }
+
+    #[test]
+    fn checks_response_ids() {
+        assert!(check_response_id(&json!({"id": 7, "result": "0x1"}), 7).is_ok());
+        assert!(matches!(check_response_id(&json!({"id": 8, "result": "0x1"}), 7), Err(Error::Decode(_))));
+        assert!(matches!(check_response_id(&json!({"id": "7", "result": "0x1"}), 7), Err(Error::Decode(_))));
+        // A null or missing id is only allowed on errors
+        assert!(check_response_id(&json!({"id": null, "error": {"code": -32700}}), 7).is_ok());
+        assert!(check_response_id(&json!({"error": {"code": -32700}}), 7).is_ok());
+        assert!(matches!(check_response_id(&json!({"id": null, "result": "0x1"}), 7), Err(Error::Decode(_))));
+    }
+
+    #[test]
+    fn measures_nesting_outside_strings() {
+        assert_eq!(json_depth(b"1"), 0);
+        assert_eq!(json_depth(br#"{"a": [1, {"b": []}]}"#), 4);
+        assert_eq!(json_depth(br#"{"a": "[[[{{\"]]"}"#), 1);
+        assert_eq!(json_depth(br#"[[]][[[]]]"#), 3);
+    }
+
+    #[test]
+    fn enforces_response_limits() {
+        let limits = ResponseLimits { max_size: 32, max_depth: 2 };
+        assert_eq!(limits.parse(br#"{"result": [1]}"#).unwrap(), json!({"result": [1]}));
+        assert!(matches!(limits.parse(br#"{"result": [[1]]}"#), Err(Error::Decode(message)) if message.contains("depth")));
+        assert!(matches!(limits.parse(&[b' '; 33]), Err(Error::Decode(message)) if message.contains("33 bytes")));
+        assert!(matches!(limits.parse(b"{"), Err(Error::Decode(_))));
+    }
+}
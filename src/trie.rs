@@ -0,0 +1,347 @@
+use std::collections::BTreeMap;
+
+use ethabi::ethereum_types::{H160, H256, U256};
+
+use crate::error::{Error, Result};
+use crate::impl_rlp;
+use crate::rlp::{self, Item};
+
+// keccak256(rlp(""))
+pub const EMPTY_ROOT: &str = "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    pub nonce: u64,
+    pub balance: U256,
+    pub storage_root: H256,
+    pub code_hash: H256,
+}
+
+impl_rlp!(Account { nonce, balance, storage_root, code_hash });
+
+// Walks a proof from the root and returns the value stored under the key, or None
+// if the proof shows the key is absent. Keys are raw: callers hash them for secure tries.
+pub fn verify_proof(root: H256, key: &[u8], proof: &[Vec<u8>]) -> Result<Option<Vec<u8>>> {
+    if root == empty_root() {
+        return Ok(None);
+    }
+
+    let nibbles = to_nibbles(key);
+    let mut position = 0;
+    let mut proof_nodes = proof.iter();
+    let mut reference = Item::Bytes(root.as_bytes().to_vec());
+
+    loop {
+        let node = match reference {
+            Item::Bytes(hash) if hash.is_empty() => return Ok(None),
+            Item::Bytes(hash) => {
+                let encoded = proof_nodes.next().ok_or_else(|| Error::Decode("Proof ends before reaching the key".to_string()))?;
+                if keccak_hash::keccak(encoded).as_bytes() != hash.as_slice() {
+                    return Err(Error::Decode("Proof node hash mismatch".to_string()));
+                }
+                rlp::decode_item(encoded)?
+            }
+            // Nodes shorter than 32 bytes are embedded in their parent
+            inline => inline,
+        };
+
+        let items = node.as_list()?;
+        match items.len() {
+            17 => {
+                if position == nibbles.len() {
+                    let value = items[16].as_bytes()?;
+                    return Ok(if value.is_empty() { None } else { Some(value.to_vec()) });
+                }
+                reference = items[nibbles[position] as usize].clone();
+                position += 1;
+            }
+            2 => {
+                let (path, is_leaf) = decode_path(items[0].as_bytes()?)?;
+                let remaining = &nibbles[position..];
+                if is_leaf {
+                    return Ok(if remaining == path.as_slice() {
+                        Some(items[1].as_bytes()?.to_vec())
+                    } else {
+                        None
+                    });
+                }
+                if !remaining.starts_with(&path) {
+                    return Ok(None);
+                }
+                position += path.len();
+                reference = items[1].clone();
+            }
+            _ => return Err(Error::Decode("Invalid trie node".to_string())),
+        }
+    }
+}
+
+// Verifies an eth_getProof account proof against a block's state root
+pub fn verify_account_proof(state_root: H256, address: H160, proof: &[Vec<u8>]) -> Result<Option<Account>> {
+    let key = keccak_hash::keccak(address.as_bytes());
+    match verify_proof(state_root, key.as_bytes(), proof)? {
+        Some(value) => Ok(Some(rlp::decode(&value)?)),
+        None => Ok(None),
+    }
+}
+
+// Verifies an eth_getProof storage proof against the account's storage root
+pub fn verify_storage_proof(storage_root: H256, slot: H256, proof: &[Vec<u8>]) -> Result<U256> {
+    let key = keccak_hash::keccak(slot.as_bytes());
+    match verify_proof(storage_root, key.as_bytes(), proof)? {
+        Some(value) => rlp::decode(&value),
+        None => Ok(U256::zero()),
+    }
+}
+
+pub fn empty_root() -> H256 {
+    H256::from_slice(&hex::decode(EMPTY_ROOT).unwrap())
+}
+
+// In-memory trie for computing roots over small local data sets
+#[derive(Debug, Clone, Default)]
+pub struct Trie {
+    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl Trie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, key: &[u8], value: Vec<u8>) {
+        if value.is_empty() {
+            self.entries.remove(key);
+        } else {
+            self.entries.insert(key.to_vec(), value);
+        }
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.entries.get(key).map(Vec::as_slice)
+    }
+
+    pub fn root(&self) -> H256 {
+        let entries: Vec<(Vec<u8>, &[u8])> = self
+            .entries
+            .iter()
+            .map(|(key, value)| (to_nibbles(key), value.as_slice()))
+            .collect();
+        keccak_hash::keccak(rlp::encode(&build_node(&entries, 0)))
+    }
+}
+
+// Root of a transactions or receipts trie, keyed by rlp(index)
+pub fn ordered_trie_root(values: &[Vec<u8>]) -> H256 {
+    let mut trie = Trie::new();
+    for (index, value) in values.iter().enumerate() {
+        trie.insert(&rlp::encode(&index), value.clone());
+    }
+    trie.root()
+}
+
+fn build_node(entries: &[(Vec<u8>, &[u8])], depth: usize) -> Item {
+    if entries.is_empty() {
+        return Item::Bytes(Vec::new());
+    }
+    if entries.len() == 1 {
+        let (key, value) = &entries[0];
+        return Item::List(vec![
+            Item::Bytes(encode_path(&key[depth..], true)),
+            Item::Bytes(value.to_vec()),
+        ]);
+    }
+
+    let prefix = common_prefix(entries, depth);
+    if prefix > 0 {
+        let child = build_node(entries, depth + prefix);
+        return Item::List(vec![
+            Item::Bytes(encode_path(&entries[0].0[depth..depth + prefix], false)),
+            node_reference(child),
+        ]);
+    }
+
+    let mut branch = vec![Item::Bytes(Vec::new()); 17];
+    let mut value = None;
+    let mut start = 0;
+    // Entries are sorted, so keys sharing the next nibble are contiguous
+    while start < entries.len() {
+        if entries[start].0.len() == depth {
+            value = Some(entries[start].1);
+            start += 1;
+            continue;
+        }
+        let nibble = entries[start].0[depth];
+        let end = entries[start..]
+            .iter()
+            .position(|(key, _)| key[depth] != nibble)
+            .map_or(entries.len(), |offset| start + offset);
+        branch[nibble as usize] = node_reference(build_node(&entries[start..end], depth + 1));
+        start = end;
+    }
+    if let Some(value) = value {
+        branch[16] = Item::Bytes(value.to_vec());
+    }
+    Item::List(branch)
+}
+
+fn node_reference(node: Item) -> Item {
+    let encoded = rlp::encode(&node);
+    if encoded.len() < 32 {
+        node
+    } else {
+        Item::Bytes(keccak_hash::keccak(encoded).as_bytes().to_vec())
+    }
+}
+
+fn common_prefix(entries: &[(Vec<u8>, &[u8])], depth: usize) -> usize {
+    let first = &entries[0].0[depth..];
+    entries[1..].iter().fold(first.len(), |length, (key, _)| {
+        first[..length]
+            .iter()
+            .zip(&key[depth..])
+            .take_while(|(a, b)| a == b)
+            .count()
+    })
+}
+
+fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]).collect()
+}
+
+// Hex-prefix encoding: flag nibble carries leaf/extension and odd/even length
+fn encode_path(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 2 } else { 0 };
+    let mut encoded = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        encoded.push(((flag + 1) << 4) | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        encoded.push(flag << 4);
+        nibbles
+    };
+    for pair in rest.chunks(2) {
+        encoded.push((pair[0] << 4) | pair[1]);
+    }
+    encoded
+}
+
+fn decode_path(encoded: &[u8]) -> Result<(Vec<u8>, bool)> {
+    let first = *encoded.first().ok_or_else(|| Error::Decode("Empty trie path".to_string()))?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return Err(Error::Decode("Invalid trie path prefix".to_string()));
+    }
+    let mut nibbles = Vec::with_capacity(encoded.len() * 2);
+    if flag & 1 == 1 {
+        nibbles.push(first & 0x0f);
+    }
+    nibbles.extend(to_nibbles(&encoded[1..]));
+    Ok((nibbles, flag & 2 == 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A state trie of 20 accounts (0x0101..01 through 0x1414..14, nonce i, balance i ether)
+    // where 0x0707..07 holds storage slots 0..5 set to 1000 + slot * 7919. Roots and
+    // proofs were generated offline with the eth_trie crate, whose get_proof output has
+    // the same shape as eth_getProof's accountProof and storageProof.
+    const STATE_ROOT: &str = "d46ca27a0061f72b1ff4cd44dc8a3b75fafb258a6e6d2cd4b8cfd248456aacb4";
+    const STORAGE_ROOT: &str = "c4b365ff6148aadb2dc49076d9349a2c81bb381c4cbac1083d43cf1ccbae7521";
+    const STATE_BRANCH: &str = "f901b1a089f09bf747d2d5fb3fec7ea2b66d6886ae1007f8ef3f3df49e751ef2f08868c5a070bc8b381da67a251c2314a26dfff0ea927cd5173fd72f3749d624290261301e80a061732e4d77234a8bae5313f8064a07c525ba7628a22c94af999f79ce5b2e694880a08586bbd119c86898e88489b10094c979065f6fe64e78daf73dd86a8f49632e83a01bf10d592a592126a3877a4499fe3ae13e272513c387314ea9c8e6cb1dcab986a061bf1829ac9ad0d1dea93ff4c155156837341a57a6d1a6c950b93daaf962bf1980a07d6bf40cc510a7d86ab7f4bb4bd0002a9547de8ec6d3c49882607eda95ac5575a03f4fa4680e75180deea04a9805e155020667cd7c874689dcd8a9538db6e3a9f9a078a6628d55604a783b0a8fa1794e51fa6b24855f11e0947fa1295eb6e7fbfad4a093336ada472d9778e2d6ed007ff2f8177186635a97935bfc1c2edccb6c8136c2a049e769876d442d100923c502aaedada4769a3f1be844cc5650d616a0d93e44cda0af93a17fd6696b355e3ca739e0f409eba7774fb9d9bf1ab7b8ede3bf30a63a0ea02de980273727097bc36dc7ed938d246dbaecee313dd551bf66edac6064ce4c4880";
+    const ACCOUNT_PROOF: [&str; 3] = [
+        STATE_BRANCH,
+        "f8918080a05aac5ae47a464f922d1399b1af6e58b4660f7099099a47db9338bbfc984c3082808080a078c2581fe1f31ccba750f8b48b72e6181ac1e517377f6e9b220126e9e7c6614b8080a0a53855f862f3402c4c062fa4cc560d8058afef549449020cda6b16bc72b0d2138080a020120bbeaca3cd3327b33d918c011a0f0cd313a6fc39c7a439234d01c8269f8380808080",
+        "f871a02092a20c2418016d3f8c4730b5f224f3275b1f815410efd3f44a3922c148b353b84ef84c07886124fee993bc0000a0c4b365ff6148aadb2dc49076d9349a2c81bb381c4cbac1083d43cf1ccbae7521a0c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+    ];
+    // Proof of absence for 0x4242..42: the path ends at another account's leaf
+    const ABSENT_ACCOUNT_PROOF: [&str; 2] = [
+        STATE_BRANCH,
+        "f871a0323ca94920d7e77c294c659f8b56b5229981195b48b3570e31f6d421b7bb7350b84ef84c0b8898a7d9b8314c0000a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421a0c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+    ];
+    const STORAGE_BRANCH: &str = "f8d1a0a30d509d8699f052e6d458f31a87ad56e4340f10448c73840936efd63aa0befe80a0f182bbf63559b43f21d7a276844cf548d6772d7372a32d131e5fc27ee571d23780a0852e490679b37d0c6221ab37c5f8426fd087e900708f8898fb7ee2456c5fd348808080a0874d958a6631e57152e47202675d9dcc303dbd97a8131d98c8822704e9fc3c468080a025f3c9fa98e4e88c8c81844fa76a92c48a01caca274a0d68c4157599b7f6f02ba06072fd495f9c8be4693f217158537f3285dbc0fc0e5030b044ad7f549aae4bec80808080";
+    const SLOT_2_PROOF: [&str; 2] = [STORAGE_BRANCH, "e5a0305787fa12a823e0f2b7631cc41b3ba8828b3321ca811111fa75cd3aa3bb5ace838241c6"];
+
+    fn h256(hex: &str) -> H256 {
+        H256::from_slice(&hex::decode(hex).unwrap())
+    }
+
+    fn proof(nodes: &[&str]) -> Vec<Vec<u8>> {
+        nodes.iter().map(|node| hex::decode(node).unwrap()).collect()
+    }
+
+    fn slot_key(slot: u64) -> Vec<u8> {
+        keccak_hash::keccak(H256::from_low_u64_be(slot).as_bytes()).as_bytes().to_vec()
+    }
+
+    #[test]
+    fn computes_known_roots() {
+        assert_eq!(Trie::new().root(), empty_root());
+        assert_eq!(ordered_trie_root(&[]), empty_root());
+
+        // The example from the Ethereum wiki's Patricia trie page
+        let mut trie = Trie::new();
+        for (key, value) in [("do", "verb"), ("dog", "puppy"), ("doge", "coin"), ("horse", "stallion")] {
+            trie.insert(key.as_bytes(), value.as_bytes().to_vec());
+        }
+        assert_eq!(trie.root(), h256("5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84"));
+
+        // An empty value deletes the key
+        trie.insert(b"cat", b"meow".to_vec());
+        trie.insert(b"cat", Vec::new());
+        assert_eq!(trie.root(), h256("5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84"));
+    }
+
+    #[test]
+    fn computes_the_fixture_roots() {
+        let mut storage = Trie::new();
+        for slot in 0..6u64 {
+            storage.insert(&slot_key(slot), rlp::encode(&U256::from(1000 + slot * 7919)));
+        }
+        assert_eq!(storage.root(), h256(STORAGE_ROOT));
+
+        let mut state = Trie::new();
+        for index in 1..=20u8 {
+            let account = Account {
+                nonce: index as u64,
+                balance: U256::from(index) * U256::exp10(18),
+                storage_root: if index == 7 { h256(STORAGE_ROOT) } else { empty_root() },
+                code_hash: H256::from_slice(keccak_hash::keccak([]).as_bytes()),
+            };
+            state.insert(keccak_hash::keccak(H160::repeat_byte(index).as_bytes()).as_bytes(), rlp::encode(&account));
+        }
+        assert_eq!(state.root(), h256(STATE_ROOT));
+    }
+
+    #[test]
+    fn verifies_account_and_storage_proofs() {
+        let account = verify_account_proof(h256(STATE_ROOT), H160::repeat_byte(7), &proof(&ACCOUNT_PROOF)).unwrap().unwrap();
+        assert_eq!(account.nonce, 7);
+        assert_eq!(account.balance, U256::from(7) * U256::exp10(18));
+        assert_eq!(account.storage_root, h256(STORAGE_ROOT));
+
+        let value = verify_storage_proof(account.storage_root, H256::from_low_u64_be(2), &proof(&SLOT_2_PROOF)).unwrap();
+        assert_eq!(value, U256::from(1000 + 2 * 7919));
+    }
+
+    #[test]
+    fn verifies_proofs_of_absence() {
+        assert_eq!(verify_account_proof(h256(STATE_ROOT), H160::repeat_byte(0x42), &proof(&ABSENT_ACCOUNT_PROOF)).unwrap(), None);
+        // Slot 9 was never written; its path ends at an empty branch child
+        assert_eq!(verify_storage_proof(h256(STORAGE_ROOT), H256::from_low_u64_be(9), &proof(&[STORAGE_BRANCH])).unwrap(), U256::zero());
+        assert_eq!(verify_proof(empty_root(), &slot_key(0), &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_tampered_or_truncated_proofs() {
+        let mut tampered = proof(&ACCOUNT_PROOF);
+        let last = tampered[2].len() - 1;
+        tampered[2][last] ^= 1;
+        assert!(verify_account_proof(h256(STATE_ROOT), H160::repeat_byte(7), &tampered).is_err());
+        assert!(verify_account_proof(h256(STATE_ROOT), H160::repeat_byte(7), &proof(&ACCOUNT_PROOF[..2])).is_err());
+        // A valid proof checked against the wrong root
+        assert!(verify_storage_proof(h256(STATE_ROOT), H256::from_low_u64_be(2), &proof(&SLOT_2_PROOF)).is_err());
+    }
+}
@@ -0,0 +1,267 @@
+use std::io::{self, Read, Write};
+
+use ethabi::ethereum_types::U256;
+use keccak_hash::H256;
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::error::{Error, Result};
+
+// Incremental keccak256 for payloads too large to hash in one go
+pub struct Keccak256 {
+    inner: Keccak,
+}
+
+impl Keccak256 {
+    pub fn new() -> Self {
+        Keccak256 {
+            inner: Keccak::v256(),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> H256 {
+        let mut output = [0u8; 32];
+        self.inner.finalize(&mut output);
+        H256::from(output)
+    }
+}
+
+impl Default for Keccak256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Lets the hasher be fed with io::copy
+impl Write for Keccak256 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Hashes everything the reader yields without buffering it in memory
+pub fn keccak256_reader(mut reader: impl Read) -> Result<H256> {
+    let mut hasher = Keccak256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher.finalize())
+}
+
+// EIP-55 mixed-case checksum encoding
+pub fn to_checksum_address(address: &str) -> String {
+    let address = address.trim_start_matches("0x").to_lowercase();
+    let hash = hex::encode(keccak_hash::keccak(address.as_bytes()));
+    let checksummed: String = address
+        .chars()
+        .zip(hash.chars())
+        .map(|(c, h)| if h.to_digit(16).unwrap_or(0) >= 8 { c.to_ascii_uppercase() } else { c })
+        .collect();
+    format!("0x{}", checksummed)
+}
+
+pub fn is_checksum_address(address: &str) -> bool {
+    let digits = address.strip_prefix("0x").unwrap_or_default();
+    digits.len() == 40 && digits.chars().all(|c| c.is_ascii_hexdigit()) && to_checksum_address(address) == address
+}
+
+// Parses a JSON-RPC hex quantity such as "0x1b4"
+pub fn parse_quantity(value: &serde_json::Value) -> Result<u64> {
+    let quantity = value.as_str().ok_or_else(|| Error::Decode(format!("Expected a hex quantity, got {}", value)))?;
+    u64::from_str_radix(quantity.trim_start_matches("0x"), 16).map_err(|error| Error::Decode(format!("Invalid quantity {}: {}", quantity, error)))
+}
+
+pub fn parse_quantity_u256(value: &serde_json::Value) -> Result<U256> {
+    let quantity = value.as_str().ok_or_else(|| Error::Decode(format!("Expected a hex quantity, got {}", value)))?;
+    U256::from_str_radix(quantity.trim_start_matches("0x"), 16).map_err(|error| Error::Decode(format!("Invalid quantity {}: {}", quantity, error)))
+}
+
+// Fixed-point decimal string of `value` with `decimals` places, trailing zeros trimmed:
+// format_units(1_500_000_000.into(), 9) == "1.5"
+pub fn format_units(value: U256, decimals: u32) -> String {
+    let digits = value.to_string();
+    let decimals = decimals as usize;
+    if decimals == 0 {
+        return digits;
+    }
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (integer, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        integer.to_string()
+    } else {
+        format!("{}.{}", integer, fraction)
+    }
+}
+
+// Inverse of format_units: parse_units("1.5", 9) == 1_500_000_000. Rejects amounts
+// with more fractional digits than the token has rather than rounding them.
+pub fn parse_units(amount: &str, decimals: u32) -> Result<U256> {
+    let invalid = |reason: &str| Error::InvalidInput(format!("Invalid amount {}: {}", amount, reason));
+    let (integer, fraction) = amount.trim().split_once('.').unwrap_or((amount.trim(), ""));
+    if integer.is_empty() && fraction.is_empty() || !integer.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return Err(invalid("expected a decimal number"));
+    }
+    if fraction.len() > decimals as usize {
+        return Err(invalid(&format!("more than {} decimal places", decimals)));
+    }
+    let digits = format!("{}{:0<width$}", integer, fraction, width = decimals as usize);
+    match digits.trim_start_matches('0') {
+        "" => Ok(U256::zero()),
+        digits => U256::from_dec_str(digits).map_err(|_| invalid("too large")),
+    }
+}
+
+pub fn format_gwei(wei: U256) -> String {
+    format_units(wei, 9)
+}
+
+// Parses an RFC 3339 timestamp (e.g. 2024-01-01T12:00:00.000Z) into unix seconds
+pub fn parse_rfc3339(timestamp: &str) -> Result<i64> {
+    let invalid = || Error::Decode(format!("Invalid RFC 3339 timestamp: {}", timestamp));
+    let field = |range: std::ops::Range<usize>| -> Result<i64> { timestamp.get(range).ok_or_else(invalid)?.parse::<i64>().map_err(|_| invalid()) };
+
+    let bytes = timestamp.as_bytes();
+    if bytes.len() < 20 || bytes[4] != b'-' || bytes[7] != b'-' || !matches!(bytes[10], b'T' | b't') || bytes[13] != b':' || bytes[16] != b':' {
+        return Err(invalid());
+    }
+    let (year, month, day) = (field(0..4)?, field(5..7)?, field(8..10)?);
+    let (hour, minute, second) = (field(11..13)?, field(14..16)?, field(17..19)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return Err(invalid());
+    }
+
+    // Skip fractional seconds, then read the offset
+    let mut rest = &timestamp[19..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        rest = fraction.trim_start_matches(|c: char| c.is_ascii_digit());
+    }
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ if rest.len() == 6 && rest.as_bytes()[3] == b':' => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return Err(invalid()),
+            };
+            let hours: i64 = rest[1..3].parse().map_err(|_| invalid())?;
+            let minutes: i64 = rest[4..6].parse().map_err(|_| invalid())?;
+            sign * (hours * 3600 + minutes * 60)
+        }
+        _ => return Err(invalid()),
+    };
+
+    Ok(days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset)
+}
+
+// Formats unix seconds as an RFC 3339 UTC timestamp
+pub fn format_rfc3339(unix_seconds: i64) -> String {
+    let days = unix_seconds.div_euclid(86400);
+    let seconds = unix_seconds.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+// Howard Hinnant's days-from-civil algorithms for the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // From EIP-55: all caps, all lower and mixed case
+    const CHECKSUMMED: [&str; 8] = [
+        "0x52908400098527886E0F7030069857D2E4169EE7",
+        "0x8617E340B3D01FA5F11F306F4090FD50E238070D",
+        "0xde709f2102306220921060314715629080e2fb77",
+        "0x27b1fdb04752bbc536007a920d24acb045561c26",
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn checksums_eip55_vectors() {
+        for address in CHECKSUMMED {
+            assert_eq!(to_checksum_address(&address.to_lowercase()), address);
+            assert_eq!(to_checksum_address(&address[2..].to_uppercase()), address);
+            assert!(is_checksum_address(address));
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_checksums() {
+        assert!(!is_checksum_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"));
+        assert!(!is_checksum_address("0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"));
+        assert!(!is_checksum_address("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"));
+        assert!(!is_checksum_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA"));
+        assert!(!is_checksum_address("0xzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz"));
+    }
+
+    #[test]
+    fn parses_and_formats_rfc3339() {
+        assert_eq!(parse_rfc3339("2021-09-30T16:25:24Z").unwrap(), 1_633_019_124);
+        assert_eq!(parse_rfc3339("2021-09-30T18:25:24.123+02:00").unwrap(), 1_633_019_124);
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z").unwrap(), 0);
+        assert_eq!(format_rfc3339(1_633_019_124), "2021-09-30T16:25:24Z");
+        assert!(parse_rfc3339("2021-13-30T16:25:24Z").is_err());
+        assert!(parse_rfc3339("2021-09-30 16:25:24").is_err());
+    }
+
+    #[test]
+    fn parses_quantities_and_formats_units() {
+        assert_eq!(parse_quantity(&serde_json::json!("0x1b4")).unwrap(), 436);
+        assert!(parse_quantity(&serde_json::json!(436)).is_err());
+        assert!(parse_quantity(&serde_json::json!("0xzz")).is_err());
+        assert_eq!(parse_quantity_u256(&serde_json::json!("0xde0b6b3a7640000")).unwrap(), U256::exp10(18));
+        assert_eq!(format_units(U256::from(1_500_000_000u64), 9), "1.5");
+        assert_eq!(format_units(U256::from(1u64), 18), "0.000000000000000001");
+        assert_eq!(format_units(U256::exp10(18), 18), "1");
+        assert_eq!(format_gwei(U256::from(30_000_000_000u64)), "30");
+        assert_eq!(parse_units("1.5", 9).unwrap(), U256::from(1_500_000_000u64));
+        assert_eq!(parse_units("0.000000000000000001", 18).unwrap(), U256::one());
+        assert_eq!(parse_units("42", 0).unwrap(), U256::from(42));
+        assert_eq!(parse_units(".5", 1).unwrap(), U256::from(5));
+        assert_eq!(parse_units("0", 18).unwrap(), U256::zero());
+        assert!(parse_units("1.0000001", 6).is_err());
+        assert!(parse_units("1e18", 18).is_err());
+        assert!(parse_units("-1", 18).is_err());
+        assert!(parse_units(".", 18).is_err());
+        assert!(parse_units(&"9".repeat(80), 0).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::abi::encode_call_args;
+use crate::eip712::TypedData;
+use crate::error::{Error, Result};
+use crate::key::SecretKey;
+use crate::transaction::{TransactionRequest, TypedTransaction};
+
+// Inputs to gen-vectors, e.g.
+//
+// {
+//   "selectors": ["transfer(address,uint256)"],
+//   "calls": [{"signature": "approve(address,uint256)", "args": ["0x...", "1"]}],
+//   "transactions": [{"chainId": "0x1", "nonce": "0x0", "gas": "0x5208", "maxFeePerGas": "0x..",
+//                     "maxPriorityFeePerGas": "0x..", "to": "0x...", "privateKey": "0x..."}],
+//   "typedData": [{"types": {...}, "primaryType": "...", "domain": {...}, "message": {...}}]
+// }
+//
+// Transactions and typed data are also signed when they carry a privateKey.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct VectorInputs {
+    #[serde(default)]
+    pub selectors: Vec<String>,
+    #[serde(default)]
+    pub calls: Vec<CallInput>,
+    #[serde(default)]
+    pub transactions: Vec<TransactionInput>,
+    #[serde(default)]
+    pub typed_data: Vec<TypedDataInput>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CallInput {
+    pub signature: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionInput {
+    #[serde(flatten)]
+    pub request: TransactionRequest,
+    pub private_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedDataInput {
+    #[serde(flatten)]
+    pub data: TypedData,
+    pub private_key: Option<String>,
+}
+
+// Key 0x4646..46 from the EIP-155 example; public test key, never fund it
+const TEST_KEY: &str = "0x4646464646464646464646464646464646464646464646464646464646464646";
+
+// The example from the EIP-712 text
+const EIP712_MAIL: &str = r#"{"types":{"EIP712Domain":[{"name":"name","type":"string"},{"name":"version","type":"string"},{"name":"chainId","type":"uint256"},{"name":"verifyingContract","type":"address"}],"Person":[{"name":"name","type":"string"},{"name":"wallet","type":"address"}],"Mail":[{"name":"from","type":"Person"},{"name":"to","type":"Person"},{"name":"contents","type":"string"}]},"primaryType":"Mail","domain":{"name":"Ether Mail","version":"1","chainId":1,"verifyingContract":"0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"},"message":{"from":{"name":"Cow","wallet":"0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"},"to":{"name":"Bob","wallet":"0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"},"contents":"Hello, Bob!"}}"#;
+
+impl VectorInputs {
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|error| Error::Decode(format!("Invalid vector inputs: {}", error)))
+    }
+
+    // Common selectors, one transaction of each signable kind and the EIP-712 example
+    pub fn default_set() -> Self {
+        let request = |value: serde_json::Value| serde_json::from_value::<TransactionRequest>(value).expect("valid request");
+        VectorInputs {
+            selectors: ["transfer(address,uint256)", "approve(address,uint256)", "balanceOf(address)", "Transfer(address,address,uint256)", "Approval(address,address,uint256)"].map(str::to_string).to_vec(),
+            calls: vec![CallInput {
+                signature: "transfer(address,uint256)".to_string(),
+                args: vec!["0x3535353535353535353535353535353535353535".to_string(), "1000000000000000000".to_string()],
+            }],
+            transactions: [
+                json!({"chainId": "0x1", "nonce": "0x9", "gas": "0x5208", "gasPrice": "0x4a817c800", "to": "0x3535353535353535353535353535353535353535", "value": "0xde0b6b3a7640000"}),
+                json!({"chainId": "0x1", "nonce": "0x0", "gas": "0x7530", "gasPrice": "0x3b9aca00", "to": "0x3535353535353535353535353535353535353535", "accessList": [{"address": "0x3535353535353535353535353535353535353535", "storageKeys": ["0x0000000000000000000000000000000000000000000000000000000000000001"]}]}),
+                json!({"chainId": "0x1", "nonce": "0x1", "gas": "0x5208", "maxFeePerGas": "0x4e3b29200", "maxPriorityFeePerGas": "0x3b9aca00", "to": "0x3535353535353535353535353535353535353535", "value": "0x1", "data": "0xdeadbeef"}),
+            ]
+            .into_iter()
+            .map(|value| TransactionInput { request: request(value), private_key: Some(TEST_KEY.to_string()) })
+            .collect(),
+            typed_data: vec![TypedDataInput { data: TypedData::from_json(EIP712_MAIL).expect("valid typed data"), private_key: Some(TEST_KEY.to_string()) }],
+        }
+    }
+}
+
+// Canonical encodings and hashes of every input, as JSON in input order
+pub fn generate(inputs: &VectorInputs) -> Result<serde_json::Value> {
+    let selectors: Vec<_> = inputs
+        .selectors
+        .iter()
+        .map(|signature| {
+            let hash = keccak_hash::keccak(signature.as_bytes());
+            json!({"signature": signature, "selector": format!("0x{}", hex::encode(&hash[..4])), "topic": format!("{:?}", hash)})
+        })
+        .collect();
+
+    let calls = inputs
+        .calls
+        .iter()
+        .map(|call| {
+            let args: Vec<&str> = call.args.iter().map(String::as_str).collect();
+            Ok(json!({"signature": call.signature, "args": call.args, "data": encode_call_args(&call.signature, &args)?}))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let transactions = inputs
+        .transactions
+        .iter()
+        .map(|input| {
+            let transaction = TypedTransaction::from_request(&input.request)?;
+            let mut vector = json!({
+                "type": transaction.tx_type(),
+                "unsigned": transaction.to_hex(),
+                "signingHash": transaction.signature_hash()?,
+            });
+            if let Some(key) = &input.private_key {
+                let signed = transaction.sign(&SecretKey::from_hex(key)?)?;
+                vector["raw"] = json!(signed.to_hex());
+                vector["hash"] = json!(signed.hash());
+                vector["sender"] = json!(signed.recover_sender()?);
+            }
+            Ok(vector)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let typed_data = inputs
+        .typed_data
+        .iter()
+        .map(|input| {
+            let data = &input.data;
+            let mut vector = json!({
+                "primaryType": data.primary_type,
+                "encodeType": data.encode_type(&data.primary_type)?,
+                "typeHash": data.type_hash(&data.primary_type)?,
+                "domainSeparator": data.domain_separator()?,
+                "structHash": data.hash_struct(&data.primary_type, &data.message)?,
+                "signingHash": data.signing_hash()?,
+            });
+            if let Some(key) = &input.private_key {
+                vector["signature"] = json!(format!("0x{}", hex::encode(data.sign(&SecretKey::from_hex(key)?)?)));
+            }
+            Ok(vector)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(json!({"selectors": selectors, "calls": calls, "transactions": transactions, "typedData": typed_data}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_reproducible_vectors() {
+        let vectors = generate(&VectorInputs::default_set()).unwrap();
+        assert_eq!(vectors["selectors"][0]["selector"], "0xa9059cbb");
+        assert_eq!(vectors["selectors"][3]["topic"], "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+        assert_eq!(vectors["calls"][0]["data"], "0xa9059cbb00000000000000000000000035353535353535353535353535353535353535350000000000000000000000000000000000000000000000000de0b6b3a7640000");
+
+        // The EIP-155 example transaction
+        let legacy = &vectors["transactions"][0];
+        assert_eq!(legacy["signingHash"], "0xdaf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53");
+        assert_eq!(
+            legacy["raw"],
+            "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+        assert_eq!(legacy["sender"], "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f");
+        assert_eq!(vectors["transactions"][1]["type"], 1);
+        assert_eq!(vectors["transactions"][2]["type"], 2);
+
+        let mail = &vectors["typedData"][0];
+        assert_eq!(mail["signingHash"], "0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2");
+        assert_eq!(generate(&VectorInputs::default_set()).unwrap(), vectors);
+
+        let inputs = VectorInputs::from_json(r#"{"transactions": [{"chainId": "0x1", "nonce": "0x0", "gas": "0x5208"}]}"#).unwrap();
+        assert!(matches!(generate(&inputs), Err(Error::InvalidInput(message)) if message.contains("gasPrice or maxFeePerGas")));
+    }
+}
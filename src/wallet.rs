@@ -0,0 +1,276 @@
+use std::sync::Arc;
+
+use ethabi::ethereum_types::H256;
+use tokio::sync::Mutex;
+
+use crate::blocks::BlockTag;
+use crate::client::EthClient;
+use crate::confirm::{ConfirmFn, TransactionSummary};
+use crate::error::{Error, Result};
+use crate::gas::{has_calldata_floor, validate_gas_limit};
+use crate::headers::get_header;
+use crate::key::SecretKey;
+use crate::stuck::{bump_fees, stuck_transactions};
+use crate::transaction::{TransactionRequest, TypedTransaction};
+
+// Signs and sends transactions from one key. Nonces are handed out locally, starting
+// from the pending count, so back-to-back sends don't wait for the node to see the
+// previous one. Clones share the nonce counter.
+#[derive(Clone)]
+pub struct Wallet {
+    client: EthClient,
+    key: SecretKey,
+    // Next nonce to hand out, None until fetched or after a failed send
+    nonce: Arc<Mutex<Option<u64>>>,
+    confirm: Option<ConfirmFn>,
+}
+
+impl Wallet {
+    pub fn new(client: &EthClient, key: SecretKey) -> Self {
+        Wallet {
+            client: client.clone(),
+            key,
+            nonce: Arc::new(Mutex::new(None)),
+            confirm: None,
+        }
+    }
+
+    // Shows every transaction to `confirm` before signing it; a declined one is not sent
+    pub fn with_confirmation(mut self, confirm: ConfirmFn) -> Self {
+        self.confirm = Some(confirm);
+        self
+    }
+
+    pub fn address(&self) -> String {
+        self.key.address()
+    }
+
+    pub fn client(&self) -> &EthClient {
+        &self.client
+    }
+
+    pub fn key(&self) -> &SecretKey {
+        &self.key
+    }
+
+    // Fills in whatever the request leaves unset (chain id, nonce, gas limit, fees) and
+    // returns the unsigned transaction: EIP-1559, or EIP-155 legacy when the request
+    // sets a gas price or the chain has no base fee. A gas limit the node would refuse
+    // outright is an InvalidInput error. Reserves the nonce it picks.
+    pub async fn prepare(&self, request: &TransactionRequest) -> Result<TypedTransaction> {
+        let request = TransactionRequest {
+            from: Some(self.address()),
+            ..request.clone()
+        };
+        let mut request = fill_fees(&self.client, request).await?;
+        let reserved = request.nonce.is_none();
+        if reserved {
+            request.nonce = Some(self.next_nonce().await?.into());
+        }
+        let transaction = TypedTransaction::from_request(&request);
+        if transaction.is_err() && reserved {
+            self.reset_nonce().await;
+        }
+        transaction
+    }
+
+    // Signs a prepared transaction and broadcasts it. A declined or rejected one forgets
+    // the local nonce, so the next send starts again from the node's count.
+    pub async fn send_transaction(&self, transaction: TypedTransaction) -> Result<H256> {
+        if let Some(confirm) = &self.confirm {
+            if !confirm(&TransactionSummary::new(&self.address(), &transaction, None)) {
+                self.reset_nonce().await;
+                return Err(Error::Declined("Transaction was not confirmed".to_string()));
+            }
+        }
+        let signed = transaction.sign(&self.key)?;
+        match self.client.send_raw_transaction(&signed).await {
+            Ok(hash) => Ok(hash),
+            Err(error) => {
+                self.reset_nonce().await;
+                Err(error)
+            }
+        }
+    }
+
+    // prepare then send_transaction
+    pub async fn send(&self, request: &TransactionRequest) -> Result<H256> {
+        let transaction = self.prepare(request).await?;
+        self.send_transaction(transaction).await
+    }
+
+    // Replaces whatever is pending at `nonce` with an empty self-transfer, paying enough
+    // more than the pending transaction (from the txpool, when the node has one) for
+    // nodes to accept the replacement
+    pub async fn cancel_transaction(&self, nonce: u64) -> Result<H256> {
+        let request = TransactionRequest {
+            to: Some(self.address()),
+            nonce: Some(nonce.into()),
+            ..Default::default()
+        }
+        .with_gas(21_000);
+        let mut transaction = self.prepare(&request).await?;
+        let stuck = stuck_transactions(&self.client, &self.address()).await?;
+        if let Some(fees) = stuck.iter().find(|stuck| stuck.nonce == nonce).and_then(|stuck| stuck.fees()) {
+            bump_fees(&mut transaction, fees)?;
+        }
+        self.send_transaction(transaction).await
+    }
+
+    pub async fn reset_nonce(&self) {
+        *self.nonce.lock().await = None;
+    }
+
+    async fn next_nonce(&self) -> Result<u64> {
+        let mut next = self.nonce.lock().await;
+        let nonce = match *next {
+            Some(nonce) => nonce,
+            None => self.client.get_transaction_count(&self.address(), BlockTag::Pending).await?,
+        };
+        *next = Some(nonce + 1);
+        Ok(nonce)
+    }
+}
+
+// Fills in the chain id, gas limit and fees a request leaves unset, as Wallet::prepare
+// does, then checks the gas limit against the latest block. The request should name its
+// sender, for the gas estimate.
+pub(crate) async fn fill_fees(client: &EthClient, mut request: TransactionRequest) -> Result<TransactionRequest> {
+    if request.chain_id.is_none() {
+        request.chain_id = Some(client.chain_id().await?.into());
+    }
+    if request.gas.is_none() {
+        request.gas = Some(client.estimate_gas(&request, BlockTag::Pending).await?.into());
+    }
+    let header = get_header(client, "latest").await?;
+    let base_fee = match (request.gas_price, request.max_fee_per_gas) {
+        (Some(_), None) => None,
+        _ => header.base_fee,
+    };
+    match base_fee {
+        Some(base_fee) => {
+            let tip = match request.max_priority_fee_per_gas {
+                Some(tip) => tip,
+                None => client.max_priority_fee().await?,
+            };
+            request.max_priority_fee_per_gas = Some(tip);
+            // Room for the base fee to double before the transaction gets stuck
+            if request.max_fee_per_gas.is_none() {
+                let max_fee = base_fee.checked_mul(2.into()).and_then(|fee| fee.checked_add(tip));
+                request.max_fee_per_gas = Some(max_fee.ok_or_else(|| Error::Decode(format!("Max fee overflows with base fee {} and tip {}", base_fee, tip)))?);
+            }
+        }
+        None if request.gas_price.is_none() => request.gas_price = Some(client.gas_price().await?),
+        None => {}
+    }
+    validate_gas(&request, header.gas_limit)?;
+    Ok(request)
+}
+
+// Refuses a gas limit the node would reject before execution: under the intrinsic gas or,
+// on chains that enforce it, the EIP-7623 calldata floor, or over the block gas limit.
+// Requests carry no authorization list, so they never describe EIP-7702 transactions.
+fn validate_gas(request: &TransactionRequest, block_gas_limit: u64) -> Result<()> {
+    let data = match &request.data {
+        Some(data) => hex::decode(data.trim_start_matches("0x")).map_err(|_| Error::InvalidInput(format!("Invalid calldata {}", data)))?,
+        None => Vec::new(),
+    };
+    let gas_limit = request.gas.unwrap_or_default().as_u64();
+    let calldata_floor = request.chain_id.is_some_and(|chain_id| has_calldata_floor(chain_id.as_u64()));
+    validate_gas_limit(gas_limit, &data, request.to.is_none(), request.access_list.as_deref().unwrap_or_default(), 0, calldata_floor, block_gas_limit)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use ethabi::ethereum_types::U256;
+    use serde_json::json;
+
+    use super::*;
+    use crate::stuck::tests::pool;
+    use crate::testing::{london_header, Failure, MockNode};
+
+    // London chain 1 at base fee 10 gwei; the nonce count is 7
+    pub(crate) fn node() -> MockNode {
+        MockNode::new(|method, params| match method {
+            "eth_chainId" => Ok(json!("0x1")),
+            "eth_estimateGas" => Ok(json!("0x5208")),
+            "eth_maxPriorityFeePerGas" => Ok(json!("0x3b9aca00")),
+            "eth_getTransactionCount" => Ok(json!("0x7")),
+            "eth_getBlockByNumber" => Ok(london_header("0x2540be400")),
+            "eth_sendRawTransaction" => Ok(json!(TypedTransaction::from_hex(params[0].as_str().unwrap()).unwrap().hash())),
+            _ => Err(Failure::unexpected(method)),
+        })
+    }
+
+    // Raw transactions broadcast through `node`, decoded
+    pub(crate) fn sent(node: &MockNode) -> Vec<TypedTransaction> {
+        node.params_of("eth_sendRawTransaction").iter().map(|params| TypedTransaction::from_hex(params[0].as_str().unwrap()).unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn fills_signs_and_numbers_sends() {
+        let node = node();
+        let key = SecretKey::from_hex(&"46".repeat(32)).unwrap();
+        let wallet = Wallet::new(&node.client(), key.clone());
+        let request = TransactionRequest {
+            to: Some("0x3535353535353535353535353535353535353535".to_string()),
+            ..Default::default()
+        }
+        .with_value(U256::exp10(18));
+
+        let first = wallet.send(&request).await.unwrap();
+        wallet.send(&request).await.unwrap();
+        let sent = sent(&node);
+        assert_eq!(sent[0].hash(), first);
+
+        let TypedTransaction::Eip1559(transaction) = &sent[0] else { panic!("expected EIP-1559") };
+        assert_eq!((transaction.chain_id, transaction.nonce, transaction.gas_limit), (1, 7, 21_000));
+        assert_eq!(transaction.max_priority_fee_per_gas, U256::from(1_000_000_000u64));
+        assert_eq!(transaction.max_fee_per_gas, U256::from(21_000_000_000u64));
+        assert_eq!(sent[0].recover_sender().unwrap(), key.address());
+        let TypedTransaction::Eip1559(second) = &sent[1] else { panic!("expected EIP-1559") };
+        assert_eq!(second.nonce, 8);
+
+        let declined = wallet.clone().with_confirmation(Arc::new(|summary: &TransactionSummary| summary.value.is_zero()));
+        assert!(matches!(declined.send(&request).await, Err(Error::Declined(_))));
+        assert_eq!(node.params_of("eth_sendRawTransaction").len(), 2);
+
+        let legacy = TransactionRequest { gas_price: Some(U256::from(5)), ..request.clone().with_gas(30_000) };
+        let TypedTransaction::Legacy(legacy) = wallet.prepare(&legacy).await.unwrap() else { panic!("expected legacy") };
+        assert_eq!((legacy.v, legacy.nonce, legacy.gas_limit, legacy.gas_price), (1, 7, 30_000, U256::from(5)));
+
+        // Under the intrinsic gas, or over the block's 30M limit, without taking a nonce
+        assert!(matches!(wallet.prepare(&request.clone().with_gas(20_000)).await, Err(Error::InvalidInput(_))));
+        assert!(matches!(wallet.prepare(&request.clone().with_gas(30_000_001)).await, Err(Error::InvalidInput(_))));
+        let TypedTransaction::Eip1559(next) = wallet.prepare(&request).await.unwrap() else { panic!("expected EIP-1559") };
+        assert_eq!(next.nonce, 8);
+
+        // A max fee that would overflow is refused rather than wrapped, unless it is given
+        let overflowing = TransactionRequest { max_priority_fee_per_gas: Some(U256::MAX), ..request.clone() };
+        assert!(matches!(fill_fees(&node.client(), overflowing.clone()).await, Err(Error::Decode(_))));
+        let given = TransactionRequest { max_fee_per_gas: Some(U256::MAX), ..overflowing };
+        assert_eq!(fill_fees(&node.client(), given).await.unwrap().max_fee_per_gas, Some(U256::MAX));
+    }
+
+    #[tokio::test]
+    async fn cancels_with_bumped_fees() {
+        let key = SecretKey::from_hex(&"46".repeat(32)).unwrap();
+        let client = pool(true).client();
+        let wallet = Wallet::new(&client, key);
+        let hash = wallet.cancel_transaction(4).await.unwrap();
+
+        // Rebuild what was sent: nonce 4 paid 20 gwei, so 112% plus one wei is needed
+        let request = TransactionRequest {
+            to: Some(wallet.address()),
+            nonce: Some(4.into()),
+            ..Default::default()
+        }
+        .with_gas(21_000);
+        let TypedTransaction::Eip1559(mut expected) = wallet.prepare(&request).await.unwrap() else { panic!("expected EIP-1559") };
+        let bumped = U256::from(22_400_000_001u64);
+        (expected.max_priority_fee_per_gas, expected.max_fee_per_gas) = (bumped, bumped);
+        let expected = TypedTransaction::Eip1559(expected).sign(wallet.key()).unwrap();
+        assert_eq!(hash, expected.hash());
+        assert_eq!(expected.value(), U256::zero());
+    }
+}
@@ -0,0 +1,91 @@
+use ethabi::ethereum_types::{H160, U256};
+use ethabi::Token;
+use serde::{Deserialize, Serialize};
+
+use crate::client::EthClient;
+use crate::rpc_method;
+
+// EIP-712 transaction type used by zkSync Era
+pub const EIP712_TX_TYPE: u8 = 0x71;
+pub const DEFAULT_GAS_PER_PUBDATA: u64 = 50_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Fee {
+    pub gas_limit: U256,
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+    pub gas_per_pubdata_limit: U256,
+}
+
+rpc_method!(pub ZksEstimateFee, "zks_estimateFee", (serde_json::Value,), Fee);
+
+// Goes in a transaction's eip712Meta as paymasterParams
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymasterParams {
+    pub paymaster: String,
+    // 0x-prefixed hex
+    pub paymaster_input: String,
+}
+
+impl PaymasterParams {
+    // IPaymasterFlow.general(bytes)
+    pub fn general(paymaster: &str, inner_input: &[u8]) -> Self {
+        PaymasterParams {
+            paymaster: paymaster.to_string(),
+            paymaster_input: encode_flow("general(bytes)", &[Token::Bytes(inner_input.to_vec())]),
+        }
+    }
+
+    // IPaymasterFlow.approvalBased(address,uint256,bytes), paying fees in `token`
+    pub fn approval_based(paymaster: &str, token: H160, min_allowance: U256, inner_input: &[u8]) -> Self {
+        PaymasterParams {
+            paymaster: paymaster.to_string(),
+            paymaster_input: encode_flow("approvalBased(address,uint256,bytes)", &[Token::Address(token), Token::Uint(min_allowance), Token::Bytes(inner_input.to_vec())]),
+        }
+    }
+}
+
+fn encode_flow(signature: &str, tokens: &[Token]) -> String {
+    let selector = &keccak_hash::keccak(signature.as_bytes())[..4];
+    format!("0x{}{}", hex::encode(selector), hex::encode(ethabi::encode(tokens)))
+}
+
+// `transaction` is a call object ({from, to, data, value}), optionally with an
+// eip712Meta holding gasPerPubdata and paymasterParams
+pub async fn estimate_fee(client: &EthClient, transaction: &serde_json::Value) -> crate::Result<Fee> {
+    client.request::<ZksEstimateFee>((transaction.clone(),)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::MockNode;
+
+    const PAYMASTER: &str = "0x1111111111111111111111111111111111111111";
+
+    #[test]
+    fn encodes_paymaster_flows() {
+        let general = PaymasterParams::general(PAYMASTER, &[]);
+        // general(bytes) with empty bytes: selector, offset and zero length
+        assert_eq!(general.paymaster_input, format!("0x8c5a3445{:064x}{:064x}", 32, 0));
+
+        let token = H160::repeat_byte(0x22);
+        let approval = PaymasterParams::approval_based(PAYMASTER, token, 1000.into(), &[0xab]);
+        let expected = format!("0x949431dc{:0>64}{:064x}{:064x}{:064x}ab{}", "22".repeat(20), 1000, 96, 1, "0".repeat(62));
+        assert_eq!(approval.paymaster_input, expected);
+        assert_eq!(json!(approval)["paymasterInput"], json!(expected));
+    }
+
+    #[tokio::test]
+    async fn estimates_fees_with_zks_estimate_fee() {
+        let fee = json!({"gas_limit": "0x5208", "max_fee_per_gas": "0xee6b280", "max_priority_fee_per_gas": "0x0", "gas_per_pubdata_limit": "0xc350"});
+        let node = MockNode::with_results([("zks_estimateFee", fee)]);
+        let transaction = json!({"from": PAYMASTER, "to": PAYMASTER, "data": "0x", "eip712Meta": {"gasPerPubdata": format!("0x{:x}", DEFAULT_GAS_PER_PUBDATA)}});
+        let fee = estimate_fee(&node.client(), &transaction).await.unwrap();
+        assert_eq!((fee.gas_limit, fee.gas_per_pubdata_limit), (21_000.into(), DEFAULT_GAS_PER_PUBDATA.into()));
+        assert_eq!(node.params_of("zks_estimateFee"), [vec![transaction]]);
+    }
+}